@@ -59,6 +59,13 @@ pub fn benchmark(c: &mut Criterion) {
             });
         });
 
+        group.bench_function(format!("mphf-get-many/gamma-{:.1}", gamma), |b| {
+            let mut out = vec![None; query_n.min(n)];
+            b.iter(|| {
+                mphf.get_many(black_box(&items[..out.len()]), &mut out);
+            });
+        });
+
         let t0 = Instant::now();
         let rkyv_bytes = rkyv::to_bytes::<_, 1024>(&mphf).unwrap();
         println!("mphf ({:.1}) rkyv serialization took: {:?}", gamma, t0.elapsed());
@@ -22,7 +22,7 @@ pub fn benchmark(c: &mut Criterion) {
 
     let t0 = Instant::now();
     let data: Vec<u64> = (0..n_u64).map(|_| random()).collect();
-    let mut indices: Vec<usize> = (0..n).collect();
+    let mut indices: Vec<u64> = (0..n as u64).collect();
     indices.shuffle(&mut rand::thread_rng());
     println!("indices generation took: {:?}", t0.elapsed());
 
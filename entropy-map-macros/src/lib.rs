@@ -0,0 +1,486 @@
+//! Macros building `entropy-map` sets and maps at compile time.
+//!
+//! `static_set!` and `static_map!` build a `Set` or `MapWithDict` from literal keys and values
+//! during compilation, serialize it with `rkyv` and embed the archive bytes into the binary as an
+//! `entropy_map::StaticArchive` static, which dereferences to the archived structure. There is no
+//! construction or validation at startup, which suits embedded and CLI users with small fixed key
+//! sets.
+//!
+//! Keys and values must be integer or string literals, with the structure type spelled as
+//! `Set<K>` or `MapWithDict<K, V>` where `K` and `V` are primitive integer types or `String`.
+//! The caller must depend on `entropy-map` of the same version with `rkyv_derive` feature enabled.
+//!
+//! # Examples
+//! ```
+//! use entropy_map_macros::{static_map, static_set};
+//!
+//! static_set! {
+//!     static COUNTRIES: Set<String> = ["gb", "pt", "us"];
+//! }
+//!
+//! static_map! {
+//!     static PORTS: MapWithDict<String, u16> = {
+//!         "http" => 80,
+//!         "https" => 443,
+//!     };
+//! }
+//!
+//! assert!(COUNTRIES.contains("pt"));
+//! assert!(!COUNTRIES.contains("fr"));
+//! assert_eq!(PORTS.get("https"), Some(&443));
+//! ```
+//!
+//! `#[derive(EntropyKey)]` implements `entropy_map::EntropyKey` canonical platform-stable key
+//! encoding for struct key types, along with `Hash` delegating to it.
+
+use std::collections::HashSet;
+use std::hash::Hash;
+use std::str::FromStr;
+
+use entropy_map::{MapWithDict, Set, DEFAULT_GAMMA};
+use proc_macro2::{Literal, Span, TokenStream};
+use quote::quote;
+use rkyv::ser::serializers::AllocSerializer;
+use syn::parse::{Parse, ParseStream};
+use syn::punctuated::Punctuated;
+use syn::{
+    braced, bracketed, parse_macro_input, parse_quote, Attribute, Data, DeriveInput, Error, Expr, ExprLit, ExprUnary,
+    Fields, GenericArgument, Ident, Index, Lit, PathArguments, Result, Token, Type, UnOp, Visibility,
+};
+
+/// Scratch space size used for serialization.
+const SCRATCH_SIZE: usize = 4096;
+
+/// Builds `Set` statics from literal keys at compile time.
+///
+/// Each static has type `entropy_map::StaticArchive<entropy_map::Set<K>>`.
+///
+/// # Examples
+/// ```
+/// use entropy_map_macros::static_set;
+///
+/// static_set! {
+///     /// Well-known ports
+///     pub static WELL_KNOWN_PORTS: Set<u16> = [22, 80, 443];
+/// }
+///
+/// assert!(WELL_KNOWN_PORTS.contains(&443));
+/// assert!(!WELL_KNOWN_PORTS.contains(&8080));
+/// ```
+#[proc_macro]
+pub fn static_set(input: proc_macro::TokenStream) -> proc_macro::TokenStream {
+    let statics = parse_macro_input!(input as Statics<SetEntries>);
+    statics.expand().unwrap_or_else(Error::into_compile_error).into()
+}
+
+/// Builds `MapWithDict` statics from literal key-value pairs at compile time.
+///
+/// Each static has type `entropy_map::StaticArchive<entropy_map::MapWithDict<K, V>>`.
+///
+/// # Examples
+/// ```
+/// use entropy_map_macros::static_map;
+///
+/// static_map! {
+///     static ERROR_CODES: MapWithDict<i32, String> = {
+///         -1 => "unknown",
+///         404 => "not found",
+///     };
+/// }
+///
+/// assert_eq!(ERROR_CODES.get(&-1).map(|v| v.as_str()), Some("unknown"));
+/// assert_eq!(ERROR_CODES.get(&500), None);
+/// ```
+#[proc_macro]
+pub fn static_map(input: proc_macro::TokenStream) -> proc_macro::TokenStream {
+    let statics = parse_macro_input!(input as Statics<MapEntries>);
+    statics.expand().unwrap_or_else(Error::into_compile_error).into()
+}
+
+/// Derives `entropy_map::EntropyKey` for a struct, encoding its fields in declaration order, and
+/// `Hash` delegating to it so that the struct is hashed canonically by `entropy-map` structures.
+///
+/// All fields must implement `EntropyKey`, and the struct must not derive `Hash` itself.
+///
+/// # Examples
+/// ```
+/// use entropy_map::{EntropyKey, MapWithDict, DEFAULT_GAMMA};
+/// use entropy_map_macros::EntropyKey;
+///
+/// #[derive(EntropyKey, Clone, PartialEq, Eq)]
+/// struct Endpoint {
+///     host: String,
+///     port: u16,
+/// }
+///
+/// let endpoint = Endpoint { host: "a".to_string(), port: 443 };
+/// assert_eq!(endpoint.key_bytes(), [1, 0, 0, 0, 0, 0, 0, 0, b'a', 187, 1]);
+///
+/// let map: MapWithDict<Endpoint, u32> = MapWithDict::from_iter_with_params([(endpoint, 1)], DEFAULT_GAMMA).unwrap();
+/// assert_eq!(map.get(&Endpoint { host: "a".to_string(), port: 443 }), Some(&1));
+/// ```
+#[proc_macro_derive(EntropyKey)]
+pub fn derive_entropy_key(input: proc_macro::TokenStream) -> proc_macro::TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    expand_entropy_key(input)
+        .unwrap_or_else(Error::into_compile_error)
+        .into()
+}
+
+/// Sequence of static item declarations.
+struct Statics<E> {
+    items: Vec<StaticItem<E>>,
+}
+
+/// Static item declaration `[attrs] [vis] static NAME: Type = entries;`.
+struct StaticItem<E> {
+    attrs: Vec<Attribute>,
+    vis: Visibility,
+    name: Ident,
+    ty: Type,
+    entries: E,
+}
+
+/// Keys of a set `[key, ...]`.
+struct SetEntries {
+    keys: Vec<Expr>,
+}
+
+/// Key-value pairs of a map `{ key => value, ... }`.
+struct MapEntries {
+    pairs: Vec<(Expr, Expr)>,
+}
+
+/// Entries of a structure which can be built and archived given its type arguments.
+trait Entries: Parse {
+    /// Name of the structure type
+    const TYPE_NAME: &'static str;
+
+    /// Builds the structure with `type_args` and returns its archive bytes.
+    fn archive(&self, type_args: &[&Type]) -> Result<Vec<u8>>;
+}
+
+impl<E: Parse> Parse for Statics<E> {
+    fn parse(input: ParseStream) -> Result<Self> {
+        let mut items = Vec::new();
+        while !input.is_empty() {
+            items.push(input.parse()?);
+        }
+        Ok(Statics { items })
+    }
+}
+
+impl<E: Parse> Parse for StaticItem<E> {
+    fn parse(input: ParseStream) -> Result<Self> {
+        let attrs = input.call(Attribute::parse_outer)?;
+        let vis = input.parse()?;
+        input.parse::<Token![static]>()?;
+        let name = input.parse()?;
+        input.parse::<Token![:]>()?;
+        let ty = input.parse()?;
+        input.parse::<Token![=]>()?;
+        let entries = input.parse()?;
+        input.parse::<Token![;]>()?;
+        Ok(StaticItem { attrs, vis, name, ty, entries })
+    }
+}
+
+impl Parse for SetEntries {
+    fn parse(input: ParseStream) -> Result<Self> {
+        let content;
+        bracketed!(content in input);
+        let keys = Punctuated::<Expr, Token![,]>::parse_terminated(&content)?;
+        Ok(SetEntries { keys: keys.into_iter().collect() })
+    }
+}
+
+impl Parse for MapEntries {
+    fn parse(input: ParseStream) -> Result<Self> {
+        let content;
+        braced!(content in input);
+        let mut pairs = Vec::new();
+        while !content.is_empty() {
+            let key = content.parse()?;
+            content.parse::<Token![=>]>()?;
+            let value = content.parse()?;
+            pairs.push((key, value));
+            if content.is_empty() {
+                break;
+            }
+            content.parse::<Token![,]>()?;
+        }
+        Ok(MapEntries { pairs })
+    }
+}
+
+impl<E: Entries> Statics<E> {
+    /// Expands every static item into an embedded archive.
+    fn expand(&self) -> Result<TokenStream> {
+        self.items.iter().map(StaticItem::expand).collect()
+    }
+}
+
+impl<E: Entries> StaticItem<E> {
+    /// Expands the static item into an `AlignedBytes` static wrapped into `StaticArchive`.
+    fn expand(&self) -> Result<TokenStream> {
+        let StaticItem { attrs, vis, name, ty, entries } = self;
+
+        let type_args = type_args(ty, E::TYPE_NAME)?;
+        let bytes = entries.archive(&type_args)?;
+        let len = bytes.len();
+        let bytes = Literal::byte_string(&bytes);
+        let type_name = Ident::new(E::TYPE_NAME, Span::call_site());
+        let endian = if cfg!(target_endian = "little") {
+            "little"
+        } else {
+            "big"
+        };
+
+        Ok(quote! {
+            #(#attrs)*
+            #vis static #name: ::entropy_map::StaticArchive<::entropy_map::#type_name<#(#type_args),*>> = {
+                const _: () = ::core::assert!(
+                    ::core::cfg!(target_endian = #endian),
+                    "archive built at compile time has different endianness from the target"
+                );
+                static BYTES: ::entropy_map::AlignedBytes<#len> = ::entropy_map::AlignedBytes(*#bytes);
+                // SAFETY: `BYTES` were produced by `rkyv::to_bytes` of the same type at compile time
+                unsafe { ::entropy_map::StaticArchive::from_bytes_unchecked(&BYTES.0) }
+            };
+        })
+    }
+}
+
+/// Returns generic type arguments of `ty`, which must be `type_name<...>`.
+fn type_args<'a>(ty: &'a Type, type_name: &str) -> Result<Vec<&'a Type>> {
+    let segment = match ty {
+        Type::Path(path) if path.qself.is_none() => path.path.segments.last(),
+        _ => None,
+    };
+
+    match segment {
+        Some(segment) if segment.ident == type_name => match &segment.arguments {
+            PathArguments::AngleBracketed(args) => args
+                .args
+                .iter()
+                .map(|arg| match arg {
+                    GenericArgument::Type(ty) => Ok(ty),
+                    _ => Err(Error::new_spanned(arg, "expected type argument")),
+                })
+                .collect(),
+            _ => Err(Error::new_spanned(ty, format!("expected `{type_name}` type arguments"))),
+        },
+        _ => Err(Error::new_spanned(ty, format!("expected `{type_name}` type"))),
+    }
+}
+
+/// Evaluates `$body` with `$t` aliased to the primitive integer type or `String` named by `$ty`.
+macro_rules! with_type {
+    ($ty:expr, $t:ident => $body:expr) => {
+        match type_ident($ty)?.to_string().as_str() {
+            "u8" => {
+                type $t = u8;
+                $body
+            }
+            "u16" => {
+                type $t = u16;
+                $body
+            }
+            "u32" => {
+                type $t = u32;
+                $body
+            }
+            "u64" => {
+                type $t = u64;
+                $body
+            }
+            "u128" => {
+                type $t = u128;
+                $body
+            }
+            "i8" => {
+                type $t = i8;
+                $body
+            }
+            "i16" => {
+                type $t = i16;
+                $body
+            }
+            "i32" => {
+                type $t = i32;
+                $body
+            }
+            "i64" => {
+                type $t = i64;
+                $body
+            }
+            "i128" => {
+                type $t = i128;
+                $body
+            }
+            "String" => {
+                type $t = String;
+                $body
+            }
+            _ => Err(Error::new_spanned($ty, "expected primitive integer type or `String`")),
+        }
+    };
+}
+
+/// Returns the identifier of a single segment type path.
+fn type_ident(ty: &Type) -> Result<&Ident> {
+    match ty {
+        Type::Path(path) if path.qself.is_none() => path.path.get_ident(),
+        _ => None,
+    }
+    .ok_or_else(|| Error::new_spanned(ty, "expected primitive integer type or `String`"))
+}
+
+impl Entries for SetEntries {
+    const TYPE_NAME: &'static str = "Set";
+
+    fn archive(&self, type_args: &[&Type]) -> Result<Vec<u8>> {
+        let [key_ty] = type_args else {
+            return Err(Error::new(Span::call_site(), "expected `Set<K>` type"));
+        };
+
+        with_type!(key_ty, K => {
+            let keys: Vec<K> = parse_unique_keys(&self.keys)?;
+            let set = Set::<K>::from_iter_with_params(keys, DEFAULT_GAMMA)
+                .map_err(|err| Error::new(Span::call_site(), format!("failed to build set: {err:?}")))?;
+            to_bytes(&set)
+        })
+    }
+}
+
+impl Entries for MapEntries {
+    const TYPE_NAME: &'static str = "MapWithDict";
+
+    fn archive(&self, type_args: &[&Type]) -> Result<Vec<u8>> {
+        let [key_ty, value_ty] = type_args else {
+            return Err(Error::new(Span::call_site(), "expected `MapWithDict<K, V>` type"));
+        };
+
+        let keys: Vec<Expr> = self.pairs.iter().map(|(key, _)| key.clone()).collect();
+        with_type!(key_ty, K => {
+            let keys: Vec<K> = parse_unique_keys(&keys)?;
+            with_type!(value_ty, V => {
+                let values = self
+                    .pairs
+                    .iter()
+                    .map(|(_, value)| V::from_literal(value))
+                    .collect::<Result<Vec<V>>>()?;
+                let map = MapWithDict::<K, V>::from_iter_with_params(keys.into_iter().zip(values), DEFAULT_GAMMA)
+                    .map_err(|err| Error::new(Span::call_site(), format!("failed to build map: {err:?}")))?;
+                to_bytes(&map)
+            })
+        })
+    }
+}
+
+/// Parses `exprs` as keys, failing on duplicates.
+fn parse_unique_keys<K: FromLiteral + Hash + Eq + Clone>(exprs: &[Expr]) -> Result<Vec<K>> {
+    let mut seen = HashSet::with_capacity(exprs.len());
+    let mut keys = Vec::with_capacity(exprs.len());
+    for expr in exprs {
+        let key = K::from_literal(expr)?;
+        if !seen.insert(key.clone()) {
+            return Err(Error::new_spanned(expr, "duplicate key"));
+        }
+        keys.push(key);
+    }
+    Ok(keys)
+}
+
+/// Serializes `value` into `rkyv` archive bytes.
+fn to_bytes<T: rkyv::Serialize<AllocSerializer<SCRATCH_SIZE>>>(value: &T) -> Result<Vec<u8>> {
+    rkyv::to_bytes::<_, SCRATCH_SIZE>(value)
+        .map(|bytes| bytes.to_vec())
+        .map_err(|err| Error::new(Span::call_site(), format!("failed to serialize: {err}")))
+}
+
+/// Types constructed from literal expressions.
+trait FromLiteral: Sized {
+    fn from_literal(expr: &Expr) -> Result<Self>;
+}
+
+impl FromLiteral for String {
+    fn from_literal(expr: &Expr) -> Result<Self> {
+        match expr {
+            Expr::Lit(ExprLit { lit: Lit::Str(lit), .. }) => Ok(lit.value()),
+            _ => Err(Error::new_spanned(expr, "expected string literal")),
+        }
+    }
+}
+
+macro_rules! impl_from_literal_int {
+    ($($t:ty),*) => {
+        $(
+            impl FromLiteral for $t {
+                fn from_literal(expr: &Expr) -> Result<Self> {
+                    let (negative, lit) = match expr {
+                        Expr::Lit(ExprLit { lit: Lit::Int(lit), .. }) => (false, lit),
+                        Expr::Unary(ExprUnary { op: UnOp::Neg(_), expr, .. }) => match expr.as_ref() {
+                            Expr::Lit(ExprLit { lit: Lit::Int(lit), .. }) => (true, lit),
+                            _ => return Err(Error::new_spanned(expr, "expected integer literal")),
+                        },
+                        _ => return Err(Error::new_spanned(expr, "expected integer literal")),
+                    };
+                    let digits = if negative { format!("-{}", lit.base10_digits()) } else { lit.base10_digits().to_string() };
+                    <$t>::from_str(&digits).map_err(|err| Error::new_spanned(expr, err))
+                }
+            }
+        )*
+    };
+}
+
+impl_from_literal_int!(u8, u16, u32, u64, u128, i8, i16, i32, i64, i128);
+
+/// Expands `#[derive(EntropyKey)]` into `EntropyKey` and `Hash` implementations.
+fn expand_entropy_key(mut input: DeriveInput) -> Result<TokenStream> {
+    let Data::Struct(data) = &input.data else {
+        return Err(Error::new_spanned(
+            &input.ident,
+            "EntropyKey can only be derived for structs",
+        ));
+    };
+    let fields: Vec<TokenStream> = match &data.fields {
+        Fields::Named(fields) => fields
+            .named
+            .iter()
+            .map(|field| {
+                let name = &field.ident;
+                quote!(self.#name)
+            })
+            .collect(),
+        Fields::Unnamed(fields) => (0..fields.unnamed.len())
+            .map(|idx| {
+                let idx = Index::from(idx);
+                quote!(self.#idx)
+            })
+            .collect(),
+        Fields::Unit => Vec::new(),
+    };
+
+    for param in input.generics.type_params_mut() {
+        param.bounds.push(parse_quote!(::entropy_map::EntropyKey));
+    }
+    let name = &input.ident;
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+
+    Ok(quote! {
+        impl #impl_generics ::entropy_map::EntropyKey for #name #ty_generics #where_clause {
+            #[inline]
+            fn write_key<__H: ::core::hash::Hasher>(&self, state: &mut __H) {
+                #(::entropy_map::EntropyKey::write_key(&#fields, state);)*
+            }
+        }
+
+        impl #impl_generics ::core::hash::Hash for #name #ty_generics #where_clause {
+            #[inline]
+            fn hash<__H: ::core::hash::Hasher>(&self, state: &mut __H) {
+                ::entropy_map::EntropyKey::write_key(self, state);
+            }
+        }
+    })
+}
@@ -0,0 +1,55 @@
+use std::collections::HashMap;
+
+use entropy_map::{EntropyKey, MapWithDict, Set, DEFAULT_GAMMA};
+use entropy_map_macros::EntropyKey;
+
+#[derive(EntropyKey, Debug, Clone, PartialEq, Eq)]
+struct Route {
+    prefix: [u8; 4],
+    prefix_len: usize,
+    region: Option<String>,
+}
+
+#[derive(EntropyKey, Debug, Clone, PartialEq, Eq)]
+struct Pair<T>(T, u8);
+
+#[derive(EntropyKey, Debug, Clone, PartialEq, Eq)]
+struct Unit;
+
+#[test]
+fn test_key_bytes() {
+    let route = Route { prefix: [10, 0, 0, 0], prefix_len: 8, region: Some("eu".to_string()) };
+    let mut expected = vec![10, 0, 0, 0];
+    expected.extend_from_slice(&8u64.to_le_bytes());
+    expected.push(1);
+    expected.extend_from_slice(&2u64.to_le_bytes());
+    expected.extend_from_slice(b"eu");
+    assert_eq!(route.key_bytes(), expected);
+
+    assert_eq!(Pair(0x0102u16, 3).key_bytes(), [2, 1, 3]);
+    assert_eq!(Unit.key_bytes(), Vec::<u8>::new());
+}
+
+#[test]
+fn test_derived_keys() {
+    let routes: HashMap<Route, u32> = (0..1000)
+        .map(|i: u32| {
+            let route = Route {
+                prefix: i.to_be_bytes(),
+                prefix_len: (i % 33) as usize,
+                region: i.is_multiple_of(2).then(|| format!("region-{}", i % 7)),
+            };
+            (route, i % 10)
+        })
+        .collect();
+
+    let map = MapWithDict::<Route, u32>::from_iter_with_params(routes.clone(), DEFAULT_GAMMA).unwrap();
+    for (route, value) in &routes {
+        assert_eq!(map.get(route), Some(value));
+    }
+    assert_eq!(map.get(&Route { prefix: [0; 4], prefix_len: 1, region: None }), None);
+
+    let set = Set::<Pair<String>>::from_iter_with_params([Pair("a".to_string(), 1)], DEFAULT_GAMMA).unwrap();
+    assert!(set.contains(&Pair("a".to_string(), 1)));
+    assert!(!set.contains(&Pair("a".to_string(), 2)));
+}
@@ -0,0 +1,49 @@
+use entropy_map_macros::{static_map, static_set};
+
+static_set! {
+    static EMPTY: Set<u64> = [];
+    static SIGNED: Set<i64> = [-3, 0, 7, 9223372036854775807];
+}
+
+static_map! {
+    static STATUS_CODES: MapWithDict<u16, String> = {
+        200 => "OK",
+        404 => "Not Found",
+        418 => "I'm a teapot",
+        500 => "Internal Server Error",
+    };
+    pub(crate) static WEIGHTS: MapWithDict<String, u8> = { "a" => 1, "b" => 2, "c" => 1 };
+}
+
+#[test]
+fn test_static_set() {
+    assert!(!EMPTY.contains(&0));
+
+    for key in [-3, 0, 7, i64::MAX] {
+        assert!(SIGNED.contains(&key));
+    }
+    assert!(!SIGNED.contains(&3));
+}
+
+#[test]
+fn test_static_map() {
+    assert_eq!(STATUS_CODES.get(&404).map(|v| v.as_str()), Some("Not Found"));
+    assert_eq!(STATUS_CODES.get(&418).map(|v| v.as_str()), Some("I'm a teapot"));
+    assert_eq!(STATUS_CODES.get(&201), None);
+
+    assert_eq!(WEIGHTS.get("a"), Some(&1));
+    assert_eq!(WEIGHTS.get("b"), Some(&2));
+    assert_eq!(WEIGHTS.get("c"), Some(&1));
+    assert_eq!(WEIGHTS.get("d"), None);
+}
+
+#[test]
+fn test_static_archive_matches_runtime_archive() {
+    let map = entropy_map::MapWithDict::<String, u8>::from_iter_with_params(
+        [("a", 1), ("b", 2), ("c", 1)].map(|(k, v)| (k.to_string(), v)),
+        entropy_map::DEFAULT_GAMMA,
+    )
+    .unwrap();
+    let bytes = rkyv::to_bytes::<_, 4096>(&map).unwrap();
+    assert_eq!(WEIGHTS.bytes(), bytes.as_slice());
+}
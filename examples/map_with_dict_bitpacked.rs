@@ -19,7 +19,7 @@ fn main() {
     assert_eq!(values_buf, vec![1, 2, 3]);
 
     // Test a key that is not present in the MPHF
-    assert_eq!(map.get_values(&4, &mut values_buf), false);
+    assert!(!map.get_values(&4, &mut values_buf));
 
     #[cfg(feature = "rkyv_derive")]
     {
@@ -33,6 +33,6 @@ fn main() {
         assert_eq!(values_buf, vec![3, 5, 7]);
         assert!(rkyv_map.get_values(&3, &mut values_buf));
         assert_eq!(values_buf, vec![1, 2, 3]);
-        assert_eq!(rkyv_map.get_values(&4, &mut values_buf), false);
+        assert!(!rkyv_map.get_values(&4, &mut values_buf));
     }
 }
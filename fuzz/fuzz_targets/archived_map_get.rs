@@ -0,0 +1,38 @@
+#![no_main]
+
+use std::sync::OnceLock;
+
+use entropy_map::MapWithDict;
+use libfuzzer_sys::fuzz_target;
+use rkyv::AlignedVec;
+
+/// Number of keys in the map used as a base for archive mutations.
+const KEYS_NUM: u64 = 1000;
+
+/// Returns serialized bytes of a small deterministic map.
+fn archive_bytes() -> &'static AlignedVec {
+    static BYTES: OnceLock<AlignedVec> = OnceLock::new();
+    BYTES.get_or_init(|| {
+        let map = MapWithDict::<u64, u32>::from_iter_with_params((0..KEYS_NUM).map(|k| (k, (k % 7) as u32)), 2.0)
+            .expect("failed to build map");
+        rkyv::to_bytes::<_, 1024>(&map).expect("failed to serialize map")
+    })
+}
+
+fuzz_target!(|mutations: Vec<(u16, u8)>| {
+    let mut bytes = archive_bytes().clone();
+    let len = bytes.len();
+    for (pos, val) in mutations {
+        bytes[pos as usize % len] ^= val;
+    }
+
+    // validation doesn't bound-check values and level group indices, so queries of mutated archives
+    // may still go out of bounds: `forbid-unsafe-queries` turns these into panics rather than UB
+    if let Ok(map) = rkyv::check_archived_root::<MapWithDict<u64, u32>>(&bytes) {
+        for key in 0..2 * KEYS_NUM {
+            let _ = map.get(&key);
+            let _ = map.contains_key(&key);
+        }
+        for _ in map.iter() {}
+    }
+});
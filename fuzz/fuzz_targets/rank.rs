@@ -0,0 +1,25 @@
+#![no_main]
+
+use entropy_map::{RankedBits, RankedBitsAccess};
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|input: (Vec<u64>, usize)| {
+    let (bits, idx) = input;
+    if bits.is_empty() {
+        return;
+    }
+
+    let idx = idx % (bits.len() * 64);
+    let ranked_bits = RankedBits::new(bits.clone().into_boxed_slice());
+
+    // compare against naive rank computation
+    let expected = if bits[idx / 64] & (1 << (idx % 64)) != 0 {
+        let full_words: usize = bits[..idx / 64].iter().map(|w| w.count_ones() as usize).sum();
+        let partial_word = (bits[idx / 64] & ((1 << (idx % 64)) - 1)).count_ones() as usize;
+        Some(full_words + partial_word)
+    } else {
+        None
+    };
+
+    assert_eq!(ranked_bits.rank(idx as u64), expected);
+});
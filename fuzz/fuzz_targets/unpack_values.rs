@@ -0,0 +1,20 @@
+#![no_main]
+
+use entropy_map::fuzzing::unpack_values;
+use libfuzzer_sys::fuzz_target;
+
+/// Padding appended to the dictionary by `MapWithDictBitpacked` construction.
+const DICT_PADDING: usize = 4 * 32;
+
+fuzz_target!(|input: (u8, Vec<u8>)| {
+    let (n, mut dict) = input;
+    if dict.is_empty() {
+        return;
+    }
+
+    // pad dictionary the same way `MapWithDictBitpacked` does
+    dict.resize(dict.len() + DICT_PADDING, 0);
+
+    let mut values = vec![0u32; n as usize];
+    unpack_values(&dict, &mut values);
+});
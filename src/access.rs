@@ -0,0 +1,40 @@
+//! Internal helpers centralizing slice accesses used on query paths.
+//!
+//! Query paths index into keys, values and rank arrays using indices derived from the MPHF, which
+//! are always within bounds for structures built by this crate, so by default these helpers skip
+//! bounds checks. With the `forbid-unsafe-queries` feature enabled they fall back to regular
+//! checked indexing instead, turning corrupted or maliciously crafted data (e.g. tampered archives)
+//! into panics rather than undefined behavior at a small performance cost.
+
+use std::slice::SliceIndex;
+
+/// Returns a reference to an element or subslice of `slice` at `index`.
+///
+/// # Safety
+/// Unless `forbid-unsafe-queries` feature is enabled, `index` must be within bounds of `slice`,
+/// see [`slice::get_unchecked`](https://doc.rust-lang.org/std/primitive.slice.html#method.get_unchecked).
+#[inline(always)]
+pub(crate) unsafe fn get<T, I: SliceIndex<[T]>>(slice: &[T], index: I) -> &I::Output {
+    #[cfg(feature = "forbid-unsafe-queries")]
+    {
+        &slice[index]
+    }
+    #[cfg(not(feature = "forbid-unsafe-queries"))]
+    {
+        slice.get_unchecked(index)
+    }
+}
+
+/// Hints the CPU to fetch the element of `slice` at `index` into the cache ahead of an access.
+/// It's a no-op for out of bounds indices and on targets without prefetch instructions support.
+#[inline(always)]
+pub(crate) fn prefetch<T>(slice: &[T], index: usize) {
+    #[cfg(target_arch = "x86_64")]
+    if index < slice.len() {
+        use std::arch::x86_64::{_mm_prefetch, _MM_HINT_T0};
+        // SAFETY: `index` is within bounds of `slice`, moreover prefetching never faults
+        unsafe { _mm_prefetch::<_MM_HINT_T0>(slice.as_ptr().add(index) as *const i8) }
+    }
+    #[cfg(not(target_arch = "x86_64"))]
+    let _ = (slice, index);
+}
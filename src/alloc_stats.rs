@@ -0,0 +1,175 @@
+//! A module providing `CountingAllocator`, a global allocator wrapper tracking allocated bytes, used
+//! to report peak memory usage of constructions in `BuildReport::peak_alloc_bytes`.
+//!
+//! Construction temporarily needs several times the memory of the final structure (key hashes,
+//! level group bits and seeds, values deduplication caches), so knowing the peak helps sizing build
+//! machines. Tracking requires installing `CountingAllocator` as the global allocator of the
+//! application:
+//!
+//! ```
+//! use std::alloc::System;
+//! use entropy_map::{CountingAllocator, Mphf, DEFAULT_GAMMA};
+//!
+//! #[global_allocator]
+//! static GLOBAL: CountingAllocator = CountingAllocator(System);
+//!
+//! fn main() {
+//!     let keys: Vec<u64> = (0..1000).collect();
+//!     let (mphf, report) = Mphf::<32, 8>::from_slice_with_report(&keys, DEFAULT_GAMMA).unwrap();
+//!     assert!(report.peak_alloc_bytes.unwrap() >= mphf.size() + keys.len() * 8);
+//! }
+//! ```
+//!
+//! Allocations are accounted per thread, so that peaks of structures built concurrently don't
+//! interfere: a construction accounts for allocations made by the thread running it only.
+
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::cell::Cell;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+thread_local! {
+    /// Net number of bytes allocated by the current thread via `CountingAllocator`, negative if the
+    /// thread freed more memory allocated by other threads than it allocated itself.
+    static ALLOCATED: Cell<isize> = const { Cell::new(0) };
+    /// Peak of `ALLOCATED` since the last tracking start.
+    static PEAK: Cell<isize> = const { Cell::new(0) };
+}
+
+/// Whether `CountingAllocator` is installed, i.e. has allocated anything.
+static INSTALLED: AtomicBool = AtomicBool::new(false);
+
+/// Global allocator wrapper counting bytes allocated by the inner allocator `A`.
+#[derive(Debug, Default)]
+pub struct CountingAllocator<A = System>(pub A);
+
+impl<A> CountingAllocator<A> {
+    /// Records `size` newly allocated bytes.
+    #[inline]
+    fn add(size: usize) {
+        if !INSTALLED.load(Ordering::Relaxed) {
+            INSTALLED.store(true, Ordering::Relaxed);
+        }
+        // accesses fail only during thread teardown, when there's nothing to track anymore
+        let _ = ALLOCATED.try_with(|allocated| {
+            let new_allocated = allocated.get().wrapping_add(size as isize);
+            allocated.set(new_allocated);
+            let _ = PEAK.try_with(|peak| peak.set(peak.get().max(new_allocated)));
+        });
+    }
+
+    /// Records `size` freed bytes.
+    #[inline]
+    fn sub(size: usize) {
+        let _ = ALLOCATED.try_with(|allocated| allocated.set(allocated.get().wrapping_sub(size as isize)));
+    }
+}
+
+// SAFETY: all allocations are delegated to the inner allocator as is
+unsafe impl<A: GlobalAlloc> GlobalAlloc for CountingAllocator<A> {
+    #[inline]
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        let ptr = self.0.alloc(layout);
+        if !ptr.is_null() {
+            Self::add(layout.size());
+        }
+        ptr
+    }
+
+    #[inline]
+    unsafe fn alloc_zeroed(&self, layout: Layout) -> *mut u8 {
+        let ptr = self.0.alloc_zeroed(layout);
+        if !ptr.is_null() {
+            Self::add(layout.size());
+        }
+        ptr
+    }
+
+    #[inline]
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        self.0.dealloc(ptr, layout);
+        Self::sub(layout.size());
+    }
+
+    #[inline]
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        let new_ptr = self.0.realloc(ptr, layout, new_size);
+        if !new_ptr.is_null() {
+            if new_size > layout.size() {
+                Self::add(new_size - layout.size());
+            } else {
+                Self::sub(layout.size() - new_size);
+            }
+        }
+        new_ptr
+    }
+}
+
+/// Tracks peak bytes allocated by the current thread between its `start` and `finish`, supporting
+/// nested tracking, e.g. of `Mphf` construction within a map construction.
+pub(crate) struct PeakTracker {
+    /// Bytes allocated when tracking started
+    baseline: isize,
+    /// Peak of the enclosing tracking, restored on finish
+    outer_peak: isize,
+}
+
+impl PeakTracker {
+    /// Starts tracking peak allocated bytes.
+    #[inline]
+    pub(crate) fn start() -> Self {
+        let baseline = ALLOCATED.with(Cell::get);
+        let outer_peak = PEAK.with(|peak| peak.replace(baseline));
+        PeakTracker { baseline, outer_peak }
+    }
+
+    /// Finishes tracking and returns the peak number of bytes allocated on top of the baseline, or
+    /// `None` if `CountingAllocator` is not installed as the global allocator.
+    #[inline]
+    pub(crate) fn finish(self) -> Option<usize> {
+        let peak = PEAK.with(|peak| peak.replace(peak.get().max(self.outer_peak)));
+        INSTALLED
+            .load(Ordering::Relaxed)
+            .then(|| peak.saturating_sub(self.baseline).max(0) as usize)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{MapWithDict, Mphf, Set, DEFAULT_GAMMA};
+
+    #[global_allocator]
+    static GLOBAL: CountingAllocator = CountingAllocator(System);
+
+    #[test]
+    fn test_peak_tracker() {
+        let outer = PeakTracker::start();
+        let outer_buf = vec![0u8; 1 << 20];
+
+        let inner = PeakTracker::start();
+        drop(vec![0u8; 1 << 16]);
+        let inner_peak = inner.finish().unwrap();
+
+        drop(outer_buf);
+        let outer_peak = outer.finish().unwrap();
+
+        assert_eq!(inner_peak, 1 << 16);
+        assert_eq!(outer_peak, (1 << 20) + (1 << 16));
+    }
+
+    #[test]
+    fn test_build_report_peak_alloc_bytes() {
+        let keys: Vec<u64> = (0..10_000).collect();
+
+        let (mphf, report) = Mphf::<32, 8>::from_slice_with_report(&keys, DEFAULT_GAMMA).unwrap();
+        // key hashes are the largest transient allocation
+        assert!(report.peak_alloc_bytes.unwrap() >= mphf.size() + 8 * keys.len());
+
+        let (set, report) = Set::<u64>::from_iter_with_report(keys.clone(), DEFAULT_GAMMA).unwrap();
+        assert!(report.peak_alloc_bytes.unwrap() >= set.size() + 8 * keys.len());
+
+        let (map, report) =
+            MapWithDict::<u64, u64>::from_iter_with_report(keys.iter().map(|&k| (k, k % 100)), DEFAULT_GAMMA).unwrap();
+        assert!(report.peak_alloc_bytes.unwrap() >= map.size() + 8 * keys.len());
+    }
+}
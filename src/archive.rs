@@ -0,0 +1,130 @@
+//! Helpers for opening archived structures without full `bytecheck` validation.
+//!
+//! `rkyv::check_archived_root` validates every byte of an archive, which for multi-GB maps adds
+//! noticeable startup latency. When archives are produced by a trusted pipeline, validation can be
+//! skipped with [`open_trusted`], or replaced with a much cheaper checksum verification using
+//! [`append_checksum`] at build time and [`open_checksummed`] at load time.
+//!
+//! Neither helper makes accessing malformed archives safe: a checksum only detects accidental
+//! corruption (truncated downloads, bit flips, partially written files), not maliciously crafted
+//! archives. Use `check_archived_root` for untrusted inputs.
+
+use rkyv::{AlignedVec, Archive, Archived};
+
+/// Size of the checksum trailer appended by [`append_checksum`].
+const CHECKSUM_SIZE: usize = 8;
+
+/// Seed of the checksum hash function.
+const CHECKSUM_SEED: u64 = 0x656e74726f7079;
+
+/// Errors that can occur when opening a checksummed archive.
+#[derive(Debug, PartialEq)]
+pub enum ChecksumError {
+    /// Error when the archive is too short to contain a checksum trailer.
+    Truncated,
+    /// Error when the stored checksum doesn't match the archive bytes.
+    Mismatch,
+}
+
+/// Returns a reference to the archived root of `T` stored in `bytes` without validating it.
+///
+/// # Safety
+/// `bytes` must contain a valid archive of `T`, i.e. one that `rkyv::check_archived_root::<T>`
+/// would accept, e.g. produced by `rkyv::to_bytes` of the same `T` with the same crate versions,
+/// and it must be properly aligned. Accessing an invalid archive is undefined behavior.
+///
+/// # Examples
+/// ```
+/// # use std::collections::HashMap;
+/// # use entropy_map::{open_trusted, MapWithDict};
+/// let map = MapWithDict::try_from(HashMap::from([(1u32, 2u32), (3, 4)])).unwrap();
+/// let bytes = rkyv::to_bytes::<_, 1024>(&map).unwrap();
+///
+/// // SAFETY: `bytes` were produced by `rkyv::to_bytes` above
+/// let archived_map = unsafe { open_trusted::<MapWithDict<u32, u32>>(&bytes) };
+/// assert_eq!(archived_map.get(&1), Some(&2));
+/// ```
+#[inline]
+pub unsafe fn open_trusted<T: Archive>(bytes: &[u8]) -> &Archived<T> {
+    rkyv::archived_root::<T>(bytes)
+}
+
+/// Appends a checksum of the archive `bytes` to their end, to be verified by [`open_checksummed`].
+///
+/// # Examples
+/// ```
+/// # use std::collections::HashMap;
+/// # use entropy_map::{append_checksum, open_checksummed, MapWithDict};
+/// let map = MapWithDict::try_from(HashMap::from([(1u32, 2u32), (3, 4)])).unwrap();
+/// let mut bytes = rkyv::to_bytes::<_, 1024>(&map).unwrap();
+/// append_checksum(&mut bytes);
+///
+/// // SAFETY: `bytes` were produced by `rkyv::to_bytes` and `append_checksum` above
+/// let archived_map = unsafe { open_checksummed::<MapWithDict<u32, u32>>(&bytes) }.unwrap();
+/// assert_eq!(archived_map.get(&1), Some(&2));
+/// ```
+#[inline]
+pub fn append_checksum(bytes: &mut AlignedVec) {
+    let checksum = checksum(bytes.as_slice());
+    bytes.extend_from_slice(&checksum.to_le_bytes());
+}
+
+/// Verifies the checksum appended by [`append_checksum`] and returns a reference to the archived
+/// root of `T` stored in `bytes` without further validation.
+///
+/// # Safety
+/// Same as for [`open_trusted`], with the archive being `bytes` without the checksum trailer.
+/// A matching checksum gives assurance against accidental corruption only.
+#[inline]
+pub unsafe fn open_checksummed<T: Archive>(bytes: &[u8]) -> Result<&Archived<T>, ChecksumError> {
+    let archive_len = bytes.len().checked_sub(CHECKSUM_SIZE).ok_or(ChecksumError::Truncated)?;
+    let (archive, trailer) = bytes.split_at(archive_len);
+
+    if checksum(archive).to_le_bytes() != trailer {
+        return Err(ChecksumError::Mismatch);
+    }
+
+    Ok(open_trusted::<T>(archive))
+}
+
+/// Computes checksum of the archive `bytes`.
+#[inline]
+fn checksum(bytes: &[u8]) -> u64 {
+    wyhash::wyhash(bytes, CHECKSUM_SEED)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::MapWithDict;
+    use rand::{Rng, SeedableRng};
+    use rand_chacha::ChaCha8Rng;
+    use std::collections::HashMap;
+
+    #[test]
+    fn test_open_checksummed() {
+        let mut rng = ChaCha8Rng::seed_from_u64(123);
+        let original_map: HashMap<u64, u32> = (0..1000).map(|_| (rng.gen(), rng.gen_range(1..=10))).collect();
+        let map = MapWithDict::try_from(original_map.clone()).unwrap();
+
+        let mut bytes = rkyv::to_bytes::<_, 1024>(&map).unwrap();
+        let archive_len = bytes.len();
+        append_checksum(&mut bytes);
+        assert_eq!(bytes.len(), archive_len + CHECKSUM_SIZE);
+
+        let archived_map = unsafe { open_checksummed::<MapWithDict<u64, u32>>(&bytes) }.unwrap();
+        let trusted_map = unsafe { open_trusted::<MapWithDict<u64, u32>>(&bytes[..archive_len]) };
+        for (k, v) in original_map.iter() {
+            assert_eq!(archived_map.get(k), Some(v));
+            assert_eq!(trusted_map.get(k), Some(v));
+        }
+
+        let mut corrupted_bytes = bytes.clone();
+        corrupted_bytes[rng.gen_range(0..archive_len)] ^= 1;
+        let res = unsafe { open_checksummed::<MapWithDict<u64, u32>>(&corrupted_bytes) };
+        assert_eq!(res.err(), Some(ChecksumError::Mismatch));
+
+        let res = unsafe { open_checksummed::<MapWithDict<u64, u32>>(&bytes[..CHECKSUM_SIZE - 1]) };
+        assert_eq!(res.err(), Some(ChecksumError::Truncated));
+    }
+}
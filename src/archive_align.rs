@@ -0,0 +1,187 @@
+//! A module providing `MinAlignSerializer`, an rkyv serializer aligning archived arrays.
+//!
+//! rkyv aligns every out-of-line array of an archive (bit vectors, rank metadata, keys, values
+//! indices and dictionaries) only as much as its element type requires, e.g. to 8 bytes for `u64`
+//! words, so hot arrays of memory-mapped archives may start in the middle of a cache line and
+//! queries touching their first entries may access two cache lines. `MinAlignSerializer` wraps a
+//! serializer and aligns every array and boxed value to at least the requested alignment, e.g. 64
+//! bytes for cache lines or 4096 bytes for pages, at the cost of up to `align - 1` padding bytes
+//! per array. Strings are not aligned.
+//!
+//! Positions are aligned relative to the start of the archive, so arrays are aligned in memory only
+//! if the archive itself is at least as aligned, which holds for memory-mapped files, while
+//! `AlignedVec` returned by [`to_bytes_with_align`] is only 16-byte aligned. Archives remain
+//! regular rkyv archives, readable with `check_archived_root` regardless of the alignment.
+
+use std::alloc::Layout;
+use std::mem::align_of;
+use std::ptr::NonNull;
+
+use rkyv::ser::serializers::AllocSerializer;
+use rkyv::ser::{ScratchSpace, Serializer, SharedSerializeRegistry};
+use rkyv::{AlignedVec, Fallible, Serialize};
+
+/// Serializer wrapper aligning every array and boxed value to at least `align` bytes, see the
+/// module documentation.
+pub struct MinAlignSerializer<S> {
+    /// Wrapped serializer
+    inner: S,
+    /// Minimum alignment of arrays and boxed values, a power of two
+    align: usize,
+}
+
+impl<S> MinAlignSerializer<S> {
+    /// Wraps `inner` serializer, aligning arrays and boxed values to at least `align` bytes.
+    ///
+    /// # Panics
+    /// Panics if `align` is not a power of two.
+    pub fn new(inner: S, align: usize) -> Self {
+        assert!(align.is_power_of_two(), "alignment {align} is not a power of two");
+        MinAlignSerializer { inner, align }
+    }
+
+    /// Returns the minimum alignment of arrays and boxed values.
+    #[inline]
+    pub fn align(&self) -> usize {
+        self.align
+    }
+
+    /// Consumes the wrapper returning the wrapped serializer.
+    #[inline]
+    pub fn into_inner(self) -> S {
+        self.inner
+    }
+}
+
+impl<S: Fallible> Fallible for MinAlignSerializer<S> {
+    type Error = S::Error;
+}
+
+impl<S: Serializer> Serializer for MinAlignSerializer<S> {
+    #[inline]
+    fn pos(&self) -> usize {
+        self.inner.pos()
+    }
+
+    #[inline]
+    fn write(&mut self, bytes: &[u8]) -> Result<(), Self::Error> {
+        self.inner.write(bytes)
+    }
+
+    /// Writes `padding` zero bytes, which unlike the default implementation may exceed 32 bytes.
+    #[inline]
+    fn pad(&mut self, mut padding: usize) -> Result<(), Self::Error> {
+        const ZEROES: [u8; 64] = [0; 64];
+        while padding > 0 {
+            let len = padding.min(ZEROES.len());
+            self.inner.write(&ZEROES[..len])?;
+            padding -= len;
+        }
+        Ok(())
+    }
+
+    /// Aligns to the alignment of `T`, but to at least the minimum alignment. rkyv calls it before
+    /// writing every array and boxed value, as well as the root object.
+    #[inline]
+    fn align_for<T>(&mut self) -> Result<usize, Self::Error> {
+        Serializer::align(self, align_of::<T>().max(self.align))
+    }
+}
+
+impl<S: ScratchSpace> ScratchSpace for MinAlignSerializer<S> {
+    #[inline]
+    unsafe fn push_scratch(&mut self, layout: Layout) -> Result<NonNull<[u8]>, Self::Error> {
+        self.inner.push_scratch(layout)
+    }
+
+    #[inline]
+    unsafe fn pop_scratch(&mut self, ptr: NonNull<u8>, layout: Layout) -> Result<(), Self::Error> {
+        self.inner.pop_scratch(ptr, layout)
+    }
+}
+
+impl<S: SharedSerializeRegistry> SharedSerializeRegistry for MinAlignSerializer<S> {
+    #[inline]
+    fn get_shared_ptr(&self, value: *const u8) -> Option<usize> {
+        self.inner.get_shared_ptr(value)
+    }
+
+    #[inline]
+    fn add_shared_ptr(&mut self, value: *const u8, pos: usize) -> Result<(), Self::Error> {
+        self.inner.add_shared_ptr(value, pos)
+    }
+}
+
+/// Same as `rkyv::to_bytes`, but aligns every array and boxed value of the archive to at least
+/// `align` bytes, see the module documentation.
+///
+/// # Panics
+/// Panics if `align` is not a power of two.
+///
+/// # Examples
+/// ```
+/// # use entropy_map::{to_bytes_with_align, Set, DEFAULT_GAMMA};
+/// let set = Set::<u64>::from_iter_with_params(0..1000, DEFAULT_GAMMA).unwrap();
+/// let bytes = to_bytes_with_align::<_, 1024>(&set, 64).unwrap();
+/// let archived_set = rkyv::check_archived_root::<Set<u64>>(&bytes).unwrap();
+/// assert!(archived_set.contains(&1));
+/// ```
+pub fn to_bytes_with_align<T, const N: usize>(
+    value: &T,
+    align: usize,
+) -> Result<AlignedVec, <AllocSerializer<N> as Fallible>::Error>
+where
+    T: Serialize<MinAlignSerializer<AllocSerializer<N>>>,
+{
+    let mut serializer = MinAlignSerializer::new(AllocSerializer::<N>::default(), align);
+    serializer.serialize_value(value)?;
+    Ok(serializer.into_inner().into_serializer().into_inner())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{MapWithDict, DEFAULT_GAMMA};
+
+    /// Returns the offset of the array starting at `ptr` within `bytes`.
+    fn offset<T>(bytes: &[u8], ptr: *const T) -> usize {
+        ptr as usize - bytes.as_ptr() as usize
+    }
+
+    #[test]
+    fn test_to_bytes_with_align() {
+        let map = MapWithDict::<u64, u32>::from_iter_with_params((0..10_000).map(|i| (i, i as u32 % 7)), DEFAULT_GAMMA)
+            .unwrap();
+        let bytes = rkyv::to_bytes::<_, 1024>(&map).unwrap();
+        assert_eq!(
+            to_bytes_with_align::<_, 1024>(&map, 1).unwrap().as_slice(),
+            bytes.as_slice()
+        );
+
+        for align in [64, 4096] {
+            let aligned_bytes = to_bytes_with_align::<_, 1024>(&map, align).unwrap();
+            assert!(aligned_bytes.len() > bytes.len());
+
+            let archived = rkyv::check_archived_root::<MapWithDict<u64, u32>>(&aligned_bytes).unwrap();
+            for ptr in [
+                archived.mphf.ranked_bits.bits.as_ptr() as *const u8,
+                archived.mphf.ranked_bits.l12_ranks.as_ptr() as *const u8,
+                archived.mphf.group_seeds.as_ptr(),
+                archived.keys.as_ptr() as *const u8,
+                archived.values_index.as_ptr() as *const u8,
+                archived.values_dict.as_ptr() as *const u8,
+            ] {
+                assert_eq!(offset(&aligned_bytes, ptr) % align, 0);
+            }
+            for key in 0..10_000 {
+                assert_eq!(archived.get(&key), Some(&(key as u32 % 7)));
+            }
+        }
+    }
+
+    #[test]
+    #[should_panic(expected = "alignment 48 is not a power of two")]
+    fn test_invalid_align() {
+        let _ = to_bytes_with_align::<_, 1024>(&0u64, 48);
+    }
+}
@@ -0,0 +1,65 @@
+//! Opening memory-mapped archives of supported structures.
+
+use std::error::Error;
+use std::fs::File;
+use std::path::Path;
+
+use clap::ValueEnum;
+use entropy_map::{ArchivedMapWithDict, ArchivedSet, MapWithDict, Set};
+use memmap2::Mmap;
+
+/// Kind of structure stored in an archive.
+#[derive(Clone, Copy, Debug, PartialEq, ValueEnum)]
+pub enum Kind {
+    /// `MapWithDict<String, String>`
+    Map,
+    /// `Set<String>`
+    Set,
+}
+
+/// Memory-maps the archive file at `path`.
+pub fn mmap(path: &Path) -> Result<Mmap, Box<dyn Error>> {
+    let file = File::open(path).map_err(|err| format!("failed to open {}: {err}", path.display()))?;
+    // SAFETY: the archive file is expected not to be modified while mapped, which is the same
+    // assumption made by services memory-mapping archives
+    let mmap = unsafe { Mmap::map(&file) }.map_err(|err| format!("failed to mmap {}: {err}", path.display()))?;
+    Ok(mmap)
+}
+
+/// Validated archived structure borrowed from memory-mapped bytes.
+pub enum Archived<'a> {
+    Map(&'a ArchivedMapWithDict<String, String>),
+    Set(&'a ArchivedSet<String>),
+}
+
+impl<'a> Archived<'a> {
+    /// Validates `bytes` as an archive of the given `kind`.
+    pub fn check(bytes: &'a [u8], kind: Kind) -> Result<Self, Box<dyn Error>> {
+        let archived = match kind {
+            Kind::Map => Archived::Map(
+                rkyv::check_archived_root::<MapWithDict<String, String>>(bytes)
+                    .map_err(|err| format!("invalid map archive: {err}"))?,
+            ),
+            Kind::Set => Archived::Set(
+                rkyv::check_archived_root::<Set<String>>(bytes).map_err(|err| format!("invalid set archive: {err}"))?,
+            ),
+        };
+        Ok(archived)
+    }
+
+    /// Returns the value of `key`, or an error for sets which have no values.
+    pub fn get(&self, key: &str) -> Result<Option<&str>, &'static str> {
+        match self {
+            Archived::Map(map) => Ok(map.get(key).map(|value| value.as_str())),
+            Archived::Set(_) => Err("get is not supported for sets"),
+        }
+    }
+
+    /// Checks if `key` is present.
+    pub fn contains(&self, key: &str) -> bool {
+        match self {
+            Archived::Map(map) => map.contains_key(key),
+            Archived::Set(set) => set.contains(key),
+        }
+    }
+}
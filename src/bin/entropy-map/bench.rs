@@ -0,0 +1,206 @@
+//! `bench` subcommand measuring MPHF construction and queries on keys from a text file.
+//!
+//! Synthetic benchmarks over random `u64` keys don't predict behavior on real-world keys (e.g.
+//! skewed strings sharing long prefixes), so this builds `Mphf` over the actual keys with every
+//! requested combination of parameters and prints a table of build time, bits per key and query
+//! latency.
+
+use std::collections::HashSet;
+use std::error::Error;
+use std::fmt;
+use std::fs;
+use std::hint::black_box;
+use std::path::PathBuf;
+use std::str::FromStr;
+use std::time::{Duration, Instant};
+
+use clap::Args;
+use entropy_map::{Mphf, DEFAULT_GAMMA};
+
+/// Arguments of the `bench` subcommand.
+#[derive(Args)]
+pub struct BenchArgs {
+    /// Input text file with one key per line, only the part before the first tab is used
+    #[arg(long)]
+    input: PathBuf,
+    /// Comma separated MPHF parameters to compare, each `B/S` or `B/S/gamma`
+    #[arg(long, value_delimiter = ',', default_value = "16/4,32/8,64/8")]
+    params: Vec<Params>,
+    /// Number of passes over all keys when measuring queries
+    #[arg(long, default_value_t = 3)]
+    rounds: usize,
+}
+
+/// MPHF parameters of a single benchmark run.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Params {
+    /// Group size in bits, `B` const parameter of `Mphf`
+    b: usize,
+    /// Number of seed bits, `S` const parameter of `Mphf`
+    s: usize,
+    /// `gamma` parameter of the construction
+    gamma: f32,
+}
+
+/// Supported `B` values, each one is a separate `Mphf` instantiation compiled into the binary.
+const SUPPORTED_B: [usize; 5] = [8, 16, 32, 64, 128];
+
+/// Supported `S` values, each one is a separate `Mphf` instantiation compiled into the binary.
+const SUPPORTED_S: [usize; 3] = [4, 6, 8];
+
+impl FromStr for Params {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || format!("invalid params {s:?}, expected B/S or B/S/gamma");
+        let mut parts = s.split('/');
+        let b = parts.next().and_then(|b| b.parse().ok()).ok_or_else(invalid)?;
+        let s_bits = parts.next().and_then(|s| s.parse().ok()).ok_or_else(invalid)?;
+        let gamma = match parts.next() {
+            Some(gamma) => gamma.parse().map_err(|_| invalid())?,
+            None => DEFAULT_GAMMA,
+        };
+        if parts.next().is_some() {
+            return Err(invalid());
+        }
+
+        if !SUPPORTED_B.contains(&b) || !SUPPORTED_S.contains(&s_bits) {
+            return Err(format!(
+                "unsupported params {s:?}, B must be one of {SUPPORTED_B:?} and S one of {SUPPORTED_S:?}"
+            ));
+        }
+        Ok(Params { b, s: s_bits, gamma })
+    }
+}
+
+impl fmt::Display for Params {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}/{}/{:.2}", self.b, self.s, self.gamma)
+    }
+}
+
+/// Measurements of a single benchmark run.
+#[derive(Debug)]
+struct BenchResult {
+    /// Construction time
+    build_time: Duration,
+    /// Final size of `Mphf` in bits per key
+    bits_per_key: f32,
+    /// Number of levels built
+    levels: usize,
+    /// Average time of a single `get` query
+    query_time: Duration,
+}
+
+/// Builds `Mphf` with every requested combination of parameters and prints the results.
+pub fn run(args: BenchArgs) -> Result<(), Box<dyn Error>> {
+    let input =
+        fs::read_to_string(&args.input).map_err(|err| format!("failed to read {}: {err}", args.input.display()))?;
+    let keys: Vec<&str> = input
+        .lines()
+        .filter(|line| !line.is_empty())
+        .map(|line| line.split_once('\t').map_or(line, |(key, _)| key))
+        .collect::<HashSet<_>>()
+        .into_iter()
+        .collect();
+    if keys.is_empty() {
+        return Err(format!("no keys in {}", args.input.display()).into());
+    }
+
+    println!("{} unique keys, {} query rounds", keys.len(), args.rounds);
+    println!(
+        "{:<14} {:>10} {:>9} {:>7} {:>10}",
+        "B/S/gamma", "build_ms", "bits/key", "levels", "query_ns"
+    );
+    for params in &args.params {
+        let res = bench(&keys, *params, args.rounds).map_err(|err| format!("failed to build {params}: {err:?}"))?;
+        println!(
+            "{:<14} {:>10.1} {:>9.2} {:>7} {:>10.1}",
+            params.to_string(),
+            res.build_time.as_secs_f64() * 1e3,
+            res.bits_per_key,
+            res.levels,
+            res.query_time.as_secs_f64() * 1e9,
+        );
+    }
+    Ok(())
+}
+
+/// Dispatches to `bench_mphf` instantiation with `params` const parameters.
+fn bench(keys: &[&str], params: Params, rounds: usize) -> Result<BenchResult, entropy_map::MphfError> {
+    macro_rules! dispatch {
+        ($($b:literal),*; $s:tt) => {
+            match params.b {
+                $(
+                    $b => dispatch!(@s $b; $s),
+                )*
+                _ => unreachable!("unsupported B is rejected by Params::from_str"),
+            }
+        };
+        (@s $b:literal; [$($s:literal),*]) => {
+            match params.s {
+                $(
+                    $s => bench_mphf::<$b, $s>(keys, params.gamma, rounds),
+                )*
+                _ => unreachable!("unsupported S is rejected by Params::from_str"),
+            }
+        };
+    }
+    dispatch!(8, 16, 32, 64, 128; [4, 6, 8])
+}
+
+/// Builds `Mphf<B, S>` over `keys` and measures `rounds` passes of queries over all of them.
+fn bench_mphf<const B: usize, const S: usize>(
+    keys: &[&str],
+    gamma: f32,
+    rounds: usize,
+) -> Result<BenchResult, entropy_map::MphfError> {
+    let start = Instant::now();
+    let (mphf, report) = Mphf::<B, S>::from_slice_with_report(keys, gamma)?;
+    let build_time = start.elapsed();
+
+    let start = Instant::now();
+    for _ in 0..rounds {
+        for key in keys {
+            black_box(mphf.get(black_box(*key)));
+        }
+    }
+    let queries = (rounds * keys.len()).max(1) as u32;
+
+    Ok(BenchResult {
+        build_time,
+        bits_per_key: report.bits_per_key,
+        levels: report.levels.len(),
+        query_time: start.elapsed() / queries,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_params() {
+        assert_eq!("32/8".parse(), Ok(Params { b: 32, s: 8, gamma: DEFAULT_GAMMA }));
+        assert_eq!("16/4/1.5".parse(), Ok(Params { b: 16, s: 4, gamma: 1.5 }));
+        assert!("32".parse::<Params>().is_err());
+        assert!("32/8/x".parse::<Params>().is_err());
+        assert!("32/8/2.0/1".parse::<Params>().is_err());
+        assert!("33/8".parse::<Params>().is_err());
+        assert!("32/7".parse::<Params>().is_err());
+    }
+
+    #[test]
+    fn test_bench() {
+        let keys: Vec<String> = (0..10_000).map(|i| format!("key-{i}")).collect();
+        let keys: Vec<&str> = keys.iter().map(String::as_str).collect();
+
+        for b in SUPPORTED_B {
+            for s in SUPPORTED_S {
+                let res = bench(&keys, Params { b, s, gamma: DEFAULT_GAMMA }, 1).unwrap();
+                assert!(res.bits_per_key > 1.0, "B = {b}, S = {s}: {res:?}");
+                assert!(res.levels > 0);
+            }
+        }
+    }
+}
@@ -0,0 +1,72 @@
+//! `build` subcommand creating archives from text files.
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::error::Error;
+use std::fs;
+use std::path::PathBuf;
+
+use clap::Args;
+use entropy_map::{to_bytes_with_align, MapWithDict, Set, DEFAULT_GAMMA};
+
+use crate::archive::Kind;
+
+/// Arguments of the `build` subcommand.
+#[derive(Args)]
+pub struct BuildArgs {
+    /// Input text file, for maps with key and value separated by a tab
+    #[arg(long)]
+    input: PathBuf,
+    /// Output archive file
+    #[arg(long, short)]
+    output: PathBuf,
+    /// Kind of structure to build
+    #[arg(long, value_enum)]
+    kind: Kind,
+    /// MPHF gamma parameter
+    #[arg(long, default_value_t = DEFAULT_GAMMA)]
+    gamma: f32,
+    /// Minimum alignment of archived arrays in bytes, e.g. 64 to align them to cache lines when the
+    /// archive is memory-mapped, by default only as required by their element types
+    #[arg(long, default_value_t = 1, value_parser = parse_align)]
+    align: usize,
+}
+
+/// Parses `--align` argument, which must be a power of two.
+pub fn parse_align(arg: &str) -> Result<usize, String> {
+    match arg.parse::<usize>() {
+        Ok(align) if align.is_power_of_two() => Ok(align),
+        _ => Err(format!("{arg} is not a power of two")),
+    }
+}
+
+/// Builds the archive, with later lines overriding values of duplicate map keys. Keys are sorted
+/// before building, so that the same input always gives a byte-identical archive.
+pub fn run(args: BuildArgs) -> Result<(), Box<dyn Error>> {
+    let input =
+        fs::read_to_string(&args.input).map_err(|err| format!("failed to read {}: {err}", args.input.display()))?;
+    let lines = input.lines().filter(|line| !line.is_empty());
+
+    let bytes = match args.kind {
+        Kind::Map => {
+            let mut entries = BTreeMap::new();
+            for (line_idx, line) in lines.enumerate() {
+                let (key, value) = line
+                    .split_once('\t')
+                    .ok_or_else(|| format!("line {}: missing tab separated value", line_idx + 1))?;
+                entries.insert(key.to_string(), value.to_string());
+            }
+            let map = MapWithDict::<String, String>::from_iter_with_params(entries, args.gamma)
+                .map_err(|err| format!("failed to build map: {err:?}"))?;
+            to_bytes_with_align::<_, 4096>(&map, args.align)?
+        }
+        Kind::Set => {
+            let keys: BTreeSet<String> = lines.map(str::to_string).collect();
+            let set = Set::<String>::from_iter_with_params(keys, args.gamma)
+                .map_err(|err| format!("failed to build set: {err:?}"))?;
+            to_bytes_with_align::<_, 4096>(&set, args.align)?
+        }
+    };
+
+    fs::write(&args.output, &bytes).map_err(|err| format!("failed to write {}: {err}", args.output.display()))?;
+    Ok(())
+}
@@ -0,0 +1,155 @@
+//! `diff` subcommand comparing two memory-mapped archives.
+//!
+//! Prints one line per difference: `+ <key>` for added, `- <key>` for removed and `~ <key>` for
+//! changed keys, followed by tab separated values for maps (both values for changed keys), and a
+//! summary of the numbers of differences to stderr.
+
+use std::error::Error;
+use std::fmt::Display;
+use std::io::{self, BufWriter, Write};
+use std::path::PathBuf;
+
+use clap::Args;
+use entropy_map::{diff, Diff, DiffEntry, DiffSummary, Diffable};
+use rkyv::string::ArchivedString;
+
+use crate::archive::{self, Archived, Kind};
+
+/// Arguments of the `diff` subcommand.
+#[derive(Args)]
+pub struct DiffArgs {
+    /// Old archive file
+    old: PathBuf,
+    /// New archive file
+    new: PathBuf,
+    /// Kind of structure stored in both archives
+    #[arg(long, value_enum)]
+    kind: Kind,
+    /// Only print the summary of the numbers of differences
+    #[arg(long)]
+    summary: bool,
+}
+
+/// Compares the archives, printing the differences to stdout.
+pub fn run(args: DiffArgs) -> Result<(), Box<dyn Error>> {
+    let old_mmap = archive::mmap(&args.old)?;
+    let new_mmap = archive::mmap(&args.new)?;
+    let old = Archived::check(&old_mmap, args.kind)?;
+    let new = Archived::check(&new_mmap, args.kind)?;
+
+    let mut out = BufWriter::new(io::stdout().lock());
+    let summary = match (old, new) {
+        (Archived::Map(old), Archived::Map(new)) => write_diff(&mut out, diff(old, new), args.summary)?,
+        (Archived::Set(old), Archived::Set(new)) => write_diff(&mut out, diff(old, new), args.summary)?,
+        _ => unreachable!("both archives are checked as the same kind"),
+    };
+    out.flush()?;
+
+    eprintln!(
+        "{} added, {} removed, {} changed",
+        summary.added, summary.removed, summary.changed
+    );
+    Ok(())
+}
+
+/// Value printed after the key of a difference line, nothing for sets.
+trait DiffValue {
+    /// Writes the value preceded by a tab.
+    fn write_to(&self, out: &mut impl Write) -> io::Result<()>;
+}
+
+impl DiffValue for ArchivedString {
+    fn write_to(&self, out: &mut impl Write) -> io::Result<()> {
+        write!(out, "\t{self}")
+    }
+}
+
+impl DiffValue for () {
+    fn write_to(&self, _out: &mut impl Write) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/// Writes every difference unless `summary_only` is set, returning the numbers of differences.
+fn write_diff<T, U>(out: &mut impl Write, diff: Diff<T, U>, summary_only: bool) -> io::Result<DiffSummary>
+where
+    T: Diffable + ?Sized,
+    T::Key: Display,
+    T::Value: DiffValue,
+    U: Diffable<Key = T::Key, Value = T::Value> + ?Sized,
+{
+    if summary_only {
+        return Ok(diff.summary());
+    }
+
+    let mut summary = DiffSummary::default();
+    for entry in diff {
+        match entry {
+            DiffEntry::Added(key, value) => {
+                summary.added += 1;
+                write!(out, "+ {key}")?;
+                value.write_to(out)?;
+            }
+            DiffEntry::Removed(key, value) => {
+                summary.removed += 1;
+                write!(out, "- {key}")?;
+                value.write_to(out)?;
+            }
+            DiffEntry::Changed(key, old_value, new_value) => {
+                summary.changed += 1;
+                write!(out, "~ {key}")?;
+                old_value.write_to(out)?;
+                new_value.write_to(out)?;
+            }
+        }
+        writeln!(out)?;
+    }
+    Ok(summary)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use entropy_map::{MapWithDict, Set};
+    use std::collections::{HashMap, HashSet};
+
+    /// Returns sorted lines written for differences of `old` and `new` archives of `kind`.
+    fn diff_lines(old: &[u8], new: &[u8], kind: Kind) -> (Vec<String>, DiffSummary) {
+        let mut out = vec![];
+        let summary = match (Archived::check(old, kind).unwrap(), Archived::check(new, kind).unwrap()) {
+            (Archived::Map(old), Archived::Map(new)) => write_diff(&mut out, diff(old, new), false),
+            (Archived::Set(old), Archived::Set(new)) => write_diff(&mut out, diff(old, new), false),
+            _ => unreachable!(),
+        }
+        .unwrap();
+        let mut lines: Vec<String> = String::from_utf8(out).unwrap().lines().map(str::to_string).collect();
+        lines.sort();
+        (lines, summary)
+    }
+
+    #[test]
+    fn test_write_diff() {
+        let map = |entries: &[(&str, &str)]| {
+            let entries: HashMap<String, String> =
+                entries.iter().map(|&(k, v)| (k.to_string(), v.to_string())).collect();
+            rkyv::to_bytes::<_, 1024>(&MapWithDict::try_from(entries).unwrap()).unwrap()
+        };
+        let old = map(&[("a", "1"), ("b", "2"), ("c", "3")]);
+        let new = map(&[("a", "1"), ("b", "4"), ("d", "5")]);
+        assert_eq!(
+            diff_lines(&old, &new, Kind::Map),
+            (
+                vec!["+ d\t5".to_string(), "- c\t3".to_string(), "~ b\t2\t4".to_string()],
+                DiffSummary { added: 1, removed: 1, changed: 1 }
+            )
+        );
+
+        let set = |keys: &[&str]| {
+            let keys: HashSet<String> = keys.iter().map(|k| k.to_string()).collect();
+            rkyv::to_bytes::<_, 1024>(&Set::try_from(keys).unwrap()).unwrap()
+        };
+        let (lines, summary) = diff_lines(&set(&["a", "b"]), &set(&["b", "c"]), Kind::Set);
+        assert_eq!(lines, ["+ c", "- a"]);
+        assert_eq!(summary, DiffSummary { added: 1, removed: 1, changed: 0 });
+    }
+}
@@ -0,0 +1,54 @@
+//! `export-c` subcommand writing archives as C headers for embedded use.
+
+use std::error::Error;
+use std::fs;
+use std::io::{self, Write};
+use std::path::PathBuf;
+
+use clap::Args;
+use entropy_map::{is_c_identifier, to_c_header, CHeaderKind};
+
+use crate::archive::{self, Archived, Kind};
+
+/// Arguments of the `export-c` subcommand.
+#[derive(Args)]
+pub struct ExportCArgs {
+    /// Archive file
+    archive: PathBuf,
+    /// Kind of structure stored in the archive
+    #[arg(long, value_enum)]
+    kind: Kind,
+    /// Prefix of the names of the array and the accessor, a valid C identifier
+    #[arg(long, value_parser = parse_name)]
+    name: String,
+    /// Output header file, stdout by default
+    #[arg(long, short)]
+    output: Option<PathBuf>,
+}
+
+/// Parses `--name` argument, which must be a valid C identifier.
+fn parse_name(arg: &str) -> Result<String, String> {
+    if is_c_identifier(arg) {
+        Ok(arg.to_string())
+    } else {
+        Err(format!("{arg} is not a valid C identifier"))
+    }
+}
+
+/// Validates the archive, as C accessors don't, and writes the header.
+pub fn run(args: ExportCArgs) -> Result<(), Box<dyn Error>> {
+    let mmap = archive::mmap(&args.archive)?;
+    let kind = match Archived::check(&mmap, args.kind)? {
+        Archived::Map(_) => CHeaderKind::Map,
+        Archived::Set(_) => CHeaderKind::Set,
+    };
+    let header = to_c_header(&mmap, &args.name, kind);
+
+    match &args.output {
+        Some(output) => {
+            fs::write(output, header).map_err(|err| format!("failed to write {}: {err}", output.display()))?
+        }
+        None => io::stdout().lock().write_all(header.as_bytes())?,
+    }
+    Ok(())
+}
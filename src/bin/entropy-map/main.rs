@@ -0,0 +1,65 @@
+//! `entropy-map` command line tool for building and querying archived maps and sets.
+//!
+//! Archives hold `MapWithDict<String, String>` or `Set<String>` serialized with `rkyv`, so they can
+//! be used directly by services loading them with `rkyv::check_archived_root`.
+
+mod archive;
+mod bench;
+mod build;
+mod diff;
+mod export_c;
+mod merge;
+mod serve;
+
+use std::process::ExitCode;
+
+use clap::{Parser, Subcommand};
+
+/// Command line arguments.
+#[derive(Parser)]
+#[command(name = "entropy-map", version, about)]
+struct Cli {
+    #[command(subcommand)]
+    command: Command,
+}
+
+/// Supported subcommands.
+#[derive(Subcommand)]
+enum Command {
+    /// Builds an archive from a text file with one key (set) or one tab separated key-value pair
+    /// (map) per line.
+    Build(build::BuildArgs),
+    /// Memory-maps an archive and answers `get`/`contains` queries over a unix socket or HTTP.
+    Serve(serve::ServeArgs),
+    /// Builds MPHFs over keys from a text file with several parameter combinations and reports
+    /// build time, bits per key and query latency.
+    Bench(bench::BenchArgs),
+    /// Compares two archives of the same kind and prints added, removed and changed keys.
+    Diff(diff::DiffArgs),
+    /// Writes a C header embedding an archive as a static byte array along with an accessor calling
+    /// the C functions of the `ffi` feature.
+    ExportC(export_c::ExportCArgs),
+    /// Merges archives of the same kind into one, resolving keys with different values in several
+    /// maps according to `--on-conflict`.
+    Merge(merge::MergeArgs),
+}
+
+fn main() -> ExitCode {
+    let cli = Cli::parse();
+    let res = match cli.command {
+        Command::Build(args) => build::run(args),
+        Command::Serve(args) => serve::run(args),
+        Command::Bench(args) => bench::run(args),
+        Command::Diff(args) => diff::run(args),
+        Command::ExportC(args) => export_c::run(args),
+        Command::Merge(args) => merge::run(args),
+    };
+
+    match res {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(err) => {
+            eprintln!("error: {err}");
+            ExitCode::FAILURE
+        }
+    }
+}
@@ -0,0 +1,161 @@
+//! `merge` subcommand combining several archives of the same kind into one.
+
+use std::collections::btree_map::Entry;
+use std::collections::{BTreeMap, BTreeSet};
+use std::error::Error;
+use std::fs;
+use std::path::PathBuf;
+
+use clap::{Args, ValueEnum};
+use entropy_map::{to_bytes_with_align, Diffable, MapWithDict, Set, DEFAULT_GAMMA};
+
+use crate::archive::{self, Archived, Kind};
+use crate::build::parse_align;
+
+/// Policy resolving keys present with different values in several map archives.
+#[derive(Clone, Copy, Debug, PartialEq, ValueEnum)]
+pub enum OnConflict {
+    /// Keep the value of the last archive containing the key
+    LastWins,
+    /// Keep the value of the first archive containing the key
+    FirstWins,
+    /// Fail the merge
+    Error,
+}
+
+/// Arguments of the `merge` subcommand.
+#[derive(Args)]
+pub struct MergeArgs {
+    /// Archive files to merge, in order of precedence for `--on-conflict`
+    #[arg(required = true, num_args = 2..)]
+    inputs: Vec<PathBuf>,
+    /// Output archive file
+    #[arg(long, short)]
+    output: PathBuf,
+    /// Kind of structure stored in all archives
+    #[arg(long, value_enum)]
+    kind: Kind,
+    /// Resolution of keys with different values in several map archives
+    #[arg(long, value_enum, default_value_t = OnConflict::LastWins)]
+    on_conflict: OnConflict,
+    /// MPHF gamma parameter of the merged structure
+    #[arg(long, default_value_t = DEFAULT_GAMMA)]
+    gamma: f32,
+    /// Minimum alignment of archived arrays in bytes, see `build --align`
+    #[arg(long, default_value_t = 1, value_parser = parse_align)]
+    align: usize,
+}
+
+/// Merges the archives, rebuilding a single structure over the union of their keys, sorted so that
+/// the same inputs always give a byte-identical archive.
+pub fn run(args: MergeArgs) -> Result<(), Box<dyn Error>> {
+    let mmaps = args
+        .inputs
+        .iter()
+        .map(|path| archive::mmap(path))
+        .collect::<Result<Vec<_>, _>>()?;
+    let archives = mmaps
+        .iter()
+        .zip(&args.inputs)
+        .map(|(mmap, path)| Archived::check(mmap, args.kind).map_err(|err| format!("{}: {err}", path.display())))
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let bytes = match args.kind {
+        Kind::Map => {
+            let entries = merge_maps(&archives, args.on_conflict)?;
+            let map = MapWithDict::<String, String>::from_iter_with_params(entries, args.gamma)
+                .map_err(|err| format!("failed to build map: {err:?}"))?;
+            to_bytes_with_align::<_, 4096>(&map, args.align)?
+        }
+        Kind::Set => {
+            let set = Set::<String>::from_iter_with_params(merge_sets(&archives), args.gamma)
+                .map_err(|err| format!("failed to build set: {err:?}"))?;
+            to_bytes_with_align::<_, 4096>(&set, args.align)?
+        }
+    };
+
+    fs::write(&args.output, &bytes).map_err(|err| format!("failed to write {}: {err}", args.output.display()))?;
+    Ok(())
+}
+
+/// Returns the entries of all map `archives`, resolving conflicting values with `on_conflict`.
+fn merge_maps(archives: &[Archived], on_conflict: OnConflict) -> Result<BTreeMap<String, String>, String> {
+    let mut entries = BTreeMap::new();
+    for (archive_idx, archived) in archives.iter().enumerate() {
+        let Archived::Map(map) = archived else {
+            unreachable!("all archives are checked as maps")
+        };
+        for (key, value) in map.iter() {
+            match entries.entry(key.to_string()) {
+                Entry::Vacant(entry) => {
+                    entry.insert(value.to_string());
+                }
+                Entry::Occupied(mut entry) if entry.get() != value.as_str() => match on_conflict {
+                    OnConflict::LastWins => {
+                        entry.insert(value.to_string());
+                    }
+                    OnConflict::FirstWins => {}
+                    OnConflict::Error => {
+                        return Err(format!(
+                            "key {key} has value {} in a previous archive and {value} in archive {}",
+                            entry.get(),
+                            archive_idx + 1
+                        ))
+                    }
+                },
+                Entry::Occupied(_) => {}
+            }
+        }
+    }
+    Ok(entries)
+}
+
+/// Returns the union of keys of all set `archives`.
+fn merge_sets(archives: &[Archived]) -> BTreeSet<String> {
+    let mut keys = BTreeSet::new();
+    for archived in archives {
+        let Archived::Set(set) = archived else {
+            unreachable!("all archives are checked as sets")
+        };
+        keys.extend((0..).map_while(|idx| set.entry(idx)).map(|(key, _)| key.to_string()));
+    }
+    keys
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::{HashMap, HashSet};
+
+    #[test]
+    fn test_merge() {
+        let map = |entries: &[(&str, &str)]| {
+            let entries: HashMap<String, String> =
+                entries.iter().map(|&(k, v)| (k.to_string(), v.to_string())).collect();
+            rkyv::to_bytes::<_, 1024>(&MapWithDict::try_from(entries).unwrap()).unwrap()
+        };
+        let (a, b) = (map(&[("a", "1"), ("b", "2")]), map(&[("b", "3"), ("c", "4")]));
+        let maps = [&a, &b].map(|bytes| Archived::check(bytes, Kind::Map).unwrap());
+
+        let merged = merge_maps(&maps, OnConflict::LastWins).unwrap();
+        assert_eq!(
+            merged,
+            BTreeMap::from([("a", "1"), ("b", "3"), ("c", "4")].map(|(k, v)| (k.into(), v.into())))
+        );
+        let merged = merge_maps(&maps, OnConflict::FirstWins).unwrap();
+        assert_eq!(merged["b"], "2");
+        assert_eq!(
+            merge_maps(&maps, OnConflict::Error),
+            Err("key b has value 2 in a previous archive and 3 in archive 2".to_string())
+        );
+        assert!(merge_maps(&maps[..1], OnConflict::Error).is_ok());
+
+        let set = |keys: &[&str]| {
+            let keys: HashSet<String> = keys.iter().map(|k| k.to_string()).collect();
+            rkyv::to_bytes::<_, 1024>(&Set::try_from(keys).unwrap()).unwrap()
+        };
+        let (a, b) = (set(&["a", "b"]), set(&["b", "c"]));
+        let sets = [&a, &b].map(|bytes| Archived::check(bytes, Kind::Set).unwrap());
+        assert_eq!(merge_sets(&sets), BTreeSet::from(["a", "b", "c"].map(String::from)));
+    }
+}
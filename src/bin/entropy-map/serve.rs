@@ -0,0 +1,392 @@
+//! `serve` subcommand answering queries against a memory-mapped archive.
+//!
+//! Two transports are supported, both handling each connection on its own thread, with at most
+//! [`MAX_CONNECTIONS`] connections at once, requests capped in size and idle connections closed
+//! after [`READ_TIMEOUT`]:
+//! - unix socket with a line protocol: requests `get <key>` or `contains <key>`, replies
+//!   `OK <value>`, `OK true`, `OK false`, `NOT_FOUND` or `ERR <message>`.
+//! - HTTP with `GET /get?key=<key>` and `GET /contains?key=<key>` endpoints, replying with the
+//!   value, `true` or `false` in the body, `404` for missing keys and `400` for invalid requests.
+
+use std::error::Error;
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::net::{TcpListener, TcpStream};
+use std::path::PathBuf;
+use std::sync::{Condvar, Mutex};
+use std::thread;
+use std::time::Duration;
+
+use clap::{ArgGroup, Args};
+
+use crate::archive::{self, Archived, Kind};
+
+/// Maximum number of connections handled at once, further ones wait in the listen backlog.
+const MAX_CONNECTIONS: usize = 64;
+/// Maximum size of an HTTP request line and headers.
+const MAX_HEADER_BYTES: u64 = 8 * 1024;
+/// Maximum size of a line protocol request, including the line feed.
+const MAX_LINE_BYTES: u64 = 8 * 1024;
+/// Time after which a connection without incoming data is closed.
+const READ_TIMEOUT: Duration = Duration::from_secs(30);
+/// Pause after a failed accept, so that e.g. running out of file descriptors doesn't spin the loop.
+const ACCEPT_ERROR_PAUSE: Duration = Duration::from_millis(100);
+
+/// Arguments of the `serve` subcommand.
+#[derive(Args)]
+#[command(group(ArgGroup::new("listen").required(true).args(["socket", "http"])))]
+pub struct ServeArgs {
+    /// Archive file to serve
+    archive: PathBuf,
+    /// Kind of structure stored in the archive
+    #[arg(long, value_enum)]
+    kind: Kind,
+    /// Path of the unix socket to listen on
+    #[arg(long)]
+    socket: Option<PathBuf>,
+    /// Address to listen on for HTTP requests, e.g. `127.0.0.1:8080`
+    #[arg(long)]
+    http: Option<String>,
+}
+
+/// Reply to a single query.
+#[derive(Debug, PartialEq)]
+enum Reply<'a> {
+    Value(&'a str),
+    Found(bool),
+    NotFound,
+    BadRequest(&'static str),
+}
+
+/// Serves the archive until the process is terminated.
+pub fn run(args: ServeArgs) -> Result<(), Box<dyn Error>> {
+    let mmap = archive::mmap(&args.archive)?;
+    let archived = Archived::check(&mmap, args.kind)?;
+
+    if let Some(addr) = args.http {
+        let listener = TcpListener::bind(&addr).map_err(|err| format!("failed to listen on {addr}: {err}"))?;
+        eprintln!(
+            "serving {} over http://{}",
+            args.archive.display(),
+            listener.local_addr()?
+        );
+        return serve_connections(listener.incoming(), |stream| handle_http(&archived, stream));
+    }
+
+    match args.socket {
+        Some(path) => serve_unix(&archived, path),
+        None => unreachable!("listen group is required"),
+    }
+}
+
+#[cfg(unix)]
+fn serve_unix(archived: &Archived, path: PathBuf) -> Result<(), Box<dyn Error>> {
+    use std::os::unix::net::UnixListener;
+
+    let listener = UnixListener::bind(&path).map_err(|err| format!("failed to listen on {}: {err}", path.display()))?;
+    eprintln!("serving over unix socket {}", path.display());
+    serve_connections(listener.incoming(), |stream| {
+        stream.set_read_timeout(Some(READ_TIMEOUT))?;
+        handle_lines(archived, &stream)
+    })
+}
+
+#[cfg(not(unix))]
+fn serve_unix(_archived: &Archived, _path: PathBuf) -> Result<(), Box<dyn Error>> {
+    Err("unix sockets are not supported on this platform".into())
+}
+
+/// Accepts connections handling each one on a scoped thread, waiting for one of
+/// [`MAX_CONNECTIONS`] threads to finish before accepting more. Failed accepts are logged and
+/// skipped, unless the listener itself is unusable.
+fn serve_connections<S, F>(mut incoming: impl Iterator<Item = io::Result<S>>, handle: F) -> Result<(), Box<dyn Error>>
+where
+    S: Send,
+    F: Fn(S) -> io::Result<()> + Sync,
+{
+    let (active, finished) = (Mutex::new(0), Condvar::new());
+    thread::scope(|scope| loop {
+        let mut connections = active.lock().unwrap();
+        while *connections >= MAX_CONNECTIONS {
+            connections = finished.wait(connections).unwrap();
+        }
+        drop(connections);
+
+        let Some(stream) = incoming.next() else {
+            return Ok(());
+        };
+        let stream = match stream {
+            Ok(stream) => stream,
+            Err(err) if is_listener_error(&err) => return Err(err.into()),
+            Err(err) => {
+                eprintln!("accept error: {err}");
+                thread::sleep(ACCEPT_ERROR_PAUSE);
+                continue;
+            }
+        };
+        *active.lock().unwrap() += 1;
+        let (handle, active, finished) = (&handle, &active, &finished);
+        scope.spawn(move || {
+            if let Err(err) = handle(stream) {
+                eprintln!("connection error: {err}");
+            }
+            *active.lock().unwrap() -= 1;
+            finished.notify_one();
+        });
+    })
+}
+
+/// Returns whether accepting failed because of the listener rather than of a single connection or
+/// of temporarily exhausted resources, so that retrying is pointless.
+fn is_listener_error(err: &io::Error) -> bool {
+    matches!(err.kind(), io::ErrorKind::InvalidInput | io::ErrorKind::Unsupported)
+}
+
+/// Answers a single `get` or `contains` query.
+fn query<'a>(archived: &'a Archived, op: &str, key: &str) -> Reply<'a> {
+    match op {
+        "get" => match archived.get(key) {
+            Ok(Some(value)) => Reply::Value(value),
+            Ok(None) => Reply::NotFound,
+            Err(err) => Reply::BadRequest(err),
+        },
+        "contains" => Reply::Found(archived.contains(key)),
+        _ => Reply::BadRequest("unknown command, expected get or contains"),
+    }
+}
+
+/// Handles line protocol requests until the client closes the connection, closing it after an
+/// error reply if a request exceeds [`MAX_LINE_BYTES`].
+fn handle_lines<S>(archived: &Archived, stream: S) -> io::Result<()>
+where
+    S: Read + Write + Copy,
+{
+    let mut writer = stream;
+    let mut reader = BufReader::new(stream);
+    let mut line = String::new();
+    loop {
+        line.clear();
+        let len = (&mut reader).take(MAX_LINE_BYTES).read_line(&mut line)?;
+        if len == 0 {
+            return Ok(());
+        }
+        if len as u64 == MAX_LINE_BYTES && !line.ends_with('\n') {
+            return writer.write_all(b"ERR request too long\n");
+        }
+        let request = line.strip_suffix('\n').unwrap_or(&line);
+        let request = request.strip_suffix('\r').unwrap_or(request);
+        let (op, key) = request.split_once(' ').unwrap_or((request, ""));
+        let reply = match query(archived, op, key) {
+            Reply::Value(value) => format!("OK {value}\n"),
+            Reply::Found(found) => format!("OK {found}\n"),
+            Reply::NotFound => "NOT_FOUND\n".to_string(),
+            Reply::BadRequest(err) => format!("ERR {err}\n"),
+        };
+        writer.write_all(reply.as_bytes())?;
+    }
+}
+
+/// Handles a single HTTP request and closes the connection.
+fn handle_http(archived: &Archived, mut stream: TcpStream) -> io::Result<()> {
+    stream.set_read_timeout(Some(READ_TIMEOUT))?;
+    let mut reader = BufReader::new((&stream).take(MAX_HEADER_BYTES));
+    let mut request_line = String::new();
+    reader.read_line(&mut request_line)?;
+
+    // Drain request headers up to the size limit, the request body is never used
+    let mut header = String::new();
+    while reader.read_line(&mut header)? > 2 {
+        header.clear();
+    }
+
+    let (status, body) = match parse_http_request(&request_line) {
+        _ if !request_line.ends_with('\n') => ("400 Bad Request", "request line too long".to_string()),
+        Some((op, key)) => match query(archived, op, &key) {
+            Reply::Value(value) => ("200 OK", value.to_string()),
+            Reply::Found(found) => ("200 OK", found.to_string()),
+            Reply::NotFound => ("404 Not Found", String::new()),
+            Reply::BadRequest(err) => ("400 Bad Request", err.to_string()),
+        },
+        None => (
+            "400 Bad Request",
+            "expected GET /get?key=<key> or GET /contains?key=<key>".to_string(),
+        ),
+    };
+
+    write!(
+        stream,
+        "HTTP/1.1 {status}\r\nContent-Type: text/plain; charset=utf-8\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{body}",
+        body.len()
+    )
+}
+
+/// Parses `GET /<op>?key=<key> HTTP/1.1` request line into operation and decoded key.
+fn parse_http_request(request_line: &str) -> Option<(&str, String)> {
+    let mut parts = request_line.split_ascii_whitespace();
+    if parts.next()? != "GET" {
+        return None;
+    }
+    let (path, query) = parts.next()?.split_once('?')?;
+    let key = query.split('&').find_map(|param| param.strip_prefix("key="))?;
+    Some((path.strip_prefix('/')?, percent_decode(key)?))
+}
+
+/// Decodes `%XX` escapes and `+` as space, returning `None` for malformed input.
+fn percent_decode(s: &str) -> Option<String> {
+    let mut bytes = Vec::with_capacity(s.len());
+    let mut iter = s.bytes();
+    while let Some(b) = iter.next() {
+        match b {
+            b'%' => {
+                let hex = [iter.next()?, iter.next()?];
+                bytes.push(u8::from_str_radix(std::str::from_utf8(&hex).ok()?, 16).ok()?);
+            }
+            b'+' => bytes.push(b' '),
+            _ => bytes.push(b),
+        }
+    }
+    String::from_utf8(bytes).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use entropy_map::{MapWithDict, Set};
+    use std::collections::{HashMap, HashSet};
+
+    #[test]
+    fn test_parse_http_request() {
+        assert_eq!(
+            parse_http_request("GET /get?key=a%20b+c%2F HTTP/1.1\r\n"),
+            Some(("get", "a b c/".to_string()))
+        );
+        assert_eq!(
+            parse_http_request("GET /contains?x=1&key=abc HTTP/1.1\r\n"),
+            Some(("contains", "abc".to_string()))
+        );
+        assert_eq!(parse_http_request("POST /get?key=abc HTTP/1.1\r\n"), None);
+        assert_eq!(parse_http_request("GET /get HTTP/1.1\r\n"), None);
+        assert_eq!(parse_http_request("GET /get?key=%zz HTTP/1.1\r\n"), None);
+    }
+
+    #[test]
+    fn test_serve_connections() {
+        let handled = Mutex::new(vec![]);
+        let incoming = vec![
+            Err(io::Error::from(io::ErrorKind::ConnectionAborted)),
+            Ok(1),
+            Err(io::Error::other("too many open files")),
+            Ok(2),
+        ];
+        let result = serve_connections(incoming.into_iter(), |stream| {
+            handled.lock().unwrap().push(stream);
+            Ok(())
+        });
+        assert!(result.is_ok());
+        handled.lock().unwrap().sort_unstable();
+        assert_eq!(*handled.lock().unwrap(), [1, 2]);
+
+        let incoming = vec![Err(io::Error::from(io::ErrorKind::InvalidInput)), Ok(3)];
+        assert!(serve_connections(incoming.into_iter(), |_| Ok(())).is_err());
+    }
+
+    #[test]
+    fn test_query() {
+        let map = MapWithDict::try_from(HashMap::from([("a".to_string(), "1".to_string())])).unwrap();
+        let map_bytes = rkyv::to_bytes::<_, 1024>(&map).unwrap();
+        let archived_map = Archived::check(&map_bytes, Kind::Map).unwrap();
+
+        assert_eq!(query(&archived_map, "get", "a"), Reply::Value("1"));
+        assert_eq!(query(&archived_map, "get", "b"), Reply::NotFound);
+        assert_eq!(query(&archived_map, "contains", "a"), Reply::Found(true));
+        assert!(matches!(query(&archived_map, "put", "a"), Reply::BadRequest(_)));
+
+        let set = Set::try_from(HashSet::from(["a".to_string()])).unwrap();
+        let set_bytes = rkyv::to_bytes::<_, 1024>(&set).unwrap();
+        let archived_set = Archived::check(&set_bytes, Kind::Set).unwrap();
+
+        assert_eq!(query(&archived_set, "contains", "a"), Reply::Found(true));
+        assert_eq!(query(&archived_set, "contains", "b"), Reply::Found(false));
+        assert!(matches!(query(&archived_set, "get", "a"), Reply::BadRequest(_)));
+        assert!(Archived::check(&set_bytes, Kind::Map).is_err());
+    }
+
+    #[test]
+    fn test_handle_http() {
+        let map = MapWithDict::try_from(HashMap::from([("a b".to_string(), "1".to_string())])).unwrap();
+        let bytes = rkyv::to_bytes::<_, 1024>(&map).unwrap();
+        let archived = Archived::check(&bytes, Kind::Map).unwrap();
+
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        let client = thread::spawn(move || {
+            let mut stream = TcpStream::connect(addr).unwrap();
+            stream
+                .write_all(b"GET /get?key=a+b HTTP/1.1\r\nHost: localhost\r\n\r\n")
+                .unwrap();
+            let mut response = String::new();
+            stream.read_to_string(&mut response).unwrap();
+            response
+        });
+
+        let (stream, _) = listener.accept().unwrap();
+        handle_http(&archived, stream).unwrap();
+
+        let response = client.join().unwrap();
+        assert!(response.starts_with("HTTP/1.1 200 OK\r\n"));
+        assert!(response.ends_with("\r\n\r\n1"));
+    }
+
+    #[test]
+    fn test_handle_http_too_long() {
+        let map = MapWithDict::try_from(HashMap::from([("a".to_string(), "1".to_string())])).unwrap();
+        let bytes = rkyv::to_bytes::<_, 1024>(&map).unwrap();
+        let archived = Archived::check(&bytes, Kind::Map).unwrap();
+
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        let client = thread::spawn(move || {
+            let mut stream = TcpStream::connect(addr).unwrap();
+            // the request line fills the limit without a line feed, so that the server reads it all
+            let request_line = format!("GET /get?key={}", "a".repeat(MAX_HEADER_BYTES as usize - 13));
+            stream.write_all(request_line.as_bytes()).unwrap();
+            let mut response = String::new();
+            stream.read_to_string(&mut response).unwrap();
+            response
+        });
+
+        let (stream, _) = listener.accept().unwrap();
+        handle_http(&archived, stream).unwrap();
+
+        let response = client.join().unwrap();
+        assert!(response.starts_with("HTTP/1.1 400 Bad Request\r\n"));
+        assert!(response.ends_with("\r\n\r\nrequest line too long"));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_handle_lines() {
+        use std::os::unix::net::UnixStream;
+
+        let map = MapWithDict::try_from(HashMap::from([("a".to_string(), "1".to_string())])).unwrap();
+        let bytes = rkyv::to_bytes::<_, 1024>(&map).unwrap();
+        let archived = Archived::check(&bytes, Kind::Map).unwrap();
+
+        let (server, mut client) = UnixStream::pair().unwrap();
+        let requests = format!(
+            "get a\r\ncontains b\nget {}\nget a\n",
+            "a".repeat(MAX_LINE_BYTES as usize)
+        );
+        let writer = thread::spawn(move || {
+            // the server closes the connection after the request too long
+            let _ = client.write_all(requests.as_bytes());
+            client.shutdown(std::net::Shutdown::Write).unwrap();
+            let mut replies = String::new();
+            client.read_to_string(&mut replies).unwrap();
+            replies
+        });
+        handle_lines(&archived, &server).unwrap();
+        drop(server);
+
+        assert_eq!(writer.join().unwrap(), "OK 1\nOK false\nERR request too long\n");
+    }
+}
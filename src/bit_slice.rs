@@ -0,0 +1,190 @@
+//! Internal bit vector abstractions centralizing unchecked word accesses.
+//!
+//! Both MPHF construction and rank queries perform a lot of word-level bit manipulation with
+//! indices derived from hashes. Instead of spreading raw `get_unchecked` calls and word math across
+//! modules, all such accesses go through `BitSlice` and `InterleavedBits`, which validate their
+//! preconditions with `debug_assert!` (so that tests and Miri catch violations) and only perform
+//! unchecked accesses in release builds.
+//!
+//! Bit indices are always `u64`, so that bit vectors larger than `usize::MAX` bits can still be
+//! addressed on 32-bit targets, only word indices are `usize`.
+
+use crate::access;
+
+/// Read-only view over bits stored in `u64` words, least significant bit first.
+#[derive(Clone, Copy)]
+pub(crate) struct BitSlice<'a> {
+    /// Underlying words
+    words: &'a [u64],
+}
+
+impl<'a> BitSlice<'a> {
+    /// Creates a view over `words`.
+    #[inline]
+    pub(crate) fn new(words: &'a [u64]) -> Self {
+        BitSlice { words }
+    }
+
+    /// Returns `true` if the bit at `idx` is set.
+    ///
+    /// # Safety
+    /// `idx` must be less than `64 * words.len()`.
+    #[inline]
+    pub(crate) unsafe fn get_unchecked(&self, idx: u64) -> bool {
+        let word_idx = (idx / 64) as usize;
+        debug_assert!(word_idx < self.words.len(), "bit {idx} out of bounds");
+        (*access::get(self.words, word_idx) >> (idx % 64)) & 1 == 1
+    }
+
+    /// Returns the number of set bits in `[64 * from_word, to_bit)` range.
+    ///
+    /// # Safety
+    /// `64 * from_word <= to_bit` and `to_bit` must be less than `64 * words.len()`.
+    #[inline]
+    pub(crate) unsafe fn count_ones_unchecked(&self, from_word: usize, to_bit: u64) -> usize {
+        let to_word = (to_bit / 64) as usize;
+        debug_assert!(from_word <= to_word, "invalid range [{}, {to_bit})", 64 * from_word);
+        debug_assert!(to_word < self.words.len(), "bit {to_bit} out of bounds");
+
+        let full_words_ones = access::get(self.words, from_word..to_word)
+            .iter()
+            .map(|&word| word.count_ones() as usize)
+            .sum::<usize>();
+
+        let word_mask = (1u64 << (to_bit % 64)) - 1;
+        let word_ones = (*access::get(self.words, to_word) & word_mask).count_ones() as usize;
+
+        full_words_ones + word_ones
+    }
+}
+
+/// Mutable bit vector made of `L` interleaved lanes, i.e. every `L` consecutive words hold the
+/// same 64-bit segment of each lane, so that accessing all lanes of a segment touches a single
+/// cache line. One extra zeroed segment is reserved at the end, which allows reading and writing
+/// bit ranges crossing a segment boundary without additional branching.
+pub(crate) struct InterleavedBits<const L: usize> {
+    /// Interleaved lanes words, including padding segment
+    words: Vec<u64>,
+}
+
+impl<const L: usize> InterleavedBits<L> {
+    /// Creates zeroed lanes of `segments` 64-bit words each.
+    #[inline]
+    pub(crate) fn new(segments: usize) -> Self {
+        InterleavedBits { words: vec![0u64; L * (segments + 1)] }
+    }
+
+    /// Returns `len` bits of `lane` starting at bit `idx`.
+    ///
+    /// # Safety
+    /// `len` must be in [1..64] range, `idx` must be less than `64 * segments` and `lane < L`.
+    #[cfg(test)]
+    #[inline]
+    pub(crate) unsafe fn read_unchecked(&self, lane: usize, idx: u64, len: usize) -> u64 {
+        let (word_idx, pos, len_1, len_2) = self.split_unchecked(lane, idx, len);
+        let bits = self.words.get_unchecked(word_idx..=word_idx + L);
+
+        let bits_1 = (bits[0] >> pos) & (u64::MAX >> (64 - len_1));
+        let bits_2 = bits[L] & ((1 << len_2) - 1);
+
+        bits_1 | bits_2.checked_shl(len_1 as u32).unwrap_or(0)
+    }
+
+    /// Overwrites `len` bits of `lane` starting at bit `idx` with the lower `len` bits of `value`.
+    ///
+    /// # Safety
+    /// Same as for [`InterleavedBits::read_unchecked`].
+    #[inline]
+    pub(crate) unsafe fn write_unchecked(&mut self, lane: usize, idx: u64, len: usize, value: u64) {
+        let (word_idx, pos, len_1, len_2) = self.split_unchecked(lane, idx, len);
+        let bits = self.words.get_unchecked_mut(word_idx..=word_idx + L);
+
+        let mask_1 = u64::MAX >> (64 - len_1);
+        bits[0] &= !(mask_1 << pos);
+        bits[0] |= (value & mask_1) << pos;
+
+        let mask_2 = (1 << len_2) - 1;
+        bits[L] &= !mask_2;
+        bits[L] |= value.checked_shr(len_1 as u32).unwrap_or(0) & mask_2;
+    }
+
+    /// Returns word index, bit position within the word and lengths of the two parts of the bits
+    /// range `[idx, idx + len)` of `lane` split by the segment boundary.
+    #[inline]
+    unsafe fn split_unchecked(&self, lane: usize, idx: u64, len: usize) -> (usize, usize, usize, usize) {
+        debug_assert!((1..=64).contains(&len), "invalid bits length {len}");
+        debug_assert!(lane < L, "invalid lane {lane}");
+
+        let word_idx = (idx / 64) as usize * L + lane;
+        debug_assert!(word_idx + L < self.words.len(), "bit {idx} out of bounds");
+
+        let pos = (idx % 64) as usize;
+        let len_1 = len.min(64 - pos);
+        (word_idx, pos, len_1, len - len_1)
+    }
+
+    /// Consumes the interleaved bits returning words of `lane`, excluding padding segment.
+    #[inline]
+    pub(crate) fn into_lane(self, lane: usize) -> Vec<u64> {
+        let words_len = self.words.len() - L;
+        self.words[..words_len]
+            .chunks_exact(L)
+            .map(|words| words[lane])
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::{Rng, SeedableRng};
+    use rand_chacha::ChaCha8Rng;
+
+    #[test]
+    fn test_bit_slice() {
+        let words = [0b1011, u64::MAX, 1 << 63];
+        let bits = BitSlice::new(&words);
+
+        unsafe {
+            assert!(bits.get_unchecked(0));
+            assert!(!bits.get_unchecked(2));
+            assert!(bits.get_unchecked(191));
+            assert!(!bits.get_unchecked(190));
+
+            assert_eq!(bits.count_ones_unchecked(0, 0), 0);
+            assert_eq!(bits.count_ones_unchecked(0, 2), 2);
+            assert_eq!(bits.count_ones_unchecked(0, 64), 3);
+            assert_eq!(bits.count_ones_unchecked(0, 191), 67);
+            assert_eq!(bits.count_ones_unchecked(1, 191), 64);
+        }
+    }
+
+    #[test]
+    fn test_interleaved_bits_read_write() {
+        let mut rng = ChaCha8Rng::seed_from_u64(123);
+        let segments = 4;
+        let mut bits = InterleavedBits::<3>::new(segments);
+        let mut model = vec![false; 64 * segments];
+
+        for _ in 0..10000 {
+            let len = rng.gen_range(1..=64);
+            let idx = rng.gen_range(0..64 * segments - len + 1);
+            let bit_idx = idx as u64;
+            let value = rng.gen::<u64>() & (u64::MAX >> (64 - len));
+
+            unsafe { bits.write_unchecked(2, bit_idx, len, value) };
+            for i in 0..len {
+                model[idx + i] = (value >> i) & 1 == 1;
+            }
+
+            let expected = (0..len).map(|i| (model[idx + i] as u64) << i).sum::<u64>();
+            assert_eq!(unsafe { bits.read_unchecked(2, bit_idx, len) }, expected);
+            assert_eq!(unsafe { bits.read_unchecked(0, bit_idx, len) }, 0);
+        }
+
+        let lane = bits.into_lane(2);
+        for (i, &bit) in model.iter().enumerate() {
+            assert_eq!((lane[i / 64] >> (i % 64)) & 1 == 1, bit);
+        }
+    }
+}
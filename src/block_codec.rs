@@ -0,0 +1,287 @@
+//! A module providing `BlockCodec`, a trait encoding `Vec<u32>` values of `MapWithDictBitpacked`
+//! into its byte dictionary, along with the available codecs.
+//!
+//! Values are encoded in blocks of up to 32 integers and different value distributions favour
+//! different block encodings:
+//! - `Bitpacked`: every block is bit-packed to the bits width of its largest value, the default,
+//! - `FrameOfReference`: every block stores its minimum followed by the bit-packed differences from
+//!   it, for large values within a narrow range (e.g. timestamps or ids),
+//! - `Delta`: every block stores its first value followed by the bit-packed zigzag-encoded
+//!   differences from the previous values, for sorted or slowly changing sequences,
+//! - `GroupVarint`: every 4 values are stored as a byte of their lengths followed by their 1 to 4
+//!   significant bytes, for skewed distributions with rare large values.
+//!
+//! The codec is chosen at construction via `BlockCodecKind`, which is recorded in the map, so that
+//! a single map type covers all encodings.
+
+use bitpacking::{BitPacker, BitPacker1x};
+
+use crate::map_with_dict_bitpacked::{pack_block, pack_values, unpack_block, unpack_values, VALUES_BLOCK_LEN};
+
+/// Encoding of `u32` values into dictionary bytes, see the module documentation.
+pub trait BlockCodec {
+    /// Appends the encoding of `values` to `dict`.
+    fn encode(values: &[u32], dict: &mut Vec<u8>);
+
+    /// Fills `res` with values encoded at the start of `dict`, the number of encoded values being
+    /// `res.len()`. The dictionary must be followed by `DICT_PADDING` bytes.
+    fn decode(dict: &[u8], res: &mut [u32]);
+}
+
+/// Number of zero bytes appended to the dictionary, so that SIMD decoding can read whole blocks
+/// past the last encoded value.
+pub const DICT_PADDING: usize = 4 * VALUES_BLOCK_LEN;
+
+/// Codec bit-packing every block to the bits width of its largest value.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct Bitpacked;
+
+impl BlockCodec for Bitpacked {
+    #[inline]
+    fn encode(values: &[u32], dict: &mut Vec<u8>) {
+        pack_values(values, dict);
+    }
+
+    #[inline]
+    fn decode(dict: &[u8], res: &mut [u32]) {
+        unpack_values(dict, res);
+    }
+}
+
+/// Codec storing the minimum of every block as 4 little endian bytes, followed by the bit-packed
+/// differences of block values from it.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct FrameOfReference;
+
+impl BlockCodec for FrameOfReference {
+    fn encode(values: &[u32], dict: &mut Vec<u8>) {
+        let bitpacker = BitPacker1x::new();
+        for block in values.chunks(VALUES_BLOCK_LEN) {
+            let min = block.iter().copied().min().unwrap_or(0);
+            let mut values_block = [0u32; VALUES_BLOCK_LEN];
+            for (v, &value) in values_block.iter_mut().zip(block) {
+                *v = value - min;
+            }
+            dict.extend_from_slice(&min.to_le_bytes());
+            pack_block(&bitpacker, &values_block, block.len(), dict);
+        }
+    }
+
+    #[inline]
+    fn decode(dict: &[u8], res: &mut [u32]) {
+        let bitpacker = BitPacker1x::new();
+        let mut dict = dict;
+        for block in res.chunks_mut(VALUES_BLOCK_LEN) {
+            let min = u32::from_le_bytes(dict[..4].try_into().unwrap());
+            let mut values_block = [0u32; VALUES_BLOCK_LEN];
+            dict = unpack_block(&bitpacker, &dict[4..], &mut values_block, block.len());
+            for (v, &delta) in block.iter_mut().zip(&values_block) {
+                *v = min.wrapping_add(delta);
+            }
+        }
+    }
+}
+
+/// Codec storing the first value of every block as 4 little endian bytes, followed by the
+/// bit-packed zigzag-encoded differences of block values from the previous ones.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct Delta;
+
+impl BlockCodec for Delta {
+    fn encode(values: &[u32], dict: &mut Vec<u8>) {
+        let bitpacker = BitPacker1x::new();
+        for block in values.chunks(VALUES_BLOCK_LEN) {
+            let mut prev = block[0];
+            let mut values_block = [0u32; VALUES_BLOCK_LEN];
+            for (v, &value) in values_block.iter_mut().zip(block) {
+                let delta = value.wrapping_sub(prev) as i32;
+                *v = ((delta << 1) ^ (delta >> 31)) as u32;
+                prev = value;
+            }
+            dict.extend_from_slice(&block[0].to_le_bytes());
+            pack_block(&bitpacker, &values_block, block.len(), dict);
+        }
+    }
+
+    #[inline]
+    fn decode(dict: &[u8], res: &mut [u32]) {
+        let bitpacker = BitPacker1x::new();
+        let mut dict = dict;
+        for block in res.chunks_mut(VALUES_BLOCK_LEN) {
+            let mut prev = u32::from_le_bytes(dict[..4].try_into().unwrap());
+            let mut values_block = [0u32; VALUES_BLOCK_LEN];
+            dict = unpack_block(&bitpacker, &dict[4..], &mut values_block, block.len());
+            for (v, &zigzag) in block.iter_mut().zip(&values_block) {
+                let delta = (zigzag >> 1) ^ (zigzag & 1).wrapping_neg();
+                prev = prev.wrapping_add(delta);
+                *v = prev;
+            }
+        }
+    }
+}
+
+/// Codec storing every group of 4 values as a byte of 2-bit lengths followed by 1 to 4 little
+/// endian significant bytes of every value.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct GroupVarint;
+
+impl BlockCodec for GroupVarint {
+    fn encode(values: &[u32], dict: &mut Vec<u8>) {
+        for group in values.chunks(4) {
+            let control_idx = dict.len();
+            dict.push(0);
+            for (i, &value) in group.iter().enumerate() {
+                let len = (4 - value.leading_zeros() as usize / 8).max(1);
+                dict[control_idx] |= ((len - 1) as u8) << (2 * i);
+                dict.extend_from_slice(&value.to_le_bytes()[..len]);
+            }
+        }
+    }
+
+    #[inline]
+    fn decode(dict: &[u8], res: &mut [u32]) {
+        let mut offset = 0;
+        for group in res.chunks_mut(4) {
+            let control = dict[offset];
+            offset += 1;
+            for (i, v) in group.iter_mut().enumerate() {
+                let len = ((control >> (2 * i)) & 3) as usize + 1;
+                // read whole 4 bytes, the dictionary padding covers the last value
+                let bytes: [u8; 4] = dict[offset..offset + 4].try_into().unwrap();
+                *v = u32::from_le_bytes(bytes) & (u32::MAX >> (32 - 8 * len));
+                offset += len;
+            }
+        }
+    }
+}
+
+/// Block codec chosen at construction of `MapWithDictBitpacked` and recorded in it.
+///
+/// # Examples
+/// ```
+/// # use entropy_map::{BlockCodecKind, BuildPolicy, MapWithDictBitpacked};
+/// let values: Vec<u32> = (1_000_000..1_000_040).collect();
+/// let (map, _) = MapWithDictBitpacked::<u32>::from_iter_with_codec(
+///     [(1, values.clone())],
+///     BlockCodecKind::FrameOfReference,
+///     &BuildPolicy::default(),
+/// )
+/// .unwrap();
+/// assert_eq!(map.block_codec(), BlockCodecKind::FrameOfReference);
+/// let mut res = vec![0; 40];
+/// assert!(map.get_values(&1, &mut res));
+/// assert_eq!(res, values);
+/// ```
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "rkyv_derive", derive(rkyv::Archive, rkyv::Deserialize, rkyv::Serialize))]
+#[cfg_attr(feature = "rkyv_derive", archive_attr(derive(rkyv::CheckBytes)))]
+#[repr(u8)]
+pub enum BlockCodecKind {
+    /// [`Bitpacked`] codec
+    #[default]
+    Bitpacked,
+    /// [`FrameOfReference`] codec
+    FrameOfReference,
+    /// [`Delta`] codec
+    Delta,
+    /// [`GroupVarint`] codec
+    GroupVarint,
+}
+
+impl BlockCodecKind {
+    /// All available block codecs.
+    pub const ALL: [BlockCodecKind; 4] = [
+        BlockCodecKind::Bitpacked,
+        BlockCodecKind::FrameOfReference,
+        BlockCodecKind::Delta,
+        BlockCodecKind::GroupVarint,
+    ];
+
+    /// Appends the encoding of `values` with the codec to `dict`.
+    #[inline]
+    pub fn encode(self, values: &[u32], dict: &mut Vec<u8>) {
+        match self {
+            BlockCodecKind::Bitpacked => Bitpacked::encode(values, dict),
+            BlockCodecKind::FrameOfReference => FrameOfReference::encode(values, dict),
+            BlockCodecKind::Delta => Delta::encode(values, dict),
+            BlockCodecKind::GroupVarint => GroupVarint::encode(values, dict),
+        }
+    }
+
+    /// Fills `res` with values encoded with the codec at the start of `dict`.
+    #[inline]
+    pub fn decode(self, dict: &[u8], res: &mut [u32]) {
+        match self {
+            BlockCodecKind::Bitpacked => Bitpacked::decode(dict, res),
+            BlockCodecKind::FrameOfReference => FrameOfReference::decode(dict, res),
+            BlockCodecKind::Delta => Delta::decode(dict, res),
+            BlockCodecKind::GroupVarint => GroupVarint::decode(dict, res),
+        }
+    }
+}
+
+/// Convert `Archived` version of `BlockCodecKind` back to it if feature is enabled
+#[cfg(feature = "rkyv_derive")]
+impl From<&ArchivedBlockCodecKind> for BlockCodecKind {
+    #[inline]
+    fn from(kind: &ArchivedBlockCodecKind) -> Self {
+        match kind {
+            ArchivedBlockCodecKind::Bitpacked => BlockCodecKind::Bitpacked,
+            ArchivedBlockCodecKind::FrameOfReference => BlockCodecKind::FrameOfReference,
+            ArchivedBlockCodecKind::Delta => BlockCodecKind::Delta,
+            ArchivedBlockCodecKind::GroupVarint => BlockCodecKind::GroupVarint,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::{Rng, SeedableRng};
+    use rand_chacha::ChaCha8Rng;
+    use test_case::test_case;
+
+    /// Encodes `values` with `codec`, checks they decode back and returns the encoded size.
+    fn round_trip(codec: BlockCodecKind, values: &[u32]) -> usize {
+        let mut dict = vec![];
+        codec.encode(values, &mut dict);
+        let size = dict.len();
+        dict.resize(size + DICT_PADDING, 0);
+
+        let mut res = vec![0; values.len()];
+        codec.decode(&dict, &mut res);
+        assert_eq!(res, values, "{codec:?}");
+        size
+    }
+
+    #[test]
+    fn test_round_trip_random() {
+        let mut rng = ChaCha8Rng::seed_from_u64(123);
+        for codec in BlockCodecKind::ALL {
+            for n in 0..=100 {
+                for num_bits in [0, 1, 7, 8, 17, 31, 32] {
+                    let values: Vec<u32> = (0..n)
+                        .map(|_| (rng.gen::<u64>() & ((1 << num_bits) - 1)) as u32)
+                        .collect();
+                    round_trip(codec, &values);
+                }
+            }
+            round_trip(codec, &[u32::MAX, 0, u32::MAX, 1, 0]);
+        }
+    }
+
+    #[test_case(BlockCodecKind::FrameOfReference; "frame of reference")]
+    #[test_case(BlockCodecKind::Delta; "delta")]
+    fn test_narrow_range(codec: BlockCodecKind) {
+        // large sorted values within a narrow range take ~1 byte each with bit-packing alone
+        let values: Vec<u32> = (0..64).map(|i| 3_000_000_000 + 3 * i).collect();
+        assert!(round_trip(codec, &values) < round_trip(BlockCodecKind::Bitpacked, &values) / 2);
+    }
+
+    #[test]
+    fn test_group_varint_skewed() {
+        // small values with rare large outliers inflate the bits width of whole blocks
+        let values: Vec<u32> = (0..64).map(|i| if i == 10 { u32::MAX } else { i % 7 }).collect();
+        assert!(round_trip(BlockCodecKind::GroupVarint, &values) < round_trip(BlockCodecKind::Bitpacked, &values));
+    }
+}
@@ -0,0 +1,317 @@
+//! A module implementing `borsh` serialization of `Mphf`, `Set`, `MapWithDict` and
+//! `MapWithDictBitpacked`, for tooling standardized on `borsh` which can't use `rkyv` archives.
+//!
+//! Unlike `rkyv` archives, `borsh` encodings are deserialized into owned structures. Only the
+//! information needed to reassemble a structure is written: rank metadata is rebuilt from the bits
+//! instead of being stored, and `usize` values indices are written as `u64`. Deserialized
+//! structures are checked with `debug_check_invariants`, as queries access them without bounds
+//! checks, so that corrupted or crafted inputs fail with `io::ErrorKind::InvalidData`.
+
+use std::hash::{Hash, Hasher};
+use std::marker::PhantomData;
+
+use borsh::io::{Error, ErrorKind, Read, Result, Write};
+use borsh::{BorshDeserialize, BorshSerialize};
+use num::{PrimInt, Unsigned};
+
+use crate::block_codec::BlockCodecKind;
+use crate::invariants::InvariantError;
+use crate::key_filter::{FilterBlock, KeyFilter};
+use crate::map_with_dict::MapWithDict;
+use crate::map_with_dict_bitpacked::MapWithDictBitpacked;
+use crate::mphf::Mphf;
+use crate::mphf_extras::{Extras, ExtrasLayout, NoExtras};
+use crate::rank::RankedBits;
+use crate::set::Set;
+
+/// Returns the `InvalidData` error of a deserialized structure failing its invariants check.
+fn invalid_data(err: InvariantError) -> Error {
+    Error::new(ErrorKind::InvalidData, format!("invariant violated: {err:?}"))
+}
+
+/// Writes `usize` values as `u64`, since `borsh` doesn't encode platform-dependent integers.
+fn serialize_usizes<W: Write>(values: &[usize], writer: &mut W) -> Result<()> {
+    let values: Vec<u64> = values.iter().map(|&value| value as u64).collect();
+    values.serialize(writer)
+}
+
+/// Reads `usize` values written by `serialize_usizes`.
+fn deserialize_usizes<R: Read>(reader: &mut R) -> Result<Box<[usize]>> {
+    Vec::<u64>::deserialize_reader(reader)?
+        .into_iter()
+        .map(|value| usize::try_from(value).map_err(|_| Error::new(ErrorKind::InvalidData, "usize overflow")))
+        .collect()
+}
+
+impl BorshSerialize for RankedBits {
+    #[inline]
+    fn serialize<W: Write>(&self, writer: &mut W) -> Result<()> {
+        self.bits.serialize(writer)
+    }
+}
+
+impl BorshDeserialize for RankedBits {
+    #[inline]
+    fn deserialize_reader<R: Read>(reader: &mut R) -> Result<Self> {
+        Ok(RankedBits::new(
+            Vec::<u64>::deserialize_reader(reader)?.into_boxed_slice(),
+        ))
+    }
+}
+
+impl BorshSerialize for KeyFilter {
+    fn serialize<W: Write>(&self, writer: &mut W) -> Result<()> {
+        let blocks: Vec<_> = self.blocks.iter().map(|block| block.0).collect();
+        blocks.serialize(writer)?;
+        self.probes.serialize(writer)?;
+        self.bits_per_key.serialize(writer)
+    }
+}
+
+impl BorshDeserialize for KeyFilter {
+    fn deserialize_reader<R: Read>(reader: &mut R) -> Result<Self> {
+        let blocks = Vec::deserialize_reader(reader)?.into_iter().map(FilterBlock).collect();
+        let probes = u32::deserialize_reader(reader)?;
+        let bits_per_key = u32::deserialize_reader(reader)?;
+        Ok(KeyFilter { blocks, probes, bits_per_key })
+    }
+}
+
+impl BorshSerialize for BlockCodecKind {
+    #[inline]
+    fn serialize<W: Write>(&self, writer: &mut W) -> Result<()> {
+        (*self as u8).serialize(writer)
+    }
+}
+
+impl BorshDeserialize for BlockCodecKind {
+    #[inline]
+    fn deserialize_reader<R: Read>(reader: &mut R) -> Result<Self> {
+        let tag = u8::deserialize_reader(reader)?;
+        BlockCodecKind::ALL
+            .into_iter()
+            .find(|&codec| codec as u8 == tag)
+            .ok_or_else(|| Error::new(ErrorKind::InvalidData, format!("unknown block codec {tag}")))
+    }
+}
+
+impl BorshSerialize for NoExtras {
+    #[inline]
+    fn serialize<W: Write>(&self, _writer: &mut W) -> Result<()> {
+        Ok(())
+    }
+}
+
+impl BorshDeserialize for NoExtras {
+    #[inline]
+    fn deserialize_reader<R: Read>(_reader: &mut R) -> Result<Self> {
+        Ok(NoExtras)
+    }
+}
+
+impl BorshSerialize for Extras {
+    fn serialize<W: Write>(&self, writer: &mut W) -> Result<()> {
+        self.hash_seed.serialize(writer)?;
+        self.build_seed.serialize(writer)?;
+        self.key_filter.serialize(writer)?;
+        self.fallback.serialize(writer)
+    }
+}
+
+impl BorshDeserialize for Extras {
+    fn deserialize_reader<R: Read>(reader: &mut R) -> Result<Self> {
+        Ok(Extras {
+            hash_seed: u64::deserialize_reader(reader)?,
+            build_seed: u64::deserialize_reader(reader)?,
+            key_filter: Option::deserialize_reader(reader)?,
+            fallback: Vec::deserialize_reader(reader)?.into_boxed_slice(),
+        })
+    }
+}
+
+impl<const B: usize, const S: usize, ST, H, E> BorshSerialize for Mphf<B, S, ST, H, RankedBits, E>
+where
+    ST: PrimInt + Unsigned + BorshSerialize,
+    H: Hasher + Default,
+    E: ExtrasLayout + BorshSerialize,
+{
+    fn serialize<W: Write>(&self, writer: &mut W) -> Result<()> {
+        self.ranked_bits.serialize(writer)?;
+        self.level_groups.serialize(writer)?;
+        self.group_seeds.serialize(writer)?;
+        self.extras.serialize(writer)
+    }
+}
+
+impl<const B: usize, const S: usize, ST, H, E> BorshDeserialize for Mphf<B, S, ST, H, RankedBits, E>
+where
+    ST: PrimInt + Unsigned + BorshDeserialize,
+    H: Hasher + Default,
+    E: ExtrasLayout + BorshDeserialize,
+{
+    fn deserialize_reader<R: Read>(reader: &mut R) -> Result<Self> {
+        let mphf = Mphf {
+            ranked_bits: RankedBits::deserialize_reader(reader)?,
+            level_groups: Vec::deserialize_reader(reader)?.into_boxed_slice(),
+            group_seeds: Vec::deserialize_reader(reader)?.into_boxed_slice(),
+            extras: E::deserialize_reader(reader)?,
+            _phantom_hasher: PhantomData,
+        };
+        mphf.debug_check_invariants().map_err(invalid_data)?;
+        Ok(mphf)
+    }
+}
+
+impl<K, const B: usize, const S: usize, ST, H, E> BorshSerialize for Set<K, B, S, ST, H, E>
+where
+    K: BorshSerialize,
+    ST: PrimInt + Unsigned + BorshSerialize,
+    H: Hasher + Default,
+    E: ExtrasLayout + BorshSerialize,
+{
+    fn serialize<W: Write>(&self, writer: &mut W) -> Result<()> {
+        self.mphf.serialize(writer)?;
+        self.keys.serialize(writer)
+    }
+}
+
+impl<K, const B: usize, const S: usize, ST, H, E> BorshDeserialize for Set<K, B, S, ST, H, E>
+where
+    K: Eq + Hash + BorshDeserialize,
+    ST: PrimInt + Unsigned + BorshDeserialize,
+    H: Hasher + Default,
+    E: ExtrasLayout + BorshDeserialize,
+{
+    fn deserialize_reader<R: Read>(reader: &mut R) -> Result<Self> {
+        let mphf = Mphf::deserialize_reader(reader)?;
+        let keys = Vec::deserialize_reader(reader)?.into_boxed_slice();
+        let set = Set { mphf, keys };
+        set.debug_check_invariants().map_err(invalid_data)?;
+        Ok(set)
+    }
+}
+
+impl<K, V, const B: usize, const S: usize, ST, H, E> BorshSerialize for MapWithDict<K, V, B, S, ST, H, E>
+where
+    K: BorshSerialize,
+    V: BorshSerialize,
+    ST: PrimInt + Unsigned + BorshSerialize,
+    H: Hasher + Default,
+    E: ExtrasLayout + BorshSerialize,
+{
+    fn serialize<W: Write>(&self, writer: &mut W) -> Result<()> {
+        self.mphf.serialize(writer)?;
+        self.keys.serialize(writer)?;
+        serialize_usizes(&self.values_index, writer)?;
+        self.values_dict.serialize(writer)
+    }
+}
+
+impl<K, V, const B: usize, const S: usize, ST, H, E> BorshDeserialize for MapWithDict<K, V, B, S, ST, H, E>
+where
+    K: Eq + Hash + Clone + BorshDeserialize,
+    V: Eq + Clone + Hash + BorshDeserialize,
+    ST: PrimInt + Unsigned + BorshDeserialize,
+    H: Hasher + Default,
+    E: ExtrasLayout + BorshDeserialize,
+{
+    fn deserialize_reader<R: Read>(reader: &mut R) -> Result<Self> {
+        let mphf = Mphf::deserialize_reader(reader)?;
+        let keys = Vec::deserialize_reader(reader)?.into_boxed_slice();
+        let values_index = deserialize_usizes(reader)?;
+        let values_dict = Vec::deserialize_reader(reader)?.into_boxed_slice();
+        MapWithDict::from_raw_parts((mphf, keys, values_index, values_dict)).map_err(invalid_data)
+    }
+}
+
+impl<K, const B: usize, const S: usize, ST, H> BorshSerialize for MapWithDictBitpacked<K, B, S, ST, H>
+where
+    K: BorshSerialize,
+    ST: PrimInt + Unsigned + BorshSerialize,
+    H: Hasher + Default,
+{
+    fn serialize<W: Write>(&self, writer: &mut W) -> Result<()> {
+        self.mphf.serialize(writer)?;
+        self.keys.serialize(writer)?;
+        serialize_usizes(&self.values_index, writer)?;
+        self.values_dict.serialize(writer)?;
+        self.block_codec.serialize(writer)
+    }
+}
+
+impl<K, const B: usize, const S: usize, ST, H> BorshDeserialize for MapWithDictBitpacked<K, B, S, ST, H>
+where
+    K: Hash + PartialEq + Clone + BorshDeserialize,
+    ST: PrimInt + Unsigned + BorshDeserialize,
+    H: Hasher + Default,
+{
+    fn deserialize_reader<R: Read>(reader: &mut R) -> Result<Self> {
+        let mphf = Mphf::deserialize_reader(reader)?;
+        let keys = Vec::deserialize_reader(reader)?.into_boxed_slice();
+        let values_index = deserialize_usizes(reader)?;
+        let values_dict = Vec::deserialize_reader(reader)?.into_boxed_slice();
+        let block_codec = BlockCodecKind::deserialize_reader(reader)?;
+        MapWithDictBitpacked::from_raw_parts((mphf, keys, values_index, values_dict, block_codec)).map_err(invalid_data)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mphf::{BuildPolicy, MphfWithExtras, DEFAULT_GAMMA};
+    use std::collections::HashMap;
+
+    #[test]
+    fn test_borsh() {
+        let keys: Vec<u64> = (0..1000).collect();
+        let policy = BuildPolicy::fixed(DEFAULT_GAMMA).with_key_filter(8).with_hash_seed(42);
+        let (mphf, _) = MphfWithExtras::<32, 8>::from_slice_with_policy(&keys, &policy).unwrap();
+        let bytes = borsh::to_vec(&mphf).unwrap();
+        let decoded: MphfWithExtras = borsh::from_slice(&bytes).unwrap();
+        assert_eq!(decoded.hash_seed(), 42);
+        assert!(decoded.key_filter().is_some());
+        for key in &keys {
+            assert_eq!(decoded.get(key), mphf.get(key));
+        }
+
+        let set = Set::<u64>::from_iter_with_params(keys.clone(), DEFAULT_GAMMA).unwrap();
+        let decoded: Set<u64> = borsh::from_slice(&borsh::to_vec(&set).unwrap()).unwrap();
+        assert!(keys.iter().all(|key| decoded.contains(key)));
+        assert!(!decoded.contains(&1000));
+
+        let entries: HashMap<u64, String> = keys.iter().map(|&key| (key, (key % 7).to_string())).collect();
+        let map = MapWithDict::try_from(entries.clone()).unwrap();
+        let decoded: MapWithDict<u64, String> = borsh::from_slice(&borsh::to_vec(&map).unwrap()).unwrap();
+        assert!(entries.iter().all(|(key, value)| decoded.get(key) == Some(value)));
+
+        let entries: HashMap<u64, Vec<u32>> = keys.iter().map(|&key| (key, vec![key as u32, 1])).collect();
+        let map = MapWithDictBitpacked::<u64>::from_iter_with_params(entries.clone(), DEFAULT_GAMMA).unwrap();
+        let decoded: MapWithDictBitpacked<u64> = borsh::from_slice(&borsh::to_vec(&map).unwrap()).unwrap();
+        let mut values = [0; 2];
+        for (key, expected) in &entries {
+            assert!(decoded.get_values(key, &mut values));
+            assert_eq!(&values, &expected[..]);
+        }
+    }
+
+    #[test]
+    fn test_borsh_invalid_data() {
+        let map = MapWithDict::try_from(HashMap::from([(1u64, 2u64), (3, 4)])).unwrap();
+        let mut bytes = borsh::to_vec(&map).unwrap();
+        // point the last values index past the dictionary of two values
+        let values_index_end = bytes.len() - 4 - 2 * 8;
+        bytes[values_index_end - 8] = 2;
+        let result = borsh::from_slice::<MapWithDict<u64, u64>>(&bytes);
+        assert!(result.is_err_and(|err| err.kind() == ErrorKind::InvalidData));
+
+        let set = Set::try_from(std::collections::HashSet::from([1u64, 2, 3])).unwrap();
+        let bytes = borsh::to_vec(&set).unwrap();
+        // swapping the keys stores them at indices the MPHF doesn't map them to
+        let mut keys: Vec<u64> = borsh::from_slice(&bytes[bytes.len() - 4 - 3 * 8..]).unwrap();
+        keys.swap(0, 1);
+        let mut bytes = bytes[..bytes.len() - 4 - 3 * 8].to_vec();
+        bytes.extend(borsh::to_vec(&keys).unwrap());
+        let result = borsh::from_slice::<Set<u64>>(&bytes);
+        assert!(result.is_err_and(|err| err.kind() == ErrorKind::InvalidData));
+    }
+}
@@ -0,0 +1,156 @@
+//! Helpers running construction of MPHF-based structures from async code.
+//!
+//! Construction is CPU-bound and can take minutes for large key sets, so running it directly
+//! inside an async task would stall the executor. [`build_async`] runs it on the `tokio` blocking
+//! pool instead and returns a [`BuildHandle`] future resolving to the built structure, which
+//! exposes the `BuildProgress` passed to the `*_with_progress` constructors and cancels the
+//! construction when dropped.
+
+use std::future::Future;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+
+use tokio::task::{JoinError, JoinHandle};
+
+use crate::progress::BuildProgress;
+
+/// Future of a construction running on the `tokio` blocking pool, returned by [`build_async`].
+///
+/// Dropping the handle before completion cancels the construction.
+#[derive(Debug)]
+pub struct BuildHandle<T> {
+    /// Handle of the blocking task running construction
+    join_handle: JoinHandle<T>,
+    /// Progress of the construction
+    progress: BuildProgress,
+}
+
+/// Runs `build` on the `tokio` blocking pool, passing it a new `BuildProgress` to forward to
+/// `*_with_progress` constructors. Must be called from within a `tokio` runtime.
+///
+/// # Examples
+/// ```
+/// # use entropy_map::{build_async, BuildPolicy, MapWithDict};
+/// # tokio::runtime::Builder::new_current_thread().build().unwrap().block_on(async {
+/// let handle = build_async(|progress| {
+///     MapWithDict::<u32, u32>::from_iter_with_progress([(1, 2), (3, 4)], &BuildPolicy::default(), progress)
+/// });
+/// println!("levels built: {}", handle.progress().snapshot().levels);
+///
+/// let (map, _) = handle.await.unwrap().unwrap();
+/// assert_eq!(map.get(&1), Some(&2));
+/// # });
+/// ```
+pub fn build_async<T, F>(build: F) -> BuildHandle<T>
+where
+    F: FnOnce(&BuildProgress) -> T + Send + 'static,
+    T: Send + 'static,
+{
+    build_async_with_progress(BuildProgress::new(), build)
+}
+
+/// Same as [`build_async`], but passes the provided `progress`, e.g. one created with
+/// [`BuildProgress::with_callback`], to `build`.
+pub fn build_async_with_progress<T, F>(progress: BuildProgress, build: F) -> BuildHandle<T>
+where
+    F: FnOnce(&BuildProgress) -> T + Send + 'static,
+    T: Send + 'static,
+{
+    let build_progress = progress.clone();
+    let join_handle = tokio::task::spawn_blocking(move || build(&build_progress));
+    BuildHandle { join_handle, progress }
+}
+
+impl<T> BuildHandle<T> {
+    /// Returns the progress of the construction.
+    #[inline]
+    pub fn progress(&self) -> &BuildProgress {
+        &self.progress
+    }
+
+    /// Requests cancellation of the construction, which then resolves to the `MphfError::Cancelled`
+    /// error returned by the constructor.
+    #[inline]
+    pub fn cancel(&self) {
+        self.progress.cancel();
+    }
+}
+
+impl<T> Future for BuildHandle<T> {
+    /// Result of the construction, or `JoinError` if it panicked.
+    type Output = Result<T, JoinError>;
+
+    #[inline]
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        Pin::new(&mut self.get_mut().join_handle).poll(cx)
+    }
+}
+
+impl<T> Drop for BuildHandle<T> {
+    fn drop(&mut self) {
+        self.progress.cancel();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{BuildPolicy, MphfError, Set};
+    use std::sync::mpsc;
+
+    fn runtime() -> tokio::runtime::Runtime {
+        tokio::runtime::Builder::new_current_thread().build().unwrap()
+    }
+
+    #[test]
+    fn test_build_async() {
+        let (set, report) = runtime()
+            .block_on(async {
+                build_async(|progress| {
+                    Set::<u64>::from_iter_with_progress(0..10_000, &BuildPolicy::default(), progress)
+                })
+                .await
+            })
+            .unwrap()
+            .unwrap();
+
+        assert_eq!(set.len(), 10_000);
+        assert_eq!(report.keys(), 10_000);
+    }
+
+    #[test]
+    fn test_build_async_cancel() {
+        let (started_tx, started_rx) = mpsc::channel();
+        let (cancelled_tx, cancelled_rx) = mpsc::channel();
+
+        let res = runtime().block_on(async move {
+            let handle = build_async(move |progress| {
+                // wait for the cancellation before starting construction
+                started_tx.send(()).unwrap();
+                cancelled_rx.recv().unwrap();
+                Set::<u64>::from_iter_with_progress(0..10_000, &BuildPolicy::default(), progress)
+            });
+            tokio::task::spawn_blocking(move || started_rx.recv().unwrap())
+                .await
+                .unwrap();
+            handle.cancel();
+            cancelled_tx.send(()).unwrap();
+            handle.await.unwrap()
+        });
+
+        assert!(matches!(res, Err(MphfError::Cancelled)));
+    }
+
+    #[test]
+    fn test_build_async_drop_cancels() {
+        let progress = BuildProgress::new();
+
+        runtime().block_on(async {
+            let handle = build_async_with_progress(progress.clone(), |_| ());
+            assert!(!handle.progress().is_cancelled());
+            drop(handle);
+        });
+
+        assert!(progress.is_cancelled());
+    }
+}
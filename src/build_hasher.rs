@@ -0,0 +1,282 @@
+//! A module providing `MphfWithHasher`, an `Mphf` hashing keys with a `BuildHasher` it stores.
+//!
+//! `Mphf` hashes keys with `H::default()`, optionally preceded by `BuildPolicy::hash_seed`, so that
+//! instances differ by a single `u64` at most. `MphfWithHasher` instead stores any `BuildHasher`,
+//! e.g. a SipHash builder with a full 128-bit key or a randomly seeded WyHash, builds the MPHF from
+//! the hashes it computes and hashes queried keys with it. `WyHashBuilder` and `SipHashBuilder`
+//! support `rkyv`, so that their seeds are carried through serialization and archived structures
+//! hash keys the same way.
+
+use std::collections::hash_map::RandomState;
+use std::hash::{BuildHasher, Hash, Hasher};
+use std::mem::size_of_val;
+
+use num::{PrimInt, Unsigned};
+use wyhash::WyHash;
+
+use crate::mphf::{BuildPolicy, Mphf, MphfError};
+use crate::report::BuildReport;
+use crate::sip_hasher::SipHasher;
+
+/// Returns a random `u64` drawn from the OS entropy source via `RandomState`.
+fn random_u64() -> u64 {
+    RandomState::new().build_hasher().finish()
+}
+
+/// `BuildHasher` creating `WyHash` hashers seeded with `seed`.
+///
+/// # Examples
+/// ```
+/// # use std::hash::BuildHasher;
+/// # use entropy_map::WyHashBuilder;
+/// assert_eq!(WyHashBuilder::new(1).hash_one(2u64), WyHashBuilder::new(1).hash_one(2u64));
+/// assert_ne!(WyHashBuilder::new(1).hash_one(2u64), WyHashBuilder::new(2).hash_one(2u64));
+/// ```
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "rkyv_derive", derive(rkyv::Archive, rkyv::Deserialize, rkyv::Serialize))]
+#[cfg_attr(feature = "rkyv_derive", archive_attr(derive(rkyv::CheckBytes)))]
+pub struct WyHashBuilder {
+    /// Seed of the hashers
+    pub seed: u64,
+}
+
+impl WyHashBuilder {
+    /// Creates a builder of hashers seeded with `seed`.
+    #[inline]
+    pub fn new(seed: u64) -> Self {
+        WyHashBuilder { seed }
+    }
+
+    /// Creates a builder of hashers seeded with a random seed.
+    pub fn random() -> Self {
+        WyHashBuilder::new(random_u64())
+    }
+}
+
+impl BuildHasher for WyHashBuilder {
+    type Hasher = WyHash;
+
+    #[inline]
+    fn build_hasher(&self) -> WyHash {
+        WyHash::with_seed(self.seed)
+    }
+}
+
+#[cfg(feature = "rkyv_derive")]
+impl BuildHasher for ArchivedWyHashBuilder {
+    type Hasher = WyHash;
+
+    #[inline]
+    fn build_hasher(&self) -> WyHash {
+        WyHash::with_seed(self.seed)
+    }
+}
+
+/// `BuildHasher` creating `SipHasher` hashers keyed with 128-bit key `(k0, k1)`.
+///
+/// # Examples
+/// ```
+/// # use std::hash::BuildHasher;
+/// # use entropy_map::SipHash13Builder;
+/// let builder = SipHash13Builder::random();
+/// assert_eq!(builder.hash_one("key"), builder.clone().hash_one("key"));
+/// assert_ne!(builder.hash_one("key"), SipHash13Builder::new(1, 2).hash_one("key"));
+/// ```
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "rkyv_derive", derive(rkyv::Archive, rkyv::Deserialize, rkyv::Serialize))]
+#[cfg_attr(feature = "rkyv_derive", archive_attr(derive(rkyv::CheckBytes)))]
+pub struct SipHashBuilder<const C: usize, const D: usize> {
+    /// First half of the key
+    pub k0: u64,
+    /// Second half of the key
+    pub k1: u64,
+}
+
+/// Builder of SipHash-1-3 hashers, see [`crate::SipHasher13`].
+pub type SipHash13Builder = SipHashBuilder<1, 3>;
+
+/// Builder of SipHash-2-4 hashers, see [`crate::SipHasher24`].
+pub type SipHash24Builder = SipHashBuilder<2, 4>;
+
+impl<const C: usize, const D: usize> SipHashBuilder<C, D> {
+    /// Creates a builder of hashers keyed with `(k0, k1)`.
+    #[inline]
+    pub fn new(k0: u64, k1: u64) -> Self {
+        SipHashBuilder { k0, k1 }
+    }
+
+    /// Creates a builder of hashers keyed with a random key.
+    pub fn random() -> Self {
+        SipHashBuilder::new(random_u64(), random_u64())
+    }
+}
+
+impl<const C: usize, const D: usize> BuildHasher for SipHashBuilder<C, D> {
+    type Hasher = SipHasher<C, D>;
+
+    #[inline]
+    fn build_hasher(&self) -> SipHasher<C, D> {
+        SipHasher::new_with_keys(self.k0, self.k1)
+    }
+}
+
+#[cfg(feature = "rkyv_derive")]
+impl<const C: usize, const D: usize> BuildHasher for ArchivedSipHashBuilder<C, D> {
+    type Hasher = SipHasher<C, D>;
+
+    #[inline]
+    fn build_hasher(&self) -> SipHasher<C, D> {
+        SipHasher::new_with_keys(self.k0, self.k1)
+    }
+}
+
+/// `Mphf` hashing keys with the `BuildHasher` `BH` it stores, see the module documentation.
+///
+/// # Examples
+/// ```
+/// # use entropy_map::{MphfWithHasher, SipHash13Builder, DEFAULT_GAMMA};
+/// let keys = ["a", "b", "c"];
+/// let mphf: MphfWithHasher<_> = MphfWithHasher::from_slice(&keys, DEFAULT_GAMMA, SipHash13Builder::random()).unwrap();
+/// assert!(mphf.get("b").unwrap() < keys.len());
+/// ```
+#[cfg_attr(feature = "rkyv_derive", derive(rkyv::Archive, rkyv::Deserialize, rkyv::Serialize))]
+#[cfg_attr(feature = "rkyv_derive", archive_attr(derive(rkyv::CheckBytes)))]
+pub struct MphfWithHasher<BH: BuildHasher, const B: usize = 32, const S: usize = 8, ST: PrimInt + Unsigned = u8> {
+    /// MPHF built from the hashes of `build_hasher`
+    mphf: Mphf<B, S, ST>,
+    /// Builder of the hashers hashing keys
+    build_hasher: BH,
+}
+
+impl<BH: BuildHasher, const B: usize, const S: usize, ST: PrimInt + Unsigned> MphfWithHasher<BH, B, S, ST> {
+    /// Initializes `MphfWithHasher` using slice of `keys` hashed with `build_hasher` and parameter
+    /// `gamma`.
+    pub fn from_slice<K: Hash>(keys: &[K], gamma: f32, build_hasher: BH) -> Result<Self, MphfError> {
+        Self::from_slice_with_policy(keys, &BuildPolicy::fixed(gamma), build_hasher).map(|(mphf, _)| mphf)
+    }
+
+    /// Initializes `MphfWithHasher` using slice of `keys` hashed with `build_hasher`, retrying
+    /// failed builds as configured by `policy`. The `policy` hash seed is ignored, as keys are
+    /// hashed by `build_hasher` only.
+    ///
+    /// # Examples
+    /// ```
+    /// # use entropy_map::{BuildPolicy, MphfWithHasher, WyHashBuilder};
+    /// let keys: Vec<u64> = (0..1000).collect();
+    /// let policy = BuildPolicy::default().with_max_levels(16);
+    /// let (mphf, report): (MphfWithHasher<_>, _) =
+    ///     MphfWithHasher::from_slice_with_policy(&keys, &policy, WyHashBuilder::new(42)).unwrap();
+    /// assert_eq!(report.keys(), 1000);
+    /// assert_eq!(mphf.build_hasher(), &WyHashBuilder::new(42));
+    /// ```
+    pub fn from_slice_with_policy<K: Hash>(
+        keys: &[K],
+        policy: &BuildPolicy,
+        build_hasher: BH,
+    ) -> Result<(Self, BuildReport), MphfError> {
+        let hashes: Vec<u64> = keys.iter().map(|key| build_hasher.hash_one(key)).collect();
+        let (mphf, mut report) = Mphf::from_hashes_with_policy(&hashes, policy)?;
+        let mphf = MphfWithHasher { mphf, build_hasher };
+        report.set_size(mphf.size());
+        Ok((mphf, report))
+    }
+
+    /// Returns the index associated with `key` hashed with the stored `BuildHasher`, see
+    /// [`Mphf::get`].
+    #[inline]
+    pub fn get<K: Hash + ?Sized>(&self, key: &K) -> Option<usize> {
+        self.mphf.get_by_hash(self.build_hasher.hash_one(key))
+    }
+
+    /// Returns the `BuildHasher` hashing keys.
+    #[inline]
+    pub fn build_hasher(&self) -> &BH {
+        &self.build_hasher
+    }
+
+    /// Returns the underlying MPHF, queried with [`Mphf::get_by_hash`] and hashes of the stored
+    /// `BuildHasher`.
+    #[inline]
+    pub fn mphf(&self) -> &Mphf<B, S, ST> {
+        &self.mphf
+    }
+
+    /// Returns the total number of bytes occupied by `MphfWithHasher`
+    pub fn size(&self) -> usize {
+        size_of_val(self) - size_of_val(&self.mphf) + self.mphf.size()
+    }
+}
+
+/// Implement `get` for `Archived` version of `MphfWithHasher` if feature is enabled
+#[cfg(feature = "rkyv_derive")]
+impl<BH, const B: usize, const S: usize, ST> ArchivedMphfWithHasher<BH, B, S, ST>
+where
+    BH: BuildHasher + rkyv::Archive,
+    rkyv::Archived<BH>: BuildHasher,
+    ST: PrimInt + Unsigned + rkyv::Archive<Archived = ST>,
+{
+    /// Returns the index associated with `key` hashed with the stored `BuildHasher`.
+    #[inline]
+    pub fn get<K: Hash + ?Sized>(&self, key: &K) -> Option<usize> {
+        self.mphf.get_by_hash(self.build_hasher.hash_one(key))
+    }
+
+    /// Returns the archived `BuildHasher` hashing keys.
+    #[inline]
+    pub fn build_hasher(&self) -> &rkyv::Archived<BH> {
+        &self.build_hasher
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mphf::DEFAULT_GAMMA;
+
+    fn check_indices<BH: BuildHasher>(mphf: &MphfWithHasher<BH>, keys: &[u64]) {
+        let mut indices: Vec<usize> = keys.iter().map(|key| mphf.get(key).unwrap()).collect();
+        indices.sort_unstable();
+        assert!(indices.into_iter().eq(0..keys.len()));
+    }
+
+    #[test]
+    fn test_build_hashers() {
+        let keys: Vec<u64> = (0..10_000).collect();
+
+        let mphf: MphfWithHasher<_> =
+            MphfWithHasher::from_slice(&keys, DEFAULT_GAMMA, WyHashBuilder::random()).unwrap();
+        check_indices(&mphf, &keys);
+        assert_eq!(
+            mphf.mphf().get_by_hash(mphf.build_hasher().hash_one(7u64)),
+            mphf.get(&7u64)
+        );
+
+        let mphf: MphfWithHasher<_> =
+            MphfWithHasher::from_slice(&keys, DEFAULT_GAMMA, SipHash24Builder::new(1, 2)).unwrap();
+        check_indices(&mphf, &keys);
+
+        let mphf: MphfWithHasher<_> = MphfWithHasher::from_slice(&keys, DEFAULT_GAMMA, RandomState::new()).unwrap();
+        check_indices(&mphf, &keys);
+        assert_eq!(mphf.size(), mphf.mphf().size() + size_of_val(mphf.build_hasher()));
+
+        // same keys hashed with different seeds result in different MPHFs
+        let a: MphfWithHasher<_> = MphfWithHasher::from_slice(&keys, DEFAULT_GAMMA, WyHashBuilder::new(1)).unwrap();
+        let b: MphfWithHasher<_> = MphfWithHasher::from_slice(&keys, DEFAULT_GAMMA, WyHashBuilder::new(2)).unwrap();
+        assert!(keys.iter().any(|key| a.get(key) != b.get(key)));
+    }
+
+    #[cfg(feature = "rkyv_derive")]
+    #[test]
+    fn test_rkyv() {
+        let keys: Vec<u64> = (0..10_000).collect();
+        let mphf: MphfWithHasher<_> =
+            MphfWithHasher::from_slice(&keys, DEFAULT_GAMMA, SipHash13Builder::random()).unwrap();
+        let rkyv_bytes = rkyv::to_bytes::<_, 1024>(&mphf).unwrap();
+        let rkyv_mphf = rkyv::check_archived_root::<MphfWithHasher<SipHash13Builder>>(&rkyv_bytes).unwrap();
+
+        assert_eq!(rkyv_mphf.build_hasher().k0, mphf.build_hasher().k0);
+        for key in &keys {
+            assert_eq!(rkyv_mphf.get(key), mphf.get(key));
+        }
+    }
+}
@@ -0,0 +1,463 @@
+//! A module providing `CompactMapWithDict`, a `MapWithDict` variant storing keys, values index and
+//! values dictionary in a single contiguous allocation.
+//!
+//! `MapWithDict` keeps its keys, values index and values dictionary in three separately allocated
+//! slices, so a successful `get` (verify key, fetch value index, fetch value) touches three distinct
+//! memory regions. `CompactMapWithDict` packs all of them into one `u64` words buffer, each part
+//! padded to a multiple of 8 bytes, improving locality and making the archived form (with
+//! `rkyv_derive` feature) a single contiguous region next to the MPHF.
+//!
+//! Keys and values are stored as plain-old-data via `bytemuck` casts, so both must be `Pod` with
+//! alignment of at most 8 bytes.
+
+use std::borrow::Borrow;
+use std::collections::HashMap;
+use std::hash::{Hash, Hasher};
+use std::marker::PhantomData;
+use std::mem::{align_of, size_of, size_of_val};
+
+use bytemuck::Pod;
+use num::{PrimInt, Unsigned};
+use wyhash::WyHash;
+
+use crate::access;
+use crate::hashed_key::HashedKey;
+use crate::map_with_dict::MapWithDict;
+use crate::mphf::{BuildPolicy, Mphf, MphfError, DEFAULT_GAMMA};
+use crate::report::{AllocScope, BuildReport};
+
+/// An efficient, immutable hash map with dictionary-packed values, storing keys, values index and
+/// values dictionary in a single allocation.
+///
+/// # Examples
+/// ```
+/// # use entropy_map::{CompactMapWithDict, DEFAULT_GAMMA};
+/// let map = CompactMapWithDict::<u64, u32>::from_iter_with_params([(1, 2), (3, 2), (5, 6)], DEFAULT_GAMMA).unwrap();
+/// assert_eq!(map.get(&1), Some(&2));
+/// assert_eq!(map.get(&5), Some(&6));
+/// assert_eq!(map.get(&7), None);
+/// ```
+#[derive(Default)]
+#[cfg_attr(feature = "rkyv_derive", derive(rkyv::Archive, rkyv::Deserialize, rkyv::Serialize))]
+#[cfg_attr(feature = "rkyv_derive", archive_attr(derive(rkyv::CheckBytes)))]
+pub struct CompactMapWithDict<K, V, const B: usize = 32, const S: usize = 8, ST = u8, H = WyHash>
+where
+    ST: PrimInt + Unsigned,
+    H: Hasher + Default,
+{
+    /// Minimally Perfect Hash Function for keys indices retrieval
+    pub(crate) mphf: Mphf<B, S, ST, H>,
+    /// Map keys, values index and unique values, each padded to a multiple of 8 bytes
+    pub(crate) data: Box<[u64]>,
+    /// Number of keys
+    pub(crate) len: u64,
+    /// Number of unique values
+    pub(crate) dict_len: u64,
+    /// Phantom field for the keys and values types
+    _phantom: PhantomData<(K, V)>,
+}
+
+/// Returns the number of `u64` words occupied by `len` items of type `T`.
+#[inline]
+fn words_for<T>(len: usize) -> usize {
+    (len * size_of::<T>()).div_ceil(8)
+}
+
+/// Casts the first `len` items of type `T` stored in `words`.
+#[inline]
+fn cast_prefix<T: Pod>(words: &[u64], len: usize) -> &[T] {
+    bytemuck::cast_slice(&bytemuck::cast_slice::<u64, u8>(words)[..len * size_of::<T>()])
+}
+
+/// Splits `data` into keys, values index and values dictionary parts.
+#[inline]
+fn split_data<K: Pod, V: Pod>(data: &[u64], len: usize, dict_len: usize) -> (&[K], &[u64], &[V]) {
+    let (keys, rest) = data.split_at(words_for::<K>(len));
+    let (values_index, values_dict) = rest.split_at(len);
+    (cast_prefix(keys, len), values_index, cast_prefix(values_dict, dict_len))
+}
+
+/// Inner implementation of `get` returning the value at MPHF index `idx` if the key stored there is
+/// equal to `key`, with `data` passed from standard and `Archived` versions.
+#[inline]
+fn get_impl<'a, K, V, Q>(data: &'a [u64], len: usize, dict_len: usize, idx: Option<usize>, key: &Q) -> Option<&'a V>
+where
+    K: Pod + PartialEq<Q>,
+    V: Pod,
+    Q: ?Sized,
+{
+    let idx = idx?;
+    let (keys, values_index, values_dict) = split_data::<K, V>(data, len, dict_len);
+
+    // SAFETY: `idx` is always within bounds (ensured during construction)
+    unsafe {
+        if access::get(keys, idx) == key {
+            // SAFETY: `idx` and `value_idx` are always within bounds (ensured during construction)
+            let value_idx = *access::get(values_index, idx) as usize;
+            Some(access::get(values_dict, value_idx))
+        } else {
+            None
+        }
+    }
+}
+
+impl<K, V, const B: usize, const S: usize, ST, H> CompactMapWithDict<K, V, B, S, ST, H>
+where
+    K: Pod + Eq + Hash,
+    V: Pod + Eq + Hash,
+    ST: PrimInt + Unsigned,
+    H: Hasher + Default,
+{
+    /// Ensure that keys and values are non zero-sized and can be cast from `u64` words
+    const LAYOUT: () = {
+        assert!(size_of::<K>() > 0 && align_of::<K>() <= 8);
+        assert!(size_of::<V>() > 0 && align_of::<V>() <= 8);
+    };
+
+    /// Constructs a `CompactMapWithDict` from an iterator of key-value pairs and MPHF function params.
+    pub fn from_iter_with_params<I>(iter: I, gamma: f32) -> Result<Self, MphfError>
+    where
+        I: IntoIterator<Item = (K, V)>,
+    {
+        Self::from_iter_with_policy(iter, &BuildPolicy::fixed(gamma)).map(|(map, _)| map)
+    }
+
+    /// Constructs a `CompactMapWithDict` from an iterator of key-value pairs, escalating MPHF
+    /// `gamma` on failed builds according to `policy`, and returns it along with a `BuildReport`.
+    ///
+    /// # Examples
+    /// ```
+    /// # use entropy_map::{BuildPolicy, CompactMapWithDict};
+    /// let policy = BuildPolicy::default();
+    /// let (map, report) = CompactMapWithDict::<u32, u32>::from_iter_with_policy([(1, 2), (3, 2)], &policy).unwrap();
+    /// assert_eq!(report.dedup_ratio, Some(2.0));
+    /// assert_eq!(report.bits_per_key, 8.0 * map.size() as f32 / 2.0);
+    /// ```
+    pub fn from_iter_with_policy<I>(iter: I, policy: &BuildPolicy) -> Result<(Self, BuildReport), MphfError>
+    where
+        I: IntoIterator<Item = (K, V)>,
+    {
+        let alloc_scope = AllocScope::start();
+        let (map, mut report) = MapWithDict::from_iter_with_policy(iter, policy)?;
+        let map = Self::from(map);
+        alloc_scope.finish(&mut report);
+        report.set_size(map.size());
+        Ok((map, report))
+    }
+
+    /// Returns a reference to the value corresponding to the key. Returns `None` if the key is
+    /// not present in the map.
+    #[inline]
+    pub fn get<Q>(&self, key: &Q) -> Option<&V>
+    where
+        K: Borrow<Q> + PartialEq<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        get_impl::<K, V, Q>(&self.data, self.len(), self.dict_len(), self.mphf.get(key), key)
+    }
+
+    /// Same as [`CompactMapWithDict::get`], but re-uses the hash precomputed by `HashedKey`.
+    ///
+    /// # Examples
+    /// ```
+    /// # use entropy_map::{CompactMapWithDict, HashedKey, DEFAULT_GAMMA};
+    /// let map = CompactMapWithDict::<u64, u32>::from_iter_with_params([(1, 2), (3, 4)], DEFAULT_GAMMA).unwrap();
+    /// assert_eq!(map.get_hashed(&HashedKey::new(&1)), Some(&2));
+    /// assert_eq!(map.get_hashed(&HashedKey::new(&5)), None);
+    /// ```
+    #[inline]
+    pub fn get_hashed<Q>(&self, key: &HashedKey<Q, H>) -> Option<&V>
+    where
+        K: Borrow<Q> + PartialEq<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        get_impl::<K, V, Q>(
+            &self.data,
+            self.len(),
+            self.dict_len(),
+            self.mphf.get_hashed(key),
+            key.key(),
+        )
+    }
+
+    /// Checks if the map contains the specified key.
+    ///
+    /// # Examples
+    /// ```
+    /// # use entropy_map::{CompactMapWithDict, DEFAULT_GAMMA};
+    /// let map = CompactMapWithDict::<u64, u32>::from_iter_with_params([(1, 2), (3, 4)], DEFAULT_GAMMA).unwrap();
+    /// assert!(map.contains_key(&1));
+    /// assert!(!map.contains_key(&2));
+    /// ```
+    #[inline]
+    pub fn contains_key<Q>(&self, key: &Q) -> bool
+    where
+        K: Borrow<Q> + PartialEq<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        self.get(key).is_some()
+    }
+
+    /// Returns the number of key-value pairs in the map.
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.len as usize
+    }
+
+    /// Returns `true` if the map contains no elements.
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Returns the number of unique values in the dictionary.
+    #[inline]
+    pub fn dict_len(&self) -> usize {
+        self.dict_len as usize
+    }
+
+    /// Returns the dictionary of unique values of the map, in no particular order.
+    #[inline]
+    pub fn values_dict(&self) -> &[V] {
+        split_data::<K, V>(&self.data, self.len(), self.dict_len()).2
+    }
+
+    /// Returns an iterator over the map, yielding key-value pairs.
+    ///
+    /// # Examples
+    /// ```
+    /// # use entropy_map::{CompactMapWithDict, DEFAULT_GAMMA};
+    /// let map = CompactMapWithDict::<u64, u32>::from_iter_with_params([(1, 2), (3, 4)], DEFAULT_GAMMA).unwrap();
+    /// let mut entries: Vec<_> = map.iter().collect();
+    /// entries.sort();
+    /// assert_eq!(entries, vec![(&1, &2), (&3, &4)]);
+    /// ```
+    #[inline]
+    pub fn iter(&self) -> impl ExactSizeIterator<Item = (&K, &V)> + DoubleEndedIterator {
+        let (keys, values_index, values_dict) = split_data::<K, V>(&self.data, self.len(), self.dict_len());
+        keys.iter().zip(values_index.iter()).map(move |(key, &value_idx)| {
+            // SAFETY: `value_idx` is always within bounds (ensured during construction)
+            let value = unsafe { access::get(values_dict, value_idx as usize) };
+            (key, value)
+        })
+    }
+
+    /// Returns the total number of bytes occupied by the structure.
+    ///
+    /// # Examples
+    /// ```
+    /// # use entropy_map::{CompactMapWithDict, DEFAULT_GAMMA};
+    /// let map = CompactMapWithDict::<u64, u32>::from_iter_with_params([(1, 2), (3, 4)], DEFAULT_GAMMA).unwrap();
+    /// assert_eq!(map.size(), 246);
+    /// ```
+    #[inline]
+    pub fn size(&self) -> usize {
+        size_of_val(self) + self.mphf.size() + size_of_val(self.data.as_ref())
+    }
+}
+
+/// Creates a `CompactMapWithDict` from a `MapWithDict`, re-using its MPHF.
+impl<K, V, const B: usize, const S: usize, ST, H> From<MapWithDict<K, V, B, S, ST, H>>
+    for CompactMapWithDict<K, V, B, S, ST, H>
+where
+    K: Pod + Eq + Hash,
+    V: Pod + Eq + Hash,
+    ST: PrimInt + Unsigned,
+    H: Hasher + Default,
+{
+    fn from(map: MapWithDict<K, V, B, S, ST, H>) -> Self {
+        #[allow(clippy::let_unit_value)]
+        let () = Self::LAYOUT;
+
+        let (len, dict_len) = (map.keys.len(), map.values_dict.len());
+        let keys_words = words_for::<K>(len);
+        let mut data = vec![0u64; keys_words + len + words_for::<V>(dict_len)];
+
+        let (keys, rest) = data.split_at_mut(keys_words);
+        let (values_index, values_dict) = rest.split_at_mut(len);
+        bytemuck::cast_slice_mut::<u64, u8>(keys)[..len * size_of::<K>()]
+            .copy_from_slice(bytemuck::cast_slice(&map.keys));
+        for (dst, &value_idx) in values_index.iter_mut().zip(map.values_index.iter()) {
+            *dst = value_idx as u64;
+        }
+        bytemuck::cast_slice_mut::<u64, u8>(values_dict)[..dict_len * size_of::<V>()]
+            .copy_from_slice(bytemuck::cast_slice(&map.values_dict));
+
+        CompactMapWithDict {
+            mphf: map.mphf,
+            data: data.into_boxed_slice(),
+            len: len as u64,
+            dict_len: dict_len as u64,
+            _phantom: PhantomData,
+        }
+    }
+}
+
+/// Creates a `CompactMapWithDict` from a `HashMap`.
+impl<K, V> TryFrom<HashMap<K, V>> for CompactMapWithDict<K, V>
+where
+    K: Pod + Eq + Hash,
+    V: Pod + Eq + Hash,
+{
+    type Error = MphfError;
+
+    #[inline]
+    fn try_from(value: HashMap<K, V>) -> Result<Self, Self::Error> {
+        CompactMapWithDict::<K, V>::from_iter_with_params(value, DEFAULT_GAMMA)
+    }
+}
+
+/// Implement `get` for `Archived` version of `CompactMapWithDict` if feature is enabled
+#[cfg(feature = "rkyv_derive")]
+impl<K, V, const B: usize, const S: usize, ST, H> ArchivedCompactMapWithDict<K, V, B, S, ST, H>
+where
+    K: Pod,
+    V: Pod,
+    ST: PrimInt + Unsigned + rkyv::Archive<Archived = ST>,
+    H: Hasher + Default,
+{
+    /// Returns a reference to the value corresponding to the key. Returns `None` if the key is
+    /// not present in the map.
+    ///
+    /// # Examples
+    /// ```
+    /// # use entropy_map::{CompactMapWithDict, DEFAULT_GAMMA};
+    /// let map = CompactMapWithDict::<u64, u32>::from_iter_with_params([(1, 2), (3, 4)], DEFAULT_GAMMA).unwrap();
+    /// let bytes = rkyv::to_bytes::<_, 1024>(&map).unwrap();
+    /// let archived_map = rkyv::check_archived_root::<CompactMapWithDict<u64, u32>>(&bytes).unwrap();
+    /// assert_eq!(archived_map.get(&1), Some(&2));
+    /// assert_eq!(archived_map.get(&5), None);
+    /// ```
+    #[inline]
+    pub fn get<Q>(&self, key: &Q) -> Option<&V>
+    where
+        K: Borrow<Q> + PartialEq<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        get_impl::<K, V, Q>(&self.data, self.len(), self.dict_len as usize, self.mphf.get(key), key)
+    }
+
+    /// Same as [`ArchivedCompactMapWithDict::get`], but re-uses the hash precomputed by `HashedKey`.
+    #[inline]
+    pub fn get_hashed<Q>(&self, key: &HashedKey<Q, H>) -> Option<&V>
+    where
+        K: Borrow<Q> + PartialEq<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        get_impl::<K, V, Q>(
+            &self.data,
+            self.len(),
+            self.dict_len as usize,
+            self.mphf.get_hashed(key),
+            key.key(),
+        )
+    }
+
+    /// Checks if the map contains the specified key.
+    #[inline]
+    pub fn contains_key<Q>(&self, key: &Q) -> bool
+    where
+        K: Borrow<Q> + PartialEq<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        self.get(key).is_some()
+    }
+
+    /// Returns the number of key-value pairs in the map.
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.len as usize
+    }
+
+    /// Returns `true` if the map contains no elements.
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::{Rng, SeedableRng};
+    use rand_chacha::ChaCha8Rng;
+    use test_case::test_case;
+
+    fn gen_map<K, V>(items_num: usize) -> HashMap<K, V>
+    where
+        K: Eq + Hash,
+        rand::distributions::Standard: rand::distributions::Distribution<K> + rand::distributions::Distribution<V>,
+    {
+        let mut rng = ChaCha8Rng::seed_from_u64(123);
+        (0..items_num).map(|_| (rng.gen(), rng.gen())).collect()
+    }
+
+    fn test_compact_map_impl<K, V>(items_num: usize)
+    where
+        K: Pod + Eq + Hash + std::fmt::Debug,
+        V: Pod + Eq + Hash + std::fmt::Debug,
+        rand::distributions::Standard: rand::distributions::Distribution<K> + rand::distributions::Distribution<V>,
+    {
+        let original_map = gen_map::<K, V>(items_num);
+        let map = MapWithDict::<K, V>::from_iter_with_params(original_map.clone(), DEFAULT_GAMMA).unwrap();
+        let compact_map = CompactMapWithDict::from(map);
+
+        assert_eq!(compact_map.len(), original_map.len());
+        assert_eq!(compact_map.iter().len(), original_map.len());
+        for (key, value) in &original_map {
+            assert_eq!(compact_map.get(key), Some(value));
+            assert_eq!(compact_map.get_hashed(&HashedKey::new(key)), Some(value));
+        }
+        for (key, value) in compact_map.iter() {
+            assert_eq!(original_map.get(key), Some(value));
+        }
+        assert!(compact_map
+            .values_dict()
+            .iter()
+            .all(|value| original_map.values().any(|v| v == value)));
+    }
+
+    #[test_case(0)]
+    #[test_case(1)]
+    #[test_case(1000)]
+    fn test_compact_map(items_num: usize) {
+        test_compact_map_impl::<u64, u32>(items_num);
+        // keys and values with sizes not multiple of 8 bytes require padding
+        test_compact_map_impl::<[u8; 3], u8>(items_num);
+        test_compact_map_impl::<u16, [u8; 5]>(items_num);
+    }
+
+    #[test]
+    fn test_compact_map_size() {
+        let original_map: HashMap<u64, u32> = gen_map::<u64, u8>(1000)
+            .into_iter()
+            .map(|(k, v)| (k, v as u32 % 10))
+            .collect();
+        let map = MapWithDict::<u64, u32>::from_iter_with_params(original_map, DEFAULT_GAMMA).unwrap();
+        let (map_size, mphf_size) = (map.size(), map.mphf.size());
+        let compact_map = CompactMapWithDict::from(map);
+
+        // keys and values index take the same space, dictionary is padded to 8 bytes
+        assert_eq!(
+            compact_map.size() - size_of_val(&compact_map),
+            mphf_size + 8 * 1000 + 8 * 1000 + 40
+        );
+        assert!(compact_map.size() <= map_size);
+    }
+
+    #[cfg(feature = "rkyv_derive")]
+    #[test]
+    fn test_rkyv() {
+        let original_map = gen_map::<u64, u32>(1000);
+        let map = CompactMapWithDict::<u64, u32>::try_from(original_map.clone()).unwrap();
+        let rkyv_bytes = rkyv::to_bytes::<_, 1024>(&map).unwrap();
+        let rkyv_map = rkyv::check_archived_root::<CompactMapWithDict<u64, u32>>(&rkyv_bytes).unwrap();
+
+        assert_eq!(rkyv_map.len(), original_map.len());
+        for (key, value) in &original_map {
+            assert_eq!(rkyv_map.get(key), Some(value));
+            assert!(rkyv_map.contains_key(key));
+        }
+        assert_eq!(rkyv_map.get(&0), None);
+    }
+}
@@ -0,0 +1,260 @@
+//! A module providing `CowKey`, a key either borrowing or owning a string or a byte slice.
+//!
+//! Build pipelines often have the keys already loaded in memory, but to serialize `MapWithDict` or
+//! `Set` with rkyv they have to be cloned into owned `String`s or `Vec<u8>`s first, as rkyv doesn't
+//! archive `Cow`. `CowKey<'a, str>` and `CowKey<'a, [u8]>` borrow keys during construction, are
+//! archived as `ArchivedString` and `ArchivedVec<u8>` (i.e. exactly like `String` and `Vec<u8>`)
+//! and deserialize into owned keys, so archived structures can be queried by `&str` and `&[u8]`.
+
+use std::borrow::{Borrow, Cow};
+use std::fmt;
+use std::hash::{Hash, Hasher};
+use std::ops::Deref;
+
+/// Key borrowing or owning a `str` or a `[u8]`, see the module documentation.
+///
+/// # Examples
+/// ```
+/// # use entropy_map::{CowKey, MapWithDict, DEFAULT_GAMMA};
+/// let keys = vec!["a".to_string(), "b".to_string()];
+/// let map = MapWithDict::<CowKey<str>, u32>::from_iter_with_params(
+///     keys.iter().map(|k| CowKey::from(k.as_str())).zip([1, 2]),
+///     DEFAULT_GAMMA,
+/// )
+/// .unwrap();
+/// assert_eq!(map.get("a"), Some(&1));
+/// assert_eq!(map.get("c"), None);
+/// ```
+pub struct CowKey<'a, T: ?Sized + ToOwned>(pub Cow<'a, T>);
+
+impl<T: ?Sized + ToOwned> CowKey<'_, T> {
+    /// Converts the key into one owning its data, cloning it if it's borrowed.
+    #[inline]
+    pub fn into_owned(self) -> CowKey<'static, T> {
+        CowKey(Cow::Owned(self.0.into_owned()))
+    }
+}
+
+impl<T: ?Sized + ToOwned> Clone for CowKey<'_, T> {
+    #[inline]
+    fn clone(&self) -> Self {
+        CowKey(self.0.clone())
+    }
+}
+
+impl<T: ?Sized + ToOwned> Deref for CowKey<'_, T> {
+    type Target = T;
+
+    #[inline]
+    fn deref(&self) -> &T {
+        &self.0
+    }
+}
+
+impl<T: ?Sized + ToOwned + fmt::Debug> fmt::Debug for CowKey<'_, T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Debug::fmt(&**self, f)
+    }
+}
+
+impl<T: ?Sized + ToOwned + PartialEq> PartialEq for CowKey<'_, T> {
+    #[inline]
+    fn eq(&self, other: &Self) -> bool {
+        **self == **other
+    }
+}
+
+impl<T: ?Sized + ToOwned + Eq> Eq for CowKey<'_, T> {}
+
+impl<T: ?Sized + ToOwned + PartialEq> PartialEq<T> for CowKey<'_, T> {
+    #[inline]
+    fn eq(&self, other: &T) -> bool {
+        **self == *other
+    }
+}
+
+/// Hashes exactly like the borrowed `T`, as required by `Borrow<T>`.
+impl<T: ?Sized + ToOwned + Hash> Hash for CowKey<'_, T> {
+    #[inline]
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        (**self).hash(state);
+    }
+}
+
+impl<T: ?Sized + ToOwned> Borrow<T> for CowKey<'_, T> {
+    #[inline]
+    fn borrow(&self) -> &T {
+        self
+    }
+}
+
+impl<'a, T: ?Sized + ToOwned> From<&'a T> for CowKey<'a, T> {
+    #[inline]
+    fn from(key: &'a T) -> Self {
+        CowKey(Cow::Borrowed(key))
+    }
+}
+
+impl<'a, T: ?Sized + ToOwned> From<Cow<'a, T>> for CowKey<'a, T> {
+    #[inline]
+    fn from(key: Cow<'a, T>) -> Self {
+        CowKey(key)
+    }
+}
+
+impl From<String> for CowKey<'_, str> {
+    #[inline]
+    fn from(key: String) -> Self {
+        CowKey(Cow::Owned(key))
+    }
+}
+
+impl From<Vec<u8>> for CowKey<'_, [u8]> {
+    #[inline]
+    fn from(key: Vec<u8>) -> Self {
+        CowKey(Cow::Owned(key))
+    }
+}
+
+#[cfg(feature = "rkyv_derive")]
+mod rkyv_impls {
+    use super::*;
+    use rkyv::ser::{ScratchSpace, Serializer};
+    use rkyv::string::{ArchivedString, StringResolver};
+    use rkyv::vec::{ArchivedVec, VecResolver};
+    use rkyv::{Archive, Deserialize, Fallible, Serialize};
+
+    /// Archived exactly like `String`.
+    impl Archive for CowKey<'_, str> {
+        type Archived = ArchivedString;
+        type Resolver = StringResolver;
+
+        #[inline]
+        unsafe fn resolve(&self, pos: usize, resolver: Self::Resolver, out: *mut Self::Archived) {
+            ArchivedString::resolve_from_str(self, pos, resolver, out);
+        }
+    }
+
+    impl<S: Serializer + ?Sized> Serialize<S> for CowKey<'_, str> {
+        #[inline]
+        fn serialize(&self, serializer: &mut S) -> Result<Self::Resolver, S::Error> {
+            ArchivedString::serialize_from_str(self, serializer)
+        }
+    }
+
+    impl<D: Fallible + ?Sized> Deserialize<CowKey<'_, str>, D> for ArchivedString {
+        #[inline]
+        fn deserialize(&self, _: &mut D) -> Result<CowKey<'static, str>, D::Error> {
+            Ok(CowKey(Cow::Owned(self.as_str().to_owned())))
+        }
+    }
+
+    impl PartialEq<CowKey<'_, str>> for ArchivedString {
+        #[inline]
+        fn eq(&self, other: &CowKey<'_, str>) -> bool {
+            self.as_str() == &**other
+        }
+    }
+
+    /// Archived exactly like `Vec<u8>`.
+    impl Archive for CowKey<'_, [u8]> {
+        type Archived = ArchivedVec<u8>;
+        type Resolver = VecResolver;
+
+        #[inline]
+        unsafe fn resolve(&self, pos: usize, resolver: Self::Resolver, out: *mut Self::Archived) {
+            ArchivedVec::resolve_from_slice(self, pos, resolver, out);
+        }
+    }
+
+    impl<S: ScratchSpace + Serializer + ?Sized> Serialize<S> for CowKey<'_, [u8]> {
+        #[inline]
+        fn serialize(&self, serializer: &mut S) -> Result<Self::Resolver, S::Error> {
+            ArchivedVec::serialize_from_slice(self, serializer)
+        }
+    }
+
+    impl<D: Fallible + ?Sized> Deserialize<CowKey<'_, [u8]>, D> for ArchivedVec<u8> {
+        #[inline]
+        fn deserialize(&self, _: &mut D) -> Result<CowKey<'static, [u8]>, D::Error> {
+            Ok(CowKey(Cow::Owned(self.as_slice().to_vec())))
+        }
+    }
+
+    impl PartialEq<CowKey<'_, [u8]>> for ArchivedVec<u8> {
+        #[inline]
+        fn eq(&self, other: &CowKey<'_, [u8]>) -> bool {
+            self.as_slice() == &**other
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{MapWithDict, DEFAULT_GAMMA};
+    use std::hash::{BuildHasher, BuildHasherDefault};
+    use wyhash::WyHash;
+
+    #[test]
+    fn test_cow_key_map() {
+        let keys: Vec<String> = (0..1000).map(|i| format!("key{i}")).collect();
+        let map = MapWithDict::<CowKey<str>, usize>::from_iter_with_params(
+            keys.iter().enumerate().map(|(i, k)| (CowKey::from(k.as_str()), i % 10)),
+            DEFAULT_GAMMA,
+        )
+        .unwrap();
+
+        // `CowKey` must hash exactly like the borrowed `str` for `Borrow` lookups to work
+        let hasher = BuildHasherDefault::<WyHash>::default();
+        assert_eq!(hasher.hash_one(CowKey::from("abc")), hasher.hash_one("abc"));
+        assert_eq!(hasher.hash_one(CowKey::from("abc".to_string())), hasher.hash_one("abc"));
+
+        for (i, key) in keys.iter().enumerate() {
+            assert_eq!(map.get(key.as_str()), Some(&(i % 10)));
+            assert_eq!(map.get(&CowKey::from(key.clone())), Some(&(i % 10)));
+        }
+        assert_eq!(map.get("key1000"), None);
+        assert!(map.keys().all(|k| matches!(k.0, Cow::Borrowed(_))));
+    }
+
+    #[cfg(feature = "rkyv_derive")]
+    #[test]
+    fn test_rkyv() {
+        let keys: Vec<String> = (0..100).map(|i| format!("a-somewhat-longer-key-{i}")).collect();
+        let map = MapWithDict::<CowKey<str>, u32>::from_iter_with_params(
+            keys.iter().map(|k| CowKey::from(k.as_str())).zip(0..),
+            DEFAULT_GAMMA,
+        )
+        .unwrap();
+        let owned_map =
+            MapWithDict::<String, u32>::from_iter_with_params(keys.iter().cloned().zip(0..), DEFAULT_GAMMA).unwrap();
+
+        // archived exactly like `String` keys
+        let rkyv_bytes = rkyv::to_bytes::<_, 1024>(&map).unwrap();
+        assert_eq!(
+            rkyv_bytes.as_slice(),
+            rkyv::to_bytes::<_, 1024>(&owned_map).unwrap().as_slice()
+        );
+
+        let rkyv_map = rkyv::check_archived_root::<MapWithDict<CowKey<str>, u32>>(&rkyv_bytes).unwrap();
+        for (i, key) in keys.iter().enumerate() {
+            assert_eq!(rkyv_map.get(key.as_str()), Some(&(i as u32)));
+        }
+        assert_eq!(rkyv_map.get("b"), None);
+
+        let deserialized: MapWithDict<CowKey<str>, u32> = rkyv::from_bytes(&rkyv_bytes).unwrap();
+        assert_eq!(deserialized.get(keys[7].as_str()), Some(&7));
+
+        let byte_keys = [b"ab".to_vec(), b"cd".to_vec()];
+        let set = crate::Set::<CowKey<[u8]>>::from_iter_with_params(
+            byte_keys.iter().map(|k| CowKey::from(k.as_slice())),
+            DEFAULT_GAMMA,
+        )
+        .unwrap();
+        let rkyv_bytes = rkyv::to_bytes::<_, 1024>(&set).unwrap();
+        let rkyv_set = rkyv::check_archived_root::<crate::Set<CowKey<[u8]>>>(&rkyv_bytes).unwrap();
+        assert!(rkyv_set.contains(b"ab".as_slice()));
+        assert!(!rkyv_set.contains(b"ef".as_slice()));
+    }
+}
@@ -0,0 +1,403 @@
+//! A module providing `diff`, comparing two maps or sets key by key.
+//!
+//! `diff` streams over both structures without allocating: it first walks the keys of `a` looking
+//! each one up in `b` to report removed and changed keys, then walks the keys of `b` looking each
+//! one up in `a` to report added keys. Keys are reported in the internal order of each structure.
+//! Archived structures are compared in place, which makes it cheap to audit multi-GB memory-mapped
+//! archives before shipping them.
+
+use std::hash::{Hash, Hasher};
+
+use num::{PrimInt, Unsigned};
+
+use crate::access;
+use crate::map_with_dict::MapWithDict;
+use crate::set::Set;
+
+#[cfg(feature = "rkyv_derive")]
+use crate::map_with_dict::ArchivedMapWithDict;
+#[cfg(feature = "rkyv_derive")]
+use crate::set::ArchivedSet;
+
+/// Structure whose entries can be compared by [`diff`].
+pub trait Diffable {
+    /// Type of keys.
+    type Key: ?Sized;
+    /// Type of values, `()` for sets.
+    type Value: PartialEq + ?Sized;
+
+    /// Returns the key and the value of the entry at `idx`, or `None` if `idx` is out of bounds.
+    fn entry(&self, idx: usize) -> Option<(&Self::Key, &Self::Value)>;
+
+    /// Returns the value of `key`, or `None` if the key is not present.
+    fn lookup(&self, key: &Self::Key) -> Option<&Self::Value>;
+}
+
+/// Single difference between two structures reported by [`diff`].
+#[derive(Debug, PartialEq, Eq)]
+pub enum DiffEntry<'a, K: ?Sized, V: ?Sized> {
+    /// Key present only in the second structure, with its value.
+    Added(&'a K, &'a V),
+    /// Key present only in the first structure, with its value.
+    Removed(&'a K, &'a V),
+    /// Key present in both structures with different values, first one followed by the second one.
+    Changed(&'a K, &'a V, &'a V),
+}
+
+/// Numbers of differences between two structures, see [`Diff::summary`].
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct DiffSummary {
+    /// Number of keys present only in the second structure
+    pub added: usize,
+    /// Number of keys present only in the first structure
+    pub removed: usize,
+    /// Number of keys present in both structures with different values
+    pub changed: usize,
+}
+
+impl DiffSummary {
+    /// Returns `true` if structures have no differences.
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.added == 0 && self.removed == 0 && self.changed == 0
+    }
+}
+
+/// Iterator over differences between two structures returned by [`diff`].
+pub struct Diff<'a, T: ?Sized, U: ?Sized> {
+    /// First structure
+    a: &'a T,
+    /// Second structure
+    b: &'a U,
+    /// Index of the next entry of `a`
+    a_idx: usize,
+    /// Index of the next entry of `b`, walked once `a` is exhausted
+    b_idx: usize,
+}
+
+/// Returns an iterator over the differences between maps or sets `a` and `b`, reporting keys
+/// added to `b`, removed from `a` and changed between them. Structures may be built with different
+/// MPHF parameters and either of them may be archived, as long as keys and values types match.
+///
+/// # Examples
+/// ```
+/// # use std::collections::HashMap;
+/// # use entropy_map::{diff, DiffEntry, DiffSummary, MapWithDict};
+/// let a = MapWithDict::try_from(HashMap::from([(1, 2), (3, 4), (5, 6)])).unwrap();
+/// let b = MapWithDict::try_from(HashMap::from([(1, 2), (3, 5), (7, 8)])).unwrap();
+/// let mut entries: Vec<_> = diff(&a, &b).collect();
+/// entries.sort_by_key(|entry| match entry {
+///     DiffEntry::Added(k, _) | DiffEntry::Removed(k, _) | DiffEntry::Changed(k, _, _) => **k,
+/// });
+/// assert_eq!(
+///     entries,
+///     [DiffEntry::Changed(&3, &4, &5), DiffEntry::Removed(&5, &6), DiffEntry::Added(&7, &8)]
+/// );
+/// assert_eq!(diff(&a, &b).summary(), DiffSummary { added: 1, removed: 1, changed: 1 });
+/// ```
+#[inline]
+pub fn diff<'a, T, U>(a: &'a T, b: &'a U) -> Diff<'a, T, U>
+where
+    T: Diffable + ?Sized,
+    U: Diffable<Key = T::Key, Value = T::Value> + ?Sized,
+{
+    Diff { a, b, a_idx: 0, b_idx: 0 }
+}
+
+impl<'a, T, U> Diff<'a, T, U>
+where
+    T: Diffable + ?Sized,
+    U: Diffable<Key = T::Key, Value = T::Value> + ?Sized,
+{
+    /// Consumes the iterator counting differences of every kind.
+    pub fn summary(self) -> DiffSummary {
+        let mut summary = DiffSummary::default();
+        for entry in self {
+            match entry {
+                DiffEntry::Added(..) => summary.added += 1,
+                DiffEntry::Removed(..) => summary.removed += 1,
+                DiffEntry::Changed(..) => summary.changed += 1,
+            }
+        }
+        summary
+    }
+}
+
+impl<'a, T, U> Iterator for Diff<'a, T, U>
+where
+    T: Diffable + ?Sized,
+    U: Diffable<Key = T::Key, Value = T::Value> + ?Sized,
+{
+    type Item = DiffEntry<'a, T::Key, T::Value>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while let Some((key, a_value)) = self.a.entry(self.a_idx) {
+            self.a_idx += 1;
+            match self.b.lookup(key) {
+                None => return Some(DiffEntry::Removed(key, a_value)),
+                Some(b_value) if a_value != b_value => return Some(DiffEntry::Changed(key, a_value, b_value)),
+                Some(_) => {}
+            }
+        }
+
+        while let Some((key, b_value)) = self.b.entry(self.b_idx) {
+            self.b_idx += 1;
+            if self.a.lookup(key).is_none() {
+                return Some(DiffEntry::Added(key, b_value));
+            }
+        }
+
+        None
+    }
+}
+
+impl<K, V, const B: usize, const S: usize, ST, H> Diffable for MapWithDict<K, V, B, S, ST, H>
+where
+    K: PartialEq + Hash,
+    V: PartialEq,
+    ST: PrimInt + Unsigned,
+    H: Hasher + Default,
+{
+    type Key = K;
+    type Value = V;
+
+    #[inline]
+    fn entry(&self, idx: usize) -> Option<(&K, &V)> {
+        let key = self.keys.get(idx)?;
+        Some((key, &self.values_dict[self.values_index[idx]]))
+    }
+
+    #[inline]
+    fn lookup(&self, key: &K) -> Option<&V> {
+        let idx = self.mphf.get(key)?;
+
+        // SAFETY: `idx` and `value_idx` are always within bounds (ensured during construction)
+        unsafe {
+            if access::get(&self.keys, idx) == key {
+                let value_idx = *access::get(&self.values_index, idx);
+                Some(access::get(&self.values_dict, value_idx))
+            } else {
+                None
+            }
+        }
+    }
+}
+
+impl<K, const B: usize, const S: usize, ST, H> Diffable for Set<K, B, S, ST, H>
+where
+    K: PartialEq + Hash,
+    ST: PrimInt + Unsigned,
+    H: Hasher + Default,
+{
+    type Key = K;
+    type Value = ();
+
+    #[inline]
+    fn entry(&self, idx: usize) -> Option<(&K, &())> {
+        self.keys.get(idx).map(|key| (key, &()))
+    }
+
+    #[inline]
+    fn lookup(&self, key: &K) -> Option<&()> {
+        let idx = self.mphf.get(key)?;
+
+        // SAFETY: `idx` is always within bounds (ensured during construction)
+        unsafe { (access::get(&self.keys, idx) == key).then_some(&()) }
+    }
+}
+
+/// Implement `Diffable` for `Archived` version of `MapWithDict` if feature is enabled, archived keys
+/// must hash the same way as original ones (e.g. `ArchivedString` hashes as `str`)
+#[cfg(feature = "rkyv_derive")]
+impl<K, V, const B: usize, const S: usize, ST, H> Diffable for ArchivedMapWithDict<K, V, B, S, ST, H>
+where
+    K: rkyv::Archive,
+    K::Archived: PartialEq + Hash,
+    V: rkyv::Archive,
+    V::Archived: PartialEq,
+    ST: PrimInt + Unsigned + rkyv::Archive<Archived = ST>,
+    H: Hasher + Default,
+{
+    type Key = K::Archived;
+    type Value = V::Archived;
+
+    #[inline]
+    fn entry(&self, idx: usize) -> Option<(&K::Archived, &V::Archived)> {
+        let key = self.keys.as_ref().get(idx)?;
+        Some((key, &self.values_dict[self.values_index[idx] as usize]))
+    }
+
+    #[inline]
+    fn lookup(&self, key: &K::Archived) -> Option<&V::Archived> {
+        let idx = self.mphf.get(key)?;
+
+        // SAFETY: `idx` and `value_idx` are always within bounds (ensured during construction)
+        unsafe {
+            if access::get(&self.keys, idx) == key {
+                let value_idx = *access::get(&self.values_index, idx) as usize;
+                Some(access::get(&self.values_dict, value_idx))
+            } else {
+                None
+            }
+        }
+    }
+}
+
+/// Implement `Diffable` for `Archived` version of `Set` if feature is enabled, archived keys must
+/// hash the same way as original ones (e.g. `ArchivedString` hashes as `str`)
+#[cfg(feature = "rkyv_derive")]
+impl<K, const B: usize, const S: usize, ST, H> Diffable for ArchivedSet<K, B, S, ST, H>
+where
+    K: rkyv::Archive,
+    K::Archived: PartialEq + Hash,
+    ST: PrimInt + Unsigned + rkyv::Archive<Archived = ST>,
+    H: Hasher + Default,
+{
+    type Key = K::Archived;
+    type Value = ();
+
+    #[inline]
+    fn entry(&self, idx: usize) -> Option<(&K::Archived, &())> {
+        self.keys.as_ref().get(idx).map(|key| (key, &()))
+    }
+
+    #[inline]
+    fn lookup(&self, key: &K::Archived) -> Option<&()> {
+        let idx = self.mphf.get(key)?;
+
+        // SAFETY: `idx` is always within bounds (ensured during construction)
+        unsafe { (access::get(&self.keys, idx) == key).then_some(&()) }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::MapFast;
+    use rand::{Rng, SeedableRng};
+    use rand_chacha::ChaCha8Rng;
+    use std::collections::{HashMap, HashSet};
+
+    /// Generates two random maps sharing most keys, with some of shared keys values changed.
+    fn gen_maps(items_num: usize) -> (HashMap<u64, u32>, HashMap<u64, u32>) {
+        let mut rng = ChaCha8Rng::seed_from_u64(123);
+        let a: HashMap<u64, u32> = (0..items_num).map(|_| (rng.gen(), rng.gen_range(1..=10))).collect();
+        let mut b = a.clone();
+        b.retain(|_, _| rng.gen_ratio(9, 10));
+        for value in b.values_mut() {
+            if rng.gen_ratio(1, 10) {
+                *value += 10;
+            }
+        }
+        b.extend((0..items_num / 10).map(|_| (rng.gen(), rng.gen_range(1..=10))));
+        (a, b)
+    }
+
+    /// Added, removed and changed keys with their values.
+    type Collected = (HashMap<u64, u32>, HashMap<u64, u32>, HashMap<u64, (u32, u32)>);
+
+    /// Collects differences of `a` and `b` into maps of added, removed and changed keys.
+    fn collect<T>(a: &T, b: &T) -> Collected
+    where
+        T: Diffable<Key = u64, Value = u32>,
+    {
+        let (mut added, mut removed, mut changed) = (HashMap::new(), HashMap::new(), HashMap::new());
+        for entry in diff(a, b) {
+            match entry {
+                DiffEntry::Added(&k, &v) => assert!(added.insert(k, v).is_none()),
+                DiffEntry::Removed(&k, &v) => assert!(removed.insert(k, v).is_none()),
+                DiffEntry::Changed(&k, &v1, &v2) => assert!(changed.insert(k, (v1, v2)).is_none()),
+            }
+        }
+        (added, removed, changed)
+    }
+
+    #[test]
+    fn test_diff_maps() {
+        let (original_a, original_b) = gen_maps(1000);
+        let a = MapWithDict::try_from(original_a.clone()).unwrap();
+        let b = MapWithDict::try_from(original_b.clone()).unwrap();
+
+        let (added, removed, changed) = collect(&a, &b);
+        for (k, v) in &original_b {
+            match original_a.get(k) {
+                None => assert_eq!(added.get(k), Some(v)),
+                Some(v1) if v1 != v => assert_eq!(changed.get(k), Some(&(*v1, *v))),
+                Some(_) => assert!(!changed.contains_key(k)),
+            }
+        }
+        for (k, v) in &original_a {
+            assert_eq!(removed.get(k), (!original_b.contains_key(k)).then_some(v));
+        }
+        assert!(!added.is_empty() && !removed.is_empty() && !changed.is_empty());
+
+        let summary = diff(&a, &b).summary();
+        assert_eq!(
+            summary,
+            DiffSummary { added: added.len(), removed: removed.len(), changed: changed.len() }
+        );
+        assert!(diff(&a, &a).summary().is_empty());
+
+        // structures built with different MPHF parameters are compared by keys and values
+        let fast_b = MapFast::from_hashmap_with_gamma(original_b, 1.5).unwrap();
+        assert_eq!(diff(&a, &fast_b).summary(), summary);
+    }
+
+    #[test]
+    fn test_diff_sets() {
+        let (original_a, original_b) = gen_maps(1000);
+        let keys_a: HashSet<u64> = original_a.into_keys().collect();
+        let keys_b: HashSet<u64> = original_b.into_keys().collect();
+        let a = Set::try_from(keys_a.clone()).unwrap();
+        let b = Set::try_from(keys_b.clone()).unwrap();
+
+        let summary = diff(&a, &b).summary();
+        assert_eq!(
+            summary,
+            DiffSummary {
+                added: keys_b.difference(&keys_a).count(),
+                removed: keys_a.difference(&keys_b).count(),
+                changed: 0,
+            }
+        );
+        for entry in diff(&a, &b) {
+            match entry {
+                DiffEntry::Added(k, _) => assert!(keys_b.contains(k) && !keys_a.contains(k)),
+                DiffEntry::Removed(k, _) => assert!(keys_a.contains(k) && !keys_b.contains(k)),
+                DiffEntry::Changed(..) => unreachable!(),
+            }
+        }
+    }
+
+    #[cfg(feature = "rkyv_derive")]
+    #[test]
+    fn test_diff_archived() {
+        let (original_a, original_b) = gen_maps(1000);
+        let a = MapWithDict::try_from(original_a).unwrap();
+        let b = MapWithDict::try_from(original_b).unwrap();
+        let a_bytes = rkyv::to_bytes::<_, 1024>(&a).unwrap();
+        let b_bytes = rkyv::to_bytes::<_, 1024>(&b).unwrap();
+        let archived_a = rkyv::check_archived_root::<MapWithDict<u64, u32>>(&a_bytes).unwrap();
+        let archived_b = rkyv::check_archived_root::<MapWithDict<u64, u32>>(&b_bytes).unwrap();
+
+        assert_eq!(collect(archived_a, archived_b), collect(&a, &b));
+
+        // archived string keys hash as `str`, so they are found in the other archive
+        let a = Set::try_from(HashSet::from(["a".to_string(), "b".to_string()])).unwrap();
+        let b = Set::try_from(HashSet::from(["b".to_string(), "c".to_string()])).unwrap();
+        let a_bytes = rkyv::to_bytes::<_, 1024>(&a).unwrap();
+        let b_bytes = rkyv::to_bytes::<_, 1024>(&b).unwrap();
+        let archived_a = rkyv::check_archived_root::<Set<String>>(&a_bytes).unwrap();
+        let archived_b = rkyv::check_archived_root::<Set<String>>(&b_bytes).unwrap();
+
+        let entries: Vec<_> = diff(archived_a, archived_b).collect();
+        assert_eq!(entries.len(), 2);
+        for entry in entries {
+            match entry {
+                DiffEntry::Added(k, _) => assert_eq!(k.as_str(), "c"),
+                DiffEntry::Removed(k, _) => assert_eq!(k.as_str(), "a"),
+                DiffEntry::Changed(..) => unreachable!(),
+            }
+        }
+    }
+}
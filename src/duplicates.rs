@@ -0,0 +1,106 @@
+//! A module providing `find_duplicate_keys`, reporting duplicate keys of an input collection.
+//!
+//! MPHF construction can never separate duplicate keys, whose hashes collide at every level, so it
+//! fails with `MphfError::MaxLevelsExceeded` without telling which keys are to blame. When a build
+//! fails, `find_duplicate_keys` scans the same input and reports the number of duplicates along with
+//! up to `cap` offending keys, so that they can be looked up in the source data. Only this
+//! diagnostic path requires keys to be `Debug + Clone`.
+
+use std::collections::HashSet;
+use std::error::Error;
+use std::fmt::{self, Debug, Display};
+use std::hash::Hash;
+
+/// Duplicate keys found by [`find_duplicate_keys`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DuplicateKeys<K> {
+    /// Number of duplicate occurrences, i.e. number of keys minus number of unique keys
+    pub count: usize,
+    /// Number of distinct keys occurring more than once
+    pub distinct: usize,
+    /// Up to `cap` distinct duplicated keys, in order of their first repeated occurrence
+    pub keys: Vec<K>,
+}
+
+impl<K: Debug> Display for DuplicateKeys<K> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "DuplicateKeys(count={}, keys={:?}", self.count, self.keys)?;
+        if self.keys.len() < self.distinct {
+            write!(f, ", ...")?;
+        }
+        write!(f, ")")
+    }
+}
+
+impl<K: Debug> Error for DuplicateKeys<K> {}
+
+/// Returns `Err` with the number of duplicate keys of `keys` and up to `cap` of them, `Ok` if all
+/// keys are unique.
+///
+/// # Examples
+/// ```
+/// # use entropy_map::{find_duplicate_keys, MphfError, Set};
+/// let keys = vec!["a", "b", "a", "c", "b", "a"];
+/// let set = Set::<&str>::from_iter_with_params(keys.clone(), 2.0);
+/// assert!(matches!(set, Err(MphfError::MaxLevelsExceeded)));
+///
+/// let duplicates = find_duplicate_keys(&keys, 10).unwrap_err();
+/// assert_eq!(duplicates.count, 3);
+/// assert_eq!(duplicates.distinct, 2);
+/// assert_eq!(duplicates.keys, ["a", "b"]);
+/// assert_eq!(duplicates.to_string(), r#"DuplicateKeys(count=3, keys=["a", "b"])"#);
+/// ```
+pub fn find_duplicate_keys<'a, K, I>(keys: I, cap: usize) -> Result<(), DuplicateKeys<K>>
+where
+    K: Hash + Eq + Debug + Clone + 'a,
+    I: IntoIterator<Item = &'a K>,
+{
+    let keys = keys.into_iter();
+    let mut seen = HashSet::with_capacity(keys.size_hint().0);
+    let mut reported = HashSet::new();
+    let mut duplicates = DuplicateKeys { count: 0, distinct: 0, keys: vec![] };
+
+    for key in keys {
+        if seen.insert(key) {
+            continue;
+        }
+        duplicates.count += 1;
+        if reported.insert(key) {
+            duplicates.distinct += 1;
+            if duplicates.keys.len() < cap {
+                duplicates.keys.push(key.clone());
+            }
+        }
+    }
+
+    if duplicates.count == 0 {
+        Ok(())
+    } else {
+        Err(duplicates)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_find_duplicate_keys() {
+        let keys: Vec<u64> = (0..1000).collect();
+        assert_eq!(find_duplicate_keys(&keys, 10), Ok(()));
+        assert_eq!(find_duplicate_keys::<u64, _>([], 10), Ok(()));
+
+        let keys: Vec<u64> = (0..1000).chain((0..100).rev()).chain(0..100).collect();
+        let duplicates = find_duplicate_keys(&keys, 3).unwrap_err();
+        assert_eq!(duplicates.count, 200);
+        assert_eq!(duplicates.distinct, 100);
+        assert_eq!(duplicates.keys, [99, 98, 97]);
+        assert_eq!(
+            duplicates.to_string(),
+            "DuplicateKeys(count=200, keys=[99, 98, 97], ...)"
+        );
+
+        let duplicates = find_duplicate_keys(&keys, 0).unwrap_err();
+        assert_eq!(duplicates, DuplicateKeys { count: 200, distinct: 100, keys: vec![] });
+    }
+}
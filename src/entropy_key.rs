@@ -0,0 +1,271 @@
+//! A module providing `EntropyKey`, a canonical platform-stable encoding of keys for hashing.
+//!
+//! MPHF hashes are baked into persisted structures, so a key must hash to the same value on every
+//! platform and Rust version the structure is queried with. `std::hash::Hash` doesn't guarantee
+//! that: `usize` and enum discriminants are hashed with platform-dependent width, integers are
+//! written in native endianness and the encoding of `str` and slices is an implementation detail
+//! of the standard library. `EntropyKey` types feed hashers with `Hasher::write` calls of fixed
+//! little endian bytes only:
+//! - integers as little endian bytes, with `usize` and `isize` widened to 64 bits,
+//! - `bool` as a single byte, `char` as a `u32`,
+//! - `str`, slices and `Vec` as a `u64` length followed by their elements,
+//! - arrays and tuples as their elements, `Option` as a `0` or `1` byte followed by the value.
+//!
+//! `#[derive(EntropyKey)]` from `entropy-map-macros` crate implements `EntropyKey` for structs by
+//! encoding their fields in declaration order, along with `Hash` delegating to it, so that the
+//! derived key types are hashed canonically by all structures of this crate:
+//!
+//! ```ignore
+//! use entropy_map::{Set, DEFAULT_GAMMA};
+//! use entropy_map_macros::EntropyKey;
+//!
+//! #[derive(EntropyKey, PartialEq, Eq)]
+//! struct Route {
+//!     prefix: [u8; 4],
+//!     prefix_len: u8,
+//!     region: String,
+//! }
+//!
+//! let route = Route { prefix: [10, 0, 0, 0], prefix_len: 8, region: "eu".to_string() };
+//! let set: Set<Route> = Set::from_iter_with_params([route], DEFAULT_GAMMA).unwrap();
+//! ```
+
+use std::hash::Hasher;
+
+/// Key type with a canonical platform-stable encoding, see the module documentation.
+///
+/// # Examples
+/// ```
+/// # use std::hash::{Hash, Hasher};
+/// # use entropy_map::{EntropyKey, Set, DEFAULT_GAMMA};
+/// #[derive(PartialEq, Eq)]
+/// struct Route {
+///     prefix: [u8; 4],
+///     prefix_len: usize,
+/// }
+///
+/// impl EntropyKey for Route {
+///     fn write_key<H: Hasher>(&self, state: &mut H) {
+///         self.prefix.write_key(state);
+///         self.prefix_len.write_key(state);
+///     }
+/// }
+///
+/// impl Hash for Route {
+///     fn hash<H: Hasher>(&self, state: &mut H) {
+///         self.write_key(state);
+///     }
+/// }
+///
+/// let route = Route { prefix: [10, 0, 0, 0], prefix_len: 8 };
+/// assert_eq!(route.key_bytes(), [10, 0, 0, 0, 8, 0, 0, 0, 0, 0, 0, 0]);
+///
+/// let set: Set<Route> = Set::from_iter_with_params([route], DEFAULT_GAMMA).unwrap();
+/// assert!(set.contains(&Route { prefix: [10, 0, 0, 0], prefix_len: 8 }));
+/// ```
+pub trait EntropyKey {
+    /// Feeds the canonical encoding of `self` into `state`, using `Hasher::write` only.
+    fn write_key<H: Hasher>(&self, state: &mut H);
+
+    /// Returns the canonical encoding of `self`.
+    fn key_bytes(&self) -> Vec<u8> {
+        let mut bytes = KeyBytes(Vec::new());
+        self.write_key(&mut bytes);
+        bytes.0
+    }
+}
+
+/// `Hasher` collecting written bytes, used to implement `EntropyKey::key_bytes`.
+struct KeyBytes(Vec<u8>);
+
+impl Hasher for KeyBytes {
+    #[inline]
+    fn write(&mut self, bytes: &[u8]) {
+        self.0.extend_from_slice(bytes);
+    }
+
+    #[inline]
+    fn finish(&self) -> u64 {
+        unreachable!("KeyBytes only collects the encoding")
+    }
+}
+
+/// Writes `len` of a variable-size value as `u64`.
+#[inline]
+fn write_len<H: Hasher>(len: usize, state: &mut H) {
+    state.write(&(len as u64).to_le_bytes());
+}
+
+macro_rules! impl_entropy_key_int {
+    ($($t:ty => $as:ty),* $(,)?) => {
+        $(
+            impl EntropyKey for $t {
+                #[inline]
+                fn write_key<H: Hasher>(&self, state: &mut H) {
+                    state.write(&(*self as $as).to_le_bytes());
+                }
+            }
+        )*
+    };
+}
+
+impl_entropy_key_int!(
+    u8 => u8, u16 => u16, u32 => u32, u64 => u64, u128 => u128, usize => u64,
+    i8 => i8, i16 => i16, i32 => i32, i64 => i64, i128 => i128, isize => i64,
+    bool => u8, char => u32,
+);
+
+impl EntropyKey for str {
+    #[inline]
+    fn write_key<H: Hasher>(&self, state: &mut H) {
+        write_len(self.len(), state);
+        state.write(self.as_bytes());
+    }
+}
+
+impl EntropyKey for String {
+    #[inline]
+    fn write_key<H: Hasher>(&self, state: &mut H) {
+        self.as_str().write_key(state);
+    }
+}
+
+impl<T: EntropyKey> EntropyKey for [T] {
+    #[inline]
+    fn write_key<H: Hasher>(&self, state: &mut H) {
+        write_len(self.len(), state);
+        for item in self {
+            item.write_key(state);
+        }
+    }
+}
+
+impl<T: EntropyKey> EntropyKey for Vec<T> {
+    #[inline]
+    fn write_key<H: Hasher>(&self, state: &mut H) {
+        self.as_slice().write_key(state);
+    }
+}
+
+impl<T: EntropyKey, const N: usize> EntropyKey for [T; N] {
+    #[inline]
+    fn write_key<H: Hasher>(&self, state: &mut H) {
+        for item in self {
+            item.write_key(state);
+        }
+    }
+}
+
+impl<T: EntropyKey> EntropyKey for Option<T> {
+    #[inline]
+    fn write_key<H: Hasher>(&self, state: &mut H) {
+        match self {
+            None => state.write(&[0]),
+            Some(value) => {
+                state.write(&[1]);
+                value.write_key(state);
+            }
+        }
+    }
+}
+
+impl<T: EntropyKey + ?Sized> EntropyKey for &T {
+    #[inline]
+    fn write_key<H: Hasher>(&self, state: &mut H) {
+        (**self).write_key(state);
+    }
+}
+
+impl<T: EntropyKey + ?Sized> EntropyKey for Box<T> {
+    #[inline]
+    fn write_key<H: Hasher>(&self, state: &mut H) {
+        (**self).write_key(state);
+    }
+}
+
+macro_rules! impl_entropy_key_tuple {
+    ($(($($t:ident . $idx:tt),+)),* $(,)?) => {
+        $(
+            impl<$($t: EntropyKey),+> EntropyKey for ($($t,)+) {
+                #[inline]
+                fn write_key<H: Hasher>(&self, state: &mut H) {
+                    $(self.$idx.write_key(state);)+
+                }
+            }
+        )*
+    };
+}
+
+impl_entropy_key_tuple!(
+    (A.0),
+    (A.0, B.1),
+    (A.0, B.1, C.2),
+    (A.0, B.1, C.2, D.3),
+    (A.0, B.1, C.2, D.3, E.4),
+    (A.0, B.1, C.2, D.3, E.4, F.5),
+);
+
+impl EntropyKey for () {
+    #[inline]
+    fn write_key<H: Hasher>(&self, _state: &mut H) {}
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mphf::hash_key_seeded;
+    use wyhash::WyHash;
+
+    #[test]
+    fn test_key_bytes() {
+        assert_eq!(0x0102u16.key_bytes(), [2, 1]);
+        assert_eq!((-2i32).key_bytes(), [0xfe, 0xff, 0xff, 0xff]);
+        assert_eq!(1usize.key_bytes(), 1u64.key_bytes());
+        assert_eq!((-1isize).key_bytes(), (-1i64).key_bytes());
+        assert_eq!(true.key_bytes(), [1]);
+        assert_eq!('a'.key_bytes(), [97, 0, 0, 0]);
+        assert_eq!("ab".key_bytes(), [2, 0, 0, 0, 0, 0, 0, 0, b'a', b'b']);
+        assert_eq!("ab".to_string().key_bytes(), "ab".key_bytes());
+        assert_eq!(vec![1u8, 2].key_bytes(), [2, 0, 0, 0, 0, 0, 0, 0, 1, 2]);
+        assert_eq!([1u8, 2].key_bytes(), [1, 2]);
+        assert_eq!(Some(3u8).key_bytes(), [1, 3]);
+        assert_eq!(None::<u8>.key_bytes(), [0]);
+        assert_eq!((1u8, "a").key_bytes(), [1, 1, 0, 0, 0, 0, 0, 0, 0, b'a']);
+        assert_eq!(().key_bytes(), [] as [u8; 0]);
+    }
+
+    #[test]
+    fn test_unambiguous_encoding() {
+        // length prefixes keep adjacent variable-size fields apart
+        assert_ne!(("a", "bc").key_bytes(), ("ab", "c").key_bytes());
+        assert_ne!(
+            (vec![1u8], vec![2u8, 3]).key_bytes(),
+            (vec![1u8, 2], vec![3u8]).key_bytes()
+        );
+    }
+
+    /// Key with `Hash` delegating to `EntropyKey`, as generated by `#[derive(EntropyKey)]`.
+    struct Key(u64, String);
+
+    impl EntropyKey for Key {
+        fn write_key<H: Hasher>(&self, state: &mut H) {
+            self.0.write_key(state);
+            self.1.write_key(state);
+        }
+    }
+
+    impl std::hash::Hash for Key {
+        fn hash<H: Hasher>(&self, state: &mut H) {
+            self.write_key(state);
+        }
+    }
+
+    #[test]
+    fn test_stable_hash() {
+        // hashes are persisted, so they must never change
+        assert_eq!(
+            hash_key_seeded::<WyHash, _>(0, &Key(1, "a".to_string())),
+            0x0ebf629942d1e21e
+        );
+    }
+}
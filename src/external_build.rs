@@ -0,0 +1,196 @@
+//! A module providing external-memory construction of `PartitionedMphf`.
+//!
+//! `from_slice` constructors need all keys in memory, and even `from_iter` ones materialize the
+//! 64-bit hashes of all keys at once, i.e. 8 GB per billion keys on top of the levels being built.
+//! [`PartitionedMphf::from_iter_external`] instead hashes keys as they stream from the iterator and
+//! spills the hashes of every partition to its own temporary file, then builds the partitions one
+//! by one from their files, so that memory usage is bounded by the hashes of a single partition,
+//! the spill buffers and the constructed structure.
+
+use std::fs::{self, File, OpenOptions};
+use std::hash::{Hash, Hasher};
+use std::io::{self, Read, Write};
+use std::path::{Path, PathBuf};
+use std::time::{Instant, SystemTime, UNIX_EPOCH};
+
+use num::{PrimInt, Unsigned};
+
+use crate::mphf::{hash_key_seeded, BuildPolicy, Mphf, MphfError};
+use crate::partitioned_mphf::{partition_idx, PartitionedMphf};
+use crate::report::BuildReport;
+
+/// Number of hashes buffered per partition before they are appended to its file.
+const SPILL_BUFFER_HASHES: usize = 4096;
+
+/// Errors that can occur during external-memory construction.
+#[derive(Debug)]
+pub enum ExternalBuildError {
+    /// Error when writing or reading the spilled hashes failed.
+    Io(io::Error),
+    /// Error when building the MPHF of a partition failed.
+    Mphf(MphfError),
+}
+
+impl From<io::Error> for ExternalBuildError {
+    #[inline]
+    fn from(err: io::Error) -> Self {
+        ExternalBuildError::Io(err)
+    }
+}
+
+impl From<MphfError> for ExternalBuildError {
+    #[inline]
+    fn from(err: MphfError) -> Self {
+        ExternalBuildError::Mphf(err)
+    }
+}
+
+/// Directory of spilled partition hashes, removed with its files when dropped.
+struct SpillDir {
+    /// Path of the directory
+    path: PathBuf,
+}
+
+impl SpillDir {
+    /// Creates a uniquely named directory within `parent`.
+    fn create(parent: &Path) -> io::Result<Self> {
+        let nanos = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |time| time.as_nanos());
+        let path = parent.join(format!("entropy-map-build-{}-{nanos}", std::process::id()));
+        fs::create_dir(&path)?;
+        Ok(SpillDir { path })
+    }
+
+    /// Returns the path of the file holding hashes of partition `idx`.
+    fn partition_path(&self, idx: usize) -> PathBuf {
+        self.path.join(format!("partition-{idx}"))
+    }
+
+    /// Appends `hashes` to the file of partition `idx`.
+    fn append(&self, idx: usize, hashes: &[u64]) -> io::Result<()> {
+        let bytes: Vec<u8> = hashes.iter().flat_map(|hash| hash.to_le_bytes()).collect();
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(self.partition_path(idx))?;
+        file.write_all(&bytes)
+    }
+
+    /// Reads and removes the hashes of partition `idx`, empty if none were spilled.
+    fn take(&self, idx: usize) -> io::Result<Vec<u64>> {
+        let path = self.partition_path(idx);
+        let mut bytes = vec![];
+        match File::open(&path) {
+            Ok(mut file) => file.read_to_end(&mut bytes)?,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(vec![]),
+            Err(err) => return Err(err),
+        };
+        fs::remove_file(&path)?;
+        Ok(bytes
+            .chunks_exact(8)
+            .map(|chunk| u64::from_le_bytes(chunk.try_into().unwrap()))
+            .collect())
+    }
+}
+
+impl Drop for SpillDir {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.path);
+    }
+}
+
+impl<const B: usize, const S: usize, ST: PrimInt + Unsigned, H: Hasher + Default> PartitionedMphf<B, S, ST, H> {
+    /// Initializes `PartitionedMphf` using `keys` split into `partitions` partitions, spilling
+    /// hashes of every partition to a temporary file within `dir` (the system temporary directory
+    /// if `None`) and building partitions one by one as configured by `policy`, see the
+    /// [`external_build`](crate::external_build) module documentation.
+    ///
+    /// Partitions should be small enough for a single one to be built in memory, e.g. a partition
+    /// per 2^24 keys takes ~128 MB of hashes at once, and the spill buffers take 32 KB per
+    /// partition. Spilled files are removed once the construction is done, including on failure.
+    /// The MPHF is the same as built by [`PartitionedMphf::from_slice_with_policy`].
+    ///
+    /// # Examples
+    /// ```
+    /// # use entropy_map::{BuildPolicy, PartitionedMphf};
+    /// let keys = (0..10_000u64).map(|i| format!("key-{i}"));
+    /// let (mphf, report) = PartitionedMphf::<32, 8>::from_iter_external(keys, &BuildPolicy::default(), 4, None).unwrap();
+    /// assert_eq!(report.keys(), 10_000);
+    /// assert!(mphf.get("key-42").unwrap() < 10_000);
+    /// ```
+    pub fn from_iter_external<K: Hash, I: IntoIterator<Item = K>>(
+        keys: I,
+        policy: &BuildPolicy,
+        partitions: usize,
+        dir: Option<&Path>,
+    ) -> Result<(Self, BuildReport), ExternalBuildError> {
+        let t0 = Instant::now();
+        let partition_bits = Self::partition_bits(partitions);
+        let partitions = 1 << partition_bits;
+        let hash_seed = policy.hash_seed;
+        let spill_dir = SpillDir::create(&dir.map_or_else(std::env::temp_dir, Path::to_path_buf))?;
+
+        let mut buffers = vec![Vec::with_capacity(SPILL_BUFFER_HASHES); partitions];
+        let mut keys_per_partition = vec![0; partitions];
+        for key in keys {
+            let hash = hash_key_seeded::<H, _>(hash_seed, &key);
+            let idx = partition_idx(hash, partition_bits);
+            buffers[idx].push(hash);
+            keys_per_partition[idx] += 1;
+            if buffers[idx].len() == SPILL_BUFFER_HASHES {
+                spill_dir.append(idx, &buffers[idx])?;
+                buffers[idx].clear();
+            }
+        }
+
+        let mut built = Vec::with_capacity(partitions);
+        for (idx, buffer) in buffers.into_iter().enumerate() {
+            let mut hashes = spill_dir.take(idx)?;
+            hashes.extend_from_slice(&buffer);
+            drop(buffer);
+            built.push(Mphf::from_hashes_with_policy(&hashes, policy)?);
+        }
+
+        Ok(Self::from_partitions(
+            built,
+            keys_per_partition.into_iter(),
+            partition_bits,
+            hash_seed,
+            t0,
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_from_iter_external() {
+        let keys: Vec<u64> = (0..100_000).collect();
+        let dir = std::env::temp_dir().join(format!("entropy-map-external-test-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+
+        let policy = BuildPolicy::keyed();
+        let (mphf, report) = PartitionedMphf::<32, 8>::from_iter_external(keys.iter(), &policy, 8, Some(&dir)).unwrap();
+        let (expected, expected_report) = PartitionedMphf::<32, 8>::from_slice_with_policy(&keys, &policy, 8).unwrap();
+        assert_eq!(report.keys(), keys.len());
+        assert_eq!(report.bits_per_key, expected_report.bits_per_key);
+        assert_eq!(mphf.len(), keys.len());
+        assert!(keys.iter().all(|key| mphf.get(key) == expected.get(key)));
+
+        // spilled files are removed, on failure too
+        assert_eq!(fs::read_dir(&dir).unwrap().count(), 0);
+        let res = PartitionedMphf::<32, 8>::from_iter_external(keys.iter(), &BuildPolicy::fixed(0.5), 8, Some(&dir));
+        assert!(matches!(
+            res,
+            Err(ExternalBuildError::Mphf(MphfError::InvalidGammaParameter))
+        ));
+        assert_eq!(fs::read_dir(&dir).unwrap().count(), 0);
+        fs::remove_dir(&dir).unwrap();
+
+        let res = PartitionedMphf::<32, 8>::from_iter_external(keys.iter(), &policy, 8, Some(&dir));
+        assert!(matches!(res, Err(ExternalBuildError::Io(_))));
+    }
+}
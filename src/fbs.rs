@@ -0,0 +1,308 @@
+//! A module providing [FlatBuffers](https://flatbuffers.dev/) export of `MapWithDict`.
+//!
+//! `rkyv` archives are effectively readable from Rust only. Maps exported with
+//! [`MapWithDict::to_flatbuffers`] follow the published [`FBS_SCHEMA`] (also available as
+//! `schema/entropy_map.fbs` in the repository), which documents the query algorithm, so that
+//! independent readers can be implemented in any language supported by FlatBuffers.
+
+use std::hash::Hasher;
+
+use flatbuffers::{FlatBufferBuilder, ForwardsUOffset, TableFinishedWIPOffset, Vector, WIPOffset};
+use num::{PrimInt, Unsigned};
+
+use crate::map_with_dict::MapWithDict;
+use crate::mphf::Mphf;
+use crate::mphf_extras::ExtrasLayout;
+use crate::rank::RankedBits;
+
+/// FlatBuffers schema of exported maps.
+pub const FBS_SCHEMA: &str = include_str!("../schema/entropy_map.fbs");
+
+/// File identifier of exported maps, see [`flatbuffers::buffer_has_identifier`].
+pub const FBS_FILE_IDENTIFIER: &str = "ENTM";
+
+/// Field slots (vtable offsets) of `Mphf` table.
+mod mphf_slots {
+    pub const B: u16 = 4;
+    pub const S: u16 = 6;
+    pub const LEVEL_GROUPS: u16 = 8;
+    pub const GROUP_SEEDS: u16 = 10;
+    pub const BITS: u16 = 12;
+    pub const L12_RANKS: u16 = 14;
+}
+
+/// Field slots (vtable offsets) of `Map` table.
+mod map_slots {
+    pub const MPHF: u16 = 4;
+    pub const UINT_KEYS: u16 = 6;
+    pub const STRING_KEYS: u16 = 8;
+    pub const VALUES_INDEX: u16 = 10;
+    pub const UINT_VALUES: u16 = 12;
+    pub const STRING_VALUES: u16 = 14;
+}
+
+/// A value of a key or value type as represented in the FlatBuffers schema.
+pub enum FbsValue<'a> {
+    /// Unsigned integer stored in `uint_*` vectors
+    Uint(u64),
+    /// String stored in `string_*` vectors
+    String(&'a str),
+}
+
+/// Types which can be exported as keys or values, all values of a type must be represented by
+/// the same `FbsValue` variant.
+pub trait FbsItem {
+    /// Returns the FlatBuffers representation of the value.
+    fn to_fbs(&self) -> FbsValue<'_>;
+}
+
+macro_rules! impl_fbs_item_uint {
+    ($($t:ty),*) => {
+        $(
+            impl FbsItem for $t {
+                #[inline]
+                fn to_fbs(&self) -> FbsValue<'_> {
+                    FbsValue::Uint(*self as u64)
+                }
+            }
+        )*
+    };
+}
+
+impl_fbs_item_uint!(u8, u16, u32, u64);
+
+impl FbsItem for String {
+    #[inline]
+    fn to_fbs(&self) -> FbsValue<'_> {
+        FbsValue::String(self)
+    }
+}
+
+impl FbsItem for &str {
+    #[inline]
+    fn to_fbs(&self) -> FbsValue<'_> {
+        FbsValue::String(self)
+    }
+}
+
+/// Errors that can occur when exporting to FlatBuffers.
+#[derive(Debug, PartialEq)]
+pub enum FbsError {
+    /// Error when values of a type are represented by different `FbsValue` variants.
+    MixedValueKinds,
+    /// Error when the map was built with a non-zero hash or build seed, which the schema doesn't
+    /// support.
+    KeyedHashing,
+    /// Error when the MPHF has a fallback table, which the schema doesn't support.
+    Fallback,
+}
+
+impl<K, V, const B: usize, const S: usize, ST, H, E> MapWithDict<K, V, B, S, ST, H, E>
+where
+    K: FbsItem,
+    V: FbsItem,
+    ST: PrimInt + Unsigned,
+    H: Hasher + Default,
+    E: ExtrasLayout,
+{
+    /// Exports the map to FlatBuffers format described by [`FBS_SCHEMA`].
+    ///
+    /// # Examples
+    /// ```
+    /// # use std::collections::HashMap;
+    /// # use entropy_map::{MapWithDict, FBS_FILE_IDENTIFIER};
+    /// let map = MapWithDict::try_from(HashMap::from([(1u64, "a".to_string()), (2, "b".to_string())])).unwrap();
+    /// let bytes = map.to_flatbuffers().unwrap();
+    /// assert!(flatbuffers::buffer_has_identifier(&bytes, FBS_FILE_IDENTIFIER, false));
+    /// ```
+    pub fn to_flatbuffers(&self) -> Result<Vec<u8>, FbsError> {
+        if self.mphf.hash_seed() != 0 || self.mphf.build_seed() != 0 {
+            return Err(FbsError::KeyedHashing);
+        }
+        if self.mphf.fallback_len() != 0 {
+            return Err(FbsError::Fallback);
+        }
+
+        let mut builder = FlatBufferBuilder::new();
+
+        let mphf = write_mphf(&mut builder, &self.mphf);
+        let keys = write_items(&mut builder, &self.keys)?;
+        let values_index = builder.create_vector_from_iter(self.values_index.iter().map(|&idx| idx as u64));
+        let values = write_items(&mut builder, &self.values_dict)?;
+
+        let table = builder.start_table();
+        builder.push_slot_always(map_slots::MPHF, mphf);
+        push_items(&mut builder, keys, map_slots::UINT_KEYS, map_slots::STRING_KEYS);
+        builder.push_slot_always(map_slots::VALUES_INDEX, values_index);
+        push_items(&mut builder, values, map_slots::UINT_VALUES, map_slots::STRING_VALUES);
+        let root = builder.end_table(table);
+
+        builder.finish(root, Some(FBS_FILE_IDENTIFIER));
+        Ok(builder.finished_data().to_vec())
+    }
+}
+
+/// Writes `Mphf` table returning its offset.
+fn write_mphf<'b, const B: usize, const S: usize, ST, H, E>(
+    builder: &mut FlatBufferBuilder<'b>,
+    mphf: &Mphf<B, S, ST, H, RankedBits, E>,
+) -> WIPOffset<TableFinishedWIPOffset>
+where
+    ST: PrimInt + Unsigned,
+    H: Hasher + Default,
+    E: ExtrasLayout,
+{
+    let ranked_bits = &mphf.ranked_bits;
+
+    let level_groups = builder.create_vector(&mphf.level_groups);
+    let group_seeds = builder.create_vector_from_iter(mphf.group_seeds.iter().map(|seed| seed.to_u32().unwrap()));
+    let bits = builder.create_vector(&ranked_bits.bits);
+    let l12_ranks: Vec<u64> = ranked_bits
+        .l12_rank_values()
+        .flat_map(|l12_rank| [l12_rank as u64, (l12_rank >> 64) as u64])
+        .collect();
+    let l12_ranks = builder.create_vector(&l12_ranks);
+
+    let table = builder.start_table();
+    builder.push_slot_always(mphf_slots::B, B as u32);
+    builder.push_slot_always(mphf_slots::S, S as u32);
+    builder.push_slot_always(mphf_slots::LEVEL_GROUPS, level_groups);
+    builder.push_slot_always(mphf_slots::GROUP_SEEDS, group_seeds);
+    builder.push_slot_always(mphf_slots::BITS, bits);
+    builder.push_slot_always(mphf_slots::L12_RANKS, l12_ranks);
+    builder.end_table(table)
+}
+
+/// Offset of a vector of exported items of either kind.
+enum ItemsOffset<'b> {
+    /// Offset of `[uint64]` vector
+    Uint(WIPOffset<Vector<'b, u64>>),
+    /// Offset of `[string]` vector
+    String(WIPOffset<Vector<'b, ForwardsUOffset<&'b str>>>),
+}
+
+/// Writes `items` as either `[uint64]` or `[string]` vector depending on their `FbsValue` kind.
+fn write_items<'b, T: FbsItem>(builder: &mut FlatBufferBuilder<'b>, items: &[T]) -> Result<ItemsOffset<'b>, FbsError> {
+    let uints: Option<Vec<u64>> = items
+        .iter()
+        .map(|item| match item.to_fbs() {
+            FbsValue::Uint(v) => Some(v),
+            FbsValue::String(_) => None,
+        })
+        .collect();
+
+    if let Some(uints) = uints {
+        return Ok(ItemsOffset::Uint(builder.create_vector(&uints)));
+    }
+
+    let strings = items
+        .iter()
+        .map(|item| match item.to_fbs() {
+            FbsValue::String(s) => Ok(builder.create_string(s)),
+            FbsValue::Uint(_) => Err(FbsError::MixedValueKinds),
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+
+    Ok(ItemsOffset::String(builder.create_vector(&strings)))
+}
+
+/// Pushes vector of items into either `uint_slot` or `string_slot` of the current table.
+fn push_items<'b>(builder: &mut FlatBufferBuilder<'b>, items: ItemsOffset<'b>, uint_slot: u16, string_slot: u16) {
+    match items {
+        ItemsOffset::Uint(offset) => builder.push_slot_always(uint_slot, offset),
+        ItemsOffset::String(offset) => builder.push_slot_always(string_slot, offset),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use flatbuffers::Table;
+    use rand::{Rng, SeedableRng};
+    use rand_chacha::ChaCha8Rng;
+    use std::collections::HashMap;
+
+    /// Reads `[uint64]` or `[uint32]` vector at `slot` of `table`.
+    fn read_uints<'a, T: flatbuffers::Follow<'a, Inner = T> + 'a>(table: &Table<'a>, slot: u16) -> Vec<T> {
+        unsafe { table.get::<ForwardsUOffset<Vector<'a, T>>>(slot, None) }
+            .unwrap()
+            .iter()
+            .collect()
+    }
+
+    #[test]
+    fn test_to_flatbuffers() {
+        let mut rng = ChaCha8Rng::seed_from_u64(123);
+        let original_map: HashMap<u64, String> = (0..1000)
+            .map(|_| (rng.gen(), rng.gen_range(1..=10).to_string()))
+            .collect();
+        let map = MapWithDict::try_from(original_map.clone()).unwrap();
+
+        let bytes = map.to_flatbuffers().unwrap();
+        assert!(flatbuffers::buffer_has_identifier(&bytes, FBS_FILE_IDENTIFIER, false));
+
+        let root = unsafe { flatbuffers::root_unchecked::<Table>(&bytes) };
+        let mphf = unsafe { root.get::<ForwardsUOffset<Table>>(map_slots::MPHF, None) }.unwrap();
+
+        assert_eq!(unsafe { mphf.get::<u32>(mphf_slots::B, None) }, Some(32));
+        assert_eq!(unsafe { mphf.get::<u32>(mphf_slots::S, None) }, Some(8));
+        assert_eq!(
+            read_uints::<u32>(&mphf, mphf_slots::LEVEL_GROUPS),
+            &*map.mphf.level_groups
+        );
+        assert_eq!(read_uints::<u64>(&mphf, mphf_slots::BITS), &*map.mphf.ranked_bits.bits);
+
+        let keys = read_uints::<u64>(&root, map_slots::UINT_KEYS);
+        let values_index = read_uints::<u64>(&root, map_slots::VALUES_INDEX);
+        let values: Vec<&str> =
+            unsafe { root.get::<ForwardsUOffset<Vector<ForwardsUOffset<&str>>>>(map_slots::STRING_VALUES, None) }
+                .unwrap()
+                .iter()
+                .collect();
+        assert!(unsafe { root.get::<ForwardsUOffset<Vector<u64>>>(map_slots::STRING_KEYS, None) }.is_none());
+
+        let level_groups = read_uints::<u32>(&mphf, mphf_slots::LEVEL_GROUPS);
+        let group_seeds = read_uints::<u32>(&mphf, mphf_slots::GROUP_SEEDS);
+        let bits = read_uints::<u64>(&mphf, mphf_slots::BITS);
+        let l12_ranks = read_uints::<u64>(&mphf, mphf_slots::L12_RANKS);
+
+        // query algorithm as documented in the schema
+        let get = |key: &u64| -> Option<&str> {
+            let hash = crate::mphf::hash_key_seeded::<wyhash::WyHash, _>(0, key);
+            let mut groups_before = 0u64;
+            for (level, &groups) in level_groups.iter().enumerate() {
+                let x = ((hash as u128) ^ (level as u128)).wrapping_mul(0x5851f42d4c957f2d);
+                let level_hash = (x as u64) ^ ((x >> 64) as u64);
+                let group_idx = groups_before + (((level_hash as u32 as u64) * groups as u64) >> 32);
+                let mut y = (level_hash as u32) ^ group_seeds[group_idx as usize];
+                y = (y ^ (y >> 16)).wrapping_mul(0x85ebca6b);
+                y = (y ^ (y >> 13)).wrapping_mul(0xc2b2ae35);
+                y ^= y >> 16;
+                let i = group_idx * 32 + (((y as u64) * 32) >> 32);
+
+                if (bits[(i / 64) as usize] >> (i % 64)) & 1 == 1 {
+                    let l1 = (i / 4096) as usize;
+                    let l12 = (l12_ranks[2 * l1] as u128) | ((l12_ranks[2 * l1 + 1] as u128) << 64);
+                    let l1_rank = (l12 & 0xFFFFFFFFFFF) as u64;
+                    let l2_rank = ((l12 >> (32 + 12 * ((i % 4096) / 512))) & 0xFFF) as u64;
+                    let block_rank = (i / 512 * 512..i)
+                        .filter(|&j| (bits[(j / 64) as usize] >> (j % 64)) & 1 == 1)
+                        .count() as u64;
+                    let idx = (l1_rank + l2_rank + block_rank) as usize;
+
+                    return (keys[idx] == *key).then(|| values[values_index[idx] as usize]);
+                }
+                groups_before += groups as u64;
+            }
+            None
+        };
+
+        assert_eq!(keys.len(), original_map.len());
+        for (key, value) in &original_map {
+            assert_eq!(get(key), Some(value.as_str()));
+        }
+        for key in 0..1000 {
+            assert_eq!(get(&key), original_map.get(&key).map(|v| v.as_str()));
+        }
+    }
+}
@@ -0,0 +1,229 @@
+//! A module providing C functions querying archives and `to_c_header` embedding archives into C.
+//!
+//! Small lookup tables built by a Rust pipeline can be baked into firmware images by serializing
+//! them with `rkyv` and embedding the archive as a static byte array of a generated C header. The
+//! header declares the C functions of this module, which query archived `MapWithDict<String,
+//! String>` and `Set<String>` (the structures built by the `entropy-map` command line tool), and
+//! defines `static inline` accessors passing the embedded array to them. The functions are exported
+//! by linking the crate built as a static library, e.g. with
+//! `cargo rustc --release --features ffi --crate-type staticlib`.
+//!
+//! The functions don't validate archives, which are expected to come from a trusted pipeline, e.g.
+//! checked when generating the header. Archives built on one target can only be queried on targets
+//! with the same endianness.
+
+use std::fmt::Write;
+use std::slice;
+
+use crate::map_with_dict::MapWithDict;
+use crate::set::Set;
+
+/// Kind of structure stored in an archive embedded by [`to_c_header`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CHeaderKind {
+    /// `MapWithDict<String, String>`, queried with `entropy_map_map_get`
+    Map,
+    /// `Set<String>`, queried with `entropy_map_set_contains`
+    Set,
+}
+
+/// Returns a pointer to the value of the key of `key_len` bytes at `key` in the archived
+/// `MapWithDict<String, String>` of `archive_len` bytes at `archive`, storing the value length in
+/// bytes to `value_len`. Returns a null pointer if the key is not present in the map.
+///
+/// # Safety
+/// `archive` must point to a valid archive of `MapWithDict<String, String>` aligned to at least 16
+/// bytes, `key` must point to `key_len` readable bytes and `value_len` must be writable. The
+/// returned value is borrowed from the archive and isn't null-terminated.
+#[no_mangle]
+pub unsafe extern "C" fn entropy_map_map_get(
+    archive: *const u8,
+    archive_len: usize,
+    key: *const u8,
+    key_len: usize,
+    value_len: *mut usize,
+) -> *const u8 {
+    let map = rkyv::archived_root::<MapWithDict<String, String>>(slice::from_raw_parts(archive, archive_len));
+    let value = std::str::from_utf8(slice::from_raw_parts(key, key_len))
+        .ok()
+        .and_then(|key| map.get(key));
+
+    match value {
+        Some(value) => {
+            *value_len = value.len();
+            value.as_ptr()
+        }
+        None => std::ptr::null(),
+    }
+}
+
+/// Returns `true` if the key of `key_len` bytes at `key` is present in the archived `Set<String>`
+/// of `archive_len` bytes at `archive`.
+///
+/// # Safety
+/// `archive` must point to a valid archive of `Set<String>` aligned to at least 16 bytes and `key`
+/// must point to `key_len` readable bytes.
+#[no_mangle]
+pub unsafe extern "C" fn entropy_map_set_contains(
+    archive: *const u8,
+    archive_len: usize,
+    key: *const u8,
+    key_len: usize,
+) -> bool {
+    let set = rkyv::archived_root::<Set<String>>(slice::from_raw_parts(archive, archive_len));
+    std::str::from_utf8(slice::from_raw_parts(key, key_len)).is_ok_and(|key| set.contains(key))
+}
+
+/// Returns a C header embedding `archive` of the given `kind` as `<name>_archive` static byte array,
+/// along with a `<name>_get` (maps) or `<name>_contains` (sets) accessor querying it with the C
+/// functions of this module, see the module documentation.
+///
+/// # Panics
+/// Panics if `name` is not a valid C identifier.
+///
+/// # Examples
+/// ```
+/// # use entropy_map::{to_c_header, CHeaderKind, Set, DEFAULT_GAMMA};
+/// let set = Set::<String>::from_iter_with_params(["gb", "pt"].map(String::from), DEFAULT_GAMMA).unwrap();
+/// let archive = rkyv::to_bytes::<_, 1024>(&set).unwrap();
+/// let header = to_c_header(&archive, "countries", CHeaderKind::Set);
+/// assert!(header.contains("static inline bool countries_contains(const char *key, size_t key_len)"));
+/// ```
+pub fn to_c_header(archive: &[u8], name: &str, kind: CHeaderKind) -> String {
+    assert!(is_c_identifier(name), "{name:?} is not a valid C identifier");
+
+    let guard = format!("ENTROPY_MAP_{}_H", name.to_ascii_uppercase());
+    let mut header = String::new();
+    // writing to a `String` never fails
+    let _ = writeln!(
+        header,
+        "/* Generated by entropy-map {}, do not edit. */",
+        env!("CARGO_PKG_VERSION")
+    );
+    let _ = writeln!(header, "#ifndef {guard}\n#define {guard}\n");
+    let _ = writeln!(
+        header,
+        "#include <stdbool.h>\n#include <stddef.h>\n#include <stdint.h>\n"
+    );
+
+    match kind {
+        CHeaderKind::Map => {
+            let _ = writeln!(
+                header,
+                "const uint8_t *entropy_map_map_get(const uint8_t *archive, size_t archive_len, \
+                 const uint8_t *key, size_t key_len, size_t *value_len);\n"
+            );
+        }
+        CHeaderKind::Set => {
+            let _ = writeln!(
+                header,
+                "bool entropy_map_set_contains(const uint8_t *archive, size_t archive_len, \
+                 const uint8_t *key, size_t key_len);\n"
+            );
+        }
+    }
+
+    let _ = writeln!(
+        header,
+        "static _Alignas(16) const uint8_t {name}_archive[{}] = {{",
+        archive.len()
+    );
+    for line in archive.chunks(16) {
+        let bytes: Vec<String> = line.iter().map(|byte| format!("0x{byte:02x},")).collect();
+        let _ = writeln!(header, "    {}", bytes.join(" "));
+    }
+    let _ = writeln!(header, "}};\n");
+
+    match kind {
+        CHeaderKind::Map => {
+            let _ = writeln!(
+                header,
+                "/* Returns the value of the key (not null-terminated, `*value_len` bytes), or NULL. */\n\
+                 static inline const char *{name}_get(const char *key, size_t key_len, size_t *value_len) {{\n    \
+                 return (const char *)entropy_map_map_get({name}_archive, sizeof({name}_archive), \
+                 (const uint8_t *)key, key_len, value_len);\n}}\n"
+            );
+        }
+        CHeaderKind::Set => {
+            let _ = writeln!(
+                header,
+                "/* Returns true if the key is present in the set. */\n\
+                 static inline bool {name}_contains(const char *key, size_t key_len) {{\n    \
+                 return entropy_map_set_contains({name}_archive, sizeof({name}_archive), \
+                 (const uint8_t *)key, key_len);\n}}\n"
+            );
+        }
+    }
+
+    let _ = writeln!(header, "#endif /* {guard} */");
+    header
+}
+
+/// Returns `true` if `name` is a valid C identifier, as required by [`to_c_header`].
+pub fn is_c_identifier(name: &str) -> bool {
+    let mut chars = name.chars();
+    chars.next().is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mphf::DEFAULT_GAMMA;
+    use std::ptr;
+
+    /// Parses bytes of the archive array back from a generated `header`.
+    fn parse_archive(header: &str) -> Vec<u8> {
+        let start = header.find("_archive[").unwrap();
+        let start = start + header[start..].find('{').unwrap() + 1;
+        let end = start + header[start..].find('}').unwrap();
+        header[start..end]
+            .split(',')
+            .map(str::trim)
+            .filter(|byte| !byte.is_empty())
+            .map(|byte| u8::from_str_radix(byte.trim_start_matches("0x"), 16).unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn test_map() {
+        let entries = [("http", "80"), ("https", "443")].map(|(k, v)| (k.to_string(), v.to_string()));
+        let map = MapWithDict::<String, String>::from_iter_with_params(entries, DEFAULT_GAMMA).unwrap();
+        let archive = rkyv::to_bytes::<_, 1024>(&map).unwrap();
+
+        let header = to_c_header(&archive, "ports", CHeaderKind::Map);
+        assert!(header.contains("#ifndef ENTROPY_MAP_PORTS_H"));
+        assert!(header.contains("static inline const char *ports_get("));
+        assert_eq!(parse_archive(&header), archive.as_slice());
+
+        let mut value_len = 0;
+        let get = |key: &[u8], value_len: &mut usize| unsafe {
+            entropy_map_map_get(archive.as_ptr(), archive.len(), key.as_ptr(), key.len(), value_len)
+        };
+        let value = get(b"https", &mut value_len);
+        assert_eq!(unsafe { slice::from_raw_parts(value, value_len) }, b"443");
+        assert_eq!(get(b"ftp", &mut value_len), ptr::null());
+        assert_eq!(get(&[0xff], &mut value_len), ptr::null());
+    }
+
+    #[test]
+    fn test_set() {
+        let set = Set::<String>::from_iter_with_params(["gb", "pt"].map(String::from), DEFAULT_GAMMA).unwrap();
+        let archive = rkyv::to_bytes::<_, 1024>(&set).unwrap();
+
+        let header = to_c_header(&archive, "countries", CHeaderKind::Set);
+        assert!(header.contains("static inline bool countries_contains("));
+        assert_eq!(parse_archive(&header), archive.as_slice());
+
+        let contains =
+            |key: &[u8]| unsafe { entropy_map_set_contains(archive.as_ptr(), archive.len(), key.as_ptr(), key.len()) };
+        assert!(contains(b"pt"));
+        assert!(!contains(b"us"));
+    }
+
+    #[test]
+    #[should_panic(expected = "\"1st\" is not a valid C identifier")]
+    fn test_invalid_name() {
+        to_c_header(&[], "1st", CHeaderKind::Set);
+    }
+}
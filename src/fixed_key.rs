@@ -0,0 +1,179 @@
+//! A module providing `FixedKey`, a fixed-size byte array key with branchless SIMD-friendly
+//! equality.
+//!
+//! Keys like hashes or UUIDs (e.g. `[u8; 16]` or `[u8; 32]`) are stored contiguously in the keys
+//! array of `MapWithDict` or `Set`, but every query still verifies the found key with a generic
+//! `PartialEq`, which may compile into a byte-wise, early-exit comparison. `FixedKey` compares keys
+//! by XOR-ing and OR-ing them in 16-byte chunks without any branches, letting the compiler use
+//! SIMD loads and a single comparison at the end. It hashes exactly like the wrapped array, so
+//! structures keyed by `FixedKey<N>` can be queried with plain `[u8; N]` keys.
+
+use std::borrow::Borrow;
+use std::hash::{Hash, Hasher};
+
+/// Fixed-size byte array key with branchless equality, see the module documentation.
+///
+/// # Examples
+/// ```
+/// # use entropy_map::{FixedKey, Set, DEFAULT_GAMMA};
+/// let set = Set::<FixedKey<16>>::from_iter_with_params([FixedKey([1; 16]), FixedKey([2; 16])], DEFAULT_GAMMA)
+///     .unwrap();
+/// assert!(set.contains(&[1; 16]));
+/// assert!(!set.contains(&[3; 16]));
+/// ```
+#[derive(Debug, Clone, Copy, Eq, PartialOrd, Ord)]
+#[cfg_attr(feature = "rkyv_derive", derive(rkyv::Archive, rkyv::Deserialize, rkyv::Serialize))]
+#[cfg_attr(feature = "rkyv_derive", archive_attr(derive(rkyv::CheckBytes)))]
+#[repr(transparent)]
+pub struct FixedKey<const N: usize>(pub [u8; N]);
+
+/// Size of the chunks compared at once, i.e. the width of a SIMD register on most targets.
+const CHUNK_LEN: usize = 16;
+
+/// Returns `true` if `a` and `b` are equal, comparing all their bytes without early exits.
+#[inline(always)]
+fn eq_bytes<const N: usize>(a: &[u8; N], b: &[u8; N]) -> bool {
+    let a_chunks = a.chunks_exact(CHUNK_LEN);
+    let b_chunks = b.chunks_exact(CHUNK_LEN);
+    let a_tail = a_chunks.remainder();
+    let b_tail = b_chunks.remainder();
+
+    let mut diff = 0u128;
+    for (a_chunk, b_chunk) in a_chunks.zip(b_chunks) {
+        let a_chunk = u128::from_ne_bytes(a_chunk.try_into().unwrap());
+        let b_chunk = u128::from_ne_bytes(b_chunk.try_into().unwrap());
+        diff |= a_chunk ^ b_chunk;
+    }
+    for (a_byte, b_byte) in a_tail.iter().zip(b_tail) {
+        diff |= (a_byte ^ b_byte) as u128;
+    }
+
+    diff == 0
+}
+
+impl<const N: usize> PartialEq for FixedKey<N> {
+    #[inline]
+    fn eq(&self, other: &Self) -> bool {
+        eq_bytes(&self.0, &other.0)
+    }
+}
+
+impl<const N: usize> PartialEq<[u8; N]> for FixedKey<N> {
+    #[inline]
+    fn eq(&self, other: &[u8; N]) -> bool {
+        eq_bytes(&self.0, other)
+    }
+}
+
+/// Hashes exactly like the wrapped array, as required by `Borrow<[u8; N]>`.
+impl<const N: usize> Hash for FixedKey<N> {
+    #[inline]
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.0.hash(state);
+    }
+}
+
+impl<const N: usize> Borrow<[u8; N]> for FixedKey<N> {
+    #[inline]
+    fn borrow(&self) -> &[u8; N] {
+        &self.0
+    }
+}
+
+impl<const N: usize> From<[u8; N]> for FixedKey<N> {
+    #[inline]
+    fn from(bytes: [u8; N]) -> Self {
+        FixedKey(bytes)
+    }
+}
+
+#[cfg(feature = "rkyv_derive")]
+impl<const N: usize> PartialEq<[u8; N]> for ArchivedFixedKey<N> {
+    #[inline]
+    fn eq(&self, other: &[u8; N]) -> bool {
+        eq_bytes(&self.0, other)
+    }
+}
+
+#[cfg(feature = "rkyv_derive")]
+impl<const N: usize> PartialEq<FixedKey<N>> for ArchivedFixedKey<N> {
+    #[inline]
+    fn eq(&self, other: &FixedKey<N>) -> bool {
+        eq_bytes(&self.0, &other.0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{MapWithDict, DEFAULT_GAMMA};
+    use rand::{Rng, SeedableRng};
+    use rand_chacha::ChaCha8Rng;
+    use std::collections::HashMap;
+    use std::hash::{BuildHasher, BuildHasherDefault};
+    use test_case::test_case;
+    use wyhash::WyHash;
+
+    fn test_eq_bytes_impl<const N: usize>() {
+        let mut rng = ChaCha8Rng::seed_from_u64(123);
+        for _ in 0..1000 {
+            let a: [u8; N] = std::array::from_fn(|_| rng.gen());
+            let mut b = a;
+            assert!(eq_bytes(&a, &b));
+
+            // flip a random bit
+            if N > 0 {
+                b[rng.gen_range(0..N)] ^= 1 << rng.gen_range(0..8);
+                assert!(!eq_bytes(&a, &b));
+            }
+        }
+    }
+
+    #[test_case(0)]
+    #[test_case(4)]
+    #[test_case(16)]
+    #[test_case(20)]
+    #[test_case(32)]
+    fn test_eq_bytes(n: usize) {
+        match n {
+            0 => test_eq_bytes_impl::<0>(),
+            4 => test_eq_bytes_impl::<4>(),
+            16 => test_eq_bytes_impl::<16>(),
+            20 => test_eq_bytes_impl::<20>(),
+            32 => test_eq_bytes_impl::<32>(),
+            _ => unreachable!(),
+        }
+    }
+
+    #[test]
+    fn test_fixed_key_map() {
+        let mut rng = ChaCha8Rng::seed_from_u64(123);
+        let original_map: HashMap<[u8; 32], u32> = (0..1000).map(|_| (rng.gen(), rng.gen_range(1..=10))).collect();
+        let map = MapWithDict::<FixedKey<32>, u32>::from_iter_with_params(
+            original_map.iter().map(|(&k, &v)| (FixedKey(k), v)),
+            DEFAULT_GAMMA,
+        )
+        .unwrap();
+
+        // `FixedKey` must hash exactly like the wrapped array for `Borrow` lookups to work
+        let hasher = BuildHasherDefault::<WyHash>::default();
+        assert_eq!(hasher.hash_one(FixedKey([7; 32])), hasher.hash_one([7u8; 32]));
+
+        for (key, value) in &original_map {
+            assert_eq!(map.get(key), Some(value));
+            assert_eq!(map.get(&FixedKey(*key)), Some(value));
+        }
+        assert_eq!(map.get(&[0; 32]), None);
+    }
+
+    #[cfg(feature = "rkyv_derive")]
+    #[test]
+    fn test_rkyv() {
+        let set = crate::Set::<FixedKey<16>>::from_iter_with_params([FixedKey([1; 16])], DEFAULT_GAMMA).unwrap();
+        let rkyv_bytes = rkyv::to_bytes::<_, 1024>(&set).unwrap();
+        let rkyv_set = rkyv::check_archived_root::<crate::Set<FixedKey<16>>>(&rkyv_bytes).unwrap();
+
+        assert!(rkyv_set.contains(&[1; 16]));
+        assert!(!rkyv_set.contains(&[2; 16]));
+    }
+}
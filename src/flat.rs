@@ -0,0 +1,446 @@
+//! A module providing flat, plain-old-data layouts of `Mphf`, `RankedBits` and `Set<u64>`.
+//!
+//! Flat layouts are an alternative to `rkyv` for environments where pulling it in is not
+//! acceptable: a structure is written as a sequence of `u64` words (a fixed-size header followed by
+//! fixed-width integer arrays) and later viewed in place from a byte slice, e.g. a memory mapped
+//! file, with `bytemuck` casts only. Opening a flat structure validates the header and arrays
+//! lengths in `O(levels)` time, which is enough for queries to never access memory out of bounds.
+//!
+//! Words are stored in native endianness, so flat structures written on a big-endian machine can't
+//! be opened on a little-endian one and vice versa (which is detected via the header magic). The
+//! hasher `H` is not recorded, so the same `H` must be used for writing and reading.
+
+use std::hash::{Hash, Hasher};
+use std::marker::PhantomData;
+use std::mem::size_of;
+
+use bytemuck::{Pod, Zeroable};
+use num::{PrimInt, Unsigned};
+use wyhash::WyHash;
+
+use crate::mphf::{get_levels, hash_key_seeded, BitLocation, Mphf};
+use crate::rank::{L12RankAccess, RankedBitsAccess};
+use crate::set::Set;
+
+/// Magic number identifying flat `Mphf` layout, the bytes of "ENTRMPH1" in native endianness.
+const FLAT_MPHF_MAGIC: u64 = u64::from_ne_bytes(*b"ENTRMPH1");
+
+/// Errors that can occur when opening flat structures.
+#[derive(Debug, PartialEq)]
+pub enum FlatError {
+    /// Error when bytes are not aligned to 8 bytes or their length is not a multiple of 8.
+    Misaligned,
+    /// Error when the data is shorter than its header declares.
+    Truncated,
+    /// Error when the header magic doesn't match, e.g. the data was written with different endianness.
+    InvalidMagic,
+    /// Error when `B`, `S` or the seed type size differ from the ones the data was written with.
+    ParamsMismatch,
+    /// Error when arrays lengths declared in the header are inconsistent with each other.
+    InvalidLayout,
+}
+
+/// Header of the flat `Mphf` layout, followed by level groups (`u32`), group seeds (`ST`), bits
+/// (`u64`) and L1/L2 ranks (`[u64; 2]`) arrays, each padded to a multiple of 8 bytes.
+#[derive(Debug, Clone, Copy, Pod, Zeroable)]
+#[repr(C)]
+struct FlatMphfHeader {
+    /// Magic number, see `FLAT_MPHF_MAGIC`
+    magic: u64,
+    /// Group size in bits `B`
+    b: u64,
+    /// Seed bits `S`
+    s: u64,
+    /// Size of seed type `ST` in bytes
+    seed_size: u64,
+    /// Number of levels
+    levels: u64,
+    /// Total number of groups (and seeds) across all levels
+    groups: u64,
+    /// Number of `u64` words of ranked bits
+    bits: u64,
+    /// Number of L1/L2 ranks
+    l12_ranks: u64,
+}
+
+/// Number of `u64` words occupied by the header.
+const HEADER_WORDS: usize = size_of::<FlatMphfHeader>() / 8;
+
+/// Zero-copy view over `RankedBits` stored in flat layout.
+#[derive(Debug, Clone, Copy)]
+pub struct FlatRankedBits<'a> {
+    /// The bit vector represented as an array of u64 integers.
+    bits: &'a [u64],
+    /// Precomputed rank information for L1 and L2 blocks, as `u128` split into low and high words.
+    l12_ranks: &'a [[u64; 2]],
+}
+
+impl L12RankAccess for &[[u64; 2]] {
+    #[inline]
+    fn l12_rank(&self, l1_pos: usize) -> u128 {
+        // SAFETY: `l1_pos` is always within bounds (ensured by `FlatRankedBits` validation)
+        let [lo, hi] = unsafe { *crate::access::get(self, l1_pos) };
+        (lo as u128) | ((hi as u128) << 64)
+    }
+}
+
+impl RankedBitsAccess for FlatRankedBits<'_> {
+    #[inline]
+    fn rank(&self, idx: u64) -> Option<usize> {
+        unsafe { Self::rank_impl(self.bits, &self.l12_ranks, idx) }
+    }
+
+    #[inline]
+    fn select(&self, rank: usize) -> Option<u64> {
+        unsafe { Self::select_impl(self.bits, &self.l12_ranks, rank) }
+    }
+}
+
+/// Zero-copy view over `Mphf` stored in flat layout.
+///
+/// Template parameters must match the ones of the `Mphf` the data was written from.
+pub struct FlatMphf<'a, const B: usize = 32, const S: usize = 8, ST = u8, H = WyHash> {
+    /// Ranked bits for efficient rank queries
+    ranked_bits: FlatRankedBits<'a>,
+    /// Group sizes at each level
+    level_groups: &'a [u32],
+    /// Combined group seeds from all levels
+    group_seeds: &'a [ST],
+    /// Phantom field for the hasher
+    _phantom_hasher: PhantomData<H>,
+}
+
+impl<'a, const B: usize, const S: usize, ST, H> FlatMphf<'a, B, S, ST, H>
+where
+    ST: PrimInt + Unsigned + Pod,
+    H: Hasher + Default,
+{
+    /// Opens flat `Mphf` stored in `bytes`, which must be aligned to 8 bytes.
+    ///
+    /// # Examples
+    /// ```
+    /// use entropy_map::{FlatMphf, Mphf, DEFAULT_GAMMA};
+    ///
+    /// let mphf = Mphf::<32, 8>::from_slice(&[1, 2, 3], DEFAULT_GAMMA).unwrap();
+    /// let words = mphf.to_flat();
+    ///
+    /// let flat_mphf = FlatMphf::<32, 8>::from_bytes(bytemuck::cast_slice(&words)).unwrap();
+    /// assert_eq!(flat_mphf.get(&1), mphf.get(&1));
+    /// ```
+    pub fn from_bytes(bytes: &'a [u8]) -> Result<Self, FlatError> {
+        let words = bytemuck::try_cast_slice(bytes).map_err(|_| FlatError::Misaligned)?;
+        Self::from_words(words)
+    }
+
+    /// Opens flat `Mphf` stored in `words`.
+    pub fn from_words(words: &'a [u64]) -> Result<Self, FlatError> {
+        Self::from_words_prefix(words).map(|(mphf, _)| mphf)
+    }
+
+    /// Opens flat `Mphf` stored at the beginning of `words`, returning it with the remaining words.
+    fn from_words_prefix(words: &'a [u64]) -> Result<(Self, &'a [u64]), FlatError> {
+        let header_words = words.get(..HEADER_WORDS).ok_or(FlatError::Truncated)?;
+        let header: FlatMphfHeader = bytemuck::pod_read_unaligned(bytemuck::cast_slice(header_words));
+
+        if header.magic != FLAT_MPHF_MAGIC {
+            return Err(FlatError::InvalidMagic);
+        }
+
+        if header.b != B as u64 || header.s != S as u64 || header.seed_size != size_of::<ST>() as u64 {
+            return Err(FlatError::ParamsMismatch);
+        }
+
+        let rest = &words[HEADER_WORDS..];
+        let (level_groups, rest) = take_array::<u32>(rest, header.levels)?;
+        let (group_seeds, rest) = take_array::<ST>(rest, header.groups)?;
+        let (bits, rest) = take_array::<u64>(rest, header.bits)?;
+        let (l12_ranks, rest) = take_array::<[u64; 2]>(rest, header.l12_ranks)?;
+
+        // all groups bits must be stored and covered by L1/L2 ranks, so that queries can't go out
+        // of bounds of any array
+        let groups = level_groups.iter().map(|&g| g as u64).sum::<u64>();
+        let groups_bits = (groups as u128) * (B as u128);
+        if groups != header.groups
+            || groups_bits > (bits.len() as u128) * 64
+            || l12_ranks.len() != bits.len().div_ceil(64)
+        {
+            return Err(FlatError::InvalidLayout);
+        }
+
+        let mphf = FlatMphf {
+            ranked_bits: FlatRankedBits { bits, l12_ranks },
+            level_groups,
+            group_seeds,
+            _phantom_hasher: PhantomData,
+        };
+
+        Ok((mphf, rest))
+    }
+
+    /// Returns the index associated with `key`, within 0 to the key collection size (exclusive).
+    /// If `key` was not in the initial collection, returns `None` or an arbitrary value from the range.
+    #[inline]
+    pub fn get<K: Hash + ?Sized>(&self, key: &K) -> Option<usize> {
+        get_levels(
+            hash_key_seeded::<H, _>(0, key),
+            B,
+            self.level_groups,
+            self.group_seeds,
+            &self.ranked_bits,
+        )
+    }
+
+    /// Returns the location of the bit set for output index `idx`, see [`Mphf::locate`].
+    #[inline]
+    pub fn locate(&self, idx: usize) -> Option<BitLocation> {
+        Mphf::<B, S, ST, H>::locate_impl(idx, self.level_groups, &self.ranked_bits)
+    }
+
+    /// Returns the ranked bits view of the MPHF.
+    #[inline]
+    pub fn ranked_bits(&self) -> &FlatRankedBits<'a> {
+        &self.ranked_bits
+    }
+}
+
+impl<const B: usize, const S: usize, ST, H> Mphf<B, S, ST, H>
+where
+    ST: PrimInt + Unsigned + Pod,
+    H: Hasher + Default,
+{
+    /// Writes `Mphf` in flat layout to be opened with `FlatMphf`, see [`FlatMphf::from_bytes`].
+    pub fn to_flat(&self) -> Vec<u64> {
+        let mut words = vec![];
+        self.write_flat(&mut words);
+        words
+    }
+
+    /// Appends `Mphf` in flat layout to `words`.
+    fn write_flat(&self, words: &mut Vec<u64>) {
+        let bits = &self.ranked_bits.bits;
+        let l12_ranks: Vec<[u64; 2]> = self
+            .ranked_bits
+            .l12_rank_values()
+            .map(|l12_rank| [l12_rank as u64, (l12_rank >> 64) as u64])
+            .collect();
+
+        let header = FlatMphfHeader {
+            magic: FLAT_MPHF_MAGIC,
+            b: B as u64,
+            s: S as u64,
+            seed_size: size_of::<ST>() as u64,
+            levels: self.level_groups.len() as u64,
+            groups: self.group_seeds.len() as u64,
+            bits: bits.len() as u64,
+            l12_ranks: l12_ranks.len() as u64,
+        };
+
+        words.extend_from_slice(bytemuck::cast_slice(&[header]));
+        push_array(words, &self.level_groups);
+        push_array(words, &self.group_seeds);
+        push_array(words, bits);
+        push_array(words, &l12_ranks);
+    }
+}
+
+/// Zero-copy view over `Set<u64>` stored in flat layout, i.e. flat `Mphf` followed by the number
+/// of keys and the keys array.
+pub struct FlatSet<'a, const B: usize = 32, const S: usize = 8, ST = u8, H = WyHash> {
+    /// Minimally Perfect Hash Function for keys indices retrieval
+    mphf: FlatMphf<'a, B, S, ST, H>,
+    /// Set keys
+    keys: &'a [u64],
+}
+
+impl<'a, const B: usize, const S: usize, ST, H> FlatSet<'a, B, S, ST, H>
+where
+    ST: PrimInt + Unsigned + Pod,
+    H: Hasher + Default,
+{
+    /// Opens flat `Set<u64>` stored in `bytes`, which must be aligned to 8 bytes.
+    ///
+    /// # Examples
+    /// ```
+    /// # use std::collections::HashSet;
+    /// # use entropy_map::{FlatSet, Set};
+    /// let set: Set<u64> = Set::try_from(HashSet::from([1, 2, 3])).unwrap();
+    /// let words = set.to_flat();
+    ///
+    /// let flat_set = FlatSet::<32, 8>::from_bytes(bytemuck::cast_slice(&words)).unwrap();
+    /// assert!(flat_set.contains(&1));
+    /// assert!(!flat_set.contains(&4));
+    /// ```
+    pub fn from_bytes(bytes: &'a [u8]) -> Result<Self, FlatError> {
+        let words = bytemuck::try_cast_slice(bytes).map_err(|_| FlatError::Misaligned)?;
+        Self::from_words(words)
+    }
+
+    /// Opens flat `Set<u64>` stored in `words`.
+    pub fn from_words(words: &'a [u64]) -> Result<Self, FlatError> {
+        let (mphf, rest) = FlatMphf::from_words_prefix(words)?;
+        let (&keys_len, rest) = rest.split_first().ok_or(FlatError::Truncated)?;
+        let (keys, _) = take_array::<u64>(rest, keys_len)?;
+
+        Ok(FlatSet { mphf, keys })
+    }
+
+    /// Returns `true` if the set contains the value.
+    #[inline]
+    pub fn contains(&self, key: &u64) -> bool {
+        // indices are bounds checked, as only arrays lengths are validated when opening
+        self.mphf
+            .get(key)
+            .and_then(|idx| self.keys.get(idx))
+            .is_some_and(|k| k == key)
+    }
+
+    /// Returns the number of elements in the set.
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.keys.len()
+    }
+
+    /// Returns `true` if the set contains no elements.
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.keys.is_empty()
+    }
+
+    /// Returns an iterator over the set elements.
+    #[inline]
+    pub fn iter(&self) -> impl ExactSizeIterator<Item = &'a u64> + DoubleEndedIterator {
+        self.keys.iter()
+    }
+}
+
+impl<const B: usize, const S: usize, ST, H> Set<u64, B, S, ST, H>
+where
+    ST: PrimInt + Unsigned + Pod,
+    H: Hasher + Default,
+{
+    /// Writes `Set<u64>` in flat layout to be opened with `FlatSet`, see [`FlatSet::from_bytes`].
+    ///
+    /// # Panics
+    /// Panics if the set was built with a non-zero hash seed, see [`Mphf::to_flat`].
+    pub fn to_flat(&self) -> Vec<u64> {
+        let mut words = vec![];
+        self.mphf.write_flat(&mut words);
+        words.push(self.keys.len() as u64);
+        push_array(&mut words, &self.keys);
+        words
+    }
+}
+
+/// Appends `items` to `words`, padding them with zero bytes to a multiple of 8 bytes.
+#[inline]
+fn push_array<T: Pod>(words: &mut Vec<u64>, items: &[T]) {
+    let bytes: &[u8] = bytemuck::cast_slice(items);
+    words.extend(bytes.chunks(8).map(|chunk| {
+        let mut word = [0u8; 8];
+        word[..chunk.len()].copy_from_slice(chunk);
+        u64::from_ne_bytes(word)
+    }));
+}
+
+/// Takes an array of `len` items padded to a multiple of 8 bytes from the beginning of `words`,
+/// returning it with the remaining words.
+#[inline]
+fn take_array<T: Pod>(words: &[u64], len: u64) -> Result<(&[T], &[u64]), FlatError> {
+    let len = usize::try_from(len).map_err(|_| FlatError::Truncated)?;
+    let array_words = len
+        .checked_mul(size_of::<T>())
+        .map(|bytes| bytes.div_ceil(8))
+        .ok_or(FlatError::Truncated)?;
+
+    if array_words > words.len() {
+        return Err(FlatError::Truncated);
+    }
+
+    let (array_words, rest) = words.split_at(array_words);
+    let array: &[T] = bytemuck::try_cast_slice(array_words).map_err(|_| FlatError::Misaligned)?;
+
+    Ok((&array[..len], rest))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::DEFAULT_GAMMA;
+    use paste::paste;
+    use rand::{Rng, SeedableRng};
+    use rand_chacha::ChaCha8Rng;
+    use std::collections::HashSet;
+
+    fn gen_set(items_num: usize) -> HashSet<u64> {
+        let mut rng = ChaCha8Rng::seed_from_u64(123);
+        (0..items_num).map(|_| rng.gen::<u64>()).collect()
+    }
+
+    macro_rules! generate_flat_tests {
+        ($(($b:expr, $s:expr, $st:ty)),* $(,)?) => {
+            $(
+                paste! {
+                    #[test]
+                    fn [<test_flat_set_ $b _ $s _ $st>]() {
+                        let original_set = gen_set(10000);
+                        let set = Set::<u64, $b, $s, $st>::from_iter_with_params(original_set.clone(), DEFAULT_GAMMA)
+                            .unwrap();
+                        let words = set.to_flat();
+                        let flat_set = FlatSet::<$b, $s, $st>::from_bytes(bytemuck::cast_slice(&words)).unwrap();
+
+                        assert_eq!(flat_set.len(), original_set.len());
+                        for key in &original_set {
+                            assert!(flat_set.contains(key));
+                            assert_eq!(flat_set.mphf.get(key), set.mphf.get(key));
+                        }
+                        assert!((0..=original_set.len()).all(|idx| flat_set.mphf.locate(idx) == set.mphf.locate(idx)));
+                        for key in 0..1000 {
+                            assert_eq!(flat_set.contains(&key), original_set.contains(&key));
+                        }
+                    }
+                }
+            )*
+        };
+    }
+
+    generate_flat_tests!((32, 8, u8), (7, 8, u8), (64, 16, u16), (1, 0, u32));
+
+    #[test]
+    fn test_flat_mphf_errors() {
+        let keys = (0..1000u64).collect::<Vec<_>>();
+        let mphf = Mphf::<32, 8>::from_slice(&keys, DEFAULT_GAMMA).unwrap();
+        let words = mphf.to_flat();
+
+        assert!(FlatMphf::<32, 8>::from_words(&words).is_ok());
+        assert_eq!(
+            FlatMphf::<16, 8>::from_words(&words).err(),
+            Some(FlatError::ParamsMismatch)
+        );
+        assert_eq!(
+            FlatMphf::<32, 8, u16>::from_words(&words).err(),
+            Some(FlatError::ParamsMismatch)
+        );
+        assert_eq!(
+            FlatMphf::<32, 8>::from_words(&words[..words.len() - 1]).err(),
+            Some(FlatError::Truncated)
+        );
+        assert_eq!(
+            FlatMphf::<32, 8>::from_words(&words[1..]).err(),
+            Some(FlatError::InvalidMagic)
+        );
+
+        let bytes: &[u8] = bytemuck::cast_slice(&words);
+        assert_eq!(
+            FlatMphf::<32, 8>::from_bytes(&bytes[1..]).err(),
+            Some(FlatError::Misaligned)
+        );
+
+        // corrupt number of groups at the first level
+        let mut corrupted_words = words.clone();
+        corrupted_words[HEADER_WORDS] += 1;
+        assert_eq!(
+            FlatMphf::<32, 8>::from_words(&corrupted_words).err(),
+            Some(FlatError::InvalidLayout)
+        );
+    }
+}
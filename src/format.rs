@@ -0,0 +1,96 @@
+//! A module providing `FORMAT_VERSION`, the version of the serialized format of all structures.
+//!
+//! Serializing the same input (keys and values in the same iteration order) with the same
+//! parameters produces byte-identical output across releases sharing the same `FORMAT_VERSION`, so
+//! that digests of serialized artifacts can be used as cache keys. This covers `rkyv` archives,
+//! flat layouts and FlatBuffers exports alike, and relies on construction being deterministic: MPHF
+//! seeds are searched in a fixed order regardless of `rayon` parallelism, and no randomness is used.
+//!
+//! `FORMAT_VERSION` is bumped by any change of the serialized output of an existing structure, be
+//! it a layout change or a construction change picking different seeds, and only in minor or major
+//! releases, never in patch releases. It is enforced by golden-file tests comparing `rkyv`
+//! archives of fixed inputs with the files checked in under `testdata/golden`, which are
+//! regenerated along with the bump by running:
+//! ```sh
+//! ENTROPY_MAP_BLESS=1 cargo test --features rkyv_derive golden
+//! ```
+//! Archives use the native endianness, so golden files are compared on little-endian targets only.
+
+/// Version of the serialized format, see the module documentation.
+pub const FORMAT_VERSION: u32 = 4;
+
+#[cfg(all(test, feature = "rkyv_derive", target_endian = "little"))]
+mod tests {
+    use super::*;
+    use crate::{MapWithDict, MapWithDictBitpacked, Mphf, Set, DEFAULT_GAMMA};
+    use std::path::PathBuf;
+    use test_case::test_case;
+
+    /// Compares `bytes` with the golden file `name` of the current `FORMAT_VERSION`, overwriting
+    /// the file instead if `ENTROPY_MAP_BLESS` environment variable is set.
+    fn check_golden(name: &str, bytes: &[u8]) {
+        let path: PathBuf = [
+            env!("CARGO_MANIFEST_DIR"),
+            "testdata",
+            "golden",
+            &format!("v{FORMAT_VERSION}"),
+            &format!("{name}.bin"),
+        ]
+        .iter()
+        .collect();
+
+        if std::env::var_os("ENTROPY_MAP_BLESS").is_some() {
+            std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+            std::fs::write(&path, bytes).unwrap();
+            return;
+        }
+
+        let golden = std::fs::read(&path).unwrap_or_else(|e| panic!("can't read {}: {e}", path.display()));
+        assert!(
+            golden == bytes,
+            "serialized {name} differs from {}, bump FORMAT_VERSION if the change is intended",
+            path.display()
+        );
+    }
+
+    /// Returns keys of golden structures.
+    fn keys() -> Vec<u64> {
+        (0..1000u64).map(|i| i.wrapping_mul(0x9E37_79B9_7F4A_7C15)).collect()
+    }
+
+    #[test_case(1.0; "gamma_1")]
+    #[test_case(DEFAULT_GAMMA; "gamma_default")]
+    fn test_golden_mphf(gamma: f32) {
+        let mphf = Mphf::<32, 8>::from_slice(&keys(), gamma).unwrap();
+        let name = format!("mphf_32_8_gamma_{gamma}");
+        check_golden(&name, &rkyv::to_bytes::<_, 1024>(&mphf).unwrap());
+    }
+
+    #[test]
+    fn test_golden_set() {
+        let set = Set::<u64>::from_iter_with_params(keys(), DEFAULT_GAMMA).unwrap();
+        check_golden("set_u64", &rkyv::to_bytes::<_, 1024>(&set).unwrap());
+    }
+
+    #[test]
+    fn test_golden_map_with_dict() {
+        let map = MapWithDict::<String, u32>::from_iter_with_params(
+            keys().into_iter().map(|key| (key.to_string(), (key % 13) as u32)),
+            DEFAULT_GAMMA,
+        )
+        .unwrap();
+        check_golden("map_with_dict_string_u32", &rkyv::to_bytes::<_, 1024>(&map).unwrap());
+    }
+
+    #[test]
+    fn test_golden_map_with_dict_bitpacked() {
+        let map = MapWithDictBitpacked::<u64>::from_iter_with_params(
+            keys()
+                .into_iter()
+                .map(|key| (key, vec![(key % 7) as u32, (key % 1000) as u32])),
+            DEFAULT_GAMMA,
+        )
+        .unwrap();
+        check_golden("map_with_dict_bitpacked_u64", &rkyv::to_bytes::<_, 1024>(&map).unwrap());
+    }
+}
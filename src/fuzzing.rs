@@ -0,0 +1,9 @@
+//! Internal decoders exposed to the fuzz targets living in the `fuzz` directory.
+//!
+//! This module is only available with the `fuzzing` feature and is not part of the public API.
+
+/// Bit-unpacks values from the bit-packed `dict` into `res`, see `MapWithDictBitpacked`.
+#[inline]
+pub fn unpack_values(dict: &[u8], res: &mut [u32]) {
+    crate::map_with_dict_bitpacked::unpack_values(dict, res)
+}
@@ -0,0 +1,154 @@
+//! A module providing `HashedKey`, a key paired with its precomputed hash.
+//!
+//! Looking up the same key in several structures keyed identically (e.g. a `Set` of blocked keys
+//! and a `MapWithDict` of per key settings) would normally hash the key once per structure.
+//! `HashedKey` computes the hash once and can then be passed to the `*_hashed` query methods of
+//! every structure using the same hasher `H`, which is enforced at compile time. Keys hashed with
+//! another hash seed than the structure's are not found, as their hashes would map to arbitrary
+//! indices.
+
+use std::fmt;
+use std::hash::{Hash, Hasher};
+use std::marker::PhantomData;
+
+use wyhash::WyHash;
+
+use crate::mphf::hash_key_seeded;
+
+/// A reference to a key together with its hash computed using hasher `H`.
+pub struct HashedKey<'a, K: ?Sized, H = WyHash> {
+    /// Referenced key
+    key: &'a K,
+    /// Precomputed key hash
+    hash: u64,
+    /// Seed hashed before the key, 0 for unkeyed hashing
+    hash_seed: u64,
+    /// Phantom field for the hasher
+    _phantom_hasher: PhantomData<H>,
+}
+
+impl<'a, K: Hash + ?Sized, H: Hasher + Default> HashedKey<'a, K, H> {
+    /// Hashes `key` with hasher `H` and wraps it together with the hash.
+    ///
+    /// # Examples
+    /// ```
+    /// # use std::collections::{HashMap, HashSet};
+    /// # use entropy_map::{HashedKey, MapWithDict, Set};
+    /// let set = Set::try_from(HashSet::from(["a".to_string(), "b".to_string()])).unwrap();
+    /// let map = MapWithDict::try_from(HashMap::from([("a".to_string(), 1), ("c".to_string(), 2)])).unwrap();
+    ///
+    /// let key = HashedKey::new("a");
+    /// assert!(set.contains_hashed(&key));
+    /// assert_eq!(map.get_hashed(&key), Some(&1));
+    /// ```
+    #[inline]
+    pub fn new(key: &'a K) -> Self {
+        Self::with_seed(key, 0)
+    }
+
+    /// Hashes `key` with hasher `H` keyed with `hash_seed`, for structures built with a non-zero
+    /// [`BuildPolicy::hash_seed`](crate::BuildPolicy::hash_seed).
+    ///
+    /// # Examples
+    /// ```
+    /// # use entropy_map::{BuildPolicy, HashedKey, SetKeyed};
+    /// let (set, _) = SetKeyed::from_iter_with_policy([1, 2, 3], &BuildPolicy::keyed()).unwrap();
+    /// assert!(set.contains_hashed(&HashedKey::with_seed(&1, set.hash_seed())));
+    /// ```
+    #[inline]
+    pub fn with_seed(key: &'a K, hash_seed: u64) -> Self {
+        HashedKey {
+            key,
+            hash: hash_key_seeded::<H, _>(hash_seed, key),
+            hash_seed,
+            _phantom_hasher: PhantomData,
+        }
+    }
+}
+
+impl<'a, K: ?Sized, H> HashedKey<'a, K, H> {
+    /// Returns the referenced key.
+    #[inline]
+    pub fn key(&self) -> &'a K {
+        self.key
+    }
+
+    /// Returns the precomputed key hash.
+    #[inline]
+    pub fn hash(&self) -> u64 {
+        self.hash
+    }
+
+    /// Returns the seed the key was hashed with.
+    #[inline]
+    pub fn hash_seed(&self) -> u64 {
+        self.hash_seed
+    }
+}
+
+impl<K: ?Sized, H> Clone for HashedKey<'_, K, H> {
+    #[inline]
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<K: ?Sized, H> Copy for HashedKey<'_, K, H> {}
+
+impl<K: fmt::Debug + ?Sized, H> fmt::Debug for HashedKey<'_, K, H> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("HashedKey")
+            .field("key", &self.key)
+            .field("hash", &self.hash)
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{MapWithDict, MapWithDictBitpacked, Mphf, Set, DEFAULT_GAMMA};
+    use rand::{Rng, SeedableRng};
+    use rand_chacha::ChaCha8Rng;
+    use std::collections::{HashMap, HashSet};
+
+    #[test]
+    fn test_hashed_key_lookups() {
+        let mut rng = ChaCha8Rng::seed_from_u64(123);
+        let original_map: HashMap<u64, u32> = (0..1000).map(|_| (rng.gen(), rng.gen_range(1..=10))).collect();
+        let keys: Vec<u64> = original_map.keys().copied().collect();
+
+        let mphf = Mphf::<32, 8>::from_slice(&keys, DEFAULT_GAMMA).unwrap();
+        let set = Set::try_from(keys.iter().copied().collect::<HashSet<_>>()).unwrap();
+        let map = MapWithDict::try_from(original_map.clone()).unwrap();
+        let bitpacked_map = MapWithDictBitpacked::<u64>::from_iter_with_params(
+            original_map.iter().map(|(&k, &v)| (k, vec![v])),
+            DEFAULT_GAMMA,
+        )
+        .unwrap();
+
+        let mut values = [0];
+        for key in keys.iter().chain([&0, &1]) {
+            let hashed_key = HashedKey::new(key);
+            assert_eq!(hashed_key.hash(), hash_key_seeded::<WyHash, _>(0, key));
+            assert_eq!(mphf.get_hashed(&hashed_key), mphf.get(key));
+            assert_eq!(set.contains_hashed(&hashed_key), set.contains(key));
+            assert_eq!(map.get_hashed(&hashed_key), map.get(key));
+            assert_eq!(map.get_with_hash(hashed_key.hash(), key), map.get(key));
+            assert_eq!(set.contains_with_hash(hashed_key.hash(), key), set.contains(key));
+            assert_eq!(map.contains_key_hashed(&hashed_key), map.contains_key(key));
+            assert_eq!(
+                bitpacked_map.contains_key_hashed(&hashed_key),
+                bitpacked_map.contains_key(key)
+            );
+            assert_eq!(
+                bitpacked_map.get_values_hashed(&hashed_key, &mut values),
+                bitpacked_map.get_values(key, &mut values)
+            );
+            assert_eq!(
+                bitpacked_map.get_values_with_hash(hashed_key.hash(), key, &mut values),
+                bitpacked_map.get_values(key, &mut values)
+            );
+        }
+    }
+}
@@ -0,0 +1,117 @@
+//! A module providing `InvariantError`, returned by `debug_check_invariants` of the structures.
+//!
+//! Construction guarantees a number of invariants which queries rely on, e.g. that rank metadata
+//! matches the bits, that every key is stored at the index the MPHF maps it to and that values
+//! indices point within the values dictionary. Structures reassembled by custom serialization code
+//! don't go through construction, so `debug_check_invariants` re-verifies these invariants by
+//! walking the whole structure. Checks are linear in the structure size and meant for tests and
+//! validation at load time, not for query paths.
+
+use std::hash::{Hash, Hasher};
+
+use num::{PrimInt, Unsigned};
+
+use crate::mphf::Mphf;
+use crate::mphf_extras::ExtrasLayout;
+use crate::rank::RankedBitsLayout;
+
+/// Invariant violation found by `debug_check_invariants`.
+#[derive(Debug, PartialEq, Eq)]
+pub enum InvariantError {
+    /// Error when the rank of a block stored in the rank metadata doesn't match the bits.
+    RankMismatch {
+        /// Index of the L1 block (or interleaved block) with the mismatching rank
+        block_idx: usize,
+    },
+    /// Error when the number of group seeds or the number of bits doesn't match the level groups.
+    GroupsMismatch,
+    /// Error when a group seed doesn't fit into `S` bits.
+    SeedOutOfRange {
+        /// Index of the group, groups being numbered consecutively across all levels
+        group_idx: usize,
+    },
+    /// Error when a padding bit past the last group is set.
+    PaddingBitSet {
+        /// Position of the bit in the underlying bit vector
+        bit_idx: u64,
+    },
+    /// Error when the hashes of the fallback table are not sorted and unique.
+    FallbackUnsorted,
+    /// Error when the number of keys or values indices doesn't match the number of MPHF outputs.
+    LengthMismatch,
+    /// Error when a key is not mapped by the MPHF to the index it's stored at.
+    KeyIndexMismatch {
+        /// Index the key is stored at
+        idx: usize,
+    },
+    /// Error when a values index points outside of the values dictionary.
+    ValueIndexOutOfBounds {
+        /// Index of the key whose values index is out of bounds
+        idx: usize,
+    },
+}
+
+/// Checks invariants of `mphf` and that every key of `keys` is mapped by it to its own index.
+pub(crate) fn check_keys<K, const B: usize, const S: usize, ST, H, R, E>(
+    mphf: &Mphf<B, S, ST, H, R, E>,
+    keys: &[K],
+) -> Result<(), InvariantError>
+where
+    K: Hash,
+    ST: PrimInt + Unsigned,
+    H: Hasher + Default,
+    R: RankedBitsLayout,
+    E: ExtrasLayout,
+{
+    check_outputs(mphf, keys.len())?;
+
+    for (idx, key) in keys.iter().enumerate() {
+        if mphf.get(key) != Some(idx) {
+            return Err(InvariantError::KeyIndexMismatch { idx });
+        }
+    }
+
+    Ok(())
+}
+
+/// Checks invariants of `mphf` and that its outputs are exactly `0..len`.
+pub(crate) fn check_outputs<const B: usize, const S: usize, ST, H, R, E>(
+    mphf: &Mphf<B, S, ST, H, R, E>,
+    len: usize,
+) -> Result<(), InvariantError>
+where
+    ST: PrimInt + Unsigned,
+    H: Hasher + Default,
+    R: RankedBitsLayout,
+    E: ExtrasLayout,
+{
+    mphf.debug_check_invariants()?;
+
+    // indices of the fallback table come first and have no location
+    let fallback_len = mphf.fallback_len();
+    let last_output = len
+        .checked_sub(1)
+        .filter(|&idx| idx >= fallback_len)
+        .map(|idx| mphf.locate(idx));
+    if len < fallback_len || last_output.is_some_and(|loc| loc.is_none()) || mphf.locate(len).is_some() {
+        return Err(InvariantError::LengthMismatch);
+    }
+
+    Ok(())
+}
+
+/// Checks that there is a values index per key, each one less than `max_index`.
+pub(crate) fn check_values_index(
+    values_index: &[usize],
+    keys_len: usize,
+    max_index: usize,
+) -> Result<(), InvariantError> {
+    if values_index.len() != keys_len {
+        return Err(InvariantError::LengthMismatch);
+    }
+
+    match values_index.iter().position(|&value_idx| value_idx >= max_index) {
+        Some(idx) => Err(InvariantError::ValueIndexOutOfBounds { idx }),
+        None => Ok(()),
+    }
+}
@@ -0,0 +1,542 @@
+//! A module providing `IpPrefixSet` and `IpPrefixMap`, static longest-prefix-match structures over
+//! IPv4 and IPv6 CIDR prefixes.
+//!
+//! Prefixes are grouped by address family and prefix length, and each group is stored as a `Set`
+//! or `MapWithDict` keyed by the network address (`u32` for IPv4, `SetU128`/`MapWithDictU128` for
+//! IPv6). A lookup masks the address to every stored prefix length, longest first, and probes the
+//! corresponding structure, so it takes at most one MPHF query per distinct prefix length of the
+//! family, typically a handful. IPv4-mapped IPv6 addresses are not converted, use
+//! `Ipv6Addr::to_canonical` beforehand to match them against IPv4 prefixes.
+
+use std::collections::HashMap;
+use std::fmt::{self, Display};
+use std::hash::Hash;
+use std::mem::size_of_val;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+use std::str::FromStr;
+
+use crate::map_with_dict::MapWithDict;
+use crate::mphf::MphfError;
+use crate::set::Set;
+use crate::u128_keys::{MapWithDictU128, SetU128};
+
+/// Errors that can occur when creating an `IpPrefix`.
+#[derive(Debug, PartialEq, Eq)]
+pub enum IpPrefixError {
+    /// Error when the address or prefix length can't be parsed.
+    InvalidFormat,
+    /// Error when the prefix length exceeds the address length (32 bits for IPv4, 128 for IPv6).
+    InvalidLength,
+}
+
+/// IPv4 or IPv6 CIDR prefix, e.g. `10.0.0.0/8` or `2001:db8::/32`, with host bits cleared.
+///
+/// # Examples
+/// ```
+/// # use entropy_map::IpPrefix;
+/// let prefix: IpPrefix = "10.1.2.3/8".parse().unwrap();
+/// assert_eq!(prefix.to_string(), "10.0.0.0/8");
+/// assert!("10.0.0.0/33".parse::<IpPrefix>().is_err());
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct IpPrefix {
+    /// Network address
+    addr: IpAddr,
+    /// Prefix length in bits
+    prefix_len: u8,
+}
+
+impl IpPrefix {
+    /// Creates a prefix of `addr` with `prefix_len` bits, clearing host bits of `addr`.
+    pub fn new(addr: IpAddr, prefix_len: u8) -> Result<Self, IpPrefixError> {
+        let addr = match addr {
+            IpAddr::V4(addr) if prefix_len <= 32 => IpAddr::V4(mask_v4(addr.into(), prefix_len).into()),
+            IpAddr::V6(addr) if prefix_len <= 128 => IpAddr::V6(mask_v6(addr.into(), prefix_len).into()),
+            _ => return Err(IpPrefixError::InvalidLength),
+        };
+        Ok(IpPrefix { addr, prefix_len })
+    }
+
+    /// Returns the network address of the prefix.
+    #[inline]
+    pub fn addr(&self) -> IpAddr {
+        self.addr
+    }
+
+    /// Returns the prefix length in bits.
+    #[inline]
+    pub fn prefix_len(&self) -> u8 {
+        self.prefix_len
+    }
+}
+
+impl FromStr for IpPrefix {
+    type Err = IpPrefixError;
+
+    /// Parses `addr/len`, or a single address as a prefix of its full length.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (addr, prefix_len) = match s.split_once('/') {
+            Some((addr, prefix_len)) => (addr, Some(prefix_len)),
+            None => (s, None),
+        };
+        let addr: IpAddr = addr.parse().map_err(|_| IpPrefixError::InvalidFormat)?;
+        let prefix_len = match prefix_len {
+            Some(prefix_len) => prefix_len.parse().map_err(|_| IpPrefixError::InvalidFormat)?,
+            None if addr.is_ipv4() => 32,
+            None => 128,
+        };
+        IpPrefix::new(addr, prefix_len)
+    }
+}
+
+impl Display for IpPrefix {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}/{}", self.addr, self.prefix_len)
+    }
+}
+
+/// Returns IPv4 address `addr` with all but the first `prefix_len` bits cleared.
+#[inline]
+fn mask_v4(addr: u32, prefix_len: u8) -> u32 {
+    addr & u32::MAX.checked_shl(32 - prefix_len as u32).unwrap_or_default()
+}
+
+/// Returns IPv6 address `addr` with all but the first `prefix_len` bits cleared.
+#[inline]
+fn mask_v6(addr: u128, prefix_len: u8) -> u128 {
+    addr & u128::MAX.checked_shl(128 - prefix_len as u32).unwrap_or_default()
+}
+
+/// Items of IPv4 and IPv6 prefixes grouped by prefix length, longest first.
+type Groups<T> = (Vec<(u8, HashMap<u32, T>)>, Vec<(u8, HashMap<u128, T>)>);
+
+/// Returns `items` of prefixes grouped by address family and prefix length, keeping the last item
+/// of duplicate prefixes.
+fn group_by_len<T>(items: impl IntoIterator<Item = (IpPrefix, T)>) -> Groups<T> {
+    let mut v4: HashMap<u8, HashMap<u32, T>> = HashMap::new();
+    let mut v6: HashMap<u8, HashMap<u128, T>> = HashMap::new();
+    for (prefix, item) in items {
+        match prefix.addr {
+            IpAddr::V4(addr) => v4.entry(prefix.prefix_len).or_default().insert(addr.into(), item),
+            IpAddr::V6(addr) => v6.entry(prefix.prefix_len).or_default().insert(addr.into(), item),
+        };
+    }
+    (sort_longest_first(v4), sort_longest_first(v6))
+}
+
+/// Returns `groups` sorted by descending prefix length.
+fn sort_longest_first<K, T>(groups: HashMap<u8, HashMap<K, T>>) -> Vec<(u8, HashMap<K, T>)> {
+    let mut groups: Vec<_> = groups.into_iter().collect();
+    groups.sort_unstable_by_key(|&(prefix_len, _)| std::cmp::Reverse(prefix_len));
+    groups
+}
+
+/// Returns the IPv4 prefix of network address `addr` with `prefix_len` bits.
+#[inline]
+fn v4_prefix(addr: u32, prefix_len: u8) -> IpPrefix {
+    IpPrefix { addr: IpAddr::V4(Ipv4Addr::from(addr)), prefix_len }
+}
+
+/// Returns the IPv6 prefix of network address `addr` with `prefix_len` bits.
+#[inline]
+fn v6_prefix(addr: u128, prefix_len: u8) -> IpPrefix {
+    IpPrefix { addr: IpAddr::V6(Ipv6Addr::from(addr)), prefix_len }
+}
+
+/// An immutable set of IPv4 and IPv6 prefixes answering longest-prefix-match queries, see the
+/// module documentation.
+///
+/// # Examples
+/// ```
+/// # use entropy_map::{IpPrefixSet, DEFAULT_GAMMA};
+/// let prefixes = ["10.0.0.0/8", "10.1.0.0/16", "2001:db8::/32"].map(|prefix| prefix.parse().unwrap());
+/// let set = IpPrefixSet::from_iter_with_params(prefixes, DEFAULT_GAMMA).unwrap();
+/// assert_eq!(set.lookup("10.1.2.3".parse().unwrap()), Some("10.1.0.0/16".parse().unwrap()));
+/// assert_eq!(set.lookup("10.2.0.1".parse().unwrap()), Some("10.0.0.0/8".parse().unwrap()));
+/// assert!(set.contains("2001:db8::1".parse().unwrap()));
+/// assert!(!set.contains("192.168.0.1".parse().unwrap()));
+/// ```
+#[derive(Default)]
+#[cfg_attr(feature = "rkyv_derive", derive(rkyv::Archive, rkyv::Deserialize, rkyv::Serialize))]
+#[cfg_attr(feature = "rkyv_derive", archive_attr(derive(rkyv::CheckBytes)))]
+pub struct IpPrefixSet {
+    /// IPv4 prefix lengths, longest first
+    v4_lens: Box<[u8]>,
+    /// IPv4 network addresses of every prefix length of `v4_lens`
+    v4_sets: Box<[Set<u32>]>,
+    /// IPv6 prefix lengths, longest first
+    v6_lens: Box<[u8]>,
+    /// IPv6 network addresses of every prefix length of `v6_lens`
+    v6_sets: Box<[SetU128]>,
+}
+
+impl IpPrefixSet {
+    /// Constructs an `IpPrefixSet` from an iterator of prefixes and MPHF function parameters.
+    pub fn from_iter_with_params<I>(iter: I, gamma: f32) -> Result<Self, MphfError>
+    where
+        I: IntoIterator<Item = IpPrefix>,
+    {
+        let (v4, v6) = group_by_len(iter.into_iter().map(|prefix| (prefix, ())));
+        Ok(IpPrefixSet {
+            v4_lens: v4.iter().map(|&(prefix_len, _)| prefix_len).collect(),
+            v4_sets: v4
+                .into_iter()
+                .map(|(_, addrs)| Set::from_iter_with_params(addrs.into_keys(), gamma))
+                .collect::<Result<_, _>>()?,
+            v6_lens: v6.iter().map(|&(prefix_len, _)| prefix_len).collect(),
+            v6_sets: v6
+                .into_iter()
+                .map(|(_, addrs)| SetU128::from_iter_with_params(addrs.into_keys(), gamma))
+                .collect::<Result<_, _>>()?,
+        })
+    }
+
+    /// Returns the longest prefix containing `ip`, or `None` if no prefix contains it.
+    #[inline]
+    pub fn lookup(&self, ip: IpAddr) -> Option<IpPrefix> {
+        match ip {
+            IpAddr::V4(ip) => {
+                let ip = ip.into();
+                let (&prefix_len, _) = self
+                    .v4_lens
+                    .iter()
+                    .zip(self.v4_sets.iter())
+                    .find(|(&prefix_len, set)| set.contains(&mask_v4(ip, prefix_len)))?;
+                Some(v4_prefix(mask_v4(ip, prefix_len), prefix_len))
+            }
+            IpAddr::V6(ip) => {
+                let ip = ip.into();
+                let (&prefix_len, _) = self
+                    .v6_lens
+                    .iter()
+                    .zip(self.v6_sets.iter())
+                    .find(|(&prefix_len, set)| set.contains(&mask_v6(ip, prefix_len)))?;
+                Some(v6_prefix(mask_v6(ip, prefix_len), prefix_len))
+            }
+        }
+    }
+
+    /// Returns `true` if any prefix contains `ip`.
+    #[inline]
+    pub fn contains(&self, ip: IpAddr) -> bool {
+        self.lookup(ip).is_some()
+    }
+
+    /// Returns the number of prefixes in the set.
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.v4_sets.iter().map(Set::len).sum::<usize>() + self.v6_sets.iter().map(SetU128::len).sum::<usize>()
+    }
+
+    /// Returns `true` if the set contains no prefixes.
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.v4_sets.is_empty() && self.v6_sets.is_empty()
+    }
+
+    /// Returns the total number of bytes occupied by the set.
+    #[inline]
+    pub fn size(&self) -> usize {
+        size_of_val(self)
+            + size_of_val(self.v4_lens.as_ref())
+            + self.v4_sets.iter().map(Set::size).sum::<usize>()
+            + size_of_val(self.v6_lens.as_ref())
+            + self.v6_sets.iter().map(SetU128::size).sum::<usize>()
+    }
+}
+
+/// An immutable map of IPv4 and IPv6 prefixes to values answering longest-prefix-match queries,
+/// see the module documentation. Values of duplicate prefixes are overwritten by the last one.
+///
+/// # Examples
+/// ```
+/// # use entropy_map::{IpPrefixMap, DEFAULT_GAMMA};
+/// let routes = [("0.0.0.0/0", "default"), ("10.0.0.0/8", "internal"), ("10.1.0.0/16", "office")];
+/// let map = IpPrefixMap::from_iter_with_params(
+///     routes.map(|(prefix, route)| (prefix.parse().unwrap(), route)),
+///     DEFAULT_GAMMA,
+/// )
+/// .unwrap();
+/// assert_eq!(map.lookup("10.1.2.3".parse().unwrap()), Some(&"office"));
+/// assert_eq!(map.lookup("10.2.0.1".parse().unwrap()), Some(&"internal"));
+/// assert_eq!(map.lookup("1.1.1.1".parse().unwrap()), Some(&"default"));
+/// assert_eq!(map.lookup("::1".parse().unwrap()), None);
+/// ```
+#[derive(Default)]
+#[cfg_attr(feature = "rkyv_derive", derive(rkyv::Archive, rkyv::Deserialize, rkyv::Serialize))]
+#[cfg_attr(feature = "rkyv_derive", archive_attr(derive(rkyv::CheckBytes)))]
+pub struct IpPrefixMap<V> {
+    /// IPv4 prefix lengths, longest first
+    v4_lens: Box<[u8]>,
+    /// IPv4 network addresses to values of every prefix length of `v4_lens`
+    v4_maps: Box<[MapWithDict<u32, V>]>,
+    /// IPv6 prefix lengths, longest first
+    v6_lens: Box<[u8]>,
+    /// IPv6 network addresses to values of every prefix length of `v6_lens`
+    v6_maps: Box<[MapWithDictU128<V>]>,
+}
+
+impl<V> IpPrefixMap<V>
+where
+    V: Eq + Clone + Hash,
+{
+    /// Constructs an `IpPrefixMap` from an iterator of prefix-value pairs and MPHF function
+    /// parameters.
+    pub fn from_iter_with_params<I>(iter: I, gamma: f32) -> Result<Self, MphfError>
+    where
+        I: IntoIterator<Item = (IpPrefix, V)>,
+    {
+        let (v4, v6) = group_by_len(iter);
+        Ok(IpPrefixMap {
+            v4_lens: v4.iter().map(|&(prefix_len, _)| prefix_len).collect(),
+            v4_maps: v4
+                .into_iter()
+                .map(|(_, entries)| MapWithDict::from_iter_with_params(entries, gamma))
+                .collect::<Result<_, _>>()?,
+            v6_lens: v6.iter().map(|&(prefix_len, _)| prefix_len).collect(),
+            v6_maps: v6
+                .into_iter()
+                .map(|(_, entries)| MapWithDictU128::from_iter_with_params(entries, gamma))
+                .collect::<Result<_, _>>()?,
+        })
+    }
+
+    /// Returns a reference to the value of the longest prefix containing `ip`, or `None` if no
+    /// prefix contains it.
+    #[inline]
+    pub fn lookup(&self, ip: IpAddr) -> Option<&V> {
+        self.lookup_prefix(ip).map(|(_, value)| value)
+    }
+
+    /// Same as [`IpPrefixMap::lookup`], but returns the matched prefix along with its value.
+    #[inline]
+    pub fn lookup_prefix(&self, ip: IpAddr) -> Option<(IpPrefix, &V)> {
+        match ip {
+            IpAddr::V4(ip) => {
+                let ip = ip.into();
+                self.v4_lens
+                    .iter()
+                    .zip(self.v4_maps.iter())
+                    .find_map(|(&prefix_len, map)| {
+                        let addr = mask_v4(ip, prefix_len);
+                        map.get(&addr).map(|value| (v4_prefix(addr, prefix_len), value))
+                    })
+            }
+            IpAddr::V6(ip) => {
+                let ip = ip.into();
+                self.v6_lens
+                    .iter()
+                    .zip(self.v6_maps.iter())
+                    .find_map(|(&prefix_len, map)| {
+                        let addr = mask_v6(ip, prefix_len);
+                        map.get(&addr).map(|value| (v6_prefix(addr, prefix_len), value))
+                    })
+            }
+        }
+    }
+
+    /// Returns the number of prefixes in the map.
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.v4_maps.iter().map(MapWithDict::len).sum::<usize>()
+            + self.v6_maps.iter().map(MapWithDictU128::len).sum::<usize>()
+    }
+
+    /// Returns `true` if the map contains no prefixes.
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.v4_maps.is_empty() && self.v6_maps.is_empty()
+    }
+
+    /// Returns the total number of bytes occupied by the map.
+    #[inline]
+    pub fn size(&self) -> usize {
+        size_of_val(self)
+            + size_of_val(self.v4_lens.as_ref())
+            + self.v4_maps.iter().map(MapWithDict::size).sum::<usize>()
+            + size_of_val(self.v6_lens.as_ref())
+            + self.v6_maps.iter().map(MapWithDictU128::size).sum::<usize>()
+    }
+}
+
+/// Implement `lookup` for `Archived` version of `IpPrefixSet` if feature is enabled
+#[cfg(feature = "rkyv_derive")]
+impl ArchivedIpPrefixSet {
+    /// Returns the longest prefix containing `ip`, or `None` if no prefix contains it.
+    #[inline]
+    pub fn lookup(&self, ip: IpAddr) -> Option<IpPrefix> {
+        match ip {
+            IpAddr::V4(ip) => {
+                let ip = ip.into();
+                let (&prefix_len, _) = self
+                    .v4_lens
+                    .iter()
+                    .zip(self.v4_sets.iter())
+                    .find(|(&prefix_len, set)| set.contains(&mask_v4(ip, prefix_len)))?;
+                Some(v4_prefix(mask_v4(ip, prefix_len), prefix_len))
+            }
+            IpAddr::V6(ip) => {
+                let ip = ip.into();
+                let (&prefix_len, _) = self
+                    .v6_lens
+                    .iter()
+                    .zip(self.v6_sets.iter())
+                    .find(|(&prefix_len, set)| set.contains(&mask_v6(ip, prefix_len)))?;
+                Some(v6_prefix(mask_v6(ip, prefix_len), prefix_len))
+            }
+        }
+    }
+
+    /// Returns `true` if any prefix contains `ip`.
+    #[inline]
+    pub fn contains(&self, ip: IpAddr) -> bool {
+        self.lookup(ip).is_some()
+    }
+}
+
+/// Implement `lookup` for `Archived` version of `IpPrefixMap` if feature is enabled
+#[cfg(feature = "rkyv_derive")]
+impl<V: rkyv::Archive> ArchivedIpPrefixMap<V> {
+    /// Returns a reference to the archived value of the longest prefix containing `ip`, or `None`
+    /// if no prefix contains it.
+    #[inline]
+    pub fn lookup(&self, ip: IpAddr) -> Option<&V::Archived> {
+        self.lookup_prefix(ip).map(|(_, value)| value)
+    }
+
+    /// Same as [`ArchivedIpPrefixMap::lookup`], but returns the matched prefix along with its
+    /// archived value.
+    #[inline]
+    pub fn lookup_prefix(&self, ip: IpAddr) -> Option<(IpPrefix, &V::Archived)> {
+        match ip {
+            IpAddr::V4(ip) => {
+                let ip = ip.into();
+                self.v4_lens
+                    .iter()
+                    .zip(self.v4_maps.iter())
+                    .find_map(|(&prefix_len, map)| {
+                        let addr = mask_v4(ip, prefix_len);
+                        map.get(&addr).map(|value| (v4_prefix(addr, prefix_len), value))
+                    })
+            }
+            IpAddr::V6(ip) => {
+                let ip = ip.into();
+                self.v6_lens
+                    .iter()
+                    .zip(self.v6_maps.iter())
+                    .find_map(|(&prefix_len, map)| {
+                        let addr = mask_v6(ip, prefix_len);
+                        map.get(&addr).map(|value| (v6_prefix(addr, prefix_len), value))
+                    })
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mphf::DEFAULT_GAMMA;
+
+    fn prefix(s: &str) -> IpPrefix {
+        s.parse().unwrap()
+    }
+
+    fn ip(s: &str) -> IpAddr {
+        s.parse().unwrap()
+    }
+
+    #[test]
+    fn test_ip_prefix() {
+        assert_eq!(prefix("192.168.1.77/24").to_string(), "192.168.1.0/24");
+        assert_eq!(prefix("192.168.1.77").to_string(), "192.168.1.77/32");
+        assert_eq!(prefix("1.2.3.4/0").to_string(), "0.0.0.0/0");
+        assert_eq!(prefix("2001:db8:ffff::1/33").to_string(), "2001:db8:8000::/33");
+        assert_eq!(prefix("::1").prefix_len(), 128);
+        assert_eq!("::/129".parse::<IpPrefix>(), Err(IpPrefixError::InvalidLength));
+        assert_eq!("10.0.0.0/x".parse::<IpPrefix>(), Err(IpPrefixError::InvalidFormat));
+        assert_eq!("10.0.0/8".parse::<IpPrefix>(), Err(IpPrefixError::InvalidFormat));
+    }
+
+    #[test]
+    fn test_ip_prefix_map() {
+        let mut entries: Vec<(IpPrefix, u32)> = vec![];
+        // overlapping prefixes of every IPv4 length, plus unrelated /24 and /128 prefixes
+        for prefix_len in 0..=32 {
+            entries.push((IpPrefix::new(ip("10.20.30.40"), prefix_len).unwrap(), prefix_len as u32));
+        }
+        for i in 0..1000u32 {
+            entries.push((v4_prefix((172 << 24) | (i << 8), 24), 1000 + i));
+            entries.push((v6_prefix((0x2001_0db8 << 96) | i as u128, 128), 2000 + i));
+        }
+        entries.push((prefix("2001:db8::/32"), 3000));
+        let map = IpPrefixMap::from_iter_with_params(entries.clone(), DEFAULT_GAMMA).unwrap();
+        assert_eq!(map.len(), entries.len());
+        assert!(!map.is_empty());
+
+        assert_eq!(map.lookup(ip("10.20.30.40")), Some(&32));
+        assert_eq!(map.lookup(ip("10.20.30.41")), Some(&31));
+        assert_eq!(map.lookup(ip("10.20.31.0")), Some(&23));
+        assert_eq!(map.lookup(ip("11.0.0.0")), Some(&7));
+        assert_eq!(map.lookup(ip("200.0.0.0")), Some(&0));
+        assert_eq!(
+            map.lookup_prefix(ip("172.0.7.200")),
+            Some((prefix("172.0.7.0/24"), &1007))
+        );
+        assert_eq!(map.lookup(ip("2001:db8::7")), Some(&2007));
+        assert_eq!(map.lookup(ip("2001:db8::1:7")), Some(&3000));
+        assert_eq!(map.lookup(ip("2001:db9::")), None);
+
+        #[cfg(feature = "rkyv_derive")]
+        {
+            let rkyv_bytes = rkyv::to_bytes::<_, 1024>(&map).unwrap();
+            let rkyv_map = rkyv::check_archived_root::<IpPrefixMap<u32>>(&rkyv_bytes).unwrap();
+            assert_eq!(rkyv_map.lookup(ip("10.20.30.41")), Some(&31));
+            assert_eq!(rkyv_map.lookup(ip("2001:db8::7")), Some(&2007));
+            assert_eq!(rkyv_map.lookup(ip("2001:db9::")), None);
+        }
+
+        // later values of duplicate prefixes win
+        let map =
+            IpPrefixMap::from_iter_with_params([(prefix("10.0.0.0/8"), 1), (prefix("10.1.0.0/8"), 2)], 2.0).unwrap();
+        assert_eq!(map.len(), 1);
+        assert_eq!(map.lookup(ip("10.2.3.4")), Some(&2));
+
+        let map = IpPrefixMap::<u32>::from_iter_with_params([], DEFAULT_GAMMA).unwrap();
+        assert!(map.is_empty());
+        assert_eq!(map.lookup(ip("10.0.0.1")), None);
+    }
+
+    #[test]
+    fn test_ip_prefix_set() {
+        let prefixes = [
+            "10.0.0.0/8",
+            "10.1.0.0/16",
+            "192.168.0.0/24",
+            "2001:db8::/32",
+            "::1/128",
+        ]
+        .map(prefix);
+        let set = IpPrefixSet::from_iter_with_params(prefixes, DEFAULT_GAMMA).unwrap();
+        assert_eq!(set.len(), prefixes.len());
+        assert!(set.size() > 0);
+
+        assert_eq!(set.lookup(ip("10.1.0.1")), Some(prefix("10.1.0.0/16")));
+        assert_eq!(set.lookup(ip("10.2.0.1")), Some(prefix("10.0.0.0/8")));
+        assert!(set.contains(ip("192.168.0.255")));
+        assert!(!set.contains(ip("192.168.1.0")));
+        assert!(set.contains(ip("::1")));
+        assert!(!set.contains(ip("::2")));
+        // IPv4-mapped addresses only match after conversion
+        assert!(!set.contains(ip("::ffff:10.0.0.1")));
+
+        #[cfg(feature = "rkyv_derive")]
+        {
+            let rkyv_bytes = rkyv::to_bytes::<_, 1024>(&set).unwrap();
+            let rkyv_set = rkyv::check_archived_root::<IpPrefixSet>(&rkyv_bytes).unwrap();
+            assert_eq!(rkyv_set.lookup(ip("10.1.0.1")), Some(prefix("10.1.0.0/16")));
+            assert!(rkyv_set.contains(ip("2001:db8:1::")));
+            assert!(!rkyv_set.contains(ip("192.168.1.0")));
+        }
+    }
+}
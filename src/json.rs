@@ -0,0 +1,400 @@
+//! A module providing human-readable JSON dumps of maps and sets for debugging.
+//!
+//! `to_json_debug` dumps entries of a structure as `{"entries": [...]}`, with `[key, value]` pairs
+//! for maps and keys for sets, so that a misbehaving production artifact can be eyeballed without
+//! writing a custom program. `to_json_debug_with_stats` additionally includes a `"stats"` object
+//! with structural statistics: the number of keys and unique values, sizes and MPHF parameters.
+//!
+//! `from_json_debug` importers build a structure back from the `"entries"` of a dump (ignoring
+//! `"stats"`) with default construction parameters, e.g. to reproduce issues with hand-edited data.
+//! Entries are dumped in the internal order of the structure and may be listed in any order when
+//! imported.
+
+use std::hash::{Hash, Hasher};
+
+use num::{PrimInt, Unsigned};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use serde_json::{json, Value};
+
+use crate::map_with_dict::MapWithDict;
+use crate::map_with_dict_bitpacked::{self, MapWithDictBitpacked};
+use crate::map_with_dict_bool::MapWithDictBool;
+use crate::mphf::{Mphf, MphfError, DEFAULT_GAMMA};
+use crate::rank::RankedBitsLayout;
+use crate::set::Set;
+
+/// Errors that can occur when importing JSON dumps.
+#[derive(Debug)]
+pub enum JsonError {
+    /// Error when the dump is not valid JSON or its entries don't match keys and values types.
+    Parse(serde_json::Error),
+    /// Error occurred during MPHF construction.
+    MphfError(MphfError),
+    /// Error when values of a bit-packed or bool map have different lengths.
+    NotEqualValuesLengths,
+}
+
+impl From<serde_json::Error> for JsonError {
+    #[inline]
+    fn from(err: serde_json::Error) -> Self {
+        JsonError::Parse(err)
+    }
+}
+
+impl From<map_with_dict_bitpacked::Error> for JsonError {
+    #[inline]
+    fn from(err: map_with_dict_bitpacked::Error) -> Self {
+        match err {
+            map_with_dict_bitpacked::Error::MphfError(err) => JsonError::MphfError(err),
+            map_with_dict_bitpacked::Error::NotEqualValuesLengths => JsonError::NotEqualValuesLengths,
+        }
+    }
+}
+
+impl<K, V, const B: usize, const S: usize, ST, H> MapWithDict<K, V, B, S, ST, H>
+where
+    K: Eq + Hash + Clone,
+    V: Eq + Clone + Hash,
+    ST: PrimInt + Unsigned,
+    H: Hasher + Default,
+{
+    /// Dumps key-value pairs of the map as pretty-printed JSON, see the module documentation.
+    ///
+    /// # Examples
+    /// ```
+    /// # use std::collections::HashMap;
+    /// # use entropy_map::MapWithDict;
+    /// let map = MapWithDict::try_from(HashMap::from([(1, "a".to_string())])).unwrap();
+    /// assert_eq!(map.to_json_debug(), "{\n  \"entries\": [\n    [\n      1,\n      \"a\"\n    ]\n  ]\n}");
+    /// ```
+    pub fn to_json_debug(&self) -> String
+    where
+        K: Serialize,
+        V: Serialize,
+    {
+        to_pretty_json(json!({ "entries": self.iter().collect::<Vec<_>>() }))
+    }
+
+    /// Same as [`MapWithDict::to_json_debug`], but also dumps structural statistics.
+    ///
+    /// # Examples
+    /// ```
+    /// # use std::collections::HashMap;
+    /// # use entropy_map::MapWithDict;
+    /// let map = MapWithDict::try_from(HashMap::from([(1, 2), (3, 2)])).unwrap();
+    /// let json: serde_json::Value = serde_json::from_str(&map.to_json_debug_with_stats()).unwrap();
+    /// assert_eq!(json["stats"]["len"], 2);
+    /// assert_eq!(json["stats"]["unique_values"], 1);
+    /// ```
+    pub fn to_json_debug_with_stats(&self) -> String
+    where
+        K: Serialize,
+        V: Serialize,
+    {
+        to_pretty_json(json!({
+            "entries": self.iter().collect::<Vec<_>>(),
+            "stats": map_stats(self.len(), self.values_dict.len(), self.size(), &self.mphf),
+        }))
+    }
+
+    /// Builds a map from the entries of a JSON dump produced by [`MapWithDict::to_json_debug`].
+    ///
+    /// # Examples
+    /// ```
+    /// # use entropy_map::MapWithDict;
+    /// let map = MapWithDict::<u32, String>::from_json_debug(r#"{"entries": [[1, "a"], [2, "b"]]}"#).unwrap();
+    /// assert_eq!(map.get(&2).map(String::as_str), Some("b"));
+    /// ```
+    pub fn from_json_debug(json: &str) -> Result<Self, JsonError>
+    where
+        K: DeserializeOwned,
+        V: DeserializeOwned,
+    {
+        let entries: Vec<(K, V)> = parse_entries(json)?;
+        MapWithDict::from_iter_with_params(entries, DEFAULT_GAMMA).map_err(JsonError::MphfError)
+    }
+}
+
+impl<K, const B: usize, const S: usize, ST, H> MapWithDictBitpacked<K, B, S, ST, H>
+where
+    K: PartialEq + Hash + Clone,
+    ST: PrimInt + Unsigned,
+    H: Hasher + Default,
+{
+    /// Dumps keys and their `n` values as pretty-printed JSON, see the module documentation.
+    ///
+    /// # Examples
+    /// ```
+    /// # use std::collections::HashMap;
+    /// # use entropy_map::MapWithDictBitpacked;
+    /// let map = MapWithDictBitpacked::try_from(HashMap::from([(1, vec![2, 3])])).unwrap();
+    /// let json: serde_json::Value = serde_json::from_str(&map.to_json_debug(2)).unwrap();
+    /// assert_eq!(json["entries"][0], serde_json::json!([1, [2, 3]]));
+    /// ```
+    pub fn to_json_debug(&self, n: usize) -> String
+    where
+        K: Serialize,
+    {
+        to_pretty_json(json!({ "entries": self.iter(n).collect::<Vec<_>>() }))
+    }
+
+    /// Same as [`MapWithDictBitpacked::to_json_debug`], but also dumps structural statistics.
+    pub fn to_json_debug_with_stats(&self, n: usize) -> String
+    where
+        K: Serialize,
+    {
+        let unique_values = count_unique_offsets(&self.values_index);
+        let mut stats = map_stats(self.len(), unique_values, self.size(), &self.mphf);
+        stats["block_codec"] = json!(format!("{:?}", self.block_codec()));
+        stats["values_dict_bytes"] = json!(self.values_dict.len());
+        to_pretty_json(json!({ "entries": self.iter(n).collect::<Vec<_>>(), "stats": stats }))
+    }
+
+    /// Builds a map from the entries of a JSON dump produced by
+    /// [`MapWithDictBitpacked::to_json_debug`].
+    pub fn from_json_debug(json: &str) -> Result<Self, JsonError>
+    where
+        K: DeserializeOwned,
+    {
+        let entries: Vec<(K, Vec<u32>)> = parse_entries(json)?;
+        Ok(MapWithDictBitpacked::from_iter_with_params(entries, DEFAULT_GAMMA)?)
+    }
+}
+
+impl<K, const B: usize, const S: usize, ST, H> MapWithDictBool<K, B, S, ST, H>
+where
+    K: Hash + PartialEq + Clone,
+    ST: PrimInt + Unsigned,
+    H: Hasher + Default,
+{
+    /// Dumps keys and their `n` values as pretty-printed JSON, see the module documentation.
+    pub fn to_json_debug(&self, n: usize) -> String
+    where
+        K: Serialize,
+    {
+        to_pretty_json(json!({ "entries": self.iter(n).collect::<Vec<_>>() }))
+    }
+
+    /// Same as [`MapWithDictBool::to_json_debug`], but also dumps structural statistics.
+    pub fn to_json_debug_with_stats(&self, n: usize) -> String
+    where
+        K: Serialize,
+    {
+        let unique_values = count_unique_offsets(&self.values_index);
+        let mut stats = map_stats(self.len(), unique_values, self.size(), &self.mphf);
+        stats["values_dict_bytes"] = json!(self.values_dict.len());
+        to_pretty_json(json!({ "entries": self.iter(n).collect::<Vec<_>>(), "stats": stats }))
+    }
+
+    /// Builds a map from the entries of a JSON dump produced by [`MapWithDictBool::to_json_debug`].
+    pub fn from_json_debug(json: &str) -> Result<Self, JsonError>
+    where
+        K: DeserializeOwned,
+    {
+        let entries: Vec<(K, Vec<bool>)> = parse_entries(json)?;
+        Ok(MapWithDictBool::from_iter_with_params(entries, DEFAULT_GAMMA)?)
+    }
+}
+
+impl<K, const B: usize, const S: usize, ST, H> Set<K, B, S, ST, H>
+where
+    K: Eq + Hash,
+    ST: PrimInt + Unsigned,
+    H: Hasher + Default,
+{
+    /// Dumps keys of the set as pretty-printed JSON, see the module documentation.
+    ///
+    /// # Examples
+    /// ```
+    /// # use std::collections::HashSet;
+    /// # use entropy_map::Set;
+    /// let set: Set<u32> = Set::try_from(HashSet::from([1])).unwrap();
+    /// assert_eq!(set.to_json_debug(), "{\n  \"entries\": [\n    1\n  ]\n}");
+    /// ```
+    pub fn to_json_debug(&self) -> String
+    where
+        K: Serialize,
+    {
+        to_pretty_json(json!({ "entries": self.keys }))
+    }
+
+    /// Same as [`Set::to_json_debug`], but also dumps structural statistics.
+    pub fn to_json_debug_with_stats(&self) -> String
+    where
+        K: Serialize,
+    {
+        to_pretty_json(json!({ "entries": self.keys, "stats": stats(self.len(), self.size(), &self.mphf) }))
+    }
+
+    /// Builds a set from the entries of a JSON dump produced by [`Set::to_json_debug`].
+    ///
+    /// # Examples
+    /// ```
+    /// # use entropy_map::Set;
+    /// let set = Set::<String>::from_json_debug(r#"{"entries": ["a", "b"]}"#).unwrap();
+    /// assert!(set.contains("a"));
+    /// ```
+    pub fn from_json_debug(json: &str) -> Result<Self, JsonError>
+    where
+        K: DeserializeOwned,
+    {
+        let keys: Vec<K> = parse_entries(json)?;
+        Set::from_iter_with_params(keys, DEFAULT_GAMMA).map_err(JsonError::MphfError)
+    }
+}
+
+/// Returns statistics of a structure with `len` keys and `size` bytes built with `mphf`.
+fn stats<const B: usize, const S: usize, ST, H, R>(len: usize, size: usize, mphf: &Mphf<B, S, ST, H, R>) -> Value
+where
+    ST: PrimInt + Unsigned,
+    H: Hasher + Default,
+    R: RankedBitsLayout,
+{
+    let bits_per_key = if len == 0 { 0.0 } else { (size * 8) as f64 / len as f64 };
+    json!({
+        "len": len,
+        "size_bytes": size,
+        "bits_per_key": bits_per_key,
+        "mphf": {
+            "b": B,
+            "s": S,
+            "level_groups": mphf.level_groups(),
+            "bits_len": mphf.bits_len(),
+            "hash_seed": mphf.hash_seed(),
+            "build_seed": mphf.build_seed(),
+            "key_filter": mphf.key_filter().is_some(),
+            "fallback_keys": mphf.fallback_len(),
+            "size_bytes": mphf.size(),
+        },
+    })
+}
+
+/// Same as [`stats`], but also includes the number of `unique_values` of a map.
+fn map_stats<const B: usize, const S: usize, ST, H, R>(
+    len: usize,
+    unique_values: usize,
+    size: usize,
+    mphf: &Mphf<B, S, ST, H, R>,
+) -> Value
+where
+    ST: PrimInt + Unsigned,
+    H: Hasher + Default,
+    R: RankedBitsLayout,
+{
+    let mut stats = stats(len, size, mphf);
+    stats["unique_values"] = json!(unique_values);
+    stats
+}
+
+/// Returns the number of distinct dictionary offsets in `values_index`, i.e. of unique values.
+fn count_unique_offsets(values_index: &[usize]) -> usize {
+    let mut offsets = values_index.to_vec();
+    offsets.sort_unstable();
+    offsets.dedup();
+    offsets.len()
+}
+
+/// Parses `"entries"` of a JSON dump.
+fn parse_entries<T: DeserializeOwned>(json: &str) -> Result<Vec<T>, JsonError> {
+    let mut dump: Value = serde_json::from_str(json)?;
+    let entries = dump.get_mut("entries").map_or(Value::Null, Value::take);
+    Ok(serde_json::from_value(entries)?)
+}
+
+/// Serializes `value` as pretty-printed JSON.
+fn to_pretty_json(value: Value) -> String {
+    // `Value` serialization only fails for maps with non-string keys, which `Value` can't hold
+    serde_json::to_string_pretty(&value).expect("failed to serialize JSON value")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::{Rng, SeedableRng};
+    use rand_chacha::ChaCha8Rng;
+    use std::collections::{HashMap, HashSet};
+
+    #[test]
+    fn test_map_with_dict_json() {
+        let mut rng = ChaCha8Rng::seed_from_u64(123);
+        let original_map: HashMap<u64, String> = (0..1000)
+            .map(|_| (rng.gen(), rng.gen_range(1..=10).to_string()))
+            .collect();
+        let map = MapWithDict::try_from(original_map.clone()).unwrap();
+
+        let imported = MapWithDict::<u64, String>::from_json_debug(&map.to_json_debug()).unwrap();
+        assert_eq!(imported.len(), original_map.len());
+        for (k, v) in &original_map {
+            assert_eq!(imported.get(k), Some(v));
+        }
+
+        // stats are ignored on import
+        let json = map.to_json_debug_with_stats();
+        let imported = MapWithDict::<u64, String>::from_json_debug(&json).unwrap();
+        assert_eq!(imported.len(), original_map.len());
+
+        let json: Value = serde_json::from_str(&json).unwrap();
+        assert_eq!(json["stats"]["len"], 1000);
+        assert_eq!(json["stats"]["unique_values"], 10);
+        assert_eq!(json["stats"]["size_bytes"], map.size());
+        assert_eq!(json["stats"]["mphf"]["b"], 32);
+        assert_eq!(json["stats"]["mphf"]["level_groups"], json!(map.mphf.level_groups()));
+    }
+
+    #[test]
+    fn test_bitpacked_and_bool_json() {
+        let mut rng = ChaCha8Rng::seed_from_u64(123);
+        let original_map: HashMap<u64, Vec<u32>> = (0..1000)
+            .map(|_| (rng.gen(), (0..5).map(|_| rng.gen_range(0..100)).collect()))
+            .collect();
+        let map = MapWithDictBitpacked::try_from(original_map.clone()).unwrap();
+        let imported = MapWithDictBitpacked::<u64>::from_json_debug(&map.to_json_debug_with_stats(5)).unwrap();
+        let mut values = vec![0; 5];
+        for (k, v) in &original_map {
+            assert!(imported.get_values(k, &mut values));
+            assert_eq!(&values, v);
+        }
+
+        let original_map: HashMap<u64, Vec<bool>> = (0..1000)
+            .map(|_| (rng.gen(), (0..5).map(|_| rng.gen()).collect()))
+            .collect();
+        let map = MapWithDictBool::try_from(original_map.clone()).unwrap();
+        let imported = MapWithDictBool::<u64>::from_json_debug(&map.to_json_debug(5)).unwrap();
+        let mut values = vec![false; 5];
+        for (k, v) in &original_map {
+            assert!(imported.get_values(k, &mut values));
+            assert_eq!(&values, v);
+        }
+
+        let json: Value = serde_json::from_str(&map.to_json_debug_with_stats(5)).unwrap();
+        assert_eq!(json["stats"]["unique_values"], 32);
+
+        assert!(matches!(
+            MapWithDictBool::<u64>::from_json_debug(r#"{"entries": [[1, [true]], [2, []]]}"#),
+            Err(JsonError::NotEqualValuesLengths)
+        ));
+    }
+
+    #[test]
+    fn test_set_json() {
+        let keys: HashSet<String> = (0..100).map(|i| format!("key-{i}")).collect();
+        let set = Set::try_from(keys.clone()).unwrap();
+
+        let imported = Set::<String>::from_json_debug(&set.to_json_debug_with_stats()).unwrap();
+        assert_eq!(imported.len(), keys.len());
+        for key in &keys {
+            assert!(imported.contains(key));
+        }
+
+        assert!(matches!(
+            Set::<u32>::from_json_debug("[1, 2]"),
+            Err(JsonError::Parse(_))
+        ));
+        assert!(matches!(Set::<u32>::from_json_debug("{"), Err(JsonError::Parse(_))));
+        assert!(matches!(
+            Set::<u32>::from_json_debug(r#"{"entries": ["a"]}"#),
+            Err(JsonError::Parse(_))
+        ));
+    }
+}
@@ -0,0 +1,290 @@
+//! A module providing `KeyFilter`, a blocked Bloom filter over key hashes which lets MPHF queries
+//! reject most absent keys before walking the levels.
+//!
+//! A query for a key absent from the structure walks every MPHF level until it finds a set bit or
+//! runs out of levels, and then compares the found key, which is the most expensive path for
+//! miss-heavy workloads. `KeyFilter` is built from the same key hashes as the MPHF and maps every
+//! hash to a single cache line sized block, in which a few bits derived from the hash are checked,
+//! so absent keys are usually rejected after one cache line probe. Present keys are never rejected.
+//!
+//! The filter is enabled with [`BuildPolicy::with_key_filter`](crate::BuildPolicy::with_key_filter),
+//! e.g. 8 bits per key reject ~97% of absent keys, or with
+//! [`BuildPolicy::with_key_filter_fpr`](crate::BuildPolicy::with_key_filter_fpr) choosing the number
+//! of bits per key from the number of keys and a target false positive rate. Flat and FlatBuffers exports don't include the
+//! filter, which only affects their performance.
+
+use std::mem::size_of_val;
+
+use crate::access;
+
+/// Number of words in every `FilterBlock`.
+const FILTER_BLOCK_WORDS: usize = 8;
+
+/// Number of bits in every `FilterBlock`.
+const FILTER_BLOCK_BITS: usize = FILTER_BLOCK_WORDS * 64;
+
+/// Number of hash bits selecting a bit within a block.
+const PROBE_BITS: u32 = FILTER_BLOCK_BITS.trailing_zeros();
+
+/// Maximum number of bits checked per key, limited by the number of probe bits in a 64-bit hash.
+const MAX_PROBES: u32 = 64 / PROBE_BITS;
+
+/// Maximum number of bits per key chosen by `KeyFilter::bits_per_key_for_fpr`, with an expected
+/// false positive rate of ~1e-7.
+const MAX_BITS_PER_KEY: u32 = 64;
+
+/// Odd multiplier deriving probe bits from the key hash, independent of bits selecting the block.
+const PROBE_MULTIPLIER: u64 = 0x9e3779b97f4a7c15;
+
+/// Cache line sized block of `KeyFilter` bits.
+#[derive(Debug, Default, Clone, Copy)]
+#[cfg_attr(feature = "rkyv_derive", derive(rkyv::Archive, rkyv::Deserialize, rkyv::Serialize))]
+#[cfg_attr(feature = "rkyv_derive", archive_attr(derive(rkyv::CheckBytes)))]
+#[repr(C, align(64))]
+pub struct FilterBlock(pub(crate) [u64; FILTER_BLOCK_WORDS]);
+
+/// Blocked Bloom filter over key hashes, see the module documentation.
+///
+/// # Examples
+/// ```
+/// # use entropy_map::KeyFilter;
+/// let filter = KeyFilter::from_hashes(&[1, 2, 3], 8);
+/// assert!(filter.contains(1));
+/// assert_eq!(filter.bits_per_key(), 8);
+/// ```
+#[derive(Debug, Default)]
+#[cfg_attr(feature = "rkyv_derive", derive(rkyv::Archive, rkyv::Deserialize, rkyv::Serialize))]
+#[cfg_attr(feature = "rkyv_derive", archive_attr(derive(rkyv::CheckBytes)))]
+pub struct KeyFilter {
+    /// Filter bits
+    pub(crate) blocks: Box<[FilterBlock]>,
+    /// Number of bits set and checked per key
+    pub(crate) probes: u32,
+    /// Number of filter bits per key requested at construction
+    pub(crate) bits_per_key: u32,
+}
+
+impl KeyFilter {
+    /// Builds a filter from key `hashes` with `bits_per_key` bits per key, which is clamped to
+    /// at least 1.
+    pub fn from_hashes(hashes: &[u64], bits_per_key: u32) -> Self {
+        let bits_per_key = bits_per_key.max(1);
+        let probes = Self::probes(bits_per_key);
+        let blocks_len = (hashes.len() * bits_per_key as usize).div_ceil(FILTER_BLOCK_BITS);
+        let mut blocks = vec![FilterBlock::default(); blocks_len].into_boxed_slice();
+
+        for &hash in hashes {
+            let block = &mut blocks[Self::block_idx(hash, blocks_len)].0;
+            Self::for_each_probe(hash, probes, |bit| block[bit / 64] |= 1 << (bit % 64));
+        }
+
+        KeyFilter { blocks, probes, bits_per_key }
+    }
+
+    /// Returns `false` if the key with `hash` is definitely absent, `true` if it may be present.
+    #[inline]
+    pub fn contains(&self, hash: u64) -> bool {
+        Self::contains_impl(&self.blocks, self.probes, hash)
+    }
+
+    /// Returns the expected false positive rate, i.e. the share of absent keys not rejected, of a
+    /// filter built from `keys` hashes with `bits_per_key` bits per key.
+    ///
+    /// # Examples
+    /// ```
+    /// # use entropy_map::KeyFilter;
+    /// let fpr = KeyFilter::expected_fpr(1_000_000, 8);
+    /// assert!(fpr > 0.02 && fpr < 0.04);
+    /// ```
+    pub fn expected_fpr(keys: usize, bits_per_key: u32) -> f64 {
+        if keys == 0 {
+            return 0.0;
+        }
+        let bits_per_key = bits_per_key.max(1);
+        let probes = Self::probes(bits_per_key);
+        let blocks_len = (keys * bits_per_key as usize).div_ceil(FILTER_BLOCK_BITS);
+        let mean_keys = keys as f64 / blocks_len as f64;
+
+        // keys per block follow a Poisson distribution, and an absent key is not rejected by a
+        // block of `block_keys` keys if all its probed bits were set by them
+        let mut block_keys_probability = (-mean_keys).exp();
+        let mut fpr = 0.0;
+        for block_keys in 0..4 * FILTER_BLOCK_BITS {
+            let bit_unset = (1.0 - 1.0 / FILTER_BLOCK_BITS as f64).powi((probes as usize * block_keys) as i32);
+            fpr += block_keys_probability * (1.0 - bit_unset).powi(probes as i32);
+            block_keys_probability *= mean_keys / (block_keys + 1) as f64;
+        }
+        fpr
+    }
+
+    /// Returns the smallest number of bits per key whose [`KeyFilter::expected_fpr`] for `keys`
+    /// keys doesn't exceed `fpr`, capped at 64 bits per key.
+    ///
+    /// # Examples
+    /// ```
+    /// # use entropy_map::KeyFilter;
+    /// let bits_per_key = KeyFilter::bits_per_key_for_fpr(1_000_000, 1e-3);
+    /// assert!(KeyFilter::expected_fpr(1_000_000, bits_per_key) <= 1e-3);
+    /// assert!(KeyFilter::expected_fpr(1_000_000, bits_per_key - 1) > 1e-3);
+    /// ```
+    pub fn bits_per_key_for_fpr(keys: usize, fpr: f64) -> u32 {
+        (1..MAX_BITS_PER_KEY)
+            .find(|&bits_per_key| Self::expected_fpr(keys, bits_per_key) <= fpr)
+            .unwrap_or(MAX_BITS_PER_KEY)
+    }
+
+    /// Returns the number of filter bits per key requested at construction.
+    #[inline]
+    pub fn bits_per_key(&self) -> u32 {
+        self.bits_per_key
+    }
+
+    /// Returns the total number of bytes occupied by `KeyFilter`.
+    #[inline]
+    pub fn size(&self) -> usize {
+        size_of_val(self) + size_of_val(self.blocks.as_ref())
+    }
+
+    /// Inner implementation of `contains` with `blocks` and `probes` passed from standard and
+    /// `Archived` versions.
+    #[inline]
+    fn contains_impl<T: AsRef<[u64; FILTER_BLOCK_WORDS]>>(blocks: &[T], probes: u32, hash: u64) -> bool {
+        if blocks.is_empty() {
+            return false;
+        }
+        // SAFETY: `block_idx` is always less than `blocks.len()`
+        let block = unsafe { access::get(blocks, Self::block_idx(hash, blocks.len())) }.as_ref();
+        let mut found = 1;
+        Self::for_each_probe(hash, probes, |bit| found &= block[bit / 64] >> (bit % 64));
+        found & 1 == 1
+    }
+
+    /// Returns the number of bits set and checked per key for `bits_per_key` bits per key.
+    #[inline]
+    fn probes(bits_per_key: u32) -> u32 {
+        // optimal number of probes of a Bloom filter is `bits_per_key * ln(2)`
+        ((bits_per_key as f64 * std::f64::consts::LN_2).round() as u32).clamp(1, MAX_PROBES)
+    }
+
+    /// Maps `hash` to a block index in `0..blocks_len` range using its high bits.
+    #[inline]
+    fn block_idx(hash: u64, blocks_len: usize) -> usize {
+        ((hash as u128 * blocks_len as u128) >> 64) as usize
+    }
+
+    /// Calls `f` with every bit index within a block probed for `hash`.
+    #[inline]
+    fn for_each_probe(hash: u64, probes: u32, mut f: impl FnMut(usize)) {
+        let mut h = hash.wrapping_mul(PROBE_MULTIPLIER);
+        for _ in 0..probes {
+            f((h >> (64 - PROBE_BITS)) as usize);
+            h = h.rotate_left(PROBE_BITS);
+        }
+    }
+}
+
+impl AsRef<[u64; FILTER_BLOCK_WORDS]> for FilterBlock {
+    #[inline]
+    fn as_ref(&self) -> &[u64; FILTER_BLOCK_WORDS] {
+        &self.0
+    }
+}
+
+#[cfg(feature = "rkyv_derive")]
+impl AsRef<[u64; FILTER_BLOCK_WORDS]> for ArchivedFilterBlock {
+    #[inline]
+    fn as_ref(&self) -> &[u64; FILTER_BLOCK_WORDS] {
+        &self.0
+    }
+}
+
+/// Implement `contains` for `Archived` version of `KeyFilter` if feature is enabled
+#[cfg(feature = "rkyv_derive")]
+impl ArchivedKeyFilter {
+    /// Returns `false` if the key with `hash` is definitely absent, `true` if it may be present.
+    #[inline]
+    pub fn contains(&self, hash: u64) -> bool {
+        KeyFilter::contains_impl(&self.blocks, self.probes, hash)
+    }
+
+    /// Returns the number of filter bits per key requested at construction.
+    #[inline]
+    pub fn bits_per_key(&self) -> u32 {
+        self.bits_per_key
+    }
+
+    /// Returns the bytes of the filter blocks, e.g. to prefault them.
+    #[inline]
+    pub(crate) fn bytes(&self) -> &[u8] {
+        crate::rank::as_bytes(&self.blocks)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::{Rng, SeedableRng};
+    use rand_chacha::ChaCha8Rng;
+    use test_case::test_case;
+
+    #[test_case(4, 0.20; "4 bits per key")]
+    #[test_case(8, 0.04; "8 bits per key")]
+    #[test_case(12, 0.01; "12 bits per key")]
+    fn test_key_filter(bits_per_key: u32, max_fpr: f64) {
+        let mut rng = ChaCha8Rng::seed_from_u64(123);
+        let hashes: Vec<u64> = (0..100_000).map(|_| rng.gen()).collect();
+        let filter = KeyFilter::from_hashes(&hashes, bits_per_key);
+
+        // no false negatives
+        assert!(hashes.iter().all(|&hash| filter.contains(hash)));
+
+        let absent = 100_000;
+        let false_positives = (0..absent).filter(|_| filter.contains(rng.gen())).count();
+        let fpr = false_positives as f64 / absent as f64;
+        assert!(fpr < max_fpr, "fpr = {fpr}");
+
+        let expected_size = hashes.len() * bits_per_key as usize / 8;
+        assert!(filter.size() >= expected_size && filter.size() < expected_size + 128);
+    }
+
+    #[test_case(1e-2; "1e-2")]
+    #[test_case(1e-3; "1e-3")]
+    #[test_case(1e-4; "1e-4")]
+    fn test_bits_per_key_for_fpr(target_fpr: f64) {
+        let mut rng = ChaCha8Rng::seed_from_u64(123);
+        let hashes: Vec<u64> = (0..100_000).map(|_| rng.gen()).collect();
+        let bits_per_key = KeyFilter::bits_per_key_for_fpr(hashes.len(), target_fpr);
+        assert!(KeyFilter::expected_fpr(hashes.len(), bits_per_key) <= target_fpr);
+        assert!(KeyFilter::expected_fpr(hashes.len(), bits_per_key - 1) > target_fpr);
+
+        let filter = KeyFilter::from_hashes(&hashes, bits_per_key);
+        let absent = 1_000_000;
+        let false_positives = (0..absent).filter(|_| filter.contains(rng.gen())).count();
+        let fpr = false_positives as f64 / absent as f64;
+        assert!(fpr < 1.5 * target_fpr, "fpr = {fpr}, bits per key = {bits_per_key}");
+    }
+
+    #[test]
+    fn test_empty() {
+        let filter = KeyFilter::from_hashes(&[], 8);
+        assert!(!filter.contains(0));
+        assert_eq!(filter.size(), size_of_val(&filter));
+    }
+
+    #[cfg(feature = "rkyv_derive")]
+    #[test]
+    fn test_rkyv() {
+        let mut rng = ChaCha8Rng::seed_from_u64(123);
+        let hashes: Vec<u64> = (0..1000).map(|_| rng.gen()).collect();
+        let filter = KeyFilter::from_hashes(&hashes, 8);
+
+        let rkyv_bytes = rkyv::to_bytes::<_, 1024>(&filter).unwrap();
+        let rkyv_filter = rkyv::check_archived_root::<KeyFilter>(&rkyv_bytes).unwrap();
+
+        assert_eq!(rkyv_filter.bits_per_key(), 8);
+        for _ in 0..1000 {
+            let hash = rng.gen();
+            assert_eq!(rkyv_filter.contains(hash), filter.contains(hash));
+        }
+    }
+}
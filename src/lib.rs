@@ -1,11 +1,162 @@
+mod access;
+#[cfg(feature = "alloc-stats")]
+pub mod alloc_stats;
+#[cfg(feature = "unchecked-archives")]
+pub mod archive;
+#[cfg(feature = "rkyv_derive")]
+pub mod archive_align;
+mod bit_slice;
+#[cfg(feature = "borsh")]
+mod borsh;
+#[cfg(feature = "tokio")]
+pub mod build_async;
+#[cfg(feature = "flat")]
+pub mod compact_map_with_dict;
+#[cfg(feature = "fbs_export")]
+pub mod fbs;
+#[cfg(feature = "ffi")]
+pub mod ffi;
+#[cfg(feature = "flat")]
+pub mod flat;
+#[cfg(feature = "fuzzing")]
+#[doc(hidden)]
+pub mod fuzzing;
+#[cfg(feature = "json_debug")]
+pub mod json;
+#[cfg(feature = "rayon")]
+pub mod parallel;
+#[cfg(feature = "poppy")]
+pub mod poppy;
+#[cfg(feature = "rank9")]
+pub mod rank9;
+#[cfg(feature = "rand")]
+mod sample;
+#[cfg(feature = "simd")]
+mod simd;
+
+pub mod block_codec;
+pub mod build_hasher;
+pub mod cow_key;
+pub mod diff;
+pub mod duplicates;
+pub mod entropy_key;
+pub mod external_build;
+pub mod fixed_key;
+pub mod format;
+pub mod hashed_key;
+pub mod invariants;
+pub mod ip_prefix;
+pub mod key_filter;
 pub mod map_with_dict;
 pub mod map_with_dict_bitpacked;
+pub mod map_with_dict_bitpacked_ranges;
+pub mod map_with_dict_bitpacked_shard;
+pub mod map_with_dict_bool;
+pub mod map_with_dict_codec;
+pub mod map_with_dict_default;
+pub mod map_with_dict_flags;
+pub mod map_with_dict_ref;
+pub mod map_with_dict_timestamps;
+pub mod map_with_dict_unchecked;
+pub mod monotone_mphf;
 pub mod mphf;
+pub mod mphf_auto;
+pub mod mphf_dyn;
+pub mod mphf_extras;
+pub mod normalized;
+#[cfg(feature = "rkyv_derive")]
+pub mod owned_archive;
+pub mod partitioned_mphf;
+pub mod pooled_values;
+pub mod prelude;
+pub mod progress;
+pub mod pthash;
 pub mod rank;
+pub mod recsplit;
+pub mod report;
+pub mod semi_dynamic_mphf;
 pub mod set;
+pub mod sip_hasher;
+pub mod size_report;
+#[cfg(feature = "rkyv_derive")]
+pub mod static_archive;
+pub mod static_table;
+#[cfg(feature = "rkyv_derive")]
+pub mod store;
+pub mod u128_keys;
+pub mod value_codec;
 
+#[cfg(feature = "alloc-stats")]
+pub use alloc_stats::*;
+#[cfg(feature = "unchecked-archives")]
+pub use archive::*;
+#[cfg(feature = "rkyv_derive")]
+pub use archive_align::*;
+pub use block_codec::*;
+#[cfg(feature = "tokio")]
+pub use build_async::*;
+pub use build_hasher::*;
+#[cfg(feature = "flat")]
+pub use compact_map_with_dict::*;
+pub use cow_key::*;
+pub use diff::*;
+pub use duplicates::*;
+pub use entropy_key::*;
+pub use external_build::*;
+#[cfg(feature = "fbs_export")]
+pub use fbs::*;
+#[cfg(feature = "ffi")]
+pub use ffi::*;
+pub use fixed_key::*;
+#[cfg(feature = "flat")]
+pub use flat::*;
+pub use format::*;
+pub use hashed_key::*;
+pub use invariants::*;
+pub use ip_prefix::*;
+#[cfg(feature = "json_debug")]
+pub use json::*;
+pub use key_filter::*;
 pub use map_with_dict::*;
 pub use map_with_dict_bitpacked::*;
+pub use map_with_dict_bitpacked_ranges::*;
+pub use map_with_dict_bitpacked_shard::*;
+pub use map_with_dict_bool::*;
+pub use map_with_dict_codec::*;
+pub use map_with_dict_default::*;
+pub use map_with_dict_flags::*;
+pub use map_with_dict_ref::*;
+pub use map_with_dict_timestamps::*;
+pub use map_with_dict_unchecked::*;
+pub use monotone_mphf::*;
 pub use mphf::*;
+pub use mphf_auto::*;
+pub use mphf_dyn::*;
+pub use mphf_extras::*;
+pub use normalized::*;
+#[cfg(feature = "rkyv_derive")]
+pub use owned_archive::*;
+#[cfg(feature = "rayon")]
+pub use parallel::*;
+pub use partitioned_mphf::*;
+pub use pooled_values::*;
+#[cfg(feature = "poppy")]
+pub use poppy::*;
+pub use progress::*;
+pub use pthash::*;
 pub use rank::*;
+#[cfg(feature = "rank9")]
+pub use rank9::*;
+pub use recsplit::*;
+pub use report::*;
+pub use semi_dynamic_mphf::*;
 pub use set::*;
+pub use sip_hasher::*;
+pub use size_report::*;
+#[cfg(feature = "rkyv_derive")]
+pub use static_archive::*;
+pub use static_table::*;
+#[cfg(feature = "rkyv_derive")]
+pub use store::*;
+pub use u128_keys::*;
+pub use value_codec::*;
@@ -8,46 +8,140 @@
 //! wasn't present in original set.
 
 use std::borrow::Borrow;
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::hash::{Hash, Hasher};
 use std::mem::size_of_val;
+use std::time::Instant;
 
 use num::{PrimInt, Unsigned};
 use wyhash::WyHash;
 
-use crate::mphf::{Mphf, MphfError, DEFAULT_GAMMA};
+use crate::access;
+use crate::hashed_key::HashedKey;
+use crate::invariants::{self, InvariantError};
+use crate::mphf::{BuildPolicy, Mphf, MphfError, DEFAULT_GAMMA};
+#[cfg(feature = "rkyv_derive")]
+use crate::mphf_extras::ExtrasAccess;
+use crate::mphf_extras::{Extras, ExtrasLayout, NoExtras};
+use crate::progress::BuildProgress;
+use crate::rank::RankedBits;
+use crate::report::{AllocScope, BuildReport};
+use crate::sip_hasher::SipHasher13;
+use crate::size_report::{self, SizeReport};
+
+/// `MapWithDict` with 64-bit groups, trading slightly slower construction and queries for a
+/// smaller MPHF (~3% fewer bits per key than the defaults).
+pub type MapCompact<K, V> = MapWithDict<K, V, 64, 8>;
+
+/// `MapWithDict` with 16-bit groups and 16 seeds per group, trading ~25% more MPHF bits per key
+/// than the defaults for several times faster construction.
+pub type MapFast<K, V> = MapWithDict<K, V, 16, 4>;
+
+/// `MapWithDict` hashing keys with `SipHasher13`, to be built with a random hash seed via
+/// [`BuildPolicy::keyed`] when keys come from untrusted input.
+pub type MapKeyed<K, V> = MapWithDict<K, V, 32, 8, u8, SipHasher13, Extras>;
+
+/// Parts of a `MapWithDict` returned by [`MapWithDict::into_raw_parts`]: the MPHF, keys and values
+/// indices stored at the indices the MPHF maps keys to, and the values dictionary.
+pub type MapWithDictRawParts<K, V, const B: usize = 32, const S: usize = 8, ST = u8, H = WyHash, E = NoExtras> =
+    (Mphf<B, S, ST, H, RankedBits, E>, Box<[K]>, Box<[usize]>, Box<[V]>);
 
 /// An efficient, immutable hash map with values dictionary-packed for optimized space usage.
 #[derive(Default)]
 #[cfg_attr(feature = "rkyv_derive", derive(rkyv::Archive, rkyv::Deserialize, rkyv::Serialize))]
 #[cfg_attr(feature = "rkyv_derive", archive_attr(derive(rkyv::CheckBytes)))]
-pub struct MapWithDict<K, V, const B: usize = 32, const S: usize = 8, ST = u8, H = WyHash>
+pub struct MapWithDict<K, V, const B: usize = 32, const S: usize = 8, ST = u8, H = WyHash, E = NoExtras>
 where
     ST: PrimInt + Unsigned,
     H: Hasher + Default,
+    E: ExtrasLayout,
 {
     /// Minimally Perfect Hash Function for keys indices retrieval
-    mphf: Mphf<B, S, ST, H>,
+    pub(crate) mphf: Mphf<B, S, ST, H, RankedBits, E>,
     /// Map keys
-    keys: Box<[K]>,
+    pub(crate) keys: Box<[K]>,
     /// Points to the value index in the dictionary
-    values_index: Box<[usize]>,
+    pub(crate) values_index: Box<[usize]>,
     /// Map unique values
-    values_dict: Box<[V]>,
+    pub(crate) values_dict: Box<[V]>,
 }
 
-impl<K, V, const B: usize, const S: usize, ST, H> MapWithDict<K, V, B, S, ST, H>
+impl<K, V, const B: usize, const S: usize, ST, H, E> MapWithDict<K, V, B, S, ST, H, E>
 where
     K: Eq + Hash + Clone,
     V: Eq + Clone + Hash,
     ST: PrimInt + Unsigned,
     H: Hasher + Default,
+    E: ExtrasLayout,
 {
     /// Constructs a `MapWithDict` from an iterator of key-value pairs and MPHF function params.
     pub fn from_iter_with_params<I>(iter: I, gamma: f32) -> Result<Self, MphfError>
     where
         I: IntoIterator<Item = (K, V)>,
     {
+        Self::from_iter_with_report(iter, gamma).map(|(map, _)| map)
+    }
+
+    /// Constructs a `MapWithDict` from a `HashMap` and MPHF function params, which unlike
+    /// `TryFrom<HashMap>` works with any `B`, `S`, `ST` and `H`.
+    ///
+    /// # Examples
+    /// ```
+    /// # use std::collections::HashMap;
+    /// # use entropy_map::{MapFast, DEFAULT_GAMMA};
+    /// let map = MapFast::from_hashmap_with_gamma(HashMap::from([(1, 2), (3, 4)]), DEFAULT_GAMMA).unwrap();
+    /// assert_eq!(map.get(&1), Some(&2));
+    /// ```
+    pub fn from_hashmap_with_gamma(map: HashMap<K, V>, gamma: f32) -> Result<Self, MphfError> {
+        Self::from_iter_with_params(map, gamma)
+    }
+
+    /// Constructs a `MapWithDict` from an iterator of key-value pairs and MPHF function params,
+    /// additionally returning a `BuildReport` with construction statistics.
+    ///
+    /// # Examples
+    /// ```
+    /// # use entropy_map::{MapWithDict, DEFAULT_GAMMA};
+    /// let (map, report) = MapWithDict::<u32, u32>::from_iter_with_report([(1, 2), (3, 2)], DEFAULT_GAMMA).unwrap();
+    /// assert_eq!(report.keys(), 2);
+    /// assert_eq!(report.dedup_ratio, Some(2.0));
+    /// ```
+    pub fn from_iter_with_report<I>(iter: I, gamma: f32) -> Result<(Self, BuildReport), MphfError>
+    where
+        I: IntoIterator<Item = (K, V)>,
+    {
+        Self::from_iter_with_policy(iter, &BuildPolicy::fixed(gamma))
+    }
+
+    /// Constructs a `MapWithDict` from an iterator of key-value pairs, escalating MPHF `gamma` on
+    /// failed builds according to `policy`, and returns it along with a `BuildReport`.
+    ///
+    /// # Examples
+    /// ```
+    /// # use entropy_map::{BuildPolicy, MapWithDict};
+    /// let policy = BuildPolicy::default();
+    /// let (map, report) = MapWithDict::<u32, u32>::from_iter_with_policy([(1, 2), (3, 2)], &policy).unwrap();
+    /// assert_eq!(report.attempts, 1);
+    /// ```
+    pub fn from_iter_with_policy<I>(iter: I, policy: &BuildPolicy) -> Result<(Self, BuildReport), MphfError>
+    where
+        I: IntoIterator<Item = (K, V)>,
+    {
+        Self::from_iter_with_progress(iter, policy, &BuildProgress::default())
+    }
+
+    /// Same as [`MapWithDict::from_iter_with_policy`], but reports MPHF construction progress to
+    /// `progress` and fails once cancellation is requested via it.
+    pub fn from_iter_with_progress<I>(
+        iter: I,
+        policy: &BuildPolicy,
+        progress: &BuildProgress,
+    ) -> Result<(Self, BuildReport), MphfError>
+    where
+        I: IntoIterator<Item = (K, V)>,
+    {
+        let t0 = Instant::now();
+        let alloc_scope = AllocScope::start();
         let mut keys = vec![];
         let mut values_index = vec![];
         let mut values_dict = vec![];
@@ -68,7 +162,21 @@ where
             }
         }
 
-        let mphf = Mphf::from_slice(&keys, gamma)?;
+        Self::from_indexed_with_progress(keys, values_index, values_dict, policy, progress, t0, alloc_scope)
+    }
+
+    /// Builds the MPHF over `keys` and re-orders `keys` and `values_index` pointing into the
+    /// already deduplicated `values_dict` accordingly.
+    fn from_indexed_with_progress(
+        mut keys: Vec<K>,
+        mut values_index: Vec<usize>,
+        values_dict: Vec<V>,
+        policy: &BuildPolicy,
+        progress: &BuildProgress,
+        t0: Instant,
+        alloc_scope: AllocScope,
+    ) -> Result<(Self, BuildReport), MphfError> {
+        let (mphf, mut report) = Mphf::from_slice_with_progress(&keys, policy, progress)?;
 
         // Re-order `keys` and `values_index` according to `mphf`
         for i in 0..keys.len() {
@@ -82,12 +190,86 @@ where
             }
         }
 
-        Ok(MapWithDict {
+        let map = MapWithDict {
             mphf,
             keys: keys.into_boxed_slice(),
             values_index: values_index.into_boxed_slice(),
             values_dict: values_dict.into_boxed_slice(),
-        })
+        };
+
+        alloc_scope.finish(&mut report);
+        report.duration = t0.elapsed();
+        report.set_size(map.size());
+        report.set_dedup(map.values_index.len(), map.values_dict.len());
+
+        Ok((map, report))
+    }
+
+    /// Assembles a `MapWithDict` from a prebuilt `mphf`, `keys` and `values_index` stored at the
+    /// indices `mphf` maps keys to, and `values_dict` the values index points into. Fails if the
+    /// parts don't match, see [`MapWithDict::debug_check_invariants`].
+    ///
+    /// # Examples
+    /// ```
+    /// # use entropy_map::{MapWithDict, Mphf, DEFAULT_GAMMA};
+    /// let mut entries = vec![(1u32, 0), (2, 1), (3, 0)];
+    /// let keys: Vec<u32> = entries.iter().map(|&(key, _)| key).collect();
+    /// let mphf: Mphf = Mphf::from_slice(&keys, DEFAULT_GAMMA).unwrap();
+    /// entries.sort_by_key(|(key, _)| mphf.get(key).unwrap());
+    /// let (keys, values_index): (Vec<_>, Vec<_>) = entries.into_iter().unzip();
+    /// let map: MapWithDict<u32, &str> =
+    ///     MapWithDict::from_parts(mphf, keys.into(), values_index.into(), vec!["a", "b"].into()).unwrap();
+    /// assert_eq!(map.get(&2), Some(&"b"));
+    /// assert_eq!(map.get(&3), Some(&"a"));
+    /// ```
+    pub fn from_parts(
+        mphf: Mphf<B, S, ST, H, RankedBits, E>,
+        keys: Box<[K]>,
+        values_index: Box<[usize]>,
+        values_dict: Box<[V]>,
+    ) -> Result<Self, InvariantError> {
+        invariants::check_keys(&mphf, &keys)?;
+        invariants::check_values_index(&values_index, keys.len(), values_dict.len())?;
+        Ok(MapWithDict { mphf, keys, values_index, values_dict })
+    }
+
+    /// Same as [`MapWithDict::from_parts`], but skips checking the parts against each other.
+    ///
+    /// # Safety
+    /// `keys` and `values_index` must hold exactly one entry per `mphf` output, each stored at the
+    /// index `mphf` maps the key to, and every values index must be less than `values_dict` length,
+    /// as queries access them without bounds checks.
+    pub unsafe fn from_parts_unchecked(
+        mphf: Mphf<B, S, ST, H, RankedBits, E>,
+        keys: Box<[K]>,
+        values_index: Box<[usize]>,
+        values_dict: Box<[V]>,
+    ) -> Self {
+        MapWithDict { mphf, keys, values_index, values_dict }
+    }
+
+    /// Decomposes the map into its MPHF, keys, values indices and values dictionary, e.g. to
+    /// migrate values into a different layout without rebuilding the MPHF.
+    ///
+    /// # Examples
+    /// ```
+    /// # use std::collections::HashMap;
+    /// # use entropy_map::MapWithDict;
+    /// let map = MapWithDict::try_from(HashMap::from([(1, 2), (3, 4)])).unwrap();
+    /// let (mphf, keys, values_index, values_dict) = map.into_raw_parts();
+    /// let values_dict = values_dict.iter().map(|v| v.to_string()).collect();
+    /// let map = MapWithDict::from_raw_parts((mphf, keys, values_index, values_dict)).unwrap();
+    /// assert_eq!(map.get(&3), Some(&"4".to_string()));
+    /// ```
+    pub fn into_raw_parts(self) -> MapWithDictRawParts<K, V, B, S, ST, H, E> {
+        (self.mphf, self.keys, self.values_index, self.values_dict)
+    }
+
+    /// Inverse of [`MapWithDict::into_raw_parts`], checking the parts like
+    /// [`MapWithDict::from_parts`].
+    pub fn from_raw_parts(parts: MapWithDictRawParts<K, V, B, S, ST, H, E>) -> Result<Self, InvariantError> {
+        let (mphf, keys, values_index, values_dict) = parts;
+        Self::from_parts(mphf, keys, values_index, values_dict)
     }
 
     /// Returns a reference to the value corresponding to the key. Returns `None` if the key is
@@ -107,20 +289,113 @@ where
         K: Borrow<Q> + PartialEq<Q>,
         Q: Hash + Eq + ?Sized,
     {
-        let idx = self.mphf.get(key)?;
+        self.get_impl(self.mphf.get(key), key)
+    }
+
+    /// Same as [`MapWithDict::get`], but re-uses the hash precomputed by `HashedKey`.
+    ///
+    /// # Examples
+    /// ```
+    /// # use std::collections::HashMap;
+    /// # use entropy_map::{HashedKey, MapWithDict};
+    /// let map = MapWithDict::try_from(HashMap::from([(1, 2), (3, 4)])).unwrap();
+    /// assert_eq!(map.get_hashed(&HashedKey::new(&1)), Some(&2));
+    /// assert_eq!(map.get_hashed(&HashedKey::new(&5)), None);
+    /// ```
+    #[inline]
+    pub fn get_hashed<Q>(&self, key: &HashedKey<Q, H>) -> Option<&V>
+    where
+        K: Borrow<Q> + PartialEq<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        self.get_impl(self.mphf.get_hashed(key), key.key())
+    }
+
+    /// Same as [`MapWithDict::get`], but skips hashing `key` and uses `hash` computed with the map
+    /// hasher `H` instead (e.g. via [`HashedKey::hash`]). The key is still compared with the stored
+    /// one, so a mismatching `hash` results in `None` rather than a wrong value.
+    ///
+    /// # Examples
+    /// ```
+    /// # use std::collections::HashMap;
+    /// # use entropy_map::{HashedKey, MapWithDict};
+    /// let map = MapWithDict::try_from(HashMap::from([(1, 2), (3, 4)])).unwrap();
+    /// let hash = HashedKey::<_>::new(&1).hash();
+    /// assert_eq!(map.get_with_hash(hash, &1), Some(&2));
+    /// assert_eq!(map.get_with_hash(hash, &3), None);
+    /// ```
+    #[inline]
+    pub fn get_with_hash<Q>(&self, hash: u64, key: &Q) -> Option<&V>
+    where
+        K: Borrow<Q> + PartialEq<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        self.get_impl(self.mphf.get_by_hash(hash), key)
+    }
+
+    /// Inner implementation of `get` returning the value at MPHF index `idx` if the key stored
+    /// there is equal to `key`.
+    #[inline]
+    fn get_impl<Q>(&self, idx: Option<usize>, key: &Q) -> Option<&V>
+    where
+        K: PartialEq<Q>,
+        Q: ?Sized,
+    {
+        let idx = idx?;
 
         // SAFETY: `idx` is always within bounds (ensured during construction)
         unsafe {
-            if self.keys.get_unchecked(idx) == key {
+            if access::get(&self.keys, idx) == key {
                 // SAFETY: `idx` and `value_idx` are always within bounds (ensure during construction)
-                let value_idx = *self.values_index.get_unchecked(idx);
-                Some(self.values_dict.get_unchecked(value_idx))
+                let value_idx = *access::get(&self.values_index, idx);
+                Some(access::get(&self.values_dict, value_idx))
             } else {
                 None
             }
         }
     }
 
+    /// Returns a copy of the value corresponding to the key. Returns `None` if the key is not
+    /// present in the map.
+    ///
+    /// # Examples
+    /// ```
+    /// # use std::collections::HashMap;
+    /// # use entropy_map::MapWithDict;
+    /// let map = MapWithDict::try_from(HashMap::from([(1, 2), (3, 4)])).unwrap();
+    /// assert_eq!(map.get_copied(&1), Some(2));
+    /// assert_eq!(map.get_copied(&5), None);
+    /// ```
+    #[inline]
+    pub fn get_copied<Q>(&self, key: &Q) -> Option<V>
+    where
+        K: Borrow<Q> + PartialEq<Q>,
+        Q: Hash + Eq + ?Sized,
+        V: Copy,
+    {
+        self.get(key).copied()
+    }
+
+    /// Returns a reference to the value corresponding to the key, or `default` if the key is not
+    /// present in the map.
+    ///
+    /// # Examples
+    /// ```
+    /// # use std::collections::HashMap;
+    /// # use entropy_map::MapWithDict;
+    /// let map = MapWithDict::try_from(HashMap::from([(1, 2), (3, 4)])).unwrap();
+    /// assert_eq!(map.get_or(&1, &0), &2);
+    /// assert_eq!(map.get_or(&5, &0), &0);
+    /// ```
+    #[inline]
+    pub fn get_or<'a, Q>(&'a self, key: &Q, default: &'a V) -> &'a V
+    where
+        K: Borrow<Q> + PartialEq<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        self.get(key).unwrap_or(default)
+    }
+
     /// Returns the number of key-value pairs in the map.
     ///
     /// # Examples
@@ -151,6 +426,40 @@ where
         self.keys.is_empty()
     }
 
+    /// Returns the key stored at `idx`, or `None` if `idx` is out of bounds. Keys are stored in
+    /// MPHF order, so indices `0..len()` map to every key once in arbitrary order.
+    ///
+    /// # Examples
+    /// ```
+    /// # use std::collections::HashMap;
+    /// # use entropy_map::MapWithDict;
+    /// let map = MapWithDict::try_from(HashMap::from([(1, 2), (3, 4)])).unwrap();
+    /// assert_eq!(map.get(map.nth_key(0).unwrap()).is_some(), true);
+    /// assert_eq!(map.nth_key(2), None);
+    /// ```
+    #[inline]
+    pub fn nth_key(&self, idx: usize) -> Option<&K> {
+        self.keys.get(idx)
+    }
+
+    /// Returns `k` distinct keys (or all keys if the map has fewer) chosen uniformly at random by
+    /// `rng`, e.g. to probe a sample of keys of a deployed map end-to-end.
+    ///
+    /// # Examples
+    /// ```
+    /// # use std::collections::HashMap;
+    /// # use entropy_map::MapWithDict;
+    /// let map = MapWithDict::try_from(HashMap::from([(1, 2), (3, 4), (5, 6)])).unwrap();
+    /// let keys = map.sample_keys(2, &mut rand::thread_rng());
+    /// assert_eq!(keys.len(), 2);
+    /// assert!(keys.iter().all(|key| map.contains_key(*key)));
+    /// ```
+    #[cfg(feature = "rand")]
+    #[inline]
+    pub fn sample_keys<R: rand::Rng + ?Sized>(&self, k: usize, rng: &mut R) -> Vec<&K> {
+        crate::sample::sample_keys(&self.keys, k, rng)
+    }
+
     /// Checks if the map contains the specified key.
     ///
     /// # Examples
@@ -167,9 +476,38 @@ where
         K: Borrow<Q> + PartialEq<Q>,
         Q: Hash + Eq + ?Sized,
     {
-        if let Some(idx) = self.mphf.get(key) {
+        self.contains_key_impl(self.mphf.get(key), key)
+    }
+
+    /// Same as [`MapWithDict::contains_key`], but re-uses the hash precomputed by `HashedKey`.
+    ///
+    /// # Examples
+    /// ```
+    /// # use std::collections::HashMap;
+    /// # use entropy_map::{HashedKey, MapWithDict};
+    /// let map = MapWithDict::try_from(HashMap::from([(1, 2), (3, 4)])).unwrap();
+    /// assert!(map.contains_key_hashed(&HashedKey::new(&1)));
+    /// assert!(!map.contains_key_hashed(&HashedKey::new(&2)));
+    /// ```
+    #[inline]
+    pub fn contains_key_hashed<Q>(&self, key: &HashedKey<Q, H>) -> bool
+    where
+        K: Borrow<Q> + PartialEq<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        self.contains_key_impl(self.mphf.get_hashed(key), key.key())
+    }
+
+    /// Inner implementation of `contains_key` checking the key stored at MPHF index `idx`.
+    #[inline]
+    fn contains_key_impl<Q>(&self, idx: Option<usize>, key: &Q) -> bool
+    where
+        K: PartialEq<Q>,
+        Q: ?Sized,
+    {
+        if let Some(idx) = idx {
             // SAFETY: `idx` is always within bounds (ensured during construction)
-            unsafe { self.keys.get_unchecked(idx) == key }
+            unsafe { access::get(&self.keys, idx) == key }
         } else {
             false
         }
@@ -187,13 +525,13 @@ where
     /// }
     /// ```
     #[inline]
-    pub fn iter(&self) -> impl Iterator<Item = (&K, &V)> {
+    pub fn iter(&self) -> impl ExactSizeIterator<Item = (&K, &V)> + DoubleEndedIterator {
         self.keys
             .iter()
             .zip(self.values_index.iter())
             .map(move |(key, &value_idx)| {
                 // SAFETY: `value_idx` is always within bounds (ensured during construction)
-                let value = unsafe { self.values_dict.get_unchecked(value_idx) };
+                let value = unsafe { access::get(&self.values_dict, value_idx) };
                 (key, value)
             })
     }
@@ -210,7 +548,7 @@ where
     /// }
     /// ```
     #[inline]
-    pub fn keys(&self) -> impl Iterator<Item = &K> {
+    pub fn keys(&self) -> impl ExactSizeIterator<Item = &K> + DoubleEndedIterator {
         self.keys.iter()
     }
 
@@ -226,13 +564,74 @@ where
     /// }
     /// ```
     #[inline]
-    pub fn values(&self) -> impl Iterator<Item = &V> {
+    pub fn values(&self) -> impl ExactSizeIterator<Item = &V> + DoubleEndedIterator {
         self.values_index.iter().map(move |&value_idx| {
             // SAFETY: `value_idx` is always within bounds (ensured during construction)
-            unsafe { self.values_dict.get_unchecked(value_idx) }
+            unsafe { access::get(&self.values_dict, value_idx) }
         })
     }
 
+    /// Returns the dictionary of unique values of the map, in no particular order.
+    ///
+    /// # Examples
+    /// ```
+    /// # use std::collections::HashMap;
+    /// # use entropy_map::MapWithDict;
+    /// let map = MapWithDict::try_from(HashMap::from([(1, 2), (3, 2), (5, 6)])).unwrap();
+    /// let mut values_dict = map.values_dict().to_vec();
+    /// values_dict.sort();
+    /// assert_eq!(values_dict, vec![2, 6]);
+    /// ```
+    #[inline]
+    pub fn values_dict(&self) -> &[V] {
+        &self.values_dict
+    }
+
+    /// Returns the seed hashed before every key, see [`Mphf::hash_seed`].
+    #[inline]
+    pub fn hash_seed(&self) -> u64 {
+        self.mphf.hash_seed()
+    }
+
+    /// Returns the number of unique values of the map.
+    ///
+    /// # Examples
+    /// ```
+    /// # use std::collections::HashMap;
+    /// # use entropy_map::MapWithDict;
+    /// let map = MapWithDict::try_from(HashMap::from([(1, 2), (3, 2), (5, 6)])).unwrap();
+    /// assert_eq!(map.dict_len(), 2);
+    /// ```
+    #[inline]
+    pub fn dict_len(&self) -> usize {
+        self.values_dict.len()
+    }
+
+    /// Returns the index of the value corresponding to the key in [`MapWithDict::values_dict`].
+    /// Returns `None` if the key is not present in the map.
+    ///
+    /// # Examples
+    /// ```
+    /// # use std::collections::HashMap;
+    /// # use entropy_map::MapWithDict;
+    /// let map = MapWithDict::try_from(HashMap::from([(1, 2), (3, 2), (5, 6)])).unwrap();
+    /// let value_idx = map.value_index_of(&1).unwrap();
+    /// assert_eq!(map.values_dict()[value_idx], 2);
+    /// assert_eq!(map.value_index_of(&3), Some(value_idx));
+    /// assert_eq!(map.value_index_of(&4), None);
+    /// ```
+    #[inline]
+    pub fn value_index_of<Q>(&self, key: &Q) -> Option<usize>
+    where
+        K: Borrow<Q> + PartialEq<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        let idx = self.mphf.get(key)?;
+
+        // SAFETY: `idx` is always within bounds (ensured during construction)
+        unsafe { (access::get(&self.keys, idx) == key).then(|| *access::get(&self.values_index, idx)) }
+    }
+
     /// Returns the total number of bytes occupied by the structure.
     ///
     /// # Examples
@@ -240,7 +639,7 @@ where
     /// # use std::collections::HashMap;
     /// # use entropy_map::MapWithDict;
     /// let map = MapWithDict::try_from(HashMap::from([(1, 2), (3, 4)])).unwrap();
-    /// assert_eq!(map.size(), 270);
+    /// assert_eq!(map.size(), 254);
     /// ```
     #[inline]
     pub fn size(&self) -> usize {
@@ -250,6 +649,149 @@ where
             + size_of_val(self.values_index.as_ref())
             + size_of_val(self.values_dict.as_ref())
     }
+
+    /// Returns the size of the map compared with an estimate of the equivalent `HashMap<K, V>`,
+    /// see [`SizeReport`].
+    ///
+    /// # Examples
+    /// ```
+    /// # use std::collections::HashMap;
+    /// # use entropy_map::MapWithDict;
+    /// let map = MapWithDict::try_from((0..10000u64).map(|i| (i, i % 10)).collect::<HashMap<_, _>>()).unwrap();
+    /// let report = map.size_report();
+    /// assert_eq!(report.size, map.size());
+    /// assert!(report.savings_ratio() > 1.5);
+    /// ```
+    pub fn size_report(&self) -> SizeReport {
+        SizeReport {
+            keys: self.len(),
+            size: self.size(),
+            std_size: size_report::std_table_size::<HashMap<K, V>, (K, V)>(self.len()),
+        }
+    }
+
+    /// Checks invariants of the MPHF, that every key is mapped by it to the index it's stored at and
+    /// that every values index points within the values dictionary, e.g. for maps reassembled by
+    /// custom deserialization code.
+    ///
+    /// # Examples
+    /// ```
+    /// # use std::collections::HashMap;
+    /// # use entropy_map::MapWithDict;
+    /// let map = MapWithDict::try_from(HashMap::from([(1, 2), (3, 4)])).unwrap();
+    /// assert_eq!(map.debug_check_invariants(), Ok(()));
+    /// ```
+    pub fn debug_check_invariants(&self) -> Result<(), InvariantError> {
+        invariants::check_keys(&self.mphf, &self.keys)?;
+        invariants::check_values_index(&self.values_index, self.keys.len(), self.values_dict.len())
+    }
+
+    /// Rebuilds the map from its entries and `updates` with `DEFAULT_GAMMA`. Entries in `updates`
+    /// replace values of existing keys and insert new keys, the last value wins for repeated keys.
+    ///
+    /// # Examples
+    /// ```
+    /// # use std::collections::HashMap;
+    /// # use entropy_map::MapWithDict;
+    /// let map = MapWithDict::try_from(HashMap::from([(1, 2), (3, 4)])).unwrap();
+    /// let map = map.with_updates([(3, 5), (6, 7)]).unwrap();
+    /// assert_eq!(map.len(), 3);
+    /// assert_eq!(map.get(&1), Some(&2));
+    /// assert_eq!(map.get(&3), Some(&5));
+    /// assert_eq!(map.get(&6), Some(&7));
+    /// ```
+    pub fn with_updates<I>(&self, updates: I) -> Result<Self, MphfError>
+    where
+        I: IntoIterator<Item = (K, V)>,
+    {
+        self.rebuild_with(updates, [])
+    }
+
+    /// Rebuilds the map with `DEFAULT_GAMMA`, the same hash seed and key filter applying `upserts`
+    /// and then `deletes` to its entries, so a key present in both is removed. Values dictionary
+    /// offsets of unchanged entries are re-used as is, only upserted values are looked up in the
+    /// dictionary, and values no longer referenced are dropped from it.
+    ///
+    /// # Examples
+    /// ```
+    /// # use std::collections::HashMap;
+    /// # use entropy_map::MapWithDict;
+    /// let map = MapWithDict::try_from(HashMap::from([(1, 2), (3, 4), (5, 6)])).unwrap();
+    /// let map = map.rebuild_with([(3, 2), (7, 8)], [5]).unwrap();
+    /// assert_eq!(map.len(), 3);
+    /// assert_eq!(map.get(&3), Some(&2));
+    /// assert_eq!(map.get(&5), None);
+    /// assert_eq!(map.get(&7), Some(&8));
+    /// ```
+    pub fn rebuild_with<U, D>(&self, upserts: U, deletes: D) -> Result<Self, MphfError>
+    where
+        U: IntoIterator<Item = (K, V)>,
+        D: IntoIterator<Item = K>,
+    {
+        let t0 = Instant::now();
+        let alloc_scope = AllocScope::start();
+        let mut upserts: HashMap<K, V> = upserts.into_iter().collect();
+        let deletes: HashSet<K> = deletes.into_iter().collect();
+
+        let mut values_dict = self.values_dict.to_vec();
+        let mut offsets_cache: HashMap<V, usize> = HashMap::new();
+        let mut offset_of = |v: V| {
+            if offsets_cache.is_empty() {
+                offsets_cache.extend(values_dict.iter().cloned().zip(0..));
+            }
+            *offsets_cache.entry(v).or_insert_with_key(|v| {
+                values_dict.push(v.clone());
+                values_dict.len() - 1
+            })
+        };
+
+        let capacity = self.len() + upserts.len();
+        let mut keys = Vec::with_capacity(capacity);
+        let mut values_index = Vec::with_capacity(capacity);
+        for (k, &offset) in self.keys.iter().zip(self.values_index.iter()) {
+            let upsert = upserts.remove(k);
+            if deletes.contains(k) {
+                continue;
+            }
+            keys.push(k.clone());
+            values_index.push(upsert.map_or(offset, &mut offset_of));
+        }
+        for (k, v) in upserts {
+            if !deletes.contains(&k) {
+                keys.push(k);
+                values_index.push(offset_of(v));
+            }
+        }
+
+        // Drop values no longer referenced and shift offsets of the remaining ones
+        let mut new_offsets = vec![None; values_dict.len()];
+        for &offset in &values_index {
+            new_offsets[offset] = Some(0);
+        }
+        for (values_num, new_offset) in new_offsets.iter_mut().flatten().enumerate() {
+            *new_offset = values_num;
+        }
+        for offset in values_index.iter_mut() {
+            *offset = new_offsets[*offset].unwrap();
+        }
+        let values_dict = values_dict
+            .into_iter()
+            .zip(&new_offsets)
+            .filter_map(|(v, new_offset)| new_offset.map(|_| v))
+            .collect();
+
+        let policy = self.mphf.rebuild_policy();
+        Self::from_indexed_with_progress(
+            keys,
+            values_index,
+            values_dict,
+            &policy,
+            &BuildProgress::default(),
+            t0,
+            alloc_scope,
+        )
+        .map(|(map, _)| map)
+    }
 }
 
 /// Creates a `MapWithDict` from a `HashMap`.
@@ -268,13 +810,15 @@ where
 
 /// Implement `get` for `Archived` version of `MapWithDict` if feature is enabled
 #[cfg(feature = "rkyv_derive")]
-impl<K, V, const B: usize, const S: usize, ST, H> ArchivedMapWithDict<K, V, B, S, ST, H>
+impl<K, V, const B: usize, const S: usize, ST, H, E> ArchivedMapWithDict<K, V, B, S, ST, H, E>
 where
     K: PartialEq + Hash + rkyv::Archive,
     K::Archived: PartialEq<K>,
     V: rkyv::Archive,
     ST: PrimInt + Unsigned + rkyv::Archive<Archived = ST>,
     H: Hasher + Default,
+    E: ExtrasLayout + rkyv::Archive,
+    rkyv::Archived<E>: ExtrasAccess<KeyFilter = crate::key_filter::ArchivedKeyFilter>,
 {
     /// Checks if the map contains the specified key.
     ///
@@ -290,15 +834,30 @@ where
     /// assert_eq!(archived_map.contains_key(&2), false);
     /// ```
     #[inline]
-    pub fn contains_key<Q: ?Sized>(&self, key: &Q) -> bool
+    pub fn contains_key<Q>(&self, key: &Q) -> bool
     where
         K: Borrow<Q>,
         <K as rkyv::Archive>::Archived: PartialEq<Q>,
-        Q: Hash + Eq,
+        Q: Hash + Eq + ?Sized,
     {
         if let Some(idx) = self.mphf.get(key) {
             // SAFETY: `idx` is always within bounds (ensured during construction)
-            unsafe { self.keys.get_unchecked(idx) == key }
+            unsafe { access::get(&self.keys, idx) == key }
+        } else {
+            false
+        }
+    }
+
+    /// Same as [`ArchivedMapWithDict::contains_key`], but re-uses the hash precomputed by `HashedKey`.
+    #[inline]
+    pub fn contains_key_hashed<Q: ?Sized>(&self, key: &HashedKey<Q, H>) -> bool
+    where
+        K: Borrow<Q>,
+        <K as rkyv::Archive>::Archived: PartialEq<Q>,
+    {
+        if let Some(idx) = self.mphf.get_hashed(key) {
+            // SAFETY: `idx` is always within bounds (ensured during construction)
+            unsafe { access::get(&self.keys, idx) == key.key() }
         } else {
             false
         }
@@ -319,35 +878,109 @@ where
     /// assert_eq!(archived_map.get(&5), None);
     /// ```
     #[inline]
-    pub fn get<Q: ?Sized>(&self, key: &Q) -> Option<&V::Archived>
+    pub fn get<Q>(&self, key: &Q) -> Option<&V::Archived>
     where
         K: Borrow<Q>,
         <K as rkyv::Archive>::Archived: PartialEq<Q>,
-        Q: Hash + Eq,
+        Q: Hash + Eq + ?Sized,
     {
-        let idx = self.mphf.get(key)?;
+        self.get_impl(self.mphf.get(key), key)
+    }
+
+    /// Same as [`ArchivedMapWithDict::get`], but re-uses the hash precomputed by `HashedKey`.
+    #[inline]
+    pub fn get_hashed<Q: ?Sized>(&self, key: &HashedKey<Q, H>) -> Option<&V::Archived>
+    where
+        K: Borrow<Q>,
+        <K as rkyv::Archive>::Archived: PartialEq<Q>,
+    {
+        self.get_impl(self.mphf.get_hashed(key), key.key())
+    }
+
+    /// Same as [`ArchivedMapWithDict::get`], but skips hashing `key` and uses `hash` computed with
+    /// the map hasher `H` instead.
+    #[inline]
+    pub fn get_with_hash<Q: ?Sized>(&self, hash: u64, key: &Q) -> Option<&V::Archived>
+    where
+        K: Borrow<Q>,
+        <K as rkyv::Archive>::Archived: PartialEq<Q>,
+    {
+        self.get_impl(self.mphf.get_by_hash(hash), key)
+    }
+
+    /// Inner implementation of `get` returning the value at MPHF index `idx` if the key stored
+    /// there is equal to `key`.
+    #[inline]
+    fn get_impl<Q: ?Sized>(&self, idx: Option<usize>, key: &Q) -> Option<&V::Archived>
+    where
+        <K as rkyv::Archive>::Archived: PartialEq<Q>,
+    {
+        let idx = idx?;
 
         // SAFETY: `idx` is always within bounds (ensured during construction)
         unsafe {
-            if self.keys.get_unchecked(idx) == key {
+            if access::get(&self.keys, idx) == key {
                 // SAFETY: `idx` and `value_idx` are always within bounds (ensure during construction)
-                let value_idx = *self.values_index.get_unchecked(idx) as usize;
-                Some(self.values_dict.get_unchecked(value_idx))
+                let value_idx = *access::get(&self.values_index, idx) as usize;
+                Some(access::get(&self.values_dict, value_idx))
             } else {
                 None
             }
         }
     }
 
+    /// Returns the archived dictionary of unique values of the map.
+    #[inline]
+    pub fn values_dict(&self) -> &[V::Archived] {
+        &self.values_dict
+    }
+
+    /// Returns the index of the value corresponding to the key in
+    /// [`ArchivedMapWithDict::values_dict`]. Returns `None` if the key is not present in the map.
+    #[inline]
+    pub fn value_index_of<Q>(&self, key: &Q) -> Option<usize>
+    where
+        K: Borrow<Q>,
+        <K as rkyv::Archive>::Archived: PartialEq<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        let idx = self.mphf.get(key)?;
+
+        // SAFETY: `idx` is always within bounds (ensured during construction)
+        unsafe { (access::get(&self.keys, idx) == key).then(|| *access::get(&self.values_index, idx) as usize) }
+    }
+
+    /// Returns the archived MPHF of the map, e.g. to
+    /// [`prefault_levels`](crate::ArchivedMphf::prefault_levels) of a memory-mapped archive.
+    #[inline]
+    pub fn mphf(&self) -> &crate::mphf::ArchivedMphf<B, S, ST, H, RankedBits, E> {
+        &self.mphf
+    }
+
+    /// Returns the archived key stored at `idx`, or `None` if `idx` is out of bounds, see
+    /// [`MapWithDict::nth_key`].
+    #[inline]
+    pub fn nth_key(&self, idx: usize) -> Option<&K::Archived> {
+        self.keys.as_ref().get(idx)
+    }
+
+    /// Returns `k` distinct archived keys chosen uniformly at random by `rng`, see
+    /// [`MapWithDict::sample_keys`].
+    #[cfg(feature = "rand")]
+    #[inline]
+    pub fn sample_keys<R: rand::Rng + ?Sized>(&self, k: usize, rng: &mut R) -> Vec<&K::Archived> {
+        crate::sample::sample_keys(&self.keys, k, rng)
+    }
+
     /// Returns an iterator over the archived map, yielding archived key-value pairs.
     #[inline]
-    pub fn iter(&self) -> impl Iterator<Item = (&K::Archived, &V::Archived)> {
+    pub fn iter(&self) -> impl ExactSizeIterator<Item = (&K::Archived, &V::Archived)> + DoubleEndedIterator {
         self.keys
             .iter()
             .zip(self.values_index.iter())
             .map(move |(key, &value_idx)| {
                 // SAFETY: `value_idx` is always within bounds (ensured during construction)
-                let value = unsafe { self.values_dict.get_unchecked(value_idx as usize) };
+                let value = unsafe { access::get(&self.values_dict, value_idx as usize) };
                 (key, value)
             })
     }
@@ -413,6 +1046,215 @@ mod tests {
         assert_eq!(map.size(), 16626);
     }
 
+    #[test]
+    fn test_iter_exact_size_double_ended() {
+        let map = MapWithDict::try_from(gen_map(1000)).unwrap();
+
+        assert_eq!(map.iter().len(), map.len());
+        assert_eq!(map.keys().len(), map.len());
+        assert_eq!(map.values().len(), map.len());
+
+        let forward: Vec<_> = map.iter().collect();
+        let mut backward: Vec<_> = map.iter().rev().collect();
+        backward.reverse();
+        assert_eq!(forward, backward);
+
+        let mut keys = map.keys();
+        keys.next_back();
+        assert_eq!(keys.len(), map.len() - 1);
+    }
+
+    #[test]
+    fn test_type_aliases() {
+        let original_map = gen_map(1000);
+        let compact_map = MapCompact::from_hashmap_with_gamma(original_map.clone(), DEFAULT_GAMMA).unwrap();
+        let fast_map = MapFast::from_hashmap_with_gamma(original_map.clone(), DEFAULT_GAMMA).unwrap();
+
+        for (key, value) in &original_map {
+            assert_eq!(compact_map.get(key), Some(value));
+            assert_eq!(fast_map.get(key), Some(value));
+        }
+        assert!(compact_map.mphf.size() < fast_map.mphf.size());
+    }
+
+    #[test]
+    fn test_keyed() {
+        let original_map = gen_map(1000);
+        let (map, _) = MapKeyed::from_iter_with_policy(original_map.clone(), &BuildPolicy::keyed()).unwrap();
+        assert_ne!(map.hash_seed(), 0);
+        for (key, value) in &original_map {
+            assert_eq!(map.get(key), Some(value));
+            assert_eq!(map.get_hashed(&HashedKey::with_seed(key, map.hash_seed())), Some(value));
+        }
+
+        // rebuilds keep the seed
+        let updated = map.with_updates([(0, 1)]).unwrap();
+        assert_eq!(updated.hash_seed(), map.hash_seed());
+        assert_eq!(updated.get(&0), Some(&1));
+
+        #[cfg(feature = "rkyv_derive")]
+        {
+            let rkyv_bytes = rkyv::to_bytes::<_, 1024>(&map).unwrap();
+            let rkyv_map = rkyv::check_archived_root::<MapKeyed<u64, u32>>(&rkyv_bytes).unwrap();
+            assert_eq!(rkyv_map.mphf.hash_seed(), map.hash_seed());
+            for (key, value) in &original_map {
+                assert_eq!(rkyv_map.get(key), Some(value));
+            }
+        }
+    }
+
+    #[test]
+    fn test_with_updates() {
+        let original_map = gen_map(1000);
+        let map = MapWithDict::try_from(original_map.clone()).unwrap();
+
+        let mut expected = original_map.clone();
+        let updates: Vec<(u64, u32)> = original_map
+            .keys()
+            .take(10)
+            .map(|&k| (k, 100))
+            .chain((0..100).map(|k| (k, 200)))
+            .collect();
+        expected.extend(updates.iter().copied());
+        let updated = map.with_updates(updates).unwrap();
+
+        assert_eq!(updated.len(), expected.len());
+        for (key, value) in &expected {
+            assert_eq!(updated.get(key), Some(value));
+        }
+    }
+
+    #[test]
+    fn test_rebuild_with() {
+        let original_map = gen_map(1000);
+        let map = MapWithDict::try_from(original_map.clone()).unwrap();
+
+        // delete all keys with value 1, re-map value 2 to 11 and insert new keys
+        let deletes: Vec<u64> = original_map.iter().filter(|(_, &v)| v == 1).map(|(&k, _)| k).collect();
+        let upserts: Vec<(u64, u32)> = original_map
+            .iter()
+            .filter(|(_, &v)| v == 2)
+            .map(|(&k, _)| (k, 11))
+            .chain((0..100).map(|k| (k, 12)))
+            .collect();
+        let mut expected = original_map.clone();
+        expected.retain(|_, v| *v != 1);
+        expected.extend(upserts.iter().copied());
+
+        let rebuilt = map.rebuild_with(upserts, deletes).unwrap();
+
+        assert_eq!(rebuilt.len(), expected.len());
+        for (key, value) in &expected {
+            assert_eq!(rebuilt.get(key), Some(value));
+        }
+        let mut values_dict = rebuilt.values_dict.to_vec();
+        values_dict.sort();
+        assert_eq!(values_dict, vec![3, 4, 5, 6, 7, 8, 9, 10, 11, 12]);
+    }
+
+    #[test]
+    fn test_values_dict() {
+        let original_map = gen_map(1000);
+        let map = MapWithDict::try_from(original_map.clone()).unwrap();
+
+        assert_eq!(map.dict_len(), 10);
+        assert_eq!(map.values_dict().len(), map.dict_len());
+        for (key, value) in &original_map {
+            let value_idx = map.value_index_of(key).unwrap();
+            assert_eq!(&map.values_dict()[value_idx], value);
+        }
+        assert_eq!(map.value_index_of(&0), None);
+    }
+
+    #[test]
+    fn test_build_report() {
+        let original_map = gen_map(1000);
+        let (map, report) =
+            MapWithDict::<u64, u32>::from_iter_with_report(original_map.clone(), DEFAULT_GAMMA).unwrap();
+
+        assert_eq!(report.keys(), original_map.len());
+        assert_eq!(report.dedup_ratio, Some(original_map.len() as f32 / 10.0));
+        assert_eq!(report.bits_per_key, (map.size() * 8) as f32 / original_map.len() as f32);
+    }
+
+    #[test]
+    fn test_debug_check_invariants() {
+        let original_map = gen_map(1000);
+        let build = || MapWithDict::try_from(original_map.clone()).unwrap();
+
+        let mut map = build();
+        map.keys.swap(3, 7);
+        assert_eq!(
+            map.debug_check_invariants(),
+            Err(InvariantError::KeyIndexMismatch { idx: 3 })
+        );
+
+        let mut map = build();
+        map.values_index[5] = map.values_dict.len();
+        assert_eq!(
+            map.debug_check_invariants(),
+            Err(InvariantError::ValueIndexOutOfBounds { idx: 5 })
+        );
+
+        let mut map = build();
+        map.values_index = map.values_index[1..].into();
+        assert_eq!(map.debug_check_invariants(), Err(InvariantError::LengthMismatch));
+
+        let mut map = build();
+        map.keys = map.keys[1..].into();
+        assert_eq!(map.debug_check_invariants(), Err(InvariantError::LengthMismatch));
+    }
+
+    #[test]
+    fn test_from_parts() {
+        let map = MapWithDict::try_from(gen_map(1000)).unwrap();
+        let parts = || {
+            (
+                Mphf::from_slice(&map.keys, DEFAULT_GAMMA).unwrap(),
+                map.keys.clone(),
+                map.values_index.clone(),
+                map.values_dict.clone(),
+            )
+        };
+
+        let (mphf, keys, values_index, values_dict) = parts();
+        let rebuilt = MapWithDict::<u64, u32>::from_parts(mphf, keys, values_index, values_dict).unwrap();
+        assert_eq!(rebuilt.iter().collect::<Vec<_>>(), map.iter().collect::<Vec<_>>());
+
+        let (_, keys, values_index, values_dict) = parts();
+        let other_mphf = Mphf::from_slice(&map.keys[1..], DEFAULT_GAMMA).unwrap();
+        assert_eq!(
+            MapWithDict::<u64, u32>::from_parts(other_mphf, keys, values_index, values_dict).err(),
+            Some(InvariantError::LengthMismatch)
+        );
+
+        let (mphf, keys, values_index, _) = parts();
+        assert_eq!(
+            MapWithDict::<u64, u32>::from_parts(mphf, keys, values_index, Box::new([])).err(),
+            Some(InvariantError::ValueIndexOutOfBounds { idx: 0 })
+        );
+    }
+
+    #[test]
+    fn test_raw_parts() {
+        let original_map = gen_map(1000);
+        let map = MapWithDict::try_from(original_map.clone()).unwrap();
+
+        let (mphf, keys, values_index, values_dict) = map.into_raw_parts();
+        let values_dict = values_dict.iter().map(|&v| v as u64 * 2).collect();
+        let map = MapWithDict::from_raw_parts((mphf, keys, values_index, values_dict)).unwrap();
+        for (k, v) in &original_map {
+            assert_eq!(map.get(k), Some(&(*v as u64 * 2)));
+        }
+
+        let (mphf, keys, mut values_index, values_dict) = map.into_raw_parts();
+        values_index[7] = values_dict.len();
+        assert_eq!(
+            MapWithDict::from_raw_parts((mphf, keys, values_index, values_dict)).err(),
+            Some(InvariantError::ValueIndexOutOfBounds { idx: 7 })
+        );
+    }
+
     /// Assert that we can call `.get()` with `K::borrow()`.
     #[test]
     fn test_get_borrow() {
@@ -427,6 +1269,34 @@ mod tests {
         assert!(!map.contains_key("c"));
     }
 
+    #[test]
+    fn test_get_copied_and_get_or() {
+        let original_map = gen_map(1000);
+        let map = MapWithDict::try_from(original_map.clone()).unwrap();
+
+        for (k, v) in &original_map {
+            assert_eq!(map.get_copied(k), Some(*v));
+            assert_eq!(map.get_or(k, &0), v);
+        }
+
+        let missing_key = (0..).find(|k| !original_map.contains_key(k)).unwrap();
+        assert_eq!(map.get_copied(&missing_key), None);
+        assert_eq!(map.get_or(&missing_key, &0), &0);
+    }
+
+    #[test]
+    fn test_get_with_hash() {
+        let original_map = gen_map(1000);
+        let map = MapWithDict::try_from(original_map.clone()).unwrap();
+        let keys: Vec<u64> = original_map.keys().copied().collect();
+
+        for (key, other_key) in keys.iter().zip(keys.iter().skip(1)) {
+            let hash = HashedKey::<_>::new(key).hash();
+            assert_eq!(map.get_with_hash(hash, key), original_map.get(key));
+            assert_eq!(map.get_with_hash(hash, other_key), None);
+        }
+    }
+
     #[cfg(feature = "rkyv_derive")]
     #[test]
     fn test_rkyv() {
@@ -435,7 +1305,7 @@ mod tests {
         let map = MapWithDict::try_from(original_map.clone()).unwrap();
         let rkyv_bytes = rkyv::to_bytes::<_, 1024>(&map).unwrap();
 
-        assert_eq!(rkyv_bytes.len(), 12464);
+        assert_eq!(rkyv_bytes.len(), 12480);
 
         let rkyv_map = rkyv::check_archived_root::<MapWithDict<u64, u32>>(&rkyv_bytes).unwrap();
 
@@ -450,6 +1320,21 @@ mod tests {
         }
     }
 
+    #[cfg(feature = "rkyv_derive")]
+    #[test]
+    fn test_rkyv_values_dict() {
+        let original_map = gen_map(1000);
+        let map = MapWithDict::try_from(original_map.clone()).unwrap();
+        let rkyv_bytes = rkyv::to_bytes::<_, 1024>(&map).unwrap();
+        let rkyv_map = rkyv::check_archived_root::<MapWithDict<u64, u32>>(&rkyv_bytes).unwrap();
+
+        assert_eq!(rkyv_map.values_dict(), map.values_dict());
+        for key in original_map.keys() {
+            assert_eq!(rkyv_map.value_index_of(key), map.value_index_of(key));
+        }
+        assert_eq!(rkyv_map.value_index_of(&0), None);
+    }
+
     #[cfg(feature = "rkyv_derive")]
     #[test]
     fn test_rkyv_get_borrow() {
@@ -478,6 +1363,9 @@ mod tests {
                                 $gamma as f32 / 100.0
                             ).unwrap();
 
+                            // Assert that internal invariants hold.
+                            assert_eq!(entropy_map.debug_check_invariants(), Ok(()));
+
                             // Assert that length matches model.
                             assert_eq!(entropy_map.len(), model.len());
                             assert_eq!(entropy_map.is_empty(), model.is_empty());
@@ -14,13 +14,22 @@
 use std::borrow::Borrow;
 use std::collections::HashMap;
 use std::hash::{Hash, Hasher};
-use std::mem::size_of_val;
+use std::mem::{size_of, size_of_val};
+use std::time::Instant;
 
 use bitpacking::{BitPacker, BitPacker1x};
 use num::{PrimInt, Unsigned};
 use wyhash::WyHash;
 
-use crate::mphf::{Mphf, DEFAULT_GAMMA};
+use crate::access;
+use crate::block_codec::{BlockCodecKind, DICT_PADDING};
+use crate::hashed_key::HashedKey;
+use crate::invariants::{self, InvariantError};
+use crate::mphf::{BuildPolicy, Mphf, DEFAULT_GAMMA};
+use crate::pooled_values::PooledValues;
+use crate::progress::BuildProgress;
+use crate::report::{AllocScope, BuildReport};
+use crate::size_report::{self, SizeReport};
 
 /// An efficient, immutable hash map with bit-packed `Vec<u32>` values for optimized space usage.
 #[derive(Default)]
@@ -32,15 +41,23 @@ where
     H: Hasher + Default,
 {
     /// Minimally Perfect Hash Function for keys indices retrieval
-    mphf: Mphf<B, S, ST, H>,
+    pub(crate) mphf: Mphf<B, S, ST, H>,
     /// Map keys
-    keys: Box<[K]>,
+    pub(crate) keys: Box<[K]>,
     /// Points to the value index in the dictionary
-    values_index: Box<[usize]>,
+    pub(crate) values_index: Box<[usize]>,
     /// Bit-packed dictionary containing values
-    values_dict: Box<[u8]>,
+    pub(crate) values_dict: Box<[u8]>,
+    /// Codec used to encode values in the dictionary
+    pub(crate) block_codec: BlockCodecKind,
 }
 
+/// Parts of a `MapWithDictBitpacked` returned by [`MapWithDictBitpacked::into_raw_parts`]: the
+/// MPHF, keys and values dictionary offsets stored at the indices the MPHF maps keys to, the values
+/// dictionary followed by `DICT_PADDING` zero bytes, and the codec values are encoded with.
+pub type MapWithDictBitpackedRawParts<K, const B: usize = 32, const S: usize = 8, ST = u8, H = WyHash> =
+    (Mphf<B, S, ST, H>, Box<[K]>, Box<[usize]>, Box<[u8]>, BlockCodecKind);
+
 /// Errors that can occur when constructing `MapWithDictBitpacked`.
 #[derive(Debug)]
 pub enum Error {
@@ -61,6 +78,104 @@ where
     where
         I: IntoIterator<Item = (K, Vec<u32>)>,
     {
+        Self::from_iter_with_report(iter, gamma).map(|(map, _)| map)
+    }
+
+    /// Constructs a `MapWithDictBitpacked` from a `HashMap` and MPHF function params, which unlike
+    /// `TryFrom<HashMap>` works with any `B`, `S`, `ST` and `H`.
+    ///
+    /// # Examples
+    /// ```
+    /// # use std::collections::HashMap;
+    /// # use entropy_map::{MapWithDictBitpacked, DEFAULT_GAMMA};
+    /// let map = MapWithDictBitpacked::<u32, 16, 4>::from_hashmap_with_gamma(
+    ///     HashMap::from([(1, vec![2]), (3, vec![4])]),
+    ///     DEFAULT_GAMMA,
+    /// ).unwrap();
+    /// let mut values = [0];
+    /// assert!(map.get_values(&1, &mut values));
+    /// assert_eq!(values, [2]);
+    /// ```
+    pub fn from_hashmap_with_gamma(map: HashMap<K, Vec<u32>>, gamma: f32) -> Result<Self, Error> {
+        Self::from_iter_with_params(map, gamma)
+    }
+
+    /// Constructs a `MapWithDictBitpacked` from an iterator of key-value pairs and MPHF function
+    /// params, additionally returning a `BuildReport` with construction statistics.
+    ///
+    /// # Examples
+    /// ```
+    /// # use entropy_map::{MapWithDictBitpacked, DEFAULT_GAMMA};
+    /// let (map, report) = MapWithDictBitpacked::<u32>::from_iter_with_report(
+    ///     [(1, vec![2]), (3, vec![2])],
+    ///     DEFAULT_GAMMA,
+    /// ).unwrap();
+    /// assert_eq!(report.keys(), 2);
+    /// assert_eq!(report.dedup_ratio, Some(2.0));
+    /// ```
+    pub fn from_iter_with_report<I>(iter: I, gamma: f32) -> Result<(Self, BuildReport), Error>
+    where
+        I: IntoIterator<Item = (K, Vec<u32>)>,
+    {
+        Self::from_iter_with_policy(iter, &BuildPolicy::fixed(gamma))
+    }
+
+    /// Constructs a `MapWithDictBitpacked` from an iterator of key-value pairs, escalating MPHF `gamma` on failed
+    /// builds according to `policy`, and returns it along with a `BuildReport`.
+    ///
+    /// # Examples
+    /// ```
+    /// # use entropy_map::{BuildPolicy, MapWithDictBitpacked};
+    /// let policy = BuildPolicy::default();
+    /// let (map, report) =
+    ///     MapWithDictBitpacked::<u32>::from_iter_with_policy([(1, vec![2]), (3, vec![2])], &policy).unwrap();
+    /// assert_eq!(report.attempts, 1);
+    /// ```
+    pub fn from_iter_with_policy<I>(iter: I, policy: &BuildPolicy) -> Result<(Self, BuildReport), Error>
+    where
+        I: IntoIterator<Item = (K, Vec<u32>)>,
+    {
+        Self::from_iter_with_progress(iter, policy, &BuildProgress::default())
+    }
+
+    /// Same as [`MapWithDictBitpacked::from_iter_with_policy`], but reports MPHF construction progress to
+    /// `progress` and fails once cancellation is requested via it.
+    pub fn from_iter_with_progress<I>(
+        iter: I,
+        policy: &BuildPolicy,
+        progress: &BuildProgress,
+    ) -> Result<(Self, BuildReport), Error>
+    where
+        I: IntoIterator<Item = (K, Vec<u32>)>,
+    {
+        Self::from_iter_impl(iter, BlockCodecKind::default(), policy, progress)
+    }
+
+    /// Same as [`MapWithDictBitpacked::from_iter_with_policy`], but encodes values with
+    /// `block_codec` instead of the default `BlockCodecKind::Bitpacked`, see [`BlockCodecKind`].
+    pub fn from_iter_with_codec<I>(
+        iter: I,
+        block_codec: BlockCodecKind,
+        policy: &BuildPolicy,
+    ) -> Result<(Self, BuildReport), Error>
+    where
+        I: IntoIterator<Item = (K, Vec<u32>)>,
+    {
+        Self::from_iter_impl(iter, block_codec, policy, &BuildProgress::default())
+    }
+
+    /// Inner implementation of construction encoding values with `block_codec`.
+    fn from_iter_impl<I>(
+        iter: I,
+        block_codec: BlockCodecKind,
+        policy: &BuildPolicy,
+        progress: &BuildProgress,
+    ) -> Result<(Self, BuildReport), Error>
+    where
+        I: IntoIterator<Item = (K, Vec<u32>)>,
+    {
+        let t0 = Instant::now();
+        let alloc_scope = AllocScope::start();
         let mut keys = vec![];
         let mut offsets_cache = HashMap::new();
         let mut values_index = vec![];
@@ -85,15 +200,16 @@ where
                 offsets_cache.insert(v.clone(), offset);
                 values_index.push(offset);
 
-                // append packed values to the dictionary
-                pack_values(&v, &mut values_dict);
+                // append encoded values to the dictionary
+                block_codec.encode(&v, &mut values_dict);
             }
         }
 
         // pad dictionary to the values block size in bytes for smooth SIMD decoding
-        values_dict.resize(values_dict.len() + 4 * VALUES_BLOCK_LEN, 0);
+        values_dict.resize(values_dict.len() + DICT_PADDING, 0);
 
-        let mphf = Mphf::from_slice(&keys, gamma).map_err(Error::MphfError)?;
+        let unique_values = offsets_cache.len();
+        let (mphf, mut report) = Mphf::from_slice_with_progress(&keys, policy, progress).map_err(Error::MphfError)?;
 
         // Re-order keys and values_index according to mphf
         for i in 0..keys.len() {
@@ -107,12 +223,54 @@ where
             }
         }
 
-        Ok(MapWithDictBitpacked {
+        let map = MapWithDictBitpacked {
             mphf,
             keys: keys.into_boxed_slice(),
             values_index: values_index.into_boxed_slice(),
             values_dict: values_dict.into_boxed_slice(),
-        })
+            block_codec,
+        };
+
+        alloc_scope.finish(&mut report);
+        report.duration = t0.elapsed();
+        report.set_size(map.size());
+        report.set_dedup(map.values_index.len(), unique_values);
+
+        Ok((map, report))
+    }
+
+    /// Decomposes the map into its MPHF, keys, values dictionary offsets, values dictionary and
+    /// block codec, e.g. to re-compress or analyze the dictionary offline.
+    ///
+    /// # Examples
+    /// ```
+    /// # use std::collections::HashMap;
+    /// # use entropy_map::MapWithDictBitpacked;
+    /// let map = MapWithDictBitpacked::try_from(HashMap::from([(1, vec![2]), (3, vec![4])])).unwrap();
+    /// let (mphf, keys, values_index, values_dict, block_codec) = map.into_raw_parts();
+    /// let map = MapWithDictBitpacked::from_raw_parts((mphf, keys, values_index, values_dict, block_codec)).unwrap();
+    /// let mut values = [0];
+    /// assert!(map.get_values(&3, &mut values));
+    /// assert_eq!(values, [4]);
+    /// ```
+    pub fn into_raw_parts(self) -> MapWithDictBitpackedRawParts<K, B, S, ST, H> {
+        (
+            self.mphf,
+            self.keys,
+            self.values_index,
+            self.values_dict,
+            self.block_codec,
+        )
+    }
+
+    /// Inverse of [`MapWithDictBitpacked::into_raw_parts`], failing if the parts don't match, see
+    /// [`MapWithDictBitpacked::debug_check_invariants`]. The values dictionary must be followed by
+    /// `DICT_PADDING` zero bytes, as appended by construction.
+    pub fn from_raw_parts(parts: MapWithDictBitpackedRawParts<K, B, S, ST, H>) -> Result<Self, InvariantError> {
+        let (mphf, keys, values_index, values_dict, block_codec) = parts;
+        let map = MapWithDictBitpacked { mphf, keys, values_index, values_dict, block_codec };
+        map.debug_check_invariants()?;
+        Ok(map)
     }
 
     /// Updates `values` to the array of values corresponding to the key. Returns `false` if the
@@ -134,26 +292,109 @@ where
         K: Borrow<Q> + PartialEq<Q>,
         Q: Hash + Eq + ?Sized,
     {
-        let idx = match self.mphf.get(key) {
+        self.get_values_impl(self.mphf.get(key), key, values)
+    }
+
+    /// Same as [`MapWithDictBitpacked::get_values`], but re-uses the hash precomputed by `HashedKey`.
+    ///
+    /// # Examples
+    /// ```
+    /// # use std::collections::HashMap;
+    /// # use entropy_map::{HashedKey, MapWithDictBitpacked};
+    /// let map = MapWithDictBitpacked::try_from(HashMap::from([(1, vec![2]), (3, vec![4])])).unwrap();
+    /// let mut values = [0];
+    /// assert!(map.get_values_hashed(&HashedKey::new(&1), &mut values));
+    /// assert_eq!(values, [2]);
+    /// ```
+    #[inline]
+    pub fn get_values_hashed<Q>(&self, key: &HashedKey<Q, H>, values: &mut [u32]) -> bool
+    where
+        K: Borrow<Q> + PartialEq<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        self.get_values_impl(self.mphf.get_hashed(key), key.key(), values)
+    }
+
+    /// Same as [`MapWithDictBitpacked::get_values`], but skips hashing `key` and uses `hash`
+    /// computed with the map hasher `H` instead (e.g. via [`HashedKey::hash`]). The key is still
+    /// compared with the stored one, so a mismatching `hash` results in `false`.
+    ///
+    /// # Examples
+    /// ```
+    /// # use std::collections::HashMap;
+    /// # use entropy_map::{HashedKey, MapWithDictBitpacked};
+    /// let map = MapWithDictBitpacked::try_from(HashMap::from([(1, vec![2]), (3, vec![4])])).unwrap();
+    /// let hash = HashedKey::<_>::new(&1).hash();
+    /// let mut values = [0];
+    /// assert!(map.get_values_with_hash(hash, &1, &mut values));
+    /// assert_eq!(values, [2]);
+    /// assert!(!map.get_values_with_hash(hash, &3, &mut values));
+    /// ```
+    #[inline]
+    pub fn get_values_with_hash<Q>(&self, hash: u64, key: &Q, values: &mut [u32]) -> bool
+    where
+        K: Borrow<Q> + PartialEq<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        self.get_values_impl(self.mphf.get_by_hash(hash), key, values)
+    }
+
+    /// Same as [`MapWithDictBitpacked::get_values`], but decodes `n` values into a buffer taken
+    /// from a thread-local pool instead of a caller-provided one, see [`PooledValues`]. Returns
+    /// `None` if the key is not present in the map.
+    ///
+    /// # Examples
+    /// ```
+    /// # use std::collections::HashMap;
+    /// # use entropy_map::MapWithDictBitpacked;
+    /// let map = MapWithDictBitpacked::try_from(HashMap::from([(1, vec![2]), (3, vec![4])])).unwrap();
+    /// assert_eq!(map.get_values_pooled(&1, 1).as_deref(), Some(&[2][..]));
+    /// assert!(map.get_values_pooled(&2, 1).is_none());
+    /// ```
+    #[inline]
+    pub fn get_values_pooled<Q>(&self, key: &Q, n: usize) -> Option<PooledValues>
+    where
+        K: Borrow<Q> + PartialEq<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        let mut values = PooledValues::take(n);
+        self.get_values(key, &mut values).then_some(values)
+    }
+
+    /// Inner implementation of `get_values` unpacking values at MPHF index `idx` if the key stored
+    /// there is equal to `key`.
+    #[inline]
+    fn get_values_impl<Q>(&self, idx: Option<usize>, key: &Q, values: &mut [u32]) -> bool
+    where
+        K: PartialEq<Q>,
+        Q: ?Sized,
+    {
+        let idx = match idx {
             Some(idx) => idx,
             None => return false,
         };
 
         // SAFETY: `idx` is always within bounds (ensured during construction)
         unsafe {
-            if self.keys.get_unchecked(idx) != key {
+            if access::get(&self.keys, idx) != key {
                 return false;
             }
 
             // SAFETY: `idx` and `value_idx` are always within bounds (ensure during construction)
-            let value_idx = *self.values_index.get_unchecked(idx);
-            let dict = self.values_dict.get_unchecked(value_idx..);
-            unpack_values(dict, values);
+            let value_idx = *access::get(&self.values_index, idx);
+            let dict = access::get(&self.values_dict, value_idx..);
+            self.block_codec.decode(dict, values);
         }
 
         true
     }
 
+    /// Returns the codec used to encode values in the dictionary.
+    #[inline]
+    pub fn block_codec(&self) -> BlockCodecKind {
+        self.block_codec
+    }
+
     /// Returns the number of keys in the map.
     ///
     /// # Examples
@@ -200,9 +441,38 @@ where
         K: Borrow<Q> + PartialEq<Q>,
         Q: Hash + Eq + ?Sized,
     {
-        if let Some(idx) = self.mphf.get(key) {
+        self.contains_key_impl(self.mphf.get(key), key)
+    }
+
+    /// Same as [`MapWithDictBitpacked::contains_key`], but re-uses the hash precomputed by `HashedKey`.
+    ///
+    /// # Examples
+    /// ```
+    /// # use std::collections::HashMap;
+    /// # use entropy_map::{HashedKey, MapWithDictBitpacked};
+    /// let map = MapWithDictBitpacked::try_from(HashMap::from([(1, vec![2]), (3, vec![4])])).unwrap();
+    /// assert!(map.contains_key_hashed(&HashedKey::new(&1)));
+    /// assert!(!map.contains_key_hashed(&HashedKey::new(&2)));
+    /// ```
+    #[inline]
+    pub fn contains_key_hashed<Q>(&self, key: &HashedKey<Q, H>) -> bool
+    where
+        K: Borrow<Q> + PartialEq<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        self.contains_key_impl(self.mphf.get_hashed(key), key.key())
+    }
+
+    /// Inner implementation of `contains_key` checking the key stored at MPHF index `idx`.
+    #[inline]
+    fn contains_key_impl<Q>(&self, idx: Option<usize>, key: &Q) -> bool
+    where
+        K: PartialEq<Q>,
+        Q: ?Sized,
+    {
+        if let Some(idx) = idx {
             // SAFETY: `idx` is always within bounds (ensured during construction)
-            unsafe { self.keys.get_unchecked(idx) == key }
+            unsafe { access::get(&self.keys, idx) == key }
         } else {
             false
         }
@@ -220,12 +490,12 @@ where
     /// }
     /// ```
     #[inline]
-    pub fn iter(&self, n: usize) -> impl Iterator<Item = (&K, Vec<u32>)> {
+    pub fn iter(&self, n: usize) -> impl ExactSizeIterator<Item = (&K, Vec<u32>)> + DoubleEndedIterator {
         self.keys().zip(self.values_index.iter()).map(move |(key, &value_idx)| {
             let mut values = vec![0; n];
             // SAFETY: `value_idx` is always within bounds (ensured during construction)
-            let dict = unsafe { self.values_dict.get_unchecked(value_idx..) };
-            unpack_values(dict, &mut values);
+            let dict = unsafe { access::get(&self.values_dict, value_idx..) };
+            self.block_codec.decode(dict, &mut values);
             (key, values)
         })
     }
@@ -242,7 +512,7 @@ where
     /// }
     /// ```
     #[inline]
-    pub fn keys(&self) -> impl Iterator<Item = &K> {
+    pub fn keys(&self) -> impl ExactSizeIterator<Item = &K> + DoubleEndedIterator {
         self.keys.iter()
     }
 
@@ -258,16 +528,109 @@ where
     /// }
     /// ```
     #[inline]
-    pub fn values(&self, n: usize) -> impl Iterator<Item = Vec<u32>> + '_ {
+    pub fn values(&self, n: usize) -> impl ExactSizeIterator<Item = Vec<u32>> + DoubleEndedIterator + '_ {
         self.values_index.iter().map(move |&value_idx| {
             let mut values = vec![0; n];
             // SAFETY: `value_idx` is always within bounds (ensured during construction)
-            let dict = unsafe { self.values_dict.get_unchecked(value_idx..) };
-            unpack_values(dict, &mut values);
+            let dict = unsafe { access::get(&self.values_dict, value_idx..) };
+            self.block_codec.decode(dict, &mut values);
             values
         })
     }
 
+    /// Returns an iterator over the values of all keys flattened into a single sequence, yielding
+    /// `n` values per key in the same key order as [`keys`](Self::keys). Values are decoded key by
+    /// key into a single buffer without allocating a `Vec` per key.
+    ///
+    /// # Examples
+    /// ```
+    /// # use std::collections::HashMap;
+    /// # use entropy_map::MapWithDictBitpacked;
+    /// let map = MapWithDictBitpacked::try_from(HashMap::from([(1, vec![2, 3]), (4, vec![5, 6])])).unwrap();
+    /// let values: Vec<u32> = map.iter_values_flat(2).collect();
+    /// let expected: Vec<u32> = map.values(2).flatten().collect();
+    /// assert_eq!(values, expected);
+    /// ```
+    #[inline]
+    pub fn iter_values_flat(&self, n: usize) -> impl ExactSizeIterator<Item = u32> + '_ {
+        FlatValuesIter {
+            values_index: self.values_index.iter(),
+            values_dict: &self.values_dict,
+            block_codec: self.block_codec,
+            values: vec![0; n],
+            pos: n,
+        }
+    }
+
+    /// Returns an iterator over bits widths of the bit-packed blocks of `n` values corresponding to
+    /// the key, each block holding up to 32 values. Returns `None` if the key is not present in the
+    /// map or values are not encoded with `BlockCodecKind::Bitpacked`.
+    ///
+    /// # Examples
+    /// ```
+    /// # use std::collections::HashMap;
+    /// # use entropy_map::MapWithDictBitpacked;
+    /// let values: Vec<u32> = (0..40).collect();
+    /// let map = MapWithDictBitpacked::try_from(HashMap::from([(1, values)])).unwrap();
+    /// assert_eq!(map.value_bit_widths(&1, 40).unwrap().collect::<Vec<_>>(), vec![5, 6]);
+    /// assert!(map.value_bit_widths(&2, 40).is_none());
+    /// ```
+    #[inline]
+    pub fn value_bit_widths<Q>(&self, key: &Q, n: usize) -> Option<impl Iterator<Item = u8> + '_>
+    where
+        K: Borrow<Q> + PartialEq<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        if self.block_codec != BlockCodecKind::Bitpacked {
+            return None;
+        }
+        let idx = self.mphf.get(key)?;
+
+        // SAFETY: `idx` and `value_idx` are always within bounds (ensured during construction)
+        unsafe {
+            if access::get(&self.keys, idx) != key {
+                return None;
+            }
+            let value_idx = *access::get(&self.values_index, idx);
+            let dict = access::get(&self.values_dict, value_idx..);
+            Some(packed_blocks(dict, n).map(|(num_bits, _)| num_bits))
+        }
+    }
+
+    /// Returns the number of bit-packed blocks of the unique values of length `n` stored in the
+    /// dictionary for every bits width in [0..32] range, e.g. to monitor values ranges. All counts
+    /// are 0 unless values are encoded with `BlockCodecKind::Bitpacked`.
+    ///
+    /// # Examples
+    /// ```
+    /// # use std::collections::HashMap;
+    /// # use entropy_map::MapWithDictBitpacked;
+    /// let map = MapWithDictBitpacked::try_from(HashMap::from([(1, vec![1, 2]), (3, vec![1, 2]), (4, vec![1, 300])]))
+    ///     .unwrap();
+    /// let histogram = map.value_bit_widths_histogram(2);
+    /// assert_eq!(histogram[2], 1);
+    /// assert_eq!(histogram[9], 1);
+    /// assert_eq!(histogram.iter().sum::<usize>(), 2);
+    /// ```
+    pub fn value_bit_widths_histogram(&self, n: usize) -> [usize; 33] {
+        let mut histogram = [0; 33];
+        if n == 0 || self.block_codec != BlockCodecKind::Bitpacked {
+            return histogram;
+        }
+
+        // walk the dictionary up to the padding, one unique values entry after another
+        let dict_len = self.values_dict.len().saturating_sub(DICT_PADDING);
+        let mut offset = 0;
+        while offset < dict_len {
+            for (num_bits, block_size) in packed_blocks(&self.values_dict[offset..], n) {
+                histogram[num_bits as usize] += 1;
+                offset += block_size;
+            }
+        }
+
+        histogram
+    }
+
     /// Returns the total number of bytes occupied by the structure.
     ///
     /// # Examples
@@ -275,7 +638,7 @@ where
     /// # use std::collections::HashMap;
     /// # use entropy_map::MapWithDictBitpacked;
     /// let map = MapWithDictBitpacked::try_from(HashMap::from([(1, vec![2]), (3, vec![4])])).unwrap();
-    /// assert_eq!(map.size(), 394);
+    /// assert_eq!(map.size(), 386);
     /// ```
     pub fn size(&self) -> usize {
         size_of_val(self)
@@ -284,6 +647,35 @@ where
             + size_of_val(self.values_index.as_ref())
             + size_of_val(self.values_dict.as_ref())
     }
+
+    /// Returns the size of the map of values of length `n` compared with an estimate of the
+    /// equivalent `HashMap<K, Vec<u32>>`, including the heap memory of values vectors, see
+    /// [`SizeReport`].
+    ///
+    /// # Examples
+    /// ```
+    /// # use std::collections::HashMap;
+    /// # use entropy_map::MapWithDictBitpacked;
+    /// let entries: HashMap<u64, Vec<u32>> = (0..10000).map(|i| (i, vec![i as u32 % 100; 4])).collect();
+    /// let map = MapWithDictBitpacked::try_from(entries).unwrap();
+    /// assert!(map.size_report(4).savings_ratio() > 3.0);
+    /// ```
+    pub fn size_report(&self, n: usize) -> SizeReport {
+        let values_bytes = if n == 0 { 0 } else { self.len() * n * size_of::<u32>() };
+        SizeReport {
+            keys: self.len(),
+            size: self.size(),
+            std_size: size_report::std_table_size::<HashMap<K, Vec<u32>>, (K, Vec<u32>)>(self.len()) + values_bytes,
+        }
+    }
+
+    /// Same as [`MapWithDict::debug_check_invariants`](crate::MapWithDict::debug_check_invariants),
+    /// values dictionary offsets being checked to point before the dictionary padding.
+    pub fn debug_check_invariants(&self) -> Result<(), InvariantError> {
+        invariants::check_keys(&self.mphf, &self.keys)?;
+        let max_offset = (self.values_dict.len() + 1).saturating_sub(DICT_PADDING);
+        invariants::check_values_index(&self.values_index, self.keys.len(), max_offset)
+    }
 }
 
 /// Creates a `MapWithDictBitpacked` from a `HashMap`.
@@ -300,54 +692,131 @@ where
 }
 
 /// Number of values bit-packed in one batch
-const VALUES_BLOCK_LEN: usize = BitPacker1x::BLOCK_LEN;
+pub(crate) const VALUES_BLOCK_LEN: usize = BitPacker1x::BLOCK_LEN;
 
 /// `pack_values` bit-packs every values block and adds it to the dictionary,
 /// each block consists of bits width followed by bit-packed integers bytes
-fn pack_values(values: &[u32], dict: &mut Vec<u8>) {
-    // initialize bit packer and buffers to be used for bit-packing
+pub(crate) fn pack_values(values: &[u32], dict: &mut Vec<u8>) {
+    // initialize bit packer to be used for bit-packing
     let bitpacker = BitPacker1x::new();
 
     for block in values.chunks(VALUES_BLOCK_LEN) {
         let mut values_block = [0u32; VALUES_BLOCK_LEN];
-        let mut values_packed_block = [0u8; 4 * VALUES_BLOCK_LEN];
-
         values_block[..block.len()].copy_from_slice(block);
-
-        // compute minimal bits width needed to encode each value in the block
-        let num_bits = bitpacker.num_bits(&values_block);
-
-        // bit-pack values block
-        bitpacker.compress(&values_block, &mut values_packed_block, num_bits);
-
-        // append bits width and bit-packed values block to the dictionary
-        let size = (block.len() * (num_bits as usize)).div_ceil(8);
-        dict.push(num_bits);
-        dict.extend_from_slice(&values_packed_block[..size]);
+        pack_block(&bitpacker, &values_block, block.len(), dict);
     }
 }
 
+/// `pack_block` bit-packs the first `len` values of a zero-padded values block and adds its bits
+/// width followed by bit-packed integers bytes to the dictionary
+#[inline]
+pub(crate) fn pack_block(
+    bitpacker: &BitPacker1x,
+    values_block: &[u32; VALUES_BLOCK_LEN],
+    len: usize,
+    dict: &mut Vec<u8>,
+) {
+    let mut values_packed_block = [0u8; 4 * VALUES_BLOCK_LEN];
+
+    // compute minimal bits width needed to encode each value in the block
+    let num_bits = bitpacker.num_bits(values_block);
+
+    // bit-pack values block
+    bitpacker.compress(values_block, &mut values_packed_block, num_bits);
+
+    // append bits width and bit-packed values block to the dictionary
+    let size = (len * (num_bits as usize)).div_ceil(8);
+    dict.push(num_bits);
+    dict.extend_from_slice(&values_packed_block[..size]);
+}
+
 /// `unpack_values` bit-unpacks every values block and adds its values to the result,
 /// each block consists of bits width followed by bit-packed integers bytes
-fn unpack_values(dict: &[u8], res: &mut [u32]) {
+pub(crate) fn unpack_values(dict: &[u8], res: &mut [u32]) {
     let bitpacker = BitPacker1x::new();
     let mut dict = dict;
     for block in res.chunks_mut(VALUES_BLOCK_LEN) {
         let mut values_block = [0u32; VALUES_BLOCK_LEN];
+        dict = unpack_block(&bitpacker, dict, &mut values_block, block.len());
+        block.copy_from_slice(&values_block[..block.len()]);
+    }
+}
 
-        // fetch bits width
-        let num_bits = dict[0];
-        dict = &dict[1..];
+/// `unpack_block` bit-unpacks a single block holding `len` values from the start of `dict` and
+/// returns the remainder of the dictionary following the block
+#[inline]
+pub(crate) fn unpack_block<'a>(
+    bitpacker: &BitPacker1x,
+    dict: &'a [u8],
+    values_block: &mut [u32; VALUES_BLOCK_LEN],
+    len: usize,
+) -> &'a [u8] {
+    // fetch bits width
+    let num_bits = dict[0];
+    let dict = &dict[1..];
+
+    // bit-unpack values block
+    let size = (len * (num_bits as usize)).div_ceil(8);
+    bitpacker.decompress(dict, values_block, num_bits);
+    &dict[size..]
+}
 
-        // bit-unpack values block
-        let size = (block.len() * (num_bits as usize)).div_ceil(8);
-        bitpacker.decompress(dict, &mut values_block, num_bits);
-        dict = &dict[size..];
+/// `packed_blocks` returns bits width and size in bytes, including bits width byte, of every
+/// block of bit-packed `n` values starting at the beginning of `dict`
+#[inline]
+fn packed_blocks(dict: &[u8], n: usize) -> impl Iterator<Item = (u8, usize)> + '_ {
+    let mut block_offset = 0;
+    (0..n).step_by(VALUES_BLOCK_LEN).map(move |values_offset| {
+        let len = (n - values_offset).min(VALUES_BLOCK_LEN);
+        let num_bits = dict[block_offset];
+        let block_size = 1 + (len * (num_bits as usize)).div_ceil(8);
+        block_offset += block_size;
+        (num_bits, block_size)
+    })
+}
 
-        block.copy_from_slice(&values_block[..block.len()]);
+/// Iterator over values of all keys of `MapWithDictBitpacked` in key-major order,
+/// returned by [`MapWithDictBitpacked::iter_values_flat`].
+struct FlatValuesIter<'a> {
+    /// Remaining dictionary offsets of keys values
+    values_index: std::slice::Iter<'a, usize>,
+    /// Dictionary containing encoded values
+    values_dict: &'a [u8],
+    /// Codec used to encode values in the dictionary
+    block_codec: BlockCodecKind,
+    /// Decoded values of the current key
+    values: Vec<u32>,
+    /// Position of the next value in `values`
+    pos: usize,
+}
+
+impl Iterator for FlatValuesIter<'_> {
+    type Item = u32;
+
+    #[inline]
+    fn next(&mut self) -> Option<u32> {
+        while self.pos == self.values.len() {
+            let &value_idx = self.values_index.next()?;
+            // SAFETY: `value_idx` is always within bounds (ensured during construction)
+            let dict = unsafe { access::get(self.values_dict, value_idx..) };
+            self.block_codec.decode(dict, &mut self.values);
+            self.pos = 0;
+        }
+
+        let value = self.values[self.pos];
+        self.pos += 1;
+        Some(value)
+    }
+
+    #[inline]
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let len = self.values_index.len() * self.values.len() + self.values.len() - self.pos;
+        (len, Some(len))
     }
 }
 
+impl ExactSizeIterator for FlatValuesIter<'_> {}
+
 /// Implement `get` for `Archived` version of `MapWithDictBitpacked` if feature is enabled
 #[cfg(feature = "rkyv_derive")]
 impl<K, const B: usize, const S: usize, ST, H> ArchivedMapWithDictBitpacked<K, B, S, ST, H>
@@ -375,25 +844,60 @@ where
     /// ```
     #[inline]
     pub fn get_values(&self, key: &K, values: &mut [u32]) -> bool {
-        let idx = match self.mphf.get(key) {
+        self.get_values_impl(self.mphf.get(key), key, values)
+    }
+
+    /// Same as [`ArchivedMapWithDictBitpacked::get_values`], but re-uses the hash precomputed by
+    /// `HashedKey`.
+    #[inline]
+    pub fn get_values_hashed(&self, key: &HashedKey<K, H>, values: &mut [u32]) -> bool {
+        self.get_values_impl(self.mphf.get_hashed(key), key.key(), values)
+    }
+
+    /// Same as [`ArchivedMapWithDictBitpacked::get_values`], but skips hashing `key` and uses
+    /// `hash` computed with the map hasher `H` instead.
+    #[inline]
+    pub fn get_values_with_hash(&self, hash: u64, key: &K, values: &mut [u32]) -> bool {
+        self.get_values_impl(self.mphf.get_by_hash(hash), key, values)
+    }
+
+    /// Same as [`ArchivedMapWithDictBitpacked::get_values`], but decodes `n` values into a buffer
+    /// taken from a thread-local pool, see [`MapWithDictBitpacked::get_values_pooled`].
+    #[inline]
+    pub fn get_values_pooled(&self, key: &K, n: usize) -> Option<PooledValues> {
+        let mut values = PooledValues::take(n);
+        self.get_values(key, &mut values).then_some(values)
+    }
+
+    /// Inner implementation of `get_values` unpacking values at MPHF index `idx` if the key stored
+    /// there is equal to `key`.
+    #[inline]
+    fn get_values_impl(&self, idx: Option<usize>, key: &K, values: &mut [u32]) -> bool {
+        let idx = match idx {
             Some(idx) => idx,
             None => return false,
         };
 
         // SAFETY: `idx` is always within bounds (ensured during construction)
         unsafe {
-            if self.keys.get_unchecked(idx) != key {
+            if access::get(&self.keys, idx) != key {
                 return false;
             }
 
             // SAFETY: `idx` and `value_idx` are always within bounds (ensure during construction)
-            let value_idx = *self.values_index.get_unchecked(idx) as usize;
-            let dict = self.values_dict.get_unchecked(value_idx..);
-            unpack_values(dict, values);
+            let value_idx = *access::get(&self.values_index, idx) as usize;
+            let dict = access::get(&self.values_dict, value_idx..);
+            BlockCodecKind::from(&self.block_codec).decode(dict, values);
         }
 
         true
     }
+
+    /// Returns the codec used to encode values in the dictionary.
+    #[inline]
+    pub fn block_codec(&self) -> BlockCodecKind {
+        BlockCodecKind::from(&self.block_codec)
+    }
 }
 
 #[cfg(test)]
@@ -533,7 +1037,161 @@ mod tests {
         }
 
         // Test size
-        assert_eq!(map.size(), 22664);
+        assert_eq!(map.size(), 22672);
+    }
+
+    #[test_case(0; "empty values")]
+    #[test_case(1; "single value")]
+    #[test_case(32; "single full block")]
+    #[test_case(75; "multiple blocks")]
+    fn test_iter_values_flat(values_num: usize) {
+        let original_map = gen_map(1000, values_num);
+        let map = MapWithDictBitpacked::try_from(original_map).unwrap();
+
+        let iter = map.iter_values_flat(values_num);
+        assert_eq!(iter.len(), map.len() * values_num);
+        let values: Vec<u32> = iter.collect();
+        let expected: Vec<u32> = map.values(values_num).flatten().collect();
+        assert_eq!(values, expected);
+    }
+
+    #[test_case(0; "empty values")]
+    #[test_case(1; "single value")]
+    #[test_case(32; "single full block")]
+    #[test_case(75; "multiple blocks")]
+    fn test_value_bit_widths(values_num: usize) {
+        let original_map = gen_map(1000, values_num);
+        let map = MapWithDictBitpacked::try_from(original_map.clone()).unwrap();
+
+        let bit_widths = |values: &[u32]| -> Vec<u8> {
+            values
+                .chunks(VALUES_BLOCK_LEN)
+                .map(|block| (32 - block.iter().max().unwrap().leading_zeros()) as u8)
+                .collect()
+        };
+        for (key, values) in &original_map {
+            let expected = bit_widths(values);
+            assert_eq!(
+                map.value_bit_widths(key, values_num).unwrap().collect::<Vec<_>>(),
+                expected
+            );
+        }
+        assert!(map.value_bit_widths(&0, values_num).is_none());
+
+        // histogram counts blocks of unique values only
+        let mut histogram = [0; 33];
+        for values in original_map.values().collect::<HashSet<_>>() {
+            for num_bits in bit_widths(values) {
+                histogram[num_bits as usize] += 1;
+            }
+        }
+        assert_eq!(map.value_bit_widths_histogram(values_num), histogram);
+    }
+
+    #[test]
+    fn test_block_codecs() {
+        let values_num = 40;
+        let original_map = gen_map(1000, values_num);
+        let mut values_buf = vec![0; values_num];
+
+        for block_codec in BlockCodecKind::ALL {
+            let (map, _) = MapWithDictBitpacked::<u64>::from_iter_with_codec(
+                original_map.clone(),
+                block_codec,
+                &BuildPolicy::default(),
+            )
+            .unwrap();
+            assert_eq!(map.block_codec(), block_codec);
+
+            for (key, value) in &original_map {
+                assert!(map.get_values(key, &mut values_buf));
+                assert_eq!(value, &values_buf);
+            }
+            for (&k, v) in map.iter(values_num) {
+                assert_eq!(original_map.get(&k), Some(&v));
+            }
+            let expected: Vec<u32> = map.values(values_num).flatten().collect();
+            assert_eq!(map.iter_values_flat(values_num).collect::<Vec<_>>(), expected);
+
+            let key = original_map.keys().next().unwrap();
+            let bitpacked = block_codec == BlockCodecKind::Bitpacked;
+            assert_eq!(map.value_bit_widths(key, values_num).is_some(), bitpacked);
+            assert_eq!(
+                map.value_bit_widths_histogram(values_num).iter().sum::<usize>() > 0,
+                bitpacked
+            );
+
+            #[cfg(feature = "rkyv_derive")]
+            {
+                let rkyv_bytes = rkyv::to_bytes::<_, 1024>(&map).unwrap();
+                let rkyv_map = rkyv::check_archived_root::<MapWithDictBitpacked<u64>>(&rkyv_bytes).unwrap();
+                assert_eq!(rkyv_map.block_codec(), block_codec);
+                for (key, value) in &original_map {
+                    assert!(rkyv_map.get_values(key, &mut values_buf));
+                    assert_eq!(value, &values_buf);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_raw_parts() {
+        let values_num = 10;
+        let original_map = gen_map(1000, values_num);
+        let map = MapWithDictBitpacked::<u64>::try_from(original_map.clone()).unwrap();
+
+        // re-compress the dictionary with another codec
+        let (mphf, keys, mut values_index, values_dict, block_codec) = map.into_raw_parts();
+        let mut new_dict = vec![];
+        let mut new_offsets = HashMap::new();
+        let mut values = vec![0; values_num];
+        for offset in values_index.iter_mut() {
+            *offset = *new_offsets.entry(*offset).or_insert_with(|| {
+                block_codec.decode(&values_dict[*offset..], &mut values);
+                let new_offset = new_dict.len();
+                BlockCodecKind::Delta.encode(&values, &mut new_dict);
+                new_offset
+            });
+        }
+        new_dict.resize(new_dict.len() + DICT_PADDING, 0);
+
+        let parts = (
+            mphf,
+            keys,
+            values_index,
+            new_dict.into_boxed_slice(),
+            BlockCodecKind::Delta,
+        );
+        let map = MapWithDictBitpacked::from_raw_parts(parts).unwrap();
+        assert_eq!(map.block_codec(), BlockCodecKind::Delta);
+        for (key, value) in &original_map {
+            assert!(map.get_values(key, &mut values));
+            assert_eq!(value, &values);
+        }
+
+        let (mphf, keys, values_index, values_dict, block_codec) = map.into_raw_parts();
+        let parts = (
+            mphf,
+            keys,
+            values_index,
+            values_dict[DICT_PADDING..].into(),
+            block_codec,
+        );
+        assert!(matches!(
+            MapWithDictBitpacked::from_raw_parts(parts),
+            Err(InvariantError::ValueIndexOutOfBounds { .. })
+        ));
+    }
+
+    #[test]
+    fn test_build_report() {
+        let original_map = gen_map(1000, 1);
+        let (map, report) =
+            MapWithDictBitpacked::<u64>::from_iter_with_report(original_map.clone(), DEFAULT_GAMMA).unwrap();
+
+        assert_eq!(report.keys(), original_map.len());
+        assert_eq!(report.dedup_ratio, Some(original_map.len() as f32 / 10.0));
+        assert_eq!(report.bits_per_key, (map.size() * 8) as f32 / original_map.len() as f32);
     }
 
     #[cfg(feature = "rkyv_derive")]
@@ -546,7 +1204,7 @@ mod tests {
         let map = MapWithDictBitpacked::try_from(original_map.clone()).unwrap();
         let rkyv_bytes = rkyv::to_bytes::<_, 1024>(&map).unwrap();
 
-        assert_eq!(rkyv_bytes.len(), 18516);
+        assert_eq!(rkyv_bytes.len(), 18520);
 
         let rkyv_map = rkyv::check_archived_root::<MapWithDictBitpacked<u64>>(&rkyv_bytes).unwrap();
 
@@ -555,7 +1213,10 @@ mod tests {
         for (k, v) in original_map {
             rkyv_map.get_values(&k, &mut values_buf);
             assert_eq!(v, values_buf);
+            assert_eq!(*rkyv_map.get_values_pooled(&k, values_num).unwrap(), v);
+            assert_eq!(*map.get_values_pooled(&k, values_num).unwrap(), v);
         }
+        assert!(rkyv_map.get_values_pooled(&u64::MAX, values_num).is_none());
     }
 
     macro_rules! proptest_map_with_dict_bitpacked_model {
@@ -570,6 +1231,9 @@ mod tests {
                                 $gamma as f32 / 100.0
                             ).unwrap();
 
+                            // Assert that internal invariants hold.
+                            assert_eq!(entropy_map.debug_check_invariants(), Ok(()));
+
                             // Assert that length matches model.
                             assert_eq!(entropy_map.len(), model.len());
                             assert_eq!(entropy_map.is_empty(), model.is_empty());
@@ -0,0 +1,253 @@
+//! A module providing `MapWithDictBitpackedRanges`, a `MapWithDictBitpacked` storing the range of
+//! every values column.
+//!
+//! Consumers validating values or sizing downstream buffers and integer types need the minimum and
+//! maximum of every value position (column) across the map, which otherwise takes decoding every
+//! value at startup. `MapWithDictBitpackedRanges` computes them once at construction, decoding
+//! only the unique values of the dictionary, and stores them alongside the map, so that they are
+//! available right away from archives too.
+
+use std::borrow::Borrow;
+use std::hash::{Hash, Hasher};
+use std::mem::size_of_val;
+
+use num::{PrimInt, Unsigned};
+use wyhash::WyHash;
+
+use crate::hashed_key::HashedKey;
+use crate::invariants::InvariantError;
+use crate::map_with_dict_bitpacked::MapWithDictBitpacked;
+
+/// Minimum and maximum of the values at one position (column) of all values of a map.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "rkyv_derive", derive(rkyv::Archive, rkyv::Deserialize, rkyv::Serialize))]
+#[cfg_attr(feature = "rkyv_derive", archive_attr(derive(rkyv::CheckBytes)))]
+pub struct ValueRange {
+    /// Minimum value of the column
+    pub min: u32,
+    /// Maximum value of the column
+    pub max: u32,
+}
+
+/// An immutable hash map with bit-packed `Vec<u32>` values like `MapWithDictBitpacked`, storing
+/// the [`ValueRange`] of every values column, built with
+/// [`MapWithDictBitpacked::with_value_ranges`].
+///
+/// # Examples
+/// ```
+/// # use std::collections::HashMap;
+/// # use entropy_map::{MapWithDictBitpacked, ValueRange};
+/// let map = MapWithDictBitpacked::try_from(HashMap::from([(1, vec![2, 30]), (4, vec![5, 6])])).unwrap();
+/// let map = map.with_value_ranges(2);
+/// assert_eq!(map.value_ranges(), [ValueRange { min: 2, max: 5 }, ValueRange { min: 6, max: 30 }]);
+/// let mut values = [0; 2];
+/// assert!(map.get_values(&4, &mut values));
+/// assert_eq!(values, [5, 6]);
+/// ```
+#[derive(Default)]
+#[cfg_attr(feature = "rkyv_derive", derive(rkyv::Archive, rkyv::Deserialize, rkyv::Serialize))]
+#[cfg_attr(feature = "rkyv_derive", archive_attr(derive(rkyv::CheckBytes)))]
+pub struct MapWithDictBitpackedRanges<K, const B: usize = 32, const S: usize = 8, ST = u8, H = WyHash>
+where
+    ST: PrimInt + Unsigned,
+    H: Hasher + Default,
+{
+    /// Map of keys to values
+    map: MapWithDictBitpacked<K, B, S, ST, H>,
+    /// Range of every values column, empty for an empty map
+    value_ranges: Box<[ValueRange]>,
+}
+
+impl<K, const B: usize, const S: usize, ST, H> MapWithDictBitpacked<K, B, S, ST, H>
+where
+    K: Hash + PartialEq + Clone,
+    ST: PrimInt + Unsigned,
+    H: Hasher + Default,
+{
+    /// Turns the map of values of length `n` into a `MapWithDictBitpackedRanges` storing the range
+    /// of every values column, computed by decoding every unique value of the dictionary once.
+    pub fn with_value_ranges(self, n: usize) -> MapWithDictBitpackedRanges<K, B, S, ST, H> {
+        let mut offsets = self.values_index.to_vec();
+        offsets.sort_unstable();
+        offsets.dedup();
+
+        let mut value_ranges = vec![ValueRange { min: u32::MAX, max: 0 }; if offsets.is_empty() { 0 } else { n }];
+        let mut values = vec![0; n];
+        for offset in offsets {
+            self.block_codec.decode(&self.values_dict[offset..], &mut values);
+            for (range, &value) in value_ranges.iter_mut().zip(&values) {
+                range.min = range.min.min(value);
+                range.max = range.max.max(value);
+            }
+        }
+
+        MapWithDictBitpackedRanges { map: self, value_ranges: value_ranges.into_boxed_slice() }
+    }
+}
+
+impl<K, const B: usize, const S: usize, ST, H> MapWithDictBitpackedRanges<K, B, S, ST, H>
+where
+    K: Hash + PartialEq + Clone,
+    ST: PrimInt + Unsigned,
+    H: Hasher + Default,
+{
+    /// Returns the range of every values column, empty for an empty map.
+    #[inline]
+    pub fn value_ranges(&self) -> &[ValueRange] {
+        &self.value_ranges
+    }
+
+    /// Updates `values` to the array of values corresponding to the key. Returns `false` if the
+    /// key is not present in the map.
+    #[inline]
+    pub fn get_values<Q>(&self, key: &Q, values: &mut [u32]) -> bool
+    where
+        K: Borrow<Q> + PartialEq<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        self.map.get_values(key, values)
+    }
+
+    /// Same as [`MapWithDictBitpackedRanges::get_values`], but re-uses the hash precomputed by
+    /// `HashedKey`.
+    #[inline]
+    pub fn get_values_hashed<Q>(&self, key: &HashedKey<Q, H>, values: &mut [u32]) -> bool
+    where
+        K: Borrow<Q> + PartialEq<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        self.map.get_values_hashed(key, values)
+    }
+
+    /// Returns the number of keys in the map.
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.map.len()
+    }
+
+    /// Returns `true` if the map contains no elements.
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.map.is_empty()
+    }
+
+    /// Returns the map of keys to values.
+    #[inline]
+    pub fn as_map(&self) -> &MapWithDictBitpacked<K, B, S, ST, H> {
+        &self.map
+    }
+
+    /// Returns the map of keys to values, dropping the value ranges.
+    #[inline]
+    pub fn into_map(self) -> MapWithDictBitpacked<K, B, S, ST, H> {
+        self.map
+    }
+
+    /// Returns the total number of bytes occupied by the map.
+    #[inline]
+    pub fn size(&self) -> usize {
+        self.map.size() + size_of_val(&self.value_ranges) + size_of_val(self.value_ranges.as_ref())
+    }
+
+    /// Checks invariants of the map and that every value range is non-empty.
+    pub fn debug_check_invariants(&self) -> Result<(), InvariantError> {
+        self.map.debug_check_invariants()?;
+        if self.value_ranges.iter().any(|range| range.min > range.max) {
+            return Err(InvariantError::LengthMismatch);
+        }
+        Ok(())
+    }
+}
+
+/// Implement `get_values` and `value_range` for `Archived` version of `MapWithDictBitpackedRanges`
+/// if feature is enabled
+#[cfg(feature = "rkyv_derive")]
+impl<K, const B: usize, const S: usize, ST, H> ArchivedMapWithDictBitpackedRanges<K, B, S, ST, H>
+where
+    K: PartialEq + Hash + rkyv::Archive,
+    K::Archived: PartialEq<K>,
+    ST: PrimInt + Unsigned + rkyv::Archive<Archived = ST>,
+    H: Hasher + Default,
+{
+    /// Returns the range of values column `column`, `None` if the map is empty or values are
+    /// shorter.
+    #[inline]
+    pub fn value_range(&self, column: usize) -> Option<ValueRange> {
+        self.value_ranges
+            .as_ref()
+            .get(column)
+            .map(|range| ValueRange { min: range.min, max: range.max })
+    }
+
+    /// Returns the number of values columns, 0 for an empty map.
+    #[inline]
+    pub fn columns(&self) -> usize {
+        self.value_ranges.len()
+    }
+
+    /// Updates `values` to the array of values corresponding to the key. Returns `false` if the
+    /// key is not present in the map.
+    #[inline]
+    pub fn get_values(&self, key: &K, values: &mut [u32]) -> bool {
+        self.map.get_values(key, values)
+    }
+
+    /// Returns the number of keys in the map.
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.map.keys.len()
+    }
+
+    /// Returns `true` if the map contains no elements.
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.map.keys.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::block_codec::BlockCodecKind;
+    use crate::mphf::{BuildPolicy, DEFAULT_GAMMA};
+
+    #[test]
+    fn test_value_ranges() {
+        let entries: Vec<(u64, Vec<u32>)> = (0..1000)
+            .map(|i| (i, vec![i as u32 % 10, 1000 + i as u32, 7]))
+            .collect();
+        let expected = [
+            ValueRange { min: 0, max: 9 },
+            ValueRange { min: 1000, max: 1999 },
+            ValueRange { min: 7, max: 7 },
+        ];
+
+        for codec in BlockCodecKind::ALL {
+            let policy = BuildPolicy::fixed(DEFAULT_GAMMA);
+            let (map, _) = MapWithDictBitpacked::<u64>::from_iter_with_codec(entries.clone(), codec, &policy).unwrap();
+            let map = map.with_value_ranges(3);
+            assert_eq!(map.value_ranges(), expected);
+            assert_eq!(map.debug_check_invariants(), Ok(()));
+
+            let mut values = [0; 3];
+            for (key, expected_values) in &entries {
+                assert!(map.get_values(key, &mut values));
+                assert_eq!(&values, &expected_values[..]);
+            }
+
+            #[cfg(feature = "rkyv_derive")]
+            {
+                let rkyv_bytes = rkyv::to_bytes::<_, 1024>(&map).unwrap();
+                let rkyv_map = rkyv::check_archived_root::<MapWithDictBitpackedRanges<u64>>(&rkyv_bytes).unwrap();
+                assert_eq!(rkyv_map.columns(), 3);
+                assert_eq!(rkyv_map.value_range(1), Some(expected[1]));
+                assert_eq!(rkyv_map.value_range(3), None);
+                assert!(rkyv_map.get_values(&5, &mut values));
+                assert_eq!(values, [5, 1005, 7]);
+            }
+        }
+
+        let map = MapWithDictBitpacked::<u64>::from_iter_with_params([], DEFAULT_GAMMA).unwrap();
+        assert!(map.with_value_ranges(3).value_ranges().is_empty());
+    }
+}
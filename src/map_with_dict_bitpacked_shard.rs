@@ -0,0 +1,446 @@
+//! A module providing `MapWithDictBitpackedShard`, a `MapWithDictBitpacked` indexing into a values
+//! dictionary shared with other shards.
+//!
+//! Key sets split into shards (e.g. to build or load them independently) often share most of their
+//! values, which every independently built `MapWithDictBitpacked` encodes into its own dictionary.
+//! Shards built with the same `SharedDictBuilder` instead index into one `SharedBitpackedDict`,
+//! storing every unique values vector once across all shards. Shards are queried along with the
+//! dictionary they were built with.
+
+use std::borrow::Borrow;
+use std::collections::{HashMap, HashSet};
+use std::hash::{Hash, Hasher};
+use std::mem::size_of_val;
+use std::time::Instant;
+
+use num::{PrimInt, Unsigned};
+use wyhash::WyHash;
+
+use crate::access;
+use crate::block_codec::{BlockCodecKind, DICT_PADDING};
+use crate::hashed_key::HashedKey;
+use crate::invariants::{self, InvariantError};
+use crate::map_with_dict_bitpacked::Error;
+use crate::mphf::{BuildPolicy, Mphf};
+use crate::report::{AllocScope, BuildReport};
+
+/// Builder of a `SharedBitpackedDict`, encoding values of the shards built with it.
+///
+/// # Examples
+/// ```
+/// # use entropy_map::{BlockCodecKind, BuildPolicy, MapWithDictBitpackedShard, SharedDictBuilder};
+/// let mut dict_builder = SharedDictBuilder::new(BlockCodecKind::Bitpacked);
+/// let policy = BuildPolicy::default();
+/// let (shard_a, _) = MapWithDictBitpackedShard::<u32>::from_iter_with_policy(
+///     [(1, vec![2, 3]), (2, vec![4, 5])],
+///     &mut dict_builder,
+///     &policy,
+/// )
+/// .unwrap();
+/// let (shard_b, _) =
+///     MapWithDictBitpackedShard::<u32>::from_iter_with_policy([(3, vec![2, 3])], &mut dict_builder, &policy)
+///         .unwrap();
+/// assert_eq!(dict_builder.unique_values(), 2);
+/// let dict = dict_builder.finish();
+///
+/// let mut values = [0; 2];
+/// assert!(shard_b.get_values(&dict, &3, &mut values));
+/// assert_eq!(values, [2, 3]);
+/// assert!(!shard_a.get_values(&dict, &3, &mut values));
+/// ```
+#[derive(Debug, Default)]
+pub struct SharedDictBuilder {
+    /// Codec used to encode values in the dictionary
+    block_codec: BlockCodecKind,
+    /// Length of values vectors, set by the first inserted values
+    values_len: Option<usize>,
+    /// Encoded values, without padding
+    values_dict: Vec<u8>,
+    /// Dictionary offsets of inserted values
+    offsets_cache: HashMap<Vec<u32>, usize>,
+}
+
+impl SharedDictBuilder {
+    /// Creates an empty builder encoding values with `block_codec`.
+    pub fn new(block_codec: BlockCodecKind) -> Self {
+        SharedDictBuilder { block_codec, ..Default::default() }
+    }
+
+    /// Returns the dictionary offset of `values`, encoding them if they were not inserted before.
+    fn insert(&mut self, values: Vec<u32>) -> Result<usize, Error> {
+        if *self.values_len.get_or_insert(values.len()) != values.len() {
+            return Err(Error::NotEqualValuesLengths);
+        }
+        if let Some(&offset) = self.offsets_cache.get(&values) {
+            return Ok(offset);
+        }
+
+        let offset = self.values_dict.len();
+        self.block_codec.encode(&values, &mut self.values_dict);
+        self.offsets_cache.insert(values, offset);
+        Ok(offset)
+    }
+
+    /// Returns the number of unique values vectors inserted so far.
+    #[inline]
+    pub fn unique_values(&self) -> usize {
+        self.offsets_cache.len()
+    }
+
+    /// Finishes the dictionary, which shards built with the builder are queried with.
+    pub fn finish(mut self) -> SharedBitpackedDict {
+        // pad dictionary to the values block size in bytes for smooth SIMD decoding
+        self.values_dict.resize(self.values_dict.len() + DICT_PADDING, 0);
+        SharedBitpackedDict { values_dict: self.values_dict.into_boxed_slice(), block_codec: self.block_codec }
+    }
+}
+
+/// Values dictionary shared by `MapWithDictBitpackedShard` shards, built by `SharedDictBuilder`.
+#[derive(Debug, Default)]
+#[cfg_attr(feature = "rkyv_derive", derive(rkyv::Archive, rkyv::Deserialize, rkyv::Serialize))]
+#[cfg_attr(feature = "rkyv_derive", archive_attr(derive(rkyv::CheckBytes)))]
+pub struct SharedBitpackedDict {
+    /// Bit-packed dictionary containing values, followed by `DICT_PADDING` zero bytes
+    values_dict: Box<[u8]>,
+    /// Codec used to encode values in the dictionary
+    block_codec: BlockCodecKind,
+}
+
+impl SharedBitpackedDict {
+    /// Returns the codec used to encode values in the dictionary.
+    #[inline]
+    pub fn block_codec(&self) -> BlockCodecKind {
+        self.block_codec
+    }
+
+    /// Returns the total number of bytes occupied by the dictionary.
+    #[inline]
+    pub fn size(&self) -> usize {
+        size_of_val(self) + size_of_val(self.values_dict.as_ref())
+    }
+}
+
+/// Implement `block_codec` for `Archived` version of `SharedBitpackedDict` if feature is enabled
+#[cfg(feature = "rkyv_derive")]
+impl ArchivedSharedBitpackedDict {
+    /// Returns the codec used to encode values in the dictionary.
+    #[inline]
+    pub fn block_codec(&self) -> BlockCodecKind {
+        BlockCodecKind::from(&self.block_codec)
+    }
+}
+
+/// An immutable hash map with bit-packed `Vec<u32>` values stored in a `SharedBitpackedDict`, see
+/// the module documentation.
+#[derive(Default)]
+#[cfg_attr(feature = "rkyv_derive", derive(rkyv::Archive, rkyv::Deserialize, rkyv::Serialize))]
+#[cfg_attr(feature = "rkyv_derive", archive_attr(derive(rkyv::CheckBytes)))]
+pub struct MapWithDictBitpackedShard<K, const B: usize = 32, const S: usize = 8, ST = u8, H = WyHash>
+where
+    ST: PrimInt + Unsigned,
+    H: Hasher + Default,
+{
+    /// Minimally Perfect Hash Function for keys indices retrieval
+    mphf: Mphf<B, S, ST, H>,
+    /// Map keys
+    keys: Box<[K]>,
+    /// Points to the value index in the shared dictionary
+    values_index: Box<[usize]>,
+    /// Minimum length of the shared dictionary, including padding, for `values_index` to be
+    /// within its bounds
+    min_dict_len: usize,
+}
+
+impl<K, const B: usize, const S: usize, ST, H> MapWithDictBitpackedShard<K, B, S, ST, H>
+where
+    K: Hash + PartialEq + Clone,
+    ST: PrimInt + Unsigned,
+    H: Hasher + Default,
+{
+    /// Constructs a shard from an iterator of key-value pairs, encoding values into `dict_builder`,
+    /// with MPHF `gamma` escalated on failed builds according to `policy`, and returns it along
+    /// with a `BuildReport`. Values of all shards built with the same builder must have the same
+    /// length.
+    pub fn from_iter_with_policy<I>(
+        iter: I,
+        dict_builder: &mut SharedDictBuilder,
+        policy: &BuildPolicy,
+    ) -> Result<(Self, BuildReport), Error>
+    where
+        I: IntoIterator<Item = (K, Vec<u32>)>,
+    {
+        let t0 = Instant::now();
+        let alloc_scope = AllocScope::start();
+        let mut keys = vec![];
+        let mut values_index = vec![];
+
+        for (k, v) in iter {
+            keys.push(k);
+            values_index.push(dict_builder.insert(v)?);
+        }
+
+        let unique_values = values_index.iter().collect::<HashSet<_>>().len();
+        let (mphf, mut report) = Mphf::from_slice_with_policy(&keys, policy).map_err(Error::MphfError)?;
+
+        // Re-order keys and values_index according to mphf
+        for i in 0..keys.len() {
+            loop {
+                let idx = mphf.get(&keys[i]).unwrap();
+                if idx == i {
+                    break;
+                }
+                keys.swap(i, idx);
+                values_index.swap(i, idx);
+            }
+        }
+
+        let shard = MapWithDictBitpackedShard {
+            mphf,
+            keys: keys.into_boxed_slice(),
+            values_index: values_index.into_boxed_slice(),
+            min_dict_len: dict_builder.values_dict.len() + DICT_PADDING,
+        };
+
+        alloc_scope.finish(&mut report);
+        report.duration = t0.elapsed();
+        report.set_size(shard.size());
+        report.set_dedup(shard.values_index.len(), unique_values);
+
+        Ok((shard, report))
+    }
+
+    /// Updates `values` to the array of values corresponding to the key, decoded from `dict`.
+    /// Returns `false` if the key is not present in the shard.
+    ///
+    /// # Panics
+    /// Panics if `dict` is shorter than the dictionary the shard was built with, i.e. is not the
+    /// dictionary finished by the same `SharedDictBuilder`.
+    #[inline]
+    pub fn get_values<Q>(&self, dict: &SharedBitpackedDict, key: &Q, values: &mut [u32]) -> bool
+    where
+        K: Borrow<Q> + PartialEq<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        self.get_values_impl(dict, self.mphf.get(key), key, values)
+    }
+
+    /// Same as [`MapWithDictBitpackedShard::get_values`], but re-uses the hash precomputed by
+    /// `HashedKey`.
+    #[inline]
+    pub fn get_values_hashed<Q>(&self, dict: &SharedBitpackedDict, key: &HashedKey<Q, H>, values: &mut [u32]) -> bool
+    where
+        K: Borrow<Q> + PartialEq<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        self.get_values_impl(dict, self.mphf.get_hashed(key), key.key(), values)
+    }
+
+    /// Inner implementation of `get_values` unpacking values at MPHF index `idx` if the key stored
+    /// there is equal to `key`.
+    #[inline]
+    fn get_values_impl<Q>(&self, dict: &SharedBitpackedDict, idx: Option<usize>, key: &Q, values: &mut [u32]) -> bool
+    where
+        K: PartialEq<Q>,
+        Q: ?Sized,
+    {
+        assert!(
+            dict.values_dict.len() >= self.min_dict_len,
+            "shard queried with a dictionary it was not built with"
+        );
+        let idx = match idx {
+            Some(idx) => idx,
+            None => return false,
+        };
+
+        // SAFETY: `idx` is always within bounds (ensured during construction)
+        unsafe {
+            if access::get(&self.keys, idx) != key {
+                return false;
+            }
+
+            // SAFETY: `idx` is always within bounds and `value_idx` is within bounds of dictionaries
+            // at least `min_dict_len` long (ensured during construction)
+            let value_idx = *access::get(&self.values_index, idx);
+            let values_dict = access::get(&dict.values_dict, value_idx..);
+            dict.block_codec.decode(values_dict, values);
+        }
+
+        true
+    }
+
+    /// Returns the number of keys in the shard.
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.keys.len()
+    }
+
+    /// Returns `true` if the shard contains no keys.
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.keys.is_empty()
+    }
+
+    /// Returns the total number of bytes occupied by the shard, excluding the shared dictionary.
+    #[inline]
+    pub fn size(&self) -> usize {
+        size_of_val(self) + self.mphf.size() + size_of_val(self.keys.as_ref()) + size_of_val(self.values_index.as_ref())
+    }
+
+    /// Checks invariants of the MPHF, that every key is stored at its own index and that `dict` is
+    /// long enough for every values index to point within it.
+    pub fn debug_check_invariants(&self, dict: &SharedBitpackedDict) -> Result<(), InvariantError> {
+        invariants::check_keys(&self.mphf, &self.keys)?;
+        if dict.values_dict.len() < self.min_dict_len {
+            return Err(InvariantError::LengthMismatch);
+        }
+        invariants::check_values_index(&self.values_index, self.keys.len(), self.min_dict_len - DICT_PADDING)
+    }
+}
+
+/// Implement `get_values` for `Archived` version of `MapWithDictBitpackedShard` if feature is
+/// enabled
+#[cfg(feature = "rkyv_derive")]
+impl<K, const B: usize, const S: usize, ST, H> ArchivedMapWithDictBitpackedShard<K, B, S, ST, H>
+where
+    K: PartialEq + Hash + rkyv::Archive,
+    K::Archived: PartialEq<K>,
+    ST: PrimInt + Unsigned + rkyv::Archive<Archived = ST>,
+    H: Hasher + Default,
+{
+    /// Updates `values` to the array of values corresponding to the key, decoded from the archived
+    /// `dict`. Returns `false` if the key is not present in the shard.
+    ///
+    /// # Panics
+    /// Panics if `dict` is shorter than the dictionary the shard was built with.
+    #[inline]
+    pub fn get_values(&self, dict: &ArchivedSharedBitpackedDict, key: &K, values: &mut [u32]) -> bool {
+        assert!(
+            dict.values_dict.len() >= self.min_dict_len as usize,
+            "shard queried with a dictionary it was not built with"
+        );
+        let idx = match self.mphf.get(key) {
+            Some(idx) => idx,
+            None => return false,
+        };
+
+        // SAFETY: `idx` is always within bounds (ensured during construction)
+        unsafe {
+            if access::get(&self.keys, idx) != key {
+                return false;
+            }
+
+            // SAFETY: `idx` is always within bounds and `value_idx` is within bounds of dictionaries
+            // at least `min_dict_len` long (ensured during construction)
+            let value_idx = *access::get(&self.values_index, idx) as usize;
+            let values_dict = access::get(&dict.values_dict, value_idx..);
+            dict.block_codec().decode(values_dict, values);
+        }
+
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::MapWithDictBitpacked;
+    use rand::{Rng, SeedableRng};
+    use rand_chacha::ChaCha8Rng;
+
+    #[test]
+    fn test_shared_dict() {
+        let mut rng = ChaCha8Rng::seed_from_u64(123);
+        let values: Vec<Vec<u32>> = (0..50)
+            .map(|_| (0..40).map(|_| rng.gen_range(0..1000)).collect())
+            .collect();
+        let shards_entries: Vec<Vec<(u64, Vec<u32>)>> = (0..4)
+            .map(|_| {
+                (0..1000)
+                    .map(|_| (rng.gen(), values[rng.gen_range(0..values.len())].clone()))
+                    .collect()
+            })
+            .collect();
+
+        for block_codec in BlockCodecKind::ALL {
+            let mut dict_builder = SharedDictBuilder::new(block_codec);
+            let shards: Vec<_> = shards_entries
+                .iter()
+                .map(|entries| {
+                    let (shard, report) = MapWithDictBitpackedShard::<u64>::from_iter_with_policy(
+                        entries.clone(),
+                        &mut dict_builder,
+                        &BuildPolicy::default(),
+                    )
+                    .unwrap();
+                    assert!(report.dedup_ratio.unwrap() > 1.0);
+                    shard
+                })
+                .collect();
+            assert_eq!(dict_builder.unique_values(), values.len());
+            let dict = dict_builder.finish();
+
+            // every values vector is stored once, while separate maps store it once per shard
+            let separate_dicts_size: usize = shards_entries
+                .iter()
+                .map(|entries| {
+                    let (map, _) = MapWithDictBitpacked::<u64>::from_iter_with_codec(
+                        entries.clone(),
+                        block_codec,
+                        &BuildPolicy::default(),
+                    )
+                    .unwrap();
+                    map.into_raw_parts().3.len()
+                })
+                .sum();
+            assert!(dict.values_dict.len() < separate_dicts_size);
+
+            let mut res = vec![0; 40];
+            for (shard, entries) in shards.iter().zip(&shards_entries) {
+                assert_eq!(shard.debug_check_invariants(&dict), Ok(()));
+                assert_eq!(shard.len(), entries.len());
+                for (key, values) in entries {
+                    assert!(shard.get_values(&dict, key, &mut res));
+                    assert_eq!(&res, values);
+                    assert!(shard.get_values_hashed(&dict, &HashedKey::new(key), &mut res));
+                    assert_eq!(&res, values);
+                }
+            }
+
+            #[cfg(feature = "rkyv_derive")]
+            {
+                let dict_bytes = rkyv::to_bytes::<_, 1024>(&dict).unwrap();
+                let rkyv_dict = rkyv::check_archived_root::<SharedBitpackedDict>(&dict_bytes).unwrap();
+                let shard_bytes = rkyv::to_bytes::<_, 1024>(&shards[0]).unwrap();
+                let rkyv_shard = rkyv::check_archived_root::<MapWithDictBitpackedShard<u64>>(&shard_bytes).unwrap();
+                for (key, values) in &shards_entries[0] {
+                    assert!(rkyv_shard.get_values(rkyv_dict, key, &mut res));
+                    assert_eq!(&res, values);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_not_equal_values_lengths() {
+        let mut dict_builder = SharedDictBuilder::new(BlockCodecKind::Bitpacked);
+        let policy = BuildPolicy::default();
+        MapWithDictBitpackedShard::<u64>::from_iter_with_policy([(1, vec![1])], &mut dict_builder, &policy).unwrap();
+        let res =
+            MapWithDictBitpackedShard::<u64>::from_iter_with_policy([(2, vec![1, 2])], &mut dict_builder, &policy);
+        assert!(matches!(res, Err(Error::NotEqualValuesLengths)));
+    }
+
+    #[test]
+    #[should_panic(expected = "shard queried with a dictionary it was not built with")]
+    fn test_other_dict() {
+        let mut dict_builder = SharedDictBuilder::new(BlockCodecKind::Bitpacked);
+        let (shard, _) = MapWithDictBitpackedShard::<u64>::from_iter_with_policy(
+            [(1, vec![1])],
+            &mut dict_builder,
+            &BuildPolicy::default(),
+        )
+        .unwrap();
+        let other_dict = SharedDictBuilder::new(BlockCodecKind::Bitpacked).finish();
+        shard.get_values(&other_dict, &1, &mut [0]);
+    }
+}
@@ -0,0 +1,451 @@
+//! A module offering `MapWithDictBool`, an immutable hash map with `Vec<bool>` values.
+//!
+//! `MapWithDictBool` is a sibling of `MapWithDictBitpacked` specialized for per-key flag vectors.
+//! Unique `Vec<bool>` values are stored in the byte dictionary at exactly 1 bit per element,
+//! without the per-block bits width header and `u32` conversion of the generic bit-packing, so
+//! that queries only need to test bits. As with `MapWithDictBitpacked`, all values vectors *must*
+//! have the same length, which is not stored in the map and has to be known by the caller.
+
+use std::borrow::Borrow;
+use std::collections::HashMap;
+use std::hash::{Hash, Hasher};
+use std::mem::size_of_val;
+use std::time::Instant;
+
+use num::{PrimInt, Unsigned};
+use wyhash::WyHash;
+
+use crate::access;
+use crate::invariants::{self, InvariantError};
+use crate::map_with_dict_bitpacked::Error;
+use crate::mphf::{BuildPolicy, Mphf, DEFAULT_GAMMA};
+use crate::progress::BuildProgress;
+use crate::report::{AllocScope, BuildReport};
+
+/// An efficient, immutable hash map with `Vec<bool>` values packed at 1 bit per element.
+#[derive(Default)]
+#[cfg_attr(feature = "rkyv_derive", derive(rkyv::Archive, rkyv::Deserialize, rkyv::Serialize))]
+#[cfg_attr(feature = "rkyv_derive", archive_attr(derive(rkyv::CheckBytes)))]
+pub struct MapWithDictBool<K, const B: usize = 32, const S: usize = 8, ST = u8, H = WyHash>
+where
+    ST: PrimInt + Unsigned,
+    H: Hasher + Default,
+{
+    /// Minimally Perfect Hash Function for keys indices retrieval
+    pub(crate) mphf: Mphf<B, S, ST, H>,
+    /// Map keys
+    pub(crate) keys: Box<[K]>,
+    /// Points to the value index in the dictionary
+    pub(crate) values_index: Box<[usize]>,
+    /// Dictionary containing values packed at 1 bit per element
+    pub(crate) values_dict: Box<[u8]>,
+}
+
+impl<K, const B: usize, const S: usize, ST, H> MapWithDictBool<K, B, S, ST, H>
+where
+    K: Hash + PartialEq + Clone,
+    ST: PrimInt + Unsigned,
+    H: Hasher + Default,
+{
+    /// Constructs a `MapWithDictBool` from an iterator of key-value pairs and MPHF function params.
+    pub fn from_iter_with_params<I>(iter: I, gamma: f32) -> Result<Self, Error>
+    where
+        I: IntoIterator<Item = (K, Vec<bool>)>,
+    {
+        Self::from_iter_with_policy(iter, &BuildPolicy::fixed(gamma)).map(|(map, _)| map)
+    }
+
+    /// Constructs a `MapWithDictBool` from an iterator of key-value pairs, escalating MPHF `gamma`
+    /// on failed builds according to `policy`, and returns it along with a `BuildReport`.
+    ///
+    /// # Examples
+    /// ```
+    /// # use entropy_map::{BuildPolicy, MapWithDictBool};
+    /// let policy = BuildPolicy::default();
+    /// let (map, report) =
+    ///     MapWithDictBool::<u32>::from_iter_with_policy([(1, vec![true]), (3, vec![true])], &policy).unwrap();
+    /// assert_eq!(report.dedup_ratio, Some(2.0));
+    /// ```
+    pub fn from_iter_with_policy<I>(iter: I, policy: &BuildPolicy) -> Result<(Self, BuildReport), Error>
+    where
+        I: IntoIterator<Item = (K, Vec<bool>)>,
+    {
+        Self::from_iter_with_progress(iter, policy, &BuildProgress::default())
+    }
+
+    /// Same as [`MapWithDictBool::from_iter_with_policy`], but reports MPHF construction progress to
+    /// `progress` and fails once cancellation is requested via it.
+    pub fn from_iter_with_progress<I>(
+        iter: I,
+        policy: &BuildPolicy,
+        progress: &BuildProgress,
+    ) -> Result<(Self, BuildReport), Error>
+    where
+        I: IntoIterator<Item = (K, Vec<bool>)>,
+    {
+        let t0 = Instant::now();
+        let alloc_scope = AllocScope::start();
+        let mut keys = vec![];
+        let mut offsets_cache = HashMap::new();
+        let mut values_index = vec![];
+        let mut values_dict = vec![];
+
+        let mut iter = iter.into_iter().peekable();
+        let v_len = iter.peek().map_or(0, |(_, v)| v.len());
+
+        for (k, v) in iter {
+            keys.push(k.clone());
+
+            if v.len() != v_len {
+                return Err(Error::NotEqualValuesLengths);
+            }
+
+            if let Some(&offset) = offsets_cache.get(&v) {
+                // re-use dictionary offset if found in cache
+                values_index.push(offset);
+            } else {
+                // store current dictionary length as an offset in both index and cache
+                let offset = values_dict.len();
+                offsets_cache.insert(v.clone(), offset);
+                values_index.push(offset);
+
+                // append packed values to the dictionary
+                pack_bools(&v, &mut values_dict);
+            }
+        }
+
+        let unique_values = offsets_cache.len();
+        let (mphf, mut report) = Mphf::from_slice_with_progress(&keys, policy, progress).map_err(Error::MphfError)?;
+
+        // Re-order keys and values_index according to mphf
+        for i in 0..keys.len() {
+            loop {
+                let idx = mphf.get(&keys[i]).unwrap();
+                if idx == i {
+                    break;
+                }
+                keys.swap(i, idx);
+                values_index.swap(i, idx);
+            }
+        }
+
+        let map = MapWithDictBool {
+            mphf,
+            keys: keys.into_boxed_slice(),
+            values_index: values_index.into_boxed_slice(),
+            values_dict: values_dict.into_boxed_slice(),
+        };
+
+        alloc_scope.finish(&mut report);
+        report.duration = t0.elapsed();
+        report.set_size(map.size());
+        report.set_dedup(map.values_index.len(), unique_values);
+
+        Ok((map, report))
+    }
+
+    /// Updates `values` to the array of values corresponding to the key. Returns `false` if the
+    /// key is not not present in the map.
+    ///
+    /// # Examples
+    /// ```
+    /// # use std::collections::HashMap;
+    /// # use entropy_map::MapWithDictBool;
+    /// let map = MapWithDictBool::try_from(HashMap::from([(1, vec![true, false]), (3, vec![false, true])])).unwrap();
+    /// let mut values = [false; 2];
+    /// assert_eq!(map.get_values(&1, &mut values), true);
+    /// assert_eq!(values, [true, false]);
+    /// assert_eq!(map.get_values(&2, &mut values), false);
+    /// ```
+    #[inline]
+    pub fn get_values<Q>(&self, key: &Q, values: &mut [bool]) -> bool
+    where
+        K: Borrow<Q> + PartialEq<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        let idx = match self.mphf.get(key) {
+            Some(idx) => idx,
+            None => return false,
+        };
+
+        // SAFETY: `idx` is always within bounds (ensured during construction)
+        unsafe {
+            if access::get(&self.keys, idx) != key {
+                return false;
+            }
+
+            // SAFETY: `idx` and `value_idx` are always within bounds (ensure during construction)
+            let value_idx = *access::get(&self.values_index, idx);
+            let dict = access::get(&self.values_dict, value_idx..);
+            unpack_bools(dict, values);
+        }
+
+        true
+    }
+
+    /// Returns the number of keys in the map.
+    ///
+    /// # Examples
+    /// ```
+    /// # use std::collections::HashMap;
+    /// # use entropy_map::MapWithDictBool;
+    /// let map = MapWithDictBool::try_from(HashMap::from([(1, vec![true]), (3, vec![false])])).unwrap();
+    /// assert_eq!(map.len(), 2);
+    /// ```
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.keys.len()
+    }
+
+    /// Returns `true` if the map contains no elements.
+    ///
+    /// # Examples
+    /// ```
+    /// # use std::collections::HashMap;
+    /// # use entropy_map::MapWithDictBool;
+    /// let map = MapWithDictBool::try_from(HashMap::from([(0, vec![true]); 0])).unwrap();
+    /// assert_eq!(map.is_empty(), true);
+    /// ```
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.keys.is_empty()
+    }
+
+    /// Checks if the map contains the specified key.
+    ///
+    /// # Examples
+    /// ```
+    /// # use std::collections::HashMap;
+    /// # use entropy_map::MapWithDictBool;
+    /// let map = MapWithDictBool::try_from(HashMap::from([(1, vec![true]), (3, vec![false])])).unwrap();
+    /// assert_eq!(map.contains_key(&1), true);
+    /// assert_eq!(map.contains_key(&2), false);
+    /// ```
+    #[inline]
+    pub fn contains_key<Q>(&self, key: &Q) -> bool
+    where
+        K: Borrow<Q> + PartialEq<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        if let Some(idx) = self.mphf.get(key) {
+            // SAFETY: `idx` is always within bounds (ensured during construction)
+            unsafe { access::get(&self.keys, idx) == key }
+        } else {
+            false
+        }
+    }
+
+    /// Returns an iterator over the map, yielding key-value pairs with `n` values each.
+    ///
+    /// # Examples
+    /// ```
+    /// # use std::collections::HashMap;
+    /// # use entropy_map::MapWithDictBool;
+    /// let map = MapWithDictBool::try_from(HashMap::from([(1, vec![true]), (3, vec![false])])).unwrap();
+    /// for (key, val) in map.iter(1) {
+    ///     println!("key: {key} val: {val:?}");
+    /// }
+    /// ```
+    #[inline]
+    pub fn iter(&self, n: usize) -> impl ExactSizeIterator<Item = (&K, Vec<bool>)> + DoubleEndedIterator {
+        self.keys
+            .iter()
+            .zip(self.values_index.iter())
+            .map(move |(key, &value_idx)| {
+                let mut values = vec![false; n];
+                // SAFETY: `value_idx` is always within bounds (ensured during construction)
+                let dict = unsafe { access::get(&self.values_dict, value_idx..) };
+                unpack_bools(dict, &mut values);
+                (key, values)
+            })
+    }
+
+    /// Returns an iterator over the keys of the map.
+    ///
+    /// # Examples
+    /// ```
+    /// # use std::collections::HashMap;
+    /// # use entropy_map::MapWithDictBool;
+    /// let map = MapWithDictBool::try_from(HashMap::from([(1, vec![true]), (3, vec![false])])).unwrap();
+    /// for key in map.keys() {
+    ///     println!("{key}");
+    /// }
+    /// ```
+    #[inline]
+    pub fn keys(&self) -> impl ExactSizeIterator<Item = &K> + DoubleEndedIterator {
+        self.keys.iter()
+    }
+
+    /// Returns the total number of bytes occupied by the structure.
+    ///
+    /// # Examples
+    /// ```
+    /// # use std::collections::HashMap;
+    /// # use entropy_map::MapWithDictBool;
+    /// let map = MapWithDictBool::try_from(HashMap::from([(1, vec![true]), (3, vec![false])])).unwrap();
+    /// assert_eq!(map.size(), 248);
+    /// ```
+    pub fn size(&self) -> usize {
+        size_of_val(self)
+            + self.mphf.size()
+            + size_of_val(self.keys.as_ref())
+            + size_of_val(self.values_index.as_ref())
+            + size_of_val(self.values_dict.as_ref())
+    }
+
+    /// Same as [`MapWithDict::debug_check_invariants`](crate::MapWithDict::debug_check_invariants),
+    /// values dictionary offsets being checked to point within or right past the dictionary.
+    pub fn debug_check_invariants(&self) -> Result<(), InvariantError> {
+        invariants::check_keys(&self.mphf, &self.keys)?;
+        invariants::check_values_index(&self.values_index, self.keys.len(), self.values_dict.len() + 1)
+    }
+}
+
+/// Creates a `MapWithDictBool` from a `HashMap`.
+impl<K> TryFrom<HashMap<K, Vec<bool>>> for MapWithDictBool<K>
+where
+    K: PartialEq + Hash + Clone,
+{
+    type Error = Error;
+
+    #[inline]
+    fn try_from(value: HashMap<K, Vec<bool>>) -> Result<Self, Self::Error> {
+        MapWithDictBool::from_iter_with_params(value, DEFAULT_GAMMA)
+    }
+}
+
+/// `pack_bools` appends `values` to the dictionary at 1 bit per value, least significant bit first
+fn pack_bools(values: &[bool], dict: &mut Vec<u8>) {
+    for chunk in values.chunks(8) {
+        let byte = chunk
+            .iter()
+            .enumerate()
+            .fold(0u8, |byte, (i, &v)| byte | ((v as u8) << i));
+        dict.push(byte);
+    }
+}
+
+/// `unpack_bools` fills `res` with values packed at 1 bit per value at the start of `dict`
+#[inline]
+fn unpack_bools(dict: &[u8], res: &mut [bool]) {
+    for (chunk, &byte) in res.chunks_mut(8).zip(dict) {
+        for (i, v) in chunk.iter_mut().enumerate() {
+            *v = (byte >> i) & 1 == 1;
+        }
+    }
+}
+
+/// Implement `get` for `Archived` version of `MapWithDictBool` if feature is enabled
+#[cfg(feature = "rkyv_derive")]
+impl<K, const B: usize, const S: usize, ST, H> ArchivedMapWithDictBool<K, B, S, ST, H>
+where
+    K: PartialEq + Hash + rkyv::Archive,
+    K::Archived: PartialEq<K>,
+    ST: PrimInt + Unsigned + rkyv::Archive<Archived = ST>,
+    H: Hasher + Default,
+{
+    /// Updates `values` to the array of values corresponding to the key. Returns `false` if the
+    /// key is not not present in the map.
+    ///
+    /// # Examples
+    /// ```
+    /// # use std::collections::HashMap;
+    /// # use entropy_map::MapWithDictBool;
+    /// let map = MapWithDictBool::try_from(HashMap::from([(1, vec![true]), (3, vec![false])])).unwrap();
+    /// let bytes = rkyv::to_bytes::<_, 1024>(&map).unwrap();
+    /// let archived_map = rkyv::check_archived_root::<MapWithDictBool<u32>>(&bytes).unwrap();
+    /// let mut values = [false];
+    /// assert_eq!(archived_map.get_values(&1, &mut values), true);
+    /// assert_eq!(values, [true]);
+    /// assert_eq!(archived_map.get_values(&2, &mut values), false);
+    /// ```
+    #[inline]
+    pub fn get_values(&self, key: &K, values: &mut [bool]) -> bool {
+        let idx = match self.mphf.get(key) {
+            Some(idx) => idx,
+            None => return false,
+        };
+
+        // SAFETY: `idx` is always within bounds (ensured during construction)
+        unsafe {
+            if access::get(&self.keys, idx) != key {
+                return false;
+            }
+
+            // SAFETY: `idx` and `value_idx` are always within bounds (ensure during construction)
+            let value_idx = *access::get(&self.values_index, idx) as usize;
+            let dict = access::get(&self.values_dict, value_idx..);
+            unpack_bools(dict, values);
+        }
+
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::{Rng, SeedableRng};
+    use rand_chacha::ChaCha8Rng;
+    use test_case::test_case;
+
+    fn gen_map(items_num: usize, values_num: usize) -> HashMap<u64, Vec<bool>> {
+        let mut rng = ChaCha8Rng::seed_from_u64(123);
+
+        (0..items_num)
+            .map(|_| {
+                let key = rng.gen::<u64>();
+                let value = (0..values_num).map(|_| rng.gen_bool(0.1)).collect();
+                (key, value)
+            })
+            .collect()
+    }
+
+    #[test_case(0; "empty values")]
+    #[test_case(3; "partial byte")]
+    #[test_case(8; "full byte")]
+    #[test_case(21; "multiple bytes")]
+    fn test_map_with_dict_bool(values_num: usize) {
+        let original_map = gen_map(1000, values_num);
+        let map = MapWithDictBool::try_from(original_map.clone()).unwrap();
+
+        assert_eq!(map.debug_check_invariants(), Ok(()));
+        assert_eq!(map.len(), original_map.len());
+        assert_eq!(map.is_empty(), original_map.is_empty());
+
+        let mut values_buf = vec![false; values_num];
+        for (key, value) in &original_map {
+            assert!(map.get_values(key, &mut values_buf));
+            assert_eq!(value, &values_buf);
+            assert!(map.contains_key(key));
+        }
+        assert!(!map.get_values(&0, &mut values_buf));
+        assert!(!map.contains_key(&0));
+
+        for (&k, v) in map.iter(values_num) {
+            assert_eq!(original_map.get(&k), Some(&v));
+        }
+    }
+
+    #[test]
+    fn test_not_equal_values_lengths() {
+        let res = MapWithDictBool::<u64>::from_iter_with_params([(1, vec![true]), (2, vec![])], DEFAULT_GAMMA);
+        assert!(matches!(res, Err(Error::NotEqualValuesLengths)));
+    }
+
+    #[cfg(feature = "rkyv_derive")]
+    #[test]
+    fn test_rkyv() {
+        let values_num = 21;
+        let original_map = gen_map(1000, values_num);
+        let map = MapWithDictBool::try_from(original_map.clone()).unwrap();
+        let rkyv_bytes = rkyv::to_bytes::<_, 1024>(&map).unwrap();
+        let rkyv_map = rkyv::check_archived_root::<MapWithDictBool<u64>>(&rkyv_bytes).unwrap();
+
+        let mut values_buf = vec![false; values_num];
+        for (k, v) in original_map {
+            assert!(rkyv_map.get_values(&k, &mut values_buf));
+            assert_eq!(v, values_buf);
+        }
+    }
+}
@@ -0,0 +1,527 @@
+//! A module offering `MapWithDictCodec`, an immutable hash map with values encoded by a pluggable
+//! `ValueCodec`.
+//!
+//! `MapWithDictCodec` generalizes `MapWithDictBitpacked` and `MapWithDictBool`: unique values are
+//! encoded by codec `C` once and appended to a byte dictionary, keys index into it via MPHF, so a
+//! domain-specific compact encoding only needs a `ValueCodec` implementation rather than a new map
+//! type. Values are deduplicated by their encoding, so `V` doesn't need to implement `Hash` or
+//! `Eq`. Queries decode values with `C::decode`, which may borrow from the dictionary, e.g. `&str`
+//! values of `BytesCodec`, including when querying archived maps.
+
+use std::borrow::Borrow;
+use std::collections::HashMap;
+use std::hash::{Hash, Hasher};
+use std::marker::PhantomData;
+use std::mem::size_of_val;
+use std::time::Instant;
+
+use num::{PrimInt, Unsigned};
+use wyhash::WyHash;
+
+use crate::access;
+use crate::hashed_key::HashedKey;
+use crate::invariants::{self, InvariantError};
+use crate::mphf::{BuildPolicy, Mphf, MphfError, DEFAULT_GAMMA};
+use crate::progress::BuildProgress;
+use crate::report::{AllocScope, BuildReport};
+use crate::value_codec::ValueCodec;
+
+/// An efficient, immutable hash map with values encoded by `ValueCodec` `C` into a byte dictionary.
+#[derive(Default)]
+#[cfg_attr(feature = "rkyv_derive", derive(rkyv::Archive, rkyv::Deserialize, rkyv::Serialize))]
+#[cfg_attr(feature = "rkyv_derive", archive_attr(derive(rkyv::CheckBytes)))]
+pub struct MapWithDictCodec<K, V, C, const B: usize = 32, const S: usize = 8, ST = u8, H = WyHash>
+where
+    ST: PrimInt + Unsigned,
+    H: Hasher + Default,
+{
+    /// Minimally Perfect Hash Function for keys indices retrieval
+    mphf: Mphf<B, S, ST, H>,
+    /// Map keys
+    keys: Box<[K]>,
+    /// Points to the value offset in the dictionary
+    values_index: Box<[usize]>,
+    /// Dictionary containing encoded values
+    values_dict: Box<[u8]>,
+    /// Phantom field for the values type and codec
+    _phantom_codec: PhantomData<(V, C)>,
+}
+
+impl<K, V, C, const B: usize, const S: usize, ST, H> MapWithDictCodec<K, V, C, B, S, ST, H>
+where
+    K: Hash + PartialEq,
+    C: ValueCodec<V>,
+    ST: PrimInt + Unsigned,
+    H: Hasher + Default,
+{
+    /// Constructs a `MapWithDictCodec` from an iterator of key-value pairs and MPHF function params.
+    pub fn from_iter_with_params<I>(iter: I, gamma: f32) -> Result<Self, MphfError>
+    where
+        I: IntoIterator<Item = (K, V)>,
+    {
+        Self::from_iter_with_policy(iter, &BuildPolicy::fixed(gamma)).map(|(map, _)| map)
+    }
+
+    /// Constructs a `MapWithDictCodec` from an iterator of key-value pairs, escalating MPHF `gamma`
+    /// on failed builds according to `policy`, and returns it along with a `BuildReport`.
+    ///
+    /// # Examples
+    /// ```
+    /// # use entropy_map::{BuildPolicy, MapWithDictCodec, VarintCodec};
+    /// let policy = BuildPolicy::default();
+    /// let (map, report) =
+    ///     MapWithDictCodec::<u32, u64, VarintCodec>::from_iter_with_policy([(1, 2), (3, 2)], &policy).unwrap();
+    /// assert_eq!(report.dedup_ratio, Some(2.0));
+    /// ```
+    pub fn from_iter_with_policy<I>(iter: I, policy: &BuildPolicy) -> Result<(Self, BuildReport), MphfError>
+    where
+        I: IntoIterator<Item = (K, V)>,
+    {
+        Self::from_iter_with_progress(iter, policy, &BuildProgress::default())
+    }
+
+    /// Same as [`MapWithDictCodec::from_iter_with_policy`], but reports MPHF construction progress
+    /// to `progress` and fails once cancellation is requested via it.
+    pub fn from_iter_with_progress<I>(
+        iter: I,
+        policy: &BuildPolicy,
+        progress: &BuildProgress,
+    ) -> Result<(Self, BuildReport), MphfError>
+    where
+        I: IntoIterator<Item = (K, V)>,
+    {
+        let t0 = Instant::now();
+        let alloc_scope = AllocScope::start();
+        let mut keys = vec![];
+        let mut offsets_cache = HashMap::new();
+        let mut values_index = vec![];
+        let mut values_dict = vec![];
+        let mut encoded = vec![];
+
+        for (k, v) in iter {
+            keys.push(k);
+
+            encoded.clear();
+            C::encode(&v, &mut encoded);
+            if let Some(&offset) = offsets_cache.get(&encoded) {
+                // re-use dictionary offset if found in cache
+                values_index.push(offset);
+            } else {
+                // store current dictionary length as an offset in both index and cache
+                let offset = values_dict.len();
+                offsets_cache.insert(encoded.clone(), offset);
+                values_index.push(offset);
+
+                // append encoded value to the dictionary
+                values_dict.extend_from_slice(&encoded);
+            }
+        }
+
+        // pad dictionary for codecs decoding whole blocks
+        values_dict.resize(values_dict.len() + C::PADDING, 0);
+
+        let unique_values = offsets_cache.len();
+        let (mphf, mut report) = Mphf::from_slice_with_progress(&keys, policy, progress)?;
+
+        // Re-order keys and values_index according to mphf
+        for i in 0..keys.len() {
+            loop {
+                let idx = mphf.get(&keys[i]).unwrap();
+                if idx == i {
+                    break;
+                }
+                keys.swap(i, idx);
+                values_index.swap(i, idx);
+            }
+        }
+
+        let map = MapWithDictCodec {
+            mphf,
+            keys: keys.into_boxed_slice(),
+            values_index: values_index.into_boxed_slice(),
+            values_dict: values_dict.into_boxed_slice(),
+            _phantom_codec: PhantomData,
+        };
+
+        alloc_scope.finish(&mut report);
+        report.duration = t0.elapsed();
+        report.set_size(map.size());
+        report.set_dedup(map.values_index.len(), unique_values);
+
+        Ok((map, report))
+    }
+
+    /// Returns the value decoded by `C` corresponding to the key, or `None` if the key is not
+    /// present in the map.
+    ///
+    /// # Examples
+    /// ```
+    /// # use std::collections::HashMap;
+    /// # use entropy_map::{BytesCodec, MapWithDictCodec};
+    /// let map: MapWithDictCodec<u32, String, BytesCodec> =
+    ///     MapWithDictCodec::try_from(HashMap::from([(1, "a".to_string()), (3, "b".to_string())])).unwrap();
+    /// assert_eq!(map.get(&1), Some("a"));
+    /// assert_eq!(map.get(&2), None);
+    /// ```
+    #[inline]
+    pub fn get<Q>(&self, key: &Q) -> Option<C::Decoded<'_>>
+    where
+        K: Borrow<Q> + PartialEq<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        self.get_impl(self.mphf.get(key), key)
+    }
+
+    /// Same as [`MapWithDictCodec::get`], but re-uses the hash precomputed by `HashedKey`.
+    ///
+    /// # Examples
+    /// ```
+    /// # use std::collections::HashMap;
+    /// # use entropy_map::{HashedKey, MapWithDictCodec, VarintCodec};
+    /// let map: MapWithDictCodec<u32, u64, VarintCodec> =
+    ///     MapWithDictCodec::try_from(HashMap::from([(1, 2), (3, 4)])).unwrap();
+    /// assert_eq!(map.get_hashed(&HashedKey::new(&1)), Some(2));
+    /// ```
+    #[inline]
+    pub fn get_hashed<Q>(&self, key: &HashedKey<Q, H>) -> Option<C::Decoded<'_>>
+    where
+        K: Borrow<Q> + PartialEq<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        self.get_impl(self.mphf.get_hashed(key), key.key())
+    }
+
+    /// Same as [`MapWithDictCodec::get`], but skips hashing `key` and uses `hash` computed with the
+    /// map hasher `H` instead (e.g. via [`HashedKey::hash`]). The key is still compared with the
+    /// stored one, so a mismatching `hash` results in `None`.
+    #[inline]
+    pub fn get_with_hash<Q>(&self, hash: u64, key: &Q) -> Option<C::Decoded<'_>>
+    where
+        K: Borrow<Q> + PartialEq<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        self.get_impl(self.mphf.get_by_hash(hash), key)
+    }
+
+    /// Inner implementation of `get` decoding the value at MPHF index `idx` if the key stored there
+    /// is equal to `key`.
+    #[inline]
+    fn get_impl<Q>(&self, idx: Option<usize>, key: &Q) -> Option<C::Decoded<'_>>
+    where
+        K: PartialEq<Q>,
+        Q: ?Sized,
+    {
+        let idx = idx?;
+
+        // SAFETY: `idx` is always within bounds (ensured during construction)
+        unsafe {
+            if access::get(&self.keys, idx) != key {
+                return None;
+            }
+
+            // SAFETY: `idx` and `value_idx` are always within bounds (ensured during construction)
+            let value_idx = *access::get(&self.values_index, idx);
+            Some(C::decode(access::get(&self.values_dict, value_idx..)))
+        }
+    }
+
+    /// Returns the number of keys in the map.
+    ///
+    /// # Examples
+    /// ```
+    /// # use std::collections::HashMap;
+    /// # use entropy_map::{MapWithDictCodec, VarintCodec};
+    /// let map: MapWithDictCodec<u32, u64, VarintCodec> =
+    ///     MapWithDictCodec::try_from(HashMap::from([(1, 2), (3, 4)])).unwrap();
+    /// assert_eq!(map.len(), 2);
+    /// ```
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.keys.len()
+    }
+
+    /// Returns `true` if the map contains no elements.
+    ///
+    /// # Examples
+    /// ```
+    /// # use std::collections::HashMap;
+    /// # use entropy_map::{MapWithDictCodec, VarintCodec};
+    /// let map: MapWithDictCodec<u32, u64, VarintCodec> = MapWithDictCodec::try_from(HashMap::new()).unwrap();
+    /// assert_eq!(map.is_empty(), true);
+    /// ```
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.keys.is_empty()
+    }
+
+    /// Checks if the map contains the specified key.
+    ///
+    /// # Examples
+    /// ```
+    /// # use std::collections::HashMap;
+    /// # use entropy_map::{MapWithDictCodec, VarintCodec};
+    /// let map: MapWithDictCodec<u32, u64, VarintCodec> =
+    ///     MapWithDictCodec::try_from(HashMap::from([(1, 2), (3, 4)])).unwrap();
+    /// assert_eq!(map.contains_key(&1), true);
+    /// assert_eq!(map.contains_key(&2), false);
+    /// ```
+    #[inline]
+    pub fn contains_key<Q>(&self, key: &Q) -> bool
+    where
+        K: Borrow<Q> + PartialEq<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        if let Some(idx) = self.mphf.get(key) {
+            // SAFETY: `idx` is always within bounds (ensured during construction)
+            unsafe { access::get(&self.keys, idx) == key }
+        } else {
+            false
+        }
+    }
+
+    /// Returns an iterator over the map, yielding key-value pairs.
+    ///
+    /// # Examples
+    /// ```
+    /// # use std::collections::HashMap;
+    /// # use entropy_map::{MapWithDictCodec, VarintCodec};
+    /// let map: MapWithDictCodec<u32, u64, VarintCodec> =
+    ///     MapWithDictCodec::try_from(HashMap::from([(1, 2), (3, 4)])).unwrap();
+    /// for (key, val) in map.iter() {
+    ///     println!("key: {key} val: {val}");
+    /// }
+    /// ```
+    #[inline]
+    pub fn iter(&self) -> impl ExactSizeIterator<Item = (&K, C::Decoded<'_>)> + DoubleEndedIterator {
+        self.keys().zip(self.values())
+    }
+
+    /// Returns an iterator over the keys of the map.
+    ///
+    /// # Examples
+    /// ```
+    /// # use std::collections::HashMap;
+    /// # use entropy_map::{MapWithDictCodec, VarintCodec};
+    /// let map: MapWithDictCodec<u32, u64, VarintCodec> =
+    ///     MapWithDictCodec::try_from(HashMap::from([(1, 2), (3, 4)])).unwrap();
+    /// for key in map.keys() {
+    ///     println!("{key}");
+    /// }
+    /// ```
+    #[inline]
+    pub fn keys(&self) -> impl ExactSizeIterator<Item = &K> + DoubleEndedIterator {
+        self.keys.iter()
+    }
+
+    /// Returns an iterator over the values of the map.
+    ///
+    /// # Examples
+    /// ```
+    /// # use std::collections::HashMap;
+    /// # use entropy_map::{MapWithDictCodec, VarintCodec};
+    /// let map: MapWithDictCodec<u32, u64, VarintCodec> =
+    ///     MapWithDictCodec::try_from(HashMap::from([(1, 2), (3, 4)])).unwrap();
+    /// for val in map.values() {
+    ///     println!("{val}");
+    /// }
+    /// ```
+    #[inline]
+    pub fn values(&self) -> impl ExactSizeIterator<Item = C::Decoded<'_>> + DoubleEndedIterator {
+        self.values_index.iter().map(move |&value_idx| {
+            // SAFETY: `value_idx` is always within bounds (ensured during construction)
+            C::decode(unsafe { access::get(&self.values_dict, value_idx..) })
+        })
+    }
+
+    /// Returns the total number of bytes occupied by the structure.
+    ///
+    /// # Examples
+    /// ```
+    /// # use std::collections::HashMap;
+    /// # use entropy_map::{MapWithDictCodec, VarintCodec};
+    /// let map: MapWithDictCodec<u32, u64, VarintCodec> =
+    ///     MapWithDictCodec::try_from(HashMap::from([(1, 2), (3, 4)])).unwrap();
+    /// assert_eq!(map.size(), 248);
+    /// ```
+    pub fn size(&self) -> usize {
+        size_of_val(self)
+            + self.mphf.size()
+            + size_of_val(self.keys.as_ref())
+            + size_of_val(self.values_index.as_ref())
+            + size_of_val(self.values_dict.as_ref())
+    }
+
+    /// Same as [`MapWithDict::debug_check_invariants`](crate::MapWithDict::debug_check_invariants),
+    /// values dictionary offsets being checked to point before the codec padding.
+    pub fn debug_check_invariants(&self) -> Result<(), InvariantError> {
+        invariants::check_keys(&self.mphf, &self.keys)?;
+        let max_offset = (self.values_dict.len() + 1).saturating_sub(C::PADDING);
+        invariants::check_values_index(&self.values_index, self.keys.len(), max_offset)
+    }
+}
+
+/// Creates a `MapWithDictCodec` from a `HashMap`.
+impl<K, V, C> TryFrom<HashMap<K, V>> for MapWithDictCodec<K, V, C>
+where
+    K: PartialEq + Hash,
+    C: ValueCodec<V>,
+{
+    type Error = MphfError;
+
+    #[inline]
+    fn try_from(value: HashMap<K, V>) -> Result<Self, Self::Error> {
+        MapWithDictCodec::from_iter_with_params(value, DEFAULT_GAMMA)
+    }
+}
+
+/// Implement `get` for `Archived` version of `MapWithDictCodec` if feature is enabled
+#[cfg(feature = "rkyv_derive")]
+impl<K, V, C, const B: usize, const S: usize, ST, H> ArchivedMapWithDictCodec<K, V, C, B, S, ST, H>
+where
+    K: PartialEq + Hash + rkyv::Archive,
+    K::Archived: PartialEq<K>,
+    C: ValueCodec<V>,
+    ST: PrimInt + Unsigned + rkyv::Archive<Archived = ST>,
+    H: Hasher + Default,
+{
+    /// Returns the value decoded by `C` corresponding to the key, or `None` if the key is not
+    /// present in the map.
+    ///
+    /// # Examples
+    /// ```
+    /// # use std::collections::HashMap;
+    /// # use entropy_map::{BytesCodec, MapWithDictCodec};
+    /// let map: MapWithDictCodec<u32, String, BytesCodec> =
+    ///     MapWithDictCodec::try_from(HashMap::from([(1, "a".to_string()), (3, "b".to_string())])).unwrap();
+    /// let bytes = rkyv::to_bytes::<_, 1024>(&map).unwrap();
+    /// let archived_map = rkyv::check_archived_root::<MapWithDictCodec<u32, String, BytesCodec>>(&bytes).unwrap();
+    /// assert_eq!(archived_map.get(&1), Some("a"));
+    /// assert_eq!(archived_map.get(&2), None);
+    /// ```
+    #[inline]
+    pub fn get(&self, key: &K) -> Option<C::Decoded<'_>> {
+        self.get_impl(self.mphf.get(key), key)
+    }
+
+    /// Same as [`ArchivedMapWithDictCodec::get`], but re-uses the hash precomputed by `HashedKey`.
+    #[inline]
+    pub fn get_hashed(&self, key: &HashedKey<K, H>) -> Option<C::Decoded<'_>> {
+        self.get_impl(self.mphf.get_hashed(key), key.key())
+    }
+
+    /// Checks if the map contains the specified key.
+    #[inline]
+    pub fn contains_key(&self, key: &K) -> bool {
+        if let Some(idx) = self.mphf.get(key) {
+            // SAFETY: `idx` is always within bounds (ensured during construction)
+            unsafe { access::get(&self.keys, idx) == key }
+        } else {
+            false
+        }
+    }
+
+    /// Inner implementation of `get` decoding the value at MPHF index `idx` if the key stored there
+    /// is equal to `key`.
+    #[inline]
+    fn get_impl(&self, idx: Option<usize>, key: &K) -> Option<C::Decoded<'_>> {
+        let idx = idx?;
+
+        // SAFETY: `idx` is always within bounds (ensured during construction)
+        unsafe {
+            if access::get(&self.keys, idx) != key {
+                return None;
+            }
+
+            // SAFETY: `idx` and `value_idx` are always within bounds (ensured during construction)
+            let value_idx = *access::get(&self.values_index, idx) as usize;
+            Some(C::decode(access::get(&self.values_dict, value_idx..)))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::value_codec::{BitpackedCodec, BytesCodec, VarintCodec};
+    use crate::MapWithDictBitpacked;
+    use rand::{Rng, SeedableRng};
+    use rand_chacha::ChaCha8Rng;
+    use std::collections::HashSet;
+
+    fn gen_map<V>(items_num: usize, mut gen_value: impl FnMut(&mut ChaCha8Rng) -> V) -> HashMap<u64, V> {
+        let mut rng = ChaCha8Rng::seed_from_u64(123);
+        (0..items_num).map(|_| (rng.gen(), gen_value(&mut rng))).collect()
+    }
+
+    #[test]
+    fn test_varint_codec() {
+        let original_map = gen_map(1000, |rng| rng.gen_range(0..1000u64));
+        let map: MapWithDictCodec<u64, u64, VarintCodec> = MapWithDictCodec::try_from(original_map.clone()).unwrap();
+
+        assert_eq!(map.debug_check_invariants(), Ok(()));
+        assert_eq!(map.len(), original_map.len());
+        assert_eq!(map.is_empty(), original_map.is_empty());
+        for (key, &value) in &original_map {
+            assert_eq!(map.get(key), Some(value));
+            assert_eq!(map.get_hashed(&HashedKey::new(key)), Some(value));
+            assert!(map.contains_key(key));
+        }
+        assert_eq!(map.get(&0), None);
+        assert!(!map.contains_key(&0));
+
+        for (&k, v) in map.iter() {
+            assert_eq!(original_map.get(&k), Some(&v));
+        }
+        // values below 1000 take at most 2 bytes
+        assert!(map.values_dict.len() <= 2 * 1000);
+    }
+
+    #[test]
+    fn test_bytes_codec() {
+        let original_map = gen_map(1000, |rng| "v".repeat(rng.gen_range(0..10)));
+        let map: MapWithDictCodec<u64, String, BytesCodec> = MapWithDictCodec::try_from(original_map.clone()).unwrap();
+
+        for (key, value) in &original_map {
+            assert_eq!(map.get(key), Some(value.as_str()));
+        }
+        // values are deduplicated by their encoding
+        assert_eq!(map.values_dict.len(), (0..10).map(|len| 1 + len).sum::<usize>());
+    }
+
+    #[test]
+    fn test_bitpacked_codec() {
+        let values_num = 10;
+        let original_map = gen_map(1000, |rng| (0..values_num).map(|_| rng.gen_range(0..100)).collect());
+        let map: MapWithDictCodec<u64, Vec<u32>, BitpackedCodec> =
+            MapWithDictCodec::try_from(original_map.clone()).unwrap();
+        let bitpacked_map = MapWithDictBitpacked::try_from(original_map.clone()).unwrap();
+
+        let mut values_buf = vec![0; values_num];
+        for (key, value) in &original_map {
+            assert_eq!(map.get(key).as_ref(), Some(value));
+            assert!(bitpacked_map.get_values(key, &mut values_buf));
+            assert_eq!(map.get(key), Some(values_buf.clone()));
+        }
+        // same bit-packed blocks as `MapWithDictBitpacked`, plus a 1-byte length per unique value
+        let unique_values = original_map.values().collect::<HashSet<_>>().len();
+        assert_eq!(
+            map.size() - size_of_val(&map),
+            bitpacked_map.size() - size_of_val(&bitpacked_map) + unique_values
+        );
+    }
+
+    #[cfg(feature = "rkyv_derive")]
+    #[test]
+    fn test_rkyv() {
+        let original_map = gen_map(1000, |rng| "v".repeat(rng.gen_range(0..10)));
+        let map: MapWithDictCodec<u64, String, BytesCodec> = MapWithDictCodec::try_from(original_map.clone()).unwrap();
+        let rkyv_bytes = rkyv::to_bytes::<_, 1024>(&map).unwrap();
+        let rkyv_map = rkyv::check_archived_root::<MapWithDictCodec<u64, String, BytesCodec>>(&rkyv_bytes).unwrap();
+
+        for (key, value) in &original_map {
+            assert_eq!(rkyv_map.get(key), Some(value.as_str()));
+            assert!(rkyv_map.contains_key(key));
+        }
+        assert_eq!(rkyv_map.get(&0), None);
+    }
+}
@@ -0,0 +1,280 @@
+//! A module providing `MapWithDictDefault`, a `MapWithDict` with a value returned for missing keys.
+//!
+//! Lookup sites falling back to the same value for every missing key pay for matching on an
+//! `Option` in the hot path. `MapWithDictDefault` stores the default value in the values
+//! dictionary instead (re-using an equal stored value if any), so that `get_or_default` merely
+//! picks between two dictionary indices and always returns a reference.
+
+use std::borrow::Borrow;
+use std::hash::{Hash, Hasher};
+use std::mem::size_of_val;
+
+use num::{PrimInt, Unsigned};
+use wyhash::WyHash;
+
+use crate::access;
+use crate::hashed_key::HashedKey;
+use crate::invariants::InvariantError;
+use crate::map_with_dict::MapWithDict;
+
+/// An immutable hash map with values dictionary-packed like `MapWithDict` and a default value
+/// returned for missing keys, built with [`MapWithDict::with_default`].
+///
+/// # Examples
+/// ```
+/// # use std::collections::HashMap;
+/// # use entropy_map::MapWithDict;
+/// let map = MapWithDict::try_from(HashMap::from([("a", 1), ("b", 2)])).unwrap().with_default(0);
+/// assert_eq!(map.get_or_default(&"a"), &1);
+/// assert_eq!(map.get_or_default(&"c"), &0);
+/// assert_eq!(map.get(&"c"), None);
+/// ```
+#[derive(Default)]
+#[cfg_attr(feature = "rkyv_derive", derive(rkyv::Archive, rkyv::Deserialize, rkyv::Serialize))]
+#[cfg_attr(feature = "rkyv_derive", archive_attr(derive(rkyv::CheckBytes)))]
+pub struct MapWithDictDefault<K, V, const B: usize = 32, const S: usize = 8, ST = u8, H = WyHash>
+where
+    ST: PrimInt + Unsigned,
+    H: Hasher + Default,
+{
+    /// Map of keys to values, whose values dictionary holds the default value
+    map: MapWithDict<K, V, B, S, ST, H>,
+    /// Index of the default value in the values dictionary
+    default_value_idx: usize,
+}
+
+impl<K, V, const B: usize, const S: usize, ST, H> MapWithDict<K, V, B, S, ST, H>
+where
+    K: Eq + Hash + Clone,
+    V: Eq + Clone + Hash,
+    ST: PrimInt + Unsigned,
+    H: Hasher + Default,
+{
+    /// Turns the map into a `MapWithDictDefault` returning `default` for missing keys from
+    /// [`get_or_default`](MapWithDictDefault::get_or_default). The default value is appended to the
+    /// values dictionary unless an equal value is stored already.
+    pub fn with_default(self, default: V) -> MapWithDictDefault<K, V, B, S, ST, H> {
+        let MapWithDict { mphf, keys, values_index, values_dict } = self;
+        let mut values_dict = values_dict.into_vec();
+        let default_value_idx = match values_dict.iter().position(|value| *value == default) {
+            Some(idx) => idx,
+            None => {
+                values_dict.push(default);
+                values_dict.len() - 1
+            }
+        };
+
+        let map = MapWithDict { mphf, keys, values_index, values_dict: values_dict.into_boxed_slice() };
+        MapWithDictDefault { map, default_value_idx }
+    }
+}
+
+impl<K, V, const B: usize, const S: usize, ST, H> MapWithDictDefault<K, V, B, S, ST, H>
+where
+    K: Eq + Hash + Clone,
+    V: Eq + Clone + Hash,
+    ST: PrimInt + Unsigned,
+    H: Hasher + Default,
+{
+    /// Returns a reference to the value corresponding to the key, or to the default value if the
+    /// key is not present in the map.
+    #[inline]
+    pub fn get_or_default<Q>(&self, key: &Q) -> &V
+    where
+        K: Borrow<Q> + PartialEq<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        self.get_or_default_impl(self.map.mphf.get(key), key)
+    }
+
+    /// Same as [`MapWithDictDefault::get_or_default`], but re-uses the hash precomputed by
+    /// `HashedKey`.
+    #[inline]
+    pub fn get_or_default_hashed<Q>(&self, key: &HashedKey<Q, H>) -> &V
+    where
+        K: Borrow<Q> + PartialEq<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        self.get_or_default_impl(self.map.mphf.get_hashed(key), key.key())
+    }
+
+    /// Inner implementation of `get_or_default` returning the value at MPHF index `idx` if the key
+    /// stored there is equal to `key`, and the default value otherwise.
+    #[inline]
+    fn get_or_default_impl<Q>(&self, idx: Option<usize>, key: &Q) -> &V
+    where
+        K: PartialEq<Q>,
+        Q: ?Sized,
+    {
+        // SAFETY: `idx`, `value_idx` and `default_value_idx` are always within bounds (ensured
+        // during construction)
+        unsafe {
+            let value_idx = match idx {
+                Some(idx) if access::get(&self.map.keys, idx) == key => *access::get(&self.map.values_index, idx),
+                _ => self.default_value_idx,
+            };
+            access::get(&self.map.values_dict, value_idx)
+        }
+    }
+
+    /// Returns a reference to the value corresponding to the key. Returns `None` if the key is
+    /// not present in the map.
+    #[inline]
+    pub fn get<Q>(&self, key: &Q) -> Option<&V>
+    where
+        K: Borrow<Q> + PartialEq<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        self.map.get(key)
+    }
+
+    /// Returns a reference to the default value.
+    #[inline]
+    pub fn default_value(&self) -> &V {
+        // SAFETY: `default_value_idx` is always within bounds (ensured during construction)
+        unsafe { access::get(&self.map.values_dict, self.default_value_idx) }
+    }
+
+    /// Returns the number of elements in the map.
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.map.len()
+    }
+
+    /// Returns `true` if the map contains no elements.
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.map.is_empty()
+    }
+
+    /// Returns the map of keys to values, whose values dictionary holds the default value.
+    #[inline]
+    pub fn as_map(&self) -> &MapWithDict<K, V, B, S, ST, H> {
+        &self.map
+    }
+
+    /// Returns the total number of bytes occupied by the map.
+    ///
+    /// # Examples
+    /// ```
+    /// # use std::collections::HashMap;
+    /// # use entropy_map::MapWithDict;
+    /// let map = MapWithDict::try_from(HashMap::from([(1u64, 2u64), (3, 4)])).unwrap().with_default(0);
+    /// assert_eq!(map.size(), map.as_map().size() + 8);
+    /// ```
+    #[inline]
+    pub fn size(&self) -> usize {
+        self.map.size() + size_of_val(&self.default_value_idx)
+    }
+
+    /// Checks invariants of the map and that the default value is within the values dictionary.
+    pub fn debug_check_invariants(&self) -> Result<(), InvariantError> {
+        self.map.debug_check_invariants()?;
+        if self.default_value_idx >= self.map.values_dict.len() {
+            return Err(InvariantError::LengthMismatch);
+        }
+        Ok(())
+    }
+}
+
+/// Implement `get_or_default` for `Archived` version of `MapWithDictDefault` if feature is enabled
+#[cfg(feature = "rkyv_derive")]
+impl<K, V, const B: usize, const S: usize, ST, H> ArchivedMapWithDictDefault<K, V, B, S, ST, H>
+where
+    K: PartialEq + Hash + rkyv::Archive,
+    K::Archived: PartialEq<K>,
+    V: rkyv::Archive,
+    ST: PrimInt + Unsigned + rkyv::Archive<Archived = ST>,
+    H: Hasher + Default,
+{
+    /// Returns a reference to the archived value corresponding to the key, or to the archived
+    /// default value if the key is not present in the map.
+    #[inline]
+    pub fn get_or_default<Q>(&self, key: &Q) -> &V::Archived
+    where
+        K: Borrow<Q>,
+        <K as rkyv::Archive>::Archived: PartialEq<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        // SAFETY: `idx`, `value_idx` and `default_value_idx` are always within bounds (ensured
+        // during construction)
+        unsafe {
+            let value_idx = match self.map.mphf.get(key) {
+                Some(idx) if access::get(&self.map.keys, idx) == key => *access::get(&self.map.values_index, idx),
+                _ => self.default_value_idx,
+            };
+            access::get(&self.map.values_dict, value_idx as usize)
+        }
+    }
+
+    /// Returns a reference to the archived value corresponding to the key. Returns `None` if the
+    /// key is not present in the map.
+    #[inline]
+    pub fn get<Q>(&self, key: &Q) -> Option<&V::Archived>
+    where
+        K: Borrow<Q>,
+        <K as rkyv::Archive>::Archived: PartialEq<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        self.map.get(key)
+    }
+
+    /// Returns a reference to the archived default value.
+    #[inline]
+    pub fn default_value(&self) -> &V::Archived {
+        // SAFETY: `default_value_idx` is always within bounds (ensured during construction)
+        unsafe { access::get(&self.map.values_dict, self.default_value_idx as usize) }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mphf::DEFAULT_GAMMA;
+
+    #[test]
+    fn test_with_default() {
+        let entries: Vec<(u64, u32)> = (0..1000).map(|i| (i, (i % 10) as u32)).collect();
+        let map = MapWithDict::<u64, u32>::from_iter_with_params(entries.clone(), DEFAULT_GAMMA).unwrap();
+
+        // equal stored value is re-used
+        let map = map.with_default(5);
+        assert_eq!(map.as_map().values_dict().len(), 10);
+        assert_eq!(map.debug_check_invariants(), Ok(()));
+
+        let map = MapWithDict::<u64, u32>::from_iter_with_params(entries.clone(), DEFAULT_GAMMA)
+            .unwrap()
+            .with_default(u32::MAX);
+        assert_eq!(map.as_map().values_dict().len(), 11);
+        assert_eq!(map.debug_check_invariants(), Ok(()));
+        assert_eq!(map.default_value(), &u32::MAX);
+
+        for (key, value) in &entries {
+            assert_eq!(map.get_or_default(key), value);
+            assert_eq!(map.get_or_default_hashed(&HashedKey::new(key)), value);
+        }
+        for key in 1000..2000 {
+            assert_eq!(map.get_or_default(&key), &u32::MAX);
+            assert_eq!(map.get(&key), None);
+        }
+
+        #[cfg(feature = "rkyv_derive")]
+        {
+            let rkyv_bytes = rkyv::to_bytes::<_, 1024>(&map).unwrap();
+            let rkyv_map = rkyv::check_archived_root::<MapWithDictDefault<u64, u32>>(&rkyv_bytes).unwrap();
+            assert_eq!(rkyv_map.default_value(), &u32::MAX);
+            for key in 0..2000 {
+                assert_eq!(rkyv_map.get_or_default(&key), map.get_or_default(&key));
+            }
+        }
+    }
+
+    #[test]
+    fn test_empty_map() {
+        let map = MapWithDict::<u64, u32>::from_iter_with_params([], DEFAULT_GAMMA)
+            .unwrap()
+            .with_default(7);
+        assert_eq!(map.get_or_default(&1), &7);
+        assert_eq!(map.debug_check_invariants(), Ok(()));
+    }
+}
@@ -0,0 +1,338 @@
+//! A module providing `MapWithDictFlags`, a `MapWithDict` with a few flag bits per entry.
+//!
+//! Per-entry booleans such as "deprecated" or "experimental" would otherwise have to be folded
+//! into the values, multiplying the number of unique values and defeating the values dictionary.
+//! `MapWithDictFlags` stores `F` flag bits (1 to 8) per entry bit-packed next to the values index
+//! instead, i.e. `F` bits per key on top of `MapWithDict`, accessed by the same MPHF index.
+
+use std::borrow::Borrow;
+use std::hash::{Hash, Hasher};
+use std::mem::size_of_val;
+
+use num::{PrimInt, Unsigned};
+use wyhash::WyHash;
+
+use crate::access;
+use crate::hashed_key::HashedKey;
+use crate::invariants::InvariantError;
+use crate::map_with_dict::MapWithDict;
+use crate::mphf::{BuildPolicy, MphfError};
+use crate::report::BuildReport;
+
+/// An immutable hash map with values dictionary-packed like `MapWithDict` and `F` flag bits per
+/// entry.
+///
+/// # Examples
+/// ```
+/// # use entropy_map::{MapWithDictFlags, DEFAULT_GAMMA};
+/// const DEPRECATED: u8 = 0b01;
+/// const EXPERIMENTAL: u8 = 0b10;
+///
+/// let entries = [("a", 1, 0), ("b", 2, DEPRECATED), ("c", 1, DEPRECATED | EXPERIMENTAL)];
+/// let map: MapWithDictFlags<&str, u32, 2> = MapWithDictFlags::from_iter_with_params(entries, DEFAULT_GAMMA).unwrap();
+/// assert_eq!(map.get(&"b"), Some(&2));
+/// assert_eq!(map.flags(&"b"), Some(DEPRECATED));
+/// assert_eq!(map.get_with_flags(&"c"), Some((&1, DEPRECATED | EXPERIMENTAL)));
+/// assert_eq!(map.flags(&"d"), None);
+/// ```
+#[derive(Default)]
+#[cfg_attr(feature = "rkyv_derive", derive(rkyv::Archive, rkyv::Deserialize, rkyv::Serialize))]
+#[cfg_attr(feature = "rkyv_derive", archive_attr(derive(rkyv::CheckBytes)))]
+pub struct MapWithDictFlags<K, V, const F: usize, const B: usize = 32, const S: usize = 8, ST = u8, H = WyHash>
+where
+    ST: PrimInt + Unsigned,
+    H: Hasher + Default,
+{
+    /// Map of keys to values
+    map: MapWithDict<K, V, B, S, ST, H>,
+    /// `F` flag bits per entry, bit-packed in the order of MPHF indices
+    flags: Box<[u64]>,
+}
+
+/// Returns the `f` flag bits of entry `idx` bit-packed in `words`.
+///
+/// # Safety
+/// `words` must hold at least `(idx + 1) * f` bits.
+#[inline]
+unsafe fn read_flags(words: &[u64], idx: usize, f: usize) -> u8 {
+    let bit_idx = idx * f;
+    let (word_idx, shift) = (bit_idx / 64, bit_idx % 64);
+    let mut bits = *access::get(words, word_idx) >> shift;
+    if shift + f > 64 {
+        bits |= *access::get(words, word_idx + 1) << (64 - shift);
+    }
+    (bits & ((1 << f) - 1)) as u8
+}
+
+impl<K, V, const F: usize, const B: usize, const S: usize, ST, H> MapWithDictFlags<K, V, F, B, S, ST, H>
+where
+    K: Eq + Hash + Clone,
+    V: Eq + Clone + Hash,
+    ST: PrimInt + Unsigned,
+    H: Hasher + Default,
+{
+    /// Constructs a `MapWithDictFlags` from an iterator of keys, values and flags and MPHF function
+    /// params. Only the lowest `F` bits of flags are stored.
+    pub fn from_iter_with_params<I>(iter: I, gamma: f32) -> Result<Self, MphfError>
+    where
+        I: IntoIterator<Item = (K, V, u8)>,
+    {
+        Self::from_iter_with_policy(iter, &BuildPolicy::fixed(gamma)).map(|(map, _)| map)
+    }
+
+    /// Constructs a `MapWithDictFlags` from an iterator of keys, values and flags, escalating MPHF
+    /// `gamma` on failed builds according to `policy`, and returns it along with a `BuildReport`.
+    pub fn from_iter_with_policy<I>(iter: I, policy: &BuildPolicy) -> Result<(Self, BuildReport), MphfError>
+    where
+        I: IntoIterator<Item = (K, V, u8)>,
+    {
+        const { assert!(F >= 1 && F <= 8, "number of flag bits must be within 1..=8") };
+
+        let mut key_flags = vec![];
+        let entries = iter.into_iter().map(|(key, value, flags)| {
+            key_flags.push((key.clone(), flags));
+            (key, value)
+        });
+        let (map, mut report) = MapWithDict::from_iter_with_policy(entries, policy)?;
+
+        let mut flags = vec![0u64; (map.len() * F).div_ceil(64)];
+        let mask = (1u64 << F) - 1;
+        for (key, key_flags) in key_flags {
+            let bit_idx = map.mphf.get(&key).unwrap() * F;
+            let bits = key_flags as u64 & mask;
+            flags[bit_idx / 64] |= bits << (bit_idx % 64);
+            if bit_idx % 64 + F > 64 {
+                flags[bit_idx / 64 + 1] |= bits >> (64 - bit_idx % 64);
+            }
+        }
+
+        let map = MapWithDictFlags { map, flags: flags.into_boxed_slice() };
+        report.set_size(map.size());
+
+        Ok((map, report))
+    }
+
+    /// Returns a reference to the value corresponding to the key. Returns `None` if the key is
+    /// not present in the map.
+    #[inline]
+    pub fn get<Q>(&self, key: &Q) -> Option<&V>
+    where
+        K: Borrow<Q> + PartialEq<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        self.map.get(key)
+    }
+
+    /// Returns the flags of the key. Returns `None` if the key is not present in the map.
+    #[inline]
+    pub fn flags<Q>(&self, key: &Q) -> Option<u8>
+    where
+        K: Borrow<Q> + PartialEq<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        self.get_with_flags(key).map(|(_, flags)| flags)
+    }
+
+    /// Returns a reference to the value and the flags of the key. Returns `None` if the key is not
+    /// present in the map.
+    #[inline]
+    pub fn get_with_flags<Q>(&self, key: &Q) -> Option<(&V, u8)>
+    where
+        K: Borrow<Q> + PartialEq<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        self.get_impl(self.map.mphf.get(key), key)
+    }
+
+    /// Same as [`MapWithDictFlags::get_with_flags`], but re-uses the hash precomputed by
+    /// `HashedKey`.
+    #[inline]
+    pub fn get_with_flags_hashed<Q>(&self, key: &HashedKey<Q, H>) -> Option<(&V, u8)>
+    where
+        K: Borrow<Q> + PartialEq<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        self.get_impl(self.map.mphf.get_hashed(key), key.key())
+    }
+
+    /// Inner implementation of `get_with_flags` returning the value and flags at MPHF index `idx`
+    /// if the key stored there is equal to `key`.
+    #[inline]
+    fn get_impl<Q>(&self, idx: Option<usize>, key: &Q) -> Option<(&V, u8)>
+    where
+        K: PartialEq<Q>,
+        Q: ?Sized,
+    {
+        let idx = idx?;
+
+        // SAFETY: `idx` is always within bounds (ensured during construction)
+        unsafe {
+            if access::get(&self.map.keys, idx) == key {
+                // SAFETY: `idx` and `value_idx` are always within bounds (ensure during construction)
+                let value_idx = *access::get(&self.map.values_index, idx);
+                Some((
+                    access::get(&self.map.values_dict, value_idx),
+                    read_flags(&self.flags, idx, F),
+                ))
+            } else {
+                None
+            }
+        }
+    }
+
+    /// Returns the number of elements in the map.
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.map.len()
+    }
+
+    /// Returns `true` if the map contains no elements.
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.map.is_empty()
+    }
+
+    /// Returns an iterator over the map, yielding keys, values and flags.
+    pub fn iter(&self) -> impl ExactSizeIterator<Item = (&K, &V, u8)> + '_ {
+        self.map
+            .iter()
+            .enumerate()
+            // SAFETY: `idx` is always within bounds (ensured during construction)
+            .map(|(idx, (key, value))| (key, value, unsafe { read_flags(&self.flags, idx, F) }))
+    }
+
+    /// Returns the map of keys to values without flags.
+    #[inline]
+    pub fn as_map(&self) -> &MapWithDict<K, V, B, S, ST, H> {
+        &self.map
+    }
+
+    /// Returns the total number of bytes occupied by the map.
+    ///
+    /// # Examples
+    /// ```
+    /// # use entropy_map::{MapWithDict, MapWithDictFlags, DEFAULT_GAMMA};
+    /// let entries = (0..1000u64).map(|i| (i, i % 10, (i % 3) as u8));
+    /// let map: MapWithDictFlags<u64, u64, 2> = MapWithDictFlags::from_iter_with_params(entries, DEFAULT_GAMMA).unwrap();
+    /// // 2000 flag bits rounded up to 32 words
+    /// assert_eq!(map.size(), map.as_map().size() + 16 + 32 * 8);
+    /// ```
+    #[inline]
+    pub fn size(&self) -> usize {
+        self.map.size() + size_of_val(&self.flags) + size_of_val(self.flags.as_ref())
+    }
+
+    /// Checks invariants of the map and that there are `F` flag bits per entry.
+    pub fn debug_check_invariants(&self) -> Result<(), InvariantError> {
+        self.map.debug_check_invariants()?;
+        if self.flags.len() != (self.map.len() * F).div_ceil(64) {
+            return Err(InvariantError::LengthMismatch);
+        }
+        Ok(())
+    }
+}
+
+/// Implement `get_with_flags` for `Archived` version of `MapWithDictFlags` if feature is enabled
+#[cfg(feature = "rkyv_derive")]
+impl<K, V, const F: usize, const B: usize, const S: usize, ST, H> ArchivedMapWithDictFlags<K, V, F, B, S, ST, H>
+where
+    K: PartialEq + Hash + rkyv::Archive,
+    K::Archived: PartialEq<K>,
+    V: rkyv::Archive,
+    ST: PrimInt + Unsigned + rkyv::Archive<Archived = ST>,
+    H: Hasher + Default,
+{
+    /// Returns a reference to the archived value corresponding to the key. Returns `None` if the
+    /// key is not present in the map.
+    #[inline]
+    pub fn get<Q>(&self, key: &Q) -> Option<&V::Archived>
+    where
+        K: Borrow<Q>,
+        <K as rkyv::Archive>::Archived: PartialEq<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        self.map.get(key)
+    }
+
+    /// Returns the flags of the key. Returns `None` if the key is not present in the map.
+    #[inline]
+    pub fn flags<Q>(&self, key: &Q) -> Option<u8>
+    where
+        K: Borrow<Q>,
+        <K as rkyv::Archive>::Archived: PartialEq<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        self.get_with_flags(key).map(|(_, flags)| flags)
+    }
+
+    /// Returns a reference to the archived value and the flags of the key. Returns `None` if the
+    /// key is not present in the map.
+    #[inline]
+    pub fn get_with_flags<Q>(&self, key: &Q) -> Option<(&V::Archived, u8)>
+    where
+        K: Borrow<Q>,
+        <K as rkyv::Archive>::Archived: PartialEq<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        let idx = self.map.mphf.get(key)?;
+
+        // SAFETY: `idx` is always within bounds (ensured during construction)
+        unsafe {
+            if access::get(&self.map.keys, idx) == key {
+                // SAFETY: `idx` and `value_idx` are always within bounds (ensure during construction)
+                let value_idx = *access::get(&self.map.values_index, idx) as usize;
+                Some((
+                    access::get(&self.map.values_dict, value_idx),
+                    read_flags(&self.flags, idx, F),
+                ))
+            } else {
+                None
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mphf::DEFAULT_GAMMA;
+
+    fn test_flags<const F: usize>() {
+        let entries: Vec<(u64, u32, u8)> = (0..1000u64).map(|i| (i, (i % 7) as u32, (i * 37) as u8)).collect();
+        let map = MapWithDictFlags::<u64, u32, F>::from_iter_with_params(entries.clone(), DEFAULT_GAMMA).unwrap();
+        let mask = ((1u16 << F) - 1) as u8;
+
+        assert_eq!(map.debug_check_invariants(), Ok(()));
+        assert_eq!(map.len(), entries.len());
+        for &(key, value, flags) in &entries {
+            assert_eq!(map.get_with_flags(&key), Some((&value, flags & mask)));
+            assert_eq!(
+                map.get_with_flags_hashed(&HashedKey::new(&key)),
+                Some((&value, flags & mask))
+            );
+        }
+        assert_eq!(map.flags(&1000), None);
+        for (&key, &value, flags) in map.iter() {
+            let (_, expected_value, expected_flags) = entries[key as usize];
+            assert_eq!((value, flags), (expected_value, expected_flags & mask));
+        }
+
+        #[cfg(feature = "rkyv_derive")]
+        {
+            let rkyv_bytes = rkyv::to_bytes::<_, 1024>(&map).unwrap();
+            let rkyv_map = rkyv::check_archived_root::<MapWithDictFlags<u64, u32, F>>(&rkyv_bytes).unwrap();
+            for &(key, value, flags) in &entries {
+                assert_eq!(rkyv_map.get_with_flags(&key), Some((&value, flags & mask)));
+            }
+            assert_eq!(rkyv_map.flags(&1000), None);
+        }
+    }
+
+    #[test]
+    fn test_map_with_dict_flags() {
+        test_flags::<1>();
+        test_flags::<3>();
+        test_flags::<5>();
+        test_flags::<8>();
+    }
+}
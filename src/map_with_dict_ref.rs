@@ -0,0 +1,252 @@
+//! A module providing `MapWithDictRef`, a `MapWithDict` referencing caller-owned keys.
+//!
+//! `MapWithDict` owns its keys, so building it from keys which are kept around anyway (e.g. a
+//! multi-GB set of strings loaded by the caller) temporarily doubles memory usage. `MapWithDictRef`
+//! stores references to the keys instead, i.e. a pointer per key, and borrows them for its whole
+//! lifetime. It can be turned into an owned `MapWithDict` without rebuilding the MPHF via
+//! [`MapWithDictRef::into_owned`].
+
+use std::borrow::Borrow;
+use std::hash::{Hash, Hasher};
+
+use num::{PrimInt, Unsigned};
+use wyhash::WyHash;
+
+use crate::access;
+use crate::hashed_key::HashedKey;
+use crate::invariants::InvariantError;
+use crate::map_with_dict::MapWithDict;
+use crate::mphf::{BuildPolicy, MphfError};
+use crate::report::BuildReport;
+
+/// An immutable hash map with values dictionary-packed like `MapWithDict`, referencing keys owned
+/// by the caller instead of storing them.
+///
+/// # Examples
+/// ```
+/// # use entropy_map::{MapWithDictRef, DEFAULT_GAMMA};
+/// let keys = vec!["a".to_string(), "b".to_string(), "c".to_string()];
+/// let map: MapWithDictRef<String, u32> =
+///     MapWithDictRef::from_iter_with_params(keys.iter().zip([1, 2, 1]), DEFAULT_GAMMA).unwrap();
+/// assert_eq!(map.get("b"), Some(&2));
+/// assert_eq!(map.get("d"), None);
+/// ```
+pub struct MapWithDictRef<'a, K, V, const B: usize = 32, const S: usize = 8, ST = u8, H = WyHash>
+where
+    ST: PrimInt + Unsigned,
+    H: Hasher + Default,
+{
+    /// Map storing references to the keys
+    inner: MapWithDict<&'a K, V, B, S, ST, H>,
+}
+
+impl<'a, K, V, const B: usize, const S: usize, ST, H> MapWithDictRef<'a, K, V, B, S, ST, H>
+where
+    K: Eq + Hash,
+    V: Eq + Clone + Hash,
+    ST: PrimInt + Unsigned,
+    H: Hasher + Default,
+{
+    /// Constructs a `MapWithDictRef` from an iterator of borrowed keys and values and MPHF
+    /// function params.
+    pub fn from_iter_with_params<I>(iter: I, gamma: f32) -> Result<Self, MphfError>
+    where
+        I: IntoIterator<Item = (&'a K, V)>,
+    {
+        Self::from_iter_with_policy(iter, &BuildPolicy::fixed(gamma)).map(|(map, _)| map)
+    }
+
+    /// Constructs a `MapWithDictRef` from an iterator of borrowed keys and values, escalating MPHF
+    /// `gamma` on failed builds according to `policy`, and returns it along with a `BuildReport`.
+    ///
+    /// # Examples
+    /// ```
+    /// # use entropy_map::{BuildPolicy, MapWithDictRef};
+    /// let keys: Vec<u64> = (0..1000).collect();
+    /// let (map, report) =
+    ///     MapWithDictRef::<u64, u8>::from_iter_with_policy(keys.iter().map(|k| (k, *k as u8)), &BuildPolicy::default())
+    ///         .unwrap();
+    /// assert_eq!(map.get(&300), Some(&44));
+    /// assert_eq!(report.keys(), 1000);
+    /// ```
+    pub fn from_iter_with_policy<I>(iter: I, policy: &BuildPolicy) -> Result<(Self, BuildReport), MphfError>
+    where
+        I: IntoIterator<Item = (&'a K, V)>,
+    {
+        let (inner, report) = MapWithDict::from_iter_with_policy(iter, policy)?;
+        Ok((MapWithDictRef { inner }, report))
+    }
+
+    /// Returns a reference to the value corresponding to the key. Returns `None` if the key is
+    /// not present in the map.
+    #[inline]
+    pub fn get<Q>(&self, key: &Q) -> Option<&V>
+    where
+        K: Borrow<Q> + PartialEq<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        self.get_impl(self.inner.mphf.get(key), key)
+    }
+
+    /// Same as [`MapWithDictRef::get`], but re-uses the hash precomputed by `HashedKey`.
+    #[inline]
+    pub fn get_hashed<Q>(&self, key: &HashedKey<Q, H>) -> Option<&V>
+    where
+        K: Borrow<Q> + PartialEq<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        self.get_impl(self.inner.mphf.get_hashed(key), key.key())
+    }
+
+    /// Inner implementation of `get` returning the value at MPHF index `idx` if the key referenced
+    /// there is equal to `key`.
+    #[inline]
+    fn get_impl<Q>(&self, idx: Option<usize>, key: &Q) -> Option<&V>
+    where
+        K: PartialEq<Q>,
+        Q: ?Sized,
+    {
+        let idx = idx?;
+
+        // SAFETY: `idx` is always within bounds (ensured during construction)
+        unsafe {
+            if *access::get(&self.inner.keys, idx) == key {
+                // SAFETY: `idx` and `value_idx` are always within bounds (ensure during construction)
+                let value_idx = *access::get(&self.inner.values_index, idx);
+                Some(access::get(&self.inner.values_dict, value_idx))
+            } else {
+                None
+            }
+        }
+    }
+
+    /// Checks if the map contains the specified key.
+    #[inline]
+    pub fn contains_key<Q>(&self, key: &Q) -> bool
+    where
+        K: Borrow<Q> + PartialEq<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        self.get(key).is_some()
+    }
+
+    /// Returns the number of elements in the map.
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.inner.len()
+    }
+
+    /// Returns `true` if the map contains no elements.
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.inner.is_empty()
+    }
+
+    /// Returns an iterator over the map, yielding key-value pairs.
+    #[inline]
+    pub fn iter(&self) -> impl ExactSizeIterator<Item = (&'a K, &V)> + DoubleEndedIterator {
+        self.inner.iter().map(|(&key, value)| (key, value))
+    }
+
+    /// Returns an iterator over the keys of the map.
+    #[inline]
+    pub fn keys(&self) -> impl ExactSizeIterator<Item = &'a K> + DoubleEndedIterator + '_ {
+        self.inner.keys().copied()
+    }
+
+    /// Returns an iterator over the values of the map.
+    #[inline]
+    pub fn values(&self) -> impl ExactSizeIterator<Item = &V> + DoubleEndedIterator {
+        self.inner.values()
+    }
+
+    /// Returns the dictionary of unique values of the map, in no particular order.
+    #[inline]
+    pub fn values_dict(&self) -> &[V] {
+        self.inner.values_dict()
+    }
+
+    /// Returns the total number of bytes occupied by the structure, excluding the referenced keys.
+    ///
+    /// # Examples
+    /// ```
+    /// # use entropy_map::{MapWithDict, MapWithDictRef, DEFAULT_GAMMA};
+    /// let keys = vec![[0u8; 64], [1u8; 64]];
+    /// let map: MapWithDictRef<[u8; 64], u8> =
+    ///     MapWithDictRef::from_iter_with_params(keys.iter().zip([1, 2]), DEFAULT_GAMMA).unwrap();
+    /// let owned: MapWithDict<[u8; 64], u8> =
+    ///     MapWithDict::from_iter_with_params(keys.iter().copied().zip([1, 2]), DEFAULT_GAMMA).unwrap();
+    /// assert_eq!(owned.size() - map.size(), 2 * (64 - 8));
+    /// ```
+    #[inline]
+    pub fn size(&self) -> usize {
+        self.inner.size()
+    }
+
+    /// Checks invariants of the MPHF, that every referenced key is mapped by it to the index it's
+    /// stored at and that every values index points within the values dictionary.
+    pub fn debug_check_invariants(&self) -> Result<(), InvariantError> {
+        self.inner.debug_check_invariants()
+    }
+
+    /// Converts the map into an owned `MapWithDict` by cloning the referenced keys, re-using the
+    /// already built MPHF and values dictionary.
+    ///
+    /// # Examples
+    /// ```
+    /// # use entropy_map::{MapWithDict, MapWithDictRef, DEFAULT_GAMMA};
+    /// let keys = vec!["a".to_string(), "b".to_string()];
+    /// let map = MapWithDictRef::from_iter_with_params(keys.iter().zip([1, 2]), DEFAULT_GAMMA).unwrap();
+    /// let owned: MapWithDict<String, i32> = map.into_owned();
+    /// drop(keys);
+    /// assert_eq!(owned.get("a"), Some(&1));
+    /// ```
+    pub fn into_owned(self) -> MapWithDict<K, V, B, S, ST, H>
+    where
+        K: Clone,
+    {
+        let MapWithDict { mphf, keys, values_index, values_dict } = self.inner;
+        MapWithDict {
+            mphf,
+            keys: keys.iter().map(|&key| key.clone()).collect(),
+            values_index,
+            values_dict,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mphf::DEFAULT_GAMMA;
+    use proptest::prelude::*;
+    use std::collections::HashMap;
+
+    proptest! {
+        #[test]
+        fn proptest_map_with_dict_ref_model(model: HashMap<String, u8>, probes: Vec<String>) {
+            let map: MapWithDictRef<String, u8> =
+                MapWithDictRef::from_iter_with_params(model.iter().map(|(k, &v)| (k, v)), DEFAULT_GAMMA).unwrap();
+
+            prop_assert_eq!(map.len(), model.len());
+            prop_assert!(map.debug_check_invariants().is_ok());
+            for (key, value) in &model {
+                prop_assert_eq!(map.get(key.as_str()), Some(value));
+                prop_assert_eq!(map.get_hashed(&HashedKey::new(key)), Some(value));
+            }
+            for probe in &probes {
+                prop_assert_eq!(map.get(probe), model.get(probe));
+                prop_assert_eq!(map.contains_key(probe), model.contains_key(probe));
+            }
+            for (key, value) in map.iter() {
+                prop_assert_eq!(model.get(key), Some(value));
+            }
+
+            let owned = map.into_owned();
+            prop_assert!(owned.debug_check_invariants().is_ok());
+            for (key, value) in &model {
+                prop_assert_eq!(owned.get(key), Some(value));
+            }
+        }
+    }
+}
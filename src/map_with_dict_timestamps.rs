@@ -0,0 +1,398 @@
+//! A module providing `MapWithDictTimestamps`, a `MapWithDict` with a timestamp per entry.
+//!
+//! Freshness metadata such as last update or expiry times embedded in the values makes almost every
+//! value unique, defeating the values dictionary. `MapWithDictTimestamps` stores a `u32` timestamp
+//! per entry (e.g. epoch minutes) next to the values index instead, as offsets from the smallest
+//! timestamp bit-packed with just enough bits for the largest offset, so that timestamps within a
+//! month of each other take 16 bits per key whatever their unit.
+
+use std::borrow::Borrow;
+use std::hash::{Hash, Hasher};
+use std::mem::size_of_val;
+
+use num::{PrimInt, Unsigned};
+use wyhash::WyHash;
+
+use crate::access;
+use crate::hashed_key::HashedKey;
+use crate::invariants::InvariantError;
+use crate::map_with_dict::MapWithDict;
+use crate::mphf::{BuildPolicy, MphfError};
+use crate::report::BuildReport;
+
+/// An immutable hash map with values dictionary-packed like `MapWithDict` and a bit-packed `u32`
+/// timestamp per entry.
+///
+/// # Examples
+/// ```
+/// # use entropy_map::{MapWithDictTimestamps, DEFAULT_GAMMA};
+/// // expiry times in epoch minutes
+/// let entries = [("a", 1, 29_000_000), ("b", 2, 29_000_600), ("c", 1, 29_001_200)];
+/// let map: MapWithDictTimestamps<&str, u32> = MapWithDictTimestamps::from_iter_with_params(entries, DEFAULT_GAMMA).unwrap();
+/// assert_eq!(map.get_with_age(&"b"), Some((&2, 29_000_600)));
+/// assert_eq!(map.timestamp(&"d"), None);
+/// assert_eq!(map.expired_keys(29_001_000).count(), 2);
+/// ```
+#[derive(Default)]
+#[cfg_attr(feature = "rkyv_derive", derive(rkyv::Archive, rkyv::Deserialize, rkyv::Serialize))]
+#[cfg_attr(feature = "rkyv_derive", archive_attr(derive(rkyv::CheckBytes)))]
+pub struct MapWithDictTimestamps<K, V, const B: usize = 32, const S: usize = 8, ST = u8, H = WyHash>
+where
+    ST: PrimInt + Unsigned,
+    H: Hasher + Default,
+{
+    /// Map of keys to values
+    map: MapWithDict<K, V, B, S, ST, H>,
+    /// Smallest timestamp, subtracted from all stored timestamps
+    base: u32,
+    /// Number of bits per stored timestamp offset, from 0 to 32
+    width: u32,
+    /// Timestamp offsets of `width` bits per entry, bit-packed in the order of MPHF indices
+    offsets: Box<[u64]>,
+}
+
+/// Returns the `width`-bit value of entry `idx` bit-packed in `words`.
+///
+/// # Safety
+/// `words` must hold at least `(idx + 1) * width` bits.
+#[inline]
+unsafe fn read_bits(words: &[u64], idx: usize, width: u32) -> u32 {
+    if width == 0 {
+        return 0;
+    }
+    let bit_idx = idx * width as usize;
+    let (word_idx, shift) = (bit_idx / 64, bit_idx % 64);
+    let mut bits = *access::get(words, word_idx) >> shift;
+    if shift + width as usize > 64 {
+        bits |= *access::get(words, word_idx + 1) << (64 - shift);
+    }
+    (bits & ((1 << width) - 1)) as u32
+}
+
+impl<K, V, const B: usize, const S: usize, ST, H> MapWithDictTimestamps<K, V, B, S, ST, H>
+where
+    K: Eq + Hash + Clone,
+    V: Eq + Clone + Hash,
+    ST: PrimInt + Unsigned,
+    H: Hasher + Default,
+{
+    /// Constructs a `MapWithDictTimestamps` from an iterator of keys, values and timestamps and
+    /// MPHF function params.
+    pub fn from_iter_with_params<I>(iter: I, gamma: f32) -> Result<Self, MphfError>
+    where
+        I: IntoIterator<Item = (K, V, u32)>,
+    {
+        Self::from_iter_with_policy(iter, &BuildPolicy::fixed(gamma)).map(|(map, _)| map)
+    }
+
+    /// Constructs a `MapWithDictTimestamps` from an iterator of keys, values and timestamps,
+    /// escalating MPHF `gamma` on failed builds according to `policy`, and returns it along with a
+    /// `BuildReport`.
+    pub fn from_iter_with_policy<I>(iter: I, policy: &BuildPolicy) -> Result<(Self, BuildReport), MphfError>
+    where
+        I: IntoIterator<Item = (K, V, u32)>,
+    {
+        let mut key_timestamps = vec![];
+        let entries = iter.into_iter().map(|(key, value, timestamp)| {
+            key_timestamps.push((key.clone(), timestamp));
+            (key, value)
+        });
+        let (map, mut report) = MapWithDict::from_iter_with_policy(entries, policy)?;
+
+        let base = key_timestamps
+            .iter()
+            .map(|&(_, timestamp)| timestamp)
+            .min()
+            .unwrap_or_default();
+        let max = key_timestamps
+            .iter()
+            .map(|&(_, timestamp)| timestamp)
+            .max()
+            .unwrap_or_default();
+        let width = u32::BITS - (max - base).leading_zeros();
+
+        let mut offsets = vec![0u64; (map.len() * width as usize).div_ceil(64)];
+        // equal timestamps take no bits
+        for (key, timestamp) in key_timestamps.into_iter().filter(|_| width > 0) {
+            let bit_idx = map.mphf.get(&key).unwrap() * width as usize;
+            let bits = (timestamp - base) as u64;
+            offsets[bit_idx / 64] |= bits << (bit_idx % 64);
+            if bit_idx % 64 + width as usize > 64 {
+                offsets[bit_idx / 64 + 1] |= bits >> (64 - bit_idx % 64);
+            }
+        }
+
+        let map = MapWithDictTimestamps { map, base, width, offsets: offsets.into_boxed_slice() };
+        report.set_size(map.size());
+
+        Ok((map, report))
+    }
+
+    /// Returns a reference to the value corresponding to the key. Returns `None` if the key is
+    /// not present in the map.
+    #[inline]
+    pub fn get<Q>(&self, key: &Q) -> Option<&V>
+    where
+        K: Borrow<Q> + PartialEq<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        self.map.get(key)
+    }
+
+    /// Returns the timestamp of the key. Returns `None` if the key is not present in the map.
+    #[inline]
+    pub fn timestamp<Q>(&self, key: &Q) -> Option<u32>
+    where
+        K: Borrow<Q> + PartialEq<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        self.get_with_age(key).map(|(_, timestamp)| timestamp)
+    }
+
+    /// Returns a reference to the value and the timestamp of the key. Returns `None` if the key is
+    /// not present in the map.
+    #[inline]
+    pub fn get_with_age<Q>(&self, key: &Q) -> Option<(&V, u32)>
+    where
+        K: Borrow<Q> + PartialEq<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        self.get_impl(self.map.mphf.get(key), key)
+    }
+
+    /// Same as [`MapWithDictTimestamps::get_with_age`], but re-uses the hash precomputed by
+    /// `HashedKey`.
+    #[inline]
+    pub fn get_with_age_hashed<Q>(&self, key: &HashedKey<Q, H>) -> Option<(&V, u32)>
+    where
+        K: Borrow<Q> + PartialEq<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        self.get_impl(self.map.mphf.get_hashed(key), key.key())
+    }
+
+    /// Inner implementation of `get_with_age` returning the value and timestamp at MPHF index
+    /// `idx` if the key stored there is equal to `key`.
+    #[inline]
+    fn get_impl<Q>(&self, idx: Option<usize>, key: &Q) -> Option<(&V, u32)>
+    where
+        K: PartialEq<Q>,
+        Q: ?Sized,
+    {
+        let idx = idx?;
+
+        // SAFETY: `idx` is always within bounds (ensured during construction)
+        unsafe {
+            if access::get(&self.map.keys, idx) == key {
+                // SAFETY: `idx` and `value_idx` are always within bounds (ensure during construction)
+                let value_idx = *access::get(&self.map.values_index, idx);
+                Some((access::get(&self.map.values_dict, value_idx), self.timestamp_at(idx)))
+            } else {
+                None
+            }
+        }
+    }
+
+    /// Returns the timestamp of the entry at MPHF index `idx`.
+    ///
+    /// # Safety
+    /// `idx` must be less than the number of entries.
+    #[inline]
+    unsafe fn timestamp_at(&self, idx: usize) -> u32 {
+        self.base + read_bits(&self.offsets, idx, self.width)
+    }
+
+    /// Returns an iterator over the keys whose timestamp is earlier than `now`, i.e. expired keys
+    /// if timestamps are expiry times (pass `now - ttl` for last update times).
+    pub fn expired_keys(&self, now: u32) -> impl Iterator<Item = &K> + '_ {
+        self.iter()
+            .filter(move |&(_, _, timestamp)| timestamp < now)
+            .map(|(key, _, _)| key)
+    }
+
+    /// Returns the number of elements in the map.
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.map.len()
+    }
+
+    /// Returns `true` if the map contains no elements.
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.map.is_empty()
+    }
+
+    /// Returns an iterator over the map, yielding keys, values and timestamps.
+    pub fn iter(&self) -> impl ExactSizeIterator<Item = (&K, &V, u32)> + '_ {
+        self.map
+            .iter()
+            .enumerate()
+            // SAFETY: `idx` is always within bounds (ensured during construction)
+            .map(|(idx, (key, value))| (key, value, unsafe { self.timestamp_at(idx) }))
+    }
+
+    /// Returns the map of keys to values without timestamps.
+    #[inline]
+    pub fn as_map(&self) -> &MapWithDict<K, V, B, S, ST, H> {
+        &self.map
+    }
+
+    /// Returns the total number of bytes occupied by the map.
+    ///
+    /// # Examples
+    /// ```
+    /// # use entropy_map::{MapWithDictTimestamps, DEFAULT_GAMMA};
+    /// let entries = (0..1000u64).map(|i| (i, i % 10, 29_000_000 + i as u32));
+    /// let map: MapWithDictTimestamps<u64, u64> = MapWithDictTimestamps::from_iter_with_params(entries, DEFAULT_GAMMA).unwrap();
+    /// // offsets up to 999 take 10 bits, 10000 bits rounded up to 157 words
+    /// assert_eq!(map.size(), map.as_map().size() + 8 + 16 + 157 * 8);
+    /// ```
+    #[inline]
+    pub fn size(&self) -> usize {
+        self.map.size()
+            + size_of_val(&self.base)
+            + size_of_val(&self.width)
+            + size_of_val(&self.offsets)
+            + size_of_val(self.offsets.as_ref())
+    }
+
+    /// Checks invariants of the map and that there are `width` offset bits per entry.
+    pub fn debug_check_invariants(&self) -> Result<(), InvariantError> {
+        self.map.debug_check_invariants()?;
+        if self.width > u32::BITS || self.offsets.len() != (self.map.len() * self.width as usize).div_ceil(64) {
+            return Err(InvariantError::LengthMismatch);
+        }
+        Ok(())
+    }
+}
+
+/// Implement `get_with_age` for `Archived` version of `MapWithDictTimestamps` if feature is enabled
+#[cfg(feature = "rkyv_derive")]
+impl<K, V, const B: usize, const S: usize, ST, H> ArchivedMapWithDictTimestamps<K, V, B, S, ST, H>
+where
+    K: PartialEq + Hash + rkyv::Archive,
+    K::Archived: PartialEq<K>,
+    V: rkyv::Archive,
+    ST: PrimInt + Unsigned + rkyv::Archive<Archived = ST>,
+    H: Hasher + Default,
+{
+    /// Returns a reference to the archived value corresponding to the key. Returns `None` if the
+    /// key is not present in the map.
+    #[inline]
+    pub fn get<Q>(&self, key: &Q) -> Option<&V::Archived>
+    where
+        K: Borrow<Q>,
+        <K as rkyv::Archive>::Archived: PartialEq<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        self.map.get(key)
+    }
+
+    /// Returns the timestamp of the key. Returns `None` if the key is not present in the map.
+    #[inline]
+    pub fn timestamp<Q>(&self, key: &Q) -> Option<u32>
+    where
+        K: Borrow<Q>,
+        <K as rkyv::Archive>::Archived: PartialEq<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        self.get_with_age(key).map(|(_, timestamp)| timestamp)
+    }
+
+    /// Returns a reference to the archived value and the timestamp of the key. Returns `None` if
+    /// the key is not present in the map.
+    #[inline]
+    pub fn get_with_age<Q>(&self, key: &Q) -> Option<(&V::Archived, u32)>
+    where
+        K: Borrow<Q>,
+        <K as rkyv::Archive>::Archived: PartialEq<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        let idx = self.map.mphf.get(key)?;
+
+        // SAFETY: `idx` is always within bounds (ensured during construction)
+        unsafe {
+            if access::get(&self.map.keys, idx) == key {
+                // SAFETY: `idx` and `value_idx` are always within bounds (ensure during construction)
+                let value_idx = *access::get(&self.map.values_index, idx) as usize;
+                Some((
+                    access::get(&self.map.values_dict, value_idx),
+                    self.base + read_bits(&self.offsets, idx, self.width),
+                ))
+            } else {
+                None
+            }
+        }
+    }
+
+    /// Returns an iterator over the archived keys whose timestamp is earlier than `now`, see
+    /// [`MapWithDictTimestamps::expired_keys`].
+    pub fn expired_keys(&self, now: u32) -> impl Iterator<Item = &K::Archived> + '_ {
+        self.map
+            .keys
+            .iter()
+            .enumerate()
+            // SAFETY: `idx` is always within bounds (ensured during construction)
+            .filter(move |&(idx, _)| self.base + unsafe { read_bits(&self.offsets, idx, self.width) } < now)
+            .map(|(_, key)| key)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mphf::DEFAULT_GAMMA;
+
+    #[test]
+    fn test_map_with_dict_timestamps() {
+        for spread in [1, 1000, 1 << 20, u32::MAX - 7] {
+            let entries: Vec<(u64, u32, u32)> = (0..1000u64)
+                .map(|i| (i, (i % 7) as u32, 7 + (i * 7919 % 1000) as u32 * (spread / 1000)))
+                .collect();
+            let map = MapWithDictTimestamps::<u64, u32>::from_iter_with_params(entries.clone(), DEFAULT_GAMMA).unwrap();
+
+            assert_eq!(map.debug_check_invariants(), Ok(()));
+            assert_eq!(map.len(), entries.len());
+            for &(key, value, timestamp) in &entries {
+                assert_eq!(map.get_with_age(&key), Some((&value, timestamp)));
+                assert_eq!(
+                    map.get_with_age_hashed(&HashedKey::new(&key)),
+                    Some((&value, timestamp))
+                );
+            }
+            assert_eq!(map.timestamp(&1000), None);
+            for (&key, &value, timestamp) in map.iter() {
+                let (_, expected_value, expected_timestamp) = entries[key as usize];
+                assert_eq!((value, timestamp), (expected_value, expected_timestamp));
+            }
+
+            let now = entries[500].2;
+            let mut expired: Vec<u64> = map.expired_keys(now).copied().collect();
+            expired.sort_unstable();
+            let expected: Vec<u64> = entries
+                .iter()
+                .filter(|&&(_, _, timestamp)| timestamp < now)
+                .map(|&(key, _, _)| key)
+                .collect();
+            assert_eq!(expired, expected);
+
+            #[cfg(feature = "rkyv_derive")]
+            {
+                let rkyv_bytes = rkyv::to_bytes::<_, 1024>(&map).unwrap();
+                let rkyv_map = rkyv::check_archived_root::<MapWithDictTimestamps<u64, u32>>(&rkyv_bytes).unwrap();
+                for &(key, value, timestamp) in &entries {
+                    assert_eq!(rkyv_map.get_with_age(&key), Some((&value, timestamp)));
+                }
+                assert_eq!(rkyv_map.timestamp(&1000), None);
+                assert_eq!(rkyv_map.expired_keys(now).count(), expected.len());
+            }
+        }
+
+        // equal timestamps take no bits
+        let entries = (0..100u64).map(|i| (i, i % 3, 42));
+        let map = MapWithDictTimestamps::<u64, u64>::from_iter_with_params(entries, DEFAULT_GAMMA).unwrap();
+        assert_eq!(map.get_with_age(&5), Some((&2, 42)));
+        assert_eq!(map.size(), map.as_map().size() + 8 + 16);
+        assert_eq!(map.expired_keys(43).count(), 100);
+    }
+}
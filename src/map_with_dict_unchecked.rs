@@ -0,0 +1,205 @@
+//! A module providing `MapWithDictUnchecked`, a `MapWithDict` stripped of its keys.
+//!
+//! `MapWithDict` stores keys only to verify that a queried key was present at construction, while
+//! the MPHF alone maps every such key to its value. `MapWithDictUnchecked`, obtained with
+//! [`MapWithDict::into_unchecked`], drops the keys and returns the value at the MPHF index of any
+//! queried key, which results in a much smaller structure when keys are larger than values (a
+//! compressed static function). Queries of keys absent at construction return either `None` or
+//! an arbitrary value of the map, so it suits deployments where the set of queried keys is known
+//! to be a subset of the map keys, e.g. validated with the full map beforehand.
+
+use std::hash::{Hash, Hasher};
+use std::mem::size_of_val;
+
+use num::{PrimInt, Unsigned};
+use wyhash::WyHash;
+
+use crate::access;
+use crate::hashed_key::HashedKey;
+use crate::invariants::{self, InvariantError};
+use crate::map_with_dict::MapWithDict;
+use crate::mphf::Mphf;
+
+/// An immutable hash map without keys, returning arbitrary values for keys absent at construction.
+///
+/// # Examples
+/// ```
+/// # use std::collections::HashMap;
+/// # use entropy_map::MapWithDict;
+/// let map = MapWithDict::try_from(HashMap::from([(1, 2), (3, 4)])).unwrap();
+/// let unchecked_map = map.into_unchecked();
+/// assert_eq!(unchecked_map.get(&1), Some(&2));
+/// assert_eq!(unchecked_map.get(&3), Some(&4));
+/// ```
+#[derive(Default)]
+#[cfg_attr(feature = "rkyv_derive", derive(rkyv::Archive, rkyv::Deserialize, rkyv::Serialize))]
+#[cfg_attr(feature = "rkyv_derive", archive_attr(derive(rkyv::CheckBytes)))]
+pub struct MapWithDictUnchecked<V, const B: usize = 32, const S: usize = 8, ST = u8, H = WyHash>
+where
+    ST: PrimInt + Unsigned,
+    H: Hasher + Default,
+{
+    /// Minimally Perfect Hash Function for keys indices retrieval
+    pub(crate) mphf: Mphf<B, S, ST, H>,
+    /// Points to the value index in the dictionary
+    pub(crate) values_index: Box<[usize]>,
+    /// Map unique values
+    pub(crate) values_dict: Box<[V]>,
+}
+
+impl<K, V, const B: usize, const S: usize, ST, H> MapWithDict<K, V, B, S, ST, H>
+where
+    ST: PrimInt + Unsigned,
+    H: Hasher + Default,
+{
+    /// Drops the keys of the map, returning a `MapWithDictUnchecked` whose queries skip key
+    /// verification, see the [module documentation](crate::map_with_dict_unchecked).
+    pub fn into_unchecked(self) -> MapWithDictUnchecked<V, B, S, ST, H> {
+        MapWithDictUnchecked { mphf: self.mphf, values_index: self.values_index, values_dict: self.values_dict }
+    }
+}
+
+impl<V, const B: usize, const S: usize, ST, H> MapWithDictUnchecked<V, B, S, ST, H>
+where
+    ST: PrimInt + Unsigned,
+    H: Hasher + Default,
+{
+    /// Returns a reference to the value corresponding to the key. Returns either `None` or an
+    /// arbitrary value of the map if the key was not present at construction.
+    #[inline]
+    pub fn get<Q: Hash + ?Sized>(&self, key: &Q) -> Option<&V> {
+        self.get_impl(self.mphf.get(key))
+    }
+
+    /// Same as [`MapWithDictUnchecked::get`], but re-uses the hash precomputed by `HashedKey`.
+    #[inline]
+    pub fn get_hashed<Q: ?Sized>(&self, key: &HashedKey<Q, H>) -> Option<&V> {
+        self.get_impl(self.mphf.get_hashed(key))
+    }
+
+    /// Inner implementation of `get` returning the value at MPHF index `idx`.
+    #[inline]
+    fn get_impl(&self, idx: Option<usize>) -> Option<&V> {
+        let idx = idx?;
+
+        // SAFETY: `idx` and `value_idx` are always within bounds (ensured during construction)
+        unsafe {
+            let value_idx = *access::get(&self.values_index, idx);
+            Some(access::get(&self.values_dict, value_idx))
+        }
+    }
+
+    /// Returns the number of keys the map was built with.
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.values_index.len()
+    }
+
+    /// Returns `true` if the map was built without keys.
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.values_index.is_empty()
+    }
+
+    /// Returns the dictionary of unique values of the map.
+    #[inline]
+    pub fn values_dict(&self) -> &[V] {
+        &self.values_dict
+    }
+
+    /// Returns the total number of bytes occupied by the map.
+    ///
+    /// # Examples
+    /// ```
+    /// # use std::collections::HashMap;
+    /// # use entropy_map::MapWithDict;
+    /// let map = MapWithDict::try_from(HashMap::from([(1u64, 2), (3, 4)])).unwrap();
+    /// let map_size = map.size();
+    /// assert_eq!(map.into_unchecked().size(), map_size - 16 - 16);
+    /// ```
+    #[inline]
+    pub fn size(&self) -> usize {
+        size_of_val(self)
+            + self.mphf.size()
+            + size_of_val(self.values_index.as_ref())
+            + size_of_val(self.values_dict.as_ref())
+    }
+
+    /// Checks invariants of the MPHF, that its outputs match the number of values indices and that
+    /// every values index points within the values dictionary.
+    pub fn debug_check_invariants(&self) -> Result<(), InvariantError> {
+        invariants::check_outputs(&self.mphf, self.values_index.len())?;
+        invariants::check_values_index(&self.values_index, self.values_index.len(), self.values_dict.len())
+    }
+}
+
+/// Implement `get` for `Archived` version of `MapWithDictUnchecked` if feature is enabled
+#[cfg(feature = "rkyv_derive")]
+impl<V, const B: usize, const S: usize, ST, H> ArchivedMapWithDictUnchecked<V, B, S, ST, H>
+where
+    V: rkyv::Archive,
+    ST: PrimInt + Unsigned + rkyv::Archive<Archived = ST>,
+    H: Hasher + Default,
+{
+    /// Returns a reference to the archived value corresponding to the key, see
+    /// [`MapWithDictUnchecked::get`].
+    #[inline]
+    pub fn get<Q: Hash + ?Sized>(&self, key: &Q) -> Option<&V::Archived> {
+        self.get_impl(self.mphf.get(key))
+    }
+
+    /// Same as [`ArchivedMapWithDictUnchecked::get`], but re-uses the hash precomputed by
+    /// `HashedKey`.
+    #[inline]
+    pub fn get_hashed<Q: ?Sized>(&self, key: &HashedKey<Q, H>) -> Option<&V::Archived> {
+        self.get_impl(self.mphf.get_hashed(key))
+    }
+
+    /// Inner implementation of `get` returning the archived value at MPHF index `idx`.
+    #[inline]
+    fn get_impl(&self, idx: Option<usize>) -> Option<&V::Archived> {
+        let idx = idx?;
+
+        // SAFETY: `idx` and `value_idx` are always within bounds (ensured during construction)
+        unsafe {
+            let value_idx = *access::get(&self.values_index, idx) as usize;
+            Some(access::get(&self.values_dict, value_idx))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mphf::DEFAULT_GAMMA;
+    use rand::{Rng, SeedableRng};
+    use rand_chacha::ChaCha8Rng;
+
+    #[test]
+    fn test_into_unchecked() {
+        let mut rng = ChaCha8Rng::seed_from_u64(123);
+        let entries: Vec<(String, u32)> = (0..1000)
+            .map(|i| (format!("key-{}", rng.gen::<u64>()), i % 10))
+            .collect();
+        let map = MapWithDict::<String, u32>::from_iter_with_params(entries.clone(), DEFAULT_GAMMA).unwrap();
+        let map_size = map.size();
+
+        let unchecked_map = map.into_unchecked();
+        assert_eq!(unchecked_map.debug_check_invariants(), Ok(()));
+        assert_eq!(unchecked_map.len(), entries.len());
+        assert!(unchecked_map.size() < map_size);
+        for (key, value) in &entries {
+            assert_eq!(unchecked_map.get(key.as_str()), Some(value));
+            assert_eq!(unchecked_map.get_hashed(&HashedKey::new(key.as_str())), Some(value));
+        }
+
+        #[cfg(feature = "rkyv_derive")]
+        {
+            let rkyv_bytes = rkyv::to_bytes::<_, 1024>(&unchecked_map).unwrap();
+            let rkyv_map = rkyv::check_archived_root::<MapWithDictUnchecked<u32>>(&rkyv_bytes).unwrap();
+            for (key, value) in &entries {
+                assert_eq!(rkyv_map.get(key.as_str()), Some(value));
+            }
+        }
+    }
+}
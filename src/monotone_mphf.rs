@@ -0,0 +1,197 @@
+//! A module providing `MonotoneMphf`, an MPHF preserving the order of sorted keys.
+//!
+//! Indices of `Mphf` are a pseudo-random permutation of keys. `MonotoneMphf` is built from keys
+//! sorted in strictly increasing order and maps the `i`-th key to `i`, so that
+//! `key_a < key_b ⇒ get(key_a) < get(key_b)`, e.g. to binary-search structures sorted by the same
+//! keys. It stores an `Mphf` along with the rank of the key of every `Mphf` index, bit-packed to
+//! `log2(n)` bits, i.e. queries cost a single bit-packed read on top of `Mphf::get` and space grows
+//! by `log2(n)` bits per key.
+
+use std::hash::{Hash, Hasher};
+use std::mem::size_of_val;
+
+use num::{PrimInt, Unsigned};
+use wyhash::WyHash;
+
+use crate::hashed_key::HashedKey;
+use crate::mphf::{BuildPolicy, Mphf, MphfError};
+use crate::report::BuildReport;
+use crate::static_table::{bit_width, pack_codes, read_code};
+
+/// Order-preserving minimal perfect hash function, see the module documentation.
+///
+/// # Examples
+/// ```
+/// # use entropy_map::{MonotoneMphf, DEFAULT_GAMMA};
+/// let keys = ["apple", "banana", "cherry", "date"];
+/// let mphf: MonotoneMphf = MonotoneMphf::from_slice(&keys, DEFAULT_GAMMA).unwrap();
+/// assert_eq!(mphf.get("apple"), Some(0));
+/// assert_eq!(mphf.get("cherry"), Some(2));
+/// ```
+#[derive(Default)]
+#[cfg_attr(feature = "rkyv_derive", derive(rkyv::Archive, rkyv::Deserialize, rkyv::Serialize))]
+#[cfg_attr(feature = "rkyv_derive", archive_attr(derive(rkyv::CheckBytes)))]
+pub struct MonotoneMphf<
+    const B: usize = 32,
+    const S: usize = 8,
+    ST: PrimInt + Unsigned = u8,
+    H: Hasher + Default = WyHash,
+> {
+    /// MPHF of keys
+    mphf: Mphf<B, S, ST, H>,
+    /// Bit width of ranks
+    rank_width: u8,
+    /// Bit-packed rank of the key of every `mphf` index
+    ranks: Box<[u64]>,
+}
+
+impl<const B: usize, const S: usize, ST: PrimInt + Unsigned, H: Hasher + Default> MonotoneMphf<B, S, ST, H> {
+    /// Initializes `MonotoneMphf` using slice of `keys` sorted in strictly increasing order and
+    /// parameter `gamma`, failing with `MphfError::UnsortedKeys` otherwise.
+    pub fn from_slice<K: Hash + Ord>(keys: &[K], gamma: f32) -> Result<Self, MphfError> {
+        Self::from_slice_with_policy(keys, &BuildPolicy::fixed(gamma)).map(|(mphf, _)| mphf)
+    }
+
+    /// Initializes `MonotoneMphf` using slice of `keys` sorted in strictly increasing order,
+    /// building the `Mphf` as configured by `policy`.
+    ///
+    /// # Examples
+    /// ```
+    /// # use entropy_map::{BuildPolicy, MonotoneMphf, MphfError};
+    /// let keys: Vec<u64> = (0..1000).map(|i| i * 3).collect();
+    /// let (mphf, report): (MonotoneMphf, _) = MonotoneMphf::from_slice_with_policy(&keys, &BuildPolicy::default()).unwrap();
+    /// assert_eq!(report.keys(), 1000);
+    /// assert_eq!(mphf.get(&300u64), Some(100));
+    ///
+    /// let res: Result<(MonotoneMphf, _), _> = MonotoneMphf::from_slice_with_policy(&[2, 1], &BuildPolicy::default());
+    /// assert!(matches!(res, Err(MphfError::UnsortedKeys)));
+    /// ```
+    pub fn from_slice_with_policy<K: Hash + Ord>(
+        keys: &[K],
+        policy: &BuildPolicy,
+    ) -> Result<(Self, BuildReport), MphfError> {
+        if keys.windows(2).any(|pair| pair[0] >= pair[1]) {
+            return Err(MphfError::UnsortedKeys);
+        }
+        let (mphf, mut report) = Mphf::from_slice_with_policy(keys, policy)?;
+
+        let mut ranks = vec![0u32; keys.len()];
+        for (rank, key) in keys.iter().enumerate() {
+            ranks[mphf.get(key).expect("key of MPHF")] = rank as u32;
+        }
+        let rank_width = bit_width(keys.len().saturating_sub(1) as u32);
+        let mphf = MonotoneMphf { mphf, rank_width, ranks: pack_codes(&ranks, rank_width) };
+        report.set_size(mphf.size());
+        Ok((mphf, report))
+    }
+
+    /// Returns the rank of `key` among the keys `MonotoneMphf` was built from. Absent keys are
+    /// mapped to `None` or an arbitrary rank, as of [`Mphf::get`].
+    #[inline]
+    pub fn get<K: Hash + ?Sized>(&self, key: &K) -> Option<usize> {
+        self.get_by_hash(self.mphf.hash_key(key))
+    }
+
+    /// Same as [`MonotoneMphf::get`], but re-uses the hash precomputed by `HashedKey`.
+    #[inline]
+    pub fn get_hashed<K: ?Sized>(&self, key: &HashedKey<K, H>) -> Option<usize> {
+        self.mphf.get_hashed(key).map(|idx| self.rank(idx))
+    }
+
+    /// Returns the rank of the key `hash`, see [`Mphf::get_by_hash`].
+    #[inline]
+    pub fn get_by_hash(&self, hash: u64) -> Option<usize> {
+        self.mphf.get_by_hash(hash).map(|idx| self.rank(idx))
+    }
+
+    /// Returns the rank of the key of `mphf` index `idx`.
+    #[inline]
+    fn rank(&self, idx: usize) -> usize {
+        // SAFETY: `idx` is an index of `mphf`, which has a rank for every index
+        unsafe { read_code(&self.ranks, idx, self.rank_width) as usize }
+    }
+
+    /// Returns the underlying MPHF mapping keys to indices of ranks.
+    #[inline]
+    pub fn mphf(&self) -> &Mphf<B, S, ST, H> {
+        &self.mphf
+    }
+
+    /// Returns the total number of bytes occupied by `MonotoneMphf`
+    pub fn size(&self) -> usize {
+        size_of_val(self) - size_of_val(&self.mphf) + self.mphf.size() + size_of_val(self.ranks.as_ref())
+    }
+}
+
+/// Implement `get` for `Archived` version of `MonotoneMphf` if feature is enabled
+#[cfg(feature = "rkyv_derive")]
+impl<const B: usize, const S: usize, ST, H> ArchivedMonotoneMphf<B, S, ST, H>
+where
+    ST: PrimInt + Unsigned + rkyv::Archive<Archived = ST>,
+    H: Hasher + Default,
+{
+    /// Returns the rank of `key`, see [`MonotoneMphf::get`].
+    #[inline]
+    pub fn get<K: Hash + ?Sized>(&self, key: &K) -> Option<usize> {
+        self.get_by_hash(self.mphf.hash_key(key))
+    }
+
+    /// Returns the rank of the key `hash`, see [`MonotoneMphf::get_by_hash`].
+    #[inline]
+    pub fn get_by_hash(&self, hash: u64) -> Option<usize> {
+        self.mphf
+            .get_by_hash(hash)
+            // SAFETY: `idx` is an index of `mphf`, which has a rank for every index
+            .map(|idx| unsafe { read_code(&self.ranks, idx, self.rank_width) as usize })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mphf::DEFAULT_GAMMA;
+
+    #[test]
+    fn test_monotone() {
+        let keys: Vec<String> = {
+            let mut keys: Vec<String> = (0..10_000).map(|i| format!("key-{i}")).collect();
+            keys.sort_unstable();
+            keys
+        };
+        let mphf: MonotoneMphf = MonotoneMphf::from_slice(&keys, DEFAULT_GAMMA).unwrap();
+        for (rank, key) in keys.iter().enumerate() {
+            assert_eq!(mphf.get(key), Some(rank));
+            assert_eq!(mphf.get_hashed(&HashedKey::new(key)), Some(rank));
+        }
+        assert_eq!(
+            mphf.size(),
+            size_of_val(&mphf) - size_of_val(mphf.mphf()) + mphf.mphf().size() + (14 * 10_000usize).div_ceil(64) * 8
+        );
+
+        let mphf: MonotoneMphf = MonotoneMphf::from_slice(&[7u64], DEFAULT_GAMMA).unwrap();
+        assert_eq!(mphf.get(&7u64), Some(0));
+        let mphf: MonotoneMphf = MonotoneMphf::from_slice::<u64>(&[], DEFAULT_GAMMA).unwrap();
+        assert_eq!(mphf.get(&7u64), None);
+    }
+
+    #[test]
+    fn test_unsorted() {
+        for keys in [[1u64, 3, 2], [1, 2, 2]] {
+            let res: Result<MonotoneMphf, _> = MonotoneMphf::from_slice(&keys, DEFAULT_GAMMA);
+            assert!(matches!(res, Err(MphfError::UnsortedKeys)));
+        }
+    }
+
+    #[cfg(feature = "rkyv_derive")]
+    #[test]
+    fn test_rkyv() {
+        let keys: Vec<u64> = (0..10_000).map(|i| i * 7).collect();
+        let mphf: MonotoneMphf = MonotoneMphf::from_slice(&keys, DEFAULT_GAMMA).unwrap();
+        let rkyv_bytes = rkyv::to_bytes::<_, 1024>(&mphf).unwrap();
+        let rkyv_mphf = rkyv::check_archived_root::<MonotoneMphf>(&rkyv_bytes).unwrap();
+
+        for (rank, key) in keys.iter().enumerate() {
+            assert_eq!(rkyv_mphf.get(key), Some(rank));
+        }
+    }
+}
@@ -7,40 +7,115 @@
 //! but prioritizes code simplicity and portability, with a special focus on optimizing the rank
 //! storage mechanism and reducing the construction time and querying latency of MPHF.
 
-use std::hash::{Hash, Hasher};
+use std::collections::hash_map::RandomState;
+use std::hash::{BuildHasher, Hash, Hasher};
 use std::marker::PhantomData;
 use std::mem::size_of_val;
+use std::ops::Range;
+use std::time::{Duration, Instant};
 
 use num::{Integer, PrimInt, Unsigned};
+#[cfg(feature = "rayon")]
+use rayon::prelude::*;
 use wyhash::WyHash;
 
+use crate::access;
+use crate::bit_slice::{BitSlice, InterleavedBits};
+use crate::hashed_key::HashedKey;
+use crate::invariants::InvariantError;
+#[cfg(feature = "rkyv_derive")]
+use crate::key_filter::ArchivedKeyFilter;
+use crate::key_filter::KeyFilter;
 use crate::mphf::MphfError::*;
-use crate::rank::{RankedBits, RankedBitsAccess};
+#[cfg(feature = "rkyv_derive")]
+use crate::mphf_extras::ExtrasAccess;
+use crate::mphf_extras::{search_fallback, Extras, ExtrasLayout, NoExtras};
+use crate::progress::BuildProgress;
+use crate::rank::{self, RankedBits, RankedBitsAccess, RankedBitsLayout, RankedBitsSections};
+use crate::report::{AllocScope, BuildReport, LevelDiagnostics, LevelReport, MphfStats, SeedTrial};
+#[cfg(feature = "simd")]
+use crate::simd;
 
 /// A Minimal Perfect Hash Function (MPHF).
 ///
 /// Template parameters:
-/// - `B`: group size in bits in [1..64] range, default 32 bits.
+/// - `B`: group size in bits in [1..256] range, default 32 bits.
 /// - `S`: defines maximum seed value to try (2^S) in [0..16] range, default 8.
 /// - `ST`: seed type (unsigned integer), default `u8`.
 /// - `H`: hasher used to hash keys, default `WyHash`.
+/// - `R`: layout of the ranked bit vector, default `RankedBits`. `InterleavedRankedBits` trades
+///   extra memory for rank queries touching a single cache line, `Rank9RankedBits` and
+///   `PoppyRankedBits` are available with `rank9` and `poppy` features.
+/// - `E`: extras stored alongside the levels, default `NoExtras`. `Extras` stores the hash seed,
+///   build seed, key filter and fallback table requested by `BuildPolicy`, see [`MphfWithExtras`].
 #[derive(Default)]
 #[cfg_attr(feature = "rkyv_derive", derive(rkyv::Archive, rkyv::Deserialize, rkyv::Serialize))]
 #[cfg_attr(feature = "rkyv_derive", archive_attr(derive(rkyv::CheckBytes)))]
-pub struct Mphf<const B: usize = 32, const S: usize = 8, ST: PrimInt + Unsigned = u8, H: Hasher + Default = WyHash> {
+pub struct Mphf<
+    const B: usize = 32,
+    const S: usize = 8,
+    ST: PrimInt + Unsigned = u8,
+    H: Hasher + Default = WyHash,
+    R: RankedBitsLayout = RankedBits,
+    E: ExtrasLayout = NoExtras,
+> {
     /// Ranked bits for efficient rank queries
-    ranked_bits: RankedBits,
+    pub(crate) ranked_bits: R,
     /// Group sizes at each level
-    level_groups: Box<[u32]>,
+    pub(crate) level_groups: Box<[u32]>,
     /// Combined group seeds from all levels
-    group_seeds: Box<[ST]>,
+    pub(crate) group_seeds: Box<[ST]>,
+    /// Optional hash seed, build seed, key filter and fallback table
+    pub(crate) extras: E,
     /// Phantom field for the hasher
-    _phantom_hasher: PhantomData<H>,
+    pub(crate) _phantom_hasher: PhantomData<H>,
 }
 
+/// `Mphf` with `Extras`, supporting every `BuildPolicy` option: keyed hashing, build seeds, key
+/// filters and fallback tables.
+pub type MphfWithExtras<const B: usize = 32, const S: usize = 8, ST = u8, H = WyHash> =
+    Mphf<B, S, ST, H, RankedBits, Extras>;
+
 /// Maximum number of levels to build for MPHF.
 const MAX_LEVELS: usize = 64;
 
+/// Minimum number of keys of a level for its seed search to be split into parallel group ranges.
+#[cfg(feature = "rayon")]
+const PAR_MIN_LEVEL_KEYS: usize = 1 << 14;
+
+/// Number of group ranges searched in parallel per `rayon` thread, more than 1 to balance load.
+#[cfg(feature = "rayon")]
+const PAR_RANGES_PER_THREAD: usize = 4;
+
+/// Number of keys of `get_many` batches, whose cache misses overlap thanks to prefetching.
+const GET_MANY_BATCH_LEN: usize = 16;
+
+/// Maximum `gamma` tried by [`Mphf::from_slice_with_budget`].
+pub const BUDGET_MAX_GAMMA: f32 = 4.0;
+
+/// Maximum number of `gamma` bisection steps of [`Mphf::from_slice_with_budget`].
+const BUDGET_SEARCH_STEPS: usize = 5;
+
+/// Difference of `gamma` below which [`Mphf::from_slice_with_budget`] stops bisecting.
+const BUDGET_MIN_GAMMA_STEP: f32 = 0.05;
+
+/// Maximum number of times keys are re-hashed with another hash seed after a 64-bit hash collision,
+/// see [`BuildPolicy::with_collision_rehash`].
+const MAX_COLLISION_REHASHES: usize = 2;
+
+/// Location of the bit set for an MPHF output index, as returned by [`Mphf::locate`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BitLocation {
+    /// Position of the bit in the underlying bit vector
+    pub bit_idx: u64,
+    /// Level the bit belongs to, levels being numbered from 0
+    pub level: usize,
+    /// Group the bit belongs to, groups being numbered consecutively across all levels
+    pub group_idx: usize,
+    /// Position of the bit within its group, in `0..B` range
+    pub bit_in_group: usize,
+}
+
 /// Errors that can occur when initializing `Mphf`.
 #[derive(Debug)]
 pub enum MphfError {
@@ -50,15 +125,395 @@ pub enum MphfError {
     InvalidSeedType,
     /// Error when the `gamma` parameter is less than 1.0.
     InvalidGammaParameter,
+    /// Error when a level is too large to be addressed, i.e. its size in bits doesn't fit into
+    /// `usize` or its number of groups doesn't fit into `u32`.
+    LevelSizeOverflow,
+    /// Error when construction was cancelled via `BuildProgress::cancel`.
+    Cancelled,
+    /// Error when the group size of `MphfDyn` is not one of `DYN_GROUP_SIZES`.
+    UnsupportedGroupSize,
+    /// Error when keys have the same 64-bit hash under every hash seed tried with
+    /// [`BuildPolicy::with_collision_rehash`], i.e. they are duplicates.
+    HashCollision,
+    /// Error when the `lambda` parameter of `PtHash` is not positive or `alpha` is not within
+    /// `(0.0, 1.0]` range.
+    InvalidPtHashParameter,
+    /// Error when the bucket size of `RecSplit` is 0 or exceeds `RECSPLIT_MAX_BUCKET_SIZE`.
+    InvalidBucketSize,
+    /// Error when keys of `MonotoneMphf` are not sorted in strictly increasing order.
+    UnsortedKeys,
+    /// Error when `BuildPolicy` requests a hash seed, build seed, key filter or fallback table,
+    /// which are only stored by structures built with `Extras`, see [`MphfWithExtras`].
+    ExtrasRequired,
 }
 
 /// Default `gamma` parameter for MPHF.
 pub const DEFAULT_GAMMA: f32 = 2.0;
 
-impl<const B: usize, const S: usize, ST: PrimInt + Unsigned, H: Hasher + Default> Mphf<B, S, ST, H> {
-    /// Ensure that `B` is in [1..64] range
+/// Policy controlling how `gamma` is escalated when MPHF construction fails, i.e. when the number
+/// of levels needed to place all keys exceeds `max_levels`, and how keys are hashed.
+///
+/// Builds are attempted with `gamma`, `gamma * gamma_growth`, `gamma * gamma_growth^2`, ... up to
+/// and including `max_gamma`, so that unattended pipelines can recover from unlucky or adversarial
+/// key sets at the cost of a larger MPHF.
+///
+/// Hash seeds, build seeds, key filters and fallback tables are only stored by structures built
+/// with `Extras` (e.g. [`MphfWithExtras`]), others fail with `MphfError::ExtrasRequired`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct BuildPolicy {
+    /// `gamma` parameter used by the first attempt
+    pub gamma: f32,
+    /// Maximum `gamma` parameter to try
+    pub max_gamma: f32,
+    /// Factor `gamma` is multiplied by after each failed attempt, must be greater than 1.0 to retry
+    pub gamma_growth: f32,
+    /// Maximum number of levels before an attempt is considered failed, capped at 64
+    pub max_levels: usize,
+    /// Seed hashed by the hasher `H` before every key and stored in the structure, 0 for unkeyed
+    /// hashing. Keys hashed by a keyed hasher (e.g. `SipHasher13`) with a secret random seed can't
+    /// be crafted to collide, see [`BuildPolicy::keyed`].
+    pub hash_seed: u64,
+    /// Seed mixed into key hashes before `hash_with_seed` computes their level hashes and stored in
+    /// the structure, 0 by default. Unlike `hash_seed`, it applies to precomputed hashes too and
+    /// leaves key hashes unchanged, so that a construction degenerating with one seed can be
+    /// retried with another, while builds with the same seed remain reproducible across runs, see
+    /// [`BuildPolicy::with_build_seed`].
+    pub build_seed: u64,
+    /// Number of `KeyFilter` bits per key checked before walking MPHF levels, 0 to build no filter.
+    /// The filter speeds up queries for absent keys at the cost of a larger structure.
+    pub key_filter_bits: u32,
+    /// Target false positive rate of the `KeyFilter`, from which its number of bits per key is
+    /// chosen at construction given the number of keys, overriding `key_filter_bits`. 0.0 to use
+    /// `key_filter_bits` instead.
+    pub key_filter_fpr: f64,
+    /// Maximum number of seeds tried per group, capped at `2^S`. Lower values bound construction
+    /// effort, pushing keys of groups without a collision-free seed among them to the next level.
+    pub max_group_seeds: u32,
+    /// Time budget of the seed search of a construction, `None` for no budget. Once it's exceeded,
+    /// groups keep the best seed found so far and the groups of further levels only try their
+    /// first seed, pushing unresolved keys to the next level. Structures built with a budget may
+    /// differ between runs and take a few hundredths of a bit per key more.
+    pub seed_search_budget: Option<Duration>,
+    /// Whether to collect `LevelDiagnostics` of every level into the `BuildReport`.
+    pub diagnostics: bool,
+    /// Whether to store keys left after `max_levels` levels in a fallback table searched before
+    /// the levels instead of failing the attempt, see [`BuildPolicy::with_fallback`].
+    pub fallback: bool,
+    /// Whether to check key hashes for 64-bit collisions before building, re-hashing keys with
+    /// another hash seed on collision, see [`BuildPolicy::with_collision_rehash`].
+    pub collision_rehash: bool,
+}
+
+impl BuildPolicy {
+    /// Creates a policy making a single attempt with `gamma`, which is what `from_*` constructors
+    /// taking `gamma` use.
+    #[inline]
+    pub fn fixed(gamma: f32) -> Self {
+        BuildPolicy {
+            gamma,
+            max_gamma: gamma,
+            gamma_growth: 1.0,
+            max_levels: MAX_LEVELS,
+            hash_seed: 0,
+            build_seed: 0,
+            key_filter_bits: 0,
+            key_filter_fpr: 0.0,
+            max_group_seeds: u32::MAX,
+            seed_search_budget: None,
+            diagnostics: false,
+            fallback: false,
+            collision_rehash: false,
+        }
+    }
+
+    /// Creates a policy starting with `gamma` and growing it by 1.5x after every failed attempt
+    /// up to `max_gamma`.
+    #[inline]
+    pub fn escalating(gamma: f32, max_gamma: f32) -> Self {
+        BuildPolicy {
+            gamma,
+            max_gamma,
+            gamma_growth: 1.5,
+            max_levels: MAX_LEVELS,
+            hash_seed: 0,
+            build_seed: 0,
+            key_filter_bits: 0,
+            key_filter_fpr: 0.0,
+            max_group_seeds: u32::MAX,
+            seed_search_budget: None,
+            diagnostics: false,
+            fallback: false,
+            collision_rehash: false,
+        }
+    }
+
+    /// Creates the default policy with a random `hash_seed`, for structures built from untrusted
+    /// keys with a keyed hasher like `SipHasher13` (e.g. `MapKeyed` or `SetKeyed`), so that an
+    /// attacker can't craft keys forcing deep levels or construction failures.
+    ///
+    /// # Examples
+    /// ```
+    /// # use entropy_map::{BuildPolicy, SetKeyed};
+    /// let (set, _) = SetKeyed::from_iter_with_policy([1, 2, 3], &BuildPolicy::keyed()).unwrap();
+    /// assert!(set.contains(&1));
+    /// assert_ne!(set.hash_seed(), 0);
+    /// ```
+    #[inline]
+    pub fn keyed() -> Self {
+        BuildPolicy::default().with_random_hash_seed()
+    }
+
+    /// Sets the factor `gamma` is multiplied by after each failed attempt.
+    #[inline]
+    pub fn with_gamma_growth(mut self, gamma_growth: f32) -> Self {
+        self.gamma_growth = gamma_growth;
+        self
+    }
+
+    /// Sets the maximum number of levels before an attempt is considered failed.
+    #[inline]
+    pub fn with_max_levels(mut self, max_levels: usize) -> Self {
+        self.max_levels = max_levels;
+        self
+    }
+
+    /// Sets the seed hashed before every key, see [`BuildPolicy::hash_seed`].
+    #[inline]
+    pub fn with_hash_seed(mut self, hash_seed: u64) -> Self {
+        self.hash_seed = hash_seed;
+        self
+    }
+
+    /// Sets the seed mixed into key hashes, see [`BuildPolicy::build_seed`].
+    ///
+    /// # Examples
+    /// ```
+    /// # use entropy_map::{BuildPolicy, MphfWithExtras};
+    /// let keys: Vec<u64> = (0..1000).collect();
+    /// let build = |seed| MphfWithExtras::<32, 8>::from_slice_with_policy(&keys, &BuildPolicy::default().with_build_seed(seed));
+    /// let ((mphf, _), (same, _), (other, _)) = (build(7).unwrap(), build(7).unwrap(), build(8).unwrap());
+    /// assert_eq!(mphf.build_seed(), 7);
+    /// assert!(keys.iter().all(|key| mphf.get(key) == same.get(key)));
+    /// assert!(keys.iter().any(|key| mphf.get(key) != other.get(key)));
+    /// ```
+    #[inline]
+    pub fn with_build_seed(mut self, seed: u64) -> Self {
+        self.build_seed = seed;
+        self
+    }
+
+    /// Sets a random non-zero seed hashed before every key, see [`BuildPolicy::hash_seed`].
+    #[inline]
+    pub fn with_random_hash_seed(self) -> Self {
+        // `RandomState` is randomly keyed from the OS entropy source
+        let hash_seed = RandomState::new().build_hasher().finish();
+        self.with_hash_seed(hash_seed.max(1))
+    }
+
+    /// Sets the number of `KeyFilter` bits per key, see [`BuildPolicy::key_filter_bits`].
+    ///
+    /// # Examples
+    /// ```
+    /// # use entropy_map::{BuildPolicy, SetKeyed, DEFAULT_GAMMA};
+    /// let policy = BuildPolicy::fixed(DEFAULT_GAMMA).with_key_filter(8);
+    /// let (set, _) = SetKeyed::<u32>::from_iter_with_policy(0..1000, &policy).unwrap();
+    /// assert!(set.contains(&1));
+    /// assert!(!set.contains(&1000));
+    /// ```
+    #[inline]
+    pub fn with_key_filter(mut self, bits_per_key: u32) -> Self {
+        self.key_filter_bits = bits_per_key;
+        self
+    }
+
+    /// Sets the target false positive rate of the `KeyFilter`, see
+    /// [`BuildPolicy::key_filter_fpr`], so that filters of datasets of any size reject the same
+    /// share of absent keys. The chosen number of bits per key is stored in the filter.
+    ///
+    /// # Examples
+    /// ```
+    /// # use entropy_map::{BuildPolicy, KeyFilter, MphfWithExtras, DEFAULT_GAMMA};
+    /// let keys: Vec<u32> = (0..1000).collect();
+    /// let policy = BuildPolicy::fixed(DEFAULT_GAMMA).with_key_filter_fpr(1e-3);
+    /// let (mphf, _) = MphfWithExtras::<32, 8>::from_slice_with_policy(&keys, &policy).unwrap();
+    /// let bits_per_key = mphf.key_filter().unwrap().bits_per_key();
+    /// assert!(KeyFilter::expected_fpr(keys.len(), bits_per_key) <= 1e-3);
+    /// ```
+    #[inline]
+    pub fn with_key_filter_fpr(mut self, fpr: f64) -> Self {
+        self.key_filter_fpr = fpr;
+        self
+    }
+
+    /// Sets the maximum number of seeds tried per group, see [`BuildPolicy::max_group_seeds`].
+    #[inline]
+    pub fn with_max_group_seeds(mut self, max_group_seeds: u32) -> Self {
+        self.max_group_seeds = max_group_seeds;
+        self
+    }
+
+    /// Sets the time budget of the seed search, see [`BuildPolicy::seed_search_budget`], e.g. for
+    /// online rebuilds with a hard latency budget.
+    ///
+    /// # Examples
+    /// ```
+    /// # use std::time::Duration;
+    /// # use entropy_map::{BuildPolicy, Set, DEFAULT_GAMMA};
+    /// let policy = BuildPolicy::fixed(DEFAULT_GAMMA).with_seed_search_budget(Duration::from_millis(500));
+    /// let (set, _) = Set::<u32>::from_iter_with_policy(0..1000, &policy).unwrap();
+    /// assert!(set.contains(&1));
+    /// ```
+    #[inline]
+    pub fn with_seed_search_budget(mut self, budget: Duration) -> Self {
+        self.seed_search_budget = Some(budget);
+        self
+    }
+
+    /// Enables collecting `LevelDiagnostics` (chosen seeds histogram, per group occupancy and
+    /// collisions per seed trial) of every level into `LevelReport::diagnostics`.
+    ///
+    /// # Examples
+    /// ```
+    /// # use entropy_map::{BuildPolicy, Mphf, DEFAULT_GAMMA};
+    /// let keys: Vec<u64> = (0..1000).collect();
+    /// let policy = BuildPolicy::fixed(DEFAULT_GAMMA).with_diagnostics();
+    /// let (_, report) = Mphf::<32, 8>::from_slice_with_policy(&keys, &policy).unwrap();
+    /// let diagnostics = report.levels[0].diagnostics.as_ref().unwrap();
+    /// assert_eq!(diagnostics.group_keys.iter().sum::<u32>(), 1000);
+    /// assert_eq!(diagnostics.group_placed_keys.iter().sum::<u32>(), 1000 - report.levels[0].retained_keys as u32);
+    /// ```
+    #[inline]
+    pub fn with_diagnostics(mut self) -> Self {
+        self.diagnostics = true;
+        self
+    }
+
+    /// Stores keys left after `max_levels` levels in a fallback table of their sorted hashes
+    /// instead of failing the attempt, so that construction succeeds with bounded levels. The
+    /// table is binary searched by every query before walking the levels, so it's only worth it
+    /// for a handful of stubborn keys, e.g. with a low `max_levels` bounding the query latency.
+    /// Keys with colliding 64-bit hashes (e.g. duplicates) still fail with `MaxLevelsExceeded`.
+    ///
+    /// # Examples
+    /// ```
+    /// # use entropy_map::{BuildPolicy, MphfWithExtras, DEFAULT_GAMMA};
+    /// let keys: Vec<u64> = (0..1000).collect();
+    /// let policy = BuildPolicy::fixed(DEFAULT_GAMMA).with_max_levels(1).with_fallback();
+    /// let (mphf, report) = MphfWithExtras::<32, 8>::from_slice_with_policy(&keys, &policy).unwrap();
+    /// assert_eq!(mphf.level_groups().len(), 1);
+    /// assert_eq!(mphf.fallback_len(), report.fallback_keys);
+    /// assert!(keys.iter().all(|key| mphf.get(key).unwrap() < keys.len()));
+    /// ```
+    #[inline]
+    pub fn with_fallback(mut self) -> Self {
+        self.fallback = true;
+        self
+    }
+
+    /// Checks key hashes for collisions before building: two distinct keys with the same 64-bit
+    /// hash can't be told apart by any level, so that without the check the build fails with
+    /// `MaxLevelsExceeded` only after exhausting all levels of every attempt, which at billions of
+    /// keys (where such a collision becomes likely) takes hours. On collision, keys are re-hashed
+    /// with another hash seed (stored in the structure), so that distinct keys fail the build only
+    /// if their hashes collide under several seeds, as likely as a collision of 128-bit hashes,
+    /// while duplicate keys fail with `MphfError::HashCollision` right away.
+    ///
+    /// Collisions are found by sorting a copy of the hashes, costing about as much as the sort of
+    /// the first level. Keys are re-hashed by `from_slice` constructors of structures storing `Extras`
+    /// only, iterator and precomputed hash constructors fail with `MphfError::HashCollision` instead.
+    ///
+    /// # Examples
+    /// ```
+    /// # use entropy_map::{BuildPolicy, Mphf, MphfError};
+    /// let policy = BuildPolicy::default().with_collision_rehash();
+    /// let (mphf, _) = Mphf::<32, 8>::from_slice_with_policy(&[1, 2, 3], &policy).unwrap();
+    /// assert!(mphf.get(&1).is_some());
+    ///
+    /// let res = Mphf::<32, 8>::from_slice_with_policy(&[1, 2, 1], &policy);
+    /// assert!(matches!(res, Err(MphfError::HashCollision)));
+    /// ```
+    #[inline]
+    pub fn with_collision_rehash(mut self) -> Self {
+        self.collision_rehash = true;
+        self
+    }
+
+    /// Returns the number of `KeyFilter` bits per key for `keys` keys, 0 to build no filter.
+    fn key_filter_bits_for(&self, keys: usize) -> u32 {
+        if self.key_filter_fpr > 0.0 {
+            KeyFilter::bits_per_key_for_fpr(keys, self.key_filter_fpr)
+        } else {
+            self.key_filter_bits
+        }
+    }
+
+    /// Returns an iterator over `gamma` values to attempt.
+    fn gammas(&self) -> impl Iterator<Item = f32> {
+        let BuildPolicy { gamma, max_gamma, gamma_growth, .. } = *self;
+        let mut next = Some(gamma);
+        std::iter::from_fn(move || {
+            let gamma = next?;
+            next = if gamma_growth > 1.0 && gamma < max_gamma {
+                Some((gamma * gamma_growth).min(max_gamma))
+            } else {
+                None
+            };
+            Some(gamma)
+        })
+    }
+}
+
+/// Group bits and seeds of a built level, along with its diagnostics if enabled.
+type BuiltLevel<ST> = (Vec<u64>, Vec<ST>, Option<LevelDiagnostics>);
+
+/// Best group bits and seeds found by the seed search of a range of groups, along with the
+/// statistics of the tried seeds.
+type SearchedSeeds<ST> = (Vec<u64>, Vec<ST>, Vec<SeedTrial>);
+
+/// Limits of the seed search of a construction derived from `BuildPolicy`.
+#[derive(Debug, Clone, Copy)]
+struct SeedSearchLimits {
+    /// Number of seeds tried per group, at least 1
+    max_group_seed: u32,
+    /// Instant after which groups only try their first seed
+    deadline: Option<Instant>,
+}
+
+impl SeedSearchLimits {
+    /// Returns the number of seeds to try per group at the moment.
+    #[inline]
+    fn max_group_seed(&self) -> u32 {
+        match self.deadline {
+            Some(deadline) if Instant::now() >= deadline => 1,
+            _ => self.max_group_seed,
+        }
+    }
+
+    /// Returns `true` if the deadline is exceeded.
+    #[inline]
+    fn is_exceeded(&self) -> bool {
+        self.deadline.is_some_and(|deadline| Instant::now() >= deadline)
+    }
+}
+
+impl Default for BuildPolicy {
+    /// Escalates `gamma` from `DEFAULT_GAMMA` up to 4x of it.
+    #[inline]
+    fn default() -> Self {
+        BuildPolicy::escalating(DEFAULT_GAMMA, 4.0 * DEFAULT_GAMMA)
+    }
+}
+
+impl<const B: usize, const S: usize, ST, H, R, E> Mphf<B, S, ST, H, R, E>
+where
+    ST: PrimInt + Unsigned,
+    H: Hasher + Default,
+    R: RankedBitsLayout,
+    E: ExtrasLayout,
+{
+    /// Ensure that `B` is in [1..256] range
     const B: usize = {
-        assert!(B >= 1 && B <= 64);
+        assert!(B >= 1 && B <= 256);
         B
     };
     /// Ensure that `S` is in [0..16] range
@@ -68,8 +523,242 @@ impl<const B: usize, const S: usize, ST: PrimInt + Unsigned, H: Hasher + Default
     };
 
     /// Initializes `Mphf` using slice of `keys` and parameter `gamma`.
+    ///
+    /// With debug assertions enabled, all constructors additionally verify that the built MPHF maps
+    /// `keys` to unique indices in `0..keys.len()` range, panicking otherwise.
     pub fn from_slice<K: Hash>(keys: &[K], gamma: f32) -> Result<Self, MphfError> {
-        if gamma < 1.0 {
+        Self::from_slice_with_report(keys, gamma).map(|(mphf, _)| mphf)
+    }
+
+    /// Initializes `Mphf` using slice of `keys` and parameter `gamma`, additionally returning
+    /// a `BuildReport` with per level construction statistics.
+    ///
+    /// # Examples
+    /// ```
+    /// use entropy_map::{Mphf, DEFAULT_GAMMA};
+    ///
+    /// let (mphf, report) = Mphf::<32, 8>::from_slice_with_report(&[1, 2, 3], DEFAULT_GAMMA).unwrap();
+    /// assert_eq!(report.keys(), 3);
+    /// assert_eq!(report.levels.last().unwrap().retained_keys, 0);
+    /// ```
+    pub fn from_slice_with_report<K: Hash>(keys: &[K], gamma: f32) -> Result<(Self, BuildReport), MphfError> {
+        Self::from_slice_with_policy(keys, &BuildPolicy::fixed(gamma))
+    }
+
+    /// Initializes `Mphf` using slice of `keys` with the largest `gamma` (i.e. the fastest to
+    /// build and query) whose MPHF takes at most `max_bits_per_key` bits per key, instead of
+    /// choosing `gamma` by trial and error.
+    ///
+    /// `gamma` is first tried at `DEFAULT_GAMMA`, then bisected within `1.0..=BUDGET_MAX_GAMMA`,
+    /// hashing keys only once. If even `gamma` 1.0 exceeds the budget, the smallest MPHF built is
+    /// returned, so callers have to compare the `bits_per_key` of the returned `BuildReport`
+    /// against the budget. The report also holds the chosen `gamma` and the number of builds in
+    /// `attempts`.
+    ///
+    /// # Examples
+    /// ```
+    /// use entropy_map::Mphf;
+    ///
+    /// let keys: Vec<u64> = (0..10_000).collect();
+    /// let (mphf, report) = Mphf::<32, 8>::from_slice_with_budget(&keys, 2.5).unwrap();
+    /// assert!(report.bits_per_key <= 2.5);
+    /// assert!(report.gamma >= 1.0);
+    /// assert!(mphf.get(&1).unwrap() < keys.len());
+    ///
+    /// // budgets below the smallest MPHF return the smallest MPHF
+    /// let (_, report) = Mphf::<32, 8>::from_slice_with_budget(&keys, 1.0).unwrap();
+    /// assert_eq!(report.gamma, 1.0);
+    /// ```
+    pub fn from_slice_with_budget<K: Hash>(
+        keys: &[K],
+        max_bits_per_key: f32,
+    ) -> Result<(Self, BuildReport), MphfError> {
+        let t0 = Instant::now();
+        let hashes: Vec<u64> = keys.iter().map(|key| hash_key_seeded::<H, _>(0, key)).collect();
+
+        // Builds with the largest `gamma` fitting the budget are preferred, then the smallest ones.
+        let mut best: Option<(Self, BuildReport)> = None;
+        let mut builds = 0;
+        let mut build = |gamma: f32| -> Result<bool, MphfError> {
+            builds += 1;
+            let policy = BuildPolicy::fixed(gamma);
+            let (mphf, report) = match Self::from_hashes_impl(
+                &hashes,
+                &policy,
+                &BuildProgress::default(),
+                Instant::now(),
+                AllocScope::start(),
+            ) {
+                Ok(built) => built,
+                Err(MaxLevelsExceeded) => return Ok(false),
+                Err(err) => return Err(err),
+            };
+            let fits = report.bits_per_key <= max_bits_per_key;
+            let best_fits = best
+                .as_ref()
+                .is_some_and(|(_, best)| best.bits_per_key <= max_bits_per_key);
+            if fits
+                || (!best_fits
+                    && best
+                        .as_ref()
+                        .is_none_or(|(_, best)| report.bits_per_key < best.bits_per_key))
+            {
+                best = Some((mphf, report));
+            }
+            Ok(fits)
+        };
+
+        let (mut lo, mut hi) = if build(DEFAULT_GAMMA)? {
+            (DEFAULT_GAMMA, BUDGET_MAX_GAMMA)
+        } else if build(1.0)? {
+            (1.0, DEFAULT_GAMMA)
+        } else {
+            (1.0, 1.0)
+        };
+        for _ in 0..BUDGET_SEARCH_STEPS {
+            if hi - lo < BUDGET_MIN_GAMMA_STEP {
+                break;
+            }
+            let gamma = (lo + hi) / 2.0;
+            if build(gamma)? {
+                lo = gamma;
+            } else {
+                hi = gamma;
+            }
+        }
+
+        let (mphf, mut report) = best.ok_or(MaxLevelsExceeded)?;
+        report.attempts = builds;
+        report.duration = t0.elapsed();
+        Ok((mphf, report))
+    }
+
+    /// Initializes `Mphf` using slice of `keys`, retrying failed builds with progressively larger
+    /// `gamma` as configured by `policy`. The returned `BuildReport` includes the number of attempts
+    /// and the `gamma` of the successful one.
+    ///
+    /// # Examples
+    /// ```
+    /// use entropy_map::{BuildPolicy, Mphf, DEFAULT_GAMMA};
+    ///
+    /// let policy = BuildPolicy::escalating(DEFAULT_GAMMA, 8.0);
+    /// let (mphf, report) = Mphf::<32, 8>::from_slice_with_policy(&[1, 2, 3], &policy).unwrap();
+    /// assert_eq!(report.attempts, 1);
+    /// assert_eq!(report.gamma, DEFAULT_GAMMA);
+    /// ```
+    pub fn from_slice_with_policy<K: Hash>(keys: &[K], policy: &BuildPolicy) -> Result<(Self, BuildReport), MphfError> {
+        Self::from_slice_with_progress(keys, policy, &BuildProgress::default())
+    }
+
+    /// Same as [`Mphf::from_slice_with_policy`], but reports construction progress to `progress`
+    /// and fails with `MphfError::Cancelled` once cancellation is requested via it.
+    ///
+    /// # Examples
+    /// ```
+    /// use entropy_map::{BuildPolicy, BuildProgress, Mphf};
+    ///
+    /// let progress = BuildProgress::new();
+    /// let (mphf, report) = Mphf::<32, 8>::from_slice_with_progress(&[1, 2, 3], &BuildPolicy::default(), &progress).unwrap();
+    /// assert_eq!(progress.snapshot().levels, report.levels.len());
+    /// ```
+    pub fn from_slice_with_progress<K: Hash>(
+        keys: &[K],
+        policy: &BuildPolicy,
+        progress: &BuildProgress,
+    ) -> Result<(Self, BuildReport), MphfError> {
+        let mut policy = *policy;
+        let mut rehashes = 0;
+        loop {
+            match Self::from_iter_with_progress(keys, &policy, progress) {
+                Err(HashCollision)
+                    if policy.collision_rehash && E::STORES_EXTRAS && rehashes < MAX_COLLISION_REHASHES =>
+                {
+                    rehashes += 1;
+                    policy.hash_seed = policy.hash_seed.wrapping_add(0x9e37_79b9_7f4a_7c15).max(1);
+                }
+                res => return res,
+            }
+        }
+    }
+
+    /// Initializes `Mphf` using `keys` and parameter `gamma`. Keys are hashed on the fly, so that
+    /// only their 64-bit hashes are materialized instead of a contiguous copy of the keys.
+    ///
+    /// # Examples
+    /// ```
+    /// use std::collections::HashSet;
+    /// use entropy_map::{Mphf, DEFAULT_GAMMA};
+    ///
+    /// let keys: HashSet<String> = (0..100).map(|i| i.to_string()).collect();
+    /// let mphf = Mphf::<32, 8>::from_iter_with_params(&keys, DEFAULT_GAMMA).unwrap();
+    /// assert!(mphf.get("42").unwrap() < keys.len());
+    /// ```
+    pub fn from_iter_with_params<K: Hash, I: IntoIterator<Item = K>>(keys: I, gamma: f32) -> Result<Self, MphfError> {
+        Self::from_iter_with_policy(keys, &BuildPolicy::fixed(gamma)).map(|(mphf, _)| mphf)
+    }
+
+    /// Same as [`Mphf::from_slice_with_policy`], but hashes `keys` on the fly.
+    pub fn from_iter_with_policy<K: Hash, I: IntoIterator<Item = K>>(
+        keys: I,
+        policy: &BuildPolicy,
+    ) -> Result<(Self, BuildReport), MphfError> {
+        Self::from_iter_with_progress(keys, policy, &BuildProgress::default())
+    }
+
+    /// Same as [`Mphf::from_slice_with_progress`], but hashes `keys` on the fly.
+    pub fn from_iter_with_progress<K: Hash, I: IntoIterator<Item = K>>(
+        keys: I,
+        policy: &BuildPolicy,
+        progress: &BuildProgress,
+    ) -> Result<(Self, BuildReport), MphfError> {
+        let t0 = Instant::now();
+        let alloc_scope = AllocScope::start();
+        let hashes: Vec<u64> = keys
+            .into_iter()
+            .map(|key| hash_key_seeded::<H, _>(policy.hash_seed, &key))
+            .collect();
+        Self::from_hashes_impl(&hashes, policy, progress, t0, alloc_scope)
+    }
+
+    /// Initializes `Mphf` using unique precomputed 64-bit key `hashes` and parameter `gamma`,
+    /// skipping key hashing entirely. Keys must then be queried with [`Mphf::get_by_hash`] using
+    /// hashes computed the same way, as `get` hashes keys with `H` instead.
+    ///
+    /// # Examples
+    /// ```
+    /// use entropy_map::{Mphf, DEFAULT_GAMMA};
+    ///
+    /// let hashes: Vec<u64> = (0..100u64).map(|i| i.wrapping_mul(0x9e3779b97f4a7c15)).collect();
+    /// let mphf = Mphf::<32, 8>::from_hashes(&hashes, DEFAULT_GAMMA).unwrap();
+    /// assert!(mphf.get_by_hash(hashes[42]).unwrap() < hashes.len());
+    /// ```
+    pub fn from_hashes(hashes: &[u64], gamma: f32) -> Result<Self, MphfError> {
+        Self::from_hashes_with_policy(hashes, &BuildPolicy::fixed(gamma)).map(|(mphf, _)| mphf)
+    }
+
+    /// Same as [`Mphf::from_hashes`], but retries failed builds as configured by `policy` and
+    /// returns a `BuildReport`. The `policy` hash seed is ignored, as keys are already hashed.
+    pub fn from_hashes_with_policy(hashes: &[u64], policy: &BuildPolicy) -> Result<(Self, BuildReport), MphfError> {
+        let policy = policy.with_hash_seed(0);
+        Self::from_hashes_impl(
+            hashes,
+            &policy,
+            &BuildProgress::default(),
+            Instant::now(),
+            AllocScope::start(),
+        )
+    }
+
+    /// Builds `Mphf` from key `hashes` computed with `policy` hash seed, retrying failed builds as
+    /// configured by `policy`, for a construction started at `t0`.
+    fn from_hashes_impl(
+        hashes: &[u64],
+        policy: &BuildPolicy,
+        progress: &BuildProgress,
+        t0: Instant,
+        alloc_scope: AllocScope,
+    ) -> Result<(Self, BuildReport), MphfError> {
+        if policy.gamma < 1.0 {
             return Err(InvalidGammaParameter);
         }
 
@@ -77,64 +766,149 @@ impl<const B: usize, const S: usize, ST: PrimInt + Unsigned, H: Hasher + Default
             return Err(InvalidSeedType);
         }
 
-        let mut hashes: Vec<u64> = keys.iter().map(|key| hash_key::<H, _>(key)).collect();
+        let key_filter_bits = policy.key_filter_bits_for(hashes.len());
+        let requires_extras = policy.hash_seed != 0 || policy.build_seed != 0 || key_filter_bits > 0 || policy.fallback;
+        if requires_extras && !E::STORES_EXTRAS {
+            return Err(ExtrasRequired);
+        }
+
+        if policy.collision_rehash && has_duplicate_hashes(hashes) {
+            return Err(HashCollision);
+        }
+
+        let max_levels = policy.max_levels.min(MAX_LEVELS);
+        let limits = SeedSearchLimits {
+            max_group_seed: policy.max_group_seeds.clamp(1, 1 << S),
+            deadline: policy.seed_search_budget.map(|budget| t0 + budget),
+        };
+        let mut attempts = 0;
+
+        for gamma in policy.gammas() {
+            attempts += 1;
+            let build = Self::from_hashes_with_report(
+                hashes.iter().map(|&hash| hash ^ policy.build_seed).collect(),
+                gamma,
+                max_levels,
+                policy.fallback,
+                limits,
+                policy.diagnostics,
+                progress,
+            );
+            match build {
+                Ok((mut mphf, fallback, mut report)) => {
+                    let key_filter = (key_filter_bits > 0).then(|| KeyFilter::from_hashes(hashes, key_filter_bits));
+                    mphf.extras = E::from_parts(policy.hash_seed, policy.build_seed, key_filter, fallback);
+                    report.set_size(mphf.size());
+                    // catch construction bugs early instead of returning wrong indices on queries
+                    debug_assert!(mphf.is_bijection(hashes), "MPHF doesn't map keys to unique indices");
+                    report.attempts = attempts;
+                    report.duration = t0.elapsed();
+                    alloc_scope.finish(&mut report);
+                    return Ok((mphf, report));
+                }
+                Err(MaxLevelsExceeded) => continue,
+                Err(err) => return Err(err),
+            }
+        }
+
+        Err(MaxLevelsExceeded)
+    }
+
+    /// Builds the levels of `Mphf` from key `hashes` using at most `max_levels` levels, returning
+    /// the sorted hashes of keys left after them for the fallback table if `fallback` is set. The
+    /// returned `Mphf` has no extras yet.
+    fn from_hashes_with_report(
+        mut hashes: Vec<u64>,
+        gamma: f32,
+        max_levels: usize,
+        fallback: bool,
+        limits: SeedSearchLimits,
+        diagnostics: bool,
+        progress: &BuildProgress,
+    ) -> Result<(Self, Box<[u64]>, BuildReport), MphfError> {
         let mut group_bits = vec![];
         let mut group_seeds = vec![];
         let mut level_groups = vec![];
+        let mut report = BuildReport { gamma, ..Default::default() };
+        progress.start_attempt(hashes.len());
 
         while !hashes.is_empty() {
+            if level_groups.len() == max_levels {
+                if !fallback {
+                    return Err(MaxLevelsExceeded);
+                }
+                hashes.sort_unstable();
+                if hashes.windows(2).any(|pair| pair[0] == pair[1]) {
+                    return Err(MaxLevelsExceeded);
+                }
+                report.fallback_keys = hashes.len();
+                break;
+            }
+
             let level = level_groups.len() as u32;
-            let (level_group_bits, level_group_seeds) = Self::build_level(level, &mut hashes, gamma);
+            let level_t0 = Instant::now();
+            let level_keys = hashes.len();
+            let (level_group_bits, level_group_seeds, level_diagnostics) =
+                Self::build_level(level, &mut hashes, gamma, limits, diagnostics, progress)?;
 
             group_bits.extend_from_slice(&level_group_bits);
             group_seeds.extend_from_slice(&level_group_seeds);
             level_groups.push(level_group_seeds.len() as u32);
-
-            if level_groups.len() == MAX_LEVELS && !hashes.is_empty() {
-                return Err(MaxLevelsExceeded);
-            }
+            report.levels.push(LevelReport {
+                duration: level_t0.elapsed(),
+                keys: level_keys,
+                retained_keys: hashes.len(),
+                diagnostics: level_diagnostics,
+            });
+            progress.level_built(hashes.len());
         }
 
-        Ok(Mphf {
-            ranked_bits: RankedBits::new(group_bits.into_boxed_slice()),
+        let mphf = Mphf {
+            ranked_bits: R::from_bits(group_bits.into_boxed_slice()),
             level_groups: level_groups.into_boxed_slice(),
             group_seeds: group_seeds.into_boxed_slice(),
+            extras: E::default(),
             _phantom_hasher: PhantomData,
-        })
+        };
+
+        Ok((mphf, hashes.into_boxed_slice(), report))
     }
 
-    /// Builds specified `level` using provided `hashes` and returns level group bits and seeds.
-    fn build_level(level: u32, hashes: &mut Vec<u64>, gamma: f32) -> (Vec<u64>, Vec<ST>) {
-        // compute level size (#bits storing non-collided hashes), number of groups and segments
-        let level_size = ((hashes.len() as f32) * gamma).ceil() as usize;
-        let (groups, segments) = Self::level_size_groups_segments(level_size);
-        let max_group_seed = 1 << S;
-
-        // Reserve x3 bits for all segments to reduce cache misses when updating/fetching group bits.
-        // Every 3 consecutive elements represent:
-        // - 0: hashes bits set for current seed
-        // - 1: hashes collision bits set for current seed
-        // - 2: hashes bits set for best seed
-        let mut group_bits = vec![0u64; 3 * segments + 3];
-        let mut best_group_seeds = vec![ST::zero(); groups];
-
-        // For each seed compute `group_bits` and then update those groups where seed produced less collisions
-        for group_seed in 0..max_group_seed {
-            Self::update_group_bits_with_seed(
-                level,
-                groups,
-                group_seed,
-                hashes,
-                &mut group_bits,
-                &mut best_group_seeds,
-            );
+    /// Builds specified `level` using provided `hashes` and returns level group bits and seeds,
+    /// along with the level diagnostics if `diagnostics` is set.
+    fn build_level(
+        level: u32,
+        hashes: &mut Vec<u64>,
+        gamma: f32,
+        limits: SeedSearchLimits,
+        diagnostics: bool,
+        progress: &BuildProgress,
+    ) -> Result<BuiltLevel<ST>, MphfError> {
+        // compute level size (#bits storing non-collided hashes) and number of groups
+        let level_size = ((hashes.len() as f32) * gamma).ceil();
+        if level_size >= usize::MAX as f32 {
+            return Err(LevelSizeOverflow);
         }
+        let (groups, _) = Self::level_size_groups_segments(level_size as usize).ok_or(LevelSizeOverflow)?;
+        let max_group_seed = limits.max_group_seed();
 
-        // finalize best group bits to be returned
-        let best_group_bits: Vec<u64> = group_bits[..group_bits.len() - 3]
-            .chunks_exact(3)
-            .map(|group_bits| group_bits[2])
-            .collect();
+        // Sort hashes by their group (`fastmod32` is monotonic), so that updating group bits for
+        // every seed below streams through memory instead of accessing it at random.
+        #[cfg(not(feature = "rayon"))]
+        hashes.sort_unstable_by_key(|&hash| hash_with_seed(hash, level) as u32);
+        #[cfg(feature = "rayon")]
+        hashes.par_sort_unstable_by_key(|&hash| hash_with_seed(hash, level) as u32);
+
+        #[cfg(not(feature = "rayon"))]
+        let (best_group_bits, best_group_seeds, seed_trials) =
+            Self::search_group_seeds(level, groups, 0..groups, hashes, max_group_seed, limits, progress)?;
+        #[cfg(feature = "rayon")]
+        let (best_group_bits, best_group_seeds, seed_trials) =
+            Self::par_search_group_seeds(level, groups, hashes, max_group_seed, limits, progress)?;
+        let best_bits = BitSlice::new(&best_group_bits);
+
+        let level_diagnostics = diagnostics
+            .then(|| Self::level_diagnostics(level, groups, hashes, &best_group_bits, &best_group_seeds, seed_trials));
 
         // filter out hashes which are already stored in `best_group_bits`
         hashes.retain(|&hash| {
@@ -143,125 +917,723 @@ impl<const B: usize, const S: usize, ST: PrimInt + Unsigned, H: Hasher + Default
             let group_seed = best_group_seeds[group_idx].to_u32().unwrap();
             let bit_idx = bit_index_for_seed::<B>(level_hash, group_seed, group_idx);
             // SAFETY: `bit_idx` is always within bounds (ensured during calculation)
-            *unsafe { best_group_bits.get_unchecked(bit_idx / 64) } & (1 << (bit_idx % 64)) == 0
+            !unsafe { best_bits.get_unchecked(bit_idx) }
         });
 
-        (best_group_bits, best_group_seeds)
+        Ok((best_group_bits, best_group_seeds, level_diagnostics))
     }
 
-    /// Returns number of groups and 64-bit segments for given `size`.
-    #[inline]
-    fn level_size_groups_segments(size: usize) -> (usize, usize) {
-        // Calculate the least common multiple of 64 and B
-        let lcm_value = Self::B.lcm(&64);
+    /// Returns diagnostics of `level` built from `hashes` into `group_bits` and `group_seeds` of
+    /// its `groups`, with `seed_trials` collected by the seed search.
+    fn level_diagnostics(
+        level: u32,
+        groups: usize,
+        hashes: &[u64],
+        group_bits: &[u64],
+        group_seeds: &[ST],
+        seed_trials: Vec<SeedTrial>,
+    ) -> LevelDiagnostics {
+        let mut group_keys = vec![0u32; groups];
+        for &hash in hashes {
+            group_keys[fastmod32(hash_with_seed(hash, level) as u32, groups as u32)] += 1;
+        }
 
-        // Adjust size to the nearest value that is a multiple of the LCM
-        let adjusted_size = size.div_ceil(lcm_value) * lcm_value;
+        let bits = BitSlice::new(group_bits);
+        let group_placed_keys: Vec<u32> = (0..groups)
+            .map(|group_idx| {
+                let bit_idx = (group_idx * Self::B) as u64;
+                // SAFETY: `bit_idx..bit_idx + B` is always within bounds of `groups` groups
+                (bit_idx..bit_idx + Self::B as u64)
+                    .filter(|&bit_idx| unsafe { bits.get_unchecked(bit_idx) })
+                    .count() as u32
+            })
+            .collect();
+
+        let mut seed_histogram = vec![];
+        for (&keys, seed) in group_keys.iter().zip(group_seeds) {
+            if keys > 0 {
+                let seed = seed.to_usize().unwrap();
+                if seed_histogram.len() <= seed {
+                    seed_histogram.resize(seed + 1, 0);
+                }
+                seed_histogram[seed] += 1;
+            }
+        }
 
-        (adjusted_size / Self::B, adjusted_size / 64)
+        LevelDiagnostics { seed_histogram, group_keys, group_placed_keys, seed_trials }
     }
 
-    /// Computes group bits for given seed and then updates those groups where seed produced least collisions.
-    #[inline]
-    fn update_group_bits_with_seed(
+    /// Finds the best seed for every group of `group_range` out of `groups` level groups, returning
+    /// best group bits and seeds of the range. All `hashes` must belong to groups of `group_range`,
+    /// which must start and end at 64-bit segment boundaries, and be sorted by their group. The
+    /// search stops after the first seed once `limits` deadline is exceeded. Also returns
+    /// statistics of the tried seeds, which are cheap to collect compared to the search itself.
+    fn search_group_seeds(
         level: u32,
         groups: usize,
-        group_seed: u32,
+        group_range: Range<usize>,
         hashes: &[u64],
-        group_bits: &mut [u64],
-        best_group_seeds: &mut [ST],
-    ) {
-        // Reset all group bits except best group bits
-        let group_bits_len = group_bits.len();
-        for bits in group_bits[..group_bits_len - 3].chunks_exact_mut(3) {
-            bits[0] = 0;
-            bits[1] = 0;
-        }
+        max_group_seed: u32,
+        limits: SeedSearchLimits,
+        progress: &BuildProgress,
+    ) -> Result<SearchedSeeds<ST>, MphfError> {
+        // Best group bits, bits for the current seed are computed per group in `group_scratch`
+        // holding bits set and collision bits set of every 64-bit chunk of the group.
+        let segments = group_range.len() * Self::B / 64;
+        let mut best_group_bits = InterleavedBits::<1>::new(segments);
+        let mut group_scratch = vec![0u64; 2 * Self::B.div_ceil(64)];
+        let mut best_group_seeds = vec![ST::zero(); group_range.len()];
+        let mut seed_trials = vec![];
 
-        // For each hash compute group bits and collision bits
+        // Pre-pass computing level hashes once instead of for every seed, along with the number of
+        // hashes of every group. Only the lower 32 bits of level hashes are used by seeds.
+        let mut level_hashes = Vec::with_capacity(hashes.len());
+        let mut group_ends = vec![0; group_range.len() + 1];
         for &hash in hashes {
             let level_hash = hash_with_seed(hash, level);
-            let group_idx = fastmod32(level_hash as u32, groups as u32);
-            let bit_idx = bit_index_for_seed::<B>(level_hash, group_seed, group_idx);
-            let mask = 1 << (bit_idx % 64);
-            let idx = (bit_idx / 64) * 3;
+            let group_idx = fastmod32(level_hash as u32, groups as u32) - group_range.start;
+            level_hashes.push(level_hash as u32);
+            group_ends[group_idx + 1] += 1;
+        }
+        for group_idx in 0..group_range.len() {
+            group_ends[group_idx + 1] += group_ends[group_idx];
+        }
+
+        // Groups are searched until their best seed reaches the maximum number of collision-free
+        // bits possible for their number of hashes, as no further seed can improve it. Empty groups
+        // are skipped altogether, keeping seed 0 and no bits.
+        let mut best_group_ones = vec![0; group_range.len()];
+        let mut active_groups: Vec<usize> = (0..group_range.len())
+            .filter(|&group_idx| group_ends[group_idx + 1] > group_ends[group_idx])
+            .collect();
 
-            // SAFETY: `idx` is always within bounds (ensured during calculation)
-            let bits = unsafe { group_bits.get_unchecked_mut(idx..idx + 2) };
+        // whole batches of seeds are tried with vector instructions if supported, the rest one by one
+        #[cfg(feature = "simd")]
+        let first_seed = if simd::has_seed_batches(Self::B) {
+            Self::search_seed_batches(
+                &level_hashes,
+                &group_ends,
+                max_group_seed,
+                limits,
+                progress,
+                &mut active_groups,
+                &mut best_group_ones,
+                &mut best_group_seeds,
+                &mut group_scratch,
+                &mut best_group_bits,
+                &mut seed_trials,
+            )?
+        } else {
+            0
+        };
+        #[cfg(not(feature = "simd"))]
+        let first_seed = 0;
 
-            bits[1] |= bits[0] & mask;
-            bits[0] |= mask;
+        for group_seed in first_seed..max_group_seed {
+            if active_groups.is_empty() || (group_seed > 0 && limits.is_exceeded()) {
+                break;
+            }
+            progress.check_cancelled()?;
+            let mut seed_trial = SeedTrial { groups: active_groups.len(), collided_keys: 0 };
+            for &group_idx in &active_groups {
+                let group_hashes = &level_hashes[group_ends[group_idx]..group_ends[group_idx + 1]];
+                let new_ones = Self::update_group_bits_with_seed(
+                    group_idx,
+                    group_seed,
+                    group_hashes,
+                    best_group_ones[group_idx],
+                    &mut group_scratch,
+                    &mut best_group_bits,
+                );
+                seed_trial.collided_keys += group_hashes.len() - new_ones as usize;
+                if new_ones > best_group_ones[group_idx] {
+                    best_group_ones[group_idx] = new_ones;
+                    best_group_seeds[group_idx] = ST::from(group_seed).unwrap();
+                }
+            }
+            active_groups.retain(|&group_idx| {
+                let group_len = (group_ends[group_idx + 1] - group_ends[group_idx]) as u32;
+                best_group_ones[group_idx] < Self::max_group_ones(group_len)
+            });
+            seed_trials.push(seed_trial);
         }
 
-        // Filter out collided bits from group bits
-        for bits in group_bits.chunks_exact_mut(3) {
-            bits[0] &= !bits[1];
+        // finalize best group bits to be returned
+        Ok((best_group_bits.into_lane(0), best_group_seeds, seed_trials))
+    }
+
+    /// Tries whole batches of `simd::SEED_BATCH` seeds below `max_group_seed` for `active_groups`
+    /// of [`Mphf::search_group_seeds`] with vector instructions, returning the first seed left to
+    /// try. Seeds of a batch are replayed in order, so that groups resolved by a seed skip the
+    /// following ones and best seeds and seed trials are the same as if seeds were tried one by
+    /// one, except for the deadline of `limits`, which is only checked between batches.
+    #[cfg(feature = "simd")]
+    #[allow(clippy::too_many_arguments)]
+    fn search_seed_batches(
+        level_hashes: &[u32],
+        group_ends: &[usize],
+        max_group_seed: u32,
+        limits: SeedSearchLimits,
+        progress: &BuildProgress,
+        active_groups: &mut Vec<usize>,
+        best_group_ones: &mut [u32],
+        best_group_seeds: &mut [ST],
+        scratch: &mut [u64],
+        best_group_bits: &mut InterleavedBits<1>,
+        seed_trials: &mut Vec<SeedTrial>,
+    ) -> Result<u32, MphfError> {
+        let mut first_seed = 0;
+        let mut batch_ones = [0; simd::SEED_BATCH];
+        while first_seed + simd::SEED_BATCH as u32 <= max_group_seed {
+            if active_groups.is_empty() || (first_seed > 0 && limits.is_exceeded()) {
+                break;
+            }
+            progress.check_cancelled()?;
+            let mut batch_trials = [SeedTrial::default(); simd::SEED_BATCH];
+            for &group_idx in active_groups.iter() {
+                let group_hashes = &level_hashes[group_ends[group_idx]..group_ends[group_idx + 1]];
+                let (best_ones, max_ones) = (
+                    best_group_ones[group_idx],
+                    Self::max_group_ones(group_hashes.len() as u32),
+                );
+                simd::seed_batch_ones(group_hashes, first_seed, Self::B as u32, &mut batch_ones);
+                for ((group_seed, &new_ones), seed_trial) in (first_seed..).zip(&batch_ones).zip(&mut batch_trials) {
+                    seed_trial.groups += 1;
+                    seed_trial.collided_keys += group_hashes.len() - new_ones as usize;
+                    if new_ones > best_group_ones[group_idx] {
+                        best_group_ones[group_idx] = new_ones;
+                        best_group_seeds[group_idx] = ST::from(group_seed).unwrap();
+                    }
+                    if best_group_ones[group_idx] >= max_ones {
+                        break;
+                    }
+                }
+                // only bits of the best seed of the batch are computed and stored
+                if best_group_ones[group_idx] > best_ones {
+                    Self::update_group_bits_with_seed(
+                        group_idx,
+                        best_group_seeds[group_idx].to_u32().unwrap(),
+                        group_hashes,
+                        best_ones,
+                        scratch,
+                        best_group_bits,
+                    );
+                }
+            }
+            active_groups.retain(|&group_idx| {
+                let group_len = (group_ends[group_idx + 1] - group_ends[group_idx]) as u32;
+                best_group_ones[group_idx] < Self::max_group_ones(group_len)
+            });
+            seed_trials.extend(batch_trials.into_iter().take_while(|seed_trial| seed_trial.groups > 0));
+            first_seed += simd::SEED_BATCH as u32;
         }
+        Ok(first_seed)
+    }
+
+    /// Same as [`Mphf::search_group_seeds`] for all level groups, but splits groups into ranges
+    /// searched in parallel on the current `rayon` thread pool. Groups are independent of each
+    /// other, so the result is the same as of the sequential search regardless of the pool size.
+    #[cfg(feature = "rayon")]
+    fn par_search_group_seeds(
+        level: u32,
+        groups: usize,
+        hashes: &[u64],
+        max_group_seed: u32,
+        limits: SeedSearchLimits,
+        progress: &BuildProgress,
+    ) -> Result<SearchedSeeds<ST>, MphfError> {
+        // ranges are made of whole blocks of groups aligned to 64-bit segments
+        let block_groups = Self::B.lcm(&64) / Self::B;
+        let blocks = groups / block_groups;
+        let ranges = if hashes.len() < PAR_MIN_LEVEL_KEYS {
+            1
+        } else {
+            (rayon::current_num_threads() * PAR_RANGES_PER_THREAD).min(blocks)
+        };
+        let range_blocks = blocks.div_ceil(ranges);
+
+        let group_idx = |hash: u64| fastmod32(hash_with_seed(hash, level) as u32, groups as u32);
+        let results = (0..blocks)
+            .step_by(range_blocks)
+            .map(|block_idx| block_idx * block_groups..(block_idx + range_blocks).min(blocks) * block_groups)
+            .collect::<Vec<_>>()
+            .into_par_iter()
+            .map(|group_range| {
+                // hashes are sorted by their group
+                let start = hashes.partition_point(|&hash| group_idx(hash) < group_range.start);
+                let end = hashes.partition_point(|&hash| group_idx(hash) < group_range.end);
+                let (range_group_bits, range_group_seeds, range_seed_trials) = Self::search_group_seeds(
+                    level,
+                    groups,
+                    group_range,
+                    &hashes[start..end],
+                    max_group_seed,
+                    limits,
+                    progress,
+                )?;
+                // seed type isn't required to be `Send`, seeds are passed back as `u32` instead
+                let range_group_seeds: Vec<u32> = range_group_seeds.iter().map(|seed| seed.to_u32().unwrap()).collect();
+                Ok((range_group_bits, range_group_seeds, range_seed_trials))
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let mut best_group_bits = Vec::with_capacity(groups * Self::B / 64);
+        let mut best_group_seeds = Vec::with_capacity(groups);
+        let mut seed_trials = vec![];
+        for (range_group_bits, range_group_seeds, range_seed_trials) in results {
+            best_group_bits.extend_from_slice(&range_group_bits);
+            best_group_seeds.extend(range_group_seeds.into_iter().map(|seed| ST::from(seed).unwrap()));
+            SeedTrial::merge(&mut seed_trials, &range_seed_trials);
+        }
+
+        Ok((best_group_bits, best_group_seeds, seed_trials))
+    }
+
+    /// Returns `true` if every hash of `hashes` is mapped to a unique index in `0..hashes.len()`.
+    fn is_bijection(&self, hashes: &[u64]) -> bool {
+        let mut seen = vec![false; hashes.len()];
+        hashes.iter().all(|&hash| match self.get_by_hash(hash) {
+            Some(idx) if idx < seen.len() => !std::mem::replace(&mut seen[idx], true),
+            _ => false,
+        })
+    }
+
+    /// Returns number of groups and 64-bit segments for given `size`, or `None` if the level of
+    /// that size can't be addressed on the current target.
+    #[inline]
+    fn level_size_groups_segments(size: usize) -> Option<(usize, usize)> {
+        // Calculate the least common multiple of 64 and B
+        let lcm_value = Self::B.lcm(&64);
 
-        // Update best group bits and seeds
-        for (group_idx, best_group_seed) in best_group_seeds.iter_mut().enumerate() {
-            let bit_idx = group_idx * Self::B;
-            let bit_pos = bit_idx % 64;
-            let idx = (bit_idx / 64) * 3;
+        // Adjust size to the nearest value that is a multiple of the LCM
+        let adjusted_size = size.div_ceil(lcm_value).checked_mul(lcm_value)?;
+        let groups = adjusted_size / Self::B;
 
-            // SAFETY: `idx` is always within bounds (ensured during calculation)
-            let bits = unsafe { group_bits.get_unchecked_mut(idx..idx + 6) };
+        // groups are indexed with `u32` during both construction and queries
+        if groups > u32::MAX as usize {
+            return None;
+        }
 
-            let bits_1 = Self::B.min(64 - bit_pos);
-            let bits_2 = Self::B - bits_1;
-            let mask_1 = u64::MAX >> (64 - bits_1);
-            let mask_2 = (1 << bits_2) - 1;
+        Some((groups, adjusted_size / 64))
+    }
 
-            let new_bits_1 = (bits[0] >> bit_pos) & mask_1;
-            let new_bits_2 = bits[3] & mask_2;
-            let new_ones = new_bits_1.count_ones() + new_bits_2.count_ones();
+    /// Returns the maximum number of collision-free bits of a group with `group_len` hashes: all of
+    /// them if they fit into the group, otherwise at most `B - 1`, as all `B` bits being
+    /// collision-free would take exactly `B` hashes.
+    #[inline]
+    fn max_group_ones(group_len: u32) -> u32 {
+        if group_len <= Self::B as u32 {
+            group_len
+        } else {
+            Self::B as u32 - 1
+        }
+    }
 
-            let best_bits_1 = (bits[2] >> bit_pos) & mask_1;
-            let best_bits_2 = bits[5] & mask_2;
-            let best_ones = best_bits_1.count_ones() + best_bits_2.count_ones();
+    /// Computes collision-free bits of group `group_idx` for given seed from lower 32 bits of
+    /// `level_hashes` of the group using `scratch`, storing them as the best group bits if there are
+    /// more than `best_ones` of them, and returns their number. Groups are indexed relative to the
+    /// first group of `best_group_bits`.
+    #[inline]
+    fn update_group_bits_with_seed(
+        group_idx: usize,
+        group_seed: u32,
+        level_hashes: &[u32],
+        best_ones: u32,
+        scratch: &mut [u64],
+        best_group_bits: &mut InterleavedBits<1>,
+    ) -> u32 {
+        scratch.fill(0);
 
-            if new_ones > best_ones {
-                bits[2] &= !(mask_1 << bit_pos);
-                bits[2] |= new_bits_1 << bit_pos;
+        // For each hash compute group bits and collision bits
+        for &level_hash in level_hashes {
+            let bit_idx = bit_index_for_seed::<B>(level_hash as u64, group_seed, 0) as usize;
+            let (word_idx, mask) = (bit_idx / 64 * 2, 1 << (bit_idx % 64));
+            scratch[word_idx + 1] |= scratch[word_idx] & mask;
+            scratch[word_idx] |= mask;
+        }
 
-                bits[5] &= !mask_2;
-                bits[5] |= new_bits_2;
+        // Count group bits without collided bits
+        let new_ones = scratch
+            .chunks_exact(2)
+            .map(|bits| (bits[0] & !bits[1]).count_ones())
+            .sum();
 
-                *best_group_seed = ST::from(group_seed).unwrap();
+        // Update best group bits, groups larger than 64 bits are written in chunks of at most 64 bits
+        if new_ones > best_ones {
+            let bit_idx = group_idx as u64 * Self::B as u64;
+            for (offset, bits) in (0..Self::B).step_by(64).zip(scratch.chunks_exact(2)) {
+                // SAFETY: chunk is always within bounds (ensured during calculation)
+                unsafe {
+                    best_group_bits.write_unchecked(
+                        0,
+                        bit_idx + offset as u64,
+                        (Self::B - offset).min(64),
+                        bits[0] & !bits[1],
+                    );
+                }
             }
         }
+
+        new_ones
     }
 
     /// Returns the index associated with `key`, within 0 to the key collection size (exclusive).
     /// If `key` was not in the initial collection, returns `None` or an arbitrary value from the range.
     #[inline]
     pub fn get<K: Hash + ?Sized>(&self, key: &K) -> Option<usize> {
-        Self::get_impl(key, &self.level_groups, &self.group_seeds, &self.ranked_bits)
+        self.get_by_hash(hash_key_seeded::<H, _>(self.extras.hash_seed(), key))
     }
 
-    /// Inner implementation of `get` with `level_groups`, `group_seeds` and `ranked_bits` passed
-    /// from standard and `Archived` version of `Mphf`.
+    /// Same as [`Mphf::get`], but re-uses the hash precomputed by `HashedKey`. Returns `None` if
+    /// `key` was hashed with another hash seed than the one of the MPHF.
+    ///
+    /// # Examples
+    /// ```
+    /// use entropy_map::{HashedKey, Mphf, DEFAULT_GAMMA};
+    ///
+    /// let mphf = Mphf::<32, 8>::from_slice(&[1, 2, 3], DEFAULT_GAMMA).unwrap();
+    /// assert_eq!(mphf.get_hashed(&HashedKey::new(&1)), mphf.get(&1));
+    /// ```
     #[inline]
-    fn get_impl<K: Hash + ?Sized>(
-        key: &K,
+    pub fn get_hashed<K: ?Sized>(&self, key: &HashedKey<K, H>) -> Option<usize> {
+        // hashes computed with another seed would map to arbitrary indices
+        if key.hash_seed() != self.extras.hash_seed() {
+            return None;
+        }
+        self.get_by_hash(key.hash())
+    }
+
+    /// Returns the seed hashed before every key, 0 for unkeyed hashing. Hashes passed to `*_hashed`
+    /// and `*_with_hash` query methods must be computed with it, e.g. via [`HashedKey::with_seed`].
+    ///
+    /// # Examples
+    /// ```
+    /// # use entropy_map::{BuildPolicy, HashedKey, MphfWithExtras};
+    /// let policy = BuildPolicy::keyed();
+    /// let (mphf, _) = MphfWithExtras::<32, 8>::from_slice_with_policy(&[1, 2, 3], &policy).unwrap();
+    /// assert_eq!(mphf.hash_seed(), policy.hash_seed);
+    /// assert_eq!(mphf.get_hashed(&HashedKey::with_seed(&1, mphf.hash_seed())), mphf.get(&1));
+    /// ```
+    #[inline]
+    pub fn hash_seed(&self) -> u64 {
+        self.extras.hash_seed()
+    }
+
+    /// Returns the seed mixed into key hashes, see [`BuildPolicy::build_seed`].
+    #[inline]
+    pub fn build_seed(&self) -> u64 {
+        self.extras.build_seed()
+    }
+
+    /// Returns the `KeyFilter` checked before walking the levels, if built with
+    /// [`BuildPolicy::with_key_filter`].
+    #[inline]
+    pub fn key_filter(&self) -> Option<&KeyFilter> {
+        self.extras.key_filter()
+    }
+
+    /// Returns the policy re-building a structure from the same keys with the same hash seed, key
+    /// filter and fallback table, e.g. after adding keys.
+    #[inline]
+    pub(crate) fn rebuild_policy(&self) -> BuildPolicy {
+        let policy = BuildPolicy::fixed(DEFAULT_GAMMA)
+            .with_hash_seed(self.hash_seed())
+            .with_build_seed(self.build_seed())
+            .with_key_filter(self.key_filter().map_or(0, KeyFilter::bits_per_key));
+        BuildPolicy { fallback: self.fallback_len() > 0, ..policy }
+    }
+
+    /// Returns the number of keys stored in the fallback table, see [`BuildPolicy::with_fallback`].
+    #[inline]
+    pub fn fallback_len(&self) -> usize {
+        self.extras.fallback().len()
+    }
+
+    /// Returns the hash of `key` computed with hasher `H` and the hash seed, which can be passed to
+    /// `get_by_hash` of every MPHF sharing them.
+    #[inline]
+    pub fn hash_key<K: Hash + ?Sized>(&self, key: &K) -> u64 {
+        hash_key_seeded::<H, _>(self.extras.hash_seed(), key)
+    }
+
+    /// Returns the index associated with the key `hash`, either precomputed for
+    /// [`Mphf::from_hashes`] or computed with hasher `H` and the hash seed, so that a key looked up
+    /// in several structures is hashed only once.
+    ///
+    /// Non-existing key hashes return either `None` or an arbitrary index in `0..n` range.
+    ///
+    /// # Examples
+    /// ```
+    /// use entropy_map::{Mphf, DEFAULT_GAMMA};
+    ///
+    /// let allowed = Mphf::<32, 8>::from_slice(&["a", "b", "c"], DEFAULT_GAMMA).unwrap();
+    /// let tagged = Mphf::<32, 8>::from_slice(&["b", "d"], DEFAULT_GAMMA).unwrap();
+    ///
+    /// let hash = allowed.hash_key("b");
+    /// assert_eq!(allowed.get_by_hash(hash), allowed.get("b"));
+    /// assert_eq!(tagged.get_by_hash(hash), tagged.get("b"));
+    /// ```
+    #[inline]
+    pub fn get_by_hash(&self, hash: u64) -> Option<usize> {
+        self.extras.get_by_hash(hash, |hash| {
+            get_levels(hash, B, &self.level_groups, &self.group_seeds, &self.ranked_bits)
+        })
+    }
+
+    /// Writes the indices associated with `keys` into `out`, same as calling `get` for every key.
+    ///
+    /// Keys are processed in small batches, first hashing them and prefetching their first level
+    /// group seeds, then prefetching their bits, so that cache misses of the batch overlap instead
+    /// of stalling every lookup one by one. Panics if `keys` and `out` lengths differ.
+    ///
+    /// # Examples
+    /// ```
+    /// # use entropy_map::{Mphf, DEFAULT_GAMMA};
+    /// let mphf = Mphf::<32, 8>::from_slice(&[1, 2, 3], DEFAULT_GAMMA).unwrap();
+    /// let mut out = [None; 3];
+    /// mphf.get_many(&[3, 2, 1], &mut out);
+    /// assert_eq!(out, [mphf.get(&3), mphf.get(&2), mphf.get(&1)]);
+    /// ```
+    pub fn get_many<K: Hash>(&self, keys: &[K], out: &mut [Option<usize>]) {
+        assert_eq!(keys.len(), out.len(), "`keys` and `out` lengths differ");
+        let mut hashes = [0; GET_MANY_BATCH_LEN];
+        for (keys, out) in keys.chunks(GET_MANY_BATCH_LEN).zip(out.chunks_mut(GET_MANY_BATCH_LEN)) {
+            let hashes = &mut hashes[..keys.len()];
+            for (hash, key) in hashes.iter_mut().zip(keys) {
+                *hash = self.hash_key(key);
+            }
+            Self::prefetch_impl(
+                hashes,
+                self.extras.build_seed(),
+                &self.level_groups,
+                &self.group_seeds,
+                &self.ranked_bits,
+            );
+            for (idx, &hash) in out.iter_mut().zip(hashes.iter()) {
+                *idx = self.get_by_hash(hash);
+            }
+        }
+    }
+
+    /// Prefetches first level group seeds of key `hashes` mixed with `build_seed` and then the
+    /// bits they select, with `level_groups`, `group_seeds` and `ranked_bits` passed from standard
+    /// and `Archived` version of `Mphf`.
+    #[inline]
+    pub(crate) fn prefetch_impl(
+        hashes: &[u64],
+        build_seed: u64,
         level_groups: &[u32],
         group_seeds: &[ST],
         ranked_bits: &impl RankedBitsAccess,
-    ) -> Option<usize> {
+    ) {
+        let Some(&groups) = level_groups.first() else {
+            return;
+        };
+        for &hash in hashes {
+            access::prefetch(
+                group_seeds,
+                fastmod32(hash_with_seed(hash ^ build_seed, 0) as u32, groups),
+            );
+        }
+        for &hash in hashes {
+            let level_hash = hash_with_seed(hash ^ build_seed, 0);
+            let group_idx = fastmod32(level_hash as u32, groups);
+            // SAFETY: `group_idx` is always within bounds of the first level
+            let group_seed = unsafe { access::get(group_seeds, group_idx).to_u32().unwrap() };
+            ranked_bits.prefetch(bit_index_for_seed::<B>(level_hash, group_seed, group_idx));
+        }
+    }
+
+    /// Returns the location of the bit set for output index `idx`, i.e. the inverse of
+    /// [`Mphf::get`], or `None` if `idx` is not less than the key collection size or belongs to a
+    /// key of the fallback table.
+    ///
+    /// # Examples
+    /// ```
+    /// # use entropy_map::{Mphf, DEFAULT_GAMMA};
+    /// let mphf = Mphf::<32, 8>::from_slice(&[1, 2, 3], DEFAULT_GAMMA).unwrap();
+    /// let loc = mphf.locate(mphf.get(&1).unwrap()).unwrap();
+    /// assert_eq!(loc.bit_idx, loc.group_idx as u64 * 32 + loc.bit_in_group as u64);
+    /// assert!(loc.level < mphf.level_groups().len());
+    /// assert_eq!(mphf.locate(3), None);
+    /// ```
+    #[inline]
+    pub fn locate(&self, idx: usize) -> Option<BitLocation> {
+        Self::locate_impl(
+            idx.checked_sub(self.extras.fallback().len())?,
+            &self.level_groups,
+            &self.ranked_bits,
+        )
+    }
+
+    /// Inner implementation of `locate` with `level_groups` and `ranked_bits` passed from standard
+    /// and `Archived` version of `Mphf`.
+    pub(crate) fn locate_impl(
+        idx: usize,
+        level_groups: &[u32],
+        ranked_bits: &impl RankedBitsAccess,
+    ) -> Option<BitLocation> {
+        let bit_idx = ranked_bits.select(idx)?;
+        let group_idx = (bit_idx / B as u64) as usize;
+
         let mut groups_before = 0;
         for (level, &groups) in level_groups.iter().enumerate() {
-            let level_hash = hash_with_seed(hash_key::<H, _>(key), level as u32);
-            let group_idx = groups_before + fastmod32(level_hash as u32, groups);
-            // SAFETY: `group_idx` is always within bounds (ensured during calculation)
-            let group_seed = unsafe { group_seeds.get_unchecked(group_idx).to_u32().unwrap() };
-            let bit_idx = bit_index_for_seed::<B>(level_hash, group_seed, group_idx);
-            if let Some(rank) = ranked_bits.rank(bit_idx) {
-                return Some(rank);
-            }
             groups_before += groups as usize;
+            if group_idx < groups_before {
+                let bit_in_group = (bit_idx % B as u64) as usize;
+                return Some(BitLocation { bit_idx, level, group_idx, bit_in_group });
+            }
+        }
+
+        // bits past the last group are padding and never set
+        None
+    }
+
+    /// Returns the ranked bit vector of `Mphf`, e.g. to inspect its
+    /// [`size_breakdown`](RankedBits::size_breakdown).
+    ///
+    /// # Examples
+    /// ```
+    /// # use entropy_map::{Mphf, DEFAULT_GAMMA};
+    /// let mphf = Mphf::<32, 8>::from_slice(&[1, 2, 3], DEFAULT_GAMMA).unwrap();
+    /// let breakdown = mphf.ranked_bits().size_breakdown();
+    /// assert!(breakdown.rank_bytes <= mphf.size());
+    /// ```
+    #[inline]
+    pub fn ranked_bits(&self) -> &R {
+        &self.ranked_bits
+    }
+
+    /// Returns the number of groups at each level, levels being ordered from first to last.
+    ///
+    /// # Examples
+    /// ```
+    /// # use entropy_map::{Mphf, DEFAULT_GAMMA};
+    /// let mphf = Mphf::<32, 8>::from_slice(&[1, 2, 3], DEFAULT_GAMMA).unwrap();
+    /// let groups: u32 = mphf.level_groups().iter().sum();
+    /// assert!(mphf.group_seed(groups as usize - 1).is_some());
+    /// assert!(mphf.group_seed(groups as usize).is_none());
+    /// ```
+    #[inline]
+    pub fn level_groups(&self) -> &[u32] {
+        &self.level_groups
+    }
+
+    /// Returns the seed chosen for the group at `group_idx`, groups being numbered consecutively
+    /// across all levels, or `None` if `group_idx` is out of bounds.
+    #[inline]
+    pub fn group_seed(&self, group_idx: usize) -> Option<u32> {
+        self.group_seeds.get(group_idx).and_then(|seed| seed.to_u32())
+    }
+
+    /// Returns the length of the underlying bit vector in bits, which holds `B` bits per group
+    /// rounded up to a multiple of 64.
+    ///
+    /// # Examples
+    /// ```
+    /// # use entropy_map::{Mphf, DEFAULT_GAMMA};
+    /// let mphf = Mphf::<32, 8>::from_slice(&[1, 2, 3], DEFAULT_GAMMA).unwrap();
+    /// let groups: u32 = mphf.level_groups().iter().sum();
+    /// assert_eq!(mphf.bits_len(), (32 * groups as u64).next_multiple_of(64));
+    /// ```
+    #[inline]
+    pub fn bits_len(&self) -> u64 {
+        self.ranked_bits.bits_len()
+    }
+
+    /// Returns statistics describing the quality of the MPHF, e.g. for applications to log them or
+    /// alert when a rebuilt structure is larger or deeper than expected. Keys placed at every level
+    /// are counted with `select` queries, in `O(levels * log(n))`.
+    ///
+    /// # Examples
+    /// ```
+    /// # use entropy_map::{Mphf, DEFAULT_GAMMA};
+    /// let keys: Vec<u64> = (0..10_000).collect();
+    /// let mphf = Mphf::<32, 8>::from_slice(&keys, DEFAULT_GAMMA).unwrap();
+    /// let stats = mphf.stats();
+    /// assert_eq!(stats.keys, 10_000);
+    /// assert_eq!(stats.keys_per_level.iter().sum::<usize>(), 10_000);
+    /// assert_eq!(stats.levels, mphf.level_groups().len());
+    /// assert!(stats.avg_query_levels >= 1.0 && stats.avg_query_levels < 2.0);
+    /// ```
+    pub fn stats(&self) -> MphfStats {
+        let mut keys_per_level = Vec::with_capacity(self.level_groups.len());
+        let (mut groups_end, mut keys_before) = (0u64, 0);
+        for &groups in self.level_groups.iter() {
+            groups_end += groups as u64;
+            let keys_end = ones_before(&self.ranked_bits, groups_end * B as u64);
+            keys_per_level.push(keys_end - keys_before);
+            keys_before = keys_end;
+        }
+
+        let keys = keys_before + self.fallback_len();
+        let query_levels: usize = keys_per_level
+            .iter()
+            .enumerate()
+            .map(|(level, &level_keys)| (level + 1) * level_keys)
+            .sum();
+        let (bits_per_key, avg_query_levels) = if keys == 0 {
+            (0.0, 0.0)
+        } else {
+            (
+                (self.size() * 8) as f32 / keys as f32,
+                query_levels as f32 / keys as f32,
+            )
+        };
+
+        MphfStats {
+            keys,
+            bits_per_key,
+            levels: self.level_groups.len(),
+            groups_per_level: self.level_groups.to_vec(),
+            keys_per_level,
+            avg_query_levels,
+            build_seed_trials: self
+                .group_seeds
+                .iter()
+                .map(|seed| seed.to_u64().map_or(0, |seed| seed + 1))
+                .sum(),
         }
+    }
+
+    /// Returns the byte slices read by queries reaching `level`, i.e. its part of the ranked bit
+    /// vector and its group seeds, or `None` if `level` is out of bounds.
+    ///
+    /// Levels are stored one after another within each array, so in a memory-mapped archive the
+    /// pages of deeper levels, only reached by keys colliding at every level before, stay on disk
+    /// until queried, see [`ArchivedMphf::prefault_levels`].
+    ///
+    /// # Examples
+    /// ```
+    /// # use entropy_map::{Mphf, DEFAULT_GAMMA};
+    /// let keys: Vec<u64> = (0..10_000).collect();
+    /// let mphf = Mphf::<32, 8>::from_slice(&keys, DEFAULT_GAMMA).unwrap();
+    /// let level_0: usize = mphf.level_sections(0).unwrap().iter().map(|section| section.len()).sum();
+    /// let level_1: usize = mphf.level_sections(1).unwrap().iter().map(|section| section.len()).sum();
+    /// assert!(level_1 < level_0);
+    /// assert!(mphf.level_sections(mphf.level_groups().len()).is_none());
+    /// ```
+    pub fn level_sections(&self, level: usize) -> Option<Vec<&[u8]>>
+    where
+        R: RankedBitsSections,
+    {
+        Self::level_sections_impl(level, &self.level_groups, &self.group_seeds, &self.ranked_bits)
+    }
+
+    /// Inner implementation of `level_sections` with `level_groups`, `group_seeds` and
+    /// `ranked_bits` passed from standard and `Archived` version of `Mphf`.
+    fn level_sections_impl<'a>(
+        level: usize,
+        level_groups: &[u32],
+        group_seeds: &'a [ST],
+        ranked_bits: &'a impl RankedBitsSections,
+    ) -> Option<Vec<&'a [u8]>> {
+        let groups_before: usize = level_groups.get(..level)?.iter().map(|&groups| groups as usize).sum();
+        let groups = groups_before..groups_before + *level_groups.get(level)? as usize;
 
-        None
+        let mut sections = ranked_bits.bits_sections((groups.start * B) as u64..(groups.end * B) as u64);
+        sections.push(rank::as_bytes(group_seeds.get(groups)?));
+        Some(sections)
     }
 
     /// Returns the total number of bytes occupied by `Mphf`
@@ -270,20 +1642,138 @@ impl<const B: usize, const S: usize, ST: PrimInt + Unsigned, H: Hasher + Default
             + size_of_val(self.level_groups.as_ref())
             + size_of_val(self.group_seeds.as_ref())
             + self.ranked_bits.size()
+            + self.extras.heap_size()
+    }
+
+    /// Checks that there is a seed fitting into `S` bits per group, that the bit vector covers all
+    /// groups with padding bits past them unset and that rank metadata matches the bits.
+    ///
+    /// # Examples
+    /// ```
+    /// # use entropy_map::{Mphf, DEFAULT_GAMMA};
+    /// let mphf = Mphf::<32, 8>::from_slice(&[1, 2, 3], DEFAULT_GAMMA).unwrap();
+    /// assert_eq!(mphf.debug_check_invariants(), Ok(()));
+    /// ```
+    pub fn debug_check_invariants(&self) -> Result<(), InvariantError> {
+        let groups: u64 = self.level_groups.iter().map(|&groups| groups as u64).sum();
+        let groups_bits = groups * B as u64;
+        if self.group_seeds.len() as u64 != groups || self.ranked_bits.bits_len() < groups_bits {
+            return Err(InvariantError::GroupsMismatch);
+        }
+
+        let max_group_seed = 1u64 << S;
+        if let Some(group_idx) = self
+            .group_seeds
+            .iter()
+            .position(|seed| seed.to_u64().is_none_or(|seed| seed >= max_group_seed))
+        {
+            return Err(InvariantError::SeedOutOfRange { group_idx });
+        }
+
+        self.ranked_bits.debug_check_invariants()?;
+
+        if self.extras.fallback().windows(2).any(|pair| pair[0] >= pair[1]) {
+            return Err(InvariantError::FallbackUnsorted);
+        }
+
+        match (groups_bits..self.ranked_bits.bits_len()).find(|&bit_idx| self.ranked_bits.rank(bit_idx).is_some()) {
+            Some(bit_idx) => Err(InvariantError::PaddingBitSet { bit_idx }),
+            None => Ok(()),
+        }
+    }
+}
+
+/// Returns the number of bits set in `ranked_bits` before bit `bit_idx`, binary searching the
+/// smallest rank selecting a bit at or past it.
+fn ones_before(ranked_bits: &impl RankedBitsAccess, bit_idx: u64) -> usize {
+    let (mut lo, mut hi) = (0, bit_idx as usize);
+    while lo < hi {
+        let mid = lo + (hi - lo) / 2;
+        if ranked_bits.select(mid).is_some_and(|selected| selected < bit_idx) {
+            lo = mid + 1;
+        } else {
+            hi = mid;
+        }
     }
+    lo
 }
 
-/// Computes a 64-bit hash for the given key using the default hasher `H`.
+/// Returns whether any two of `hashes` are equal.
+fn has_duplicate_hashes(hashes: &[u64]) -> bool {
+    let mut hashes = hashes.to_vec();
+    #[cfg(not(feature = "rayon"))]
+    hashes.sort_unstable();
+    #[cfg(feature = "rayon")]
+    hashes.par_sort_unstable();
+    hashes.windows(2).any(|pair| pair[0] == pair[1])
+}
+
+/// Computes a 64-bit hash for the given key using the default hasher `H`, which hashes
+/// `hash_seed` before the key unless it's 0.
 #[inline]
-fn hash_key<H: Hasher + Default, T: Hash + ?Sized>(key: &T) -> u64 {
+pub(crate) fn hash_key_seeded<H: Hasher + Default, T: Hash + ?Sized>(hash_seed: u64, key: &T) -> u64 {
     let mut hasher = H::default();
+    if hash_seed != 0 {
+        hasher.write_u64(hash_seed);
+    }
     key.hash(&mut hasher);
     hasher.finish()
 }
 
+/// Inner implementation of `get` of `MphfDyn` with key `hash` mixed with `build_seed` searched in
+/// the `fallback` table before walking the levels of `b`-bit groups, see `search_fallback`.
+#[inline(always)]
+pub(crate) fn get_with_fallback<T: PrimInt>(
+    hash: u64,
+    build_seed: u64,
+    b: usize,
+    fallback: &[u64],
+    level_groups: &[u32],
+    group_seeds: &[T],
+    ranked_bits: &impl RankedBitsAccess,
+) -> Option<usize> {
+    search_fallback(hash ^ build_seed, fallback, |hash| {
+        get_levels(hash, b, level_groups, group_seeds, ranked_bits)
+    })
+}
+
+/// Inner implementation of `get` walking the levels of `b`-bit groups for key `hash`. The group
+/// size is passed at runtime so that `MphfDyn` shares the query logic, while `Mphf` passes `B`,
+/// which is constant-folded once inlined.
+#[inline(always)]
+pub(crate) fn get_levels<T: PrimInt>(
+    hash: u64,
+    b: usize,
+    level_groups: &[u32],
+    group_seeds: &[T],
+    ranked_bits: &impl RankedBitsAccess,
+) -> Option<usize> {
+    let mut groups_before = 0;
+    for (level, &groups) in level_groups.iter().enumerate() {
+        let level_hash = hash_with_seed(hash, level as u32);
+        let group_idx = groups_before + fastmod32(level_hash as u32, groups);
+        // SAFETY: `group_idx` is always within bounds (ensured during calculation)
+        let group_seed = unsafe { access::get(group_seeds, group_idx).to_u32().unwrap() };
+        let bit_idx = bit_index_for_group_size(level_hash, group_seed, group_idx, b);
+        if let Some(rank) = ranked_bits.rank(bit_idx) {
+            return Some(rank);
+        }
+        groups_before += groups as usize;
+    }
+
+    None
+}
+
 /// Computes bit index based on `hash`, `group_seed`, `groups_before` and const `B`.
+/// Bit index is `u64` to avoid overflows on 32-bit targets for bit vectors larger than 2^32 bits.
 #[inline]
-fn bit_index_for_seed<const B: usize>(hash: u64, group_seed: u32, groups_before: usize) -> usize {
+fn bit_index_for_seed<const B: usize>(hash: u64, group_seed: u32, groups_before: usize) -> u64 {
+    bit_index_for_group_size(hash, group_seed, groups_before, B)
+}
+
+/// Computes bit index based on `hash`, `group_seed`, `groups_before` and group size `b`.
+#[inline(always)]
+pub(crate) fn bit_index_for_group_size(hash: u64, group_seed: u32, groups_before: usize, b: usize) -> u64 {
     // Take the lower 32 bits of the hash and XOR with the group_seed
     let mut x = (hash as u32) ^ group_seed;
 
@@ -292,7 +1782,7 @@ fn bit_index_for_seed<const B: usize>(hash: u64, group_seed: u32, groups_before:
     x = (x ^ (x >> 13)).wrapping_mul(0xc2b2ae35);
     x ^= x >> 16;
 
-    groups_before * B + fastmod32(x, B as u32)
+    (groups_before as u64) * (b as u64) + fastmod32(x, b as u32) as u64
 }
 
 /// Combines a 64-bit hash with a 32-bit seed, then multiplies by a prime constant to enhance hash uniformity and reduces the result back to 64 bits.
@@ -311,24 +1801,158 @@ fn fastmod32(x: u32, n: u32) -> usize {
 
 /// Implement `get` for `Archived` version of `Mphf` if feature is enabled
 #[cfg(feature = "rkyv_derive")]
-impl<const B: usize, const S: usize, ST, H> ArchivedMphf<B, S, ST, H>
+impl<const B: usize, const S: usize, ST, H, R, E> ArchivedMphf<B, S, ST, H, R, E>
 where
     ST: PrimInt + Unsigned + rkyv::Archive<Archived = ST>,
     H: Hasher + Default,
+    R: RankedBitsLayout + rkyv::Archive,
+    rkyv::Archived<R>: RankedBitsAccess,
+    E: ExtrasLayout + rkyv::Archive,
+    rkyv::Archived<E>: ExtrasAccess<KeyFilter = ArchivedKeyFilter>,
 {
     #[inline]
     pub fn get<K: Hash + ?Sized>(&self, key: &K) -> Option<usize> {
-        Mphf::<B, S, ST, H>::get_impl(key, &self.level_groups, &self.group_seeds, &self.ranked_bits)
+        self.get_by_hash(hash_key_seeded::<H, _>(self.extras.hash_seed(), key))
+    }
+
+    #[inline]
+    pub fn get_hashed<K: ?Sized>(&self, key: &HashedKey<K, H>) -> Option<usize> {
+        // hashes computed with another seed would map to arbitrary indices
+        if key.hash_seed() != self.extras.hash_seed() {
+            return None;
+        }
+        self.get_by_hash(key.hash())
+    }
+
+    /// Returns the seed hashed before every key, see [`Mphf::hash_seed`].
+    #[inline]
+    pub fn hash_seed(&self) -> u64 {
+        self.extras.hash_seed()
+    }
+
+    /// Returns the seed mixed into key hashes, see [`Mphf::build_seed`].
+    #[inline]
+    pub fn build_seed(&self) -> u64 {
+        self.extras.build_seed()
+    }
+
+    /// Returns the `KeyFilter` checked before walking the levels, see [`Mphf::key_filter`].
+    #[inline]
+    pub fn key_filter(&self) -> Option<&ArchivedKeyFilter> {
+        self.extras.key_filter()
+    }
+
+    /// Returns the location of the bit set for output index `idx`, see [`Mphf::locate`].
+    #[inline]
+    pub fn locate(&self, idx: usize) -> Option<BitLocation> {
+        Mphf::<B, S, ST, H>::locate_impl(
+            idx.checked_sub(self.extras.fallback().len())?,
+            &self.level_groups,
+            &self.ranked_bits,
+        )
+    }
+
+    /// Returns the number of keys stored in the fallback table, see [`Mphf::fallback_len`].
+    #[inline]
+    pub fn fallback_len(&self) -> usize {
+        self.extras.fallback().len()
+    }
+
+    /// Returns the byte slices read by queries reaching `level`, see [`Mphf::level_sections`].
+    pub fn level_sections(&self, level: usize) -> Option<Vec<&[u8]>>
+    where
+        rkyv::Archived<R>: RankedBitsSections,
+    {
+        Mphf::<B, S, ST, H>::level_sections_impl(level, &self.level_groups, &self.group_seeds, &self.ranked_bits)
+    }
+
+    /// Reads every page of the sections of the first `levels` levels (and of the key filter and
+    /// the fallback table, which are read by every query), so that queries of a memory-mapped archive resolved by them don't
+    /// wait for page faults, while the pages of deeper levels are only read from disk on demand.
+    ///
+    /// # Examples
+    /// ```
+    /// # use entropy_map::{Mphf, DEFAULT_GAMMA};
+    /// let keys: Vec<u64> = (0..10_000).collect();
+    /// let mphf = Mphf::<32, 8>::from_slice(&keys, DEFAULT_GAMMA).unwrap();
+    /// let bytes = rkyv::to_bytes::<_, 1024>(&mphf).unwrap();
+    /// let archived_mphf = rkyv::check_archived_root::<Mphf<32, 8>>(&bytes).unwrap();
+    /// archived_mphf.prefault_levels(1);
+    /// assert!(archived_mphf.get(&1u64).is_some());
+    /// ```
+    pub fn prefault_levels(&self, levels: usize)
+    where
+        rkyv::Archived<R>: RankedBitsSections,
+    {
+        /// Smallest page size of supported platforms
+        const PAGE_SIZE: usize = 4096;
+
+        let levels = (0..levels).map_while(|level| self.level_sections(level));
+        let key_filter = self.extras.key_filter().map(|filter| filter.bytes());
+        let fallback = crate::rank::as_bytes(self.extras.fallback());
+        for section in levels.flatten().chain(key_filter).chain([fallback]) {
+            for idx in (0..section.len()).step_by(PAGE_SIZE) {
+                std::hint::black_box(section[idx]);
+            }
+        }
+    }
+
+    /// Returns the hash of `key` computed with hasher `H` and the hash seed, see [`Mphf::hash_key`].
+    #[inline]
+    pub fn hash_key<K: Hash + ?Sized>(&self, key: &K) -> u64 {
+        hash_key_seeded::<H, _>(self.extras.hash_seed(), key)
+    }
+
+    /// Returns the index associated with the key `hash`, see [`Mphf::get_by_hash`].
+    #[inline]
+    pub fn get_by_hash(&self, hash: u64) -> Option<usize> {
+        self.extras.get_by_hash(hash, |hash| {
+            get_levels(hash, B, &self.level_groups, &self.group_seeds, &self.ranked_bits)
+        })
+    }
+
+    /// Writes the indices associated with `keys` into `out`, see [`Mphf::get_many`].
+    pub fn get_many<K: Hash>(&self, keys: &[K], out: &mut [Option<usize>]) {
+        assert_eq!(keys.len(), out.len(), "`keys` and `out` lengths differ");
+        let mut hashes = [0; GET_MANY_BATCH_LEN];
+        for (keys, out) in keys.chunks(GET_MANY_BATCH_LEN).zip(out.chunks_mut(GET_MANY_BATCH_LEN)) {
+            let hashes = &mut hashes[..keys.len()];
+            for (hash, key) in hashes.iter_mut().zip(keys) {
+                *hash = self.hash_key(key);
+            }
+            Mphf::<B, S, ST, H>::prefetch_impl(
+                hashes,
+                self.extras.build_seed(),
+                &self.level_groups,
+                &self.group_seeds,
+                &self.ranked_bits,
+            );
+            for (idx, &hash) in out.iter_mut().zip(hashes.iter()) {
+                *idx = self.get_by_hash(hash);
+            }
+        }
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::rank::InterleavedRankedBits;
+    use crate::sip_hasher::SipHasher13;
     use paste::paste;
+    use proptest::prelude::*;
     use std::collections::HashSet;
     use test_case::test_case;
 
+    #[test_case(0 => 0)]
+    #[test_case(1 => 1)]
+    #[test_case(16 => 16)]
+    #[test_case(17 => 15)]
+    #[test_case(100 => 15)]
+    fn test_max_group_ones(group_len: u32) -> u32 {
+        Mphf::<16, 4>::max_group_ones(group_len)
+    }
+
     /// Helper function that contains the test logic
     fn test_mphfs_impl<const B: usize, const S: usize>(n: usize, gamma: f32) -> String {
         let keys = (0..n as u64).collect::<Vec<u64>>();
@@ -345,19 +1969,21 @@ mod tests {
         }
         assert_eq!(set.len(), n);
 
-        // Compute average number of levels which needed to be accessed during `get`
-        let mut avg_levels = 0f32;
-        let total_groups: u32 = mphf.level_groups.iter().sum();
-        for (i, &groups) in mphf.level_groups.iter().enumerate() {
-            avg_levels += ((i + 1) as f32 * groups as f32) / (total_groups as f32);
-        }
-        let bits = mphf.size() as f32 * (8.0 / n as f32);
+        // Compute average number of levels which needed to be accessed during `get`, weighted by
+        // the number of groups of every level
+        let stats = mphf.stats();
+        assert_eq!(stats.keys, n);
+        let total_groups: u32 = stats.groups_per_level.iter().sum();
+        let avg_levels: f32 = stats
+            .groups_per_level
+            .iter()
+            .enumerate()
+            .map(|(i, &groups)| ((i + 1) as f32 * groups as f32) / (total_groups as f32))
+            .sum();
 
         format!(
             "bits: {:.2} total_levels: {} avg_levels: {:.2}",
-            bits,
-            mphf.level_groups.len(),
-            avg_levels
+            stats.bits_per_key, stats.levels, avg_levels
         )
     }
 
@@ -395,6 +2021,10 @@ mod tests {
         (61, 8, 10000, 100, "bits: 2.82 total_levels: 4 avg_levels: 2.00"),
         (63, 8, 10000, 100, "bits: 2.89 total_levels: 4 avg_levels: 2.00"),
         (64, 8, 10000, 100, "bits: 2.25 total_levels: 8 avg_levels: 1.84"),
+        (65, 8, 10000, 100, "bits: 2.98 total_levels: 4 avg_levels: 2.00"),
+        (100, 8, 10000, 100, "bits: 2.59 total_levels: 5 avg_levels: 2.00"),
+        (128, 8, 10000, 100, "bits: 2.37 total_levels: 9 avg_levels: 2.05"),
+        (256, 8, 10000, 100, "bits: 2.48 total_levels: 8 avg_levels: 2.17"),
         (32, 7, 10000, 100, "bits: 2.29 total_levels: 7 avg_levels: 1.70"),
         (32, 5, 10000, 100, "bits: 2.47 total_levels: 8 avg_levels: 1.84"),
         (32, 4, 10000, 100, "bits: 2.58 total_levels: 9 avg_levels: 1.92"),
@@ -406,6 +2036,683 @@ mod tests {
         (32, 6, 100000, 200, "bits: 2.84 total_levels: 5 avg_levels: 1.11"),
     );
 
+    #[test]
+    fn test_introspection() {
+        let keys: Vec<u64> = (0..10_000).collect();
+        let mphf = Mphf::<32, 8>::from_slice(&keys, DEFAULT_GAMMA).unwrap();
+
+        let groups: usize = mphf.level_groups().iter().map(|&groups| groups as usize).sum();
+        assert_eq!(groups, mphf.group_seeds.len());
+        assert!((0..groups).all(|group_idx| mphf.group_seed(group_idx).is_some_and(|seed| seed < 1 << 8)));
+        assert_eq!(mphf.group_seed(groups), None);
+        assert_eq!(mphf.bits_len(), (32 * groups as u64).next_multiple_of(64));
+    }
+
+    #[test]
+    fn test_from_iter() {
+        let keys: Vec<String> = (0..10_000).map(|i| i.to_string()).collect();
+        let mphf = Mphf::<32, 8>::from_slice(&keys, DEFAULT_GAMMA).unwrap();
+
+        // owned and borrowed keys hash the same as slice elements
+        let from_owned = Mphf::<32, 8>::from_iter_with_params(keys.clone(), DEFAULT_GAMMA).unwrap();
+        let (from_borrowed, report) = Mphf::<32, 8>::from_iter_with_policy(&keys, &BuildPolicy::default()).unwrap();
+        assert_eq!(report.keys(), keys.len());
+        for key in &keys {
+            assert_eq!(from_owned.get(key), mphf.get(key));
+            assert_eq!(from_borrowed.get(key), mphf.get(key));
+        }
+    }
+
+    #[test]
+    fn test_from_hashes() {
+        let hashes: Vec<u64> = (0..10_000u64).map(|i| hash_key_seeded::<WyHash, _>(0, &i)).collect();
+        let mphf = Mphf::<32, 8>::from_hashes(&hashes, DEFAULT_GAMMA).unwrap();
+        assert!(mphf.is_bijection(&hashes));
+        assert!((0..10_000u64).all(|i| mphf.hash_key(&i) == hashes[i as usize]));
+
+        // hashes computed with `H` and a zero seed are also queryable by key
+        let policy = BuildPolicy::default().with_hash_seed(42).with_key_filter(8);
+        let (mphf, report) = MphfWithExtras::<32, 8>::from_hashes_with_policy(&hashes, &policy).unwrap();
+        assert_eq!(report.keys(), hashes.len());
+        assert_eq!(mphf.hash_seed(), 0);
+        assert!((0..10_000u64).all(|i| mphf.get(&i) == mphf.get_by_hash(hashes[i as usize])));
+
+        assert!(matches!(
+            Mphf::<32, 8>::from_hashes(&hashes, 0.5),
+            Err(InvalidGammaParameter)
+        ));
+    }
+
+    #[test]
+    fn test_get_many() {
+        let keys: Vec<u64> = (0..1000).collect();
+        let mphf = Mphf::<32, 8>::from_slice(&keys, DEFAULT_GAMMA).unwrap();
+
+        // batches of every length, including a partial last one and non-existing keys
+        let queries: Vec<u64> = (500..1037).collect();
+        let mut out = vec![Some(usize::MAX); queries.len()];
+        mphf.get_many(&queries, &mut out);
+        assert!(queries.iter().zip(&out).all(|(key, &idx)| mphf.get(key) == idx));
+
+        #[cfg(feature = "rkyv_derive")]
+        {
+            let rkyv_bytes = rkyv::to_bytes::<_, 1024>(&mphf).unwrap();
+            let rkyv_mphf = rkyv::check_archived_root::<Mphf<32, 8>>(&rkyv_bytes).unwrap();
+            let mut rkyv_out = vec![None; queries.len()];
+            rkyv_mphf.get_many(&queries, &mut rkyv_out);
+            assert_eq!(rkyv_out, out);
+        }
+
+        let mphf = Mphf::<32, 8>::from_slice::<u64>(&[], DEFAULT_GAMMA).unwrap();
+        let mut out = [Some(0)];
+        mphf.get_many(&[1u64], &mut out);
+        assert_eq!(out, [None]);
+    }
+
+    #[test]
+    fn test_fallback() {
+        let keys: Vec<u64> = (0..10_000).collect();
+        let policy = BuildPolicy::fixed(DEFAULT_GAMMA).with_max_levels(2);
+        assert!(matches!(
+            Mphf::<32, 8>::from_slice_with_policy(&keys, &policy),
+            Err(MaxLevelsExceeded)
+        ));
+
+        // the fallback table is only stored with extras
+        assert!(matches!(
+            Mphf::<32, 8>::from_slice_with_policy(&keys, &policy.with_fallback()),
+            Err(ExtrasRequired)
+        ));
+
+        let (mphf, report) = MphfWithExtras::<32, 8>::from_slice_with_policy(&keys, &policy.with_fallback()).unwrap();
+        assert_eq!(mphf.level_groups().len(), 2);
+        assert!(mphf.fallback_len() > 0);
+        assert_eq!(report.fallback_keys, mphf.fallback_len());
+        assert_eq!(report.levels[1].retained_keys, mphf.fallback_len());
+        assert!(mphf.rebuild_policy().fallback);
+        assert_eq!(crate::invariants::check_outputs(&mphf, keys.len()), Ok(()));
+        let hashes: Vec<u64> = keys.iter().map(|key| hash_key_seeded::<WyHash, _>(0, key)).collect();
+        assert!(mphf.is_bijection(&hashes));
+
+        // fallback keys take the lowest indices, which have no bit location
+        let idx = mphf.get(&keys[0]).unwrap();
+        assert_eq!(mphf.locate(idx).is_none(), idx < mphf.fallback_len());
+        assert_eq!(mphf.locate(0), None);
+
+        #[cfg(feature = "rkyv_derive")]
+        {
+            let rkyv_bytes = rkyv::to_bytes::<_, 1024>(&mphf).unwrap();
+            let rkyv_mphf = rkyv::check_archived_root::<MphfWithExtras<32, 8>>(&rkyv_bytes).unwrap();
+            assert_eq!(rkyv_mphf.fallback_len(), mphf.fallback_len());
+            assert!(keys.iter().all(|key| rkyv_mphf.get(key) == mphf.get(key)));
+        }
+
+        // all keys are stored in the fallback table without levels
+        let (mphf, report) =
+            MphfWithExtras::<32, 8>::from_slice_with_policy(&keys[..10], &policy.with_max_levels(0).with_fallback())
+                .unwrap();
+        assert_eq!((mphf.fallback_len(), report.keys()), (10, 10));
+        assert_eq!(crate::invariants::check_outputs(&mphf, 10), Ok(()));
+        let mut indices: Vec<usize> = keys[..10].iter().map(|key| mphf.get(key).unwrap()).collect();
+        indices.sort_unstable();
+        assert_eq!(indices, (0..10).collect::<Vec<_>>());
+
+        // duplicate keys can't be told apart by the fallback table either
+        let policy = policy.with_max_levels(0).with_fallback();
+        assert!(matches!(
+            MphfWithExtras::<32, 8>::from_slice_with_policy(&[1, 1], &policy),
+            Err(MaxLevelsExceeded)
+        ));
+    }
+
+    /// Hasher hashing keys 2k and 2k+1 to the same hash without hash seed, and to distinct hashes
+    /// with any hash seed.
+    #[derive(Default)]
+    struct CollidingHasher {
+        state: u64,
+        writes: usize,
+    }
+
+    impl Hasher for CollidingHasher {
+        fn write(&mut self, bytes: &[u8]) {
+            // fold bytes into 8-byte words, so that keys of up to 8 bytes collide like `u64` keys
+            for chunk in bytes.chunks(8) {
+                let mut word = [0; 8];
+                word[..chunk.len()].copy_from_slice(chunk);
+                self.write_u64(u64::from_le_bytes(word));
+            }
+        }
+
+        fn write_u64(&mut self, i: u64) {
+            self.state = self.state.rotate_left(29) ^ i;
+            self.writes += 1;
+        }
+
+        fn finish(&self) -> u64 {
+            let state = if self.writes == 1 { self.state >> 1 } else { self.state };
+            hash_with_seed(state, 0)
+        }
+    }
+
+    #[test]
+    fn test_budget() {
+        let keys: Vec<u64> = (0..20_000).collect();
+        let mut last_gamma = BUDGET_MAX_GAMMA;
+        for budget in [4.0, 3.0, 2.6, 2.3] {
+            let (mphf, report) = Mphf::<32, 8>::from_slice_with_budget(&keys, budget).unwrap();
+            assert!(report.bits_per_key <= budget, "budget = {budget}");
+            assert!((1.0..=last_gamma).contains(&report.gamma), "budget = {budget}");
+            assert!(report.attempts > 1);
+            assert_eq!(report.bits_per_key, mphf.stats().bits_per_key);
+            assert!(mphf.is_bijection(&keys.iter().map(|key| mphf.hash_key(key)).collect::<Vec<_>>()));
+            last_gamma = report.gamma;
+        }
+
+        // unreachable budget returns the smallest MPHF
+        let (_, report) = Mphf::<32, 8>::from_slice_with_budget(&keys, 0.5).unwrap();
+        let (_, smallest) = Mphf::<32, 8>::from_slice_with_report(&keys, 1.0).unwrap();
+        assert_eq!((report.gamma, report.bits_per_key), (1.0, smallest.bits_per_key));
+        assert_eq!(report.attempts, 2);
+    }
+
+    #[test]
+    fn test_stats() {
+        let keys: Vec<u64> = (0..10_000).collect();
+        let policy = BuildPolicy::default().with_max_levels(2).with_fallback();
+        let (mphf, report) = MphfWithExtras::<32, 8>::from_slice_with_policy(&keys, &policy).unwrap();
+        let stats = mphf.stats();
+
+        assert_eq!(stats.keys, keys.len());
+        assert_eq!(stats.bits_per_key, report.bits_per_key);
+        assert_eq!(stats.groups_per_level, mphf.level_groups());
+        assert_eq!(
+            stats.keys_per_level.iter().sum::<usize>() + mphf.fallback_len(),
+            keys.len()
+        );
+        let placed: Vec<usize> = report
+            .levels
+            .iter()
+            .map(|level| level.keys - level.retained_keys)
+            .collect();
+        assert_eq!(stats.keys_per_level, placed);
+        assert!(stats.build_seed_trials >= stats.groups_per_level.iter().sum::<u32>() as u64);
+
+        // levels walked by every key, derived from the location of its bit
+        let query_levels: usize = keys
+            .iter()
+            .filter_map(|key| mphf.locate(mphf.get(key).unwrap()))
+            .map(|location| location.level + 1)
+            .sum();
+        assert_eq!(stats.avg_query_levels, query_levels as f32 / keys.len() as f32);
+
+        assert_eq!(MphfWithExtras::<32, 8>::default().stats(), MphfStats::default());
+    }
+
+    #[test]
+    fn test_collision_rehash() {
+        let keys: Vec<u64> = (0..1000).collect();
+        let policy = BuildPolicy::default().with_max_levels(8);
+        assert!(matches!(
+            Mphf::<32, 8, u8, CollidingHasher>::from_slice_with_policy(&keys, &policy),
+            Err(MaxLevelsExceeded)
+        ));
+
+        let policy = policy.with_collision_rehash();
+        let (mphf, _) = MphfWithExtras::<32, 8, u8, CollidingHasher>::from_slice_with_policy(&keys, &policy).unwrap();
+        assert_ne!(mphf.hash_seed(), 0);
+        assert_eq!(crate::invariants::check_outputs(&mphf, keys.len()), Ok(()));
+        let indices: HashSet<usize> = keys.iter().map(|key| mphf.get(key).unwrap()).collect();
+        assert_eq!(indices.len(), keys.len());
+
+        // colliding keys are not re-hashed by iterator and precomputed hash constructors
+        assert!(matches!(
+            Mphf::<32, 8, u8, CollidingHasher>::from_iter_with_policy(keys.iter(), &policy),
+            Err(HashCollision)
+        ));
+        assert!(matches!(
+            Mphf::<32, 8>::from_hashes_with_policy(&[1, 2, 1], &policy),
+            Err(HashCollision)
+        ));
+        assert!(matches!(
+            Mphf::<32, 8>::from_slice_with_policy(&[1, 2, 1], &policy),
+            Err(HashCollision)
+        ));
+
+        // keys written as bytes collide like `u64` keys
+        let hash_bytes = |key: u64| {
+            let mut hasher = CollidingHasher::default();
+            hasher.write(&key.to_le_bytes());
+            hasher.finish()
+        };
+        assert_eq!(hash_bytes(2), hash_bytes(3));
+        assert_eq!(hash_bytes(2), hash_key_seeded::<CollidingHasher, _>(0, &2u64));
+    }
+
+    #[test]
+    fn test_is_bijection() {
+        let hashes: Vec<u64> = (0..1000)
+            .map(|key: u64| hash_key_seeded::<WyHash, _>(0, &key))
+            .collect();
+        let mphf = Mphf::<32, 8>::from_slice(&(0..1000).collect::<Vec<u64>>(), DEFAULT_GAMMA).unwrap();
+
+        assert!(mphf.is_bijection(&hashes));
+        assert!(!mphf.is_bijection(&hashes[..999]));
+        assert!(!mphf.is_bijection(&[hashes[0], hashes[0]]));
+    }
+
+    #[test]
+    fn test_level_size_overflow() {
+        assert_eq!(Mphf::<32, 8>::level_size_groups_segments(100), Some((4, 2)));
+        assert_eq!(Mphf::<32, 8>::level_size_groups_segments(usize::MAX), None);
+        // more than `u32::MAX` groups are only addressable on 64-bit targets
+        #[cfg(target_pointer_width = "64")]
+        assert_eq!(Mphf::<1, 8>::level_size_groups_segments(1 << 33), None);
+    }
+
+    #[cfg(feature = "rayon")]
+    #[test_case(1_000; "single range")]
+    #[test_case(100_000; "multiple ranges")]
+    fn test_par_search_group_seeds(n: u64) {
+        type M = Mphf<24, 8>;
+        let level = 1;
+        let mut hashes: Vec<u64> = (0..n).map(|key| hash_key_seeded::<WyHash, _>(0, &key)).collect();
+        hashes.sort_unstable_by_key(|&hash| hash_with_seed(hash, level) as u32);
+        let (groups, _) = M::level_size_groups_segments(2 * n as usize).unwrap();
+
+        let progress = BuildProgress::new();
+        let limits = SeedSearchLimits { max_group_seed: 256, deadline: None };
+        let expected = M::search_group_seeds(level, groups, 0..groups, &hashes, 256, limits, &progress).unwrap();
+        let pool = rayon::ThreadPoolBuilder::new().num_threads(3).build().unwrap();
+        let actual =
+            pool.install(|| M::par_search_group_seeds(level, groups, &hashes, 256, limits, &progress).unwrap());
+        assert_eq!(actual, expected);
+    }
+
+    #[test]
+    fn test_build_report() {
+        let n = 10000;
+        let keys = (0..n as u64).collect::<Vec<u64>>();
+        let (mphf, report) = Mphf::<32, 8>::from_slice_with_report(&keys, DEFAULT_GAMMA).unwrap();
+
+        assert_eq!(report.keys(), n);
+        assert_eq!(report.levels.len(), mphf.level_groups.len());
+        assert_eq!(report.levels.last().unwrap().retained_keys, 0);
+        for levels in report.levels.windows(2) {
+            assert_eq!(levels[0].retained_keys, levels[1].keys);
+        }
+        assert_eq!(report.dedup_ratio, None);
+        assert_eq!(report.bits_per_key, (mphf.size() * 8) as f32 / n as f32);
+        assert_eq!(report.gamma, DEFAULT_GAMMA);
+        assert_eq!(report.attempts, 1);
+    }
+
+    #[test]
+    fn test_build_policy_gammas() {
+        let gammas = |policy: BuildPolicy| policy.gammas().collect::<Vec<f32>>();
+
+        assert_eq!(gammas(BuildPolicy::fixed(2.0)), vec![2.0]);
+        assert_eq!(gammas(BuildPolicy::escalating(2.0, 2.0)), vec![2.0]);
+        assert_eq!(
+            gammas(BuildPolicy::escalating(2.0, 8.0).with_gamma_growth(2.0)),
+            vec![2.0, 4.0, 8.0]
+        );
+        assert_eq!(
+            gammas(BuildPolicy::escalating(2.0, 5.0).with_gamma_growth(2.0)),
+            vec![2.0, 4.0, 5.0]
+        );
+        assert_eq!(
+            gammas(BuildPolicy::escalating(2.0, 8.0).with_gamma_growth(0.5)),
+            vec![2.0]
+        );
+    }
+
+    #[test]
+    fn test_build_policy_escalation() {
+        let keys = (0..10000u64).collect::<Vec<u64>>();
+
+        let policy = BuildPolicy::fixed(1.0).with_max_levels(2);
+        assert!(matches!(
+            Mphf::<32, 8>::from_slice_with_policy(&keys, &policy),
+            Err(MaxLevelsExceeded)
+        ));
+
+        let policy = BuildPolicy::escalating(1.0, 1000.0)
+            .with_gamma_growth(4.0)
+            .with_max_levels(2);
+        let (mphf, report) = Mphf::<32, 8>::from_slice_with_policy(&keys, &policy).unwrap();
+        assert!(report.attempts > 1);
+        assert_eq!(report.gamma, 4.0f32.powi(report.attempts as i32 - 1));
+        assert!(report.levels.len() <= 2);
+
+        let mut set = HashSet::with_capacity(keys.len());
+        for key in &keys {
+            let idx = mphf.get(key).unwrap();
+            assert!(idx < keys.len() && set.insert(idx));
+        }
+
+        let policy = BuildPolicy::escalating(0.5, 8.0);
+        assert!(matches!(
+            Mphf::<32, 8>::from_slice_with_policy(&keys, &policy),
+            Err(InvalidGammaParameter)
+        ));
+    }
+
+    #[test]
+    fn test_seed_search_limits() {
+        let keys = (0..10000u64).collect::<Vec<u64>>();
+        let (_, unlimited) = Mphf::<32, 8>::from_slice_with_policy(&keys, &BuildPolicy::default()).unwrap();
+
+        // an exhausted budget or a single seed per group push more keys to further levels
+        for policy in [
+            BuildPolicy::default().with_seed_search_budget(Duration::ZERO),
+            BuildPolicy::default().with_max_group_seeds(1),
+            BuildPolicy::default().with_max_group_seeds(0),
+        ] {
+            let (mphf, report) = Mphf::<32, 8>::from_slice_with_policy(&keys, &policy).unwrap();
+            assert!(report.levels.len() > unlimited.levels.len());
+            assert!(mphf.group_seeds.iter().all(|&seed| seed == 0));
+
+            let mut set = HashSet::with_capacity(keys.len());
+            for key in &keys {
+                let idx = mphf.get(key).unwrap();
+                assert!(idx < keys.len() && set.insert(idx));
+            }
+        }
+
+        let policy = BuildPolicy::default().with_seed_search_budget(Duration::from_secs(3600));
+        let (mphf, _) = Mphf::<32, 8>::from_slice_with_policy(&keys, &policy).unwrap();
+        assert_eq!(
+            mphf.group_seeds,
+            Mphf::<32, 8>::from_slice(&keys, DEFAULT_GAMMA).unwrap().group_seeds
+        );
+    }
+
+    #[test]
+    fn test_diagnostics() {
+        let keys = (0..10000u64).collect::<Vec<u64>>();
+        let (_, report) = Mphf::<32, 8>::from_slice_with_policy(&keys, &BuildPolicy::default()).unwrap();
+        assert!(report.levels.iter().all(|level| level.diagnostics.is_none()));
+
+        let policy = BuildPolicy::default().with_diagnostics();
+        let (mphf, report) = Mphf::<32, 8>::from_slice_with_policy(&keys, &policy).unwrap();
+        let mut level_groups = mphf.level_groups.iter();
+        let mut group_seeds = mphf.group_seeds.iter();
+        for level in &report.levels {
+            let diagnostics = level.diagnostics.as_ref().unwrap();
+            let groups = *level_groups.next().unwrap() as usize;
+            let seeds: Vec<u8> = group_seeds.by_ref().take(groups).copied().collect();
+            assert_eq!(diagnostics.group_keys.len(), groups);
+            assert_eq!(diagnostics.group_placed_keys.len(), groups);
+            assert_eq!(diagnostics.group_keys.iter().sum::<u32>() as usize, level.keys);
+            assert_eq!(
+                diagnostics.group_placed_keys.iter().sum::<u32>() as usize,
+                level.keys - level.retained_keys
+            );
+
+            let non_empty_groups = diagnostics.group_keys.iter().filter(|&&keys| keys > 0).count();
+            assert_eq!(diagnostics.seed_histogram.iter().sum::<usize>(), non_empty_groups);
+            let max_seed = seeds.iter().copied().max().unwrap_or(0) as usize;
+            assert!(diagnostics.seed_histogram.len() <= max_seed + 1);
+
+            // every non-empty group tries the first seed, the number of trials never increases
+            assert_eq!(diagnostics.seed_trials[0].groups, non_empty_groups);
+            assert!(diagnostics.seed_trials[0].collided_keys <= level.keys);
+            assert!(diagnostics.seed_trials.windows(2).all(|w| w[0].groups >= w[1].groups));
+        }
+    }
+
+    #[test]
+    fn test_hash_seed() {
+        let keys: Vec<u64> = (0..10000).collect();
+        let policy = BuildPolicy::fixed(DEFAULT_GAMMA).with_hash_seed(42);
+        let (mphf, _) = MphfWithExtras::<32, 8, u8, SipHasher13>::from_slice_with_policy(&keys, &policy).unwrap();
+        let unkeyed_mphf = Mphf::<32, 8, u8, SipHasher13>::from_slice(&keys, DEFAULT_GAMMA).unwrap();
+        assert_eq!(mphf.hash_seed(), 42);
+        assert_eq!(unkeyed_mphf.hash_seed(), 0);
+
+        let mut set = HashSet::with_capacity(keys.len());
+        for key in &keys {
+            let idx = mphf.get(key).unwrap();
+            assert!(set.insert(idx), "duplicate idx = {} for key {}", idx, key);
+            assert_eq!(mphf.get_hashed(&HashedKey::with_seed(key, 42)), Some(idx));
+            assert_eq!(mphf.get_hashed(&HashedKey::new(key)), None);
+        }
+        // hashes and therefore the built levels depend on the seed
+        assert_ne!(mphf.group_seeds, unkeyed_mphf.group_seeds);
+
+        assert_ne!(BuildPolicy::keyed().hash_seed, 0);
+        assert_ne!(BuildPolicy::keyed().hash_seed, BuildPolicy::keyed().hash_seed);
+    }
+
+    #[test]
+    fn test_build_seed() {
+        let keys: Vec<u64> = (0..10000).collect();
+        let hashes: Vec<u64> = keys.iter().map(|key| hash_key_seeded::<WyHash, _>(0, key)).collect();
+        let unseeded_mphf = Mphf::<32, 8>::from_slice(&keys, DEFAULT_GAMMA).unwrap();
+        for max_levels in [MAX_LEVELS, 2] {
+            let policy = BuildPolicy { max_levels, fallback: true, ..BuildPolicy::fixed(DEFAULT_GAMMA) };
+            let build = |seed| {
+                MphfWithExtras::<32, 8>::from_slice_with_policy(&keys, &policy.with_build_seed(seed))
+                    .unwrap()
+                    .0
+            };
+            let (mphf, same_mphf, other_mphf) = (build(7), build(7), build(8));
+            let (hashes_mphf, _) =
+                MphfWithExtras::<32, 8>::from_hashes_with_policy(&hashes, &policy.with_build_seed(7)).unwrap();
+            assert_eq!(mphf.build_seed(), 7);
+            assert_eq!(mphf.rebuild_policy().build_seed, 7);
+            assert_eq!(mphf.fallback_len() > 0, max_levels == 2);
+
+            // builds with the same seed are reproducible, while other seeds give other indices
+            let mut set = HashSet::with_capacity(keys.len());
+            for (key, &hash) in keys.iter().zip(&hashes) {
+                let idx = mphf.get(key).unwrap();
+                assert!(set.insert(idx), "duplicate idx = {} for key {}", idx, key);
+                assert_eq!(same_mphf.get(key), Some(idx));
+                assert_eq!(hashes_mphf.get_by_hash(hash), Some(idx));
+                assert_eq!(mphf.get_hashed(&HashedKey::new(key)), Some(idx));
+            }
+            assert!(keys.iter().any(|key| mphf.get(key) != other_mphf.get(key)));
+            assert!(keys.iter().any(|key| mphf.get(key) != unseeded_mphf.get(key)));
+        }
+    }
+
+    #[test]
+    fn test_key_filter() {
+        let keys: Vec<u64> = (0..10000).collect();
+        let policy = BuildPolicy::fixed(DEFAULT_GAMMA).with_key_filter(8);
+        let (mphf, report) = MphfWithExtras::<32, 8>::from_slice_with_policy(&keys, &policy).unwrap();
+        let unfiltered_mphf = MphfWithExtras::<32, 8>::from_slice(&keys, DEFAULT_GAMMA).unwrap();
+
+        // the filter doesn't change indices of present keys
+        for key in &keys {
+            assert_eq!(mphf.get(key), unfiltered_mphf.get(key));
+        }
+
+        let absent_keys = 10000..20000;
+        let found = absent_keys.clone().filter(|key| mphf.get(key).is_some()).count();
+        let unfiltered_found = absent_keys.filter(|key| unfiltered_mphf.get(key).is_some()).count();
+        assert!(
+            found * 10 < unfiltered_found,
+            "found = {found} unfiltered_found = {unfiltered_found}"
+        );
+
+        assert_eq!(mphf.key_filter().unwrap().bits_per_key(), 8);
+        assert_eq!(mphf.rebuild_policy().key_filter_bits, 8);
+        let filter = mphf.key_filter().unwrap();
+        assert_eq!(
+            mphf.size(),
+            unfiltered_mphf.size() + filter.size() - size_of_val(filter)
+        );
+        assert_eq!(report.bits_per_key, mphf.size() as f32 * 8.0 / keys.len() as f32);
+
+        #[cfg(feature = "rkyv_derive")]
+        {
+            let rkyv_bytes = rkyv::to_bytes::<_, 1024>(&mphf).unwrap();
+            let rkyv_mphf = rkyv::check_archived_root::<MphfWithExtras<32, 8>>(&rkyv_bytes).unwrap();
+            for key in 0..20000u64 {
+                assert_eq!(rkyv_mphf.get(&key), mphf.get(&key));
+            }
+        }
+    }
+
+    /// Walks the levels like `get_impl` does and returns the location of the bit found for `key`.
+    fn expected_location<const B: usize, R: RankedBitsLayout>(
+        mphf: &Mphf<B, 8, u8, WyHash, R>,
+        key: &u64,
+    ) -> BitLocation {
+        let hash = hash_key_seeded::<WyHash, _>(0, key);
+        let mut groups_before = 0;
+        for (level, &groups) in mphf.level_groups.iter().enumerate() {
+            let level_hash = hash_with_seed(hash, level as u32);
+            let group_idx = groups_before + fastmod32(level_hash as u32, groups);
+            let bit_idx = bit_index_for_seed::<B>(level_hash, mphf.group_seeds[group_idx] as u32, group_idx);
+            if mphf.ranked_bits.rank(bit_idx).is_some() {
+                let bit_in_group = bit_idx as usize - group_idx * B;
+                return BitLocation { bit_idx, level, group_idx, bit_in_group };
+            }
+            groups_before += groups as usize;
+        }
+        unreachable!("key {key} is not in mphf")
+    }
+
+    #[test]
+    fn test_locate() {
+        let n = 10000;
+        let keys = (0..n as u64).collect::<Vec<u64>>();
+        let mphf = Mphf::<32, 8>::from_slice(&keys, DEFAULT_GAMMA).unwrap();
+        let interleaved_mphf =
+            Mphf::<32, 8, u8, WyHash, InterleavedRankedBits>::from_slice(&keys, DEFAULT_GAMMA).unwrap();
+
+        for key in &keys {
+            let idx = mphf.get(key).unwrap();
+            assert_eq!(mphf.locate(idx), Some(expected_location(&mphf, key)));
+            let idx = interleaved_mphf.get(key).unwrap();
+            assert_eq!(
+                interleaved_mphf.locate(idx),
+                Some(expected_location(&interleaved_mphf, key))
+            );
+        }
+        assert_eq!(mphf.locate(n), None);
+        assert_eq!(interleaved_mphf.locate(n), None);
+
+        #[cfg(feature = "rkyv_derive")]
+        {
+            let rkyv_bytes = rkyv::to_bytes::<_, 1024>(&mphf).unwrap();
+            let rkyv_mphf = rkyv::check_archived_root::<Mphf<32, 8>>(&rkyv_bytes).unwrap();
+            assert!((0..=n).all(|idx| rkyv_mphf.locate(idx) == mphf.locate(idx)));
+        }
+    }
+
+    #[test]
+    fn test_level_sections() {
+        let contains = |section: &[u8], ptr: *const u8| section.as_ptr_range().contains(&ptr);
+        let keys = (0..10000u64).collect::<Vec<u64>>();
+        let mphf = Mphf::<32, 8>::from_slice(&keys, DEFAULT_GAMMA).unwrap();
+        assert!(mphf.level_groups.len() > 1);
+
+        for key in &keys {
+            let loc = mphf.locate(mphf.get(key).unwrap()).unwrap();
+            let sections = mphf.level_sections(loc.level).unwrap();
+            let word = &mphf.ranked_bits.bits[(loc.bit_idx / 64) as usize];
+            assert!(contains(sections[0], word as *const u64 as *const u8));
+            let seed = &mphf.group_seeds[loc.group_idx];
+            assert!(contains(sections[sections.len() - 1], seed));
+        }
+        assert_eq!(mphf.level_sections(mphf.level_groups.len()), None);
+
+        let interleaved_mphf =
+            Mphf::<32, 8, u8, WyHash, InterleavedRankedBits>::from_slice(&keys, DEFAULT_GAMMA).unwrap();
+        let sections_len: usize = (0..interleaved_mphf.level_groups.len())
+            .flat_map(|level| interleaved_mphf.level_sections(level).unwrap())
+            .map(|section| section.len())
+            .sum();
+        assert!(sections_len >= interleaved_mphf.group_seeds.len() + (interleaved_mphf.bits_len() / 8) as usize);
+
+        #[cfg(feature = "rkyv_derive")]
+        {
+            let rkyv_bytes = rkyv::to_bytes::<_, 1024>(&mphf).unwrap();
+            let rkyv_mphf = rkyv::check_archived_root::<Mphf<32, 8>>(&rkyv_bytes).unwrap();
+            for level in 0..mphf.level_groups.len() {
+                let sections = rkyv_mphf.level_sections(level).unwrap();
+                assert!(sections.iter().all(|section| contains(&rkyv_bytes, section.as_ptr())));
+                assert_eq!(sections, mphf.level_sections(level).unwrap());
+            }
+            rkyv_mphf.prefault_levels(usize::MAX);
+        }
+    }
+
+    #[test]
+    fn test_interleaved_ranked_bits() {
+        let n = 10000;
+        let keys = (0..n as u64).collect::<Vec<u64>>();
+        let mphf = Mphf::<32, 8>::from_slice(&keys, DEFAULT_GAMMA).unwrap();
+        let interleaved_mphf =
+            Mphf::<32, 8, u8, WyHash, InterleavedRankedBits>::from_slice(&keys, DEFAULT_GAMMA).unwrap();
+
+        assert!(interleaved_mphf.bits_len() >= mphf.bits_len());
+        assert!(interleaved_mphf.size() > mphf.size());
+        for key in &keys {
+            assert_eq!(interleaved_mphf.get(key), mphf.get(key));
+        }
+
+        #[cfg(feature = "rkyv_derive")]
+        {
+            let rkyv_bytes = rkyv::to_bytes::<_, 1024>(&interleaved_mphf).unwrap();
+            let rkyv_mphf =
+                rkyv::check_archived_root::<Mphf<32, 8, u8, WyHash, InterleavedRankedBits>>(&rkyv_bytes).unwrap();
+            for key in &keys {
+                assert_eq!(rkyv_mphf.get(key), mphf.get(key));
+            }
+        }
+    }
+
+    proptest! {
+        #[test]
+        fn proptest_debug_check_invariants(keys: HashSet<u64>) {
+            let keys: Vec<u64> = keys.into_iter().collect();
+            let mphf = Mphf::<32, 8>::from_slice(&keys, DEFAULT_GAMMA).unwrap();
+            prop_assert_eq!(mphf.debug_check_invariants(), Ok(()));
+            let mphf = Mphf::<8, 4, u8, WyHash, InterleavedRankedBits>::from_slice(&keys, DEFAULT_GAMMA).unwrap();
+            prop_assert_eq!(mphf.debug_check_invariants(), Ok(()));
+        }
+    }
+
+    #[test]
+    fn test_debug_check_invariants() {
+        let keys = (0..1000u64).collect::<Vec<u64>>();
+        let build = || Mphf::<32, 4>::from_slice(&keys, DEFAULT_GAMMA).unwrap();
+
+        let mut mphf = build();
+        mphf.group_seeds[5] = 16;
+        assert_eq!(
+            mphf.debug_check_invariants(),
+            Err(InvariantError::SeedOutOfRange { group_idx: 5 })
+        );
+
+        let mut mphf = build();
+        mphf.level_groups[0] += 1;
+        assert_eq!(mphf.debug_check_invariants(), Err(InvariantError::GroupsMismatch));
+
+        // a set bit past the last group
+        let mut mphf = build();
+        let groups_bits = mphf.bits_len();
+        let mut bits = mphf.ranked_bits.bits.to_vec();
+        bits.push(1);
+        mphf.ranked_bits = RankedBits::new(bits.into_boxed_slice());
+        assert_eq!(
+            mphf.debug_check_invariants(),
+            Err(InvariantError::PaddingBitSet { bit_idx: groups_bits })
+        );
+
+        let mut mphf = build();
+        mphf.ranked_bits.l12_ranks[0] = 0.into();
+        assert_eq!(
+            mphf.debug_check_invariants(),
+            Err(InvariantError::RankMismatch { block_idx: 0 })
+        );
+    }
+
     #[cfg(feature = "rkyv_derive")]
     #[test]
     fn test_rkyv() {
@@ -414,7 +2721,7 @@ mod tests {
         let mphf = Mphf::<32, 4>::from_slice(&keys, DEFAULT_GAMMA).expect("failed to create mphf");
         let rkyv_bytes = rkyv::to_bytes::<_, 1024>(&mphf).unwrap();
 
-        assert_eq!(rkyv_bytes.len(), 3804);
+        assert_eq!(rkyv_bytes.len(), 3884);
 
         let rkyv_mphf = rkyv::check_archived_root::<Mphf<32, 4>>(&rkyv_bytes).unwrap();
 
@@ -0,0 +1,237 @@
+//! A module providing `MphfAuto`, an `Mphf` with group size and seeds picked at construction.
+//!
+//! `Mphf` parameters `B` and `S` are compile-time constants, while most users only care whether the
+//! structure is as small as possible or as fast to build as possible. `MphfAuto` picks parameters
+//! per dataset according to an `AutoGoal` using the bits per key and construction time curves
+//! measured for the supported parameters:
+//!
+//! | `B`/`S` | bits per key (`gamma` 1.0 / 2.0 / 4.0) | construction time per key |
+//! |---------|----------------------------------------|---------------------------|
+//! | 64/8    | 2.14 / 2.64 / 4.64                     | ~1.0-2.0 µs               |
+//! | 32/8    | 2.10 / 2.72 / 5.13                     | ~1.0-1.5 µs               |
+//! | 16/4    | 2.65 / 3.42 / 6.18                     | ~0.1-0.2 µs               |
+//!
+//! i.e. 64-bit groups are smallest for `gamma` of 1.5 and above, 32-bit groups below it, and 16-bit
+//! groups with 16 seeds build ~10x faster. Sets of up to `AUTO_SMALL_KEYS` keys are built within a
+//! few milliseconds with any parameters, so the smallest parameters are picked for them regardless
+//! of the goal.
+
+use std::hash::{Hash, Hasher};
+
+use wyhash::WyHash;
+
+use crate::hashed_key::HashedKey;
+use crate::mphf::{BuildPolicy, Mphf, MphfError};
+use crate::report::BuildReport;
+
+/// Maximum number of keys built with the smallest parameters regardless of `AutoGoal`.
+pub const AUTO_SMALL_KEYS: usize = 1 << 12;
+
+/// `gamma` from which 64-bit groups result in a smaller MPHF than 32-bit groups.
+const COMPACT_MIN_GAMMA: f32 = 1.5;
+
+/// What `MphfAuto` optimizes parameters for.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum AutoGoal {
+    /// Minimize the number of bits per key.
+    #[default]
+    Smallest,
+    /// Minimize construction time.
+    Fastest,
+}
+
+/// `Mphf` parameters picked by [`AutoGoal::params`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct AutoParams {
+    /// Group size in bits
+    pub b: usize,
+    /// Number of bits of group seeds
+    pub s: usize,
+}
+
+impl AutoGoal {
+    /// Returns `Mphf` parameters best meeting the goal for `n` keys and `gamma`.
+    ///
+    /// # Examples
+    /// ```
+    /// # use entropy_map::{AutoGoal, AutoParams, DEFAULT_GAMMA};
+    /// assert_eq!(AutoGoal::Smallest.params(1_000_000, DEFAULT_GAMMA), AutoParams { b: 64, s: 8 });
+    /// assert_eq!(AutoGoal::Smallest.params(1_000_000, 1.0), AutoParams { b: 32, s: 8 });
+    /// assert_eq!(AutoGoal::Fastest.params(1_000_000, DEFAULT_GAMMA), AutoParams { b: 16, s: 4 });
+    /// assert_eq!(AutoGoal::Fastest.params(100, DEFAULT_GAMMA), AutoParams { b: 64, s: 8 });
+    /// ```
+    pub fn params(self, n: usize, gamma: f32) -> AutoParams {
+        match self {
+            AutoGoal::Fastest if n > AUTO_SMALL_KEYS => AutoParams { b: 16, s: 4 },
+            _ if gamma >= COMPACT_MIN_GAMMA => AutoParams { b: 64, s: 8 },
+            _ => AutoParams { b: 32, s: 8 },
+        }
+    }
+}
+
+/// `Mphf` with parameters picked at construction time according to an `AutoGoal`, see the module
+/// documentation.
+///
+/// # Examples
+/// ```
+/// # use entropy_map::{AutoGoal, MphfAuto, DEFAULT_GAMMA};
+/// let keys: Vec<u64> = (0..10_000).collect();
+/// let mphf: MphfAuto = MphfAuto::from_slice(&keys, DEFAULT_GAMMA, AutoGoal::Fastest).unwrap();
+/// assert_eq!(mphf.params().b, 16);
+/// assert!(mphf.get(&1).unwrap() < 10_000);
+/// ```
+#[cfg_attr(feature = "rkyv_derive", derive(rkyv::Archive, rkyv::Deserialize, rkyv::Serialize))]
+#[cfg_attr(feature = "rkyv_derive", archive_attr(derive(rkyv::CheckBytes)))]
+pub enum MphfAuto<H: Hasher + Default = WyHash> {
+    /// 64-bit groups with 256 seeds per group
+    Compact(Mphf<64, 8, u8, H>),
+    /// 32-bit groups with 256 seeds per group
+    Default(Mphf<32, 8, u8, H>),
+    /// 16-bit groups with 16 seeds per group
+    Fast(Mphf<16, 4, u8, H>),
+}
+
+/// Dispatches `$body` to the `Mphf` wrapped by any variant of `MphfAuto` or `ArchivedMphfAuto`.
+macro_rules! dispatch {
+    ($enum:ident, $value:expr, $mphf:ident => $body:expr) => {
+        match $value {
+            $enum::Compact($mphf) => $body,
+            $enum::Default($mphf) => $body,
+            $enum::Fast($mphf) => $body,
+        }
+    };
+}
+
+impl<H: Hasher + Default> MphfAuto<H> {
+    /// Initializes `MphfAuto` using slice of `keys` and parameter `gamma`, with parameters picked
+    /// according to `goal`.
+    pub fn from_slice<K: Hash>(keys: &[K], gamma: f32, goal: AutoGoal) -> Result<Self, MphfError> {
+        Self::from_slice_with_policy(keys, &BuildPolicy::fixed(gamma), goal).map(|(mphf, _)| mphf)
+    }
+
+    /// Initializes `MphfAuto` using slice of `keys`, retrying failed builds as configured by
+    /// `policy`, with parameters picked according to `goal` for the initial `gamma` of `policy`.
+    ///
+    /// # Examples
+    /// ```
+    /// # use entropy_map::{AutoGoal, BuildPolicy, MphfAuto};
+    /// let keys: Vec<u64> = (0..10_000).collect();
+    /// let policy = BuildPolicy::default();
+    /// let (mphf, report): (MphfAuto, _) = MphfAuto::from_slice_with_policy(&keys, &policy, AutoGoal::Smallest).unwrap();
+    /// assert_eq!(mphf.params().b, 64);
+    /// assert_eq!(report.keys(), 10_000);
+    /// ```
+    pub fn from_slice_with_policy<K: Hash>(
+        keys: &[K],
+        policy: &BuildPolicy,
+        goal: AutoGoal,
+    ) -> Result<(Self, BuildReport), MphfError> {
+        match goal.params(keys.len(), policy.gamma) {
+            AutoParams { b: 64, .. } => Mphf::from_slice_with_policy(keys, policy).map(|(m, r)| (Self::Compact(m), r)),
+            AutoParams { b: 32, .. } => Mphf::from_slice_with_policy(keys, policy).map(|(m, r)| (Self::Default(m), r)),
+            _ => Mphf::from_slice_with_policy(keys, policy).map(|(m, r)| (Self::Fast(m), r)),
+        }
+    }
+
+    /// Returns the parameters the MPHF was built with.
+    #[inline]
+    pub fn params(&self) -> AutoParams {
+        match self {
+            MphfAuto::Compact(_) => AutoParams { b: 64, s: 8 },
+            MphfAuto::Default(_) => AutoParams { b: 32, s: 8 },
+            MphfAuto::Fast(_) => AutoParams { b: 16, s: 4 },
+        }
+    }
+
+    /// Returns the index associated with `key`, see [`Mphf::get`].
+    #[inline]
+    pub fn get<K: Hash + ?Sized>(&self, key: &K) -> Option<usize> {
+        dispatch!(MphfAuto, self, mphf => mphf.get(key))
+    }
+
+    /// Same as [`MphfAuto::get`], but re-uses the hash precomputed by `HashedKey`.
+    #[inline]
+    pub fn get_hashed<K: ?Sized>(&self, key: &HashedKey<K, H>) -> Option<usize> {
+        dispatch!(MphfAuto, self, mphf => mphf.get_hashed(key))
+    }
+
+    /// Returns the number of bits of the ranked bit vector, see [`Mphf::bits_len`].
+    #[inline]
+    pub fn bits_len(&self) -> u64 {
+        dispatch!(MphfAuto, self, mphf => mphf.bits_len())
+    }
+
+    /// Returns the total number of bytes occupied by the MPHF.
+    #[inline]
+    pub fn size(&self) -> usize {
+        dispatch!(MphfAuto, self, mphf => mphf.size())
+    }
+}
+
+/// Implement `get` for `Archived` version of `MphfAuto` if feature is enabled
+#[cfg(feature = "rkyv_derive")]
+impl<H: Hasher + Default> ArchivedMphfAuto<H> {
+    /// Returns the parameters the MPHF was built with.
+    #[inline]
+    pub fn params(&self) -> AutoParams {
+        match self {
+            ArchivedMphfAuto::Compact(_) => AutoParams { b: 64, s: 8 },
+            ArchivedMphfAuto::Default(_) => AutoParams { b: 32, s: 8 },
+            ArchivedMphfAuto::Fast(_) => AutoParams { b: 16, s: 4 },
+        }
+    }
+
+    /// Returns the index associated with `key`, see [`Mphf::get`].
+    #[inline]
+    pub fn get<K: Hash + ?Sized>(&self, key: &K) -> Option<usize> {
+        dispatch!(ArchivedMphfAuto, self, mphf => mphf.get(key))
+    }
+
+    /// Same as [`ArchivedMphfAuto::get`], but re-uses the hash precomputed by `HashedKey`.
+    #[inline]
+    pub fn get_hashed<K: ?Sized>(&self, key: &HashedKey<K, H>) -> Option<usize> {
+        dispatch!(ArchivedMphfAuto, self, mphf => mphf.get_hashed(key))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mphf::DEFAULT_GAMMA;
+    use std::collections::HashSet;
+    use test_case::test_case;
+
+    #[test_case(AutoGoal::Smallest, 100_000, 1.0 => AutoParams { b: 32, s: 8 })]
+    #[test_case(AutoGoal::Smallest, 100_000, DEFAULT_GAMMA => AutoParams { b: 64, s: 8 })]
+    #[test_case(AutoGoal::Fastest, 100_000, 1.0 => AutoParams { b: 16, s: 4 })]
+    #[test_case(AutoGoal::Fastest, 100_000, DEFAULT_GAMMA => AutoParams { b: 16, s: 4 })]
+    #[test_case(AutoGoal::Fastest, AUTO_SMALL_KEYS, 1.0 => AutoParams { b: 32, s: 8 })]
+    #[test_case(AutoGoal::Fastest, AUTO_SMALL_KEYS, DEFAULT_GAMMA => AutoParams { b: 64, s: 8 })]
+    fn test_params(goal: AutoGoal, n: usize, gamma: f32) -> AutoParams {
+        goal.params(n, gamma)
+    }
+
+    #[test_case(AutoGoal::Smallest, 1.0)]
+    #[test_case(AutoGoal::Smallest, DEFAULT_GAMMA)]
+    #[test_case(AutoGoal::Fastest, DEFAULT_GAMMA)]
+    fn test_mphf_auto(goal: AutoGoal, gamma: f32) {
+        let n = 10_000;
+        let keys: Vec<u64> = (0..n as u64).collect();
+        let mphf = MphfAuto::<WyHash>::from_slice(&keys, gamma, goal).unwrap();
+
+        assert_eq!(mphf.params(), goal.params(n, gamma));
+        let indices: HashSet<usize> = keys.iter().map(|key| mphf.get(key).unwrap()).collect();
+        assert_eq!(indices, (0..n).collect::<HashSet<_>>());
+        assert!(keys
+            .iter()
+            .all(|key| mphf.get_hashed(&HashedKey::new(key)) == mphf.get(key)));
+
+        #[cfg(feature = "rkyv_derive")]
+        {
+            let rkyv_bytes = rkyv::to_bytes::<_, 1024>(&mphf).unwrap();
+            let rkyv_mphf = rkyv::check_archived_root::<MphfAuto>(&rkyv_bytes).unwrap();
+            assert_eq!(rkyv_mphf.params(), mphf.params());
+            assert!(keys.iter().all(|key| rkyv_mphf.get(key) == mphf.get(key)));
+        }
+    }
+}
@@ -0,0 +1,411 @@
+//! A module providing `MphfDyn`, an `Mphf` with group size and seed bits chosen at runtime.
+//!
+//! `Mphf` parameters `B` and `S` are compile-time constants, so tools picking them from a config
+//! file or a command line flag have to instantiate every combination they support. `MphfDyn`
+//! stores them as plain fields instead: construction dispatches to the `Mphf` build of the group
+//! size (one of `DYN_GROUP_SIZES`) with `2^s` seeds tried per group, and queries walk the levels
+//! with the same logic as `Mphf`, taking the group size as an argument. Indices are identical to the
+//! ones of `Mphf<b, s>` built from the same keys.
+
+use std::hash::{Hash, Hasher};
+use std::marker::PhantomData;
+use std::mem::size_of_val;
+
+use wyhash::WyHash;
+
+use crate::hashed_key::HashedKey;
+use crate::key_filter::KeyFilter;
+use crate::mphf::{get_with_fallback, hash_key_seeded, BuildPolicy, Mphf, MphfError, DEFAULT_GAMMA};
+use crate::mphf_extras::Extras;
+use crate::rank::RankedBits;
+use crate::report::BuildReport;
+
+/// Group sizes in bits supported by `MphfDyn`.
+pub const DYN_GROUP_SIZES: [usize; 9] = [8, 16, 24, 32, 48, 64, 96, 128, 256];
+
+/// Maximum number of seed bits supported by `MphfDyn`.
+const MAX_SEED_BITS: usize = 16;
+
+/// Group seeds stored with the smallest type fitting the seed bits.
+#[derive(Debug)]
+#[cfg_attr(feature = "rkyv_derive", derive(rkyv::Archive, rkyv::Deserialize, rkyv::Serialize))]
+#[cfg_attr(feature = "rkyv_derive", archive_attr(derive(rkyv::CheckBytes)))]
+enum GroupSeeds {
+    /// Seeds of up to 8 bits
+    U8(Box<[u8]>),
+    /// Seeds of up to 16 bits
+    U16(Box<[u16]>),
+}
+
+/// `Mphf` with group size `b` and seed bits `s` chosen at construction time, see the module
+/// documentation.
+///
+/// # Examples
+/// ```
+/// # use entropy_map::{Mphf, MphfDyn, DEFAULT_GAMMA};
+/// let keys: Vec<u64> = (0..10_000).collect();
+/// let mphf: MphfDyn = MphfDyn::from_slice(&keys, 32, 8, DEFAULT_GAMMA).unwrap();
+/// assert_eq!((mphf.b(), mphf.s()), (32, 8));
+///
+/// let static_mphf = Mphf::<32, 8>::from_slice(&keys, DEFAULT_GAMMA).unwrap();
+/// assert_eq!(mphf.get(&1), static_mphf.get(&1));
+/// ```
+#[derive(Debug)]
+#[cfg_attr(feature = "rkyv_derive", derive(rkyv::Archive, rkyv::Deserialize, rkyv::Serialize))]
+#[cfg_attr(feature = "rkyv_derive", archive_attr(derive(rkyv::CheckBytes)))]
+pub struct MphfDyn<H: Hasher + Default = WyHash> {
+    /// Group size in bits
+    b: u32,
+    /// Number of bits of group seeds
+    s: u32,
+    /// Ranked bits for efficient rank queries
+    ranked_bits: RankedBits,
+    /// Group sizes at each level
+    level_groups: Box<[u32]>,
+    /// Combined group seeds from all levels
+    group_seeds: GroupSeeds,
+    /// Seed hashed before every key, 0 for unkeyed hashing
+    hash_seed: u64,
+    /// Seed mixed into key hashes before computing their level hashes
+    build_seed: u64,
+    /// Optional filter rejecting most absent keys before walking the levels
+    key_filter: Option<KeyFilter>,
+    /// Sorted hashes of keys not placed within the level budget, mapped to the lowest indices
+    fallback: Box<[u64]>,
+    /// Phantom field for the hasher
+    _phantom_hasher: PhantomData<H>,
+}
+
+impl<H: Hasher + Default> MphfDyn<H> {
+    /// Initializes `MphfDyn` with `b`-bit groups and `s`-bit seeds using slice of `keys` and
+    /// parameter `gamma`.
+    pub fn from_slice<K: Hash>(keys: &[K], b: usize, s: usize, gamma: f32) -> Result<Self, MphfError> {
+        Self::from_slice_with_policy(keys, b, s, &BuildPolicy::fixed(gamma)).map(|(mphf, _)| mphf)
+    }
+
+    /// Initializes `MphfDyn` with `b`-bit groups and `s`-bit seeds using slice of `keys`, retrying
+    /// failed builds as configured by `policy`.
+    ///
+    /// Fails with `MphfError::UnsupportedGroupSize` if `b` is not one of `DYN_GROUP_SIZES` and with
+    /// `MphfError::InvalidSeedType` if `s` is greater than 16.
+    ///
+    /// # Examples
+    /// ```
+    /// # use entropy_map::{BuildPolicy, MphfDyn, MphfError};
+    /// let keys: Vec<u64> = (0..10_000).collect();
+    /// let (mphf, report): (MphfDyn, _) = MphfDyn::from_slice_with_policy(&keys, 16, 4, &BuildPolicy::default()).unwrap();
+    /// assert_eq!(report.keys(), 10_000);
+    /// assert_eq!(report.bits_per_key, (mphf.size() * 8) as f32 / 10_000.0);
+    ///
+    /// let res: Result<(MphfDyn, _), _> = MphfDyn::from_slice_with_policy(&keys, 20, 4, &BuildPolicy::default());
+    /// assert!(matches!(res, Err(MphfError::UnsupportedGroupSize)));
+    /// ```
+    pub fn from_slice_with_policy<K: Hash>(
+        keys: &[K],
+        b: usize,
+        s: usize,
+        policy: &BuildPolicy,
+    ) -> Result<(Self, BuildReport), MphfError> {
+        Self::from_iter_with_policy(keys, b, s, policy)
+    }
+
+    /// Same as [`MphfDyn::from_slice_with_policy`], but consumes `keys` from an iterator, see
+    /// [`Mphf::from_iter_with_policy`].
+    pub fn from_iter_with_policy<K: Hash, I: IntoIterator<Item = K>>(
+        keys: I,
+        b: usize,
+        s: usize,
+        policy: &BuildPolicy,
+    ) -> Result<(Self, BuildReport), MphfError> {
+        if s > MAX_SEED_BITS {
+            return Err(MphfError::InvalidSeedType);
+        }
+        // `Mphf` with 16-bit seeds trying at most `2^s` seeds per group builds the same levels as
+        // `Mphf` with `s`-bit seeds
+        let policy = policy.with_max_group_seeds(policy.max_group_seeds.min(1 << s));
+
+        /// Dispatches the construction to `Mphf` with every supported group size.
+        macro_rules! build {
+            ($($size:literal),*) => {
+                match b {
+                    $($size => Self::build::<$size, K, I>(keys, s, &policy),)*
+                    _ => Err(MphfError::UnsupportedGroupSize),
+                }
+            };
+        }
+        build!(8, 16, 24, 32, 48, 64, 96, 128, 256)
+    }
+
+    /// Builds `MphfDyn` from `Mphf` with `B`-bit groups, narrowing its seeds to `s` bits.
+    fn build<const B: usize, K: Hash, I: IntoIterator<Item = K>>(
+        keys: I,
+        s: usize,
+        policy: &BuildPolicy,
+    ) -> Result<(Self, BuildReport), MphfError> {
+        let (mphf, mut report) =
+            Mphf::<B, MAX_SEED_BITS, u16, H, RankedBits, Extras>::from_iter_with_policy(keys, policy)?;
+        let group_seeds = if s <= 8 {
+            GroupSeeds::U8(mphf.group_seeds.iter().map(|&seed| seed as u8).collect())
+        } else {
+            GroupSeeds::U16(mphf.group_seeds)
+        };
+        let mphf = MphfDyn {
+            b: B as u32,
+            s: s as u32,
+            ranked_bits: mphf.ranked_bits,
+            level_groups: mphf.level_groups,
+            group_seeds,
+            hash_seed: mphf.extras.hash_seed,
+            build_seed: mphf.extras.build_seed,
+            key_filter: mphf.extras.key_filter,
+            fallback: mphf.extras.fallback,
+            _phantom_hasher: PhantomData,
+        };
+        report.set_size(mphf.size());
+        Ok((mphf, report))
+    }
+
+    /// Returns the group size in bits.
+    #[inline]
+    pub fn b(&self) -> usize {
+        self.b as usize
+    }
+
+    /// Returns the number of bits of group seeds.
+    #[inline]
+    pub fn s(&self) -> usize {
+        self.s as usize
+    }
+
+    /// Returns the index associated with `key`, see [`Mphf::get`].
+    #[inline]
+    pub fn get<K: Hash + ?Sized>(&self, key: &K) -> Option<usize> {
+        self.get_by_hash(self.hash_key(key))
+    }
+
+    /// Same as [`MphfDyn::get`], but re-uses the hash precomputed by `HashedKey`.
+    #[inline]
+    pub fn get_hashed<K: ?Sized>(&self, key: &HashedKey<K, H>) -> Option<usize> {
+        // hashes computed with another seed would map to arbitrary indices
+        if key.hash_seed() != self.hash_seed {
+            return None;
+        }
+        self.get_by_hash(key.hash())
+    }
+
+    /// Returns the seed hashed before every key, see [`Mphf::hash_seed`].
+    #[inline]
+    pub fn hash_seed(&self) -> u64 {
+        self.hash_seed
+    }
+
+    /// Returns the hash of `key` computed with hasher `H` and the hash seed, see [`Mphf::hash_key`].
+    #[inline]
+    pub fn hash_key<K: Hash + ?Sized>(&self, key: &K) -> u64 {
+        hash_key_seeded::<H, _>(self.hash_seed, key)
+    }
+
+    /// Returns the index associated with the key `hash`, see [`Mphf::get_by_hash`].
+    #[inline]
+    pub fn get_by_hash(&self, hash: u64) -> Option<usize> {
+        if self.key_filter.as_ref().is_some_and(|filter| !filter.contains(hash)) {
+            return None;
+        }
+        let (b, fallback, level_groups) = (self.b as usize, &self.fallback, &self.level_groups);
+        match &self.group_seeds {
+            GroupSeeds::U8(seeds) => get_with_fallback(
+                hash,
+                self.build_seed,
+                b,
+                fallback,
+                level_groups,
+                seeds,
+                &self.ranked_bits,
+            ),
+            GroupSeeds::U16(seeds) => get_with_fallback(
+                hash,
+                self.build_seed,
+                b,
+                fallback,
+                level_groups,
+                seeds,
+                &self.ranked_bits,
+            ),
+        }
+    }
+
+    /// Returns the number of keys stored in the fallback table, see [`BuildPolicy::with_fallback`].
+    #[inline]
+    pub fn fallback_len(&self) -> usize {
+        self.fallback.len()
+    }
+
+    /// Returns the total number of bytes occupied by `MphfDyn`
+    pub fn size(&self) -> usize {
+        let group_seeds = match &self.group_seeds {
+            GroupSeeds::U8(seeds) => size_of_val(seeds.as_ref()),
+            GroupSeeds::U16(seeds) => size_of_val(seeds.as_ref()),
+        };
+        size_of_val(self)
+            + size_of_val(self.level_groups.as_ref())
+            + group_seeds
+            + size_of_val(self.fallback.as_ref())
+            + self.ranked_bits.size()
+            + self
+                .key_filter
+                .as_ref()
+                .map_or(0, |filter| filter.size() - size_of_val(filter))
+    }
+}
+
+impl<H: Hasher + Default> Default for MphfDyn<H> {
+    /// Returns an empty `MphfDyn` with the default `Mphf` parameters.
+    fn default() -> Self {
+        Self::from_slice::<u64>(&[], 32, 8, DEFAULT_GAMMA).expect("empty MPHF")
+    }
+}
+
+/// Implement `get` for `Archived` version of `MphfDyn` if feature is enabled
+#[cfg(feature = "rkyv_derive")]
+impl<H: Hasher + Default> ArchivedMphfDyn<H> {
+    /// Returns the group size in bits.
+    #[inline]
+    pub fn b(&self) -> usize {
+        self.b as usize
+    }
+
+    /// Returns the number of bits of group seeds.
+    #[inline]
+    pub fn s(&self) -> usize {
+        self.s as usize
+    }
+
+    /// Returns the index associated with `key`, see [`Mphf::get`].
+    #[inline]
+    pub fn get<K: Hash + ?Sized>(&self, key: &K) -> Option<usize> {
+        self.get_by_hash(hash_key_seeded::<H, _>(self.hash_seed, key))
+    }
+
+    /// Same as [`ArchivedMphfDyn::get`], but re-uses the hash precomputed by `HashedKey`.
+    #[inline]
+    pub fn get_hashed<K: ?Sized>(&self, key: &HashedKey<K, H>) -> Option<usize> {
+        // hashes computed with another seed would map to arbitrary indices
+        if key.hash_seed() != self.hash_seed {
+            return None;
+        }
+        self.get_by_hash(key.hash())
+    }
+
+    /// Returns the index associated with the key `hash`, see [`Mphf::get_by_hash`].
+    #[inline]
+    pub fn get_by_hash(&self, hash: u64) -> Option<usize> {
+        if self.key_filter.as_ref().is_some_and(|filter| !filter.contains(hash)) {
+            return None;
+        }
+        let (b, fallback, level_groups) = (self.b as usize, &self.fallback, &self.level_groups);
+        match &self.group_seeds {
+            ArchivedGroupSeeds::U8(seeds) => get_with_fallback(
+                hash,
+                self.build_seed,
+                b,
+                fallback,
+                level_groups,
+                seeds,
+                &self.ranked_bits,
+            ),
+            ArchivedGroupSeeds::U16(seeds) => get_with_fallback(
+                hash,
+                self.build_seed,
+                b,
+                fallback,
+                level_groups,
+                seeds,
+                &self.ranked_bits,
+            ),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_matches_mphf() {
+        let keys: Vec<u64> = (0..20_000).collect();
+
+        let mphf: MphfDyn = MphfDyn::from_slice(&keys, 32, 8, DEFAULT_GAMMA).unwrap();
+        let static_mphf = Mphf::<32, 8>::from_slice(&keys, DEFAULT_GAMMA).unwrap();
+        assert!(matches!(mphf.group_seeds, GroupSeeds::U8(_)));
+        assert_eq!(
+            mphf.size(),
+            static_mphf.size() + size_of_val(&mphf) - size_of_val(&static_mphf)
+        );
+        for key in &keys {
+            assert_eq!(mphf.get(key), static_mphf.get(key));
+        }
+
+        let mphf: MphfDyn = MphfDyn::from_slice(&keys, 16, 4, DEFAULT_GAMMA).unwrap();
+        let static_mphf = Mphf::<16, 4>::from_slice(&keys, DEFAULT_GAMMA).unwrap();
+        for key in &keys {
+            assert_eq!(mphf.get(key), static_mphf.get(key));
+            assert_eq!(mphf.get_hashed(&HashedKey::new(key)), static_mphf.get(key));
+        }
+
+        let mphf: MphfDyn = MphfDyn::from_slice(&keys, 64, 12, DEFAULT_GAMMA).unwrap();
+        let static_mphf = Mphf::<64, 12, u16>::from_slice(&keys, DEFAULT_GAMMA).unwrap();
+        assert!(matches!(mphf.group_seeds, GroupSeeds::U16(_)));
+        for key in &keys {
+            assert_eq!(mphf.get(key), static_mphf.get(key));
+        }
+    }
+
+    #[test]
+    fn test_group_sizes() {
+        let keys: Vec<u64> = (0..5_000).collect();
+        for b in DYN_GROUP_SIZES {
+            let mphf: MphfDyn = MphfDyn::from_slice(&keys, b, 8, DEFAULT_GAMMA).unwrap();
+            assert_eq!(mphf.b(), b);
+            let mut indices: Vec<usize> = keys.iter().map(|key| mphf.get(key).unwrap()).collect();
+            indices.sort_unstable();
+            assert!(indices.into_iter().eq(0..keys.len()), "b = {b}");
+        }
+
+        let res: Result<MphfDyn, _> = MphfDyn::from_slice(&keys, 40, 8, DEFAULT_GAMMA);
+        assert!(matches!(res, Err(MphfError::UnsupportedGroupSize)));
+        let res: Result<MphfDyn, _> = MphfDyn::from_slice(&keys, 32, 17, DEFAULT_GAMMA);
+        assert!(matches!(res, Err(MphfError::InvalidSeedType)));
+        assert_eq!(MphfDyn::<WyHash>::default().get(&1), None);
+    }
+
+    #[test]
+    fn test_policy() {
+        let keys: Vec<u64> = (0..10_000).collect();
+        let policy = BuildPolicy::keyed().with_key_filter(8).with_fallback();
+        let (mphf, report): (MphfDyn, _) = MphfDyn::from_slice_with_policy(&keys, 64, 8, &policy).unwrap();
+        assert_eq!(report.bits_per_key, (mphf.size() * 8) as f32 / 10_000.0);
+        assert_eq!(mphf.hash_seed(), policy.hash_seed);
+
+        let mut indices: Vec<usize> = keys.iter().map(|key| mphf.get(key).unwrap()).collect();
+        indices.sort_unstable();
+        assert!(indices.into_iter().eq(0..keys.len()));
+        assert_eq!(mphf.get_by_hash(mphf.hash_key(&1u64)), mphf.get(&1u64));
+    }
+
+    #[cfg(feature = "rkyv_derive")]
+    #[test]
+    fn test_rkyv() {
+        let keys: Vec<u64> = (0..10_000).collect();
+        for (b, s) in [(32, 8), (16, 12)] {
+            let mphf: MphfDyn = MphfDyn::from_slice(&keys, b, s, DEFAULT_GAMMA).unwrap();
+            let rkyv_bytes = rkyv::to_bytes::<_, 1024>(&mphf).unwrap();
+            let rkyv_mphf = rkyv::check_archived_root::<MphfDyn>(&rkyv_bytes).unwrap();
+
+            assert_eq!((rkyv_mphf.b(), rkyv_mphf.s()), (b, s));
+            for key in &keys {
+                assert_eq!(rkyv_mphf.get(key), mphf.get(key));
+                assert_eq!(rkyv_mphf.get_hashed(&HashedKey::new(key)), mphf.get(key));
+            }
+        }
+    }
+}
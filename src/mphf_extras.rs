@@ -0,0 +1,240 @@
+//! A module providing the optional extras stored by `Mphf` alongside its levels.
+//!
+//! Keyed hashing, build seeds, key filters and fallback tables (see `BuildPolicy`) are only needed
+//! by some structures, so they are selected by the `E` type parameter of `Mphf` instead of being
+//! stored and checked by every MPHF. `NoExtras`, the default, takes no space and adds nothing to
+//! queries, while `Extras` stores all of them and supports every `BuildPolicy` option.
+
+use std::mem::size_of_val;
+
+#[cfg(feature = "rkyv_derive")]
+use crate::key_filter::ArchivedKeyFilter;
+use crate::key_filter::KeyFilter;
+
+/// Trait for extras stored by `Mphf`, consistent across standard and `Archived` formats.
+pub trait ExtrasAccess {
+    /// Key filter type, `KeyFilter` or its `Archived` version
+    type KeyFilter;
+
+    /// Returns the seed hashed before every key, 0 for unkeyed hashing.
+    fn hash_seed(&self) -> u64;
+
+    /// Returns the seed mixed into key hashes, 0 by default.
+    fn build_seed(&self) -> u64;
+
+    /// Returns the filter checked before walking the levels, if any.
+    fn key_filter(&self) -> Option<&Self::KeyFilter>;
+
+    /// Returns the sorted hashes of the keys of the fallback table.
+    fn fallback(&self) -> &[u64];
+
+    /// Returns the index associated with the key `hash`, applying the extras around `levels`,
+    /// which walks the MPHF levels for a hash mixed with the build seed.
+    fn get_by_hash(&self, hash: u64, levels: impl FnOnce(u64) -> Option<usize>) -> Option<usize>;
+}
+
+/// Trait for extras `Mphf` can be built with.
+pub trait ExtrasLayout: ExtrasAccess<KeyFilter = KeyFilter> + Default {
+    /// Whether the extras can store a non-zero hash seed or build seed, a key filter or a fallback
+    /// table, otherwise constructions requesting any of them fail with `MphfError::ExtrasRequired`.
+    const STORES_EXTRAS: bool;
+
+    /// Builds the extras, which must be able to store the given ones.
+    fn from_parts(hash_seed: u64, build_seed: u64, key_filter: Option<KeyFilter>, fallback: Box<[u64]>) -> Self;
+
+    /// Returns the number of bytes occupied by the extras beyond their inline size.
+    fn heap_size(&self) -> usize;
+}
+
+/// No extras, the default of `Mphf`, which then hashes keys without a seed and walks its levels
+/// directly.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "rkyv_derive", derive(rkyv::Archive, rkyv::Deserialize, rkyv::Serialize))]
+#[cfg_attr(feature = "rkyv_derive", archive_attr(derive(rkyv::CheckBytes)))]
+pub struct NoExtras;
+
+/// Hash seed, build seed, key filter and fallback table of `Mphf`, see `BuildPolicy`.
+#[derive(Debug, Default)]
+#[cfg_attr(feature = "rkyv_derive", derive(rkyv::Archive, rkyv::Deserialize, rkyv::Serialize))]
+#[cfg_attr(feature = "rkyv_derive", archive_attr(derive(rkyv::CheckBytes)))]
+pub struct Extras {
+    /// Seed hashed by `H` before every key, 0 for unkeyed hashing
+    pub(crate) hash_seed: u64,
+    /// Seed mixed into key hashes before computing their level hashes, see
+    /// [`BuildPolicy::build_seed`](crate::BuildPolicy::build_seed)
+    pub(crate) build_seed: u64,
+    /// Optional filter rejecting most absent keys before walking the levels
+    pub(crate) key_filter: Option<KeyFilter>,
+    /// Sorted hashes of keys not placed within the level budget, mapped to the lowest indices
+    pub(crate) fallback: Box<[u64]>,
+}
+
+impl ExtrasAccess for NoExtras {
+    type KeyFilter = KeyFilter;
+
+    #[inline]
+    fn hash_seed(&self) -> u64 {
+        0
+    }
+
+    #[inline]
+    fn build_seed(&self) -> u64 {
+        0
+    }
+
+    #[inline]
+    fn key_filter(&self) -> Option<&KeyFilter> {
+        None
+    }
+
+    #[inline]
+    fn fallback(&self) -> &[u64] {
+        &[]
+    }
+
+    #[inline(always)]
+    fn get_by_hash(&self, hash: u64, levels: impl FnOnce(u64) -> Option<usize>) -> Option<usize> {
+        levels(hash)
+    }
+}
+
+impl ExtrasLayout for NoExtras {
+    const STORES_EXTRAS: bool = false;
+
+    #[inline]
+    fn from_parts(hash_seed: u64, build_seed: u64, key_filter: Option<KeyFilter>, fallback: Box<[u64]>) -> Self {
+        debug_assert!(hash_seed == 0 && build_seed == 0 && key_filter.is_none() && fallback.is_empty());
+        NoExtras
+    }
+
+    #[inline]
+    fn heap_size(&self) -> usize {
+        0
+    }
+}
+
+impl ExtrasAccess for Extras {
+    type KeyFilter = KeyFilter;
+
+    #[inline]
+    fn hash_seed(&self) -> u64 {
+        self.hash_seed
+    }
+
+    #[inline]
+    fn build_seed(&self) -> u64 {
+        self.build_seed
+    }
+
+    #[inline]
+    fn key_filter(&self) -> Option<&KeyFilter> {
+        self.key_filter.as_ref()
+    }
+
+    #[inline]
+    fn fallback(&self) -> &[u64] {
+        &self.fallback
+    }
+
+    #[inline(always)]
+    fn get_by_hash(&self, hash: u64, levels: impl FnOnce(u64) -> Option<usize>) -> Option<usize> {
+        if self.key_filter.as_ref().is_some_and(|filter| !filter.contains(hash)) {
+            return None;
+        }
+        search_fallback(hash ^ self.build_seed, &self.fallback, levels)
+    }
+}
+
+impl ExtrasLayout for Extras {
+    const STORES_EXTRAS: bool = true;
+
+    #[inline]
+    fn from_parts(hash_seed: u64, build_seed: u64, key_filter: Option<KeyFilter>, fallback: Box<[u64]>) -> Self {
+        Extras { hash_seed, build_seed, key_filter, fallback }
+    }
+
+    #[inline]
+    fn heap_size(&self) -> usize {
+        size_of_val(self.fallback.as_ref())
+            + self
+                .key_filter
+                .as_ref()
+                .map_or(0, |filter| filter.size() - size_of_val(filter))
+    }
+}
+
+#[cfg(feature = "rkyv_derive")]
+impl ExtrasAccess for ArchivedNoExtras {
+    type KeyFilter = ArchivedKeyFilter;
+
+    #[inline]
+    fn hash_seed(&self) -> u64 {
+        0
+    }
+
+    #[inline]
+    fn build_seed(&self) -> u64 {
+        0
+    }
+
+    #[inline]
+    fn key_filter(&self) -> Option<&ArchivedKeyFilter> {
+        None
+    }
+
+    #[inline]
+    fn fallback(&self) -> &[u64] {
+        &[]
+    }
+
+    #[inline(always)]
+    fn get_by_hash(&self, hash: u64, levels: impl FnOnce(u64) -> Option<usize>) -> Option<usize> {
+        levels(hash)
+    }
+}
+
+#[cfg(feature = "rkyv_derive")]
+impl ExtrasAccess for ArchivedExtras {
+    type KeyFilter = ArchivedKeyFilter;
+
+    #[inline]
+    fn hash_seed(&self) -> u64 {
+        self.hash_seed
+    }
+
+    #[inline]
+    fn build_seed(&self) -> u64 {
+        self.build_seed
+    }
+
+    #[inline]
+    fn key_filter(&self) -> Option<&ArchivedKeyFilter> {
+        self.key_filter.as_ref()
+    }
+
+    #[inline]
+    fn fallback(&self) -> &[u64] {
+        &self.fallback
+    }
+
+    #[inline(always)]
+    fn get_by_hash(&self, hash: u64, levels: impl FnOnce(u64) -> Option<usize>) -> Option<usize> {
+        if self.key_filter.as_ref().is_some_and(|filter| !filter.contains(hash)) {
+            return None;
+        }
+        search_fallback(hash ^ self.build_seed, &self.fallback, levels)
+    }
+}
+
+/// Returns the index of the key `hash` in the `fallback` table, or walks the `levels` otherwise,
+/// with indices of the levels following the ones of the fallback table.
+#[inline(always)]
+pub(crate) fn search_fallback(hash: u64, fallback: &[u64], levels: impl FnOnce(u64) -> Option<usize>) -> Option<usize> {
+    // fallback keys must be found before the levels, where they may hit bits of other keys
+    if !fallback.is_empty() {
+        if let Ok(idx) = fallback.binary_search(&hash) {
+            return Some(idx);
+        }
+    }
+    levels(hash).map(|rank| fallback.len() + rank)
+}
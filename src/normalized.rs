@@ -0,0 +1,244 @@
+//! A module providing `Normalized`, a wrapper applying key normalization to `MapWithDict` and `Set`.
+//!
+//! Some key spaces have several spellings of the same logical key, e.g. hostnames which are case
+//! insensitive. `Normalized` applies a `Normalizer` consistently at both construction and query
+//! time, so that lookups can't accidentally skip normalization at one of the call sites. Keys
+//! which normalize to the same value are deduplicated during construction, keeping the first one.
+//!
+//! Archived structures don't store the normalizer, queries against them should normalize keys
+//! with the same `Normalizer` via `Normalizer::normalize` explicitly.
+
+use std::borrow::{Borrow, Cow};
+use std::collections::HashSet;
+use std::hash::{Hash, Hasher};
+
+use num::{PrimInt, Unsigned};
+
+use crate::map_with_dict::MapWithDict;
+use crate::mphf::MphfError;
+use crate::set::Set;
+
+/// Normalizes keys of type `Q` before they are hashed and compared.
+pub trait Normalizer<Q: ?Sized + ToOwned> {
+    /// Returns normalized version of `key`, borrowing it if it's already normalized.
+    fn normalize<'a>(&self, key: &'a Q) -> Cow<'a, Q>;
+}
+
+/// Normalizes string keys by converting ASCII characters to lowercase (e.g. hostnames).
+#[derive(Debug, Clone, Copy, Default)]
+pub struct AsciiLowercase;
+
+impl Normalizer<str> for AsciiLowercase {
+    #[inline]
+    fn normalize<'a>(&self, key: &'a str) -> Cow<'a, str> {
+        if key.bytes().any(|b| b.is_ascii_uppercase()) {
+            Cow::Owned(key.to_ascii_lowercase())
+        } else {
+            Cow::Borrowed(key)
+        }
+    }
+}
+
+/// Any function with a suitable signature can be used as a `Normalizer`.
+impl<Q, F> Normalizer<Q> for F
+where
+    Q: ?Sized + ToOwned,
+    F: for<'a> Fn(&'a Q) -> Cow<'a, Q>,
+{
+    #[inline]
+    fn normalize<'a>(&self, key: &'a Q) -> Cow<'a, Q> {
+        self(key)
+    }
+}
+
+/// Wrapper around `MapWithDict` or `Set` normalizing keys with `N` at construction and query time.
+pub struct Normalized<T, N> {
+    /// Wrapped structure built from normalized keys
+    inner: T,
+    /// Normalizer applied to all keys
+    normalizer: N,
+}
+
+impl<T, N> Normalized<T, N> {
+    /// Returns a reference to the wrapped structure built from normalized keys.
+    #[inline]
+    pub fn inner(&self) -> &T {
+        &self.inner
+    }
+
+    /// Returns a reference to the normalizer.
+    #[inline]
+    pub fn normalizer(&self) -> &N {
+        &self.normalizer
+    }
+
+    /// Consumes the wrapper returning the wrapped structure and the normalizer.
+    #[inline]
+    pub fn into_parts(self) -> (T, N) {
+        (self.inner, self.normalizer)
+    }
+}
+
+impl<K, V, const B: usize, const S: usize, ST, H, N> Normalized<MapWithDict<K, V, B, S, ST, H>, N>
+where
+    K: Eq + Hash + Clone,
+    V: Eq + Clone + Hash,
+    ST: PrimInt + Unsigned,
+    H: Hasher + Default,
+{
+    /// Constructs a `MapWithDict` with keys normalized by `normalizer` from an iterator of key-value
+    /// pairs and MPHF function params. For keys normalizing to the same value the first pair wins.
+    ///
+    /// # Examples
+    /// ```
+    /// # use entropy_map::{AsciiLowercase, MapWithDict, Normalized, DEFAULT_GAMMA};
+    /// let map = Normalized::<MapWithDict<String, u32>, _>::from_iter_with_params(
+    ///     [("Example.COM".to_string(), 1)],
+    ///     DEFAULT_GAMMA,
+    ///     AsciiLowercase,
+    /// ).unwrap();
+    /// assert_eq!(map.get("example.com"), Some(&1));
+    /// assert_eq!(map.get("EXAMPLE.com"), Some(&1));
+    /// ```
+    pub fn from_iter_with_params<I, Q>(iter: I, gamma: f32, normalizer: N) -> Result<Self, MphfError>
+    where
+        I: IntoIterator<Item = (K, V)>,
+        K: Borrow<Q>,
+        Q: ?Sized + ToOwned<Owned = K>,
+        N: Normalizer<Q>,
+    {
+        let mut seen = HashSet::new();
+        let pairs: Vec<(K, V)> = iter
+            .into_iter()
+            .map(|(k, v)| (normalizer.normalize(k.borrow()).into_owned(), v))
+            .filter(|(k, _)| seen.insert(k.clone()))
+            .collect();
+
+        let inner = MapWithDict::from_iter_with_params(pairs, gamma)?;
+
+        Ok(Normalized { inner, normalizer })
+    }
+
+    /// Returns a reference to the value corresponding to the normalized key. Returns `None` if the
+    /// key is not present in the map.
+    #[inline]
+    pub fn get<Q>(&self, key: &Q) -> Option<&V>
+    where
+        K: Borrow<Q> + PartialEq<Q>,
+        Q: Hash + Eq + ?Sized + ToOwned,
+        N: Normalizer<Q>,
+    {
+        self.inner.get(self.normalizer.normalize(key).as_ref())
+    }
+
+    /// Checks if the map contains the specified normalized key.
+    #[inline]
+    pub fn contains_key<Q>(&self, key: &Q) -> bool
+    where
+        K: Borrow<Q> + PartialEq<Q>,
+        Q: Hash + Eq + ?Sized + ToOwned,
+        N: Normalizer<Q>,
+    {
+        self.inner.contains_key(self.normalizer.normalize(key).as_ref())
+    }
+}
+
+impl<K, const B: usize, const S: usize, ST, H, N> Normalized<Set<K, B, S, ST, H>, N>
+where
+    K: Eq + Hash,
+    ST: PrimInt + Unsigned,
+    H: Hasher + Default,
+{
+    /// Constructs a `Set` with keys normalized by `normalizer` from an iterator of keys and MPHF
+    /// function params.
+    ///
+    /// # Examples
+    /// ```
+    /// # use entropy_map::{AsciiLowercase, Normalized, Set, DEFAULT_GAMMA};
+    /// let set = Normalized::<Set<String>, _>::from_iter_with_params(
+    ///     ["Example.COM".to_string(), "example.com".to_string()],
+    ///     DEFAULT_GAMMA,
+    ///     AsciiLowercase,
+    /// ).unwrap();
+    /// assert_eq!(set.inner().len(), 1);
+    /// assert!(set.contains("EXAMPLE.com"));
+    /// ```
+    pub fn from_iter_with_params<I, Q>(iter: I, gamma: f32, normalizer: N) -> Result<Self, MphfError>
+    where
+        I: IntoIterator<Item = K>,
+        K: Borrow<Q>,
+        Q: ?Sized + ToOwned<Owned = K>,
+        N: Normalizer<Q>,
+    {
+        let keys: HashSet<K> = iter
+            .into_iter()
+            .map(|k| normalizer.normalize(k.borrow()).into_owned())
+            .collect();
+
+        let inner = Set::from_iter_with_params(keys, gamma)?;
+
+        Ok(Normalized { inner, normalizer })
+    }
+
+    /// Returns `true` if the set contains the normalized value.
+    #[inline]
+    pub fn contains<Q>(&self, key: &Q) -> bool
+    where
+        K: Borrow<Q> + PartialEq<Q>,
+        Q: Hash + Eq + ?Sized + ToOwned,
+        N: Normalizer<Q>,
+    {
+        self.inner.contains(self.normalizer.normalize(key).as_ref())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mphf::DEFAULT_GAMMA;
+
+    #[test]
+    fn test_ascii_lowercase() {
+        assert!(matches!(
+            AsciiLowercase.normalize("example.com"),
+            Cow::Borrowed("example.com")
+        ));
+        assert_eq!(AsciiLowercase.normalize("ExAmPlE.CoM"), "example.com");
+        assert_eq!(AsciiLowercase.normalize("ÄBC"), "Äbc");
+    }
+
+    #[test]
+    fn test_normalized_map() {
+        let pairs = [
+            ("a.COM".to_string(), 1),
+            ("B.com".to_string(), 2),
+            ("A.com".to_string(), 3),
+        ];
+        let map =
+            Normalized::<MapWithDict<String, u32>, _>::from_iter_with_params(pairs, DEFAULT_GAMMA, AsciiLowercase)
+                .unwrap();
+
+        assert_eq!(map.inner().len(), 2);
+        assert_eq!(map.get("a.com"), Some(&1));
+        assert_eq!(map.get("A.Com"), Some(&1));
+        assert_eq!(map.get("b.COM"), Some(&2));
+        assert!(map.contains_key("B.COM"));
+        assert_eq!(map.get("c.com"), None);
+        assert!(!map.contains_key("c.com"));
+    }
+
+    #[test]
+    fn test_normalized_set_with_fn() {
+        fn trim_dot(key: &str) -> Cow<'_, str> {
+            Cow::Borrowed(key.trim_end_matches('.'))
+        }
+
+        let keys = ["a.com.".to_string(), "b.com".to_string()];
+        let set = Normalized::<Set<String>, _>::from_iter_with_params(keys, DEFAULT_GAMMA, trim_dot).unwrap();
+
+        assert!(set.contains("a.com"));
+        assert!(set.contains("a.com."));
+        assert!(set.contains("b.com."));
+        assert!(!set.contains("c.com"));
+    }
+}
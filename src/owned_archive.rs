@@ -0,0 +1,214 @@
+//! A module providing `OwnedArchive`, a validated archive owning its bytes.
+//!
+//! `rkyv::check_archived_root` returns a reference borrowing the archive bytes, so consumers keeping
+//! archived structures around (e.g. in a service state swapped on reloads) have to store the bytes
+//! and re-derive the archived reference, or resort to self-referential structs. `OwnedArchive`
+//! owns the bytes, either a `Vec<u8>`, an `Arc<[u8]>` shared with other owners or a memory-mapped
+//! file (with `mmap` feature), validates them once at construction and dereferences to the archived
+//! structure. `OwnedArchivedMap` and `OwnedArchivedSet` are shorthands for the common structures.
+//!
+//! Archives must be aligned to 16 bytes as produced by `rkyv::to_bytes`. Unaligned `Vec<u8>` and
+//! `Arc<[u8]>` buffers are copied into an aligned one, memory-mapped files are page aligned.
+
+use std::fs;
+use std::io;
+use std::marker::PhantomData;
+use std::ops::Deref;
+use std::path::Path;
+use std::sync::Arc;
+
+use rkyv::validation::validators::DefaultValidator;
+use rkyv::{AlignedVec, Archive, Archived, CheckBytes};
+
+use crate::map_with_dict::MapWithDict;
+use crate::set::Set;
+
+/// Alignment of archive bytes required to access them in place.
+const ARCHIVE_ALIGN: usize = 16;
+
+/// Errors that can occur when creating an `OwnedArchive`.
+#[derive(Debug)]
+pub enum OwnedArchiveError {
+    /// Error when reading the archive file failed.
+    Io(io::Error),
+    /// Error when the bytes are not a valid archive, with the validation error message.
+    Invalid(String),
+}
+
+impl From<io::Error> for OwnedArchiveError {
+    #[inline]
+    fn from(err: io::Error) -> Self {
+        OwnedArchiveError::Io(err)
+    }
+}
+
+/// Bytes owned by an `OwnedArchive` or a `Store`.
+pub(crate) enum Buffer {
+    /// Bytes aligned by `rkyv`
+    Aligned(AlignedVec),
+    /// Aligned bytes of a `Vec`
+    Vec(Vec<u8>),
+    /// Aligned bytes shared with other owners
+    Shared(Arc<[u8]>),
+    /// Memory-mapped file
+    #[cfg(feature = "mmap")]
+    Mmap(memmap2::Mmap),
+}
+
+impl Buffer {
+    /// Returns the bytes of the buffer.
+    #[inline]
+    pub(crate) fn bytes(&self) -> &[u8] {
+        match self {
+            Buffer::Aligned(bytes) => bytes,
+            Buffer::Vec(bytes) => bytes,
+            Buffer::Shared(bytes) => bytes,
+            #[cfg(feature = "mmap")]
+            Buffer::Mmap(bytes) => bytes,
+        }
+    }
+}
+
+/// Copies unaligned `bytes` into an aligned buffer, returning `None` if they are aligned already.
+pub(crate) fn align(bytes: &[u8]) -> Option<Buffer> {
+    if (bytes.as_ptr() as usize).is_multiple_of(ARCHIVE_ALIGN) {
+        return None;
+    }
+    let mut aligned = AlignedVec::with_capacity(bytes.len());
+    aligned.extend_from_slice(bytes);
+    Some(Buffer::Aligned(aligned))
+}
+
+/// Archive of `T` owning its bytes, validated at construction and dereferencing to the archived `T`.
+///
+/// # Examples
+/// ```
+/// # use std::collections::HashMap;
+/// # use entropy_map::{MapWithDict, OwnedArchivedMap};
+/// let map = MapWithDict::try_from(HashMap::from([(1u32, 2u32), (3, 4)])).unwrap();
+/// let bytes = rkyv::to_bytes::<_, 1024>(&map).unwrap().into_vec();
+///
+/// let archive = OwnedArchivedMap::<u32, u32>::from_vec(bytes).unwrap();
+/// assert_eq!(archive.get(&1), Some(&2));
+/// assert!(OwnedArchivedMap::<u32, u32>::from_vec(vec![0; 4]).is_err());
+/// ```
+pub struct OwnedArchive<T: Archive> {
+    /// Archive bytes
+    buffer: Buffer,
+    /// Phantom field for the archived type
+    _phantom: PhantomData<fn() -> T>,
+}
+
+/// Archive of `MapWithDict<K, V>` owning its bytes.
+pub type OwnedArchivedMap<K, V> = OwnedArchive<MapWithDict<K, V>>;
+
+/// Archive of `Set<K>` owning its bytes.
+pub type OwnedArchivedSet<K> = OwnedArchive<Set<K>>;
+
+impl<T: Archive> OwnedArchive<T>
+where
+    Archived<T>: for<'a> CheckBytes<DefaultValidator<'a>>,
+{
+    /// Validates archive `bytes` of `T`, copying them only if they are not aligned.
+    pub fn from_vec(bytes: Vec<u8>) -> Result<Self, OwnedArchiveError> {
+        Self::new(align(&bytes).unwrap_or(Buffer::Vec(bytes)))
+    }
+
+    /// Validates archive `bytes` of `T`, e.g. as returned by `rkyv::to_bytes`.
+    pub fn from_aligned_vec(bytes: AlignedVec) -> Result<Self, OwnedArchiveError> {
+        Self::new(Buffer::Aligned(bytes))
+    }
+
+    /// Validates archive `bytes` of `T` shared with other owners, copying them only if they are
+    /// not aligned.
+    pub fn from_arc(bytes: Arc<[u8]>) -> Result<Self, OwnedArchiveError> {
+        Self::new(align(&bytes).unwrap_or(Buffer::Shared(bytes)))
+    }
+
+    /// Reads and validates the archive of `T` stored in the file at `path`.
+    pub fn from_path(path: impl AsRef<Path>) -> Result<Self, OwnedArchiveError> {
+        Self::from_vec(fs::read(path)?)
+    }
+
+    /// Validates the archive of `T` stored in the memory-mapped file `mmap`.
+    #[cfg(feature = "mmap")]
+    pub fn from_mmap(mmap: memmap2::Mmap) -> Result<Self, OwnedArchiveError> {
+        Self::new(align(&mmap).unwrap_or(Buffer::Mmap(mmap)))
+    }
+
+    /// Validates the aligned bytes of `buffer` as an archive of `T`.
+    fn new(buffer: Buffer) -> Result<Self, OwnedArchiveError> {
+        rkyv::check_archived_root::<T>(buffer.bytes()).map_err(|err| OwnedArchiveError::Invalid(err.to_string()))?;
+        Ok(OwnedArchive { buffer, _phantom: PhantomData })
+    }
+}
+
+impl<T: Archive> OwnedArchive<T> {
+    /// Returns the archive bytes.
+    #[inline]
+    pub fn bytes(&self) -> &[u8] {
+        self.buffer.bytes()
+    }
+}
+
+impl<T: Archive> Deref for OwnedArchive<T> {
+    type Target = Archived<T>;
+
+    #[inline]
+    fn deref(&self) -> &Archived<T> {
+        // SAFETY: bytes were validated as an archive of `T` at construction and are never modified
+        unsafe { rkyv::archived_root::<T>(self.buffer.bytes()) }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::{HashMap, HashSet};
+
+    #[test]
+    fn test_owned_archive() {
+        let map = MapWithDict::try_from(HashMap::from([(1u64, 2u64), (3, 4)])).unwrap();
+        let bytes = rkyv::to_bytes::<_, 1024>(&map).unwrap();
+
+        let archive = OwnedArchivedMap::<u64, u64>::from_aligned_vec(bytes.clone()).unwrap();
+        assert_eq!(archive.get(&3), Some(&4));
+        assert_eq!(archive.bytes(), bytes.as_slice());
+
+        let shared: Arc<[u8]> = bytes.as_slice().into();
+        let archive = OwnedArchivedMap::<u64, u64>::from_arc(shared.clone()).unwrap();
+        assert_eq!(archive.get(&1), Some(&2));
+
+        // unaligned bytes are copied
+        let mut unaligned = vec![0u8; bytes.len() + 1];
+        unaligned[1..].copy_from_slice(&bytes);
+        let unaligned: Arc<[u8]> = unaligned[1..].into();
+        let archive = OwnedArchivedMap::<u64, u64>::from_arc(unaligned).unwrap();
+        assert_eq!(archive.get(&1), Some(&2));
+        assert_eq!(archive.bytes().as_ptr() as usize % ARCHIVE_ALIGN, 0);
+
+        // archives can be moved and sent across threads
+        let archive = std::thread::spawn(move || {
+            assert_eq!(archive.get(&3), Some(&4));
+            archive
+        })
+        .join()
+        .unwrap();
+        assert_eq!(archive.iter().len(), 2);
+
+        let set = Set::try_from(HashSet::from([1u32, 2, 3])).unwrap();
+        let path = std::env::temp_dir().join(format!("entropy-map-owned-archive-{}", std::process::id()));
+        fs::write(&path, rkyv::to_bytes::<_, 1024>(&set).unwrap()).unwrap();
+        let archive = OwnedArchivedSet::<u32>::from_path(&path).unwrap();
+        assert!(archive.contains(&2));
+        assert!(matches!(
+            OwnedArchivedMap::<u64, u64>::from_path(&path),
+            Err(OwnedArchiveError::Invalid(_))
+        ));
+        fs::remove_file(&path).unwrap();
+        assert!(matches!(
+            OwnedArchivedSet::<u32>::from_path(&path),
+            Err(OwnedArchiveError::Io(_))
+        ));
+    }
+}
@@ -0,0 +1,62 @@
+//! Helpers running construction of MPHF-based structures on a dedicated `rayon` thread pool.
+//!
+//! With the `rayon` feature enabled, MPHF construction searches group seeds of large levels in
+//! parallel on the current `rayon` thread pool, which is the global pool unless construction runs
+//! inside [`rayon::ThreadPool::install`]. [`build_in_pool`] runs construction on a given pool
+//! instead, e.g. a dedicated low-priority pool, so that it can't starve threads of the global
+//! pool. Built structures don't depend on the pool size.
+
+use rayon::ThreadPool;
+
+/// Runs `build` on `pool` if provided, or on the current thread otherwise, so that parallel
+/// construction done by `build` uses `pool` instead of the global `rayon` thread pool.
+///
+/// # Examples
+/// ```
+/// # use entropy_map::{build_in_pool, Set};
+/// let pool = rayon::ThreadPoolBuilder::new().num_threads(2).build().unwrap();
+/// let set = build_in_pool(Some(&pool), || Set::<u32>::from_iter_with_params(0..100_000, 2.0)).unwrap();
+/// assert!(set.contains(&1));
+/// ```
+pub fn build_in_pool<T, F>(pool: Option<&ThreadPool>, build: F) -> T
+where
+    F: FnOnce() -> T + Send,
+    T: Send,
+{
+    match pool {
+        Some(pool) => pool.install(build),
+        None => build(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mphf::Mphf;
+    use rayon::ThreadPoolBuilder;
+    use test_case::test_case;
+
+    #[test_case(1_000, 1; "small level single thread")]
+    #[test_case(1_000, 4; "small level")]
+    #[test_case(100_000, 1; "large levels single thread")]
+    #[test_case(100_000, 4; "large levels")]
+    #[test_case(100_000, 7; "large levels odd threads")]
+    fn test_build_in_pool(n: u64, threads: usize) {
+        let keys: Vec<u64> = (0..n).collect();
+        let expected = Mphf::<32, 8>::from_slice(&keys, 2.0).unwrap();
+
+        let pool = ThreadPoolBuilder::new().num_threads(threads).build().unwrap();
+        let mphf = build_in_pool(Some(&pool), || Mphf::<32, 8>::from_slice(&keys, 2.0)).unwrap();
+
+        // parallel construction must build the same MPHF regardless of the pool size
+        assert_eq!(mphf.level_groups(), expected.level_groups());
+        assert_eq!(mphf.bits_len(), expected.bits_len());
+        for idx in 0..n as usize {
+            assert_eq!(mphf.locate(idx), expected.locate(idx));
+        }
+        for group_idx in 0..mphf.level_groups().iter().sum::<u32>() as usize {
+            assert_eq!(mphf.group_seed(group_idx), expected.group_seed(group_idx));
+        }
+        mphf.debug_check_invariants().unwrap();
+    }
+}
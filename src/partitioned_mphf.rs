@@ -0,0 +1,356 @@
+//! A module providing `PartitionedMphf`, an MPHF split into independently built partitions.
+//!
+//! Levels of a single `Mphf` are built one after another, each one only parallelized across its
+//! groups (with `rayon` feature), which leaves cores idle on the small deeper levels and makes a
+//! single build of hundreds of millions of keys take minutes. `PartitionedMphf` splits key hashes
+//! into a power of two number of partitions by their high bits and builds an independent `Mphf` per
+//! partition, in parallel with `rayon` feature. Queries select the partition by the same hash
+//! bits and add the partition offset, i.e. the number of keys of all previous partitions, to the
+//! index returned by its `Mphf`, so that indices are in `0..n` range as of a monolithic MPHF.
+
+use std::hash::{Hash, Hasher};
+use std::mem::size_of_val;
+use std::time::Instant;
+
+use num::{PrimInt, Unsigned};
+#[cfg(feature = "rayon")]
+use rayon::prelude::*;
+use wyhash::WyHash;
+
+use crate::hashed_key::HashedKey;
+use crate::mphf::{hash_key_seeded, BuildPolicy, Mphf, MphfError};
+use crate::report::{BuildReport, LevelReport};
+
+/// Number of keys per partition targeted by [`PartitionedMphf::from_slice`].
+pub const PARTITION_KEYS: usize = 1 << 20;
+
+/// Maximum number of partitions, keeping the offsets table small.
+const MAX_PARTITION_BITS: u32 = 16;
+
+/// MPHF split into independently built partitions, see the module documentation.
+///
+/// # Examples
+/// ```
+/// # use entropy_map::{BuildPolicy, PartitionedMphf};
+/// let keys: Vec<u64> = (0..10_000).collect();
+/// let (mphf, report) = PartitionedMphf::<32, 8>::from_slice_with_policy(&keys, &BuildPolicy::default(), 4).unwrap();
+/// assert_eq!((mphf.partitions(), mphf.len()), (4, 10_000));
+/// assert_eq!(report.keys(), 10_000);
+///
+/// let mut indices: Vec<usize> = keys.iter().map(|key| mphf.get(key).unwrap()).collect();
+/// indices.sort_unstable();
+/// assert!(indices.into_iter().eq(0..keys.len()));
+/// ```
+#[derive(Default)]
+#[cfg_attr(feature = "rkyv_derive", derive(rkyv::Archive, rkyv::Deserialize, rkyv::Serialize))]
+#[cfg_attr(feature = "rkyv_derive", archive_attr(derive(rkyv::CheckBytes)))]
+pub struct PartitionedMphf<
+    const B: usize = 32,
+    const S: usize = 8,
+    ST: PrimInt + Unsigned = u8,
+    H: Hasher + Default = WyHash,
+> {
+    /// MPHFs of the partitions, built from hashes computed with `hash_seed`
+    partitions: Box<[Mphf<B, S, ST, H>]>,
+    /// Index of the first key of every partition, followed by the total number of keys
+    offsets: Box<[u64]>,
+    /// Number of high hash bits selecting the partition
+    partition_bits: u32,
+    /// Seed hashed before every key, 0 for unkeyed hashing
+    hash_seed: u64,
+}
+
+/// Returns the partition of key `hash` selected by its `partition_bits` high bits.
+#[inline(always)]
+pub(crate) fn partition_idx(hash: u64, partition_bits: u32) -> usize {
+    hash.checked_shr(64 - partition_bits).unwrap_or(0) as usize
+}
+
+impl<const B: usize, const S: usize, ST, H> PartitionedMphf<B, S, ST, H>
+where
+    ST: PrimInt + Unsigned + Send,
+    H: Hasher + Default + Send,
+{
+    /// Initializes `PartitionedMphf` using slice of `keys` and parameter `gamma`, with a partition
+    /// per `PARTITION_KEYS` keys.
+    pub fn from_slice<K: Hash>(keys: &[K], gamma: f32) -> Result<Self, MphfError> {
+        let partitions = keys.len() / PARTITION_KEYS;
+        Self::from_slice_with_policy(keys, &BuildPolicy::fixed(gamma), partitions).map(|(mphf, _)| mphf)
+    }
+
+    /// Initializes `PartitionedMphf` using slice of `keys` split into `partitions` partitions
+    /// (rounded up to a power of two, at most 2^16), building each one as configured by `policy`.
+    ///
+    /// The returned `BuildReport` sums keys and durations of the same level across partitions,
+    /// without `LevelDiagnostics`, and holds the largest `gamma` and number of attempts of any
+    /// partition. Its `duration` is the total construction time.
+    pub fn from_slice_with_policy<K: Hash>(
+        keys: &[K],
+        policy: &BuildPolicy,
+        partitions: usize,
+    ) -> Result<(Self, BuildReport), MphfError> {
+        let t0 = Instant::now();
+        let partition_bits = Self::partition_bits(partitions);
+        let hash_seed = policy.hash_seed;
+
+        let mut buckets = vec![vec![]; 1 << partition_bits];
+        for key in keys {
+            let hash = hash_key_seeded::<H, _>(hash_seed, key);
+            buckets[partition_idx(hash, partition_bits)].push(hash);
+        }
+
+        #[cfg(not(feature = "rayon"))]
+        let built = buckets.iter();
+        #[cfg(feature = "rayon")]
+        let built = buckets.par_iter();
+        let built = built
+            .map(|hashes| Mphf::from_hashes_with_policy(hashes, policy))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let keys_per_partition = buckets.iter().map(Vec::len);
+        Ok(Self::from_partitions(
+            built,
+            keys_per_partition,
+            partition_bits,
+            hash_seed,
+            t0,
+        ))
+    }
+}
+
+impl<const B: usize, const S: usize, ST: PrimInt + Unsigned, H: Hasher + Default> PartitionedMphf<B, S, ST, H> {
+    /// Returns the number of partition bits for `partitions` partitions, rounded up to a power of
+    /// two and capped at 2^16.
+    pub(crate) fn partition_bits(partitions: usize) -> u32 {
+        partitions
+            .max(1)
+            .next_power_of_two()
+            .trailing_zeros()
+            .min(MAX_PARTITION_BITS)
+    }
+
+    /// Assembles `PartitionedMphf` from the MPHFs and reports `built` for every partition, with
+    /// `keys_per_partition` keys, for a construction started at `t0`.
+    pub(crate) fn from_partitions(
+        built: Vec<(Mphf<B, S, ST, H>, BuildReport)>,
+        keys_per_partition: impl Iterator<Item = usize>,
+        partition_bits: u32,
+        hash_seed: u64,
+        t0: Instant,
+    ) -> (Self, BuildReport) {
+        let mut offsets = Vec::with_capacity(built.len() + 1);
+        offsets.push(0);
+        for keys in keys_per_partition {
+            offsets.push(offsets.last().unwrap() + keys as u64);
+        }
+
+        let mut report = BuildReport::default();
+        let mut partitions = Vec::with_capacity(built.len());
+        for (mphf, partition_report) in built {
+            merge_report(&mut report, partition_report);
+            partitions.push(mphf);
+        }
+
+        let mphf = PartitionedMphf {
+            partitions: partitions.into_boxed_slice(),
+            offsets: offsets.into_boxed_slice(),
+            partition_bits,
+            hash_seed,
+        };
+        report.set_size(mphf.size());
+        report.duration = t0.elapsed();
+        (mphf, report)
+    }
+}
+
+/// Adds the statistics of the `partition` report to the `report` of all partitions.
+fn merge_report(report: &mut BuildReport, partition: BuildReport) {
+    if report.levels.len() < partition.levels.len() {
+        report.levels.resize(partition.levels.len(), LevelReport::default());
+    }
+    for (level, partition_level) in report.levels.iter_mut().zip(partition.levels) {
+        level.duration += partition_level.duration;
+        level.keys += partition_level.keys;
+        level.retained_keys += partition_level.retained_keys;
+    }
+    report.gamma = report.gamma.max(partition.gamma);
+    report.attempts = report.attempts.max(partition.attempts);
+    report.fallback_keys += partition.fallback_keys;
+}
+
+impl<const B: usize, const S: usize, ST: PrimInt + Unsigned, H: Hasher + Default> PartitionedMphf<B, S, ST, H> {
+    /// Returns the index associated with `key`, see [`Mphf::get`].
+    #[inline]
+    pub fn get<K: Hash + ?Sized>(&self, key: &K) -> Option<usize> {
+        self.get_by_hash(self.hash_key(key))
+    }
+
+    /// Same as [`PartitionedMphf::get`], but re-uses the hash precomputed by `HashedKey`.
+    #[inline]
+    pub fn get_hashed<K: ?Sized>(&self, key: &HashedKey<K, H>) -> Option<usize> {
+        // hashes computed with another seed would map to arbitrary indices
+        if key.hash_seed() != self.hash_seed {
+            return None;
+        }
+        self.get_by_hash(key.hash())
+    }
+
+    /// Returns the index associated with the key `hash`, see [`Mphf::get_by_hash`].
+    #[inline]
+    pub fn get_by_hash(&self, hash: u64) -> Option<usize> {
+        let partition = partition_idx(hash, self.partition_bits);
+        let idx = self.partitions.get(partition)?.get_by_hash(hash)?;
+        Some(self.offsets[partition] as usize + idx)
+    }
+
+    /// Returns the hash of `key` computed with hasher `H` and the hash seed, see [`Mphf::hash_key`].
+    #[inline]
+    pub fn hash_key<K: Hash + ?Sized>(&self, key: &K) -> u64 {
+        hash_key_seeded::<H, _>(self.hash_seed, key)
+    }
+
+    /// Returns the seed hashed before every key, see [`Mphf::hash_seed`].
+    #[inline]
+    pub fn hash_seed(&self) -> u64 {
+        self.hash_seed
+    }
+
+    /// Returns the number of partitions.
+    #[inline]
+    pub fn partitions(&self) -> usize {
+        self.partitions.len()
+    }
+
+    /// Returns the MPHF of partition `idx`, queried with hashes of its keys, or `None` if `idx` is
+    /// out of bounds.
+    #[inline]
+    pub fn partition(&self, idx: usize) -> Option<&Mphf<B, S, ST, H>> {
+        self.partitions.get(idx)
+    }
+
+    /// Returns the number of keys.
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.offsets.last().map_or(0, |&len| len as usize)
+    }
+
+    /// Returns `true` if there are no keys.
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Returns the total number of bytes occupied by `PartitionedMphf`
+    pub fn size(&self) -> usize {
+        size_of_val(self) + size_of_val(self.offsets.as_ref()) + self.partitions.iter().map(Mphf::size).sum::<usize>()
+    }
+}
+
+/// Implement `get` for `Archived` version of `PartitionedMphf` if feature is enabled
+#[cfg(feature = "rkyv_derive")]
+impl<const B: usize, const S: usize, ST, H> ArchivedPartitionedMphf<B, S, ST, H>
+where
+    ST: PrimInt + Unsigned + rkyv::Archive<Archived = ST>,
+    H: Hasher + Default,
+{
+    /// Returns the index associated with `key`, see [`Mphf::get`].
+    #[inline]
+    pub fn get<K: Hash + ?Sized>(&self, key: &K) -> Option<usize> {
+        self.get_by_hash(hash_key_seeded::<H, _>(self.hash_seed, key))
+    }
+
+    /// Same as [`ArchivedPartitionedMphf::get`], but re-uses the hash precomputed by `HashedKey`.
+    #[inline]
+    pub fn get_hashed<K: ?Sized>(&self, key: &HashedKey<K, H>) -> Option<usize> {
+        // hashes computed with another seed would map to arbitrary indices
+        if key.hash_seed() != self.hash_seed {
+            return None;
+        }
+        self.get_by_hash(key.hash())
+    }
+
+    /// Returns the index associated with the key `hash`, see [`Mphf::get_by_hash`].
+    #[inline]
+    pub fn get_by_hash(&self, hash: u64) -> Option<usize> {
+        let partition = partition_idx(hash, self.partition_bits);
+        let idx = self.partitions.as_ref().get(partition)?.get_by_hash(hash)?;
+        Some(self.offsets[partition] as usize + idx)
+    }
+
+    /// Returns the number of keys.
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.offsets.last().map_or(0, |&len| len as usize)
+    }
+
+    /// Returns `true` if there are no keys.
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mphf::DEFAULT_GAMMA;
+
+    fn check_indices(mphf: &PartitionedMphf, keys: &[u64]) {
+        let mut indices: Vec<usize> = keys.iter().map(|key| mphf.get(key).unwrap()).collect();
+        indices.sort_unstable();
+        assert!(indices.into_iter().eq(0..keys.len()));
+    }
+
+    #[test]
+    fn test_partitioned_mphf() {
+        let keys: Vec<u64> = (0..50_000).collect();
+        for partitions in [0, 1, 3, 8, 64] {
+            let (mphf, report) =
+                PartitionedMphf::from_slice_with_policy(&keys, &BuildPolicy::default(), partitions).unwrap();
+            assert_eq!(mphf.partitions(), partitions.max(1).next_power_of_two());
+            assert_eq!(mphf.len(), keys.len());
+            assert_eq!(report.keys(), keys.len());
+            assert_eq!(report.levels.last().unwrap().retained_keys, 0);
+            check_indices(&mphf, &keys);
+            assert_eq!(mphf.get_hashed(&HashedKey::new(&7u64)), mphf.get(&7u64));
+        }
+
+        // partitions are balanced by the hash bits selecting them
+        let mphf: PartitionedMphf = PartitionedMphf::from_slice_with_policy(&keys, &BuildPolicy::default(), 4)
+            .unwrap()
+            .0;
+        for idx in 0..4 {
+            let partition_keys = mphf.offsets[idx + 1] - mphf.offsets[idx];
+            assert!(
+                partition_keys.abs_diff(12_500) < 1_000,
+                "partition_keys = {partition_keys}"
+            );
+        }
+        assert!(mphf.partition(4).is_none());
+
+        let keyed = PartitionedMphf::from_slice_with_policy(&keys, &BuildPolicy::keyed(), 4)
+            .unwrap()
+            .0;
+        assert_ne!(keyed.hash_seed(), 0);
+        check_indices(&keyed, &keys);
+
+        let empty: PartitionedMphf = PartitionedMphf::from_slice::<u64>(&[], DEFAULT_GAMMA).unwrap();
+        assert!(empty.is_empty());
+        assert_eq!(empty.get(&1u64), None);
+        assert!(PartitionedMphf::<32, 8>::default().get(&1u64).is_none());
+    }
+
+    #[cfg(feature = "rkyv_derive")]
+    #[test]
+    fn test_rkyv() {
+        let keys: Vec<u64> = (0..10_000).collect();
+        let (mphf, _) = PartitionedMphf::<32, 8>::from_slice_with_policy(&keys, &BuildPolicy::default(), 8).unwrap();
+        let rkyv_bytes = rkyv::to_bytes::<_, 1024>(&mphf).unwrap();
+        let rkyv_mphf = rkyv::check_archived_root::<PartitionedMphf<32, 8>>(&rkyv_bytes).unwrap();
+
+        assert_eq!(rkyv_mphf.len(), mphf.len());
+        for key in &keys {
+            assert_eq!(rkyv_mphf.get(key), mphf.get(key));
+        }
+    }
+}
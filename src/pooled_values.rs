@@ -0,0 +1,112 @@
+//! A module providing `PooledValues`, a values buffer taken from a thread-local pool.
+//!
+//! `MapWithDictBitpacked::get_values` decodes values into a caller-provided `&mut [u32]`, which is
+//! awkward to carry around e.g. through async code. `get_values_pooled` decodes them into a
+//! `PooledValues` buffer instead, which is returned to the pool of the thread dropping it, so that
+//! after warm-up queries don't allocate. Guards are `Send`, e.g. can be held across `.await`.
+
+use std::cell::RefCell;
+use std::mem;
+use std::ops::{Deref, DerefMut};
+
+/// Maximum number of buffers kept in the pool of every thread, bounding the memory retained by
+/// threads which dropped many guards taken on other threads.
+const MAX_POOLED: usize = 16;
+
+thread_local! {
+    /// Buffers of dropped `PooledValues` of the current thread.
+    static POOL: RefCell<Vec<Vec<u32>>> = const { RefCell::new(Vec::new()) };
+}
+
+/// Values buffer taken from a thread-local pool and returned to it on drop, dereferencing to the
+/// values slice.
+///
+/// # Examples
+/// ```
+/// # use std::collections::HashMap;
+/// # use entropy_map::MapWithDictBitpacked;
+/// let map = MapWithDictBitpacked::try_from(HashMap::from([(1, vec![2, 3]), (4, vec![5, 6])])).unwrap();
+/// let values = map.get_values_pooled(&1, 2).unwrap();
+/// assert_eq!(*values, [2, 3]);
+/// assert!(map.get_values_pooled(&2, 2).is_none());
+/// ```
+#[derive(Debug)]
+pub struct PooledValues {
+    /// Buffer holding exactly the values
+    buf: Vec<u32>,
+}
+
+impl PooledValues {
+    /// Takes a buffer of `len` zeroes from the pool of the current thread, allocating only if the
+    /// pool is empty or the buffer is shorter than `len`.
+    pub fn take(len: usize) -> Self {
+        let mut buf = POOL
+            .try_with(|pool| pool.borrow_mut().pop())
+            .ok()
+            .flatten()
+            .unwrap_or_default();
+        buf.clear();
+        buf.resize(len, 0);
+        PooledValues { buf }
+    }
+
+    /// Returns the values as a `Vec`, which isn't returned to the pool.
+    pub fn into_vec(mut self) -> Vec<u32> {
+        mem::take(&mut self.buf)
+    }
+}
+
+impl Deref for PooledValues {
+    type Target = [u32];
+
+    #[inline]
+    fn deref(&self) -> &[u32] {
+        &self.buf
+    }
+}
+
+impl DerefMut for PooledValues {
+    #[inline]
+    fn deref_mut(&mut self) -> &mut [u32] {
+        &mut self.buf
+    }
+}
+
+impl Drop for PooledValues {
+    fn drop(&mut self) {
+        if self.buf.capacity() == 0 {
+            return;
+        }
+        let buf = mem::take(&mut self.buf);
+        // accesses fail only during thread teardown, when the buffer is simply freed
+        let _ = POOL.try_with(|pool| {
+            let mut pool = pool.borrow_mut();
+            if pool.len() < MAX_POOLED {
+                pool.push(buf);
+            }
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pool_reuse() {
+        let mut values = PooledValues::take(100);
+        values[99] = 1;
+        let ptr = values.as_ptr();
+        drop(values);
+
+        // buffer is re-used and zeroed
+        let values = PooledValues::take(50);
+        assert_eq!(values.as_ptr(), ptr);
+        assert_eq!(*values, [0; 50]);
+        assert_eq!(values.into_vec().len(), 50);
+
+        let guards: Vec<_> = (0..2 * MAX_POOLED).map(|_| PooledValues::take(1)).collect();
+        drop(guards);
+        assert_eq!(POOL.with(|pool| pool.borrow().len()), MAX_POOLED);
+    }
+}
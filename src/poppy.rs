@@ -0,0 +1,311 @@
+//! A module providing `PoppyRankedBits`, an alternative `Mphf` rank layout based on poppy.
+//!
+//! poppy (see [Space-Efficient, High-Performance Rank & Select Structures on Uncompressed Bit
+//! Sequences](https://www.cs.cmu.edu/~dga/papers/zhou-sea2013.pdf)) stores a 64-bit number of set
+//! bits before every 2^32-bit L0 block, and a single 64-bit word for every 2048-bit L1 block holding
+//! the 32-bit number of set bits before it within its L0 block followed by three 10-bit numbers of
+//! set bits of its first three 512-bit L2 blocks. Rank metadata overhead is ~3.125% like with
+//! `RankedBits`, but every L1 block entry fits into 8 bytes instead of 16, so that rank metadata of
+//! twice as many bits fits into the same cache line, suiting low-memory deployments.
+
+use std::mem::size_of_val;
+
+use crate::access;
+use crate::bit_slice::BitSlice;
+use crate::invariants::InvariantError;
+use crate::rank::{select_in_words, RankedBitsAccess, RankedBitsLayout};
+
+/// Size of the L0 block in bits.
+const L0_BIT_SIZE: u64 = 1 << 32;
+/// Size of the L1 block in bits.
+const L1_BIT_SIZE: u64 = 2048;
+/// Size of the L2 block in bits.
+const L2_BIT_SIZE: u64 = 512;
+/// Number of bit vector words in every L1 block.
+const L1_WORDS: usize = (L1_BIT_SIZE / 64) as usize;
+/// Number of bit vector words in every L2 block.
+const L2_WORDS: usize = (L2_BIT_SIZE / 64) as usize;
+/// Number of L1 blocks in every L0 block.
+const L0_L1_BLOCKS: usize = (L0_BIT_SIZE / L1_BIT_SIZE) as usize;
+
+/// Ranked bit vector with poppy layout, see the module documentation.
+///
+/// # Examples
+/// ```
+/// # use entropy_map::{Mphf, PoppyRankedBits, DEFAULT_GAMMA};
+/// let mphf = Mphf::<32, 8, u8, wyhash::WyHash, PoppyRankedBits>::from_slice(&[1, 2, 3], DEFAULT_GAMMA).unwrap();
+/// assert!(mphf.get(&1).unwrap() < 3);
+/// ```
+#[derive(Debug, Default)]
+#[cfg_attr(feature = "rkyv_derive", derive(rkyv::Archive, rkyv::Deserialize, rkyv::Serialize))]
+#[cfg_attr(feature = "rkyv_derive", archive_attr(derive(rkyv::CheckBytes)))]
+pub struct PoppyRankedBits {
+    /// The bit vector represented as an array of u64 integers.
+    bits: Box<[u64]>,
+    /// Number of set bits before every L0 block.
+    l0_ranks: Box<[u64]>,
+    /// L1 rank relative to the L0 block in the lower 32 bits, followed by 10-bit numbers of set bits
+    /// of the first three L2 blocks of every L1 block.
+    l12_ranks: Box<[u64]>,
+}
+
+/// Computes `l0_ranks` and `l12_ranks` of `bits`.
+fn compute_ranks(bits: &[u64]) -> (Vec<u64>, Vec<u64>) {
+    let mut l0_ranks = vec![];
+    let mut l12_ranks = vec![];
+    let mut rank = 0u64;
+    for (l1_pos, l1_words) in bits.chunks(L1_WORDS).enumerate() {
+        if l1_pos % L0_L1_BLOCKS == 0 {
+            l0_ranks.push(rank);
+        }
+
+        let mut l12_rank = rank - l0_ranks[l0_ranks.len() - 1];
+        for (l2_pos, l2_words) in l1_words.chunks(L2_WORDS).enumerate() {
+            let ones = l2_words.iter().map(|word| word.count_ones() as u64).sum::<u64>();
+            if l2_pos < 3 {
+                l12_rank |= ones << (32 + 10 * l2_pos);
+            }
+            rank += ones;
+        }
+        l12_ranks.push(l12_rank);
+    }
+    (l0_ranks, l12_ranks)
+}
+
+impl PoppyRankedBits {
+    /// Initializes `PoppyRankedBits` with a provided bit vector.
+    pub fn new(bits: Box<[u64]>) -> Self {
+        let (l0_ranks, l12_ranks) = compute_ranks(&bits);
+        PoppyRankedBits {
+            bits,
+            l0_ranks: l0_ranks.into_boxed_slice(),
+            l12_ranks: l12_ranks.into_boxed_slice(),
+        }
+    }
+
+    /// Returns the number of bytes occupied by rank metadata on top of the bits.
+    ///
+    /// # Examples
+    /// ```
+    /// # use entropy_map::PoppyRankedBits;
+    /// let ranked_bits = PoppyRankedBits::new(vec![u64::MAX; 128].into_boxed_slice());
+    /// assert_eq!(ranked_bits.overhead(), 8 + 32);
+    /// ```
+    pub fn overhead(&self) -> usize {
+        size_of_val(self.l0_ranks.as_ref()) + size_of_val(self.l12_ranks.as_ref())
+    }
+
+    /// Returns the number of set bits before the L1 block `l1_pos`.
+    ///
+    /// # Safety
+    /// `l1_pos` must be within the bounds of `l12_ranks` and `l0_ranks` must hold ranks of all its
+    /// L0 blocks.
+    #[inline]
+    unsafe fn l1_rank(l0_ranks: &[u64], l12_ranks: &[u64], l1_pos: usize) -> u64 {
+        *access::get(l0_ranks, l1_pos / L0_L1_BLOCKS) + (*access::get(l12_ranks, l1_pos) & 0xFFFF_FFFF)
+    }
+
+    /// Returns the number of set bits of the L1 block with L1 and L2 ranks `l12_rank` before its L2
+    /// block `l2_pos`.
+    #[inline]
+    fn l2_rank(l12_rank: u64, l2_pos: usize) -> u64 {
+        (0..l2_pos).map(|pos| (l12_rank >> (32 + 10 * pos)) & 0x3FF).sum()
+    }
+
+    /// Inner implementation of `rank` with `bits`, `l0_ranks` and `l12_ranks` passed from standard
+    /// and `Archived` versions.
+    ///
+    /// # Safety
+    /// `idx` must be within the bounds of `bits` and rank metadata must hold ranks of all its blocks.
+    #[inline]
+    unsafe fn rank_impl(bits: &[u64], l0_ranks: &[u64], l12_ranks: &[u64], idx: u64) -> Option<usize> {
+        let bits = BitSlice::new(bits);
+        if !bits.get_unchecked(idx) {
+            return None;
+        }
+
+        let l1_pos = (idx / L1_BIT_SIZE) as usize;
+        let l2_pos = ((idx % L1_BIT_SIZE) / L2_BIT_SIZE) as usize;
+        let l1_rank = Self::l1_rank(l0_ranks, l12_ranks, l1_pos);
+        let l2_rank = Self::l2_rank(*access::get(l12_ranks, l1_pos), l2_pos);
+        let block_rank = bits.count_ones_unchecked(l1_pos * L1_WORDS + l2_pos * L2_WORDS, idx) as u64;
+
+        Some((l1_rank + l2_rank + block_rank) as usize)
+    }
+
+    /// Inner implementation of `select` with `bits`, `l0_ranks` and `l12_ranks` passed from
+    /// standard and `Archived` versions.
+    #[inline]
+    fn select_impl(bits: &[u64], l0_ranks: &[u64], l12_ranks: &[u64], rank: usize) -> Option<u64> {
+        // binary search the last L1 block with at most `rank` set bits before it
+        // SAFETY: `l1_pos` is always within bounds of `l12_ranks` and `l0_ranks` hold ranks of all
+        // L0 blocks (ensured during construction)
+        let l1_rank = |l1_pos| unsafe { Self::l1_rank(l0_ranks, l12_ranks, l1_pos) } as usize;
+        if l12_ranks.is_empty() {
+            return None;
+        }
+        let (mut l1_pos, mut l1_end) = (0, l12_ranks.len());
+        while l1_end - l1_pos > 1 {
+            let mid = (l1_pos + l1_end) / 2;
+            if l1_rank(mid) <= rank {
+                l1_pos = mid;
+            } else {
+                l1_end = mid;
+            }
+        }
+        let mut rank = rank - l1_rank(l1_pos);
+
+        // skip L2 blocks with at most `rank` set bits, the last one is never skipped
+        let l12_rank = l12_ranks[l1_pos];
+        let mut l2_pos = 0;
+        while l2_pos < 3 {
+            let ones = ((l12_rank >> (32 + 10 * l2_pos)) & 0x3FF) as usize;
+            if rank < ones {
+                break;
+            }
+            rank -= ones;
+            l2_pos += 1;
+        }
+
+        let word_offset = l1_pos * L1_WORDS + l2_pos * L2_WORDS;
+        let words = &bits[word_offset.min(bits.len())..(l1_pos * L1_WORDS + L1_WORDS).min(bits.len())];
+        select_in_words(words, word_offset, rank)
+    }
+}
+
+impl RankedBitsLayout for PoppyRankedBits {
+    #[inline]
+    fn from_bits(bits: Box<[u64]>) -> Self {
+        PoppyRankedBits::new(bits)
+    }
+
+    #[inline]
+    fn bits_len(&self) -> u64 {
+        self.bits.len() as u64 * 64
+    }
+
+    #[inline]
+    fn size(&self) -> usize {
+        size_of_val(self)
+            + size_of_val(self.bits.as_ref())
+            + size_of_val(self.l0_ranks.as_ref())
+            + size_of_val(self.l12_ranks.as_ref())
+    }
+
+    fn debug_check_invariants(&self) -> Result<(), InvariantError> {
+        let (l0_ranks, l12_ranks) = compute_ranks(&self.bits);
+        if *self.l0_ranks != *l0_ranks {
+            return Err(InvariantError::RankMismatch { block_idx: 0 });
+        }
+        if self.l12_ranks.len() != l12_ranks.len() {
+            return Err(InvariantError::RankMismatch { block_idx: self.l12_ranks.len().min(l12_ranks.len()) });
+        }
+
+        match l12_ranks
+            .iter()
+            .zip(self.l12_ranks.iter())
+            .position(|(expected, l12_rank)| expected != l12_rank)
+        {
+            Some(block_idx) => Err(InvariantError::RankMismatch { block_idx }),
+            None => Ok(()),
+        }
+    }
+}
+
+impl RankedBitsAccess for PoppyRankedBits {
+    #[inline]
+    fn rank(&self, idx: u64) -> Option<usize> {
+        unsafe { Self::rank_impl(&self.bits, &self.l0_ranks, &self.l12_ranks, idx) }
+    }
+
+    #[inline]
+    fn select(&self, rank: usize) -> Option<u64> {
+        Self::select_impl(&self.bits, &self.l0_ranks, &self.l12_ranks, rank)
+    }
+}
+
+/// Implement `rank` and `select` for `Archived` version of `PoppyRankedBits` if feature is enabled
+#[cfg(feature = "rkyv_derive")]
+impl RankedBitsAccess for ArchivedPoppyRankedBits {
+    #[inline]
+    fn rank(&self, idx: u64) -> Option<usize> {
+        unsafe { PoppyRankedBits::rank_impl(&self.bits, &self.l0_ranks, &self.l12_ranks, idx) }
+    }
+
+    #[inline]
+    fn select(&self, rank: usize) -> Option<u64> {
+        PoppyRankedBits::select_impl(&self.bits, &self.l0_ranks, &self.l12_ranks, rank)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rank::RankedBits;
+    use crate::{Mphf, DEFAULT_GAMMA};
+    use rand::distributions::Standard;
+    use rand::{Rng, SeedableRng};
+    use rand_chacha::ChaCha8Rng;
+    use wyhash::WyHash;
+
+    #[test]
+    fn test_rank_and_select() {
+        for words in [0, 1, 8, 31, 32, 33, 1001] {
+            let bits: Vec<u64> = ChaCha8Rng::seed_from_u64(123)
+                .sample_iter(Standard)
+                .take(words)
+                .collect();
+            let poppy = PoppyRankedBits::new(bits.clone().into_boxed_slice());
+            let ranked_bits = RankedBits::new(bits.clone().into_boxed_slice());
+
+            assert_eq!(poppy.debug_check_invariants(), Ok(()));
+            assert_eq!(
+                poppy.overhead(),
+                8 * words.div_ceil(L1_WORDS) + if words > 0 { 8 } else { 0 }
+            );
+            for idx in 0..poppy.bits_len() {
+                assert_eq!(poppy.rank(idx), ranked_bits.rank(idx), "Rank mismatch at index {}", idx);
+            }
+            let ones = bits.iter().map(|word| word.count_ones() as usize).sum::<usize>();
+            for rank in 0..=ones {
+                assert_eq!(
+                    poppy.select(rank),
+                    ranked_bits.select(rank),
+                    "Select mismatch at rank {}",
+                    rank
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_debug_check_invariants() {
+        let mut poppy = PoppyRankedBits::new(vec![u64::MAX; 1000].into_boxed_slice());
+        poppy.l12_ranks[10] += 1;
+        assert_eq!(
+            poppy.debug_check_invariants(),
+            Err(InvariantError::RankMismatch { block_idx: 10 })
+        );
+    }
+
+    #[test]
+    fn test_mphf() {
+        let keys = (0..10000u64).collect::<Vec<u64>>();
+        let mphf = Mphf::<32, 8>::from_slice(&keys, DEFAULT_GAMMA).unwrap();
+        let poppy_mphf = Mphf::<32, 8, u8, WyHash, PoppyRankedBits>::from_slice(&keys, DEFAULT_GAMMA).unwrap();
+
+        assert_eq!(poppy_mphf.debug_check_invariants(), Ok(()));
+        for key in &keys {
+            assert_eq!(poppy_mphf.get(key), mphf.get(key));
+        }
+
+        #[cfg(feature = "rkyv_derive")]
+        {
+            let rkyv_bytes = rkyv::to_bytes::<_, 1024>(&poppy_mphf).unwrap();
+            let rkyv_mphf = rkyv::check_archived_root::<Mphf<32, 8, u8, WyHash, PoppyRankedBits>>(&rkyv_bytes).unwrap();
+            for key in &keys {
+                assert_eq!(rkyv_mphf.get(key), mphf.get(key));
+            }
+        }
+    }
+}
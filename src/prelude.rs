@@ -0,0 +1,119 @@
+//! A module re-exporting the most commonly used types and traits of the crate.
+//!
+//! Glob-importing the prelude brings the structures, their archived counterparts (with
+//! `rkyv_derive` feature), construction parameters and traits needed to query them into scope.
+//!
+//! # Examples
+//! ```
+//! use entropy_map::prelude::*;
+//!
+//! let set: Set<u32> = Set::from_iter_with_params([1, 2, 3], DEFAULT_GAMMA).unwrap();
+//! assert!(set.contains(&1));
+//! assert!(set.contains_hashed(&HashedKey::new(&2)));
+//! ```
+
+pub use crate::block_codec::{BlockCodec, BlockCodecKind};
+pub use crate::build_hasher::{MphfWithHasher, SipHash13Builder, SipHash24Builder, SipHashBuilder, WyHashBuilder};
+pub use crate::cow_key::CowKey;
+pub use crate::diff::{diff, DiffEntry, Diffable};
+pub use crate::duplicates::{find_duplicate_keys, DuplicateKeys};
+pub use crate::entropy_key::EntropyKey;
+pub use crate::external_build::ExternalBuildError;
+pub use crate::fixed_key::FixedKey;
+pub use crate::hashed_key::HashedKey;
+pub use crate::invariants::InvariantError;
+pub use crate::ip_prefix::{IpPrefix, IpPrefixError, IpPrefixMap, IpPrefixSet};
+pub use crate::key_filter::KeyFilter;
+pub use crate::map_with_dict::{MapCompact, MapFast, MapKeyed, MapWithDict, MapWithDictRawParts};
+pub use crate::map_with_dict_bitpacked::{
+    Error as MapWithDictBitpackedError, MapWithDictBitpacked, MapWithDictBitpackedRawParts,
+};
+pub use crate::map_with_dict_bitpacked_ranges::{MapWithDictBitpackedRanges, ValueRange};
+pub use crate::map_with_dict_bitpacked_shard::{MapWithDictBitpackedShard, SharedBitpackedDict, SharedDictBuilder};
+pub use crate::map_with_dict_bool::MapWithDictBool;
+pub use crate::map_with_dict_codec::MapWithDictCodec;
+pub use crate::map_with_dict_default::MapWithDictDefault;
+pub use crate::map_with_dict_flags::MapWithDictFlags;
+pub use crate::map_with_dict_ref::MapWithDictRef;
+pub use crate::map_with_dict_timestamps::MapWithDictTimestamps;
+pub use crate::map_with_dict_unchecked::MapWithDictUnchecked;
+pub use crate::monotone_mphf::MonotoneMphf;
+pub use crate::mphf::{BitLocation, BuildPolicy, Mphf, MphfError, DEFAULT_GAMMA};
+pub use crate::mphf_auto::{AutoGoal, AutoParams, MphfAuto};
+pub use crate::mphf_dyn::{MphfDyn, DYN_GROUP_SIZES};
+pub use crate::normalized::{AsciiLowercase, Normalized, Normalizer};
+pub use crate::partitioned_mphf::PartitionedMphf;
+pub use crate::pooled_values::PooledValues;
+pub use crate::progress::{BuildProgress, ProgressSnapshot};
+pub use crate::pthash::{PtHash, DEFAULT_PTHASH_ALPHA, DEFAULT_PTHASH_LAMBDA};
+pub use crate::rank::{
+    InterleavedRankedBits, L12RankAccess, RankedBits, RankedBitsAccess, RankedBitsLayout, RankedBitsSections, RankedRef,
+};
+pub use crate::recsplit::{RecSplit, DEFAULT_RECSPLIT_BUCKET_SIZE, RECSPLIT_LEAF_SIZE, RECSPLIT_MAX_BUCKET_SIZE};
+pub use crate::report::{BuildReport, MphfStats};
+pub use crate::semi_dynamic_mphf::{PendingRebuild, SemiDynamicMphf};
+pub use crate::set::{Set, SetCompact, SetFast, SetKeyed};
+pub use crate::sip_hasher::{SipHasher13, SipHasher24};
+pub use crate::static_table::{Cell, ColumnType, Row, StaticTable, StaticTableError};
+pub use crate::u128_keys::{MapWithDictU128, SetU128, U128Hasher};
+pub use crate::value_codec::{BitpackedCodec, BytesCodec, ValueCodec, VarintCodec};
+
+#[cfg(feature = "flat")]
+pub use crate::compact_map_with_dict::CompactMapWithDict;
+#[cfg(feature = "poppy")]
+pub use crate::poppy::PoppyRankedBits;
+#[cfg(feature = "rank9")]
+pub use crate::rank9::Rank9RankedBits;
+
+#[cfg(feature = "rkyv_derive")]
+pub use crate::build_hasher::ArchivedMphfWithHasher;
+#[cfg(all(feature = "flat", feature = "rkyv_derive"))]
+pub use crate::compact_map_with_dict::ArchivedCompactMapWithDict;
+#[cfg(feature = "rkyv_derive")]
+pub use crate::ip_prefix::{ArchivedIpPrefixMap, ArchivedIpPrefixSet};
+#[cfg(feature = "rkyv_derive")]
+pub use crate::map_with_dict::ArchivedMapWithDict;
+#[cfg(feature = "rkyv_derive")]
+pub use crate::map_with_dict_bitpacked::ArchivedMapWithDictBitpacked;
+#[cfg(feature = "rkyv_derive")]
+pub use crate::map_with_dict_bitpacked_ranges::ArchivedMapWithDictBitpackedRanges;
+#[cfg(feature = "rkyv_derive")]
+pub use crate::map_with_dict_bitpacked_shard::{ArchivedMapWithDictBitpackedShard, ArchivedSharedBitpackedDict};
+#[cfg(feature = "rkyv_derive")]
+pub use crate::map_with_dict_bool::ArchivedMapWithDictBool;
+#[cfg(feature = "rkyv_derive")]
+pub use crate::map_with_dict_codec::ArchivedMapWithDictCodec;
+#[cfg(feature = "rkyv_derive")]
+pub use crate::map_with_dict_default::ArchivedMapWithDictDefault;
+#[cfg(feature = "rkyv_derive")]
+pub use crate::map_with_dict_flags::ArchivedMapWithDictFlags;
+#[cfg(feature = "rkyv_derive")]
+pub use crate::map_with_dict_timestamps::ArchivedMapWithDictTimestamps;
+#[cfg(feature = "rkyv_derive")]
+pub use crate::map_with_dict_unchecked::ArchivedMapWithDictUnchecked;
+#[cfg(feature = "rkyv_derive")]
+pub use crate::monotone_mphf::ArchivedMonotoneMphf;
+#[cfg(feature = "rkyv_derive")]
+pub use crate::mphf::ArchivedMphf;
+#[cfg(feature = "rkyv_derive")]
+pub use crate::mphf_auto::ArchivedMphfAuto;
+#[cfg(feature = "rkyv_derive")]
+pub use crate::mphf_dyn::ArchivedMphfDyn;
+#[cfg(feature = "rkyv_derive")]
+pub use crate::owned_archive::{OwnedArchive, OwnedArchivedMap, OwnedArchivedSet};
+#[cfg(feature = "rkyv_derive")]
+pub use crate::partitioned_mphf::ArchivedPartitionedMphf;
+#[cfg(feature = "rkyv_derive")]
+pub use crate::pthash::ArchivedPtHash;
+#[cfg(feature = "rkyv_derive")]
+pub use crate::recsplit::ArchivedRecSplit;
+#[cfg(feature = "rkyv_derive")]
+pub use crate::set::ArchivedSet;
+#[cfg(feature = "rkyv_derive")]
+pub use crate::static_archive::{LazyArchive, StaticArchive};
+#[cfg(feature = "rkyv_derive")]
+pub use crate::static_table::{ArchivedRow, ArchivedStaticTable};
+#[cfg(feature = "rkyv_derive")]
+pub use crate::store::{Store, StoreError, StoreWriter};
+#[cfg(feature = "rkyv_derive")]
+pub use crate::u128_keys::{ArchivedMapWithDictU128, ArchivedSetU128};
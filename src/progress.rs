@@ -0,0 +1,200 @@
+//! A module providing `BuildProgress`, a handle to observe and cancel the construction of
+//! MPHF-based structures.
+//!
+//! A `BuildProgress` is passed to the `*_with_progress` constructors and can be cloned and shared
+//! with other threads, which can poll its `snapshot`, get notified after every built level via a
+//! callback, or `cancel` the construction, making it fail with `MphfError::Cancelled` shortly.
+
+use std::fmt;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::Arc;
+
+use crate::mphf::MphfError;
+
+/// Callback invoked with the progress snapshot after every built level.
+type ProgressCallback = Box<dyn Fn(&ProgressSnapshot) + Send + Sync>;
+
+/// Shared handle to observe and cancel construction, see the module documentation.
+#[derive(Clone, Default)]
+pub struct BuildProgress {
+    inner: Arc<ProgressInner>,
+}
+
+/// Shared state of `BuildProgress`.
+#[derive(Default)]
+struct ProgressInner {
+    /// Whether construction was cancelled
+    cancelled: AtomicBool,
+    /// Number of started MPHF build attempts
+    attempts: AtomicUsize,
+    /// Number of levels built by the current attempt
+    levels: AtomicUsize,
+    /// Number of keys the structure is built from
+    keys: AtomicUsize,
+    /// Number of keys not yet placed by the current attempt
+    retained_keys: AtomicUsize,
+    /// Optional callback invoked after every built level
+    callback: Option<ProgressCallback>,
+}
+
+/// Point-in-time view of construction progress.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ProgressSnapshot {
+    /// Number of started MPHF build attempts, greater than 1 if `gamma` was escalated
+    pub attempts: usize,
+    /// Number of levels built by the current attempt
+    pub levels: usize,
+    /// Number of keys the structure is built from
+    pub keys: usize,
+    /// Number of keys not yet placed by the current attempt
+    pub retained_keys: usize,
+}
+
+impl BuildProgress {
+    /// Creates a new progress handle.
+    #[inline]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Creates a new progress handle invoking `callback` after every built level.
+    ///
+    /// # Examples
+    /// ```
+    /// # use std::sync::atomic::{AtomicUsize, Ordering};
+    /// # use std::sync::Arc;
+    /// # use entropy_map::{BuildPolicy, BuildProgress, Set};
+    /// let levels = Arc::new(AtomicUsize::new(0));
+    /// let levels_clone = levels.clone();
+    /// let progress = BuildProgress::with_callback(move |snapshot| {
+    ///     levels_clone.store(snapshot.levels, Ordering::Relaxed);
+    /// });
+    ///
+    /// let (_, report) = Set::<u32>::from_iter_with_progress(0..1000, &BuildPolicy::default(), &progress).unwrap();
+    /// assert_eq!(levels.load(Ordering::Relaxed), report.levels.len());
+    /// ```
+    pub fn with_callback(callback: impl Fn(&ProgressSnapshot) + Send + Sync + 'static) -> Self {
+        BuildProgress {
+            inner: Arc::new(ProgressInner { callback: Some(Box::new(callback)), ..Default::default() }),
+        }
+    }
+
+    /// Requests cancellation of the construction using this handle.
+    ///
+    /// # Examples
+    /// ```
+    /// # use entropy_map::{BuildPolicy, BuildProgress, MphfError, Set};
+    /// let progress = BuildProgress::new();
+    /// progress.cancel();
+    ///
+    /// let res = Set::<u32>::from_iter_with_progress(0..1000, &BuildPolicy::default(), &progress);
+    /// assert!(matches!(res, Err(MphfError::Cancelled)));
+    /// ```
+    #[inline]
+    pub fn cancel(&self) {
+        self.inner.cancelled.store(true, Ordering::Relaxed);
+    }
+
+    /// Returns `true` if cancellation was requested.
+    #[inline]
+    pub fn is_cancelled(&self) -> bool {
+        self.inner.cancelled.load(Ordering::Relaxed)
+    }
+
+    /// Returns current construction progress.
+    #[inline]
+    pub fn snapshot(&self) -> ProgressSnapshot {
+        ProgressSnapshot {
+            attempts: self.inner.attempts.load(Ordering::Relaxed),
+            levels: self.inner.levels.load(Ordering::Relaxed),
+            keys: self.inner.keys.load(Ordering::Relaxed),
+            retained_keys: self.inner.retained_keys.load(Ordering::Relaxed),
+        }
+    }
+
+    /// Returns `MphfError::Cancelled` if cancellation was requested.
+    #[inline]
+    pub(crate) fn check_cancelled(&self) -> Result<(), MphfError> {
+        if self.is_cancelled() {
+            Err(MphfError::Cancelled)
+        } else {
+            Ok(())
+        }
+    }
+
+    /// Records the start of a new MPHF build attempt from `keys`.
+    pub(crate) fn start_attempt(&self, keys: usize) {
+        self.inner.attempts.fetch_add(1, Ordering::Relaxed);
+        self.inner.levels.store(0, Ordering::Relaxed);
+        self.inner.keys.store(keys, Ordering::Relaxed);
+        self.inner.retained_keys.store(keys, Ordering::Relaxed);
+    }
+
+    /// Records a built level which retained `retained_keys` for the next levels.
+    pub(crate) fn level_built(&self, retained_keys: usize) {
+        self.inner.levels.fetch_add(1, Ordering::Relaxed);
+        self.inner.retained_keys.store(retained_keys, Ordering::Relaxed);
+        if let Some(callback) = &self.inner.callback {
+            callback(&self.snapshot());
+        }
+    }
+}
+
+impl fmt::Debug for BuildProgress {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("BuildProgress")
+            .field("cancelled", &self.is_cancelled())
+            .field("snapshot", &self.snapshot())
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{BuildPolicy, MapWithDict, Mphf};
+    use std::sync::{Mutex, OnceLock};
+
+    #[test]
+    fn test_build_progress() {
+        let keys: Vec<u64> = (0..10_000).collect();
+        let snapshots = Arc::new(Mutex::new(vec![]));
+        let snapshots_clone = snapshots.clone();
+        let progress = BuildProgress::with_callback(move |snapshot| snapshots_clone.lock().unwrap().push(*snapshot));
+
+        let (_, report) = Mphf::<32, 8>::from_slice_with_progress(&keys, &BuildPolicy::default(), &progress).unwrap();
+
+        let snapshots = snapshots.lock().unwrap();
+        assert_eq!(snapshots.len(), report.levels.len());
+        for (level, (snapshot, level_report)) in snapshots.iter().zip(&report.levels).enumerate() {
+            assert_eq!(
+                *snapshot,
+                ProgressSnapshot {
+                    attempts: 1,
+                    levels: level + 1,
+                    keys: keys.len(),
+                    retained_keys: level_report.retained_keys
+                }
+            );
+        }
+        assert_eq!(progress.snapshot(), *snapshots.last().unwrap());
+    }
+
+    #[test]
+    fn test_build_progress_cancel() {
+        // cancel construction from the callback once the first level is built
+        let handle = Arc::new(OnceLock::<BuildProgress>::new());
+        let handle_clone = handle.clone();
+        let progress = BuildProgress::with_callback(move |_| handle_clone.get().unwrap().cancel());
+        handle.set(progress.clone()).unwrap();
+
+        let res = MapWithDict::<u64, u64>::from_iter_with_progress(
+            (0..10_000).map(|k| (k, k % 10)),
+            &BuildPolicy::default(),
+            &progress,
+        );
+        assert!(matches!(res, Err(MphfError::Cancelled)));
+        assert!(progress.is_cancelled());
+        assert_eq!(progress.snapshot().levels, 1);
+    }
+}
@@ -0,0 +1,442 @@
+//! A module providing `PtHash`, an MPHF built with the PTHash algorithm.
+//!
+//! `Mphf` is a fingerprint-based MPHF: a query walks levels of bits and ranks the matching one, so
+//! hot lookups of keys placed in later levels touch several cache lines. PTHash (Pibiri and Trani,
+//! SIGIR 2021) instead maps keys to buckets and stores a "pilot" per bucket, searched at
+//! construction so that `hash ^ pilot` sends every key of the bucket to a distinct free position
+//! of a table slightly larger than the number of keys. A query reads a single pilot and, for the
+//! few keys landing past the last index, a single remapped position, at the cost of a slower
+//! construction and slightly more bits per key (~2.8 with the default parameters vs ~2.7 for
+//! `Mphf` with `DEFAULT_GAMMA`).
+//!
+//! Buckets are processed from the largest to the smallest, with ~60% of keys skewed into ~30% of
+//! buckets so that large buckets are placed while the table is mostly empty. Pilots are bit-packed
+//! to the width of the largest one.
+
+use std::hash::{Hash, Hasher};
+use std::marker::PhantomData;
+use std::mem::size_of_val;
+
+use wyhash::WyHash;
+
+use crate::hashed_key::HashedKey;
+use crate::mphf::{hash_key_seeded, MphfError};
+use crate::static_table::{bit_width, pack_codes, read_code};
+
+/// Default average number of keys per bucket of `PtHash`.
+pub const DEFAULT_PTHASH_LAMBDA: f32 = 5.0;
+
+/// Default ratio of keys to table positions of `PtHash`.
+pub const DEFAULT_PTHASH_ALPHA: f32 = 0.99;
+
+/// Keys with the low 32 bits of their hash below this threshold (~60% of keys) are assigned to the
+/// dense buckets.
+const DENSE_KEYS_THRESHOLD: u32 = (0.6 * (1u64 << 32) as f64) as u32;
+
+/// Fraction of buckets being dense.
+const DENSE_BUCKETS_RATIO: f64 = 0.3;
+
+/// Minimal perfect hash function built with the PTHash algorithm, see the module documentation.
+///
+/// # Examples
+/// ```
+/// # use entropy_map::{PtHash, DEFAULT_PTHASH_ALPHA, DEFAULT_PTHASH_LAMBDA};
+/// let keys: Vec<u64> = (0..10_000).collect();
+/// let mphf: PtHash = PtHash::from_slice(&keys, DEFAULT_PTHASH_LAMBDA, DEFAULT_PTHASH_ALPHA).unwrap();
+///
+/// let mut indices: Vec<usize> = keys.iter().map(|key| mphf.get(key).unwrap()).collect();
+/// indices.sort_unstable();
+/// assert!(indices.into_iter().eq(0..keys.len()));
+/// ```
+#[derive(Debug)]
+#[cfg_attr(feature = "rkyv_derive", derive(rkyv::Archive, rkyv::Deserialize, rkyv::Serialize))]
+#[cfg_attr(feature = "rkyv_derive", archive_attr(derive(rkyv::CheckBytes)))]
+pub struct PtHash<H: Hasher + Default = WyHash> {
+    /// Number of keys
+    n: u64,
+    /// Number of table positions, at least `n`
+    table_size: u64,
+    /// Number of dense buckets, followed by the sparse ones
+    dense_buckets: u64,
+    /// Number of sparse buckets
+    sparse_buckets: u64,
+    /// Bit width of pilots
+    pilot_width: u8,
+    /// Bit-packed pilot of every bucket
+    pilots: Box<[u64]>,
+    /// Bit width of remapped positions
+    remap_width: u8,
+    /// Bit-packed free positions below `n` of the taken positions starting from `n`
+    remap: Box<[u64]>,
+    /// Seed hashed before every key, 0 for unkeyed hashing
+    hash_seed: u64,
+    /// Phantom field for the hasher
+    _phantom_hasher: PhantomData<H>,
+}
+
+impl<H: Hasher + Default> PtHash<H> {
+    /// Initializes `PtHash` using slice of `keys` with `lambda` keys per bucket on average and a
+    /// table of `keys.len() / alpha` positions.
+    ///
+    /// Larger `lambda` means fewer pilots, hence fewer bits per key, but a longer pilot search,
+    /// while `alpha` closer to 1.0 shortens the remap table but makes placing the last buckets
+    /// slower. Keys with the same 64-bit hash fail with `MphfError::HashCollision`.
+    pub fn from_slice<K: Hash>(keys: &[K], lambda: f32, alpha: f32) -> Result<Self, MphfError> {
+        Self::from_slice_with_seed(keys, lambda, alpha, 0)
+    }
+
+    /// Same as [`PtHash::from_slice`], but hashes `hash_seed` before every key, see
+    /// [`BuildPolicy::with_hash_seed`](crate::BuildPolicy::with_hash_seed).
+    pub fn from_slice_with_seed<K: Hash>(
+        keys: &[K],
+        lambda: f32,
+        alpha: f32,
+        hash_seed: u64,
+    ) -> Result<Self, MphfError> {
+        let hashes: Vec<u64> = keys.iter().map(|key| hash_key_seeded::<H, _>(hash_seed, key)).collect();
+        Self::from_hashes(&hashes, lambda, alpha, hash_seed)
+    }
+
+    /// Builds `PtHash` from the 64-bit `hashes` of keys computed with `hash_seed`.
+    fn from_hashes(hashes: &[u64], lambda: f32, alpha: f32, hash_seed: u64) -> Result<Self, MphfError> {
+        if !(lambda > 0.0 && alpha > 0.0 && alpha <= 1.0) {
+            return Err(MphfError::InvalidPtHashParameter);
+        }
+        let n = hashes.len() as u64;
+        if n == 0 {
+            return Ok(Self::empty(hash_seed));
+        }
+        if n > u32::MAX as u64 {
+            return Err(MphfError::LevelSizeOverflow);
+        }
+
+        let table_size = ((n as f64 / alpha as f64).ceil() as u64).max(n);
+        let num_buckets = ((n as f64 / lambda as f64).ceil() as u64).max(2);
+        let dense_buckets = ((num_buckets as f64 * DENSE_BUCKETS_RATIO) as u64).max(1);
+        let sparse_buckets = num_buckets - dense_buckets;
+        let bucket_of = |hash| bucket(hash, dense_buckets, sparse_buckets);
+
+        // group hashes by bucket with a counting sort
+        let mut offsets = vec![0u32; num_buckets as usize + 1];
+        for &hash in hashes {
+            offsets[bucket_of(hash) + 1] += 1;
+        }
+        for idx in 0..num_buckets as usize {
+            offsets[idx + 1] += offsets[idx];
+        }
+        let mut cursors = offsets.clone();
+        let mut bucket_hashes = vec![0u64; hashes.len()];
+        for &hash in hashes {
+            let cursor = &mut cursors[bucket_of(hash)];
+            bucket_hashes[*cursor as usize] = hash;
+            *cursor += 1;
+        }
+
+        let mut buckets: Vec<u32> = (0..num_buckets as u32).collect();
+        buckets.sort_by_key(|&idx| std::cmp::Reverse(offsets[idx as usize + 1] - offsets[idx as usize]));
+
+        let mut taken = vec![0u64; table_size.div_ceil(64) as usize];
+        let mut pilots = vec![0u32; num_buckets as usize];
+        let mut positions = Vec::new();
+        for bucket_idx in buckets {
+            let keys =
+                &mut bucket_hashes[offsets[bucket_idx as usize] as usize..offsets[bucket_idx as usize + 1] as usize];
+            if keys.is_empty() {
+                break;
+            }
+            keys.sort_unstable();
+            if keys.windows(2).any(|pair| pair[0] == pair[1]) {
+                return Err(MphfError::HashCollision);
+            }
+
+            let pilot = (0..=u32::MAX)
+                .find(|&pilot| {
+                    positions.clear();
+                    for &hash in keys.iter() {
+                        let pos = position(hash, pilot, table_size);
+                        if taken[pos as usize / 64] & (1 << (pos % 64)) != 0 {
+                            return false;
+                        }
+                        positions.push(pos);
+                    }
+                    positions.sort_unstable();
+                    positions.windows(2).all(|pair| pair[0] != pair[1])
+                })
+                .ok_or(MphfError::HashCollision)?;
+            for &pos in &positions {
+                taken[pos as usize / 64] |= 1 << (pos % 64);
+            }
+            pilots[bucket_idx as usize] = pilot;
+        }
+
+        // map taken positions past the last index to the free ones below it
+        let is_taken = |pos: u64| taken[pos as usize / 64] & (1 << (pos % 64)) != 0;
+        let mut free = (0..n).filter(|&pos| !is_taken(pos));
+        let remap: Vec<u32> = (n..table_size)
+            .map(|pos| if is_taken(pos) { free.next().unwrap() as u32 } else { 0 })
+            .collect();
+
+        let pilot_width = bit_width(pilots.iter().copied().max().unwrap_or(0));
+        let remap_width = bit_width(n as u32 - 1);
+        Ok(PtHash {
+            n,
+            table_size,
+            dense_buckets,
+            sparse_buckets,
+            pilot_width,
+            pilots: pack_codes(&pilots, pilot_width),
+            remap_width,
+            remap: pack_codes(&remap, remap_width),
+            hash_seed,
+            _phantom_hasher: PhantomData,
+        })
+    }
+
+    /// Returns an empty `PtHash` with `hash_seed`.
+    fn empty(hash_seed: u64) -> Self {
+        PtHash {
+            n: 0,
+            table_size: 0,
+            dense_buckets: 0,
+            sparse_buckets: 0,
+            pilot_width: 0,
+            pilots: Box::new([]),
+            remap_width: 0,
+            remap: Box::new([]),
+            hash_seed,
+            _phantom_hasher: PhantomData,
+        }
+    }
+
+    /// Returns the index associated with `key`, within `0..len()` range. Unlike
+    /// [`Mphf::get`](crate::Mphf::get), absent keys are mapped to an arbitrary index, `None` being
+    /// returned only when `PtHash` is empty.
+    #[inline]
+    pub fn get<K: Hash + ?Sized>(&self, key: &K) -> Option<usize> {
+        self.get_by_hash(self.hash_key(key))
+    }
+
+    /// Same as [`PtHash::get`], but re-uses the hash precomputed by `HashedKey`.
+    #[inline]
+    pub fn get_hashed<K: ?Sized>(&self, key: &HashedKey<K, H>) -> Option<usize> {
+        // hashes computed with another seed would map to arbitrary indices
+        if key.hash_seed() != self.hash_seed {
+            return None;
+        }
+        self.get_by_hash(key.hash())
+    }
+
+    /// Returns the hash of `key` as computed by `get`, to be passed to [`PtHash::get_by_hash`].
+    #[inline]
+    pub fn hash_key<K: Hash + ?Sized>(&self, key: &K) -> u64 {
+        hash_key_seeded::<H, _>(self.hash_seed, key)
+    }
+
+    /// Returns the index associated with the key `hash`, see [`PtHash::get`].
+    #[inline]
+    pub fn get_by_hash(&self, hash: u64) -> Option<usize> {
+        get_impl(
+            hash,
+            self.n,
+            self.table_size,
+            self.dense_buckets,
+            self.sparse_buckets,
+            (&self.pilots, self.pilot_width),
+            (&self.remap, self.remap_width),
+        )
+    }
+
+    /// Returns the seed hashed before every key, 0 for unkeyed hashing.
+    #[inline]
+    pub fn hash_seed(&self) -> u64 {
+        self.hash_seed
+    }
+
+    /// Returns the number of keys.
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.n as usize
+    }
+
+    /// Returns `true` if there are no keys.
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.n == 0
+    }
+
+    /// Returns the total number of bytes occupied by `PtHash`.
+    pub fn size(&self) -> usize {
+        size_of_val(self) + size_of_val(self.pilots.as_ref()) + size_of_val(self.remap.as_ref())
+    }
+}
+
+impl<H: Hasher + Default> Default for PtHash<H> {
+    /// Returns an empty `PtHash`.
+    fn default() -> Self {
+        Self::empty(0)
+    }
+}
+
+/// Returns the bucket of `hash`, skewing ~60% of hashes into the `dense_buckets` first buckets.
+#[inline]
+fn bucket(hash: u64, dense_buckets: u64, sparse_buckets: u64) -> usize {
+    if (hash as u32) < DENSE_KEYS_THRESHOLD {
+        fastrange64(hash, dense_buckets) as usize
+    } else {
+        (dense_buckets + fastrange64(hash, sparse_buckets)) as usize
+    }
+}
+
+/// Returns the table position of `hash` within a bucket with `pilot`.
+#[inline]
+fn position(hash: u64, pilot: u32, table_size: u64) -> u64 {
+    let pilot_hash = (pilot as u64 + 1).wrapping_mul(0x9e37_79b9_7f4a_7c15);
+    let x = ((hash ^ pilot_hash) as u128).wrapping_mul(0x5851f42d4c957f2d);
+    fastrange64((x as u64) ^ ((x >> 64) as u64), table_size)
+}
+
+/// Maps `x` uniformly to `0..n` range, see `fastmod32` of `Mphf`.
+#[inline]
+fn fastrange64(x: u64, n: u64) -> u64 {
+    (((x as u128) * (n as u128)) >> 64) as u64
+}
+
+/// Inner implementation of `get_by_hash` shared with the archived version.
+#[inline]
+fn get_impl(
+    hash: u64,
+    n: u64,
+    table_size: u64,
+    dense_buckets: u64,
+    sparse_buckets: u64,
+    (pilots, pilot_width): (&[u64], u8),
+    (remap, remap_width): (&[u64], u8),
+) -> Option<usize> {
+    if n == 0 {
+        return None;
+    }
+    let bucket_idx = bucket(hash, dense_buckets, sparse_buckets);
+    // SAFETY: `bucket_idx` is below the number of buckets pilots were packed for
+    let pilot = unsafe { read_code(pilots, bucket_idx, pilot_width) };
+    let pos = position(hash, pilot, table_size);
+    if pos < n {
+        Some(pos as usize)
+    } else {
+        // SAFETY: `pos - n` is below `table_size - n`, the number of remapped positions
+        Some(unsafe { read_code(remap, (pos - n) as usize, remap_width) } as usize)
+    }
+}
+
+/// Implement `get` for `Archived` version of `PtHash` if feature is enabled
+#[cfg(feature = "rkyv_derive")]
+impl<H: Hasher + Default> ArchivedPtHash<H> {
+    /// Returns the index associated with `key`, see [`PtHash::get`].
+    #[inline]
+    pub fn get<K: Hash + ?Sized>(&self, key: &K) -> Option<usize> {
+        self.get_by_hash(hash_key_seeded::<H, _>(self.hash_seed, key))
+    }
+
+    /// Same as [`ArchivedPtHash::get`], but re-uses the hash precomputed by `HashedKey`.
+    #[inline]
+    pub fn get_hashed<K: ?Sized>(&self, key: &HashedKey<K, H>) -> Option<usize> {
+        // hashes computed with another seed would map to arbitrary indices
+        if key.hash_seed() != self.hash_seed {
+            return None;
+        }
+        self.get_by_hash(key.hash())
+    }
+
+    /// Returns the index associated with the key `hash`, see [`PtHash::get`].
+    #[inline]
+    pub fn get_by_hash(&self, hash: u64) -> Option<usize> {
+        get_impl(
+            hash,
+            self.n,
+            self.table_size,
+            self.dense_buckets,
+            self.sparse_buckets,
+            (&self.pilots, self.pilot_width),
+            (&self.remap, self.remap_width),
+        )
+    }
+
+    /// Returns the number of keys.
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.n as usize
+    }
+
+    /// Returns `true` if there are no keys.
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.n == 0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_bijection(mphf: &PtHash, keys: &[u64]) {
+        let mut indices: Vec<usize> = keys.iter().map(|key| mphf.get(key).unwrap()).collect();
+        indices.sort_unstable();
+        assert!(indices.into_iter().eq(0..keys.len()), "n = {}", keys.len());
+    }
+
+    #[test]
+    fn test_pthash() {
+        for n in [1, 2, 3, 10, 100, 1_000, 100_000] {
+            let keys: Vec<u64> = (0..n).collect();
+            let mphf: PtHash = PtHash::from_slice(&keys, DEFAULT_PTHASH_LAMBDA, DEFAULT_PTHASH_ALPHA).unwrap();
+            assert_eq!(mphf.len(), keys.len());
+            assert_bijection(&mphf, &keys);
+        }
+
+        let keys: Vec<u64> = (0..100_000).collect();
+        let mphf: PtHash = PtHash::from_slice(&keys, DEFAULT_PTHASH_LAMBDA, DEFAULT_PTHASH_ALPHA).unwrap();
+        let bits_per_key = mphf.size() as f32 * 8.0 / keys.len() as f32;
+        assert!(bits_per_key < 5.0, "{bits_per_key}");
+        assert!(keys
+            .iter()
+            .all(|key| mphf.get_hashed(&HashedKey::new(key)) == mphf.get(key)));
+
+        for (lambda, alpha) in [(2.0, 1.0), (8.0, 0.9)] {
+            let mphf: PtHash = PtHash::from_slice(&keys[..10_000], lambda, alpha).unwrap();
+            assert_bijection(&mphf, &keys[..10_000]);
+        }
+
+        let mphf: PtHash =
+            PtHash::from_slice_with_seed(&keys, DEFAULT_PTHASH_LAMBDA, DEFAULT_PTHASH_ALPHA, 42).unwrap();
+        assert_eq!(mphf.hash_seed(), 42);
+        assert_bijection(&mphf, &keys);
+    }
+
+    #[test]
+    fn test_errors() {
+        let mphf: PtHash = PtHash::from_slice::<u64>(&[], DEFAULT_PTHASH_LAMBDA, DEFAULT_PTHASH_ALPHA).unwrap();
+        assert!(mphf.is_empty());
+        assert_eq!(mphf.get(&1), None);
+
+        for (lambda, alpha) in [(0.0, 0.99), (5.0, 0.0), (5.0, 1.5), (f32::NAN, 0.99)] {
+            let res: Result<PtHash, _> = PtHash::from_slice(&[1u64], lambda, alpha);
+            assert!(matches!(res, Err(MphfError::InvalidPtHashParameter)));
+        }
+
+        let res: Result<PtHash, _> = PtHash::from_slice(&[1u64, 2, 1], DEFAULT_PTHASH_LAMBDA, DEFAULT_PTHASH_ALPHA);
+        assert!(matches!(res, Err(MphfError::HashCollision)));
+    }
+
+    #[cfg(feature = "rkyv_derive")]
+    #[test]
+    fn test_rkyv() {
+        let keys: Vec<u64> = (0..10_000).collect();
+        let mphf: PtHash = PtHash::from_slice(&keys, DEFAULT_PTHASH_LAMBDA, DEFAULT_PTHASH_ALPHA).unwrap();
+        let rkyv_bytes = rkyv::to_bytes::<_, 1024>(&mphf).unwrap();
+        let rkyv_mphf = rkyv::check_archived_root::<PtHash>(&rkyv_bytes).unwrap();
+
+        assert_eq!(rkyv_mphf.len(), keys.len());
+        for key in &keys {
+            assert_eq!(rkyv_mphf.get(key), mphf.get(key));
+        }
+    }
+}
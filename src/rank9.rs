@@ -0,0 +1,236 @@
+//! A module providing `Rank9RankedBits`, an alternative `Mphf` rank layout based on rank9.
+//!
+//! rank9 (see [Broadword Implementation of Rank/Select Queries](https://vigna.di.unimi.it/ftp/papers/Broadword.pdf))
+//! stores for every 512-bit block the number of set bits before it along with seven 9-bit numbers
+//! of set bits before each of its words. Rank queries take constant time without counting set bits
+//! of more than a single word, which makes them faster than with `RankedBits` at the cost of 25%
+//! rank metadata overhead instead of ~3.125%, suiting low-latency deployments.
+
+use std::mem::size_of_val;
+
+use crate::access;
+use crate::invariants::InvariantError;
+use crate::rank::{select_in_words, RankedBitsAccess, RankedBitsLayout};
+
+/// Number of bit vector words in every block.
+const BLOCK_WORDS: usize = 8;
+
+/// Ranked bit vector with rank9 layout, see the module documentation.
+///
+/// # Examples
+/// ```
+/// # use entropy_map::{Mphf, Rank9RankedBits, DEFAULT_GAMMA};
+/// let mphf = Mphf::<32, 8, u8, wyhash::WyHash, Rank9RankedBits>::from_slice(&[1, 2, 3], DEFAULT_GAMMA).unwrap();
+/// assert!(mphf.get(&1).unwrap() < 3);
+/// ```
+#[derive(Debug, Default)]
+#[cfg_attr(feature = "rkyv_derive", derive(rkyv::Archive, rkyv::Deserialize, rkyv::Serialize))]
+#[cfg_attr(feature = "rkyv_derive", archive_attr(derive(rkyv::CheckBytes)))]
+pub struct Rank9RankedBits {
+    /// The bit vector represented as an array of u64 integers.
+    bits: Box<[u64]>,
+    /// Number of set bits before every block, followed by 9-bit numbers of set bits of the block
+    /// before its words 1..8.
+    counts: Box<[[u64; 2]]>,
+}
+
+/// Computes `counts` of every block of `bits`.
+fn compute_counts(bits: &[u64]) -> impl Iterator<Item = [u64; 2]> + '_ {
+    let mut rank = 0u64;
+    bits.chunks(BLOCK_WORDS).map(move |words| {
+        let mut sub_ranks = 0u64;
+        let mut sub_rank = 0u64;
+        for (i, word) in words.iter().enumerate() {
+            if i > 0 {
+                sub_ranks |= sub_rank << (9 * (i - 1));
+            }
+            sub_rank += word.count_ones() as u64;
+        }
+        let block_counts = [rank, sub_ranks];
+        rank += sub_rank;
+        block_counts
+    })
+}
+
+impl Rank9RankedBits {
+    /// Initializes `Rank9RankedBits` with a provided bit vector.
+    pub fn new(bits: Box<[u64]>) -> Self {
+        let counts = compute_counts(&bits).collect();
+        Rank9RankedBits { bits, counts }
+    }
+
+    /// Returns the number of bytes occupied by rank metadata on top of the bits.
+    ///
+    /// # Examples
+    /// ```
+    /// # use entropy_map::Rank9RankedBits;
+    /// let ranked_bits = Rank9RankedBits::new(vec![u64::MAX; 128].into_boxed_slice());
+    /// assert_eq!(ranked_bits.overhead(), 256);
+    /// ```
+    pub fn overhead(&self) -> usize {
+        size_of_val(self.counts.as_ref())
+    }
+
+    /// Inner implementation of `rank` with `bits` and `counts` passed from standard and `Archived`
+    /// versions.
+    ///
+    /// # Safety
+    /// `idx` must be within the bounds of `bits` and `counts` must hold counts of all its blocks.
+    #[inline]
+    unsafe fn rank_impl(bits: &[u64], counts: &[[u64; 2]], idx: u64) -> Option<usize> {
+        let word_idx = (idx / 64) as usize;
+        let word = *access::get(bits, word_idx);
+        if (word >> (idx % 64)) & 1 == 0 {
+            return None;
+        }
+
+        let [block_rank, sub_ranks] = *access::get(counts, word_idx / BLOCK_WORDS);
+        // the first word of a block wraps around to the always unset most significant bit
+        let t = ((word_idx % BLOCK_WORDS) as u64).wrapping_sub(1);
+        let sub_rank = (sub_ranks >> (t.wrapping_add((t >> 60) & 8) * 9)) & 0x1FF;
+        let word_rank = (word & ((1 << (idx % 64)) - 1)).count_ones() as u64;
+
+        Some((block_rank + sub_rank + word_rank) as usize)
+    }
+
+    /// Inner implementation of `select` with `bits` and `counts` passed from standard and
+    /// `Archived` versions.
+    #[inline]
+    fn select_impl(bits: &[u64], counts: &[[u64; 2]], rank: usize) -> Option<u64> {
+        // binary search the last block with at most `rank` set bits before it
+        let block_idx = counts
+            .partition_point(|&[block_rank, _]| block_rank as usize <= rank)
+            .checked_sub(1)?;
+        let word_offset = block_idx * BLOCK_WORDS;
+        let words = &bits[word_offset..(word_offset + BLOCK_WORDS).min(bits.len())];
+        select_in_words(words, word_offset, rank - counts[block_idx][0] as usize)
+    }
+}
+
+impl RankedBitsLayout for Rank9RankedBits {
+    #[inline]
+    fn from_bits(bits: Box<[u64]>) -> Self {
+        Rank9RankedBits::new(bits)
+    }
+
+    #[inline]
+    fn bits_len(&self) -> u64 {
+        self.bits.len() as u64 * 64
+    }
+
+    #[inline]
+    fn size(&self) -> usize {
+        size_of_val(self) + size_of_val(self.bits.as_ref()) + size_of_val(self.counts.as_ref())
+    }
+
+    fn debug_check_invariants(&self) -> Result<(), InvariantError> {
+        let blocks = self.bits.len().div_ceil(BLOCK_WORDS);
+        if self.counts.len() != blocks {
+            return Err(InvariantError::RankMismatch { block_idx: self.counts.len().min(blocks) });
+        }
+
+        match compute_counts(&self.bits)
+            .zip(self.counts.iter())
+            .position(|(expected, &counts)| expected != counts)
+        {
+            Some(block_idx) => Err(InvariantError::RankMismatch { block_idx }),
+            None => Ok(()),
+        }
+    }
+}
+
+impl RankedBitsAccess for Rank9RankedBits {
+    #[inline]
+    fn rank(&self, idx: u64) -> Option<usize> {
+        unsafe { Self::rank_impl(&self.bits, &self.counts, idx) }
+    }
+
+    #[inline]
+    fn select(&self, rank: usize) -> Option<u64> {
+        Self::select_impl(&self.bits, &self.counts, rank)
+    }
+}
+
+/// Implement `rank` and `select` for `Archived` version of `Rank9RankedBits` if feature is enabled
+#[cfg(feature = "rkyv_derive")]
+impl RankedBitsAccess for ArchivedRank9RankedBits {
+    #[inline]
+    fn rank(&self, idx: u64) -> Option<usize> {
+        unsafe { Rank9RankedBits::rank_impl(&self.bits, &self.counts, idx) }
+    }
+
+    #[inline]
+    fn select(&self, rank: usize) -> Option<u64> {
+        Rank9RankedBits::select_impl(&self.bits, &self.counts, rank)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rank::RankedBits;
+    use crate::{Mphf, DEFAULT_GAMMA};
+    use rand::distributions::Standard;
+    use rand::{Rng, SeedableRng};
+    use rand_chacha::ChaCha8Rng;
+    use wyhash::WyHash;
+
+    #[test]
+    fn test_rank_and_select() {
+        for words in [0, 1, 7, 8, 9, 1001] {
+            let bits: Vec<u64> = ChaCha8Rng::seed_from_u64(123)
+                .sample_iter(Standard)
+                .take(words)
+                .collect();
+            let rank9 = Rank9RankedBits::new(bits.clone().into_boxed_slice());
+            let ranked_bits = RankedBits::new(bits.clone().into_boxed_slice());
+
+            assert_eq!(rank9.debug_check_invariants(), Ok(()));
+            assert_eq!(rank9.overhead(), 16 * words.div_ceil(BLOCK_WORDS));
+            for idx in 0..rank9.bits_len() {
+                assert_eq!(rank9.rank(idx), ranked_bits.rank(idx), "Rank mismatch at index {}", idx);
+            }
+            let ones = bits.iter().map(|word| word.count_ones() as usize).sum::<usize>();
+            for rank in 0..=ones {
+                assert_eq!(
+                    rank9.select(rank),
+                    ranked_bits.select(rank),
+                    "Select mismatch at rank {}",
+                    rank
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_debug_check_invariants() {
+        let mut rank9 = Rank9RankedBits::new(vec![u64::MAX; 100].into_boxed_slice());
+        rank9.counts[3][1] += 1;
+        assert_eq!(
+            rank9.debug_check_invariants(),
+            Err(InvariantError::RankMismatch { block_idx: 3 })
+        );
+    }
+
+    #[test]
+    fn test_mphf() {
+        let keys = (0..10000u64).collect::<Vec<u64>>();
+        let mphf = Mphf::<32, 8>::from_slice(&keys, DEFAULT_GAMMA).unwrap();
+        let rank9_mphf = Mphf::<32, 8, u8, WyHash, Rank9RankedBits>::from_slice(&keys, DEFAULT_GAMMA).unwrap();
+
+        assert_eq!(rank9_mphf.debug_check_invariants(), Ok(()));
+        assert!(rank9_mphf.size() > mphf.size());
+        for key in &keys {
+            assert_eq!(rank9_mphf.get(key), mphf.get(key));
+        }
+
+        #[cfg(feature = "rkyv_derive")]
+        {
+            let rkyv_bytes = rkyv::to_bytes::<_, 1024>(&rank9_mphf).unwrap();
+            let rkyv_mphf = rkyv::check_archived_root::<Mphf<32, 8, u8, WyHash, Rank9RankedBits>>(&rkyv_bytes).unwrap();
+            for key in &keys {
+                assert_eq!(rkyv_mphf.get(key), mphf.get(key));
+            }
+        }
+    }
+}
@@ -0,0 +1,647 @@
+//! A module providing `RecSplit`, an MPHF built with the RecSplit algorithm.
+//!
+//! RecSplit (Esposito, Müller Graf and Vigna, ALENEX 2020) gets closer to the theoretical minimum
+//! of ~1.44 bits per key than `Mphf` and `PtHash`, at the cost of a much slower construction and
+//! slower queries. Keys are assigned to buckets of `bucket_size` keys on average, and every bucket
+//! is split recursively: a seed is searched for each node so that the node keys are split into
+//! children of predefined sizes, down to leaves of up to `RECSPLIT_LEAF_SIZE` keys, for which a
+//! seed mapping keys bijectively to the leaf positions is searched. Seeds are Golomb-Rice coded
+//! with a parameter depending only on the node size, so that a query skips the sibling subtrees
+//! preceding its child without decoding them.
+//!
+//! The number of keys before every bucket and the position of its seeds are stored as Elias-Fano
+//! sequences, whose high bits use `RankedBits::select`.
+
+use std::hash::{Hash, Hasher};
+use std::marker::PhantomData;
+use std::mem::size_of_val;
+use std::sync::OnceLock;
+
+#[cfg(feature = "rayon")]
+use rayon::prelude::*;
+use wyhash::WyHash;
+
+use crate::hashed_key::HashedKey;
+use crate::mphf::{hash_key_seeded, MphfError};
+use crate::rank::{RankedBits, RankedBitsAccess};
+use crate::static_table::{pack_codes, read_code};
+
+/// Maximum number of keys in a leaf of the splitting tree.
+pub const RECSPLIT_LEAF_SIZE: usize = 8;
+
+/// Default average number of keys per bucket of `RecSplit`.
+pub const DEFAULT_RECSPLIT_BUCKET_SIZE: usize = 2000;
+
+/// Maximum average number of keys per bucket of `RecSplit`.
+pub const RECSPLIT_MAX_BUCKET_SIZE: usize = 4096;
+
+/// Number of keys of nodes whose children are leaves.
+const LOWER_UNIT: usize = 4 * RECSPLIT_LEAF_SIZE;
+
+/// Number of keys of nodes whose children are lower nodes.
+const UPPER_UNIT: usize = 3 * LOWER_UNIT;
+
+/// Maximum number of keys of a bucket, i.e. of the root of a splitting tree.
+const MAX_SPLIT_SIZE: usize = 2 * RECSPLIT_MAX_BUCKET_SIZE;
+
+/// Shape of the splitting tree of a node of a given size.
+#[derive(Debug, Clone, Copy, Default)]
+struct SplitInfo {
+    /// Size of children but the last one, 1 for leaves
+    unit: u32,
+    /// Golomb-Rice parameter of the node seed
+    rice: u8,
+    /// Number of nodes of the subtree
+    nodes: u32,
+    /// Number of fixed bits of Golomb-Rice codes of the subtree
+    fixed_bits: u32,
+}
+
+/// Returns the shapes of splitting trees of nodes of up to `MAX_SPLIT_SIZE` keys.
+fn split_infos() -> &'static [SplitInfo] {
+    static SPLIT_INFOS: OnceLock<Box<[SplitInfo]>> = OnceLock::new();
+    SPLIT_INFOS.get_or_init(|| {
+        let mut ln_factorials = vec![0f64; MAX_SPLIT_SIZE + 1];
+        for m in 1..=MAX_SPLIT_SIZE {
+            ln_factorials[m] = ln_factorials[m - 1] + (m as f64).ln();
+        }
+
+        let mut infos = vec![SplitInfo { unit: 1, ..SplitInfo::default() }; MAX_SPLIT_SIZE + 1];
+        for m in 2..=MAX_SPLIT_SIZE {
+            let unit = match m {
+                _ if m <= RECSPLIT_LEAF_SIZE => 1,
+                _ if m <= LOWER_UNIT => RECSPLIT_LEAF_SIZE,
+                _ if m <= UPPER_UNIT => LOWER_UNIT,
+                _ => (m / 2).div_ceil(UPPER_UNIT) * UPPER_UNIT,
+            };
+
+            // probability of a seed splitting keys into children of the expected sizes
+            let mut ln_probability = ln_factorials[m];
+            let (mut nodes, mut fixed_bits) = (1, 0);
+            for child in (0..m).step_by(unit) {
+                let size = unit.min(m - child);
+                ln_probability += size as f64 * (size as f64 / m as f64).ln() - ln_factorials[size];
+                nodes += infos[size].nodes;
+                fixed_bits += infos[size].fixed_bits;
+            }
+
+            // optimal Golomb-Rice parameter of the geometrically distributed number of seeds tried
+            let rice = (std::f64::consts::LN_2.log2() - ln_probability / std::f64::consts::LN_2).max(0.0) as u8;
+            infos[m] = SplitInfo { unit: unit as u32, rice, nodes, fixed_bits: fixed_bits + rice as u32 };
+        }
+        infos.into_boxed_slice()
+    })
+}
+
+/// Monotone sequence of integers stored with the Elias-Fano encoding.
+#[derive(Debug, Default)]
+#[cfg_attr(feature = "rkyv_derive", derive(rkyv::Archive, rkyv::Deserialize, rkyv::Serialize))]
+#[cfg_attr(feature = "rkyv_derive", archive_attr(derive(rkyv::CheckBytes)))]
+struct EliasFano {
+    /// Bit width of low bits of values
+    low_width: u8,
+    /// Bit-packed low bits of values
+    low: Box<[u64]>,
+    /// High bits of values, the high bits `h` of the `i`-th value being encoded by bit `h + i`
+    high: RankedBits,
+}
+
+impl EliasFano {
+    /// Encodes monotone `values`.
+    fn new(values: &[u64]) -> Self {
+        let max = values.last().copied().unwrap_or(0);
+        let low_width = match max / (values.len() as u64).max(1) {
+            0 => 0,
+            ratio => (u64::BITS - 1 - ratio.leading_zeros()).min(32) as u8,
+        };
+        let low_mask = (1u64 << low_width) - 1;
+        let low: Vec<u32> = values.iter().map(|&value| (value & low_mask) as u32).collect();
+
+        let mut high = vec![0u64; ((max >> low_width) as usize + values.len()).div_ceil(64).max(1)];
+        for (idx, &value) in values.iter().enumerate() {
+            let bit_idx = (value >> low_width) as usize + idx;
+            high[bit_idx / 64] |= 1 << (bit_idx % 64);
+        }
+        EliasFano {
+            low_width,
+            low: pack_codes(&low, low_width),
+            high: RankedBits::new(high.into_boxed_slice()),
+        }
+    }
+
+    /// Returns the total number of bytes occupied by `EliasFano`.
+    fn size(&self) -> usize {
+        size_of_val(self) - size_of_val(&self.high) + size_of_val(self.low.as_ref()) + self.high.size()
+    }
+}
+
+/// Returns the `idx`-th value of an Elias-Fano sequence, shared with the archived version.
+///
+/// # Safety
+/// `idx` must be below the number of values of the sequence.
+#[inline]
+unsafe fn elias_fano_get(low_width: u8, low: &[u64], high: &impl RankedBitsAccess, idx: usize) -> u64 {
+    let high_bits = high.select(idx).unwrap_unchecked() - idx as u64;
+    (high_bits << low_width) | read_code(low, idx, low_width) as u64
+}
+
+/// Growable bit vector the seeds are written to.
+#[derive(Default)]
+struct BitWriter {
+    /// Bits packed into words
+    words: Vec<u64>,
+    /// Number of bits
+    len: usize,
+}
+
+impl BitWriter {
+    /// Appends the `width` low bits of `value`.
+    fn push_bits(&mut self, value: u64, width: usize) {
+        if width == 0 {
+            return;
+        }
+        let shift = self.len % 64;
+        if shift == 0 {
+            self.words.push(0);
+        }
+        *self.words.last_mut().unwrap() |= value << shift;
+        if shift + width > 64 {
+            self.words.push(value >> (64 - shift));
+        }
+        self.len += width;
+    }
+
+    /// Appends `value` in unary, i.e. `value` zeros followed by a one.
+    fn push_unary(&mut self, mut value: u64) {
+        while value >= 64 {
+            self.push_bits(0, 64);
+            value -= 64;
+        }
+        self.push_bits(1 << value, value as usize + 1);
+    }
+
+    /// Appends the bits of `other`.
+    fn append(&mut self, other: &BitWriter) {
+        for (idx, &word) in other.words.iter().enumerate() {
+            self.push_bits(word, 64.min(other.len - idx * 64));
+        }
+    }
+}
+
+/// Minimal perfect hash function built with the RecSplit algorithm, see the module documentation.
+///
+/// # Examples
+/// ```
+/// # use entropy_map::{RecSplit, DEFAULT_RECSPLIT_BUCKET_SIZE};
+/// let keys: Vec<u64> = (0..10_000).collect();
+/// let mphf: RecSplit = RecSplit::from_slice(&keys, DEFAULT_RECSPLIT_BUCKET_SIZE).unwrap();
+///
+/// let mut indices: Vec<usize> = keys.iter().map(|key| mphf.get(key).unwrap()).collect();
+/// indices.sort_unstable();
+/// assert!(indices.into_iter().eq(0..keys.len()));
+/// ```
+#[derive(Debug, Default)]
+#[cfg_attr(feature = "rkyv_derive", derive(rkyv::Archive, rkyv::Deserialize, rkyv::Serialize))]
+#[cfg_attr(feature = "rkyv_derive", archive_attr(derive(rkyv::CheckBytes)))]
+pub struct RecSplit<H: Hasher + Default = WyHash> {
+    /// Number of keys
+    n: u64,
+    /// Number of buckets
+    num_buckets: u64,
+    /// Number of keys before every bucket, followed by `n`
+    bucket_offsets: EliasFano,
+    /// Position in `seeds` of the seeds of every bucket
+    bucket_positions: EliasFano,
+    /// Golomb-Rice coded seeds of all buckets, the fixed bits of all nodes of a bucket followed by
+    /// their unary bits, both in depth-first order
+    seeds: Box<[u64]>,
+    /// Seed hashed before every key, 0 for unkeyed hashing
+    hash_seed: u64,
+    /// Phantom field for the hasher
+    _phantom_hasher: PhantomData<H>,
+}
+
+impl<H: Hasher + Default> RecSplit<H> {
+    /// Initializes `RecSplit` using slice of `keys` with `bucket_size` keys per bucket on average.
+    ///
+    /// Larger buckets mean fewer bits per key (~1.7 with `DEFAULT_RECSPLIT_BUCKET_SIZE`, ~1.85 with
+    /// buckets of 100 keys) but slower queries, as they skip more nodes of the splitting trees.
+    /// Buckets are built in parallel with `rayon` feature. Keys with the same 64-bit hash fail with
+    /// `MphfError::HashCollision`.
+    pub fn from_slice<K: Hash>(keys: &[K], bucket_size: usize) -> Result<Self, MphfError> {
+        Self::from_slice_with_seed(keys, bucket_size, 0)
+    }
+
+    /// Same as [`RecSplit::from_slice`], but hashes `hash_seed` before every key, see
+    /// [`BuildPolicy::with_hash_seed`](crate::BuildPolicy::with_hash_seed).
+    pub fn from_slice_with_seed<K: Hash>(keys: &[K], bucket_size: usize, hash_seed: u64) -> Result<Self, MphfError> {
+        let hashes: Vec<u64> = keys.iter().map(|key| hash_key_seeded::<H, _>(hash_seed, key)).collect();
+        Self::from_hashes(&hashes, bucket_size, hash_seed)
+    }
+
+    /// Builds `RecSplit` from the 64-bit `hashes` of keys computed with `hash_seed`.
+    fn from_hashes(hashes: &[u64], bucket_size: usize, hash_seed: u64) -> Result<Self, MphfError> {
+        if bucket_size == 0 || bucket_size > RECSPLIT_MAX_BUCKET_SIZE {
+            return Err(MphfError::InvalidBucketSize);
+        }
+        let n = hashes.len() as u64;
+        let num_buckets = n.div_ceil(bucket_size as u64).max(1);
+
+        let mut buckets = vec![vec![]; num_buckets as usize];
+        for &hash in hashes {
+            buckets[fastrange64(hash, num_buckets) as usize].push(hash);
+        }
+
+        #[cfg(not(feature = "rayon"))]
+        let built = buckets.iter_mut();
+        #[cfg(feature = "rayon")]
+        let built = buckets.par_iter_mut();
+        let built = built
+            .map(|hashes| build_bucket(hashes))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let mut seeds = BitWriter::default();
+        let (mut offsets, mut positions) = (vec![0], vec![]);
+        for (hashes, bucket_seeds) in buckets.iter().zip(&built) {
+            offsets.push(offsets.last().unwrap() + hashes.len() as u64);
+            positions.push(seeds.len as u64);
+            seeds.append(bucket_seeds);
+        }
+        // trailing word so that queries read two words for codes crossing a word boundary
+        seeds.words.push(0);
+
+        Ok(RecSplit {
+            n,
+            num_buckets,
+            bucket_offsets: EliasFano::new(&offsets),
+            bucket_positions: EliasFano::new(&positions),
+            seeds: seeds.words.into_boxed_slice(),
+            hash_seed,
+            _phantom_hasher: PhantomData,
+        })
+    }
+
+    /// Returns the index associated with `key`, within `0..len()` range. Unlike
+    /// [`Mphf::get`](crate::Mphf::get), absent keys are mapped to an arbitrary index, `None` being
+    /// returned only when `RecSplit` is empty.
+    #[inline]
+    pub fn get<K: Hash + ?Sized>(&self, key: &K) -> Option<usize> {
+        self.get_by_hash(self.hash_key(key))
+    }
+
+    /// Same as [`RecSplit::get`], but re-uses the hash precomputed by `HashedKey`.
+    #[inline]
+    pub fn get_hashed<K: ?Sized>(&self, key: &HashedKey<K, H>) -> Option<usize> {
+        // hashes computed with another seed would map to arbitrary indices
+        if key.hash_seed() != self.hash_seed {
+            return None;
+        }
+        self.get_by_hash(key.hash())
+    }
+
+    /// Returns the hash of `key` as computed by `get`, to be passed to [`RecSplit::get_by_hash`].
+    #[inline]
+    pub fn hash_key<K: Hash + ?Sized>(&self, key: &K) -> u64 {
+        hash_key_seeded::<H, _>(self.hash_seed, key)
+    }
+
+    /// Returns the index associated with the key `hash`, see [`RecSplit::get`].
+    #[inline]
+    pub fn get_by_hash(&self, hash: u64) -> Option<usize> {
+        if self.n == 0 {
+            return None;
+        }
+        let bucket_idx = fastrange64(hash, self.num_buckets) as usize;
+        let (offsets, positions) = (&self.bucket_offsets, &self.bucket_positions);
+        // SAFETY: offsets hold `num_buckets + 1` values and positions `num_buckets` ones
+        let (offset, end, position) = unsafe {
+            (
+                elias_fano_get(offsets.low_width, &offsets.low, &offsets.high, bucket_idx),
+                elias_fano_get(offsets.low_width, &offsets.low, &offsets.high, bucket_idx + 1),
+                elias_fano_get(positions.low_width, &positions.low, &positions.high, bucket_idx),
+            )
+        };
+        Some(get_in_bucket(hash, &self.seeds, position, offset, end - offset))
+    }
+
+    /// Returns the seed hashed before every key, 0 for unkeyed hashing.
+    #[inline]
+    pub fn hash_seed(&self) -> u64 {
+        self.hash_seed
+    }
+
+    /// Returns the number of keys.
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.n as usize
+    }
+
+    /// Returns `true` if there are no keys.
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.n == 0
+    }
+
+    /// Returns the total number of bytes occupied by `RecSplit`.
+    pub fn size(&self) -> usize {
+        size_of_val(self) - size_of_val(&self.bucket_offsets) - size_of_val(&self.bucket_positions)
+            + self.bucket_offsets.size()
+            + self.bucket_positions.size()
+            + size_of_val(self.seeds.as_ref())
+    }
+}
+
+/// Builds the splitting tree of a bucket of `hashes`, returning the fixed bits of its seeds
+/// followed by their unary bits.
+fn build_bucket(hashes: &mut [u64]) -> Result<BitWriter, MphfError> {
+    if hashes.len() > MAX_SPLIT_SIZE {
+        return Err(MphfError::LevelSizeOverflow);
+    }
+    hashes.sort_unstable();
+    if hashes.windows(2).any(|pair| pair[0] == pair[1]) {
+        return Err(MphfError::HashCollision);
+    }
+
+    let (mut fixed, mut unary) = (BitWriter::default(), BitWriter::default());
+    build_node(hashes, 0, split_infos(), &mut fixed, &mut unary);
+    fixed.append(&unary);
+    Ok(fixed)
+}
+
+/// Searches the seed of the node of `hashes` at `depth` and of its children, writing their
+/// Golomb-Rice codes to `fixed` and `unary`.
+fn build_node(hashes: &mut [u64], depth: u32, infos: &[SplitInfo], fixed: &mut BitWriter, unary: &mut BitWriter) {
+    let m = hashes.len();
+    if m <= 1 {
+        return;
+    }
+    let info = infos[m];
+    let unit = info.unit as usize;
+
+    let seed = (0u64..)
+        .find(|&seed| {
+            if m <= RECSPLIT_LEAF_SIZE {
+                let mut taken = 0u32;
+                hashes.iter().all(|&hash| {
+                    let bit = 1 << fastrange64(remix(hash, seed, depth), m as u64);
+                    let free = taken & bit == 0;
+                    taken |= bit;
+                    free
+                })
+            } else {
+                let mut counts = [0usize; 4];
+                for &hash in hashes.iter() {
+                    counts[fastrange64(remix(hash, seed, depth), m as u64) as usize / unit] += 1;
+                }
+                (0..m)
+                    .step_by(unit)
+                    .enumerate()
+                    .all(|(child, start)| counts[child] == unit.min(m - start))
+            }
+        })
+        .unwrap();
+    fixed.push_bits(seed & ((1 << info.rice) - 1), info.rice as usize);
+    unary.push_unary(seed >> info.rice);
+
+    if m > RECSPLIT_LEAF_SIZE {
+        hashes.sort_unstable_by_key(|&hash| fastrange64(remix(hash, seed, depth), m as u64));
+        for child in hashes.chunks_mut(unit) {
+            build_node(child, depth + 1, infos, fixed, unary);
+        }
+    }
+}
+
+/// Returns the index of the key `hash` within a bucket of `m` keys starting at index `offset`,
+/// whose seeds start at bit `position` of `seeds`. Shared with the archived version.
+#[inline]
+fn get_in_bucket(hash: u64, seeds: &[u64], position: u64, mut offset: u64, mut m: u64) -> usize {
+    let infos = split_infos();
+    let mut fixed_pos = position as usize;
+    let mut unary_pos = fixed_pos + infos[m as usize].fixed_bits as usize;
+    let mut depth = 0;
+    while m > 1 {
+        let info = infos[m as usize];
+        let quotient = read_unary(seeds, &mut unary_pos);
+        let seed = (quotient << info.rice) | read_bits(seeds, fixed_pos, info.rice);
+        fixed_pos += info.rice as usize;
+
+        let pos = fastrange64(remix(hash, seed, depth), m);
+        if m <= RECSPLIT_LEAF_SIZE as u64 {
+            return (offset + pos) as usize;
+        }
+
+        // skip the subtrees of children preceding the one of `hash`, all of `unit` keys
+        let unit = info.unit as u64;
+        let child = pos / unit;
+        let unit_info = infos[unit as usize];
+        fixed_pos += child as usize * unit_info.fixed_bits as usize;
+        skip_ones(seeds, &mut unary_pos, child as usize * unit_info.nodes as usize);
+        offset += child * unit;
+        m = unit.min(m - child * unit);
+        depth += 1;
+    }
+    offset as usize
+}
+
+/// Returns the `width` bits of `seeds` starting at bit `pos`.
+#[inline]
+fn read_bits(seeds: &[u64], pos: usize, width: u8) -> u64 {
+    if width == 0 {
+        return 0;
+    }
+    let (word, shift) = (pos / 64, pos % 64);
+    let mut bits = seeds[word] >> shift;
+    if shift + width as usize > 64 {
+        bits |= seeds[word + 1] << (64 - shift);
+    }
+    bits & (u64::MAX >> (64 - width))
+}
+
+/// Returns the unary code of `seeds` starting at bit `pos`, moving `pos` past it.
+#[inline]
+fn read_unary(seeds: &[u64], pos: &mut usize) -> u64 {
+    let mut value = 0;
+    loop {
+        let shift = *pos % 64;
+        let word = seeds[*pos / 64] >> shift;
+        if word != 0 {
+            let zeros = word.trailing_zeros() as usize;
+            *pos += zeros + 1;
+            return value + zeros as u64;
+        }
+        value += (64 - shift) as u64;
+        *pos += 64 - shift;
+    }
+}
+
+/// Moves `pos` past the next `count` set bits of `seeds`.
+#[inline]
+fn skip_ones(seeds: &[u64], pos: &mut usize, mut count: usize) {
+    while count > 0 {
+        let shift = *pos % 64;
+        let mut word = seeds[*pos / 64] >> shift;
+        let ones = word.count_ones() as usize;
+        if ones < count {
+            count -= ones;
+            *pos += 64 - shift;
+            continue;
+        }
+        for _ in 1..count {
+            word &= word - 1;
+        }
+        *pos += word.trailing_zeros() as usize + 1;
+        return;
+    }
+}
+
+/// Returns `hash` remixed with the `seed` of a node at `depth`.
+#[inline]
+fn remix(hash: u64, seed: u64, depth: u32) -> u64 {
+    let seed = (seed | (depth as u64) << 48).wrapping_mul(0x9e37_79b9_7f4a_7c15);
+    let x = ((hash ^ seed) as u128).wrapping_mul(0x5851f42d4c957f2d);
+    (x as u64) ^ ((x >> 64) as u64)
+}
+
+/// Maps `x` uniformly to `0..n` range, see `fastmod32` of `Mphf`.
+#[inline]
+fn fastrange64(x: u64, n: u64) -> u64 {
+    (((x as u128) * (n as u128)) >> 64) as u64
+}
+
+/// Implement `get` for `Archived` version of `RecSplit` if feature is enabled
+#[cfg(feature = "rkyv_derive")]
+impl<H: Hasher + Default> ArchivedRecSplit<H> {
+    /// Returns the index associated with `key`, see [`RecSplit::get`].
+    #[inline]
+    pub fn get<K: Hash + ?Sized>(&self, key: &K) -> Option<usize> {
+        self.get_by_hash(hash_key_seeded::<H, _>(self.hash_seed, key))
+    }
+
+    /// Same as [`ArchivedRecSplit::get`], but re-uses the hash precomputed by `HashedKey`.
+    #[inline]
+    pub fn get_hashed<K: ?Sized>(&self, key: &HashedKey<K, H>) -> Option<usize> {
+        // hashes computed with another seed would map to arbitrary indices
+        if key.hash_seed() != self.hash_seed {
+            return None;
+        }
+        self.get_by_hash(key.hash())
+    }
+
+    /// Returns the index associated with the key `hash`, see [`RecSplit::get`].
+    #[inline]
+    pub fn get_by_hash(&self, hash: u64) -> Option<usize> {
+        if self.n == 0 {
+            return None;
+        }
+        let bucket_idx = fastrange64(hash, self.num_buckets) as usize;
+        let (offsets, positions) = (&self.bucket_offsets, &self.bucket_positions);
+        // SAFETY: offsets hold `num_buckets + 1` values and positions `num_buckets` ones
+        let (offset, end, position) = unsafe {
+            (
+                elias_fano_get(offsets.low_width, &offsets.low, &offsets.high, bucket_idx),
+                elias_fano_get(offsets.low_width, &offsets.low, &offsets.high, bucket_idx + 1),
+                elias_fano_get(positions.low_width, &positions.low, &positions.high, bucket_idx),
+            )
+        };
+        Some(get_in_bucket(hash, &self.seeds, position, offset, end - offset))
+    }
+
+    /// Returns the number of keys.
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.n as usize
+    }
+
+    /// Returns `true` if there are no keys.
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.n == 0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_bijection(mphf: &RecSplit, keys: &[u64]) {
+        let mut indices: Vec<usize> = keys.iter().map(|key| mphf.get(key).unwrap()).collect();
+        indices.sort_unstable();
+        assert!(indices.into_iter().eq(0..keys.len()), "n = {}", keys.len());
+    }
+
+    #[test]
+    fn test_split_infos() {
+        let infos = split_infos();
+        assert_eq!(infos[1].nodes, 0);
+        assert_eq!(infos[RECSPLIT_LEAF_SIZE].nodes, 1);
+        assert_eq!(infos[LOWER_UNIT].nodes, 5);
+        assert_eq!(infos[UPPER_UNIT].nodes, 16);
+        assert_eq!(infos[200].unit as usize, 2 * UPPER_UNIT);
+        assert!(infos.iter().skip(2).all(|info| info.nodes > 0 && info.unit > 0));
+    }
+
+    #[test]
+    fn test_elias_fano() {
+        let values: Vec<u64> = (0..1000u64).map(|i| i * i / 3).collect();
+        let ef = EliasFano::new(&values);
+        for (idx, &value) in values.iter().enumerate() {
+            assert_eq!(unsafe { elias_fano_get(ef.low_width, &ef.low, &ef.high, idx) }, value);
+        }
+    }
+
+    #[test]
+    fn test_recsplit() {
+        for n in [1, 2, 3, 10, 100, 1_000, 50_000] {
+            let keys: Vec<u64> = (0..n).collect();
+            let mphf: RecSplit = RecSplit::from_slice(&keys, DEFAULT_RECSPLIT_BUCKET_SIZE).unwrap();
+            assert_eq!(mphf.len(), keys.len());
+            assert_bijection(&mphf, &keys);
+        }
+
+        let keys: Vec<u64> = (0..50_000).collect();
+        for bucket_size in [1, 8, 100] {
+            let mphf: RecSplit = RecSplit::from_slice(&keys[..5_000], bucket_size).unwrap();
+            assert_bijection(&mphf, &keys[..5_000]);
+        }
+
+        let mphf: RecSplit = RecSplit::from_slice_with_seed(&keys, DEFAULT_RECSPLIT_BUCKET_SIZE, 42).unwrap();
+        assert_eq!(mphf.hash_seed(), 42);
+        assert_bijection(&mphf, &keys);
+
+        let mphf: RecSplit = RecSplit::from_slice(&keys, DEFAULT_RECSPLIT_BUCKET_SIZE).unwrap();
+        let bits_per_key = mphf.size() as f32 * 8.0 / keys.len() as f32;
+        assert!(bits_per_key < 1.8, "{bits_per_key}");
+        assert!(keys
+            .iter()
+            .all(|key| mphf.get_hashed(&HashedKey::new(key)) == mphf.get(key)));
+    }
+
+    #[test]
+    fn test_errors() {
+        let mphf: RecSplit = RecSplit::from_slice::<u64>(&[], DEFAULT_RECSPLIT_BUCKET_SIZE).unwrap();
+        assert!(mphf.is_empty());
+        assert_eq!(mphf.get(&1), None);
+
+        for bucket_size in [0, RECSPLIT_MAX_BUCKET_SIZE + 1] {
+            let res: Result<RecSplit, _> = RecSplit::from_slice(&[1u64], bucket_size);
+            assert!(matches!(res, Err(MphfError::InvalidBucketSize)));
+        }
+
+        let res: Result<RecSplit, _> = RecSplit::from_slice(&[1u64, 2, 1], DEFAULT_RECSPLIT_BUCKET_SIZE);
+        assert!(matches!(res, Err(MphfError::HashCollision)));
+    }
+
+    #[cfg(feature = "rkyv_derive")]
+    #[test]
+    fn test_rkyv() {
+        let keys: Vec<u64> = (0..10_000).collect();
+        let mphf: RecSplit = RecSplit::from_slice(&keys, 100).unwrap();
+        let rkyv_bytes = rkyv::to_bytes::<_, 1024>(&mphf).unwrap();
+        let rkyv_mphf = rkyv::check_archived_root::<RecSplit>(&rkyv_bytes).unwrap();
+
+        assert_eq!(rkyv_mphf.len(), keys.len());
+        for key in &keys {
+            assert_eq!(rkyv_mphf.get(key), mphf.get(key));
+        }
+    }
+}
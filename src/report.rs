@@ -0,0 +1,160 @@
+//! A module providing `BuildReport`, a summary of the construction of MPHF-based structures.
+//!
+//! Reports are returned by the `*_with_report` constructors alongside the built structure and
+//! expose the same quality metrics tests and benchmarks compute internally (levels occupancy,
+//! construction time and final bits per key), so that applications can log them and alert on
+//! build-quality regressions. With `alloc-stats` feature enabled, reports also include the peak
+//! memory allocated during construction, see `CountingAllocator`.
+
+use std::time::Duration;
+
+/// Statistics collected while building a single MPHF level.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct LevelReport {
+    /// Time spent building the level
+    pub duration: Duration,
+    /// Number of keys which entered the level
+    pub keys: usize,
+    /// Number of keys retained for the next level, i.e. keys which collided at this level
+    pub retained_keys: usize,
+    /// Detailed statistics of the level, `None` unless enabled with `BuildPolicy::with_diagnostics`
+    pub diagnostics: Option<LevelDiagnostics>,
+}
+
+/// Detailed statistics of a single MPHF level, e.g. for comparing construction quality against
+/// other MPHF implementations.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct LevelDiagnostics {
+    /// Number of non-empty groups by their chosen seed, indexed by seed
+    pub seed_histogram: Vec<usize>,
+    /// Number of keys which entered every group of the level, indexed by group
+    pub group_keys: Vec<u32>,
+    /// Number of keys placed without collisions in every group of the level, indexed by group
+    pub group_placed_keys: Vec<u32>,
+    /// Statistics of group seed trials, indexed by seed
+    pub seed_trials: Vec<SeedTrial>,
+}
+
+/// Statistics of trying a single seed over the groups of a level.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct SeedTrial {
+    /// Number of groups which tried the seed, i.e. weren't resolved by previous seeds
+    pub groups: usize,
+    /// Total number of keys of these groups which collided with the seed
+    pub collided_keys: usize,
+}
+
+impl SeedTrial {
+    /// Adds statistics of `other` trials of the same seed, e.g. over another range of groups.
+    #[cfg(feature = "rayon")]
+    #[inline]
+    pub(crate) fn merge(trials: &mut Vec<SeedTrial>, other: &[SeedTrial]) {
+        if trials.len() < other.len() {
+            trials.resize(other.len(), SeedTrial::default());
+        }
+        for (trial, other) in trials.iter_mut().zip(other) {
+            trial.groups += other.groups;
+            trial.collided_keys += other.collided_keys;
+        }
+    }
+}
+
+/// Report describing the construction of `Mphf` or any structure built on top of it.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct BuildReport {
+    /// Per level statistics, in the order levels were built
+    pub levels: Vec<LevelReport>,
+    /// Total construction time, including keys re-ordering and values packing for maps
+    pub duration: Duration,
+    /// Ratio of total values to unique values stored in the dictionary, `None` for structures
+    /// without values (`Mphf` and `Set`)
+    pub dedup_ratio: Option<f32>,
+    /// Final size of the constructed structure in bits per key
+    pub bits_per_key: f32,
+    /// `gamma` parameter of the successful MPHF build attempt
+    pub gamma: f32,
+    /// Number of MPHF build attempts, greater than 1 if `gamma` was escalated by `BuildPolicy`
+    pub attempts: usize,
+    /// Number of keys stored in the MPHF fallback table, see
+    /// [`BuildPolicy::with_fallback`](crate::BuildPolicy::with_fallback)
+    pub fallback_keys: usize,
+    /// Peak number of bytes allocated during construction (including the constructed structure)
+    /// on top of the memory allocated before it started. `None` unless `alloc-stats` feature is
+    /// enabled and `CountingAllocator` is installed as the global allocator.
+    pub peak_alloc_bytes: Option<usize>,
+}
+
+/// Statistics of a constructed `Mphf` describing its quality, see
+/// [`Mphf::stats`](crate::Mphf::stats).
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct MphfStats {
+    /// Number of keys the MPHF maps, including the ones of the fallback table
+    pub keys: usize,
+    /// Size of the MPHF in bits per key
+    pub bits_per_key: f32,
+    /// Number of levels
+    pub levels: usize,
+    /// Number of groups at each level, levels being ordered from first to last
+    pub groups_per_level: Vec<u32>,
+    /// Number of keys placed at each level, levels being ordered from first to last
+    pub keys_per_level: Vec<usize>,
+    /// Average number of levels walked by queries of present keys, 0 for keys of the fallback table
+    pub avg_query_levels: f32,
+    /// Number of seeds tried by the construction counting the chosen seed of every group plus one,
+    /// exact for groups which placed all their keys, a lower bound for the others
+    pub build_seed_trials: u64,
+}
+
+/// Scope of peak allocation tracking of a single construction, a no-op without `alloc-stats` feature.
+pub(crate) struct AllocScope(#[cfg(feature = "alloc-stats")] crate::alloc_stats::PeakTracker);
+
+impl AllocScope {
+    /// Starts tracking allocations of a construction.
+    #[inline]
+    pub(crate) fn start() -> Self {
+        AllocScope(
+            #[cfg(feature = "alloc-stats")]
+            crate::alloc_stats::PeakTracker::start(),
+        )
+    }
+
+    /// Finishes tracking and records the peak allocated bytes into `report`.
+    #[inline]
+    pub(crate) fn finish(self, report: &mut BuildReport) {
+        #[cfg(feature = "alloc-stats")]
+        {
+            report.peak_alloc_bytes = self.0.finish();
+        }
+        #[cfg(not(feature = "alloc-stats"))]
+        let _ = report;
+    }
+}
+
+impl BuildReport {
+    /// Returns the number of keys the structure was built from.
+    #[inline]
+    pub fn keys(&self) -> usize {
+        self.levels.first().map_or(self.fallback_keys, |level| level.keys)
+    }
+
+    /// Updates `bits_per_key` using the total `size` of the structure in bytes.
+    #[inline]
+    pub(crate) fn set_size(&mut self, size: usize) {
+        let keys = self.keys();
+        self.bits_per_key = if keys == 0 {
+            0.0
+        } else {
+            (size * 8) as f32 / keys as f32
+        };
+    }
+
+    /// Updates `dedup_ratio` using the number of `values` and `unique_values` in the dictionary.
+    #[inline]
+    pub(crate) fn set_dedup(&mut self, values: usize, unique_values: usize) {
+        self.dedup_ratio = Some(if unique_values == 0 {
+            1.0
+        } else {
+            values as f32 / unique_values as f32
+        });
+    }
+}
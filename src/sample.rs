@@ -0,0 +1,16 @@
+//! Uniform sampling of stored keys, e.g. to probe a random subset of keys of a deployed structure.
+
+use rand::seq::index;
+use rand::Rng;
+
+/// Returns `min(k, keys.len())` distinct keys of `keys` chosen uniformly at random by `rng`, in
+/// random order.
+pub(crate) fn sample_keys<'a, K, R>(keys: &'a [K], k: usize, rng: &mut R) -> Vec<&'a K>
+where
+    R: Rng + ?Sized,
+{
+    index::sample(rng, keys.len(), k.min(keys.len()))
+        .into_iter()
+        .map(|idx| &keys[idx])
+        .collect()
+}
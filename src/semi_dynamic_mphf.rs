@@ -0,0 +1,319 @@
+//! A module providing `SemiDynamicMphf`, an `Mphf` accepting inserts between rebuilds.
+//!
+//! `Mphf` is immutable, so adding keys means rebuilding it from scratch. `SemiDynamicMphf` keeps
+//! the keys of a frozen `Mphf` in the order of their indices, so that lookups of absent keys are
+//! detected, and inserts new keys into an overflow `HashMap` assigning them the next indices. Once
+//! the overflow grows large enough, e.g. as a fraction of `frozen_len()`, [`SemiDynamicMphf::rebuild`]
+//! merges it into a new `Mphf`, so that the cost of construction is amortized over many inserts.
+//!
+//! Rebuilds can run in the background: [`SemiDynamicMphf::start_rebuild`] snapshots the keys into
+//! a [`PendingRebuild`] to build on another thread (e.g. with `build_async`), while inserts keep
+//! going to the overflow, then [`SemiDynamicMphf::finish_rebuild`] swaps the rebuilt structure in
+//! and re-inserts the keys added since the snapshot.
+//!
+//! Indices are in `0..len()` range, but rebuilds reassign them, as of any `Mphf` construction.
+
+use std::borrow::Borrow;
+use std::collections::HashMap;
+use std::hash::{Hash, Hasher};
+
+use num::{PrimInt, Unsigned};
+use wyhash::WyHash;
+
+use crate::mphf::{BuildPolicy, Mphf, MphfError};
+use crate::progress::BuildProgress;
+use crate::report::BuildReport;
+
+/// `Mphf` with an overflow of keys inserted since its construction, see the module documentation.
+///
+/// # Examples
+/// ```
+/// # use entropy_map::{SemiDynamicMphf, DEFAULT_GAMMA};
+/// let mut mphf: SemiDynamicMphf<u64> = SemiDynamicMphf::from_slice(&[1, 2, 3], DEFAULT_GAMMA).unwrap();
+/// assert_eq!(mphf.insert(4), 3);
+/// assert_eq!(mphf.get(&4), Some(3));
+/// assert_eq!(mphf.get(&5), None);
+///
+/// mphf.rebuild().unwrap();
+/// assert_eq!((mphf.frozen_len(), mphf.overflow_len()), (4, 0));
+/// assert!(mphf.get(&4).unwrap() < 4);
+/// ```
+pub struct SemiDynamicMphf<
+    K,
+    const B: usize = 32,
+    const S: usize = 8,
+    ST: PrimInt + Unsigned = u8,
+    H: Hasher + Default = WyHash,
+> {
+    /// MPHF of the frozen keys
+    mphf: Mphf<B, S, ST, H>,
+    /// Frozen keys in the order of their `mphf` indices
+    keys: Box<[K]>,
+    /// Keys inserted since the construction of `mphf`, with their indices following the frozen ones
+    overflow: HashMap<K, usize>,
+    /// Policy of `mphf` constructions
+    policy: BuildPolicy,
+}
+
+/// Keys snapshotted by [`SemiDynamicMphf::start_rebuild`] to build a `SemiDynamicMphf` from, e.g.
+/// on a background thread.
+pub struct PendingRebuild<K> {
+    /// Frozen and overflow keys at the time of the snapshot
+    keys: Vec<K>,
+    /// Policy of the `Mphf` construction
+    policy: BuildPolicy,
+}
+
+impl<K, const B: usize, const S: usize, ST, H> SemiDynamicMphf<K, B, S, ST, H>
+where
+    K: Hash + Eq,
+    ST: PrimInt + Unsigned,
+    H: Hasher + Default,
+{
+    /// Initializes `SemiDynamicMphf` using slice of distinct `keys` and parameter `gamma`.
+    pub fn from_slice(keys: &[K], gamma: f32) -> Result<Self, MphfError>
+    where
+        K: Clone,
+    {
+        Self::from_vec(keys.to_vec(), &BuildPolicy::fixed(gamma)).map(|(mphf, _)| mphf)
+    }
+
+    /// Initializes `SemiDynamicMphf` using distinct `keys`, building the frozen `Mphf` and the ones
+    /// of later rebuilds as configured by `policy`.
+    pub fn from_vec(keys: Vec<K>, policy: &BuildPolicy) -> Result<(Self, BuildReport), MphfError> {
+        Self::from_vec_with_progress(keys, policy, &BuildProgress::default())
+    }
+
+    /// Same as [`SemiDynamicMphf::from_vec`], but reports construction progress to `progress`.
+    fn from_vec_with_progress(
+        keys: Vec<K>,
+        policy: &BuildPolicy,
+        progress: &BuildProgress,
+    ) -> Result<(Self, BuildReport), MphfError> {
+        let (mphf, report) = Mphf::from_slice_with_progress(&keys, policy, progress)?;
+        let mut indexed: Vec<(usize, K)> = keys
+            .into_iter()
+            .map(|key| (mphf.get(&key).expect("key of MPHF"), key))
+            .collect();
+        indexed.sort_unstable_by_key(|&(idx, _)| idx);
+        let keys = indexed.into_iter().map(|(_, key)| key).collect();
+        Ok((
+            SemiDynamicMphf { mphf, keys, overflow: HashMap::new(), policy: *policy },
+            report,
+        ))
+    }
+
+    /// Returns the index associated with `key`, or `None` if it was neither frozen nor inserted.
+    #[inline]
+    pub fn get<Q>(&self, key: &Q) -> Option<usize>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        match self.mphf.get(key) {
+            Some(idx) if self.keys[idx].borrow() == key => Some(idx),
+            _ => self.overflow.get(key).copied(),
+        }
+    }
+
+    /// Returns `true` if `key` was frozen or inserted.
+    #[inline]
+    pub fn contains<Q>(&self, key: &Q) -> bool
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        self.get(key).is_some()
+    }
+
+    /// Inserts `key` into the overflow unless already present, returning its index.
+    pub fn insert(&mut self, key: K) -> usize {
+        if let Some(idx) = self.get(&key) {
+            return idx;
+        }
+        let idx = self.len();
+        self.overflow.insert(key, idx);
+        idx
+    }
+
+    /// Returns the number of keys, frozen and inserted.
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.keys.len() + self.overflow.len()
+    }
+
+    /// Returns `true` if there are no keys.
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Returns the number of keys of the frozen `Mphf`.
+    #[inline]
+    pub fn frozen_len(&self) -> usize {
+        self.keys.len()
+    }
+
+    /// Returns the number of keys inserted since the last rebuild.
+    #[inline]
+    pub fn overflow_len(&self) -> usize {
+        self.overflow.len()
+    }
+
+    /// Returns the frozen `Mphf`.
+    #[inline]
+    pub fn mphf(&self) -> &Mphf<B, S, ST, H> {
+        &self.mphf
+    }
+
+    /// Rebuilds the frozen `Mphf` from all keys, emptying the overflow and reassigning indices.
+    /// The structure is left unchanged on failure.
+    pub fn rebuild(&mut self) -> Result<BuildReport, MphfError> {
+        let keys: Vec<&K> = self.keys_by_index().collect();
+        let (mphf, report) = Mphf::from_slice_with_policy(&keys, &self.policy)?;
+
+        let mut indexed: Vec<(usize, K)> = std::mem::take(&mut self.keys)
+            .into_vec()
+            .into_iter()
+            .chain(std::mem::take(&mut self.overflow).into_keys())
+            .map(|key| (mphf.get(&key).expect("key of MPHF"), key))
+            .collect();
+        indexed.sort_unstable_by_key(|&(idx, _)| idx);
+        self.keys = indexed.into_iter().map(|(_, key)| key).collect();
+        self.mphf = mphf;
+        Ok(report)
+    }
+
+    /// Snapshots all keys into a [`PendingRebuild`], to be built while inserts continue and then
+    /// passed to [`SemiDynamicMphf::finish_rebuild`].
+    ///
+    /// # Examples
+    /// ```
+    /// # use entropy_map::{BuildProgress, SemiDynamicMphf, DEFAULT_GAMMA};
+    /// let mut mphf: SemiDynamicMphf<u64> = SemiDynamicMphf::from_slice(&[1, 2, 3], DEFAULT_GAMMA).unwrap();
+    /// mphf.insert(4);
+    ///
+    /// let pending = mphf.start_rebuild();
+    /// let rebuild = std::thread::spawn(move || pending.build(&BuildProgress::default()));
+    /// mphf.insert(5);
+    ///
+    /// let (rebuilt, _) = rebuild.join().unwrap().unwrap();
+    /// mphf.finish_rebuild(rebuilt);
+    /// assert_eq!((mphf.frozen_len(), mphf.overflow_len()), (4, 1));
+    /// assert_eq!(mphf.get(&5), Some(4));
+    /// ```
+    pub fn start_rebuild(&self) -> PendingRebuild<K>
+    where
+        K: Clone,
+    {
+        PendingRebuild { keys: self.keys_by_index().cloned().collect(), policy: self.policy }
+    }
+
+    /// Replaces the structure with `rebuilt`, built from a [`PendingRebuild`] of it, and
+    /// re-inserts the keys inserted since [`SemiDynamicMphf::start_rebuild`].
+    pub fn finish_rebuild(&mut self, rebuilt: Self) {
+        let old = std::mem::replace(self, rebuilt);
+        let mut inserted: Vec<(K, usize)> = old.overflow.into_iter().collect();
+        inserted.sort_unstable_by_key(|&(_, idx)| idx);
+        for (key, _) in inserted {
+            self.insert(key);
+        }
+    }
+
+    /// Returns frozen keys followed by the inserted ones, in the order of their indices.
+    fn keys_by_index(&self) -> impl Iterator<Item = &K> {
+        let mut inserted: Vec<(&K, usize)> = self.overflow.iter().map(|(key, &idx)| (key, idx)).collect();
+        inserted.sort_unstable_by_key(|&(_, idx)| idx);
+        self.keys.iter().chain(inserted.into_iter().map(|(key, _)| key))
+    }
+}
+
+impl<K: Hash + Eq> PendingRebuild<K> {
+    /// Returns the number of snapshotted keys.
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.keys.len()
+    }
+
+    /// Returns `true` if no keys were snapshotted.
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.keys.is_empty()
+    }
+
+    /// Builds a `SemiDynamicMphf` with an empty overflow from the snapshotted keys, reporting
+    /// construction progress to `progress` and failing with `MphfError::Cancelled` once
+    /// cancellation is requested via it, e.g. by dropping the handle of `build_async`.
+    pub fn build<const B: usize, const S: usize, ST, H>(
+        self,
+        progress: &BuildProgress,
+    ) -> Result<(SemiDynamicMphf<K, B, S, ST, H>, BuildReport), MphfError>
+    where
+        ST: PrimInt + Unsigned,
+        H: Hasher + Default,
+    {
+        SemiDynamicMphf::from_vec_with_progress(self.keys, &self.policy, progress)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mphf::DEFAULT_GAMMA;
+
+    fn check_indices(mphf: &SemiDynamicMphf<String>, keys: &[String]) {
+        let mut indices: Vec<usize> = keys.iter().map(|key| mphf.get(key.as_str()).unwrap()).collect();
+        indices.sort_unstable();
+        assert!(indices.into_iter().eq(0..keys.len()));
+    }
+
+    #[test]
+    fn test_inserts_and_rebuild() {
+        let keys: Vec<String> = (0..2_000).map(|i| format!("key-{i}")).collect();
+        let mut mphf: SemiDynamicMphf<String> = SemiDynamicMphf::from_slice(&keys[..1_000], DEFAULT_GAMMA).unwrap();
+        check_indices(&mphf, &keys[..1_000]);
+        assert!(!mphf.contains("key-1000"));
+
+        for (idx, key) in keys[1_000..].iter().enumerate() {
+            assert_eq!(mphf.insert(key.clone()), 1_000 + idx);
+        }
+        assert_eq!(mphf.insert(keys[42].clone()), mphf.get("key-42").unwrap());
+        assert_eq!(mphf.insert(keys[1_042].clone()), 1_042);
+        assert_eq!(
+            (mphf.len(), mphf.frozen_len(), mphf.overflow_len()),
+            (2_000, 1_000, 1_000)
+        );
+        check_indices(&mphf, &keys);
+
+        let report = mphf.rebuild().unwrap();
+        assert_eq!(report.keys(), 2_000);
+        assert_eq!((mphf.frozen_len(), mphf.overflow_len()), (2_000, 0));
+        check_indices(&mphf, &keys);
+        assert!(!mphf.contains("key-2000"));
+    }
+
+    #[test]
+    fn test_background_rebuild() {
+        let keys: Vec<String> = (0..3_000).map(|i| format!("key-{i}")).collect();
+        let mut mphf: SemiDynamicMphf<String> = SemiDynamicMphf::from_slice(&keys[..1_000], DEFAULT_GAMMA).unwrap();
+        keys[1_000..2_000].iter().for_each(|key| {
+            mphf.insert(key.clone());
+        });
+
+        let pending = mphf.start_rebuild();
+        assert_eq!(pending.len(), 2_000);
+        let (rebuilt, _) = pending.build(&BuildProgress::default()).unwrap();
+        keys[2_000..].iter().for_each(|key| {
+            mphf.insert(key.clone());
+        });
+
+        mphf.finish_rebuild(rebuilt);
+        assert_eq!((mphf.frozen_len(), mphf.overflow_len()), (2_000, 1_000));
+        check_indices(&mphf, &keys);
+
+        let progress = BuildProgress::new();
+        progress.cancel();
+        let res = mphf.start_rebuild().build::<32, 8, u8, WyHash>(&progress);
+        assert!(matches!(res, Err(MphfError::Cancelled)));
+    }
+}
@@ -14,32 +14,58 @@ use std::borrow::Borrow;
 use std::collections::HashSet;
 use std::hash::{Hash, Hasher};
 use std::mem::size_of_val;
+use std::time::Instant;
 
 use num::{PrimInt, Unsigned};
 use wyhash::WyHash;
 
-use crate::mphf::{Mphf, MphfError, DEFAULT_GAMMA};
+use crate::access;
+use crate::hashed_key::HashedKey;
+use crate::invariants::{self, InvariantError};
+use crate::mphf::{BuildPolicy, Mphf, MphfError, DEFAULT_GAMMA};
+#[cfg(feature = "rkyv_derive")]
+use crate::mphf_extras::ExtrasAccess;
+use crate::mphf_extras::{Extras, ExtrasLayout, NoExtras};
+use crate::progress::BuildProgress;
+use crate::rank::RankedBits;
+use crate::report::{AllocScope, BuildReport};
+use crate::sip_hasher::SipHasher13;
+use crate::size_report::{self, SizeReport};
+
+/// `Set` with 64-bit groups, trading slightly slower construction and queries for a smaller MPHF
+/// (~3% fewer bits per key than the defaults).
+pub type SetCompact<K> = Set<K, 64, 8>;
+
+/// `Set` with 16-bit groups and 16 seeds per group, trading ~25% more MPHF bits per key than the
+/// defaults for several times faster construction.
+pub type SetFast<K> = Set<K, 16, 4>;
+
+/// `Set` hashing keys with `SipHasher13`, to be built with a random hash seed via
+/// [`BuildPolicy::keyed`] when keys come from untrusted input.
+pub type SetKeyed<K> = Set<K, 32, 8, u8, SipHasher13, Extras>;
 
 /// An efficient, immutable set.
 #[derive(Default)]
 #[cfg_attr(feature = "rkyv_derive", derive(rkyv::Archive, rkyv::Deserialize, rkyv::Serialize))]
 #[cfg_attr(feature = "rkyv_derive", archive_attr(derive(rkyv::CheckBytes)))]
-pub struct Set<K, const B: usize = 32, const S: usize = 8, ST = u8, H = WyHash>
+pub struct Set<K, const B: usize = 32, const S: usize = 8, ST = u8, H = WyHash, E = NoExtras>
 where
     ST: PrimInt + Unsigned,
     H: Hasher + Default,
+    E: ExtrasLayout,
 {
     /// Minimally Perfect Hash Function for keys indices retrieval
-    mphf: Mphf<B, S, ST, H>,
+    pub(crate) mphf: Mphf<B, S, ST, H, RankedBits, E>,
     /// Set keys
-    keys: Box<[K]>,
+    pub(crate) keys: Box<[K]>,
 }
 
-impl<K, const B: usize, const S: usize, ST, H> Set<K, B, S, ST, H>
+impl<K, const B: usize, const S: usize, ST, H, E> Set<K, B, S, ST, H, E>
 where
     K: Eq + Hash,
     ST: PrimInt + Unsigned,
     H: Hasher + Default,
+    E: ExtrasLayout,
 {
     /// Constructs a `Set` from an iterator of keys and MPHF function parameters.
     ///
@@ -54,9 +80,74 @@ where
     where
         I: IntoIterator<Item = K>,
     {
+        Self::from_iter_with_report(iter, gamma).map(|(set, _)| set)
+    }
+
+    /// Constructs a `Set` from a `HashSet` and MPHF function parameters, which unlike
+    /// `TryFrom<HashSet>` works with any `B`, `S`, `ST` and `H`.
+    ///
+    /// # Examples
+    /// ```
+    /// # use std::collections::HashSet;
+    /// # use entropy_map::{SetCompact, DEFAULT_GAMMA};
+    /// let set = SetCompact::from_hashset_with_gamma(HashSet::from([1, 2, 3]), DEFAULT_GAMMA).unwrap();
+    /// assert!(set.contains(&1));
+    /// ```
+    pub fn from_hashset_with_gamma(set: HashSet<K>, gamma: f32) -> Result<Self, MphfError> {
+        Self::from_iter_with_params(set, gamma)
+    }
+
+    /// Constructs a `Set` from an iterator of keys and MPHF function parameters, additionally
+    /// returning a `BuildReport` with construction statistics.
+    ///
+    /// # Examples
+    /// ```
+    /// use entropy_map::{Set, DEFAULT_GAMMA};
+    ///
+    /// let (set, report) = Set::<u32>::from_iter_with_report([1, 2, 3], DEFAULT_GAMMA).unwrap();
+    /// assert_eq!(report.keys(), 3);
+    /// assert_eq!(report.dedup_ratio, None);
+    /// ```
+    pub fn from_iter_with_report<I>(iter: I, gamma: f32) -> Result<(Self, BuildReport), MphfError>
+    where
+        I: IntoIterator<Item = K>,
+    {
+        Self::from_iter_with_policy(iter, &BuildPolicy::fixed(gamma))
+    }
+
+    /// Constructs a `Set` from an iterator of keys, escalating MPHF `gamma` on failed
+    /// builds according to `policy`, and returns it along with a `BuildReport`.
+    ///
+    /// # Examples
+    /// ```
+    /// use entropy_map::{BuildPolicy, Set};
+    ///
+    /// let policy = BuildPolicy::default();
+    /// let (set, report) = Set::<u32>::from_iter_with_policy([1, 2, 3], &policy).unwrap();
+    /// assert_eq!(report.attempts, 1);
+    /// ```
+    pub fn from_iter_with_policy<I>(iter: I, policy: &BuildPolicy) -> Result<(Self, BuildReport), MphfError>
+    where
+        I: IntoIterator<Item = K>,
+    {
+        Self::from_iter_with_progress(iter, policy, &BuildProgress::default())
+    }
+
+    /// Same as [`Set::from_iter_with_policy`], but reports MPHF construction progress to
+    /// `progress` and fails once cancellation is requested via it.
+    pub fn from_iter_with_progress<I>(
+        iter: I,
+        policy: &BuildPolicy,
+        progress: &BuildProgress,
+    ) -> Result<(Self, BuildReport), MphfError>
+    where
+        I: IntoIterator<Item = K>,
+    {
+        let t0 = Instant::now();
+        let alloc_scope = AllocScope::start();
         let mut keys: Vec<K> = iter.into_iter().collect();
 
-        let mphf = Mphf::from_slice(&keys, gamma)?;
+        let (mphf, mut report) = Mphf::from_slice_with_progress(&keys, policy, progress)?;
 
         // Re-order `keys` and according to `mphf`
         for i in 0..keys.len() {
@@ -69,7 +160,40 @@ where
             }
         }
 
-        Ok(Set { mphf, keys: keys.into_boxed_slice() })
+        let set = Set { mphf, keys: keys.into_boxed_slice() };
+
+        alloc_scope.finish(&mut report);
+        report.duration = t0.elapsed();
+        report.set_size(set.size());
+
+        Ok((set, report))
+    }
+
+    /// Assembles a `Set` from a prebuilt `mphf` and `keys` stored at the indices `mphf` maps them
+    /// to, e.g. for custom containers sharing the MPHF with other structures. Fails if `keys`
+    /// don't match `mphf`, see [`Set::debug_check_invariants`].
+    ///
+    /// # Examples
+    /// ```
+    /// # use entropy_map::{Mphf, Set, DEFAULT_GAMMA};
+    /// let mut keys = vec![1u32, 2, 3];
+    /// let mphf: Mphf = Mphf::from_slice(&keys, DEFAULT_GAMMA).unwrap();
+    /// keys.sort_by_key(|key| mphf.get(key).unwrap());
+    /// let set = Set::<u32>::from_mphf_and_keys(mphf, keys.into_boxed_slice()).unwrap();
+    /// assert!(set.contains(&1));
+    /// ```
+    pub fn from_mphf_and_keys(mphf: Mphf<B, S, ST, H, RankedBits, E>, keys: Box<[K]>) -> Result<Self, InvariantError> {
+        invariants::check_keys(&mphf, &keys)?;
+        Ok(Set { mphf, keys })
+    }
+
+    /// Same as [`Set::from_mphf_and_keys`], but skips checking `keys` against `mphf`.
+    ///
+    /// # Safety
+    /// `keys` must hold exactly one key per `mphf` output, each stored at the index `mphf` maps it
+    /// to, as queries access keys without bounds checks.
+    pub unsafe fn from_mphf_and_keys_unchecked(mphf: Mphf<B, S, ST, H, RankedBits, E>, keys: Box<[K]>) -> Self {
+        Set { mphf, keys }
     }
 
     /// Returns `true` if the set contains the value.
@@ -87,11 +211,60 @@ where
     where
         K: Borrow<Q> + PartialEq<Q>,
         Q: Hash + Eq + ?Sized,
+    {
+        self.contains_impl(self.mphf.get(key), key)
+    }
+
+    /// Same as [`Set::contains`], but re-uses the hash precomputed by `HashedKey`.
+    ///
+    /// # Examples
+    /// ```
+    /// # use std::collections::HashSet;
+    /// # use entropy_map::{HashedKey, Set};
+    /// let set = Set::try_from(HashSet::from([1, 2, 3])).unwrap();
+    /// assert!(set.contains_hashed(&HashedKey::new(&1)));
+    /// assert!(!set.contains_hashed(&HashedKey::new(&4)));
+    /// ```
+    #[inline]
+    pub fn contains_hashed<Q>(&self, key: &HashedKey<Q, H>) -> bool
+    where
+        K: Borrow<Q> + PartialEq<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        self.contains_impl(self.mphf.get_hashed(key), key.key())
+    }
+
+    /// Same as [`Set::contains`], but skips hashing `key` and uses `hash` computed with the set
+    /// hasher `H` instead (e.g. via [`HashedKey::hash`]). The key is still compared with the stored
+    /// one, so a mismatching `hash` results in `false`.
+    ///
+    /// # Examples
+    /// ```
+    /// # use std::collections::HashSet;
+    /// # use entropy_map::{HashedKey, Set};
+    /// let set = Set::try_from(HashSet::from([1, 2, 3])).unwrap();
+    /// let hash = HashedKey::<_>::new(&1).hash();
+    /// assert!(set.contains_with_hash(hash, &1));
+    /// assert!(!set.contains_with_hash(hash, &4));
+    /// ```
+    #[inline]
+    pub fn contains_with_hash<Q>(&self, hash: u64, key: &Q) -> bool
+    where
+        K: Borrow<Q> + PartialEq<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        self.contains_impl(self.mphf.get_by_hash(hash), key)
+    }
+
+    /// Inner implementation of `contains` checking the key stored at MPHF index `idx`.
+    #[inline]
+    fn contains_impl<Q>(&self, idx: Option<usize>, key: &Q) -> bool
+    where
+        K: PartialEq<Q>,
+        Q: ?Sized,
     {
         // SAFETY: `idx` is always within array bounds (ensured during construction)
-        self.mphf
-            .get(key)
-            .map(|idx| unsafe { self.keys.get_unchecked(idx) == key })
+        idx.map(|idx| unsafe { access::get(&self.keys, idx) == key })
             .unwrap_or_default()
     }
 
@@ -125,6 +298,39 @@ where
         self.keys.is_empty()
     }
 
+    /// Returns the key stored at `idx`, or `None` if `idx` is out of bounds. Keys are stored in
+    /// MPHF order, so indices `0..len()` map to every key once in arbitrary order.
+    ///
+    /// # Examples
+    /// ```
+    /// # use std::collections::HashSet;
+    /// # use entropy_map::Set;
+    /// let set = Set::try_from(HashSet::from([1, 2, 3])).unwrap();
+    /// assert_eq!(set.contains(set.nth_key(0).unwrap()), true);
+    /// assert_eq!(set.nth_key(3), None);
+    /// ```
+    #[inline]
+    pub fn nth_key(&self, idx: usize) -> Option<&K> {
+        self.keys.get(idx)
+    }
+
+    /// Returns `k` distinct keys (or all keys if the set has fewer) chosen uniformly at random by
+    /// `rng`, e.g. to probe a sample of keys of a deployed set end-to-end.
+    ///
+    /// # Examples
+    /// ```
+    /// # use std::collections::HashSet;
+    /// # use entropy_map::Set;
+    /// let set = Set::try_from(HashSet::from([1, 2, 3])).unwrap();
+    /// let keys = set.sample_keys(5, &mut rand::thread_rng());
+    /// assert_eq!(keys.len(), 3);
+    /// ```
+    #[cfg(feature = "rand")]
+    #[inline]
+    pub fn sample_keys<R: rand::Rng + ?Sized>(&self, k: usize, rng: &mut R) -> Vec<&K> {
+        crate::sample::sample_keys(&self.keys, k, rng)
+    }
+
     /// Returns an iterator visiting set elements in arbitrary order.
     ///
     /// # Examples
@@ -137,7 +343,7 @@ where
     /// }
     /// ```
     #[inline]
-    pub fn iter(&self) -> impl Iterator<Item = &K> {
+    pub fn iter(&self) -> impl ExactSizeIterator<Item = &K> + DoubleEndedIterator {
         self.keys.iter()
     }
 
@@ -148,14 +354,119 @@ where
     /// # use std::collections::HashSet;
     /// # use entropy_map::Set;
     /// let set = Set::try_from(HashSet::from([1, 2, 3])).unwrap();
-    /// assert_eq!(set.size(), 218);
+    /// assert_eq!(set.size(), 202);
     /// ```
     #[inline]
     pub fn size(&self) -> usize {
         size_of_val(self) + self.mphf.size() + size_of_val(self.keys.as_ref())
     }
+
+    /// Returns the size of the set compared with an estimate of the equivalent `HashSet<K>`, see
+    /// [`SizeReport`].
+    ///
+    /// # Examples
+    /// ```
+    /// # use entropy_map::Set;
+    /// let set = Set::<u64>::from_iter_with_params(0..10000, 2.0).unwrap();
+    /// let report = set.size_report();
+    /// assert_eq!(report.size, set.size());
+    /// assert!(report.savings_ratio() > 1.5);
+    /// ```
+    pub fn size_report(&self) -> SizeReport {
+        SizeReport {
+            keys: self.len(),
+            size: self.size(),
+            std_size: size_report::std_table_size::<HashSet<K>, K>(self.len()),
+        }
+    }
+
+    /// Checks invariants of the MPHF and that every key is mapped by it to the index it's stored
+    /// at, e.g. for sets reassembled by custom deserialization code.
+    ///
+    /// # Examples
+    /// ```
+    /// # use std::collections::HashSet;
+    /// # use entropy_map::Set;
+    /// let set: Set<u32> = Set::try_from(HashSet::from([1, 2, 3])).unwrap();
+    /// assert_eq!(set.debug_check_invariants(), Ok(()));
+    /// ```
+    pub fn debug_check_invariants(&self) -> Result<(), InvariantError> {
+        invariants::check_keys(&self.mphf, &self.keys)
+    }
+
+    /// Returns the number of keys present in both `self` and `other`.
+    ///
+    /// Keys of the smaller set are probed in the larger one in batches, first resolving MPHF
+    /// indices of the whole batch and prefetching the candidate keys, then comparing them, so that
+    /// cache misses of the batch overlap instead of stalling every lookup one by one.
+    ///
+    /// # Examples
+    /// ```
+    /// # use std::collections::HashSet;
+    /// # use entropy_map::Set;
+    /// let set = Set::try_from(HashSet::from([1, 2, 3, 4])).unwrap();
+    /// let other = Set::try_from(HashSet::from([3, 4, 5])).unwrap();
+    /// assert_eq!(set.intersection_len(&other), 2);
+    /// ```
+    pub fn intersection_len(&self, other: &Self) -> usize {
+        let (small, large) = if self.len() <= other.len() {
+            (self, other)
+        } else {
+            (other, self)
+        };
+        let mut indices = [None; INTERSECTION_BATCH_LEN];
+        let mut count = 0;
+
+        for batch in small.keys.chunks(INTERSECTION_BATCH_LEN) {
+            for (key, idx) in batch.iter().zip(indices.iter_mut()) {
+                *idx = large.mphf.get(key);
+                if let Some(idx) = *idx {
+                    access::prefetch(&large.keys, idx);
+                }
+            }
+
+            count += batch
+                .iter()
+                .zip(indices)
+                .filter(|&(key, idx)| large.contains_impl(idx, key))
+                .count();
+        }
+
+        count
+    }
+
+    /// Rebuilds the set from its keys and `extra` keys with `DEFAULT_GAMMA`, the same hash seed and
+    /// key filter. Keys already present in the set or repeated in `extra` are added only once.
+    ///
+    /// # Examples
+    /// ```
+    /// # use std::collections::HashSet;
+    /// # use entropy_map::Set;
+    /// let set = Set::try_from(HashSet::from([1, 2, 3])).unwrap();
+    /// let set = set.with_added([3, 4, 4]).unwrap();
+    /// assert_eq!(set.len(), 4);
+    /// assert!(set.contains(&4));
+    /// ```
+    pub fn with_added<I>(&self, extra: I) -> Result<Self, MphfError>
+    where
+        K: Clone,
+        I: IntoIterator<Item = K>,
+    {
+        let extra: HashSet<K> = extra.into_iter().filter(|key| !self.contains(key)).collect();
+        let policy = self.mphf.rebuild_policy();
+        Self::from_iter_with_policy(self.keys.iter().cloned().chain(extra), &policy).map(|(set, _)| set)
+    }
+
+    /// Returns the seed hashed before every key, see [`Mphf::hash_seed`].
+    #[inline]
+    pub fn hash_seed(&self) -> u64 {
+        self.mphf.hash_seed()
+    }
 }
 
+/// Number of keys probed at once by [`Set::intersection_len`].
+const INTERSECTION_BATCH_LEN: usize = 16;
+
 /// Creates a `Set` from a `HashSet`.
 impl<K> TryFrom<HashSet<K>> for Set<K>
 where
@@ -171,12 +482,14 @@ where
 
 /// Implement `contains` for `Archived` version of `Set` if feature is enabled
 #[cfg(feature = "rkyv_derive")]
-impl<K, const B: usize, const S: usize, ST, H> ArchivedSet<K, B, S, ST, H>
+impl<K, const B: usize, const S: usize, ST, H, E> ArchivedSet<K, B, S, ST, H, E>
 where
     K: Eq + Hash + rkyv::Archive,
     K::Archived: PartialEq<K>,
     ST: PrimInt + Unsigned + rkyv::Archive<Archived = ST>,
     H: Hasher + Default,
+    E: ExtrasLayout + rkyv::Archive,
+    rkyv::Archived<E>: ExtrasAccess<KeyFilter = crate::key_filter::ArchivedKeyFilter>,
 {
     /// Returns `true` if the set contains the value.
     ///
@@ -192,18 +505,69 @@ where
     /// assert_eq!(archived_set.contains(&4), false);
     /// ```
     #[inline]
-    pub fn contains<Q: ?Sized>(&self, key: &Q) -> bool
+    pub fn contains<Q>(&self, key: &Q) -> bool
     where
         K: Borrow<Q>,
         <K as rkyv::Archive>::Archived: PartialEq<Q>,
-        Q: Hash + Eq,
+        Q: Hash + Eq + ?Sized,
     {
         // SAFETY: `idx` is always within bounds (ensured during construction)
         self.mphf
             .get(key)
-            .map(|idx| unsafe { self.keys.get_unchecked(idx) == key })
+            .map(|idx| unsafe { access::get(&self.keys, idx) == key })
             .unwrap_or_default()
     }
+
+    /// Same as [`ArchivedSet::contains`], but re-uses the hash precomputed by `HashedKey`.
+    #[inline]
+    pub fn contains_hashed<Q: ?Sized>(&self, key: &HashedKey<Q, H>) -> bool
+    where
+        K: Borrow<Q>,
+        <K as rkyv::Archive>::Archived: PartialEq<Q>,
+    {
+        // SAFETY: `idx` is always within bounds (ensured during construction)
+        self.mphf
+            .get_hashed(key)
+            .map(|idx| unsafe { access::get(&self.keys, idx) == key.key() })
+            .unwrap_or_default()
+    }
+
+    /// Same as [`ArchivedSet::contains`], but skips hashing `key` and uses `hash` computed with the
+    /// set hasher `H` instead.
+    #[inline]
+    pub fn contains_with_hash<Q: ?Sized>(&self, hash: u64, key: &Q) -> bool
+    where
+        K: Borrow<Q>,
+        <K as rkyv::Archive>::Archived: PartialEq<Q>,
+    {
+        // SAFETY: `idx` is always within bounds (ensured during construction)
+        self.mphf
+            .get_by_hash(hash)
+            .map(|idx| unsafe { access::get(&self.keys, idx) == key })
+            .unwrap_or_default()
+    }
+
+    /// Returns the archived MPHF of the set, e.g. to
+    /// [`prefault_levels`](crate::ArchivedMphf::prefault_levels) of a memory-mapped archive.
+    #[inline]
+    pub fn mphf(&self) -> &crate::mphf::ArchivedMphf<B, S, ST, H, RankedBits, E> {
+        &self.mphf
+    }
+
+    /// Returns the archived key stored at `idx`, or `None` if `idx` is out of bounds, see
+    /// [`Set::nth_key`].
+    #[inline]
+    pub fn nth_key(&self, idx: usize) -> Option<&K::Archived> {
+        self.keys.as_ref().get(idx)
+    }
+
+    /// Returns `k` distinct archived keys chosen uniformly at random by `rng`, see
+    /// [`Set::sample_keys`].
+    #[cfg(feature = "rand")]
+    #[inline]
+    pub fn sample_keys<R: rand::Rng + ?Sized>(&self, k: usize, rng: &mut R) -> Vec<&K::Archived> {
+        crate::sample::sample_keys(&self.keys, k, rng)
+    }
 }
 
 #[cfg(test)]
@@ -243,11 +607,125 @@ mod tests {
         for &k in set.iter() {
             assert!(original_set.contains(&k));
         }
+        assert_eq!(set.iter().len(), original_set.len());
+        assert!(set.iter().rev().eq(set.iter().collect::<Vec<_>>().into_iter().rev()));
 
         // Test size
         assert_eq!(set.size(), 8540);
     }
 
+    #[test]
+    fn test_nth_key_and_sample_keys() {
+        let original_set = gen_set(1000);
+        let set = Set::try_from(original_set.clone()).unwrap();
+
+        let keys: HashSet<u64> = (0..set.len()).map(|idx| *set.nth_key(idx).unwrap()).collect();
+        assert_eq!(keys, original_set);
+        assert_eq!(set.nth_key(set.len()), None);
+
+        #[cfg(feature = "rand")]
+        {
+            let mut rng = ChaCha8Rng::seed_from_u64(123);
+            let sample = set.sample_keys(100, &mut rng);
+            assert_eq!(sample.iter().collect::<HashSet<_>>().len(), 100);
+            assert!(sample.iter().all(|key| original_set.contains(key)));
+            assert_eq!(set.sample_keys(2000, &mut rng).len(), 1000);
+        }
+
+        #[cfg(feature = "rkyv_derive")]
+        {
+            let bytes = rkyv::to_bytes::<_, 1024>(&set).unwrap();
+            let rkyv_set = rkyv::check_archived_root::<Set<u64>>(&bytes).unwrap();
+            assert_eq!(rkyv_set.nth_key(0), set.nth_key(0));
+            #[cfg(feature = "rand")]
+            assert!(rkyv_set
+                .sample_keys(10, &mut ChaCha8Rng::seed_from_u64(123))
+                .iter()
+                .all(|key| original_set.contains(key)));
+        }
+    }
+
+    #[test]
+    fn test_from_mphf_and_keys() {
+        let set = Set::try_from(gen_set(1000)).unwrap();
+        let mphf = Mphf::from_slice(&set.keys, DEFAULT_GAMMA).unwrap();
+        let rebuilt = Set::<u64>::from_mphf_and_keys(mphf, set.keys.clone()).unwrap();
+        assert!(set.iter().all(|key| rebuilt.contains(key)));
+
+        let mut keys = set.keys.clone();
+        keys.swap(3, 7);
+        let mphf = Mphf::from_slice(&set.keys, DEFAULT_GAMMA).unwrap();
+        assert_eq!(
+            Set::<u64>::from_mphf_and_keys(mphf, keys).err(),
+            Some(InvariantError::KeyIndexMismatch { idx: 3 })
+        );
+    }
+
+    #[test]
+    fn test_type_aliases() {
+        let original_set = gen_set(1000);
+        let compact_set = SetCompact::from_hashset_with_gamma(original_set.clone(), DEFAULT_GAMMA).unwrap();
+        let fast_set = SetFast::from_hashset_with_gamma(original_set.clone(), DEFAULT_GAMMA).unwrap();
+
+        for key in &original_set {
+            assert!(compact_set.contains(key));
+            assert!(fast_set.contains(key));
+        }
+        assert!(compact_set.mphf.size() < fast_set.mphf.size());
+    }
+
+    #[test]
+    fn test_with_added() {
+        let original_set = gen_set(1000);
+        let set = Set::try_from(original_set.clone()).unwrap();
+
+        let extra: Vec<u64> = original_set
+            .iter()
+            .take(10)
+            .copied()
+            .chain(1_000_000..1_000_100)
+            .collect();
+        let updated = set.with_added(extra.iter().chain(&extra).copied()).unwrap();
+
+        assert_eq!(updated.len(), original_set.len() + 100);
+        for key in original_set.iter().chain(&extra) {
+            assert!(updated.contains(key));
+        }
+        assert!(!updated.contains(&2_000_000));
+
+        // rebuilds keep the key filter
+        let policy = BuildPolicy::fixed(DEFAULT_GAMMA).with_key_filter(8);
+        let (set, _) = Set::<u64, 32, 8, u8, WyHash, Extras>::from_iter_with_policy(original_set, &policy).unwrap();
+        let updated = set.with_added([2_000_000]).unwrap();
+        assert_eq!(updated.mphf.key_filter().map(|filter| filter.bits_per_key()), Some(8));
+        assert!(updated.contains(&2_000_000));
+    }
+
+    #[test]
+    fn test_intersection_len() {
+        let original_set = gen_set(1000);
+        let set = Set::try_from(original_set.clone()).unwrap();
+
+        let other_set: HashSet<u64> = original_set.iter().step_by(3).copied().chain(0..100).collect();
+        let other = Set::try_from(other_set.clone()).unwrap();
+
+        let expected = original_set.intersection(&other_set).count();
+        assert_eq!(set.intersection_len(&other), expected);
+        assert_eq!(other.intersection_len(&set), expected);
+        assert_eq!(set.intersection_len(&set), set.len());
+        assert_eq!(set.intersection_len(&Set::try_from(HashSet::new()).unwrap()), 0);
+    }
+
+    #[test]
+    fn test_build_report() {
+        let original_set = gen_set(1000);
+        let (set, report) = Set::<u64>::from_iter_with_report(original_set.clone(), DEFAULT_GAMMA).unwrap();
+
+        assert_eq!(report.keys(), original_set.len());
+        assert_eq!(report.dedup_ratio, None);
+        assert_eq!(report.bits_per_key, (set.size() * 8) as f32 / original_set.len() as f32);
+    }
+
     /// Assert that we can call `.contains()` with `K::borrow()`.
     #[test]
     fn test_contains_borrow() {
@@ -300,6 +778,9 @@ mod tests {
                                 $gamma as f32 / 100.0
                             ).unwrap();
 
+                            // Assert that internal invariants hold.
+                            assert_eq!(entropy_set.debug_check_invariants(), Ok(()));
+
                             // Assert that length matches model.
                             assert_eq!(entropy_set.len(), model.len());
                             assert_eq!(entropy_set.is_empty(), model.is_empty());
@@ -357,7 +838,7 @@ mod tests {
             let entropy_set = Set::try_from(model.clone()).unwrap();
 
             for elm in &model {
-                assert!(entropy_set.contains(&elm));
+                assert!(entropy_set.contains(elm));
             }
 
             for elm in arbitrary {
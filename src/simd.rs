@@ -0,0 +1,136 @@
+//! Vectorized group seed search of MPHF construction.
+//!
+//! The seed search computes the bits of every group once per candidate seed, i.e. hashes every
+//! level hash of the group up to `2^S` times, which dominates construction time. On `x86_64` CPUs
+//! supporting AVX2 (detected at runtime), groups of at most 64 bits try [`SEED_BATCH`] consecutive
+//! seeds at once instead, one per vector lane: the MurmurHash3 finalizer and the `fastmod32`
+//! reduction of [`bit_index_for_group_size`] are computed for all of them, and group bits and
+//! collision bits are accumulated in vector registers. Seeds of a batch are then replayed in order,
+//! so that structures built with and without the `simd` feature are byte-identical.
+
+use crate::mphf::bit_index_for_group_size;
+
+/// Number of consecutive seeds tried at once by [`seed_batch_ones`].
+pub(crate) const SEED_BATCH: usize = 8;
+
+/// Returns `true` if [`seed_batch_ones`] is vectorized for groups of `b` bits on the current CPU.
+#[inline]
+pub(crate) fn has_seed_batches(b: usize) -> bool {
+    #[cfg(target_arch = "x86_64")]
+    return b <= 64 && is_x86_feature_detected!("avx2");
+    #[cfg(not(target_arch = "x86_64"))]
+    false
+}
+
+/// Computes the number of collision-free bits of a group of `b` bits, at most 64, set by
+/// `level_hashes` for every seed of the batch starting at `first_seed` into `ones`.
+#[inline]
+pub(crate) fn seed_batch_ones(level_hashes: &[u32], first_seed: u32, b: u32, ones: &mut [u32; SEED_BATCH]) {
+    debug_assert!(b <= 64);
+    #[cfg(target_arch = "x86_64")]
+    if is_x86_feature_detected!("avx2") {
+        // SAFETY: AVX2 support was just detected
+        unsafe { avx2::seed_batch_ones(level_hashes, first_seed, b, ones) };
+        return;
+    }
+    scalar_seed_batch_ones(level_hashes, first_seed, b, ones);
+}
+
+/// Scalar version of [`seed_batch_ones`].
+fn scalar_seed_batch_ones(level_hashes: &[u32], first_seed: u32, b: u32, ones: &mut [u32; SEED_BATCH]) {
+    for (seed_offset, ones) in (0..).zip(ones.iter_mut()) {
+        let seed = first_seed.wrapping_add(seed_offset);
+        let (mut bits, mut collisions) = (0u64, 0u64);
+        for &level_hash in level_hashes {
+            let mask = 1 << bit_index_for_group_size(level_hash as u64, seed, 0, b as usize);
+            collisions |= bits & mask;
+            bits |= mask;
+        }
+        *ones = (bits & !collisions).count_ones();
+    }
+}
+
+#[cfg(target_arch = "x86_64")]
+mod avx2 {
+    use super::SEED_BATCH;
+    use std::arch::x86_64::*;
+
+    /// AVX2 version of [`super::seed_batch_ones`].
+    ///
+    /// # Safety
+    /// The CPU must support AVX2.
+    #[target_feature(enable = "avx2")]
+    pub(super) unsafe fn seed_batch_ones(level_hashes: &[u32], first_seed: u32, b: u32, ones: &mut [u32; SEED_BATCH]) {
+        let seeds = _mm256_add_epi32(
+            _mm256_set1_epi32(first_seed as i32),
+            _mm256_setr_epi32(0, 1, 2, 3, 4, 5, 6, 7),
+        );
+        let bs = _mm256_set1_epi32(b as i32);
+        let (c1, c2) = (
+            _mm256_set1_epi32(0x85ebca6bu32 as i32),
+            _mm256_set1_epi32(0xc2b2ae35u32 as i32),
+        );
+        let one = _mm256_set1_epi64x(1);
+
+        // group bits and collision bits of the lower and upper 4 seeds in 64-bit lanes
+        let (mut bits_lo, mut collisions_lo) = (_mm256_setzero_si256(), _mm256_setzero_si256());
+        let (mut bits_hi, mut collisions_hi) = (_mm256_setzero_si256(), _mm256_setzero_si256());
+        for &level_hash in level_hashes {
+            // MurmurHash3's finalizer step, same as in `bit_index_for_group_size`
+            let mut x = _mm256_xor_si256(_mm256_set1_epi32(level_hash as i32), seeds);
+            x = _mm256_mullo_epi32(_mm256_xor_si256(x, _mm256_srli_epi32::<16>(x)), c1);
+            x = _mm256_mullo_epi32(_mm256_xor_si256(x, _mm256_srli_epi32::<13>(x)), c2);
+            x = _mm256_xor_si256(x, _mm256_srli_epi32::<16>(x));
+
+            // `fastmod32` takes upper halves of 64-bit products, which are computed for even and
+            // odd lanes separately and blended back into the lanes they come from
+            let even = _mm256_srli_epi64::<32>(_mm256_mul_epu32(x, bs));
+            let odd = _mm256_mul_epu32(_mm256_srli_epi64::<32>(x), bs);
+            let bit_idx = _mm256_blend_epi32::<0b1010_1010>(even, odd);
+
+            let mask_lo = _mm256_sllv_epi64(one, _mm256_cvtepu32_epi64(_mm256_castsi256_si128(bit_idx)));
+            let mask_hi = _mm256_sllv_epi64(one, _mm256_cvtepu32_epi64(_mm256_extracti128_si256::<1>(bit_idx)));
+            collisions_lo = _mm256_or_si256(collisions_lo, _mm256_and_si256(bits_lo, mask_lo));
+            collisions_hi = _mm256_or_si256(collisions_hi, _mm256_and_si256(bits_hi, mask_hi));
+            bits_lo = _mm256_or_si256(bits_lo, mask_lo);
+            bits_hi = _mm256_or_si256(bits_hi, mask_hi);
+        }
+
+        let mut free_bits = [0u64; SEED_BATCH];
+        _mm256_storeu_si256(
+            free_bits.as_mut_ptr() as *mut __m256i,
+            _mm256_andnot_si256(collisions_lo, bits_lo),
+        );
+        _mm256_storeu_si256(
+            free_bits.as_mut_ptr().add(4) as *mut __m256i,
+            _mm256_andnot_si256(collisions_hi, bits_hi),
+        );
+        for (ones, free_bits) in ones.iter_mut().zip(free_bits) {
+            *ones = free_bits.count_ones();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::{Rng, SeedableRng};
+    use rand_chacha::ChaCha8Rng;
+
+    #[test]
+    fn test_seed_batch_ones() {
+        let mut rng = ChaCha8Rng::seed_from_u64(42);
+        let level_hashes: Vec<u32> = (0..300).map(|_| rng.gen()).collect();
+        for b in [1, 7, 8, 32, 63, 64] {
+            for first_seed in [0, 8, 248, u32::MAX - 7] {
+                for len in [0, 1, 13, 32, 100, 300] {
+                    let (mut ones, mut expected) = ([0; SEED_BATCH], [0; SEED_BATCH]);
+                    seed_batch_ones(&level_hashes[..len], first_seed, b, &mut ones);
+                    scalar_seed_batch_ones(&level_hashes[..len], first_seed, b, &mut expected);
+                    assert_eq!(ones, expected, "b = {b} first_seed = {first_seed} len = {len}");
+                    assert!(ones.iter().all(|&ones| ones <= (len as u32).min(b)));
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,186 @@
+//! A module providing `SipHasher`, a portable implementation of the SipHash family of keyed hash
+//! functions.
+//!
+//! `std::collections::hash_map::DefaultHasher` is SipHash as well, but its algorithm is explicitly
+//! unspecified and may change between Rust releases, which is not acceptable for structures
+//! persisted with `rkyv`. `SipHasher13` (SipHash-1-3) is meant to be used together with a random
+//! `BuildPolicy::hash_seed` when keys come from untrusted input: unlike `WyHash` the resulting hashes
+//! can't be predicted without knowing the seed, so an attacker can't craft keys colliding into deep
+//! MPHF levels or failing the construction, see `BuildPolicy::keyed`.
+
+use std::hash::Hasher;
+
+/// SipHash with `C` compression and `D` finalization rounds, see
+/// [SipHash: a fast short-input PRF](https://www.aumasson.jp/siphash/siphash.pdf).
+///
+/// # Examples
+/// ```
+/// # use std::hash::Hasher;
+/// # use entropy_map::SipHasher13;
+/// let mut hasher = SipHasher13::new_with_keys(1, 2);
+/// hasher.write_u64(3);
+/// assert_ne!(hasher.finish(), SipHasher13::default().finish());
+/// ```
+#[derive(Debug, Clone, Copy)]
+pub struct SipHasher<const C: usize, const D: usize> {
+    /// Internal state
+    v: [u64; 4],
+    /// Bytes not yet compressed, in little endian order
+    tail: u64,
+    /// Number of bytes in `tail`
+    ntail: usize,
+    /// Total number of bytes written
+    length: usize,
+}
+
+/// SipHash-1-3, the variant used by Rust's standard library.
+pub type SipHasher13 = SipHasher<1, 3>;
+
+/// SipHash-2-4, the variant recommended by the SipHash authors.
+pub type SipHasher24 = SipHasher<2, 4>;
+
+impl<const C: usize, const D: usize> SipHasher<C, D> {
+    /// Creates a hasher keyed with 128-bit key `(k0, k1)`.
+    #[inline]
+    pub fn new_with_keys(k0: u64, k1: u64) -> Self {
+        SipHasher {
+            v: [
+                k0 ^ 0x736f6d6570736575,
+                k1 ^ 0x646f72616e646f6d,
+                k0 ^ 0x6c7967656e657261,
+                k1 ^ 0x7465646279746573,
+            ],
+            tail: 0,
+            ntail: 0,
+            length: 0,
+        }
+    }
+
+    /// Applies a single SipRound to the state.
+    #[inline(always)]
+    fn round(v: &mut [u64; 4]) {
+        v[0] = v[0].wrapping_add(v[1]);
+        v[1] = v[1].rotate_left(13) ^ v[0];
+        v[0] = v[0].rotate_left(32);
+        v[2] = v[2].wrapping_add(v[3]);
+        v[3] = v[3].rotate_left(16) ^ v[2];
+        v[0] = v[0].wrapping_add(v[3]);
+        v[3] = v[3].rotate_left(21) ^ v[0];
+        v[2] = v[2].wrapping_add(v[1]);
+        v[1] = v[1].rotate_left(17) ^ v[2];
+        v[2] = v[2].rotate_left(32);
+    }
+
+    /// Compresses a single 8-byte message word `m` into the state.
+    #[inline(always)]
+    fn compress(&mut self, m: u64) {
+        self.v[3] ^= m;
+        for _ in 0..C {
+            Self::round(&mut self.v);
+        }
+        self.v[0] ^= m;
+    }
+}
+
+impl<const C: usize, const D: usize> Default for SipHasher<C, D> {
+    /// Creates a hasher keyed with all-zero key.
+    #[inline]
+    fn default() -> Self {
+        Self::new_with_keys(0, 0)
+    }
+}
+
+impl<const C: usize, const D: usize> Hasher for SipHasher<C, D> {
+    #[inline]
+    fn write(&mut self, mut bytes: &[u8]) {
+        self.length += bytes.len();
+
+        // complete the pending tail first
+        if self.ntail != 0 {
+            let needed = (8 - self.ntail).min(bytes.len());
+            for (i, &byte) in bytes[..needed].iter().enumerate() {
+                self.tail |= (byte as u64) << (8 * (self.ntail + i));
+            }
+            self.ntail += needed;
+            bytes = &bytes[needed..];
+            if self.ntail < 8 {
+                return;
+            }
+            self.compress(self.tail);
+            self.tail = 0;
+            self.ntail = 0;
+        }
+
+        let mut chunks = bytes.chunks_exact(8);
+        for chunk in &mut chunks {
+            self.compress(u64::from_le_bytes(chunk.try_into().unwrap()));
+        }
+        for (i, &byte) in chunks.remainder().iter().enumerate() {
+            self.tail |= (byte as u64) << (8 * i);
+        }
+        self.ntail = chunks.remainder().len();
+    }
+
+    #[inline]
+    fn finish(&self) -> u64 {
+        let mut hasher = *self;
+        hasher.compress(((self.length as u64 & 0xff) << 56) | self.tail);
+
+        hasher.v[2] ^= 0xff;
+        for _ in 0..D {
+            Self::round(&mut hasher.v);
+        }
+        hasher.v[0] ^ hasher.v[1] ^ hasher.v[2] ^ hasher.v[3]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::{Rng, SeedableRng};
+    use rand_chacha::ChaCha8Rng;
+
+    /// Reference SipHash-2-4 outputs for key `00 01 .. 0f` and messages `00 01 .. (len - 1)`
+    /// from the SipHash paper and reference implementation.
+    const SIPHASH24_VECTORS: [(usize, u64); 5] = [
+        (0, 0x726fdb47dd0e0e31),
+        (1, 0x74f839c593dc67fd),
+        (7, 0xab0200f58b01d137),
+        (8, 0x93f5f5799a932462),
+        (15, 0xa129ca6149be45e5),
+    ];
+
+    #[test]
+    fn test_siphash24_vectors() {
+        let k0 = u64::from_le_bytes([0, 1, 2, 3, 4, 5, 6, 7]);
+        let k1 = u64::from_le_bytes([8, 9, 10, 11, 12, 13, 14, 15]);
+        for (len, expected) in SIPHASH24_VECTORS {
+            let msg: Vec<u8> = (0..len as u8).collect();
+            let mut hasher = SipHasher24::new_with_keys(k0, k1);
+            hasher.write(&msg);
+            assert_eq!(hasher.finish(), expected, "len = {}", len);
+        }
+    }
+
+    #[test]
+    fn test_split_writes() {
+        let mut rng = ChaCha8Rng::seed_from_u64(123);
+        let msg: Vec<u8> = (0..100).map(|_| rng.gen()).collect();
+
+        let mut hasher = SipHasher13::new_with_keys(1, 2);
+        hasher.write(&msg);
+        let expected = hasher.finish();
+
+        // hash must not depend on how the message is split into writes
+        for _ in 0..100 {
+            let mut hasher = SipHasher13::new_with_keys(1, 2);
+            let mut rest = &msg[..];
+            while !rest.is_empty() {
+                let (chunk, tail) = rest.split_at(rng.gen_range(0..=rest.len().min(11)));
+                hasher.write(chunk);
+                rest = tail;
+            }
+            assert_eq!(hasher.finish(), expected);
+        }
+    }
+}
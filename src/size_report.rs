@@ -0,0 +1,121 @@
+//! A module providing `SizeReport`, comparing the size of a structure with an estimate of the
+//! equivalent `std` collection.
+//!
+//! `size_report` of maps and sets returns the bytes occupied by the structure (see `size`) along
+//! with the bytes an equivalent `HashMap` or `HashSet` holding the same entries would occupy, to
+//! quantify the savings of adopting the crate. The estimate follows the `hashbrown` table layout of
+//! `std` collections built from exactly that many entries: a power of two number of buckets at
+//! most 87.5% full, each holding one entry inline and a control byte. Like `size`, heap memory
+//! owned by keys and values (e.g. `String` contents) is not counted on either side.
+
+use std::fmt::{self, Display};
+use std::mem::size_of;
+
+/// Width of `hashbrown` control byte groups, also the number of extra trailing control bytes.
+const GROUP_WIDTH: usize = 16;
+
+/// Size of a structure compared with the equivalent `std` collection, see the module documentation.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SizeReport {
+    /// Number of keys of the structure
+    pub keys: usize,
+    /// Total number of bytes occupied by the structure
+    pub size: usize,
+    /// Estimated number of bytes occupied by the equivalent `HashMap` or `HashSet`
+    pub std_size: usize,
+}
+
+impl SizeReport {
+    /// Returns how many times the equivalent `std` collection is larger than the structure.
+    #[inline]
+    pub fn savings_ratio(&self) -> f64 {
+        self.std_size as f64 / self.size as f64
+    }
+
+    /// Returns the size of the structure in bits per key, `0.0` for empty structures.
+    #[inline]
+    pub fn bits_per_key(&self) -> f64 {
+        bits_per_key(self.size, self.keys)
+    }
+
+    /// Returns the estimated size of the equivalent `std` collection in bits per key, `0.0` for
+    /// empty structures.
+    #[inline]
+    pub fn std_bits_per_key(&self) -> f64 {
+        bits_per_key(self.std_size, self.keys)
+    }
+}
+
+impl Display for SizeReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} keys: {} bytes ({:.1} bits/key) vs ~{} bytes ({:.1} bits/key) for std collection, {:.2}x smaller",
+            self.keys,
+            self.size,
+            self.bits_per_key(),
+            self.std_size,
+            self.std_bits_per_key(),
+            self.savings_ratio()
+        )
+    }
+}
+
+/// Returns `size` bytes in bits per each of `keys` keys, `0.0` if there are no keys.
+#[inline]
+fn bits_per_key(size: usize, keys: usize) -> f64 {
+    if keys == 0 {
+        0.0
+    } else {
+        (size * 8) as f64 / keys as f64
+    }
+}
+
+/// Returns the estimated number of bytes occupied by a `std` hash collection of type `C` holding
+/// `len` entries of type `T` inline.
+pub(crate) fn std_table_size<C, T>(len: usize) -> usize {
+    if len == 0 {
+        // empty collections don't allocate
+        return size_of::<C>();
+    }
+    let buckets = if len < 4 {
+        4
+    } else if len < 8 {
+        8
+    } else {
+        (len * 8 / 7).next_power_of_two()
+    };
+    let data = (buckets * size_of::<T>()).next_multiple_of(GROUP_WIDTH);
+    size_of::<C>() + data + buckets + GROUP_WIDTH
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    #[test]
+    fn test_std_table_size() {
+        type M = HashMap<u64, u64>;
+        assert_eq!(std_table_size::<M, (u64, u64)>(0), size_of::<M>());
+        // 3 entries fit into 4 buckets, 7 into 8, 8 need 16
+        assert_eq!(std_table_size::<M, (u64, u64)>(3), size_of::<M>() + 64 + 4 + 16);
+        assert_eq!(std_table_size::<M, (u64, u64)>(7), size_of::<M>() + 128 + 8 + 16);
+        assert_eq!(std_table_size::<M, (u64, u64)>(8), size_of::<M>() + 256 + 16 + 16);
+        // capacity of a table built from exactly that many entries matches the estimate
+        for len in [1, 3, 7, 8, 100, 1000] {
+            let map: M = (0..len as u64).map(|i| (i, i)).collect();
+            let buckets = (std_table_size::<M, (u64, u64)>(len) - size_of::<M>() - GROUP_WIDTH) / 17;
+            let capacity = if buckets <= 8 { buckets - 1 } else { buckets / 8 * 7 };
+            assert_eq!(map.capacity(), capacity, "len = {len}");
+        }
+
+        let report = SizeReport { keys: 1000, size: 16000, std_size: 64000 };
+        assert_eq!(report.savings_ratio(), 4.0);
+        assert_eq!(report.bits_per_key(), 128.0);
+        assert_eq!(
+            report.to_string(),
+            "1000 keys: 16000 bytes (128.0 bits/key) vs ~64000 bytes (512.0 bits/key) for std collection, 4.00x smaller"
+        );
+    }
+}
@@ -0,0 +1,207 @@
+//! A module providing `StaticArchive`, a typed view of an archive embedded into the binary.
+//!
+//! Archives of small fixed key sets can be built at compile time with the `static_set!` and
+//! `static_map!` macros of the `entropy-map-macros` crate, which embed the archive bytes into
+//! an `AlignedBytes` static and expose them as a `StaticArchive`. Dereferencing a `StaticArchive`
+//! doesn't validate the archive, so accessing embedded structures has no startup cost.
+//!
+//! Archives built by a separate pipeline can be embedded from files with [`include_archive!`],
+//! which aligns the bytes included with `include_bytes!` and exposes them as a `LazyArchive`,
+//! validating the archive on first access.
+
+use std::marker::PhantomData;
+use std::ops::Deref;
+use std::sync::OnceLock;
+
+use rkyv::validation::validators::DefaultValidator;
+use rkyv::{Archive, Archived, CheckBytes};
+
+/// Byte array aligned for accessing the archive it holds.
+#[repr(C, align(16))]
+pub struct AlignedBytes<const N: usize>(pub [u8; N]);
+
+/// Archive of `T` stored in static memory, dereferencing to the archived `T`.
+pub struct StaticArchive<T: Archive> {
+    /// Archive bytes
+    bytes: &'static [u8],
+    /// Phantom field for the archived type
+    _phantom: PhantomData<fn() -> T>,
+}
+
+impl<T: Archive> StaticArchive<T> {
+    /// Wraps static archive `bytes` of `T` without validating them.
+    ///
+    /// # Safety
+    /// `bytes` must contain a properly aligned valid archive of `T`, e.g. produced by
+    /// `rkyv::to_bytes` of the same `T` with the same crate versions for the same endianness.
+    ///
+    /// # Examples
+    /// ```
+    /// # use entropy_map::{AlignedBytes, Set, StaticArchive};
+    /// static BYTES: AlignedBytes<4> = AlignedBytes([0; 4]);
+    /// // SAFETY: not dereferenced, as `BYTES` is not a valid archive
+    /// let archive = unsafe { StaticArchive::<Set<u32>>::from_bytes_unchecked(&BYTES.0) };
+    /// assert_eq!(archive.bytes().len(), 4);
+    /// ```
+    #[inline]
+    pub const unsafe fn from_bytes_unchecked(bytes: &'static [u8]) -> Self {
+        StaticArchive { bytes, _phantom: PhantomData }
+    }
+
+    /// Returns the archive bytes.
+    #[inline]
+    pub fn bytes(&self) -> &'static [u8] {
+        self.bytes
+    }
+}
+
+impl<T: Archive> Deref for StaticArchive<T> {
+    type Target = Archived<T>;
+
+    #[inline]
+    fn deref(&self) -> &Archived<T> {
+        // SAFETY: `bytes` is a valid archive of `T` (ensured by `from_bytes_unchecked` caller)
+        unsafe { rkyv::archived_root::<T>(self.bytes) }
+    }
+}
+
+/// Archive of `T` stored in static memory, validated on first access and dereferencing to the
+/// archived `T`.
+pub struct LazyArchive<T: Archive> {
+    /// Archive bytes
+    bytes: &'static [u8],
+    /// Validation result, set on first access
+    validation: OnceLock<Result<(), String>>,
+    /// Phantom field for the archived type
+    _phantom: PhantomData<fn() -> T>,
+}
+
+impl<T: Archive> LazyArchive<T> {
+    /// Wraps static archive `bytes` of `T`, which are validated on first access.
+    #[inline]
+    pub const fn new(bytes: &'static [u8]) -> Self {
+        LazyArchive { bytes, validation: OnceLock::new(), _phantom: PhantomData }
+    }
+
+    /// Returns the archive bytes.
+    #[inline]
+    pub fn bytes(&self) -> &'static [u8] {
+        self.bytes
+    }
+}
+
+impl<T: Archive> LazyArchive<T>
+where
+    Archived<T>: for<'a> CheckBytes<DefaultValidator<'a>>,
+{
+    /// Returns the archived `T`, validating the archive on first call, or the validation error if
+    /// the archive is invalid.
+    ///
+    /// # Examples
+    /// ```
+    /// # use entropy_map::{LazyArchive, Set};
+    /// static ARCHIVE: LazyArchive<Set<u32>> = LazyArchive::new(&[0; 4]);
+    /// assert!(ARCHIVE.archived().is_err());
+    /// ```
+    pub fn archived(&self) -> Result<&Archived<T>, &str> {
+        self.validation
+            .get_or_init(|| match rkyv::check_archived_root::<T>(self.bytes) {
+                Ok(_) => Ok(()),
+                Err(err) => Err(err.to_string()),
+            })
+            .as_ref()
+            .map_err(String::as_str)?;
+
+        // SAFETY: `bytes` were validated as an archive of `T` above
+        Ok(unsafe { rkyv::archived_root::<T>(self.bytes) })
+    }
+}
+
+impl<T: Archive> Deref for LazyArchive<T>
+where
+    Archived<T>: for<'a> CheckBytes<DefaultValidator<'a>>,
+{
+    type Target = Archived<T>;
+
+    /// Dereferences to the archived `T`, see [`LazyArchive::archived`].
+    ///
+    /// # Panics
+    /// Panics if the archive is invalid.
+    #[inline]
+    fn deref(&self) -> &Archived<T> {
+        self.archived().unwrap_or_else(|err| panic!("invalid archive: {err}"))
+    }
+}
+
+/// Embeds archive files into `LazyArchive` statics with `include_bytes!`, aligning the bytes for
+/// accessing the archive. Paths are relative to the file invoking the macro, as with
+/// `include_bytes!`, and archives are validated on first access.
+///
+/// # Examples
+/// ```
+/// use entropy_map::{include_archive, Set};
+///
+/// include_archive! {
+///     static KEYS: Set<u64> = concat!(env!("CARGO_MANIFEST_DIR"), "/testdata/golden/v4/set_u64.bin");
+/// }
+///
+/// assert!(KEYS.contains(&0));
+/// ```
+#[macro_export]
+macro_rules! include_archive {
+    ($($(#[$attr:meta])* $vis:vis static $name:ident: $ty:ty = $path:expr;)*) => {
+        $(
+            $(#[$attr])*
+            $vis static $name: $crate::LazyArchive<$ty> = {
+                static BYTES: $crate::AlignedBytes<{ include_bytes!($path).len() }> =
+                    $crate::AlignedBytes(*include_bytes!($path));
+                $crate::LazyArchive::new(&BYTES.0)
+            };
+        )*
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::MapWithDict;
+    use rand::{Rng, SeedableRng};
+    use rand_chacha::ChaCha8Rng;
+    use std::collections::HashMap;
+
+    #[test]
+    fn test_static_archive() {
+        let mut rng = ChaCha8Rng::seed_from_u64(123);
+        let original_map: HashMap<u64, u32> = (0..1000).map(|_| (rng.gen(), rng.gen_range(1..=10))).collect();
+        let map = MapWithDict::try_from(original_map.clone()).unwrap();
+        let bytes: &'static [u8] = Box::leak(Box::new(rkyv::to_bytes::<_, 1024>(&map).unwrap()));
+
+        // SAFETY: `bytes` were produced by `rkyv::to_bytes` above
+        let archive = unsafe { StaticArchive::<MapWithDict<u64, u32>>::from_bytes_unchecked(bytes) };
+        for (k, v) in original_map.iter() {
+            assert_eq!(archive.get(k), Some(v));
+        }
+    }
+
+    #[cfg(target_endian = "little")]
+    include_archive! {
+        static GOLDEN_SET: crate::Set<u64> = "../testdata/golden/v4/set_u64.bin";
+    }
+
+    #[test]
+    #[cfg(target_endian = "little")]
+    fn test_include_archive() {
+        assert_eq!(GOLDEN_SET.bytes().as_ptr() as usize % 16, 0);
+        assert!(GOLDEN_SET.archived().is_ok());
+        for i in 0..1000u64 {
+            assert!(GOLDEN_SET.contains(&i.wrapping_mul(0x9E37_79B9_7F4A_7C15)));
+        }
+    }
+
+    #[test]
+    #[should_panic(expected = "invalid archive")]
+    fn test_lazy_archive_invalid() {
+        static ARCHIVE: LazyArchive<MapWithDict<u64, u32>> = LazyArchive::new(&[0; 4]);
+        ARCHIVE.get(&1);
+    }
+}
@@ -0,0 +1,687 @@
+//! A module providing `StaticTable`, an immutable table of typed columns keyed by a shared MPHF.
+//!
+//! Several attributes of the same keys would otherwise be stored as parallel maps, each with its
+//! own MPHF and copy of the keys, and queried by hashing the key once per map. `StaticTable`
+//! stores the MPHF and keys once along with a column per attribute, so that a single query returns
+//! the whole row. Columns store one value per key in the order of MPHF indices:
+//! - `U32`: integers bit-packed to the bit width of the largest value,
+//! - `F32`: floats quantized uniformly between the smallest and the largest value to `bits` bits,
+//!   i.e. with a maximum error of half the quantization step,
+//! - `Bytes`: byte strings stored back to back, with bit-packed end offsets,
+//! - `Flags`: `bits` flag bits.
+
+use std::borrow::Borrow;
+use std::hash::{Hash, Hasher};
+use std::mem::size_of_val;
+
+use num::{PrimInt, Unsigned};
+use wyhash::WyHash;
+
+use crate::access;
+use crate::hashed_key::HashedKey;
+use crate::invariants::{self, InvariantError};
+use crate::mphf::{BuildPolicy, MphfError};
+use crate::report::BuildReport;
+use crate::set::Set;
+
+/// Type of a `StaticTable` column, see the module documentation.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ColumnType {
+    /// `u32` values bit-packed to the bit width of the largest value
+    U32,
+    /// `f32` values quantized to `bits` bits, within `1..=32`
+    F32 {
+        /// Number of bits per quantized value
+        bits: u8,
+    },
+    /// Byte strings
+    Bytes,
+    /// `bits` flag bits, within `1..=8`
+    Flags {
+        /// Number of flag bits
+        bits: u8,
+    },
+}
+
+/// Cell of a row passed to `StaticTable` construction, of the type of its column.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Cell {
+    /// Value of a `ColumnType::U32` column
+    U32(u32),
+    /// Value of a `ColumnType::F32` column, which must be finite
+    F32(f32),
+    /// Value of a `ColumnType::Bytes` column
+    Bytes(Vec<u8>),
+    /// Value of a `ColumnType::Flags` column, of which only the lowest `bits` bits are stored
+    Flags(u8),
+}
+
+/// Errors that can occur when constructing `StaticTable`.
+#[derive(Debug)]
+pub enum StaticTableError {
+    /// Error occurred during mphf construction
+    MphfError(MphfError),
+    /// Number of bits of a column type is out of its range
+    InvalidColumnType,
+    /// Number of cells of a row differs from the number of columns
+    RowLengthMismatch,
+    /// Type of a cell differs from the type of its column
+    CellTypeMismatch,
+    /// `F32` cell is not finite
+    NonFiniteFloat,
+    /// Total length of a `Bytes` column doesn't fit into `u32`
+    BytesOverflow,
+}
+
+/// Tags of column types stored in `Column`.
+const KIND_U32: u8 = 0;
+const KIND_F32: u8 = 1;
+const KIND_BYTES: u8 = 2;
+const KIND_FLAGS: u8 = 3;
+
+/// Column of a `StaticTable`.
+#[derive(Default)]
+#[cfg_attr(feature = "rkyv_derive", derive(rkyv::Archive, rkyv::Deserialize, rkyv::Serialize))]
+#[cfg_attr(feature = "rkyv_derive", archive_attr(derive(rkyv::CheckBytes)))]
+pub struct Column {
+    /// Column type tag
+    kind: u8,
+    /// Bit width of codes
+    width: u8,
+    /// Smallest value of `F32` columns
+    min: f32,
+    /// Quantization step of `F32` columns
+    step: f32,
+    /// Bit-packed values (`U32`), quantized values (`F32`), end offsets into `bytes` (`Bytes`) or
+    /// flags (`Flags`)
+    codes: Box<[u64]>,
+    /// Byte strings of `Bytes` columns
+    bytes: Box<[u8]>,
+}
+
+/// Returns the type of column with type tag `kind` and codes bit width `width`.
+#[inline]
+fn column_type(kind: u8, width: u8) -> ColumnType {
+    match kind {
+        KIND_U32 => ColumnType::U32,
+        KIND_F32 => ColumnType::F32 { bits: width },
+        KIND_BYTES => ColumnType::Bytes,
+        _ => ColumnType::Flags { bits: width },
+    }
+}
+
+/// Returns the number of bits needed to store `value`.
+#[inline]
+pub(crate) fn bit_width(value: u32) -> u8 {
+    (u32::BITS - value.leading_zeros()) as u8
+}
+
+/// Returns the `width`-bit code of entry `idx` bit-packed in `words`.
+///
+/// # Safety
+/// `words` must hold at least `(idx + 1) * width` bits.
+#[inline]
+pub(crate) unsafe fn read_code(words: &[u64], idx: usize, width: u8) -> u32 {
+    if width == 0 {
+        return 0;
+    }
+    let bit_idx = idx * width as usize;
+    let (word_idx, shift) = (bit_idx / 64, bit_idx % 64);
+    let mut bits = *access::get(words, word_idx) >> shift;
+    if shift + width as usize > 64 {
+        bits |= *access::get(words, word_idx + 1) << (64 - shift);
+    }
+    (bits & ((1 << width) - 1)) as u32
+}
+
+/// Returns `codes` bit-packed to `width` bits each.
+pub(crate) fn pack_codes(codes: &[u32], width: u8) -> Box<[u64]> {
+    let width = width as usize;
+    let mut words = vec![0u64; (codes.len() * width).div_ceil(64)];
+    if width == 0 {
+        return words.into_boxed_slice();
+    }
+    for (idx, &code) in codes.iter().enumerate() {
+        let bit_idx = idx * width;
+        words[bit_idx / 64] |= (code as u64) << (bit_idx % 64);
+        if bit_idx % 64 + width > 64 {
+            words[bit_idx / 64 + 1] |= (code as u64) >> (64 - bit_idx % 64);
+        }
+    }
+    words.into_boxed_slice()
+}
+
+impl Column {
+    /// Builds a column of type `column_type` from its cells in the order of MPHF indices.
+    fn from_cells(column_type: ColumnType, cells: Vec<Cell>) -> Result<Self, StaticTableError> {
+        let mut column = Column::default();
+        match column_type {
+            ColumnType::U32 => {
+                let values = cells
+                    .into_iter()
+                    .map(|cell| match cell {
+                        Cell::U32(value) => Ok(value),
+                        _ => Err(StaticTableError::CellTypeMismatch),
+                    })
+                    .collect::<Result<Vec<_>, _>>()?;
+                column.kind = KIND_U32;
+                column.width = bit_width(values.iter().copied().max().unwrap_or_default());
+                column.codes = pack_codes(&values, column.width);
+            }
+            ColumnType::F32 { bits } => {
+                let values = cells
+                    .into_iter()
+                    .map(|cell| match cell {
+                        Cell::F32(value) if value.is_finite() => Ok(value),
+                        Cell::F32(_) => Err(StaticTableError::NonFiniteFloat),
+                        _ => Err(StaticTableError::CellTypeMismatch),
+                    })
+                    .collect::<Result<Vec<_>, _>>()?;
+                let min = values.iter().copied().reduce(f32::min).unwrap_or_default();
+                let max = values.iter().copied().reduce(f32::max).unwrap_or_default();
+                let max_code = (u32::MAX >> (32 - bits)) as f64;
+                let step = ((max as f64 - min as f64) / max_code) as f32;
+                let codes: Vec<u32> = values
+                    .iter()
+                    .map(|&value| {
+                        if step > 0.0 {
+                            ((value as f64 - min as f64) / step as f64).round().min(max_code) as u32
+                        } else {
+                            0
+                        }
+                    })
+                    .collect();
+                column.kind = KIND_F32;
+                column.width = bits;
+                column.min = min;
+                column.step = step;
+                column.codes = pack_codes(&codes, bits);
+            }
+            ColumnType::Bytes => {
+                let mut bytes = vec![];
+                let mut ends = Vec::with_capacity(cells.len());
+                for cell in cells {
+                    match cell {
+                        Cell::Bytes(value) => bytes.extend_from_slice(&value),
+                        _ => return Err(StaticTableError::CellTypeMismatch),
+                    }
+                    ends.push(u32::try_from(bytes.len()).map_err(|_| StaticTableError::BytesOverflow)?);
+                }
+                column.kind = KIND_BYTES;
+                column.width = bit_width(bytes.len() as u32);
+                column.codes = pack_codes(&ends, column.width);
+                column.bytes = bytes.into_boxed_slice();
+            }
+            ColumnType::Flags { bits } => {
+                let flags = cells
+                    .into_iter()
+                    .map(|cell| match cell {
+                        Cell::Flags(flags) => Ok((flags as u32) & ((1 << bits) - 1)),
+                        _ => Err(StaticTableError::CellTypeMismatch),
+                    })
+                    .collect::<Result<Vec<_>, _>>()?;
+                column.kind = KIND_FLAGS;
+                column.width = bits;
+                column.codes = pack_codes(&flags, bits);
+            }
+        }
+        Ok(column)
+    }
+
+    /// Returns the type of the column.
+    #[inline]
+    pub fn column_type(&self) -> ColumnType {
+        column_type(self.kind, self.width)
+    }
+
+    /// Returns the total number of bytes occupied by the column.
+    #[inline]
+    pub fn size(&self) -> usize {
+        size_of_val(self) + size_of_val(self.codes.as_ref()) + size_of_val(self.bytes.as_ref())
+    }
+}
+
+/// An immutable table of typed columns, of which whole rows are queried by key.
+///
+/// # Examples
+/// ```
+/// # use entropy_map::{Cell, ColumnType, StaticTable, DEFAULT_GAMMA};
+/// let columns = [ColumnType::U32, ColumnType::F32 { bits: 16 }, ColumnType::Bytes, ColumnType::Flags { bits: 2 }];
+/// let rows = [
+///     ("gb", vec![Cell::U32(44), Cell::F32(0.5), Cell::Bytes(b"London".to_vec()), Cell::Flags(0b01)]),
+///     ("pt", vec![Cell::U32(351), Cell::F32(1.0), Cell::Bytes(b"Lisbon".to_vec()), Cell::Flags(0b10)]),
+/// ];
+/// let table: StaticTable<&str> = StaticTable::from_iter_with_params(&columns, rows, DEFAULT_GAMMA).unwrap();
+///
+/// let row = table.get(&"pt").unwrap();
+/// assert_eq!(row.u32(0), 351);
+/// assert!((row.f32(1) - 1.0).abs() < 1e-4);
+/// assert_eq!(row.bytes(2), b"Lisbon");
+/// assert_eq!(row.flags(3), 0b10);
+/// assert!(table.get(&"us").is_none());
+/// ```
+#[derive(Default)]
+#[cfg_attr(feature = "rkyv_derive", derive(rkyv::Archive, rkyv::Deserialize, rkyv::Serialize))]
+#[cfg_attr(feature = "rkyv_derive", archive_attr(derive(rkyv::CheckBytes)))]
+pub struct StaticTable<K, const B: usize = 32, const S: usize = 8, ST = u8, H = WyHash>
+where
+    ST: PrimInt + Unsigned,
+    H: Hasher + Default,
+{
+    /// Keys along with the MPHF mapping them to row indices
+    set: Set<K, B, S, ST, H>,
+    /// Columns holding one value per row
+    columns: Box<[Column]>,
+}
+
+impl<K, const B: usize, const S: usize, ST, H> StaticTable<K, B, S, ST, H>
+where
+    K: Eq + Hash + Clone,
+    ST: PrimInt + Unsigned,
+    H: Hasher + Default,
+{
+    /// Constructs a `StaticTable` with `columns` from an iterator of keys and rows of cells of the
+    /// types of the columns, and MPHF function params.
+    pub fn from_iter_with_params<I>(columns: &[ColumnType], iter: I, gamma: f32) -> Result<Self, StaticTableError>
+    where
+        I: IntoIterator<Item = (K, Vec<Cell>)>,
+    {
+        Self::from_iter_with_policy(columns, iter, &BuildPolicy::fixed(gamma)).map(|(table, _)| table)
+    }
+
+    /// Constructs a `StaticTable` with `columns` from an iterator of keys and rows, escalating
+    /// MPHF `gamma` on failed builds according to `policy`, and returns it along with a
+    /// `BuildReport`.
+    pub fn from_iter_with_policy<I>(
+        columns: &[ColumnType],
+        iter: I,
+        policy: &BuildPolicy,
+    ) -> Result<(Self, BuildReport), StaticTableError>
+    where
+        I: IntoIterator<Item = (K, Vec<Cell>)>,
+    {
+        for &column_type in columns {
+            let valid = match column_type {
+                ColumnType::F32 { bits } => (1..=32).contains(&bits),
+                ColumnType::Flags { bits } => (1..=8).contains(&bits),
+                ColumnType::U32 | ColumnType::Bytes => true,
+            };
+            if !valid {
+                return Err(StaticTableError::InvalidColumnType);
+            }
+        }
+
+        let rows: Vec<(K, Vec<Cell>)> = iter.into_iter().collect();
+        if rows.iter().any(|(_, cells)| cells.len() != columns.len()) {
+            return Err(StaticTableError::RowLengthMismatch);
+        }
+
+        let (set, mut report) = Set::from_iter_with_policy(rows.iter().map(|(key, _)| key.clone()), policy)
+            .map_err(StaticTableError::MphfError)?;
+
+        // Transpose rows into columns of cells in the order of MPHF indices
+        let mut column_cells: Vec<Vec<Cell>> = columns.iter().map(|_| vec![Cell::U32(0); rows.len()]).collect();
+        for (key, cells) in rows {
+            let idx = set.mphf.get(&key).unwrap();
+            for (column_idx, cell) in cells.into_iter().enumerate() {
+                column_cells[column_idx][idx] = cell;
+            }
+        }
+        let columns = columns
+            .iter()
+            .zip(column_cells)
+            .map(|(&column_type, cells)| Column::from_cells(column_type, cells))
+            .collect::<Result<Box<[_]>, _>>()?;
+
+        let table = StaticTable { set, columns };
+        report.set_size(table.size());
+
+        Ok((table, report))
+    }
+}
+
+impl<K, const B: usize, const S: usize, ST, H> StaticTable<K, B, S, ST, H>
+where
+    ST: PrimInt + Unsigned,
+    H: Hasher + Default,
+{
+    /// Returns the row of the key. Returns `None` if the key is not present in the table.
+    #[inline]
+    pub fn get<Q>(&self, key: &Q) -> Option<Row<'_>>
+    where
+        K: Borrow<Q> + PartialEq<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        self.get_impl(self.set.mphf.get(key), key)
+    }
+
+    /// Same as [`StaticTable::get`], but re-uses the hash precomputed by `HashedKey`.
+    #[inline]
+    pub fn get_hashed<Q>(&self, key: &HashedKey<Q, H>) -> Option<Row<'_>>
+    where
+        K: Borrow<Q> + PartialEq<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        self.get_impl(self.set.mphf.get_hashed(key), key.key())
+    }
+
+    /// Inner implementation of `get` returning the row at MPHF index `idx` if the key stored there
+    /// is equal to `key`.
+    #[inline]
+    fn get_impl<Q>(&self, idx: Option<usize>, key: &Q) -> Option<Row<'_>>
+    where
+        K: PartialEq<Q>,
+        Q: ?Sized,
+    {
+        let idx = idx?;
+
+        // SAFETY: `idx` is always within bounds (ensured during construction)
+        if unsafe { access::get(&self.set.keys, idx) } == key {
+            Some(Row { columns: &self.columns, idx })
+        } else {
+            None
+        }
+    }
+
+    /// Returns the number of rows in the table.
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.set.keys.len()
+    }
+
+    /// Returns `true` if the table contains no rows.
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.set.keys.is_empty()
+    }
+
+    /// Returns the number of columns of the table.
+    #[inline]
+    pub fn num_columns(&self) -> usize {
+        self.columns.len()
+    }
+
+    /// Returns the columns of the table.
+    #[inline]
+    pub fn columns(&self) -> &[Column] {
+        &self.columns
+    }
+
+    /// Returns an iterator over the keys and rows of the table.
+    pub fn iter(&self) -> impl ExactSizeIterator<Item = (&K, Row<'_>)> + '_ {
+        self.set
+            .keys
+            .iter()
+            .enumerate()
+            .map(|(idx, key)| (key, Row { columns: &self.columns, idx }))
+    }
+
+    /// Returns the total number of bytes occupied by the table.
+    #[inline]
+    pub fn size(&self) -> usize {
+        size_of_val(self)
+            + self.set.mphf.size()
+            + size_of_val(self.set.keys.as_ref())
+            + self.columns.iter().map(Column::size).sum::<usize>()
+    }
+
+    /// Checks invariants of the MPHF and that every column holds a value per key.
+    pub fn debug_check_invariants(&self) -> Result<(), InvariantError> {
+        invariants::check_outputs(&self.set.mphf, self.len())?;
+        for column in self.columns.iter() {
+            if column.kind > KIND_FLAGS || column.codes.len() != (self.len() * column.width as usize).div_ceil(64) {
+                return Err(InvariantError::LengthMismatch);
+            }
+            // SAFETY: `codes` holds a code per key, as checked above
+            if column.kind == KIND_BYTES
+                && !self.is_empty()
+                && unsafe { read_code(&column.codes, self.len() - 1, column.width) } as usize != column.bytes.len()
+            {
+                return Err(InvariantError::LengthMismatch);
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Row of a `StaticTable`, returned by [`StaticTable::get`].
+#[derive(Clone, Copy)]
+pub struct Row<'a> {
+    /// Columns of the table
+    columns: &'a [Column],
+    /// Index of the row
+    idx: usize,
+}
+
+/// Row of an archived `StaticTable`, returned by `ArchivedStaticTable::get`.
+#[cfg(feature = "rkyv_derive")]
+#[derive(Clone, Copy)]
+pub struct ArchivedRow<'a> {
+    /// Columns of the table
+    columns: &'a [ArchivedColumn],
+    /// Index of the row
+    idx: usize,
+}
+
+/// Implements typed cell accessors of a row struct over columns of type `$column`.
+macro_rules! impl_row {
+    ($row:ident, $column:ty) => {
+        impl<'a> $row<'a> {
+            /// Returns column `column_idx`, checking that it has the expected type tag `kind`.
+            #[inline]
+            fn column(&self, column_idx: usize, kind: u8) -> &'a $column {
+                let column = &self.columns[column_idx];
+                assert!(
+                    column.kind == kind,
+                    "column {column_idx} is {:?}",
+                    column_type(column.kind, column.width)
+                );
+                column
+            }
+
+            /// Returns the value of `U32` column `column_idx`.
+            ///
+            /// # Panics
+            /// Panics if the column doesn't exist or is not of `U32` type.
+            #[inline]
+            pub fn u32(&self, column_idx: usize) -> u32 {
+                let column = self.column(column_idx, KIND_U32);
+                // SAFETY: `idx` is always within bounds (ensured during construction)
+                unsafe { read_code(&column.codes, self.idx, column.width) }
+            }
+
+            /// Returns the dequantized value of `F32` column `column_idx`.
+            ///
+            /// # Panics
+            /// Panics if the column doesn't exist or is not of `F32` type.
+            #[inline]
+            pub fn f32(&self, column_idx: usize) -> f32 {
+                let column = self.column(column_idx, KIND_F32);
+                // SAFETY: `idx` is always within bounds (ensured during construction)
+                let code = unsafe { read_code(&column.codes, self.idx, column.width) };
+                column.min + code as f32 * column.step
+            }
+
+            /// Returns the value of `Bytes` column `column_idx`.
+            ///
+            /// # Panics
+            /// Panics if the column doesn't exist or is not of `Bytes` type.
+            #[inline]
+            pub fn bytes(&self, column_idx: usize) -> &'a [u8] {
+                let column = self.column(column_idx, KIND_BYTES);
+                // SAFETY: `idx` is always within bounds and end offsets are non-decreasing and
+                // within `bytes` (ensured during construction)
+                unsafe {
+                    let start = match self.idx {
+                        0 => 0,
+                        idx => read_code(&column.codes, idx - 1, column.width) as usize,
+                    };
+                    let end = read_code(&column.codes, self.idx, column.width) as usize;
+                    column.bytes.get_unchecked(start..end)
+                }
+            }
+
+            /// Returns the flags of `Flags` column `column_idx`.
+            ///
+            /// # Panics
+            /// Panics if the column doesn't exist or is not of `Flags` type.
+            #[inline]
+            pub fn flags(&self, column_idx: usize) -> u8 {
+                let column = self.column(column_idx, KIND_FLAGS);
+                // SAFETY: `idx` is always within bounds (ensured during construction)
+                unsafe { read_code(&column.codes, self.idx, column.width) as u8 }
+            }
+        }
+    };
+}
+
+impl_row!(Row, Column);
+#[cfg(feature = "rkyv_derive")]
+impl_row!(ArchivedRow, ArchivedColumn);
+
+/// Implement `get` for `Archived` version of `StaticTable` if feature is enabled
+#[cfg(feature = "rkyv_derive")]
+impl<K, const B: usize, const S: usize, ST, H> ArchivedStaticTable<K, B, S, ST, H>
+where
+    K: PartialEq + Hash + rkyv::Archive,
+    K::Archived: PartialEq<K>,
+    ST: PrimInt + Unsigned + rkyv::Archive<Archived = ST>,
+    H: Hasher + Default,
+{
+    /// Returns the archived row of the key. Returns `None` if the key is not present in the table.
+    #[inline]
+    pub fn get<Q>(&self, key: &Q) -> Option<ArchivedRow<'_>>
+    where
+        K: Borrow<Q>,
+        <K as rkyv::Archive>::Archived: PartialEq<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        let idx = self.set.mphf.get(key)?;
+
+        // SAFETY: `idx` is always within bounds (ensured during construction)
+        if unsafe { access::get(&self.set.keys, idx) } == key {
+            Some(ArchivedRow { columns: &self.columns, idx })
+        } else {
+            None
+        }
+    }
+
+    /// Returns the number of rows in the table.
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.set.keys.len()
+    }
+
+    /// Returns `true` if the table contains no rows.
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.set.keys.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mphf::DEFAULT_GAMMA;
+    use rand::{Rng, SeedableRng};
+    use rand_chacha::ChaCha8Rng;
+
+    const COLUMNS: [ColumnType; 4] = [
+        ColumnType::U32,
+        ColumnType::F32 { bits: 12 },
+        ColumnType::Bytes,
+        ColumnType::Flags { bits: 3 },
+    ];
+
+    /// Returns random rows of `COLUMNS` types.
+    fn rows() -> Vec<(u64, Vec<Cell>)> {
+        let mut rng = ChaCha8Rng::seed_from_u64(123);
+        (0..1000)
+            .map(|_| {
+                let len = rng.gen_range(0..10);
+                let cells = vec![
+                    Cell::U32(rng.gen_range(0..100_000)),
+                    Cell::F32(rng.gen_range(-10.0..10.0)),
+                    Cell::Bytes((0..len).map(|_| rng.gen()).collect()),
+                    Cell::Flags(rng.gen()),
+                ];
+                (rng.gen(), cells)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_static_table() {
+        let rows = rows();
+        let table = StaticTable::<u64>::from_iter_with_params(&COLUMNS, rows.clone(), DEFAULT_GAMMA).unwrap();
+        assert_eq!(table.debug_check_invariants(), Ok(()));
+        assert_eq!(table.len(), rows.len());
+        assert_eq!(table.num_columns(), COLUMNS.len());
+        for (column, column_type) in table.columns().iter().zip(COLUMNS) {
+            assert_eq!(column.column_type(), column_type);
+        }
+
+        // quantization step of 20 / (2^12 - 1)
+        let max_error = 20.0 / 4095.0;
+        let check_row = |row: Row, cells: &[Cell]| {
+            assert_eq!(Cell::U32(row.u32(0)), cells[0]);
+            let Cell::F32(value) = cells[1] else { unreachable!() };
+            assert!((row.f32(1) - value).abs() <= max_error);
+            assert_eq!(Cell::Bytes(row.bytes(2).to_vec()), cells[2]);
+            let Cell::Flags(flags) = cells[3] else { unreachable!() };
+            assert_eq!(row.flags(3), flags & 0b111);
+        };
+        for (key, cells) in &rows {
+            check_row(table.get(key).unwrap(), cells);
+            check_row(table.get_hashed(&HashedKey::new(key)).unwrap(), cells);
+        }
+        assert_eq!(table.iter().count(), rows.len());
+        assert!(table
+            .get(&rows.iter().map(|(key, _)| key).max().unwrap().wrapping_add(1))
+            .is_none());
+
+        #[cfg(feature = "rkyv_derive")]
+        {
+            let rkyv_bytes = rkyv::to_bytes::<_, 1024>(&table).unwrap();
+            let rkyv_table = rkyv::check_archived_root::<StaticTable<u64>>(&rkyv_bytes).unwrap();
+            assert_eq!(rkyv_table.len(), rows.len());
+            for (key, cells) in &rows {
+                let row = rkyv_table.get(key).unwrap();
+                assert_eq!(Cell::U32(row.u32(0)), cells[0]);
+                assert_eq!(Cell::Bytes(row.bytes(2).to_vec()), cells[2]);
+            }
+        }
+    }
+
+    #[test]
+    fn test_invalid_rows() {
+        let build = |columns: &[ColumnType], cells: Vec<Cell>| {
+            StaticTable::<u64>::from_iter_with_params(columns, [(1, cells)], DEFAULT_GAMMA).map(|_| ())
+        };
+        assert!(matches!(
+            build(&[ColumnType::Flags { bits: 9 }], vec![Cell::Flags(0)]),
+            Err(StaticTableError::InvalidColumnType)
+        ));
+        assert!(matches!(
+            build(&[ColumnType::U32], vec![]),
+            Err(StaticTableError::RowLengthMismatch)
+        ));
+        assert!(matches!(
+            build(&[ColumnType::U32], vec![Cell::Flags(0)]),
+            Err(StaticTableError::CellTypeMismatch)
+        ));
+        assert!(matches!(
+            build(&[ColumnType::F32 { bits: 8 }], vec![Cell::F32(f32::NAN)]),
+            Err(StaticTableError::NonFiniteFloat)
+        ));
+    }
+
+    #[test]
+    #[should_panic(expected = "column 0 is U32")]
+    fn test_column_type_mismatch() {
+        let table =
+            StaticTable::<u64>::from_iter_with_params(&[ColumnType::U32], [(1, vec![Cell::U32(2)])], DEFAULT_GAMMA)
+                .unwrap();
+        table.get(&1).unwrap().bytes(0);
+    }
+}
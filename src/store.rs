@@ -0,0 +1,456 @@
+//! A module providing `Store`, a read-only single-file container of named archived structures.
+//!
+//! Services loading several related structures (e.g. a set of allowed keys, a map of their
+//! metadata and a bit-packed map of their features) would otherwise ship a directory of separate
+//! archives and re-implement their cataloging, integrity checks and memory mapping. A store file
+//! holds any number of named sections, each one an `rkyv` archive of an arbitrary structure, built
+//! with `StoreWriter`:
+//! - a 64-byte header with the magic bytes, the store version and the location of the table of
+//!   contents,
+//! - sections aligned to 64 bytes, so that they can be accessed in place,
+//! - a table of contents with the name, location and checksum of every section, itself
+//!   checksummed.
+//!
+//! `Store` owns the file bytes, read into memory or memory-mapped (with `mmap` feature), and only
+//! checks the header and the table of contents when opened. A section is verified when it's first
+//! accessed by name, so opening a store and querying a few of its sections only pages in those
+//! sections. Checksums detect accidental corruption, [`Store::get`] additionally validates sections
+//! with `bytecheck`, while [`Store::get_unchecked`] skips it for sections of a trusted pipeline.
+//!
+//! Sections are independent archives: structures sharing a key set are stored with their own copy
+//! of the keys, see `StaticTable` for several columns over a single MPHF and copy of the keys.
+
+use std::fs;
+use std::io::{self, Write};
+use std::path::Path;
+
+use rkyv::ser::serializers::AllocSerializer;
+use rkyv::validation::validators::DefaultValidator;
+use rkyv::{AlignedVec, Archive, Archived, CheckBytes, Serialize};
+
+use crate::owned_archive::{align, Buffer};
+
+/// Magic bytes at the start of store files.
+const MAGIC: [u8; 8] = *b"ENTMSTOR";
+
+/// Version of the store file layout.
+pub const STORE_VERSION: u32 = 1;
+
+/// Size of the header, which is also the offset of the first section.
+const HEADER_SIZE: usize = 64;
+
+/// Alignment of section offsets.
+const SECTION_ALIGN: usize = 64;
+
+/// Seed of the section and table of contents checksums.
+const CHECKSUM_SEED: u64 = 0x73746f7265;
+
+/// Errors that can occur when building, opening or accessing a `Store`.
+#[derive(Debug)]
+pub enum StoreError {
+    /// Error when reading the store file failed.
+    Io(io::Error),
+    /// Error when the bytes don't start with a store header.
+    InvalidHeader,
+    /// Error when the store file layout version is not supported.
+    UnsupportedVersion(u32),
+    /// Error when the table of contents is truncated, corrupted or points outside of the store.
+    InvalidToc,
+    /// Error when a section with the same name has already been added.
+    DuplicateSection(String),
+    /// Error when there is no section with the name.
+    SectionNotFound(String),
+    /// Error when the checksum of the named section doesn't match its bytes.
+    ChecksumMismatch(String),
+    /// Error when a structure can't be serialized or a section is not a valid archive of the
+    /// requested type, with the error message.
+    Invalid(String),
+}
+
+impl From<io::Error> for StoreError {
+    #[inline]
+    fn from(err: io::Error) -> Self {
+        StoreError::Io(err)
+    }
+}
+
+/// Computes checksum of section or table of contents `bytes`.
+#[inline]
+fn checksum(bytes: &[u8]) -> u64 {
+    wyhash::wyhash(bytes, CHECKSUM_SEED)
+}
+
+/// Builder of store files, see the module documentation.
+///
+/// # Examples
+/// ```
+/// # use entropy_map::{MapWithDict, Set, Store, StoreWriter, DEFAULT_GAMMA};
+/// let set = Set::<u64>::from_iter_with_params([1, 2, 3], DEFAULT_GAMMA).unwrap();
+/// let map = MapWithDict::<u64, u32>::from_iter_with_params([(1, 10), (2, 20)], DEFAULT_GAMMA).unwrap();
+///
+/// let mut writer = StoreWriter::new();
+/// writer.add("allowed", &set).unwrap().add("weights", &map).unwrap();
+/// let store = Store::from_aligned_vec(writer.to_bytes()).unwrap();
+///
+/// assert!(store.get::<Set<u64>>("allowed").unwrap().contains(&2));
+/// assert_eq!(store.get::<MapWithDict<u64, u32>>("weights").unwrap().get(&1), Some(&10));
+/// assert!(store.get::<Set<u64>>("missing").is_err());
+/// ```
+#[derive(Default)]
+pub struct StoreWriter {
+    /// Names and archive bytes of the sections, in the order of addition
+    sections: Vec<(String, AlignedVec)>,
+}
+
+impl StoreWriter {
+    /// Creates a writer of an empty store.
+    #[inline]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds section `name` holding the archive of `value`.
+    pub fn add<T>(&mut self, name: &str, value: &T) -> Result<&mut Self, StoreError>
+    where
+        T: Serialize<AllocSerializer<1024>>,
+    {
+        let bytes = rkyv::to_bytes::<_, 1024>(value).map_err(|err| StoreError::Invalid(format!("{err:?}")))?;
+        self.add_archive(name, bytes)
+    }
+
+    /// Adds section `name` holding archive `bytes` serialized beforehand, e.g. with
+    /// `to_bytes_with_align`.
+    pub fn add_archive(&mut self, name: &str, bytes: AlignedVec) -> Result<&mut Self, StoreError> {
+        if self.sections.iter().any(|(section_name, _)| section_name == name) {
+            return Err(StoreError::DuplicateSection(name.to_string()));
+        }
+        self.sections.push((name.to_string(), bytes));
+        Ok(self)
+    }
+
+    /// Writes the store file to `writer`.
+    pub fn write_to<W: Write>(&self, mut writer: W) -> io::Result<()> {
+        let mut toc = vec![];
+        let mut offset = HEADER_SIZE;
+        for (name, bytes) in &self.sections {
+            toc.extend_from_slice(&(name.len() as u32).to_le_bytes());
+            toc.extend_from_slice(name.as_bytes());
+            toc.extend_from_slice(&(offset as u64).to_le_bytes());
+            toc.extend_from_slice(&(bytes.len() as u64).to_le_bytes());
+            toc.extend_from_slice(&checksum(bytes).to_le_bytes());
+            offset = (offset + bytes.len()).next_multiple_of(SECTION_ALIGN);
+        }
+
+        let mut header = [0; HEADER_SIZE];
+        header[0..8].copy_from_slice(&MAGIC);
+        header[8..12].copy_from_slice(&STORE_VERSION.to_le_bytes());
+        header[12..16].copy_from_slice(&(self.sections.len() as u32).to_le_bytes());
+        header[16..24].copy_from_slice(&(offset as u64).to_le_bytes());
+        header[24..32].copy_from_slice(&(toc.len() as u64).to_le_bytes());
+        header[32..40].copy_from_slice(&checksum(&toc).to_le_bytes());
+        writer.write_all(&header)?;
+
+        let padding = [0; SECTION_ALIGN];
+        for (_, bytes) in &self.sections {
+            writer.write_all(bytes)?;
+            writer.write_all(&padding[..bytes.len().next_multiple_of(SECTION_ALIGN) - bytes.len()])?;
+        }
+
+        writer.write_all(&toc)
+    }
+
+    /// Returns the store file bytes, which can be opened with [`Store::from_aligned_vec`].
+    pub fn to_bytes(&self) -> AlignedVec {
+        let mut bytes = AlignedVec::new();
+        self.write_to(&mut bytes).expect("writing to memory never fails");
+        bytes
+    }
+}
+
+/// Location and checksum of a store section.
+struct Section {
+    /// Section name
+    name: String,
+    /// Offset of the section bytes from the start of the store
+    offset: usize,
+    /// Number of section bytes
+    len: usize,
+    /// Checksum of the section bytes
+    checksum: u64,
+}
+
+/// Reads `N` bytes at `pos` of `bytes` advancing `pos`, or returns `None` if `bytes` are too short.
+#[inline]
+fn read_array<const N: usize>(bytes: &[u8], pos: &mut usize) -> Option<[u8; N]> {
+    let array = bytes.get(*pos..pos.checked_add(N)?)?.try_into().ok()?;
+    *pos += N;
+    Some(array)
+}
+
+/// Reads a little-endian `u64` at `pos` of `bytes` as `usize` advancing `pos`.
+#[inline]
+fn read_usize(bytes: &[u8], pos: &mut usize) -> Option<usize> {
+    usize::try_from(u64::from_le_bytes(read_array(bytes, pos)?)).ok()
+}
+
+/// Read-only store of named archived structures owning its bytes, see the module documentation.
+pub struct Store {
+    /// Store file bytes
+    buffer: Buffer,
+    /// Sections listed in the table of contents
+    sections: Vec<Section>,
+}
+
+impl Store {
+    /// Opens a store from its file `bytes`, copying them only if they are not aligned.
+    pub fn from_vec(bytes: Vec<u8>) -> Result<Self, StoreError> {
+        Self::new(align(&bytes).unwrap_or(Buffer::Vec(bytes)))
+    }
+
+    /// Opens a store from its file `bytes`, e.g. as returned by [`StoreWriter::to_bytes`].
+    pub fn from_aligned_vec(bytes: AlignedVec) -> Result<Self, StoreError> {
+        Self::new(Buffer::Aligned(bytes))
+    }
+
+    /// Reads and opens the store file at `path`.
+    pub fn from_path(path: impl AsRef<Path>) -> Result<Self, StoreError> {
+        Self::from_vec(fs::read(path)?)
+    }
+
+    /// Opens the store file memory-mapped by `mmap`, so that sections are paged in on access.
+    #[cfg(feature = "mmap")]
+    pub fn from_mmap(mmap: memmap2::Mmap) -> Result<Self, StoreError> {
+        Self::new(align(&mmap).unwrap_or(Buffer::Mmap(mmap)))
+    }
+
+    /// Checks the header and the table of contents of the aligned store `buffer`.
+    fn new(buffer: Buffer) -> Result<Self, StoreError> {
+        let bytes = buffer.bytes();
+        if bytes.len() < HEADER_SIZE || bytes[0..8] != MAGIC {
+            return Err(StoreError::InvalidHeader);
+        }
+
+        let mut pos = MAGIC.len();
+        let version = u32::from_le_bytes(read_array(bytes, &mut pos).unwrap());
+        if version != STORE_VERSION {
+            return Err(StoreError::UnsupportedVersion(version));
+        }
+        let count = u32::from_le_bytes(read_array(bytes, &mut pos).unwrap());
+        let toc_offset = read_usize(bytes, &mut pos).ok_or(StoreError::InvalidToc)?;
+        let toc_len = read_usize(bytes, &mut pos).ok_or(StoreError::InvalidToc)?;
+        let toc_checksum = u64::from_le_bytes(read_array(bytes, &mut pos).unwrap());
+
+        let toc = toc_offset
+            .checked_add(toc_len)
+            .and_then(|toc_end| bytes.get(toc_offset..toc_end))
+            .ok_or(StoreError::InvalidToc)?;
+        if checksum(toc) != toc_checksum {
+            return Err(StoreError::InvalidToc);
+        }
+
+        let mut sections = vec![];
+        let mut pos = 0;
+        for _ in 0..count {
+            let section = (|| {
+                let name_len = u32::from_le_bytes(read_array(toc, &mut pos)?) as usize;
+                let name = toc.get(pos..pos.checked_add(name_len)?)?;
+                pos += name_len;
+                Some(Section {
+                    name: String::from_utf8(name.to_vec()).ok()?,
+                    offset: read_usize(toc, &mut pos)?,
+                    len: read_usize(toc, &mut pos)?,
+                    checksum: u64::from_le_bytes(read_array(toc, &mut pos)?),
+                })
+            })()
+            .ok_or(StoreError::InvalidToc)?;
+
+            let in_bounds = section.offset >= HEADER_SIZE
+                && section.offset % SECTION_ALIGN == 0
+                && section
+                    .offset
+                    .checked_add(section.len)
+                    .is_some_and(|end| end <= toc_offset);
+            if !in_bounds {
+                return Err(StoreError::InvalidToc);
+            }
+            sections.push(section);
+        }
+        if pos != toc.len() {
+            return Err(StoreError::InvalidToc);
+        }
+
+        Ok(Store { buffer, sections })
+    }
+
+    /// Returns the names of the sections, in the order they were added.
+    pub fn names(&self) -> impl ExactSizeIterator<Item = &str> {
+        self.sections.iter().map(|section| section.name.as_str())
+    }
+
+    /// Returns the number of sections.
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.sections.len()
+    }
+
+    /// Returns `true` if the store has no sections.
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.sections.is_empty()
+    }
+
+    /// Returns the bytes of section `name` after verifying its checksum.
+    pub fn section(&self, name: &str) -> Result<&[u8], StoreError> {
+        let section = self
+            .sections
+            .iter()
+            .find(|section| section.name == name)
+            .ok_or_else(|| StoreError::SectionNotFound(name.to_string()))?;
+        self.section_bytes(section)
+    }
+
+    /// Returns the bytes of `section` after verifying its checksum.
+    fn section_bytes(&self, section: &Section) -> Result<&[u8], StoreError> {
+        let bytes = &self.buffer.bytes()[section.offset..section.offset + section.len];
+        if checksum(bytes) != section.checksum {
+            return Err(StoreError::ChecksumMismatch(section.name.clone()));
+        }
+        Ok(bytes)
+    }
+
+    /// Returns the archived `T` of section `name`, after verifying its checksum and validating it
+    /// with `bytecheck`.
+    pub fn get<T: Archive>(&self, name: &str) -> Result<&Archived<T>, StoreError>
+    where
+        Archived<T>: for<'a> CheckBytes<DefaultValidator<'a>>,
+    {
+        rkyv::check_archived_root::<T>(self.section(name)?).map_err(|err| StoreError::Invalid(err.to_string()))
+    }
+
+    /// Returns the archived `T` of section `name` after verifying its checksum only.
+    ///
+    /// # Safety
+    /// Section `name` must hold a valid archive of `T`, e.g. produced by [`StoreWriter::add`] of
+    /// the same `T` with the same crate versions. A matching checksum only guards against accidental
+    /// corruption.
+    pub unsafe fn get_unchecked<T: Archive>(&self, name: &str) -> Result<&Archived<T>, StoreError> {
+        Ok(rkyv::archived_root::<T>(self.section(name)?))
+    }
+
+    /// Verifies checksums of all sections, e.g. right after downloading a store file.
+    pub fn verify(&self) -> Result<(), StoreError> {
+        for section in &self.sections {
+            self.section_bytes(section)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::map_with_dict::MapWithDict;
+    use crate::map_with_dict_bitpacked::MapWithDictBitpacked;
+    use crate::mphf::DEFAULT_GAMMA;
+    use crate::set::Set;
+
+    fn build_store() -> AlignedVec {
+        let set = Set::<u64>::from_iter_with_params(0..1000, DEFAULT_GAMMA).unwrap();
+        let map =
+            MapWithDict::<String, u32>::from_iter_with_params((0..1000).map(|i| (i.to_string(), i % 7)), DEFAULT_GAMMA)
+                .unwrap();
+        let bitpacked = MapWithDictBitpacked::<u64>::from_iter_with_params(
+            (0..1000).map(|i| (i, vec![i as u32, 1])),
+            DEFAULT_GAMMA,
+        )
+        .unwrap();
+
+        let mut writer = StoreWriter::new();
+        writer
+            .add("set", &set)
+            .unwrap()
+            .add("map", &map)
+            .unwrap()
+            .add("bitpacked", &bitpacked)
+            .unwrap();
+        assert!(matches!(writer.add("set", &set), Err(StoreError::DuplicateSection(_))));
+        writer.to_bytes()
+    }
+
+    fn check_store(store: &Store) {
+        assert_eq!(store.names().collect::<Vec<_>>(), ["set", "map", "bitpacked"]);
+        assert!(store.verify().is_ok());
+
+        let set = store.get::<Set<u64>>("set").unwrap();
+        assert!(set.contains(&999));
+        assert!(!set.contains(&1000));
+        let map = store.get::<MapWithDict<String, u32>>("map").unwrap();
+        assert_eq!(map.get("10"), Some(&3));
+        let bitpacked = unsafe { store.get_unchecked::<MapWithDictBitpacked<u64>>("bitpacked") }.unwrap();
+        let mut values = [0; 2];
+        assert!(bitpacked.get_values(&7, &mut values));
+        assert_eq!(values, [7, 1]);
+        assert!(matches!(
+            store.get::<Set<u64>>("missing"),
+            Err(StoreError::SectionNotFound(_))
+        ));
+    }
+
+    #[test]
+    fn test_store() {
+        let bytes = build_store();
+        check_store(&Store::from_aligned_vec(bytes.clone()).unwrap());
+
+        // unaligned bytes are copied
+        let mut unaligned = vec![0u8; bytes.len() + 1];
+        unaligned[1..].copy_from_slice(&bytes);
+        check_store(&Store::from_vec(unaligned[1..].to_vec()).unwrap());
+
+        let path = std::env::temp_dir().join(format!("entropy-map-store-{}", std::process::id()));
+        let mut file = fs::File::create(&path).unwrap();
+        StoreWriter::new().write_to(&mut file).unwrap();
+        assert!(Store::from_path(&path).unwrap().is_empty());
+        fs::write(&path, &bytes).unwrap();
+        check_store(&Store::from_path(&path).unwrap());
+        fs::remove_file(&path).unwrap();
+
+        // a corrupted section doesn't prevent accessing other sections
+        let store = Store::from_aligned_vec(bytes.clone()).unwrap();
+        let corrupted_at = store.sections[0].offset + store.sections[0].len / 2;
+        let mut corrupted = bytes.clone();
+        corrupted[corrupted_at] ^= 1;
+        let store = Store::from_aligned_vec(corrupted).unwrap();
+        assert!(matches!(
+            store.get::<Set<u64>>("set"),
+            Err(StoreError::ChecksumMismatch(_))
+        ));
+        assert!(matches!(store.verify(), Err(StoreError::ChecksumMismatch(_))));
+        assert!(store.get::<MapWithDict<String, u32>>("map").is_ok());
+
+        let mut corrupted = bytes.clone();
+        corrupted[0] ^= 1;
+        assert!(matches!(
+            Store::from_aligned_vec(corrupted),
+            Err(StoreError::InvalidHeader)
+        ));
+        let mut corrupted = bytes.clone();
+        corrupted[8] = 2;
+        assert!(matches!(
+            Store::from_aligned_vec(corrupted),
+            Err(StoreError::UnsupportedVersion(_))
+        ));
+        let mut corrupted = bytes.clone();
+        let last = corrupted.len() - 1;
+        corrupted[last] ^= 1;
+        assert!(matches!(
+            Store::from_aligned_vec(corrupted),
+            Err(StoreError::InvalidToc)
+        ));
+        let mut truncated = AlignedVec::new();
+        truncated.extend_from_slice(&bytes[..bytes.len() - 1]);
+        assert!(matches!(
+            Store::from_aligned_vec(truncated),
+            Err(StoreError::InvalidToc)
+        ));
+    }
+}
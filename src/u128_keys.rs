@@ -0,0 +1,591 @@
+//! A module providing `SetU128` and `MapWithDictU128`, a set and a map specialized for `u128` keys
+//! such as IPv6 addresses.
+//!
+//! `Set<u128>` and `MapWithDict<u128, V>` work, but hashing a `u128` with `WyHash` goes through the
+//! generic byte slice path of `Hasher::write`, and every query compares whole 16-byte keys loaded
+//! from an array of 16-byte aligned elements. `U128Hasher` hashes the two 64-bit halves of a key
+//! with a couple of multiplications, and `SetU128`/`MapWithDictU128` store keys as two planes of
+//! low and high `u64` halves, so that a query verifies the low half first and touches the high
+//! plane only if it matches, which for absent keys sharing a prefix (e.g. addresses of the same
+//! /64 network) is almost never. Use `u128::from(addr)` to query by `Ipv6Addr`.
+
+use std::hash::Hasher;
+use std::mem::size_of_val;
+
+use num::{PrimInt, Unsigned};
+
+use crate::access;
+use crate::hashed_key::HashedKey;
+use crate::invariants::{self, InvariantError};
+use crate::map_with_dict::MapWithDict;
+use crate::mphf::{BuildPolicy, Mphf, MphfError};
+use crate::report::BuildReport;
+use crate::set::Set;
+
+/// Multiplicative constants of `U128Hasher`, borrowed from `wyhash`.
+const P0: u64 = 0xa076_1d64_78bd_642f;
+const P1: u64 = 0xe703_7ed1_a0b4_28db;
+const P2: u64 = 0x8ebc_6af0_9c88_c6e3;
+
+/// Multiplies `a` and `b` into 128 bits and folds the result back into 64 bits.
+#[inline(always)]
+fn mum(a: u64, b: u64) -> u64 {
+    let r = a as u128 * b as u128;
+    (r as u64) ^ ((r >> 64) as u64)
+}
+
+/// Splits `key` into its low and high 64-bit halves.
+#[inline(always)]
+fn split(key: u128) -> (u64, u64) {
+    (key as u64, (key >> 64) as u64)
+}
+
+/// Joins low and high 64-bit halves back into a `u128` key.
+#[inline(always)]
+fn join(lo: u64, hi: u64) -> u128 {
+    ((hi as u128) << 64) | lo as u128
+}
+
+/// Hasher for `u128` keys (and `u64` hash seeds) mixing both halves of a key with two
+/// multiplications instead of hashing its bytes, default hasher of `SetU128` and `MapWithDictU128`.
+/// Other keys are hashed 8 bytes at a time, so it can be used as `H` of any structure, but it's not
+/// meant to be resistant to crafted keys, see `BuildPolicy::keyed` for untrusted input.
+///
+/// # Examples
+/// ```
+/// # use std::hash::{Hash, Hasher};
+/// # use entropy_map::U128Hasher;
+/// let mut hasher = U128Hasher::default();
+/// 1u128.hash(&mut hasher);
+/// assert_ne!(hasher.finish(), U128Hasher::default().finish());
+/// ```
+#[derive(Debug, Clone, Copy, Default)]
+pub struct U128Hasher {
+    /// Internal state
+    state: u64,
+}
+
+impl Hasher for U128Hasher {
+    #[inline]
+    fn write(&mut self, bytes: &[u8]) {
+        for chunk in bytes.chunks(8) {
+            let mut word = [0; 8];
+            word[..chunk.len()].copy_from_slice(chunk);
+            self.write_u64(u64::from_le_bytes(word));
+        }
+        self.write_u64(bytes.len() as u64);
+    }
+
+    #[inline]
+    fn write_u64(&mut self, i: u64) {
+        self.state = mum(i ^ self.state ^ P0, P1);
+    }
+
+    #[inline]
+    fn write_u128(&mut self, i: u128) {
+        let (lo, hi) = split(i);
+        self.state = mum(mum(lo ^ self.state ^ P0, P1) ^ hi, P2);
+    }
+
+    #[inline]
+    fn finish(&self) -> u64 {
+        self.state
+    }
+}
+
+/// An immutable set of `u128` keys storing them as planes of low and high halves, see the module
+/// documentation.
+///
+/// # Examples
+/// ```
+/// # use std::net::Ipv6Addr;
+/// # use entropy_map::{SetU128, DEFAULT_GAMMA};
+/// let addrs: Vec<Ipv6Addr> = vec!["2001:db8::1".parse().unwrap(), "2001:db8::2".parse().unwrap()];
+/// let set = SetU128::<32, 8>::from_iter_with_params(addrs.iter().map(|&addr| u128::from(addr)), DEFAULT_GAMMA).unwrap();
+/// assert!(set.contains(&u128::from(addrs[0])));
+/// assert!(!set.contains(&u128::from("2001:db8::3".parse::<Ipv6Addr>().unwrap())));
+/// ```
+#[derive(Default)]
+#[cfg_attr(feature = "rkyv_derive", derive(rkyv::Archive, rkyv::Deserialize, rkyv::Serialize))]
+#[cfg_attr(feature = "rkyv_derive", archive_attr(derive(rkyv::CheckBytes)))]
+pub struct SetU128<const B: usize = 32, const S: usize = 8, ST = u8, H = U128Hasher>
+where
+    ST: PrimInt + Unsigned,
+    H: Hasher + Default,
+{
+    /// Minimally Perfect Hash Function for keys indices retrieval
+    mphf: Mphf<B, S, ST, H>,
+    /// Low halves of the set keys
+    keys_lo: Box<[u64]>,
+    /// High halves of the set keys
+    keys_hi: Box<[u64]>,
+}
+
+impl<const B: usize, const S: usize, ST, H> SetU128<B, S, ST, H>
+where
+    ST: PrimInt + Unsigned,
+    H: Hasher + Default,
+{
+    /// Constructs a `SetU128` from an iterator of keys and MPHF function parameters.
+    pub fn from_iter_with_params<I>(iter: I, gamma: f32) -> Result<Self, MphfError>
+    where
+        I: IntoIterator<Item = u128>,
+    {
+        Ok(Set::from_iter_with_params(iter, gamma)?.into())
+    }
+
+    /// Constructs a `SetU128` from an iterator of keys, escalating MPHF `gamma` on failed builds
+    /// according to `policy`, and returns it along with a `BuildReport`.
+    pub fn from_iter_with_policy<I>(iter: I, policy: &BuildPolicy) -> Result<(Self, BuildReport), MphfError>
+    where
+        I: IntoIterator<Item = u128>,
+    {
+        let (set, mut report) = Set::from_iter_with_policy(iter, policy)?;
+        let set = Self::from(set);
+        report.set_size(set.size());
+        Ok((set, report))
+    }
+
+    /// Returns `true` if the set contains the key.
+    #[inline]
+    pub fn contains(&self, key: &u128) -> bool {
+        self.contains_impl(self.mphf.get(key), *key)
+    }
+
+    /// Same as [`SetU128::contains`], but re-uses the hash precomputed by `HashedKey`.
+    #[inline]
+    pub fn contains_hashed(&self, key: &HashedKey<u128, H>) -> bool {
+        self.contains_impl(self.mphf.get_hashed(key), *key.key())
+    }
+
+    /// Inner implementation of `contains` checking the key stored at MPHF index `idx`.
+    #[inline]
+    fn contains_impl(&self, idx: Option<usize>, key: u128) -> bool {
+        idx.is_some_and(|idx| planes_eq(&self.keys_lo, &self.keys_hi, idx, key))
+    }
+
+    /// Returns the number of keys in the set.
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.keys_lo.len()
+    }
+
+    /// Returns `true` if the set contains no keys.
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.keys_lo.is_empty()
+    }
+
+    /// Returns an iterator over the set keys, in MPHF order.
+    #[inline]
+    pub fn iter(&self) -> impl ExactSizeIterator<Item = u128> + DoubleEndedIterator + '_ {
+        self.keys_lo
+            .iter()
+            .zip(self.keys_hi.iter())
+            .map(|(&lo, &hi)| join(lo, hi))
+    }
+
+    /// Returns the total number of bytes occupied by the set.
+    #[inline]
+    pub fn size(&self) -> usize {
+        size_of_val(self) + self.mphf.size() + size_of_val(self.keys_lo.as_ref()) + size_of_val(self.keys_hi.as_ref())
+    }
+
+    /// Checks invariants of the MPHF and that every key is mapped by it to the index it's stored at.
+    pub fn debug_check_invariants(&self) -> Result<(), InvariantError> {
+        check_planes(&self.mphf, &self.keys_lo, &self.keys_hi)
+    }
+}
+
+/// Splits the keys of `set` into planes, keeping its MPHF.
+impl<const B: usize, const S: usize, ST, H> From<Set<u128, B, S, ST, H>> for SetU128<B, S, ST, H>
+where
+    ST: PrimInt + Unsigned,
+    H: Hasher + Default,
+{
+    fn from(set: Set<u128, B, S, ST, H>) -> Self {
+        let (keys_lo, keys_hi) = split_planes(&set.keys);
+        SetU128 { mphf: set.mphf, keys_lo, keys_hi }
+    }
+}
+
+/// An immutable hash map of `u128` keys storing them as planes of low and high halves, with values
+/// packed into a dictionary like `MapWithDict`, see the module documentation.
+///
+/// # Examples
+/// ```
+/// # use entropy_map::{MapWithDictU128, DEFAULT_GAMMA};
+/// let map = MapWithDictU128::<&str>::from_iter_with_params([(1 << 100, "a"), (2, "b")], DEFAULT_GAMMA).unwrap();
+/// assert_eq!(map.get(&(1 << 100)), Some(&"a"));
+/// assert_eq!(map.get(&(2 << 100)), None);
+/// ```
+#[derive(Default)]
+#[cfg_attr(feature = "rkyv_derive", derive(rkyv::Archive, rkyv::Deserialize, rkyv::Serialize))]
+#[cfg_attr(feature = "rkyv_derive", archive_attr(derive(rkyv::CheckBytes)))]
+pub struct MapWithDictU128<V, const B: usize = 32, const S: usize = 8, ST = u8, H = U128Hasher>
+where
+    ST: PrimInt + Unsigned,
+    H: Hasher + Default,
+{
+    /// Minimally Perfect Hash Function for keys indices retrieval
+    mphf: Mphf<B, S, ST, H>,
+    /// Low halves of the map keys
+    keys_lo: Box<[u64]>,
+    /// High halves of the map keys
+    keys_hi: Box<[u64]>,
+    /// Points to the value index in the dictionary
+    values_index: Box<[usize]>,
+    /// Map unique values
+    values_dict: Box<[V]>,
+}
+
+impl<V, const B: usize, const S: usize, ST, H> MapWithDictU128<V, B, S, ST, H>
+where
+    V: Eq + Clone + std::hash::Hash,
+    ST: PrimInt + Unsigned,
+    H: Hasher + Default,
+{
+    /// Constructs a `MapWithDictU128` from an iterator of key-value pairs and MPHF function params.
+    pub fn from_iter_with_params<I>(iter: I, gamma: f32) -> Result<Self, MphfError>
+    where
+        I: IntoIterator<Item = (u128, V)>,
+    {
+        Ok(MapWithDict::from_iter_with_params(iter, gamma)?.into())
+    }
+
+    /// Constructs a `MapWithDictU128` from an iterator of key-value pairs, escalating MPHF `gamma`
+    /// on failed builds according to `policy`, and returns it along with a `BuildReport`.
+    pub fn from_iter_with_policy<I>(iter: I, policy: &BuildPolicy) -> Result<(Self, BuildReport), MphfError>
+    where
+        I: IntoIterator<Item = (u128, V)>,
+    {
+        let (map, mut report) = MapWithDict::from_iter_with_policy(iter, policy)?;
+        let map = Self::from(map);
+        report.set_size(map.size());
+        Ok((map, report))
+    }
+}
+
+impl<V, const B: usize, const S: usize, ST, H> MapWithDictU128<V, B, S, ST, H>
+where
+    ST: PrimInt + Unsigned,
+    H: Hasher + Default,
+{
+    /// Returns a reference to the value corresponding to the key. Returns `None` if the key is
+    /// not present in the map.
+    #[inline]
+    pub fn get(&self, key: &u128) -> Option<&V> {
+        self.get_impl(self.mphf.get(key), *key)
+    }
+
+    /// Same as [`MapWithDictU128::get`], but re-uses the hash precomputed by `HashedKey`.
+    #[inline]
+    pub fn get_hashed(&self, key: &HashedKey<u128, H>) -> Option<&V> {
+        self.get_impl(self.mphf.get_hashed(key), *key.key())
+    }
+
+    /// Checks if the map contains the specified key.
+    #[inline]
+    pub fn contains_key(&self, key: &u128) -> bool {
+        self.mphf
+            .get(key)
+            .is_some_and(|idx| planes_eq(&self.keys_lo, &self.keys_hi, idx, *key))
+    }
+
+    /// Inner implementation of `get` returning the value of the key stored at MPHF index `idx`.
+    #[inline]
+    fn get_impl(&self, idx: Option<usize>, key: u128) -> Option<&V> {
+        let idx = idx?;
+        if !planes_eq(&self.keys_lo, &self.keys_hi, idx, key) {
+            return None;
+        }
+        // SAFETY: `idx` and `value_idx` are always within bounds (ensured during construction)
+        unsafe {
+            let value_idx = *access::get(&self.values_index, idx);
+            Some(access::get(&self.values_dict, value_idx))
+        }
+    }
+
+    /// Returns the number of keys in the map.
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.keys_lo.len()
+    }
+
+    /// Returns `true` if the map contains no elements.
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.keys_lo.is_empty()
+    }
+
+    /// Returns an iterator over the map, yielding key-value pairs in MPHF order.
+    #[inline]
+    pub fn iter(&self) -> impl ExactSizeIterator<Item = (u128, &V)> + DoubleEndedIterator {
+        self.keys_lo
+            .iter()
+            .zip(self.keys_hi.iter())
+            .zip(self.values_index.iter())
+            .map(|((&lo, &hi), &value_idx)| (join(lo, hi), &self.values_dict[value_idx]))
+    }
+
+    /// Returns the total number of bytes occupied by the map.
+    #[inline]
+    pub fn size(&self) -> usize {
+        size_of_val(self)
+            + self.mphf.size()
+            + size_of_val(self.keys_lo.as_ref())
+            + size_of_val(self.keys_hi.as_ref())
+            + size_of_val(self.values_index.as_ref())
+            + size_of_val(self.values_dict.as_ref())
+    }
+
+    /// Checks invariants of the MPHF, that every key is mapped by it to the index it's stored at
+    /// and that every values index points within the values dictionary.
+    pub fn debug_check_invariants(&self) -> Result<(), InvariantError> {
+        check_planes(&self.mphf, &self.keys_lo, &self.keys_hi)?;
+        invariants::check_values_index(&self.values_index, self.keys_lo.len(), self.values_dict.len())
+    }
+}
+
+/// Splits the keys of `map` into planes, keeping its MPHF and values.
+impl<V, const B: usize, const S: usize, ST, H> From<MapWithDict<u128, V, B, S, ST, H>>
+    for MapWithDictU128<V, B, S, ST, H>
+where
+    ST: PrimInt + Unsigned,
+    H: Hasher + Default,
+{
+    fn from(map: MapWithDict<u128, V, B, S, ST, H>) -> Self {
+        let (keys_lo, keys_hi) = split_planes(&map.keys);
+        MapWithDictU128 {
+            mphf: map.mphf,
+            keys_lo,
+            keys_hi,
+            values_index: map.values_index,
+            values_dict: map.values_dict,
+        }
+    }
+}
+
+/// Splits `keys` into planes of low and high halves.
+fn split_planes(keys: &[u128]) -> (Box<[u64]>, Box<[u64]>) {
+    let (keys_lo, keys_hi): (Vec<u64>, Vec<u64>) = keys.iter().map(|&key| split(key)).unzip();
+    (keys_lo.into_boxed_slice(), keys_hi.into_boxed_slice())
+}
+
+/// Returns `true` if the key stored at `idx` of `keys_lo` and `keys_hi` planes is `key`, loading the
+/// high half only if the low one matches.
+#[inline(always)]
+fn planes_eq(keys_lo: &[u64], keys_hi: &[u64], idx: usize, key: u128) -> bool {
+    let (lo, hi) = split(key);
+    // SAFETY: `idx` is always within bounds (ensured during construction)
+    unsafe { *access::get(keys_lo, idx) == lo && *access::get(keys_hi, idx) == hi }
+}
+
+/// Checks invariants of `mphf` and that every key of `keys_lo` and `keys_hi` planes is mapped by it
+/// to its own index.
+fn check_planes<const B: usize, const S: usize, ST, H>(
+    mphf: &Mphf<B, S, ST, H>,
+    keys_lo: &[u64],
+    keys_hi: &[u64],
+) -> Result<(), InvariantError>
+where
+    ST: PrimInt + Unsigned,
+    H: Hasher + Default,
+{
+    if keys_lo.len() != keys_hi.len() {
+        return Err(InvariantError::LengthMismatch);
+    }
+    invariants::check_outputs(mphf, keys_lo.len())?;
+
+    for (idx, (&lo, &hi)) in keys_lo.iter().zip(keys_hi).enumerate() {
+        if mphf.get(&join(lo, hi)) != Some(idx) {
+            return Err(InvariantError::KeyIndexMismatch { idx });
+        }
+    }
+
+    Ok(())
+}
+
+/// Implement `contains` for `Archived` version of `SetU128` if feature is enabled
+#[cfg(feature = "rkyv_derive")]
+impl<const B: usize, const S: usize, ST, H> ArchivedSetU128<B, S, ST, H>
+where
+    ST: PrimInt + Unsigned + rkyv::Archive<Archived = ST>,
+    H: Hasher + Default,
+{
+    /// Returns `true` if the set contains the key.
+    #[inline]
+    pub fn contains(&self, key: &u128) -> bool {
+        self.mphf
+            .get(key)
+            .is_some_and(|idx| planes_eq(&self.keys_lo, &self.keys_hi, idx, *key))
+    }
+
+    /// Same as [`ArchivedSetU128::contains`], but re-uses the hash precomputed by `HashedKey`.
+    #[inline]
+    pub fn contains_hashed(&self, key: &HashedKey<u128, H>) -> bool {
+        self.mphf
+            .get_hashed(key)
+            .is_some_and(|idx| planes_eq(&self.keys_lo, &self.keys_hi, idx, *key.key()))
+    }
+
+    /// Returns the number of keys in the set.
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.keys_lo.len()
+    }
+
+    /// Returns `true` if the set contains no keys.
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.keys_lo.is_empty()
+    }
+}
+
+/// Implement `get` for `Archived` version of `MapWithDictU128` if feature is enabled
+#[cfg(feature = "rkyv_derive")]
+impl<V, const B: usize, const S: usize, ST, H> ArchivedMapWithDictU128<V, B, S, ST, H>
+where
+    V: rkyv::Archive,
+    ST: PrimInt + Unsigned + rkyv::Archive<Archived = ST>,
+    H: Hasher + Default,
+{
+    /// Returns a reference to the archived value corresponding to the key. Returns `None` if the
+    /// key is not present in the map.
+    #[inline]
+    pub fn get(&self, key: &u128) -> Option<&V::Archived> {
+        let idx = self.mphf.get(key)?;
+        if !planes_eq(&self.keys_lo, &self.keys_hi, idx, *key) {
+            return None;
+        }
+        // SAFETY: `idx` and `value_idx` are always within bounds (ensured during construction)
+        unsafe {
+            let value_idx = *access::get(&self.values_index, idx) as usize;
+            Some(access::get(&self.values_dict, value_idx))
+        }
+    }
+
+    /// Checks if the map contains the specified key.
+    #[inline]
+    pub fn contains_key(&self, key: &u128) -> bool {
+        self.mphf
+            .get(key)
+            .is_some_and(|idx| planes_eq(&self.keys_lo, &self.keys_hi, idx, *key))
+    }
+
+    /// Returns the number of keys in the map.
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.keys_lo.len()
+    }
+
+    /// Returns `true` if the map contains no elements.
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.keys_lo.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mphf::DEFAULT_GAMMA;
+    use rand::{Rng, SeedableRng};
+    use rand_chacha::ChaCha8Rng;
+    use std::collections::HashSet;
+    use std::hash::Hash;
+
+    /// Returns `n` IPv6-like keys: addresses of a few /64 networks with random interface ids.
+    fn gen_keys(n: usize) -> Vec<u128> {
+        let mut rng = ChaCha8Rng::seed_from_u64(123);
+        let keys: HashSet<u128> = (0..n)
+            .map(|i| join(rng.gen(), 0x2001_0db8_0000_0000 | (i % 16) as u64))
+            .collect();
+        keys.into_iter().collect()
+    }
+
+    #[test]
+    fn test_u128_hasher() {
+        let hash = |key: u128| {
+            let mut hasher = U128Hasher::default();
+            key.hash(&mut hasher);
+            hasher.finish()
+        };
+
+        // keys differing only in one half, including the half absorbing the multiplication
+        let keys: HashSet<u128> = (0..100_000u128)
+            .flat_map(|i| [i, i << 64, join(P0, i as u64), join(i as u64, P0)])
+            .collect();
+        let hashes: HashSet<u64> = keys.iter().map(|&key| hash(key)).collect();
+        assert_eq!(hashes.len(), keys.len());
+
+        let mut hasher = U128Hasher::default();
+        "ab".hash(&mut hasher);
+        let mut other = U128Hasher::default();
+        "ab\0".hash(&mut other);
+        assert_ne!(hasher.finish(), other.finish());
+    }
+
+    #[test]
+    fn test_set_u128() {
+        let keys = gen_keys(10000);
+        let (set, report) = SetU128::<32, 8>::from_iter_with_policy(keys.clone(), &BuildPolicy::default()).unwrap();
+        assert_eq!(report.keys(), keys.len());
+        assert_eq!(set.len(), keys.len());
+        assert_eq!(set.debug_check_invariants(), Ok(()));
+        assert_eq!(
+            set.iter().collect::<HashSet<_>>(),
+            keys.iter().copied().collect::<HashSet<_>>()
+        );
+
+        for key in &keys {
+            assert!(set.contains(key));
+            assert!(set.contains_hashed(&HashedKey::new(key)));
+            assert!(!set.contains(&(key ^ 1)));
+            assert!(!set.contains(&(key ^ (1 << 127))));
+        }
+
+        #[cfg(feature = "rkyv_derive")]
+        {
+            let rkyv_bytes = rkyv::to_bytes::<_, 1024>(&set).unwrap();
+            let rkyv_set = rkyv::check_archived_root::<SetU128<32, 8>>(&rkyv_bytes).unwrap();
+            assert_eq!(rkyv_set.len(), keys.len());
+            for key in &keys {
+                assert!(rkyv_set.contains(key));
+                assert!(!rkyv_set.contains(&(key ^ (1 << 127))));
+            }
+        }
+
+        let set = SetU128::<32, 8>::from_iter_with_params([], DEFAULT_GAMMA).unwrap();
+        assert!(set.is_empty());
+        assert!(!set.contains(&0));
+    }
+
+    #[test]
+    fn test_map_with_dict_u128() {
+        let keys = gen_keys(10000);
+        let entries: Vec<(u128, u32)> = keys.iter().map(|&key| (key, key as u32 % 7)).collect();
+        let map = MapWithDictU128::<u32>::from_iter_with_params(entries.clone(), DEFAULT_GAMMA).unwrap();
+        assert_eq!(map.len(), entries.len());
+        assert_eq!(map.debug_check_invariants(), Ok(()));
+        assert_eq!(map.iter().len(), entries.len());
+
+        for (key, value) in &entries {
+            assert_eq!(map.get(key), Some(value));
+            assert_eq!(map.get_hashed(&HashedKey::new(key)), Some(value));
+            assert!(map.contains_key(key));
+            assert_eq!(map.get(&(key ^ (1 << 127))), None);
+        }
+
+        #[cfg(feature = "rkyv_derive")]
+        {
+            let rkyv_bytes = rkyv::to_bytes::<_, 1024>(&map).unwrap();
+            let rkyv_map = rkyv::check_archived_root::<MapWithDictU128<u32>>(&rkyv_bytes).unwrap();
+            assert_eq!(rkyv_map.len(), entries.len());
+            for (key, value) in &entries {
+                assert_eq!(rkyv_map.get(key), Some(value));
+                assert!(!rkyv_map.contains_key(&(key ^ 1)));
+            }
+        }
+    }
+}
@@ -0,0 +1,243 @@
+//! A module providing `ValueCodec`, a trait encoding map values into dictionary bytes, along with
+//! common codecs.
+//!
+//! `MapWithDictCodec` stores every unique value once in a byte dictionary encoded by a
+//! `ValueCodec`, so that domain-specific compact representations (e.g. varint structs or small
+//! enums as single bytes) don't need a bespoke map type per encoding. Encodings must be
+//! self-delimiting: `decode` receives the dictionary bytes starting at the encoded value and
+//! running up to the end of the dictionary. Codecs are stateless, so that archived maps can decode
+//! values without deserializing anything but the dictionary bytes.
+//!
+//! Provided codecs:
+//! - `VarintCodec`: unsigned integers as LEB128 varints,
+//! - `BytesCodec`: `Vec<u8>` and `String` values as a varint length followed by their bytes,
+//!   decoded without copying into `&[u8]` and `&str`,
+//! - `BitpackedCodec`: `Vec<u32>` values as a varint length followed by the bit-packed blocks of
+//!   `MapWithDictBitpacked`, but without requiring all values to have the same length.
+
+use crate::map_with_dict_bitpacked::{pack_values, unpack_values, VALUES_BLOCK_LEN};
+
+/// Encoding of values of type `V` into dictionary bytes, see the module documentation.
+///
+/// # Examples
+/// ```
+/// # use entropy_map::{MapWithDictCodec, ValueCodec};
+/// #[derive(Debug, PartialEq)]
+/// enum Color {
+///     Red,
+///     Green,
+/// }
+///
+/// /// Encodes `Color` as a single byte
+/// struct ColorCodec;
+///
+/// impl ValueCodec<Color> for ColorCodec {
+///     type Decoded<'a> = Color;
+///
+///     fn encode(value: &Color, out: &mut Vec<u8>) {
+///         out.push(matches!(value, Color::Green) as u8);
+///     }
+///
+///     fn decode(bytes: &[u8]) -> Color {
+///         if bytes[0] == 0 { Color::Red } else { Color::Green }
+///     }
+/// }
+///
+/// let map = MapWithDictCodec::<u32, Color, ColorCodec>::from_iter_with_params(
+///     [(1, Color::Red), (2, Color::Green), (3, Color::Red)],
+///     entropy_map::DEFAULT_GAMMA,
+/// )
+/// .unwrap();
+/// assert_eq!(map.get(&2), Some(Color::Green));
+/// ```
+pub trait ValueCodec<V> {
+    /// Type of decoded values, which may borrow from the dictionary bytes.
+    type Decoded<'a>;
+
+    /// Number of zero bytes appended to the dictionary, e.g. to let `decode` read whole SIMD
+    /// blocks past the last encoded value.
+    const PADDING: usize = 0;
+
+    /// Appends the encoding of `value` to `out`.
+    fn encode(value: &V, out: &mut Vec<u8>);
+
+    /// Decodes the value encoded at the start of `bytes`.
+    fn decode(bytes: &[u8]) -> Self::Decoded<'_>;
+}
+
+/// Codec encoding unsigned integers as LEB128 varints, i.e. 7 bits per byte.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct VarintCodec;
+
+macro_rules! impl_varint_codec {
+    ($($t:ty),* $(,)?) => {
+        $(
+            impl ValueCodec<$t> for VarintCodec {
+                type Decoded<'a> = $t;
+
+                #[inline]
+                fn encode(value: &$t, out: &mut Vec<u8>) {
+                    write_varint(*value as u64, out);
+                }
+
+                #[inline]
+                fn decode(bytes: &[u8]) -> $t {
+                    read_varint(bytes).0 as $t
+                }
+            }
+        )*
+    };
+}
+
+impl_varint_codec!(u8, u16, u32, u64, usize);
+
+/// Codec encoding byte strings as a varint length followed by their bytes.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct BytesCodec;
+
+impl ValueCodec<Vec<u8>> for BytesCodec {
+    type Decoded<'a> = &'a [u8];
+
+    #[inline]
+    fn encode(value: &Vec<u8>, out: &mut Vec<u8>) {
+        write_varint(value.len() as u64, out);
+        out.extend_from_slice(value);
+    }
+
+    #[inline]
+    fn decode(bytes: &[u8]) -> &[u8] {
+        let (len, size) = read_varint(bytes);
+        &bytes[size..size + len as usize]
+    }
+}
+
+impl ValueCodec<String> for BytesCodec {
+    type Decoded<'a> = &'a str;
+
+    #[inline]
+    fn encode(value: &String, out: &mut Vec<u8>) {
+        write_varint(value.len() as u64, out);
+        out.extend_from_slice(value.as_bytes());
+    }
+
+    /// Decodes a string, panics if the dictionary is corrupted and the bytes are not valid UTF-8.
+    #[inline]
+    fn decode(bytes: &[u8]) -> &str {
+        let (len, size) = read_varint(bytes);
+        std::str::from_utf8(&bytes[size..size + len as usize]).expect("dictionary string is not valid UTF-8")
+    }
+}
+
+/// Codec bit-packing `Vec<u32>` values into blocks of `MapWithDictBitpacked`, prefixed with a
+/// varint length so that values may have different lengths.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct BitpackedCodec;
+
+impl ValueCodec<Vec<u32>> for BitpackedCodec {
+    type Decoded<'a> = Vec<u32>;
+
+    /// Bit-unpacking reads whole blocks, the last one may run past the last encoded value.
+    const PADDING: usize = 4 * VALUES_BLOCK_LEN;
+
+    #[inline]
+    fn encode(value: &Vec<u32>, out: &mut Vec<u8>) {
+        write_varint(value.len() as u64, out);
+        pack_values(value, out);
+    }
+
+    #[inline]
+    fn decode(bytes: &[u8]) -> Vec<u32> {
+        let (len, size) = read_varint(bytes);
+        let mut values = vec![0; len as usize];
+        unpack_values(&bytes[size..], &mut values);
+        values
+    }
+}
+
+/// Appends `value` encoded as LEB128 varint to `out`.
+#[inline]
+fn write_varint(mut value: u64, out: &mut Vec<u8>) {
+    while value >= 0x80 {
+        out.push(value as u8 | 0x80);
+        value >>= 7;
+    }
+    out.push(value as u8);
+}
+
+/// Reads LEB128 varint at the start of `bytes`, returns the value and its encoded size in bytes.
+#[inline]
+fn read_varint(bytes: &[u8]) -> (u64, usize) {
+    let mut value = 0;
+    for (i, &byte) in bytes.iter().enumerate().take(10) {
+        value |= ((byte & 0x7f) as u64) << (7 * i);
+        if byte < 0x80 {
+            return (value, i + 1);
+        }
+    }
+    panic!("dictionary varint is truncated or too long")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::{Rng, SeedableRng};
+    use rand_chacha::ChaCha8Rng;
+
+    /// Encodes all `values` one after another into padded dictionary bytes, returns the bytes and
+    /// offsets of the values.
+    fn encode_all<V, C: ValueCodec<V>>(values: &[V]) -> (Vec<u8>, Vec<usize>) {
+        let mut offsets = vec![];
+        let mut bytes = vec![];
+        for value in values {
+            offsets.push(bytes.len());
+            C::encode(value, &mut bytes);
+        }
+        bytes.resize(bytes.len() + C::PADDING, 0);
+        (bytes, offsets)
+    }
+
+    #[test]
+    fn test_varint_codec() {
+        let mut rng = ChaCha8Rng::seed_from_u64(123);
+        let mut values = vec![0, 1, 127, 128, 16383, 16384, u64::MAX];
+        values.extend((0..100).map(|_| rng.gen::<u64>() >> rng.gen_range(0..64)));
+        let (bytes, offsets) = encode_all::<u64, VarintCodec>(&values);
+        for (value, offset) in values.iter().zip(offsets) {
+            assert_eq!(<VarintCodec as ValueCodec<u64>>::decode(&bytes[offset..]), *value);
+        }
+
+        let mut bytes = vec![];
+        VarintCodec::encode(&300u32, &mut bytes);
+        assert_eq!(bytes, [0xac, 0x02]);
+    }
+
+    #[test]
+    fn test_bytes_codec() {
+        let values = vec![vec![], vec![1, 2, 3], vec![7; 200]];
+        let (bytes, offsets) = encode_all::<Vec<u8>, BytesCodec>(&values);
+        for (value, offset) in values.iter().zip(offsets) {
+            assert_eq!(<BytesCodec as ValueCodec<Vec<u8>>>::decode(&bytes[offset..]), value);
+        }
+
+        let values = vec![String::new(), "entropy".to_string(), "ü".repeat(100)];
+        let (bytes, offsets) = encode_all::<String, BytesCodec>(&values);
+        for (value, offset) in values.iter().zip(offsets) {
+            assert_eq!(<BytesCodec as ValueCodec<String>>::decode(&bytes[offset..]), value);
+        }
+    }
+
+    #[test]
+    fn test_bitpacked_codec() {
+        let mut rng = ChaCha8Rng::seed_from_u64(123);
+        let values: Vec<Vec<u32>> = (0..100)
+            .map(|_| {
+                let max = rng.gen_range(1..=u32::MAX);
+                (0..rng.gen_range(0..100)).map(|_| rng.gen_range(0..max)).collect()
+            })
+            .collect();
+        let (bytes, offsets) = encode_all::<Vec<u32>, BitpackedCodec>(&values);
+        for (value, offset) in values.iter().zip(offsets) {
+            assert_eq!(&BitpackedCodec::decode(&bytes[offset..]), value);
+        }
+    }
+}